@@ -0,0 +1,69 @@
+//! Parse → obfuscate (Basic) → emit → re-parse on arbitrary input
+//!
+//! Input that doesn't parse, or that the pipeline rejects with an error, is
+//! fine; a panic, or protected output that no longer parses or lost a string
+//! literal, is a bug.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use luau_obfuscator::{
+    AnalysisOptions, CodeGenConfig, CryptoContext, LuauParser, ObfuscationPipeline,
+    ObfuscationTier, PipelineSettings,
+};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+fn literals(source: &str) -> Option<BTreeMap<String, usize>> {
+    let parse_result = LuauParser::new().parse(source).ok()?;
+    let mut counts = BTreeMap::new();
+    for literal in parse_result.strings {
+        *counts.entry(literal.value).or_insert(0) += 1;
+    }
+    Some(counts)
+}
+
+fuzz_target!(|data: &[u8]| {
+    static CRYPTO: OnceLock<CryptoContext> = OnceLock::new();
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Some(original) = literals(source) else {
+        return;
+    };
+
+    // Folding merges literals into one, so it would break the count
+    let settings = PipelineSettings {
+        tier: ObfuscationTier::Basic,
+        seed: Some(7),
+        lint: false,
+        analysis: AnalysisOptions {
+            fold_constants: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let config = CodeGenConfig {
+        license_key: "FUZZ-0000".to_string(),
+        script_id: "fuzz".to_string(),
+        ..Default::default()
+    };
+    let crypto = CRYPTO
+        .get_or_init(|| CryptoContext::new("fuzz_password", None).unwrap())
+        .clone();
+    let Ok(protected) = ObfuscationPipeline::new(crypto).protect(source, &settings, config) else {
+        return;
+    };
+
+    assert!(literals(&protected.script).is_some(), "protected script doesn't parse");
+    let code = protected
+        .script
+        .split("-- [CODE] Protected Script")
+        .nth(1)
+        .expect("protected script has no code section");
+    let mut kept = literals(code).expect("protected code doesn't parse");
+    for entry in &protected.obfuscated.encrypted_strings {
+        *kept.entry(entry.original.clone()).or_insert(0) += entry.sites.len();
+    }
+    assert_eq!(kept, original, "string literals changed");
+});
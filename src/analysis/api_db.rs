@@ -0,0 +1,286 @@
+//! Roblox API database
+//!
+//! The set of identifiers that must survive obfuscation (services, classes,
+//! datatypes, enums) is loaded from a versioned database rather than being
+//! hard-coded. A snapshot is bundled with the binary; `update-api-db`
+//! regenerates it from Roblox's published API dump.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Bundled API database snapshot
+const BUNDLED_API_DB: &str = include_str!("roblox_api.json");
+
+/// Endpoint returning the current Roblox Studio version hash
+pub const ROBLOX_VERSION_ENDPOINT: &str = "https://setup.rbxcdn.com/versionQTStudio";
+
+/// Base URL for versioned API dumps (`{base}/{version}-API-Dump.json`)
+pub const ROBLOX_API_DUMP_BASE: &str = "https://setup.rbxcdn.com";
+
+/// File name of the refreshed database inside the cache directory
+pub const API_DB_FILE: &str = "roblox_api.json";
+
+/// Preserved-identifier database derived from the Roblox API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiDatabase {
+    /// Roblox version hash this database was generated from ("bundled" for the snapshot)
+    pub version: String,
+    /// Global objects (game, workspace, ...)
+    pub globals: Vec<String>,
+    /// Service class names
+    pub services: Vec<String>,
+    /// Datatype constructors (not part of the class dump)
+    pub datatypes: Vec<String>,
+    /// Creatable/referenced class names
+    pub classes: Vec<String>,
+    /// Remote and bindable class names
+    pub remotes: Vec<String>,
+    /// Enum names
+    #[serde(default)]
+    pub enums: Vec<String>,
+}
+
+/// Subset of Roblox's API dump format that we consume
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RobloxApiDump {
+    classes: Vec<DumpClass>,
+    #[serde(default)]
+    enums: Vec<DumpEnum>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpClass {
+    name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DumpEnum {
+    name: String,
+}
+
+impl ApiDatabase {
+    /// Load the snapshot bundled with the binary
+    pub fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_API_DB).expect("Bundled Roblox API database is valid JSON")
+    }
+
+    /// Load the refreshed database from `cache_dir`, falling back to the bundled snapshot
+    ///
+    /// When `pinned_version` is set, a cached database for a different version
+    /// is ignored so builds stay reproducible.
+    pub fn load(cache_dir: Option<&Path>, pinned_version: Option<&str>) -> Self {
+        let cached = cache_dir
+            .map(|dir| dir.join(API_DB_FILE))
+            .filter(|path| path.exists())
+            .and_then(|path| match Self::load_file(&path) {
+                Ok(db) => Some(db),
+                Err(e) => {
+                    log::warn!("Ignoring unreadable API database {:?}: {}", path, e);
+                    None
+                }
+            });
+
+        match (cached, pinned_version) {
+            (Some(db), Some(pin)) if db.version != pin => {
+                log::warn!(
+                    "Cached API database is {} but config pins {}; using bundled snapshot",
+                    db.version,
+                    pin
+                );
+                Self::bundled()
+            }
+            (Some(db), _) => {
+                debug!("Using Roblox API database {}", db.version);
+                db
+            }
+            (None, _) => Self::bundled(),
+        }
+    }
+
+    /// Load a database file
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read API database: {:?}", path))?;
+        serde_json::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid API database {:?}: {}", path, e)).into()
+        })
+    }
+
+    /// Write the database as pretty JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize API database")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write API database: {:?}", path))
+    }
+
+    /// Build a database from a Roblox API dump
+    ///
+    /// Globals and datatypes are not part of the class dump, so they are
+    /// carried over from the bundled snapshot.
+    pub fn from_api_dump(version: &str, dump_json: &str) -> Result<Self> {
+        let dump: RobloxApiDump = serde_json::from_str(dump_json)
+            .map_err(|e| ObfuscatorError::ApiError(format!("Invalid Roblox API dump: {}", e)))?;
+
+        let bundled = Self::bundled();
+        let mut services = Vec::new();
+        let mut classes = Vec::new();
+        let mut remotes = Vec::new();
+
+        for class in &dump.classes {
+            if class.tags.iter().any(|t| t == "Service") {
+                services.push(class.name.clone());
+            }
+            if class.name.contains("Remote") || class.name.starts_with("Bindable") {
+                remotes.push(class.name.clone());
+            }
+            if !class.tags.iter().any(|t| t == "Deprecated") {
+                classes.push(class.name.clone());
+            }
+        }
+
+        let mut enums: Vec<String> = dump.enums.into_iter().map(|e| e.name).collect();
+        for list in [&mut services, &mut classes, &mut remotes, &mut enums] {
+            list.sort();
+            list.dedup();
+        }
+
+        Ok(Self {
+            version: version.to_string(),
+            globals: bundled.globals,
+            services,
+            datatypes: bundled.datatypes,
+            classes,
+            remotes,
+            enums,
+        })
+    }
+
+    /// Fetch the API dump for `version` (or the latest Studio version) and convert it
+    #[cfg(feature = "network")]
+    pub fn fetch(version: Option<&str>) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        let version = match version {
+            Some(v) => v.to_string(),
+            None => client
+                .get(ROBLOX_VERSION_ENDPOINT)
+                .send()
+                .and_then(|r| r.error_for_status())
+                .and_then(|r| r.text())
+                .map_err(|e| {
+                    ObfuscatorError::ApiError(format!("Failed to fetch Roblox version: {}", e))
+                })?
+                .trim()
+                .to_string(),
+        };
+
+        let url = format!("{}/{}-API-Dump.json", ROBLOX_API_DUMP_BASE, version);
+        log::info!("Fetching Roblox API dump: {}", url);
+
+        let dump = client
+            .get(&url)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .map_err(|e| ObfuscatorError::ApiError(format!("Failed to fetch API dump: {}", e)))?;
+
+        Self::from_api_dump(&version, &dump)
+    }
+
+    /// All identifiers that must not be renamed
+    pub fn preserved_names(&self) -> HashSet<String> {
+        self.globals
+            .iter()
+            .chain(&self.services)
+            .chain(&self.datatypes)
+            .chain(&self.remotes)
+            .cloned()
+            .collect()
+    }
+
+    /// Default on-disk location of the refreshed database
+    pub fn default_path(cache_dir: &Path) -> PathBuf {
+        cache_dir.join(API_DB_FILE)
+    }
+}
+
+impl Default for ApiDatabase {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_DUMP: &str = r#"{
+        "Version": 1,
+        "Classes": [
+            {"Name": "Instance", "Superclass": "<<<ROOT>>>", "Tags": ["NotCreatable"]},
+            {"Name": "Players", "Superclass": "Instance", "Tags": ["Service", "NotCreatable"]},
+            {"Name": "NewFancyService", "Superclass": "Instance", "Tags": ["Service"]},
+            {"Name": "RemoteEvent", "Superclass": "Instance", "Tags": []},
+            {"Name": "OldThing", "Superclass": "Instance", "Tags": ["Deprecated"]}
+        ],
+        "Enums": [{"Name": "KeyCode", "Items": []}]
+    }"#;
+
+    #[test]
+    fn test_bundled_database_loads() {
+        let db = ApiDatabase::bundled();
+        assert_eq!(db.version, "bundled");
+        assert!(db.services.contains(&"Players".to_string()));
+        assert!(db.datatypes.contains(&"CFrame".to_string()));
+    }
+
+    #[test]
+    fn test_from_api_dump() {
+        let db = ApiDatabase::from_api_dump("version-test", SAMPLE_DUMP).unwrap();
+
+        assert_eq!(db.version, "version-test");
+        assert_eq!(db.services, vec!["NewFancyService", "Players"]);
+        assert_eq!(db.remotes, vec!["RemoteEvent"]);
+        assert!(!db.classes.contains(&"OldThing".to_string()));
+        assert_eq!(db.enums, vec!["KeyCode"]);
+        // Datatypes come from the bundled snapshot
+        assert!(db.datatypes.contains(&"Vector3".to_string()));
+    }
+
+    #[test]
+    fn test_load_respects_pinned_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = ApiDatabase::from_api_dump("version-a", SAMPLE_DUMP).unwrap();
+        db.save(&ApiDatabase::default_path(dir.path())).unwrap();
+
+        let loaded = ApiDatabase::load(Some(dir.path()), Some("version-a"));
+        assert_eq!(loaded.version, "version-a");
+
+        let mismatched = ApiDatabase::load(Some(dir.path()), Some("version-b"));
+        assert_eq!(mismatched.version, "bundled");
+    }
+
+    #[test]
+    fn test_preserved_names() {
+        let names = ApiDatabase::bundled().preserved_names();
+        assert!(names.contains("game"));
+        assert!(names.contains("HttpService"));
+        assert!(!names.contains("myVariable"));
+    }
+}
@@ -1,18 +1,70 @@
 //! Control flow analysis
+//!
+//! Builds a statement-level CFG of the top-level chunk from the AST.
+//! `if`/`elseif` chains and `while`/`repeat` loops become conditional
+//! blocks whose edges carry the branch taken; `break`, `continue` and
+//! `return` end their block. Numeric and generic `for` loops and function
+//! bodies stay single statements, so a `break` inside them is untouched.
 
 use crate::parser::ParseResult;
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use full_moon::ast::{Block, LastStmt, Stmt};
+use full_moon::node::Node;
 use std::collections::HashMap;
 
+/// Statement inside a basic block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockStatement {
+    pub line: usize,
+    pub end_line: usize,
+    /// Source text, trimmed of surrounding trivia
+    pub code: String,
+}
+
+/// When an edge is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Unconditional jump or fallthrough
+    Always,
+    /// The block's branch condition is truthy
+    IfTrue,
+    /// The block's branch condition is falsy
+    IfFalse,
+}
+
+/// Outgoing edge of a basic block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: usize,
+    pub kind: EdgeKind,
+}
+
+/// How control leaves a basic block
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Terminator {
+    /// Continue at the single successor, or leave the chunk if there is none
+    #[default]
+    Jump,
+    /// Evaluate the condition and follow the `IfTrue` or `IfFalse` edge
+    Branch(String),
+    /// Leave the chunk with this `return` statement
+    Return(String),
+}
+
 /// Basic block in control flow graph
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct BasicBlock {
     pub id: usize,
     pub start_line: usize,
     pub end_line: usize,
     pub successors: Vec<usize>,
     pub predecessors: Vec<usize>,
+    /// Statements in execution order (without the terminator)
+    pub statements: Vec<BlockStatement>,
+    /// Outgoing edges, in the same order as `successors`
+    pub edges: Vec<Edge>,
+    pub terminator: Terminator,
 }
 
 /// Control flow graph
@@ -23,54 +75,251 @@ pub struct ControlFlowGraph {
     pub exit_blocks: Vec<usize>,
 }
 
+/// Jump targets of the innermost enclosing loop
+struct LoopTargets {
+    continue_to: usize,
+    break_to: usize,
+}
+
 /// Analyzes control flow
 pub struct ControlFlowAnalyzer {
     next_block_id: usize,
+    /// Graph under construction
+    graph: ControlFlowGraph,
+    loops: Vec<LoopTargets>,
 }
 
 impl ControlFlowAnalyzer {
     pub fn new() -> Self {
-        Self { next_block_id: 0 }
+        Self {
+            next_block_id: 0,
+            graph: ControlFlowGraph {
+                blocks: HashMap::new(),
+                entry_block: 0,
+                exit_blocks: Vec::new(),
+            },
+            loops: Vec::new(),
+        }
     }
 
     /// Build control flow graph from parsed code
-    pub fn analyze(&self, _parse_result: &ParseResult) -> Result<ControlFlowGraph> {
-        // Create simple linear control flow for now
-        // In a complete implementation, this would analyze:
-        // - if/then/else branches
-        // - loops (for, while, repeat)
-        // - function calls
-        // - return statements
-        
-        let entry_block = BasicBlock {
-            id: 0,
-            start_line: 1,
-            end_line: usize::MAX,
-            successors: Vec::new(),
-            predecessors: Vec::new(),
-        };
+    pub fn analyze(&self, parse_result: &ParseResult) -> Result<ControlFlowGraph> {
+        let mut builder = Self::new();
+        let entry_block = builder.create_block(1).id;
 
-        let mut blocks = HashMap::new();
-        blocks.insert(0, entry_block);
+        match &parse_result.ast {
+            Some(ast) => {
+                builder.lower_block(ast.nodes(), entry_block);
+            }
+            // Without an AST the whole chunk is one block
+            None => builder.block_mut(entry_block).end_line = usize::MAX,
+        }
 
-        Ok(ControlFlowGraph {
-            blocks,
-            entry_block: 0,
-            exit_blocks: vec![0],
-        })
+        let mut cfg = builder.graph;
+        cfg.entry_block = entry_block;
+        cfg.exit_blocks = cfg
+            .blocks
+            .values()
+            .filter(|block| block.successors.is_empty())
+            .map(|block| block.id)
+            .collect();
+        cfg.exit_blocks.sort_unstable();
+        cfg.validate()?;
+        Ok(cfg)
     }
 
-    fn create_block(&mut self, start_line: usize) -> BasicBlock {
+    fn create_block(&mut self, start_line: usize) -> &mut BasicBlock {
         let id = self.next_block_id;
         self.next_block_id += 1;
 
-        BasicBlock {
+        self.graph.blocks.entry(id).or_insert(BasicBlock {
             id,
             start_line,
             end_line: start_line,
-            successors: Vec::new(),
-            predecessors: Vec::new(),
+            ..BasicBlock::default()
+        })
+    }
+
+    /// Lower `block` into the graph starting in `current`
+    ///
+    /// Returns the block control falls out of, or `None` when every path
+    /// ends in `return`, `break` or `continue`.
+    fn lower_block(&mut self, block: &Block, mut current: usize) -> Option<usize> {
+        let mut reachable = true;
+        for stmt in block.stmts() {
+            // Code after a branch that always leaves still has to be emitted
+            if !reachable {
+                current = self.create_block(lines_of(stmt).0).id;
+            }
+            match self.lower_stmt(stmt, current) {
+                Some(next) => {
+                    current = next;
+                    reachable = true;
+                }
+                None => reachable = false,
+            }
         }
+
+        if let Some(last_stmt) = block.last_stmt() {
+            if !reachable {
+                current = self.create_block(lines_of(last_stmt).0).id;
+            }
+            return self.lower_last_stmt(last_stmt, current);
+        }
+        Some(current).filter(|_| reachable)
+    }
+
+    fn lower_stmt(&mut self, stmt: &Stmt, current: usize) -> Option<usize> {
+        let (line, end_line) = lines_of(stmt);
+
+        match stmt {
+            Stmt::If(if_stmt) => {
+                let mut ends = Vec::new();
+                let mut condition_block = current;
+                self.set_branch(condition_block, if_stmt.condition().to_string(), line);
+
+                let then_entry = self.create_block(line).id;
+                self.graph.add_branch(condition_block, then_entry, EdgeKind::IfTrue);
+                ends.push(self.lower_block(if_stmt.block(), then_entry));
+
+                for else_if in if_stmt.else_if().into_iter().flatten() {
+                    let else_if_line = lines_of(else_if).0;
+                    let next = self.create_block(else_if_line).id;
+                    self.graph.add_branch(condition_block, next, EdgeKind::IfFalse);
+                    self.set_branch(next, else_if.condition().to_string(), else_if_line);
+                    condition_block = next;
+
+                    let entry = self.create_block(else_if_line).id;
+                    self.graph.add_branch(condition_block, entry, EdgeKind::IfTrue);
+                    ends.push(self.lower_block(else_if.block(), entry));
+                }
+
+                let falls_through = match if_stmt.else_block() {
+                    Some(else_block) => {
+                        let entry = self.create_block(line).id;
+                        self.graph.add_branch(condition_block, entry, EdgeKind::IfFalse);
+                        ends.push(self.lower_block(else_block, entry));
+                        false
+                    }
+                    None => true,
+                };
+
+                if !falls_through && ends.iter().all(Option::is_none) {
+                    return None;
+                }
+                let join = self.create_block(end_line).id;
+                if falls_through {
+                    self.graph.add_branch(condition_block, join, EdgeKind::IfFalse);
+                }
+                for end in ends.into_iter().flatten() {
+                    self.graph.add_edge(end, join);
+                }
+                Some(join)
+            }
+
+            Stmt::While(while_loop) => {
+                let header = self.create_block(line).id;
+                self.graph.add_edge(current, header);
+                self.set_branch(header, while_loop.condition().to_string(), line);
+
+                let body = self.create_block(line).id;
+                let exit = self.create_block(end_line).id;
+                self.graph.add_branch(header, body, EdgeKind::IfTrue);
+                self.graph.add_branch(header, exit, EdgeKind::IfFalse);
+
+                self.loops.push(LoopTargets {
+                    continue_to: header,
+                    break_to: exit,
+                });
+                if let Some(end) = self.lower_block(while_loop.block(), body) {
+                    self.graph.add_edge(end, header);
+                }
+                self.loops.pop();
+                Some(exit)
+            }
+
+            Stmt::Repeat(repeat) => {
+                let body = self.create_block(line).id;
+                self.graph.add_edge(current, body);
+                let condition_block = self.create_block(end_line).id;
+                let exit = self.create_block(end_line).id;
+
+                // `continue` still evaluates `until`
+                self.loops.push(LoopTargets {
+                    continue_to: condition_block,
+                    break_to: exit,
+                });
+                if let Some(end) = self.lower_block(repeat.block(), body) {
+                    self.graph.add_edge(end, condition_block);
+                }
+                self.loops.pop();
+
+                self.set_branch(condition_block, repeat.until().to_string(), end_line);
+                self.graph.add_branch(condition_block, exit, EdgeKind::IfTrue);
+                self.graph.add_branch(condition_block, body, EdgeKind::IfFalse);
+                Some(exit)
+            }
+
+            Stmt::Do(do_block) => self.lower_block(do_block.block(), current),
+
+            _ => {
+                self.push_statement(current, stmt.to_string(), line, end_line);
+                Some(current)
+            }
+        }
+    }
+
+    fn lower_last_stmt(&mut self, last_stmt: &LastStmt, current: usize) -> Option<usize> {
+        let (line, end_line) = lines_of(last_stmt);
+        let target = match last_stmt {
+            LastStmt::Return(ret) => {
+                let block = self.block_mut(current);
+                block.terminator = Terminator::Return(ret.to_string().trim().to_string());
+                block.end_line = block.end_line.max(end_line);
+                return None;
+            }
+            LastStmt::Break(_) => self.loops.last().map(|targets| targets.break_to),
+            LastStmt::Continue(_) => self.loops.last().map(|targets| targets.continue_to),
+            _ => None,
+        };
+
+        match target {
+            Some(target) => {
+                self.graph.add_edge(current, target);
+                None
+            }
+            // Outside a lowered loop (never valid Luau): keep it as written
+            None => {
+                self.push_statement(current, last_stmt.to_string(), line, end_line);
+                Some(current)
+            }
+        }
+    }
+
+    fn block_mut(&mut self, id: usize) -> &mut BasicBlock {
+        self.graph
+            .blocks
+            .get_mut(&id)
+            .expect("blocks are never removed while building")
+    }
+
+    fn push_statement(&mut self, id: usize, code: String, line: usize, end_line: usize) {
+        let block = self.block_mut(id);
+        if block.statements.is_empty() {
+            block.start_line = line;
+        }
+        block.end_line = block.end_line.max(end_line);
+        block.statements.push(BlockStatement {
+            line,
+            end_line,
+            code: code.trim().to_string(),
+        });
+    }
+
+    fn set_branch(&mut self, id: usize, condition: String, line: usize) {
+        let block = self.block_mut(id);
+        block.terminator = Terminator::Branch(condition.trim().to_string());
+        block.end_line = block.end_line.max(line);
     }
 }
 
@@ -80,6 +329,13 @@ impl Default for ControlFlowAnalyzer {
     }
 }
 
+/// First and last source line of an AST node
+fn lines_of(node: &impl Node) -> (usize, usize) {
+    let line = node.start_position().map(|p| p.line()).unwrap_or(1);
+    let end_line = node.end_position().map(|p| p.line()).unwrap_or(line);
+    (line, end_line)
+}
+
 impl ControlFlowGraph {
     /// Get all blocks in the graph
     pub fn all_blocks(&self) -> Vec<&BasicBlock> {
@@ -93,13 +349,42 @@ impl ControlFlowGraph {
 
     /// Add edge between blocks
     pub fn add_edge(&mut self, from: usize, to: usize) {
+        self.add_branch(from, to, EdgeKind::Always);
+    }
+
+    /// Add an edge taken when `from`'s condition matches `kind`
+    pub fn add_branch(&mut self, from: usize, to: usize, kind: EdgeKind) {
         if let Some(from_block) = self.blocks.get_mut(&from) {
             from_block.successors.push(to);
+            from_block.edges.push(Edge { target: to, kind });
         }
         if let Some(to_block) = self.blocks.get_mut(&to) {
             to_block.predecessors.push(from);
         }
     }
+
+    /// `(if_true, if_false)` targets of a conditional block
+    pub fn branch_targets(&self, id: usize) -> Option<(usize, usize)> {
+        let edges = &self.blocks.get(&id)?.edges;
+        let target = |kind| edges.iter().find(|e| e.kind == kind).map(|e| e.target);
+        Some((target(EdgeKind::IfTrue)?, target(EdgeKind::IfFalse)?))
+    }
+
+    /// Check that every conditional block has both branches
+    fn validate(&self) -> Result<()> {
+        for block in self.blocks.values() {
+            if matches!(block.terminator, Terminator::Branch(_))
+                && self.branch_targets(block.id).is_none()
+            {
+                return Err(ObfuscatorError::AnalysisError(format!(
+                    "Conditional block {} (line {}) is missing a branch",
+                    block.id, block.start_line
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -130,6 +415,7 @@ mod tests {
             end_line: 5,
             successors: Vec::new(),
             predecessors: Vec::new(),
+            ..Default::default()
         };
 
         cfg.blocks.insert(0, block1);
@@ -153,6 +439,7 @@ mod tests {
                 end_line: 5,
                 successors: Vec::new(),
                 predecessors: Vec::new(),
+                ..Default::default()
             },
         );
 
@@ -164,6 +451,7 @@ mod tests {
                 end_line: 10,
                 successors: Vec::new(),
                 predecessors: Vec::new(),
+                ..Default::default()
             },
         );
 
@@ -172,4 +460,85 @@ mod tests {
         assert_eq!(cfg.get_block(0).unwrap().successors.len(), 1);
         assert_eq!(cfg.get_block(1).unwrap().predecessors.len(), 1);
     }
+
+    fn analyze(source: &str) -> ControlFlowGraph {
+        let parsed = crate::parser::LuauParser::new().parse(source).unwrap();
+        ControlFlowAnalyzer::new().analyze(&parsed).unwrap()
+    }
+
+    /// Block ending in `condition`
+    fn branch_on<'a>(cfg: &'a ControlFlowGraph, condition: &str) -> &'a BasicBlock {
+        cfg.all_blocks()
+            .into_iter()
+            .find(|b| b.terminator == Terminator::Branch(condition.to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_if_elseif_chain_edges() {
+        let cfg = analyze(
+            "local x = 1\n\
+             if x > 0 then\n\
+                 print(\"a\")\n\
+             elseif x < 0 then\n\
+                 return x\n\
+             else\n\
+                 print(\"c\")\n\
+             end\n\
+             print(\"done\")\n",
+        );
+
+        let entry = cfg.get_block(cfg.entry_block).unwrap();
+        assert_eq!(entry.statements[0].code, "local x = 1");
+        assert_eq!(entry.terminator, Terminator::Branch("x > 0".to_string()));
+
+        let (then_block, else_if) = cfg.branch_targets(entry.id).unwrap();
+        assert_eq!(cfg.get_block(then_block).unwrap().statements[0].code, "print(\"a\")");
+        assert_eq!(cfg.get_block(else_if).unwrap().start_line, 4);
+
+        let (returns, else_block) = cfg.branch_targets(branch_on(&cfg, "x < 0").id).unwrap();
+        assert_eq!(
+            cfg.get_block(returns).unwrap().terminator,
+            Terminator::Return("return x".to_string())
+        );
+        assert!(cfg.exit_blocks.contains(&returns));
+
+        // Both non-returning branches meet before the last statement
+        let join = cfg.get_block(then_block).unwrap().successors[0];
+        assert_eq!(cfg.get_block(else_block).unwrap().successors, vec![join]);
+        assert_eq!(cfg.get_block(join).unwrap().statements[0].code, "print(\"done\")");
+    }
+
+    #[test]
+    fn test_loops_break_and_continue() {
+        let cfg = analyze(
+            "local x = 0\n\
+             while x < 10 do\n\
+                 if x == 5 then break end\n\
+                 x += 1\n\
+             end\n\
+             repeat\n\
+                 x -= 1\n\
+                 if x == 3 then continue end\n\
+             until x <= 0\n",
+        );
+
+        let header = branch_on(&cfg, "x < 10");
+        let (body, exit) = cfg.branch_targets(header.id).unwrap();
+        assert_eq!(cfg.get_block(body).unwrap().terminator, Terminator::Branch("x == 5".to_string()));
+
+        // `break` jumps straight to the loop exit, the body loops back
+        let (breaks, rest) = cfg.branch_targets(branch_on(&cfg, "x == 5").id).unwrap();
+        assert_eq!(cfg.get_block(breaks).unwrap().successors, vec![exit]);
+        assert!(cfg.get_block(breaks).unwrap().statements.is_empty());
+        assert_eq!(cfg.get_block(rest).unwrap().successors, vec![header.id]);
+
+        // `continue` in `repeat` still evaluates `until`
+        let until = branch_on(&cfg, "x <= 0");
+        let (continues, _) = cfg.branch_targets(branch_on(&cfg, "x == 3").id).unwrap();
+        assert_eq!(cfg.get_block(continues).unwrap().successors, vec![until.id]);
+        let (done, again) = cfg.branch_targets(until.id).unwrap();
+        assert!(cfg.exit_blocks.contains(&done));
+        assert_eq!(cfg.get_block(again).unwrap().statements[0].code, "x -= 1");
+    }
 }
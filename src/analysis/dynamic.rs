@@ -0,0 +1,315 @@
+//! Dynamic global access detection
+//!
+//! Names are mangled by text, so a global the script also reaches through
+//! its environment (`_G.Handler = Handler`, `getfenv().Handler`,
+//! `shared["Handler"]`) would be renamed at the identifier but not in the
+//! key, and break without an error. Each such access keeps the name it
+//! spells out. When the key is computed or the table escapes
+//! (`_G[name]`, `local env = getfenv()`, `setfenv(...)`) any global could be
+//! reached, so every global the script declares keeps its name.
+
+use crate::diagnostics::{Diagnostic, Span};
+use crate::parser::{scan_types, ParseResult};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Tables that hold (or stand in for) a script's globals
+const GLOBAL_TABLES: &[&str] = &["_G", "shared"];
+
+/// Assignment operators, compound ones included
+const ASSIGNMENTS: &[&str] = &["=", "+=", "-=", "*=", "/=", "//=", "%=", "^=", "..="];
+
+/// A read or write of globals that bypasses their identifiers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DynamicGlobal {
+    /// `_G`, `shared`, `getfenv()` or `setfenv()`
+    pub via: String,
+    /// Global spelled out by the access; `None` when any global could be reached
+    pub name: Option<String>,
+    pub write: bool,
+    pub line: usize,
+    /// Identifiers this access keeps from being renamed
+    pub kept: Vec<String>,
+}
+
+impl DynamicGlobal {
+    /// Whether the accessed globals are unknown
+    pub fn is_opaque(&self) -> bool {
+        self.name.is_none()
+    }
+
+    /// The access as a `dynamic-global` diagnostic
+    pub fn diagnostic(&self) -> Diagnostic {
+        let suggestion = match &self.name {
+            Some(name) => {
+                format!("reference `{}` directly instead of through `{}`", name, self.via)
+            }
+            None => format!(
+                "use a literal key with `{}` so only that global keeps its name",
+                self.via
+            ),
+        };
+        Diagnostic::warning("dynamic-global", self.describe())
+            .at(Span::line(self.line))
+            .with_suggestion(suggestion)
+    }
+
+    fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => format!(
+                "`{}.{}` {} a global by name, so `{}` is not renamed",
+                self.via,
+                name,
+                if self.write { "writes" } else { "reads" },
+                name
+            ),
+            None => format!(
+                "`{}` can reach any global, so {} global name(s) are not renamed ({})",
+                self.via,
+                self.kept.len(),
+                self.kept.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for DynamicGlobal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.describe())
+    }
+}
+
+/// Finds `_G`, `shared`, `getfenv` and `setfenv` accesses
+pub struct DynamicGlobalDetector;
+
+impl DynamicGlobalDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Detect dynamic global accesses in parsed code
+    ///
+    /// Streaming parses keep no AST and report none.
+    pub fn detect(&self, parse_result: &ParseResult) -> Result<Vec<DynamicGlobal>> {
+        match &parse_result.ast {
+            Some(ast) => self.detect_source(&ast.nodes().to_string()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Detect dynamic global accesses in Luau source
+    pub fn detect_source(&self, source: &str) -> Result<Vec<DynamicGlobal>> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        // `x: typeof(_G)` isn't an access
+        let annotations = scan_types(source)?.annotations;
+        let mut annotation = annotations.iter().peekable();
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .filter(|t| {
+                let at = t.start_position().bytes();
+                while annotation.next_if(|span| span.end <= at).is_some() {}
+                !annotation.peek().is_some_and(|span| span.contains(&at))
+            })
+            .collect();
+
+        let scanner = Scanner {
+            tokens: &significant,
+        };
+        Ok((0..significant.len())
+            .filter_map(|i| scanner.access(i))
+            .collect())
+    }
+
+    /// Fill in what each access keeps, dropping the ones that keep nothing
+    ///
+    /// `renamable` holds the names the mangler would otherwise rename and
+    /// `globals` the ones the script uses as globals.
+    pub fn resolve(
+        &self,
+        accesses: Vec<DynamicGlobal>,
+        renamable: &BTreeSet<String>,
+        globals: &BTreeSet<String>,
+    ) -> Vec<DynamicGlobal> {
+        accesses
+            .into_iter()
+            .filter_map(|mut access| {
+                access.kept = match &access.name {
+                    Some(name) if renamable.contains(name) => vec![name.clone()],
+                    Some(_) => Vec::new(),
+                    None => globals.intersection(renamable).cloned().collect(),
+                };
+                (!access.kept.is_empty()).then_some(access)
+            })
+            .collect()
+    }
+}
+
+impl Default for DynamicGlobalDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Scanner<'a> {
+    tokens: &'a [&'a Token],
+}
+
+impl Scanner<'_> {
+    /// The access starting at token `i`, if one does
+    fn access(&self, i: usize) -> Option<DynamicGlobal> {
+        let name = self.identifier(i)?;
+        // `t._G` and `local _G` aren't the global
+        if i > 0
+            && (matches!(self.symbol(i - 1), Some(Symbol::Dot) | Some(Symbol::Colon))
+                || self.symbol(i - 1) == Some(Symbol::Local))
+        {
+            return None;
+        }
+
+        let line = self.tokens[i].start_position().line();
+        let (via, end) = if GLOBAL_TABLES.contains(&name) {
+            (name.to_string(), i)
+        } else if name == "getfenv" || name == "setfenv" {
+            let via = format!("{}()", name);
+            match self.call_end(i + 1) {
+                Some(end) if name == "getfenv" => (via, end),
+                // A replaced environment resolves every global through a table
+                _ => return Some(opaque(via, line)),
+            }
+        } else {
+            return None;
+        };
+
+        let key = match self.symbol(end + 1) {
+            Some(Symbol::Dot) => self.identifier(end + 2).map(|key| (key.to_string(), end + 2)),
+            Some(Symbol::LeftBracket) if self.symbol(end + 3) == Some(Symbol::RightBracket) => {
+                self.string(end + 2).map(|key| (key, end + 3))
+            }
+            _ => None,
+        };
+        Some(match key {
+            Some((key, last)) => DynamicGlobal {
+                via,
+                name: Some(key),
+                write: self
+                    .tokens
+                    .get(last + 1)
+                    .is_some_and(|t| ASSIGNMENTS.contains(&t.to_string().as_str())),
+                line,
+                kept: Vec::new(),
+            },
+            // Computed key, method call, or the table passed on or aliased
+            None => opaque(via, line),
+        })
+    }
+
+    /// Closing paren of the call whose `(` is token `open`
+    fn call_end(&self, open: usize) -> Option<usize> {
+        if self.symbol(open) != Some(Symbol::LeftParen) {
+            return None;
+        }
+        let mut depth = 0;
+        for j in open..self.tokens.len() {
+            match self.symbol(j) {
+                Some(Symbol::LeftParen) => depth += 1,
+                Some(Symbol::RightParen) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(j);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn string(&self, i: usize) -> Option<String> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::StringLiteral {
+                literal,
+                multi_line: None,
+                ..
+            } if !literal.contains('\\') => Some(literal.to_string()),
+            _ => None,
+        }
+    }
+
+    fn symbol(&self, i: usize) -> Option<Symbol> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Symbol { symbol } => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn identifier(&self, i: usize) -> Option<&str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+}
+
+fn opaque(via: String, line: usize) -> DynamicGlobal {
+    DynamicGlobal {
+        via,
+        name: None,
+        write: false,
+        line,
+        kept: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_named_and_opaque_accesses() {
+        let source = r#"local function handler() end
+_G.Handler = handler
+print(shared["Config"], getfenv().Version)
+getfenv(2)[key] = 1
+local t = {}
+t._G = 1
+"#;
+        let accesses = DynamicGlobalDetector::new().detect_source(source).unwrap();
+        let summary: Vec<(&str, Option<&str>, bool, usize)> = accesses
+            .iter()
+            .map(|a| (a.via.as_str(), a.name.as_deref(), a.write, a.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("_G", Some("Handler"), true, 2),
+                ("shared", Some("Config"), false, 3),
+                ("getfenv()", Some("Version"), false, 3),
+                ("getfenv()", None, false, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_keeps_renamable_names() {
+        let detector = DynamicGlobalDetector::new();
+        let accesses = detector
+            .detect_source("_G.Handler = 1\n_G.Other = 2\nlocal env = getfenv()\n")
+            .unwrap();
+        let renamable: BTreeSet<String> =
+            ["Handler", "update", "env"].iter().map(|s| s.to_string()).collect();
+        let globals: BTreeSet<String> = ["update", "print"].iter().map(|s| s.to_string()).collect();
+
+        let resolved = detector.resolve(accesses, &renamable, &globals);
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].kept, vec!["Handler".to_string()]);
+        assert!(resolved[1].is_opaque());
+        assert_eq!(resolved[1].kept, vec!["update".to_string()]);
+        assert!(resolved[1].to_string().contains("(update)"));
+    }
+}
@@ -0,0 +1,582 @@
+//! Constant folding
+//!
+//! `"Remote" .. "Event"` and `string.format("%s_%d", "Shop", 3)` give the
+//! pieces of a string away one literal at a time, and each piece would be
+//! its own encrypted entry. Likewise `60 * 60 * 24` shows its factors next
+//! to whatever the constant pass turns each of them into. This pre-pass
+//! evaluates such expressions when every operand is a literal, so the
+//! string and constant passes take the result as one literal instead.
+//!
+//! Only what is certain to evaluate the same is folded:
+//! - operands are plain string literals (no escapes or quotes) and decimal
+//!   integers, which concatenate the same way everywhere
+//! - `..` is right-associative and a metamethod on a non-constant operand
+//!   would see the operands in order, so only a constant run that ends the
+//!   chain is folded (`x .. "a" .. "b"` but not `"a" .. "b" .. x`)
+//! - `string.format` is folded with `%s`, `%d`, `%i` and `%%` only, and not
+//!   when the script declares its own `string`
+//! - arithmetic is folded only where the operator precedence around it
+//!   makes it a whole subexpression (`x - 2 * 3` folds `2 * 3`, `x - 2 + 3`
+//!   folds nothing), and not when the result is infinite, NaN or `-0`; `^`
+//!   is folded only between integers with an exact result
+
+use super::scope::Scope;
+use crate::parser::{
+    format_number, parse_number, safe_integer, scan_types, ParseResult, MAX_SAFE_INTEGER,
+};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+
+/// Largest integer `tostring` writes without an exponent
+const MAX_FOLDED_INTEGER: u64 = 99_999_999_999_999;
+
+/// Operators that bind tighter than `..` and would take its last operand
+const TIGHTER_OPERATORS: &[&str] = &["+", "-", "*", "/", "//", "%", "^"];
+
+/// Tokens after which an expression starts (so a literal there isn't the
+/// argument of `f "x"` call sugar or the operand of a unary operator)
+const EXPRESSION_STARTS: &[&str] = &[
+    "=", "(", ",", "{", "[", "return", "if", "elseif", "while", "until", "then", "else", "and",
+    "or", "==", "~=", "<", "<=", ">", ">=", "+=", "-=", "*=", "/=", "//=", "%=", "^=", "..=",
+];
+
+/// Precedence of the unary operators (`-`, `not`, `#`); only `^` binds tighter
+const UNARY_PRECEDENCE: u8 = 7;
+
+/// A constant expression and its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldedConstant {
+    /// String contents as they would be written between double quotes, or a
+    /// number literal
+    pub value: String,
+    /// Position of the expression's first token
+    pub line: usize,
+    pub column: usize,
+    /// Position of its last token
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl FoldedConstant {
+    /// Whether a token starting at `line`, `column` is part of the expression
+    pub fn covers(&self, line: usize, column: usize) -> bool {
+        (self.line, self.column) <= (line, column)
+            && (line, column) <= (self.end_line, self.end_column)
+    }
+}
+
+/// The constant expressions of a script
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    pub strings: Vec<FoldedConstant>,
+    pub numbers: Vec<FoldedConstant>,
+}
+
+/// Finds the constant expressions of a script
+pub struct ConstantFolder;
+
+impl ConstantFolder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Fold the constant expressions of parsed code
+    ///
+    /// Streaming parses keep no AST and fold nothing.
+    pub fn fold(&self, parse_result: &ParseResult, scopes: &[Scope]) -> Result<Folds> {
+        match &parse_result.ast {
+            Some(ast) => self.fold_source(&ast.nodes().to_string(), scopes),
+            None => Ok(Folds::default()),
+        }
+    }
+
+    /// Fold the constant expressions of Luau source
+    pub fn fold_source(&self, source: &str, scopes: &[Scope]) -> Result<Folds> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        let annotations = scan_types(source)?.annotations;
+        let mut annotation = annotations.iter().peekable();
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .filter(|t| {
+                let at = t.start_position().bytes();
+                while annotation.next_if(|span| span.end <= at).is_some() {}
+                !annotation.peek().is_some_and(|span| span.contains(&at))
+            })
+            .collect();
+
+        let scanner = Scanner {
+            tokens: &significant,
+            format: !scopes.iter().any(|scope| scope.variables.contains_key("string")),
+        };
+        let mut folds = Folds::default();
+        let mut i = 0;
+        while i < significant.len() {
+            match scanner.run(i) {
+                Some((fold, last)) => {
+                    folds.strings.extend(fold);
+                    i = last + 1;
+                }
+                None => i += 1,
+            }
+        }
+        let mut i = 0;
+        while i < significant.len() {
+            match scanner.arithmetic(i) {
+                Some((fold, last)) => {
+                    folds.numbers.push(fold);
+                    i = last + 1;
+                }
+                None => i += 1,
+            }
+        }
+        Ok(folds)
+    }
+}
+
+impl Default for ConstantFolder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A literal operand
+enum Constant {
+    Str(String),
+    Int(u64),
+}
+
+impl Constant {
+    fn text(&self) -> String {
+        match self {
+            Constant::Str(value) => value.clone(),
+            Constant::Int(value) => value.to_string(),
+        }
+    }
+}
+
+struct Scanner<'a> {
+    tokens: &'a [&'a Token],
+    /// Whether `string.format` is the built-in
+    format: bool,
+}
+
+impl Scanner<'_> {
+    /// The constant run of `..` operands starting at token `i`, and its last
+    /// token; the fold is `None` when the run can't be folded
+    fn run(&self, i: usize) -> Option<(Option<FoldedConstant>, usize)> {
+        let (first, mut last) = self.operand(i)?;
+        let mut value = first.text();
+        let mut operands = 1;
+        let mut called = matches!(first, Constant::Str(_)) && last > i;
+        while self.symbol(last + 1) == Some(Symbol::TwoDots) {
+            let Some((next, end)) = self.operand(last + 2) else {
+                // A constant prefix of the chain isn't safe to fold
+                return Some((None, last));
+            };
+            called |= end > last + 2;
+            value.push_str(&next.text());
+            operands += 1;
+            last = end;
+        }
+
+        let starts = i > 0
+            && (self.symbol(i - 1) == Some(Symbol::TwoDots)
+                || EXPRESSION_STARTS.contains(&self.text(i - 1).as_str()));
+        let ends = !self
+            .tokens
+            .get(last + 1)
+            .is_some_and(|t| TIGHTER_OPERATORS.contains(&t.to_string().as_str()));
+        if !starts || !ends || (operands == 1 && !called) {
+            return Some((None, last));
+        }
+
+        let first = self.tokens[i].start_position();
+        let end = self.tokens[last].start_position();
+        Some((
+            Some(FoldedConstant {
+                value,
+                line: first.line(),
+                column: first.character(),
+                end_line: end.line(),
+                end_column: end.character(),
+            }),
+            last,
+        ))
+    }
+
+    /// The literal or `string.format` call at token `i`, and its last token
+    fn operand(&self, i: usize) -> Option<(Constant, usize)> {
+        if let Some(literal) = self.literal(i) {
+            return Some((literal, i));
+        }
+        if !self.format
+            || self.identifier(i) != Some("string")
+            || self.symbol(i + 1) != Some(Symbol::Dot)
+            || self.identifier(i + 2) != Some("format")
+            || self.symbol(i + 3) != Some(Symbol::LeftParen)
+        {
+            return None;
+        }
+
+        let Some(Constant::Str(pattern)) = self.literal(i + 4) else {
+            return None;
+        };
+        let mut args = Vec::new();
+        let mut j = i + 5;
+        while self.symbol(j) == Some(Symbol::Comma) {
+            args.push(self.literal(j + 1)?);
+            j += 2;
+        }
+        if self.symbol(j) != Some(Symbol::RightParen) {
+            return None;
+        }
+        // `string.format(...):upper()` can't become `"...":upper()`
+        let suffixed = matches!(
+            self.symbol(j + 1),
+            Some(Symbol::Colon | Symbol::Dot | Symbol::LeftBracket | Symbol::LeftParen | Symbol::LeftBrace)
+        ) || self.literal(j + 1).is_some();
+        if suffixed {
+            return None;
+        }
+        Some((Constant::Str(evaluate_format(&pattern, &args)?), j))
+    }
+
+    /// A plain string literal or decimal integer at token `i`
+    fn literal(&self, i: usize) -> Option<Constant> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::StringLiteral {
+                literal,
+                multi_line: None,
+                ..
+            } if !literal.contains(['\\', '"', '\'']) => Some(Constant::Str(literal.to_string())),
+            TokenType::Number { text } => {
+                let digits = text.replace('_', "");
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                let value: u64 = digits.parse().ok()?;
+                (value <= MAX_FOLDED_INTEGER).then_some(Constant::Int(value))
+            }
+            _ => None,
+        }
+    }
+
+    /// The foldable arithmetic expression starting at token `i`, and its
+    /// last token
+    fn arithmetic(&self, i: usize) -> Option<(FoldedConstant, usize)> {
+        if i == 0 {
+            return None;
+        }
+        let min_precedence = self.operand_precedence(i - 1)?;
+        let expr = self.expression(i, min_precedence)?;
+        if expr.operators == 0 {
+            return None;
+        }
+
+        let first = self.tokens[i].start_position();
+        let end = self.tokens[expr.last].start_position();
+        Some((
+            FoldedConstant {
+                value: format_number(expr.value),
+                line: first.line(),
+                column: first.character(),
+                end_line: end.line(),
+                end_column: end.character(),
+            },
+            expr.last,
+        ))
+    }
+
+    /// Lowest precedence an operand after token `i` may bind, or `None`
+    /// when no expression starts there
+    fn operand_precedence(&self, i: usize) -> Option<u8> {
+        let text = self.text(i);
+        let unary = text == "not"
+            || text == "#"
+            || (text == "-" && (i == 0 || self.opens_operand(i - 1)));
+        if unary {
+            return Some(UNARY_PRECEDENCE + 1);
+        }
+        if let Some((precedence, right)) = binary_operator(&text) {
+            return Some(if right { precedence } else { precedence + 1 });
+        }
+        EXPRESSION_STARTS.contains(&text.as_str()).then_some(0)
+    }
+
+    /// Whether an operand follows token `i`
+    fn opens_operand(&self, i: usize) -> bool {
+        let text = self.text(i);
+        matches!(text.as_str(), "not" | "#")
+            || binary_operator(&text).is_some()
+            || EXPRESSION_STARTS.contains(&text.as_str())
+    }
+
+    /// The constant expression at token `i` made of operators binding at
+    /// least `min_precedence`
+    ///
+    /// Stops before an operator whose right operand isn't constant; the
+    /// expression so far is still a whole subexpression then, but not one
+    /// an enclosing operator can take as its operand.
+    fn expression(&self, i: usize, min_precedence: u8) -> Option<Arithmetic> {
+        let mut left = self.unary(i)?;
+        while let Some(operator) = self.tokens.get(left.last + 1).map(|t| t.to_string()) {
+            let Some((precedence, right)) = arithmetic_operator(&operator) else {
+                break;
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            let next = if right { precedence } else { precedence + 1 };
+            let rhs = self
+                .expression(left.last + 2, next)
+                .filter(|rhs| rhs.complete)
+                .and_then(|rhs| Some((apply(&operator, left.value, rhs.value)?, rhs)));
+            let Some((value, rhs)) = rhs else {
+                left.complete = false;
+                break;
+            };
+            left = Arithmetic {
+                value,
+                last: rhs.last,
+                operators: left.operators + rhs.operators + 1,
+                complete: true,
+            };
+        }
+        Some(left)
+    }
+
+    /// A number, a negated operand or a parenthesized expression at token `i`
+    fn unary(&self, i: usize) -> Option<Arithmetic> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Number { text } => Some(Arithmetic {
+                value: parse_number(text.as_str())?,
+                last: i,
+                operators: 0,
+                complete: true,
+            }),
+            TokenType::Symbol {
+                symbol: Symbol::Minus,
+            } => {
+                let operand = self
+                    .expression(i + 1, UNARY_PRECEDENCE + 1)
+                    .filter(|operand| operand.complete)?;
+                Some(Arithmetic {
+                    value: checked(-operand.value)?,
+                    ..operand
+                })
+            }
+            TokenType::Symbol {
+                symbol: Symbol::LeftParen,
+            } => {
+                let inner = self.expression(i + 1, 0).filter(|inner| inner.complete)?;
+                let close = inner.last + 1;
+                if self.symbol(close) != Some(Symbol::RightParen) {
+                    return None;
+                }
+                // `(2 * 3):method()` can't become `6:method()`
+                let suffixed = matches!(
+                    self.symbol(close + 1),
+                    Some(Symbol::Colon | Symbol::Dot | Symbol::LeftBracket | Symbol::LeftParen | Symbol::LeftBrace)
+                ) || self.literal(close + 1).is_some();
+                if suffixed {
+                    return None;
+                }
+                Some(Arithmetic { last: close, ..inner })
+            }
+            _ => None,
+        }
+    }
+
+    fn text(&self, i: usize) -> String {
+        self.tokens[i].to_string()
+    }
+
+    fn symbol(&self, i: usize) -> Option<Symbol> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Symbol { symbol } => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn identifier(&self, i: usize) -> Option<&str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// A constant arithmetic expression being folded
+struct Arithmetic {
+    value: f64,
+    /// Index of its last token
+    last: usize,
+    /// Binary operators folded into it
+    operators: usize,
+    /// Whether it ends where the real expression does (see `Scanner::expression`)
+    complete: bool,
+}
+
+/// Precedence and right-associativity of a binary operator
+fn binary_operator(text: &str) -> Option<(u8, bool)> {
+    arithmetic_operator(text).or(match text {
+        "or" => Some((1, false)),
+        "and" => Some((2, false)),
+        "<" | ">" | "<=" | ">=" | "~=" | "==" => Some((3, false)),
+        ".." => Some((4, true)),
+        _ => None,
+    })
+}
+
+/// Precedence and right-associativity of an arithmetic operator
+fn arithmetic_operator(text: &str) -> Option<(u8, bool)> {
+    match text {
+        "+" | "-" => Some((5, false)),
+        "*" | "/" | "//" | "%" => Some((6, false)),
+        "^" => Some((8, true)),
+        _ => None,
+    }
+}
+
+/// `a <operator> b` as the Luau VM computes it
+fn apply(operator: &str, a: f64, b: f64) -> Option<f64> {
+    let value = match operator {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "//" => (a / b).floor(),
+        "%" => a - (a / b).floor() * b,
+        // `pow` may round differently from Rust's, so only exact powers
+        "^" => {
+            let base = safe_integer(a)?;
+            let exponent = u32::try_from(safe_integer(b)?).ok().filter(|e| *e <= 64)?;
+            let power = base.checked_pow(exponent)? as f64;
+            if power.abs() > MAX_SAFE_INTEGER {
+                return None;
+            }
+            power
+        }
+        _ => return None,
+    };
+    checked(value)
+}
+
+/// `value` if a literal can stand for it
+fn checked(value: f64) -> Option<f64> {
+    let negative_zero = value == 0.0 && value.is_sign_negative();
+    (value.is_finite() && !negative_zero).then_some(value)
+}
+
+/// `string.format(pattern, args...)`, if it only uses `%s`, `%d`, `%i` and `%%`
+fn evaluate_format(pattern: &str, args: &[Constant]) -> Option<String> {
+    let mut output = String::with_capacity(pattern.len());
+    let mut args = args.iter();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match (chars.next()?, args.next()) {
+            ('%', _) => output.push('%'),
+            ('s', Some(arg)) => output.push_str(&arg.text()),
+            ('d' | 'i', Some(Constant::Int(value))) => output.push_str(&value.to_string()),
+            _ => return None,
+        }
+    }
+    // Extra arguments are ignored at runtime, but likely a mistake worth keeping
+    args.next().is_none().then_some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(source: &str) -> Vec<String> {
+        ConstantFolder::new()
+            .fold_source(source, &[])
+            .unwrap()
+            .strings
+            .into_iter()
+            .map(|fold| fold.value)
+            .collect()
+    }
+
+    fn numbers(source: &str) -> Vec<String> {
+        ConstantFolder::new()
+            .fold_source(source, &[])
+            .unwrap()
+            .numbers
+            .into_iter()
+            .map(|fold| fold.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_folds_constant_concatenation_and_format() {
+        let source = r#"local remote = "Remote" .. "Event" .. 2
+local label = string.format("%s_%d (100%%)", "Shop", 3)
+print(name .. "a" .. "b")
+local url = "https://" .. string.format("%s.com", "roblox")
+"#;
+        assert_eq!(
+            values(source),
+            vec!["RemoteEvent2", "Shop_3 (100%)", "ab", "https://roblox.com"]
+        );
+    }
+
+    #[test]
+    fn test_skips_unsafe_expressions() {
+        let source = r#"local a = "a" .. "b" .. name
+local b = "x" .. "1" + 2
+local c = string.format("%5.2f", 1)
+local d = string.format("%s", "x"):upper()
+print "a" .. "b"
+local e = "a\n" .. "b"
+local f = -"1" .. "2"
+"#;
+        assert!(values(source).is_empty());
+    }
+
+    #[test]
+    fn test_covers_span() {
+        let folded = ConstantFolder::new()
+            .fold_source("local s = \"a\" ..\n    \"b\"\n", &[])
+            .unwrap()
+            .strings;
+        assert_eq!(folded.len(), 1);
+        assert!(folded[0].covers(1, 11));
+        assert!(folded[0].covers(2, 5));
+        assert!(!folded[0].covers(1, 7));
+    }
+
+    #[test]
+    fn test_folds_arithmetic_subexpressions() {
+        let source = r#"local day = 60 * 60 * 24
+local mask = 0xFF + 1
+local a = x - 2 * 3
+local b = -(2 ^ 10) .. "!"
+local c = 7 // 2 + 7 % 3 + 1 / 4
+print(f(10 * 1e3), t[1 + 1])
+"#;
+        assert_eq!(numbers(source), vec!["86400", "256", "6", "-1024", "4.25", "10000", "2"]);
+    }
+
+    #[test]
+    fn test_skips_arithmetic_bound_elsewhere() {
+        let source = r#"local a = x - 2 + 3
+local b = 2 * 3 * x
+local c = 1 / 0
+local d = 2 ^ 0.5
+local e = (1 + 2):method()
+local f = -2
+"#;
+        // `2 * 3 * x` is `(2 * 3) * x`, and `(3):method()` keeps its parens
+        assert_eq!(numbers(source), vec!["6", "3"]);
+    }
+}
@@ -0,0 +1,1010 @@
+//! Table key analysis
+//!
+//! Finds the string keys a script uses on tables (`t.key`, `t:key()`,
+//! `{key = ...}`, `t["key"]`, `function t.key()`) and decides which ones can
+//! be rewritten. Keys are rewritten by name, so a key is only safe when
+//! every site using it is on a table the script builds itself
+//! (`local t = {...}`) and that table never leaves the analysis: it is not
+//! passed to a function that might read its keys, returned, stored, aliased
+//! or indexed with a computed key. Every other key is kept and reported with
+//! the first reason found.
+
+use super::scope::ScopeAnalyzer;
+use crate::parser::{scan_types, BlockNesting, ParseResult};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Functions that take a table without reading its string keys
+const KEY_BLIND: &[&str] = &[
+    "getmetatable",
+    "ipairs",
+    "rawlen",
+    "tostring",
+    "type",
+    "typeof",
+    "unpack",
+    "table.clear",
+    "table.concat",
+    "table.find",
+    "table.freeze",
+    "table.insert",
+    "table.isfrozen",
+    "table.remove",
+    "table.sort",
+    "table.unpack",
+];
+
+/// How a key is used at a site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySiteKind {
+    /// `t.key`
+    Field,
+    /// `t:key(...)`; `plain` when the arguments are in parentheses
+    Method { plain: bool },
+    /// `{key = ...}`
+    Constructor,
+    /// `t["key"]` or `{["key"] = ...}`
+    Bracket,
+    /// Last name of `function t.key()` or `function t:key()`
+    Declaration { method: bool },
+    /// Inner name of `function t.key.f()`
+    Path,
+}
+
+/// One use of a table key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeySite {
+    pub key: String,
+    pub kind: KeySiteKind,
+    /// Position of the key's identifier (or string literal, for brackets)
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Why a key keeps its name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "reason", content = "detail")]
+pub enum KeepReason {
+    /// `__index`, `__call`, ...: the VM looks these up by name
+    Metamethod,
+    /// A Roblox API name or another preserved identifier
+    Preserved,
+    /// Used on a value that isn't provably a table local to the script
+    ForeignTable(String),
+    /// Used on a table that is passed, stored or aliased
+    Escapes(String),
+    /// Used on a table the module returns
+    Exported(String),
+    /// Also a string value, or used on a table indexed with computed keys
+    DynamicLookup,
+    /// Used where the settings leave the code alone
+    Excluded,
+}
+
+impl std::fmt::Display for KeepReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeepReason::Metamethod => write!(f, "metamethod"),
+            KeepReason::Preserved => write!(f, "preserved name"),
+            KeepReason::ForeignTable(on) => {
+                write!(f, "used on `{}`, which is not a local table", on)
+            }
+            KeepReason::Escapes(table) => write!(f, "`{}` is passed, stored or aliased", table),
+            KeepReason::Exported(table) => write!(f, "`{}` is returned by the module", table),
+            KeepReason::DynamicLookup => write!(f, "also a string value or computed lookup"),
+            KeepReason::Excluded => write!(f, "obfuscation is off at a use"),
+        }
+    }
+}
+
+/// A key left as it is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeptKey {
+    pub key: String,
+    pub reason: KeepReason,
+    /// Line of the site the reason was found at
+    pub line: usize,
+}
+
+/// Table keys found in a script
+#[derive(Debug, Clone, Default)]
+pub struct TableKeys {
+    /// Every site of a key that is safe to rewrite, in source order
+    pub sites: Vec<KeySite>,
+    /// Keys that must keep their name, by key
+    pub kept: Vec<KeptKey>,
+}
+
+impl TableKeys {
+    /// Keys safe to rewrite
+    pub fn keys(&self) -> BTreeSet<&str> {
+        self.sites.iter().map(|site| site.key.as_str()).collect()
+    }
+
+    /// Stop rewriting `key` and report it as kept for `reason`
+    pub fn keep(&mut self, key: &str, reason: KeepReason, line: usize) {
+        if !self.sites.iter().any(|site| site.key == key) {
+            return;
+        }
+        self.sites.retain(|site| site.key != key);
+        let at = self.kept.partition_point(|kept| kept.key.as_str() < key);
+        self.kept.insert(
+            at,
+            KeptKey {
+                key: key.to_string(),
+                reason,
+                line,
+            },
+        );
+    }
+}
+
+/// Decides which table keys a script can rewrite
+pub struct TableKeyAnalyzer {
+    preserved: HashSet<String>,
+}
+
+impl TableKeyAnalyzer {
+    /// Keys in `preserved` (e.g. Roblox API names) are always kept
+    pub fn new(preserved: &[String]) -> Self {
+        Self {
+            preserved: preserved.iter().cloned().collect(),
+        }
+    }
+
+    /// Analyze the keys of parsed code
+    ///
+    /// Streaming parses keep no AST, so no keys are found.
+    pub fn analyze(&self, parse_result: &ParseResult) -> Result<TableKeys> {
+        match &parse_result.ast {
+            Some(ast) => self.analyze_source(&ast.nodes().to_string()),
+            None => Ok(TableKeys::default()),
+        }
+    }
+
+    /// Analyze the keys of Luau source
+    pub fn analyze_source(&self, source: &str) -> Result<TableKeys> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        // `a: T` and `x :: T` aren't key uses
+        let annotations = scan_types(source)?.annotations;
+        let mut annotation = annotations.iter().peekable();
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .filter(|t| {
+                let at = t.start_position().bytes();
+                while annotation.next_if(|span| span.end <= at).is_some() {}
+                !annotation.peek().is_some_and(|span| span.contains(&at))
+            })
+            .collect();
+
+        let mut scanner = KeyScanner::new(source, &significant);
+        scanner.run();
+        let globals = ScopeAnalyzer::new().global_names_in_source(source)?;
+        Ok(self.resolve(scanner, &globals))
+    }
+
+    /// Classify every key from the scanned sites
+    fn resolve(&self, scanner: KeyScanner, globals: &BTreeSet<String>) -> TableKeys {
+        // `t.method` read as a value can be called with any `self`
+        let method_reads: HashSet<(&str, &str)> = scanner
+            .sites
+            .iter()
+            .filter(|raw| matches!(raw.site.kind, KeySiteKind::Field | KeySiteKind::Bracket))
+            .filter_map(|raw| match &raw.receiver {
+                Receiver::Name { name, .. } => Some((name.as_str(), raw.site.key.as_str())),
+                _ => None,
+            })
+            .collect();
+
+        let receiver_reason = |receiver: &Receiver| -> Option<KeepReason> {
+            let name = match receiver {
+                Receiver::Name { name, .. } => name,
+                Receiver::Constructor(Origin::Named(name)) => name,
+                Receiver::Constructor(Origin::Returned) => {
+                    return Some(KeepReason::Exported("{...}".to_string()))
+                }
+                Receiver::Constructor(Origin::Unnamed) => {
+                    return Some(KeepReason::Escapes("{...}".to_string()))
+                }
+                Receiver::Other(text) => return Some(KeepReason::ForeignTable(text.clone())),
+            };
+            let binding = scanner.bindings.get(name);
+            let local_table = !globals.contains(name)
+                && binding.is_some_and(|b| b.tables > 0 && !b.other);
+            if !local_table {
+                return Some(KeepReason::ForeignTable(name.clone()));
+            }
+            if let Receiver::Name {
+                via_self: Some(method),
+                ..
+            } = receiver
+            {
+                if scanner.declares_self || method_reads.contains(&(name.as_str(), method.as_str())) {
+                    return Some(KeepReason::ForeignTable("self".to_string()));
+                }
+            }
+            match binding.and_then(|b| b.escape) {
+                Some((true, _)) => Some(KeepReason::Exported(name.clone())),
+                Some((false, _)) => Some(KeepReason::Escapes(name.clone())),
+                None if binding.is_some_and(|b| b.computed) => Some(KeepReason::DynamicLookup),
+                None => None,
+            }
+        };
+
+        let mut by_key: BTreeMap<&str, Vec<&RawSite>> = BTreeMap::new();
+        for raw in &scanner.sites {
+            by_key.entry(raw.site.key.as_str()).or_default().push(raw);
+        }
+
+        let mut keys = TableKeys::default();
+        for (key, sites) in by_key {
+            let first_line = sites[0].site.line;
+            let kept = if key.starts_with("__") {
+                Some((KeepReason::Metamethod, first_line))
+            } else if self.preserved.contains(key) {
+                Some((KeepReason::Preserved, first_line))
+            } else if scanner.strings.contains(key) {
+                Some((KeepReason::DynamicLookup, first_line))
+            } else {
+                sites.iter().find_map(|raw| {
+                    receiver_reason(&raw.receiver).map(|reason| (reason, raw.site.line))
+                })
+            };
+            match kept {
+                Some((reason, line)) => keys.kept.push(KeptKey {
+                    key: key.to_string(),
+                    reason,
+                    line,
+                }),
+                None => keys.sites.extend(sites.iter().map(|raw| raw.site.clone())),
+            }
+        }
+        keys.sites.sort_by_key(|site| (site.line, site.column));
+        keys
+    }
+}
+
+/// Construct open at the scan position
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Frame {
+    /// Function body and what `self` means in it
+    Function(SelfBinding),
+    /// `do`, loop, `then`/`else` or `repeat` body
+    Block,
+    /// Statement `if` before its first `then`
+    If,
+    /// `if` used as an expression; has no `end`
+    IfExpression,
+    /// `for` header, whose `do` opens the body
+    For,
+    Paren,
+    Bracket,
+    /// Table constructor and where its value goes
+    Brace(Origin),
+}
+
+/// What `self` refers to inside a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SelfBinding {
+    /// Not a method: `self` is whatever encloses the function
+    Inherit,
+    /// `function t:method()` with `t` a plain name
+    Method { table: String, method: String },
+    /// A method on a longer path, or `self` as an explicit parameter
+    Opaque,
+}
+
+/// Where a table constructor's value goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Origin {
+    /// `local t = {` or `t = {`
+    Named(String),
+    /// `return {` at the top level
+    Returned,
+    Unnamed,
+}
+
+/// What a site's key is looked up on
+#[derive(Debug, Clone)]
+enum Receiver {
+    /// A plain name; `via_self` is the method when it was `self` there
+    Name {
+        name: String,
+        via_self: Option<String>,
+    },
+    Constructor(Origin),
+    /// A longer expression (`a.b`, `f()`), by its source text
+    Other(String),
+}
+
+#[derive(Debug)]
+struct RawSite {
+    site: KeySite,
+    receiver: Receiver,
+}
+
+/// Everything the scan learns about one name
+#[derive(Debug, Default)]
+struct Binding {
+    /// Definitions as a fresh table (`local t = {`, `t = {`)
+    tables: usize,
+    /// Any other definition (parameter, loop variable, other value)
+    other: bool,
+    /// First use handing the table elsewhere: whether it is the module's
+    /// return value, and the line
+    escape: Option<(bool, usize)>,
+    /// Indexed with a computed key somewhere
+    computed: bool,
+}
+
+/// A name is defined either as a fresh table or as something else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Definition {
+    Table,
+    Other,
+}
+
+/// Token-level walk collecting key sites and what is known of each name
+struct KeyScanner<'a> {
+    source: &'a str,
+    tokens: &'a [&'a Token],
+    /// Index of the matching bracket for every `(`, `[`, `{` and closer
+    partner: Vec<Option<usize>>,
+    /// Whether each token is where an expression is expected, so an `if`
+    /// there starts an if-expression
+    expression_at: Vec<bool>,
+    frames: Vec<Frame>,
+    sites: Vec<RawSite>,
+    bindings: HashMap<String, Binding>,
+    /// Values of string literals that aren't `t["key"]` keys
+    strings: HashSet<String>,
+    /// The script declares or assigns a variable named `self`
+    declares_self: bool,
+}
+
+impl<'a> KeyScanner<'a> {
+    fn new(source: &'a str, tokens: &'a [&'a Token]) -> Self {
+        let mut partner = vec![None; tokens.len()];
+        let mut open = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token.token_type() {
+                TokenType::Symbol {
+                    symbol: Symbol::LeftParen | Symbol::LeftBracket | Symbol::LeftBrace,
+                } => open.push(i),
+                TokenType::Symbol {
+                    symbol: Symbol::RightParen | Symbol::RightBracket | Symbol::RightBrace,
+                } => {
+                    if let Some(start) = open.pop() {
+                        partner[start] = Some(i);
+                        partner[i] = Some(start);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut nesting = BlockNesting::new();
+        let expression_at = tokens
+            .iter()
+            .map(|token| {
+                let expression = nesting.in_expression();
+                nesting.advance(token);
+                expression
+            })
+            .collect();
+
+        Self {
+            source,
+            tokens,
+            partner,
+            expression_at,
+            frames: Vec::new(),
+            sites: Vec::new(),
+            bindings: HashMap::new(),
+            strings: HashSet::new(),
+            declares_self: false,
+        }
+    }
+
+    fn run(&mut self) {
+        let mut i = 0;
+
+        while i < self.tokens.len() {
+            i = match self.symbol(i) {
+                Some(Symbol::Local) if self.symbol(i + 1) == Some(Symbol::Function) => {
+                    if let Some(name) = self.identifier(i + 2) {
+                        self.define(name, Definition::Other);
+                    }
+                    self.function_body(i + 3, SelfBinding::Inherit)
+                }
+                Some(Symbol::Local) => self.local_names(i + 1),
+                Some(Symbol::Function) => self.function(i + 1),
+                Some(Symbol::For) => {
+                    self.frames.push(Frame::For);
+                    let mut j = i + 1;
+                    while let Some(name) = self.identifier(j) {
+                        self.define(name, Definition::Other);
+                        j += 1;
+                        if self.symbol(j) != Some(Symbol::Comma) {
+                            break;
+                        }
+                        j += 1;
+                    }
+                    j
+                }
+                Some(Symbol::Do) => {
+                    match self.frames.last_mut() {
+                        Some(frame @ Frame::For) => *frame = Frame::Block,
+                        _ => self.frames.push(Frame::Block),
+                    }
+                    i + 1
+                }
+                Some(Symbol::Repeat) => {
+                    self.frames.push(Frame::Block);
+                    i + 1
+                }
+                Some(Symbol::If) => {
+                    let frame = if self.expression_at[i] {
+                        Frame::IfExpression
+                    } else {
+                        Frame::If
+                    };
+                    self.frames.push(frame);
+                    i + 1
+                }
+                Some(Symbol::Then) => {
+                    if self.frames.last() == Some(&Frame::If) {
+                        self.frames.pop();
+                        self.frames.push(Frame::Block);
+                    }
+                    i + 1
+                }
+                Some(Symbol::ElseIf) => {
+                    if self.frames.last() == Some(&Frame::Block) {
+                        self.frames.pop();
+                        self.frames.push(Frame::If);
+                    }
+                    i + 1
+                }
+                Some(Symbol::Else) => match self.frames.last() {
+                    // `else if` continues the same if-expression
+                    Some(Frame::IfExpression) if self.symbol(i + 1) == Some(Symbol::If) => i + 2,
+                    Some(Frame::IfExpression) => {
+                        self.frames.pop();
+                        i + 1
+                    }
+                    _ => i + 1,
+                },
+                Some(
+                    Symbol::End
+                    | Symbol::Until
+                    | Symbol::RightParen
+                    | Symbol::RightBracket
+                    | Symbol::RightBrace,
+                ) => {
+                    self.frames.pop();
+                    i + 1
+                }
+                Some(Symbol::LeftParen) => {
+                    self.frames.push(Frame::Paren);
+                    i + 1
+                }
+                Some(Symbol::LeftBrace) => {
+                    let origin = self.origin(i);
+                    self.frames.push(Frame::Brace(origin));
+                    i + 1
+                }
+                Some(Symbol::LeftBracket) => self.bracket(i),
+                Some(Symbol::Dot | Symbol::Colon) => self.member(i),
+                _ => match self.tokens[i].token_type() {
+                    TokenType::Identifier { .. } => self.name(i),
+                    TokenType::StringLiteral { literal, .. } => {
+                        self.strings.insert(literal.to_string());
+                        i + 1
+                    }
+                    _ => i + 1,
+                },
+            };
+        }
+    }
+
+    /// `function name.path:method(...)` statement or `function(...)` expression
+    fn function(&mut self, i: usize) -> usize {
+        let Some(root) = self.identifier(i) else {
+            return self.function_body(i, SelfBinding::Inherit);
+        };
+
+        let mut path = vec![i];
+        let mut j = i + 1;
+        let mut method = false;
+        while matches!(self.symbol(j), Some(Symbol::Dot) | Some(Symbol::Colon))
+            && self.identifier(j + 1).is_some()
+        {
+            method = self.symbol(j) == Some(Symbol::Colon);
+            path.push(j + 1);
+            j += 2;
+        }
+
+        if path.len() == 1 {
+            self.define(root, Definition::Other);
+            return self.function_body(j, SelfBinding::Inherit);
+        }
+
+        for depth in 1..path.len() {
+            let kind = if depth == path.len() - 1 {
+                KeySiteKind::Declaration { method }
+            } else {
+                KeySiteKind::Path
+            };
+            let receiver = if depth == 1 {
+                self.name_receiver(root)
+            } else {
+                Receiver::Other(self.text(i, path[depth - 1]))
+            };
+            self.push_site(path[depth], kind, receiver);
+        }
+
+        let binding = match (method, path.len(), self.identifier(path[path.len() - 1])) {
+            (true, 2, Some(name)) if root != "self" => SelfBinding::Method {
+                table: root.to_string(),
+                method: name.to_string(),
+            },
+            (true, _, _) => SelfBinding::Opaque,
+            _ => SelfBinding::Inherit,
+        };
+        self.function_body(j, binding)
+    }
+
+    /// Parameter list at `i` and the body; returns the index after `)`
+    fn function_body(&mut self, i: usize, mut binding: SelfBinding) -> usize {
+        if self.symbol(i) != Some(Symbol::LeftParen) {
+            self.frames.push(Frame::Function(binding));
+            return i;
+        }
+        let mut j = i + 1;
+        while j < self.tokens.len() && self.symbol(j) != Some(Symbol::RightParen) {
+            match self.identifier(j) {
+                Some("self") => binding = SelfBinding::Opaque,
+                Some(name) => self.define(name, Definition::Other),
+                None => {}
+            }
+            j += 1;
+        }
+        self.frames.push(Frame::Function(binding));
+        j + 1
+    }
+
+    /// `a, b <const>, c` after `local`; returns the index after the names
+    fn local_names(&mut self, i: usize) -> usize {
+        let mut names = Vec::new();
+        let mut j = i;
+        while let Some(name) = self.identifier(j) {
+            names.push(name);
+            j += 1;
+            // Luau attributes: `<const>`, `<close>`
+            if self.symbol(j) == Some(Symbol::LessThan) {
+                j += 3;
+            }
+            if self.symbol(j) != Some(Symbol::Comma) {
+                break;
+            }
+            j += 1;
+        }
+
+        let fresh_table = names.len() == 1
+            && self.symbol(j) == Some(Symbol::Equal)
+            && self.symbol(j + 1) == Some(Symbol::LeftBrace);
+        for name in names {
+            let definition = if fresh_table {
+                Definition::Table
+            } else {
+                Definition::Other
+            };
+            self.define(name, definition);
+        }
+        j
+    }
+
+    /// `.key` or `:key` at `i`
+    fn member(&mut self, i: usize) -> usize {
+        if self.identifier(i + 1).is_none() {
+            return i + 1;
+        }
+        let kind = if self.symbol(i) == Some(Symbol::Colon) {
+            KeySiteKind::Method {
+                plain: self.symbol(i + 2) == Some(Symbol::LeftParen),
+            }
+        } else {
+            KeySiteKind::Field
+        };
+        let receiver = self.receiver(i);
+        self.push_site(i + 1, kind, receiver);
+        i + 2
+    }
+
+    /// `t[...]` index or `{[...] = v}` key at `i`
+    fn bracket(&mut self, i: usize) -> usize {
+        let literal = self
+            .key_literal(i + 1)
+            .filter(|_| self.symbol(i + 2) == Some(Symbol::RightBracket));
+
+        if i > 0 && self.continues_chain(i - 1) {
+            let receiver = self.receiver(i);
+            if literal.is_some() {
+                self.push_site(i + 1, KeySiteKind::Bracket, receiver);
+                return i + 3;
+            }
+            let number = matches!(
+                self.tokens.get(i + 1).map(|t| t.token_type()),
+                Some(TokenType::Number { .. })
+            ) && self.symbol(i + 2) == Some(Symbol::RightBracket);
+            if let (false, Receiver::Name { name, .. }) = (number, &receiver) {
+                self.bindings.entry(name.clone()).or_default().computed = true;
+            }
+        } else if let Some(Frame::Brace(origin)) = self.frames.last() {
+            if literal.is_some() && self.symbol(i + 3) == Some(Symbol::Equal) {
+                let receiver = Receiver::Constructor(origin.clone());
+                self.push_site(i + 1, KeySiteKind::Bracket, receiver);
+                return i + 3;
+            }
+        }
+
+        self.frames.push(Frame::Bracket);
+        i + 1
+    }
+
+    /// Identifier at `i` outside a `.key`/`:key` position
+    fn name(&mut self, i: usize) -> usize {
+        let Some(name) = self.identifier(i) else {
+            return i + 1;
+        };
+        let next = self.symbol(i + 1);
+
+        if let Some(Frame::Brace(origin)) = self.frames.last() {
+            if next == Some(Symbol::Equal) {
+                let receiver = Receiver::Constructor(origin.clone());
+                self.push_site(i, KeySiteKind::Constructor, receiver);
+                return i + 1;
+            }
+        }
+
+        let previous = i.checked_sub(1).and_then(|p| self.symbol(p));
+        let statement = matches!(
+            self.frames.last(),
+            None | Some(Frame::Function(_)) | Some(Frame::Block)
+        );
+        match next {
+            Some(Symbol::Equal) if statement && previous != Some(Symbol::Comma) => {
+                let definition = if self.symbol(i + 2) == Some(Symbol::LeftBrace) {
+                    Definition::Table
+                } else {
+                    Definition::Other
+                };
+                self.define(name, definition);
+            }
+            // Last target of `a, b = ...`
+            Some(Symbol::Equal) if statement => self.define(name, Definition::Other),
+            // Other targets of a multiple assignment, or values of a `return`
+            Some(Symbol::Comma) if statement => {
+                self.define(name, Definition::Other);
+                self.value_use(i, name);
+            }
+            // Root of a site; `member` and `bracket` handle it
+            Some(Symbol::Dot | Symbol::Colon | Symbol::LeftBracket) => {}
+            _ => self.value_use(i, name),
+        }
+        i + 1
+    }
+
+    /// `name` at `i` used as a value, which may hand its keys elsewhere
+    fn value_use(&mut self, i: usize, name: &str) {
+        if self.harmless_use(i) {
+            return;
+        }
+        let table = if name == "self" {
+            match self.self_binding() {
+                SelfBinding::Method { table, .. } => table,
+                _ => return,
+            }
+        } else {
+            name.to_string()
+        };
+
+        let exported = i > 0
+            && self.symbol(i - 1) == Some(Symbol::Return)
+            && !self.frames.iter().any(|f| matches!(f, Frame::Function(_)));
+        let line = self.tokens[i].start_position().line();
+        self.bindings
+            .entry(table)
+            .or_default()
+            .escape
+            .get_or_insert((exported, line));
+    }
+
+    /// Whether the value at `i` is only tested, measured or handed to a
+    /// function that doesn't read string keys
+    fn harmless_use(&self, i: usize) -> bool {
+        let previous = i.checked_sub(1).and_then(|p| self.symbol(p));
+        let next = self.symbol(i + 1);
+        if matches!(previous, Some(Symbol::Hash | Symbol::Not | Symbol::TwoEqual | Symbol::TildeEqual))
+            || matches!(next, Some(Symbol::TwoEqual | Symbol::TildeEqual))
+        {
+            return true;
+        }
+        if matches!(previous, Some(Symbol::If | Symbol::ElseIf | Symbol::While))
+            && matches!(next, Some(Symbol::Then | Symbol::Do))
+        {
+            return true;
+        }
+
+        // First argument of a key-blind function: `table.insert(t, v)`
+        if previous != Some(Symbol::LeftParen)
+            || !matches!(next, Some(Symbol::Comma | Symbol::RightParen))
+            || i < 2
+        {
+            return false;
+        }
+        let callee = i - 2;
+        let Some(function) = self.identifier(callee) else {
+            return false;
+        };
+        let qualified = match callee.checked_sub(2) {
+            Some(library) if self.symbol(callee - 1) == Some(Symbol::Dot) => {
+                match self.identifier(library) {
+                    Some(library) => format!("{}.{}", library, function),
+                    None => return false,
+                }
+            }
+            _ if matches!(
+                callee.checked_sub(1).and_then(|p| self.symbol(p)),
+                Some(Symbol::Dot | Symbol::Colon)
+            ) =>
+            {
+                return false
+            }
+            _ => function.to_string(),
+        };
+        KEY_BLIND.contains(&qualified.as_str())
+    }
+
+    /// What the key after the `.`, `:` or `[` at `separator` is looked up on
+    fn receiver(&self, separator: usize) -> Receiver {
+        let Some(last) = separator.checked_sub(1) else {
+            return Receiver::Other(String::new());
+        };
+        let start = self.chain_start(last);
+        match self.identifier(last) {
+            Some(name) if start == last => self.name_receiver(name),
+            _ => Receiver::Other(self.text(start, last)),
+        }
+    }
+
+    fn name_receiver(&self, name: &str) -> Receiver {
+        if name != "self" {
+            return Receiver::Name {
+                name: name.to_string(),
+                via_self: None,
+            };
+        }
+        match self.self_binding() {
+            SelfBinding::Method { table, method } => Receiver::Name {
+                name: table,
+                via_self: Some(method),
+            },
+            _ => Receiver::Other("self".to_string()),
+        }
+    }
+
+    /// What `self` means at the scan position
+    fn self_binding(&self) -> SelfBinding {
+        self.frames
+            .iter()
+            .rev()
+            .find_map(|frame| match frame {
+                Frame::Function(SelfBinding::Inherit) => None,
+                Frame::Function(binding) => Some(binding.clone()),
+                _ => None,
+            })
+            .unwrap_or(SelfBinding::Opaque)
+    }
+
+    /// First token of the prefix expression ending at `last` (`a.b[c]:d()`)
+    fn chain_start(&self, last: usize) -> usize {
+        let mut j = last;
+        loop {
+            match self.symbol(j) {
+                Some(Symbol::RightParen | Symbol::RightBracket) => {
+                    let Some(open) = self.partner[j] else {
+                        return j;
+                    };
+                    // A call or index continues the chain; `(expr)` starts it
+                    match open.checked_sub(1) {
+                        Some(p) if self.continues_chain(p) => j = p,
+                        _ => return open,
+                    }
+                }
+                _ if self.identifier(j).is_some() => {
+                    match j.checked_sub(1).and_then(|p| self.symbol(p)) {
+                        Some(Symbol::Dot | Symbol::Colon) if j >= 2 => j -= 2,
+                        _ => return j,
+                    }
+                }
+                _ => return j,
+            }
+        }
+    }
+
+    /// Whether a `(` or `[` after token `i` calls or indexes it
+    fn continues_chain(&self, i: usize) -> bool {
+        self.identifier(i).is_some()
+            || matches!(
+                self.symbol(i),
+                Some(Symbol::RightParen | Symbol::RightBracket)
+            )
+    }
+
+    /// Where the constructor opening at `i` goes
+    fn origin(&self, i: usize) -> Origin {
+        let statement = matches!(
+            self.frames.last(),
+            None | Some(Frame::Function(_)) | Some(Frame::Block)
+        );
+        match i.checked_sub(1).and_then(|p| self.symbol(p)) {
+            Some(Symbol::Equal) if statement && i >= 2 => {
+                let target = i - 2;
+                let plain = !matches!(
+                    target.checked_sub(1).and_then(|p| self.symbol(p)),
+                    Some(Symbol::Dot | Symbol::Colon | Symbol::Comma)
+                );
+                match self.identifier(target) {
+                    Some(name) if plain => Origin::Named(name.to_string()),
+                    _ => Origin::Unnamed,
+                }
+            }
+            Some(Symbol::Return) if !self.frames.iter().any(|f| matches!(f, Frame::Function(_))) => {
+                Origin::Returned
+            }
+            _ => Origin::Unnamed,
+        }
+    }
+
+    fn define(&mut self, name: &str, definition: Definition) {
+        if name == "self" {
+            self.declares_self = true;
+        }
+        let binding = self.bindings.entry(name.to_string()).or_default();
+        match definition {
+            Definition::Table => binding.tables += 1,
+            Definition::Other => binding.other = true,
+        }
+    }
+
+    fn push_site(&mut self, i: usize, kind: KeySiteKind, receiver: Receiver) {
+        let key = match kind {
+            KeySiteKind::Bracket => self.key_literal(i),
+            _ => self.identifier(i).map(str::to_string),
+        };
+        let Some(key) = key else {
+            return;
+        };
+        let position = self.tokens[i].start_position();
+        self.sites.push(RawSite {
+            site: KeySite {
+                key,
+                kind,
+                line: position.line(),
+                column: position.character(),
+            },
+            receiver,
+        });
+    }
+
+    /// Value of a single-line string literal without escapes at `i`
+    fn key_literal(&self, i: usize) -> Option<String> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::StringLiteral {
+                literal,
+                multi_line: None,
+                ..
+            } if !literal.contains('\\') => Some(literal.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Source text from token `start` to token `end`, inclusive
+    fn text(&self, start: usize, end: usize) -> String {
+        let from = self.tokens[start].start_position().bytes();
+        let to = self.tokens[end].end_position().bytes();
+        self.source[from..to].to_string()
+    }
+
+    fn symbol(&self, i: usize) -> Option<Symbol> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Symbol { symbol } => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn identifier(&self, i: usize) -> Option<&'a str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason<'k>(keys: &'k TableKeys, key: &str) -> Option<&'k KeepReason> {
+        keys.kept.iter().find(|kept| kept.key == key).map(|kept| &kept.reason)
+    }
+
+    #[test]
+    fn test_local_table_keys_are_rewritable() {
+        let source = r#"local config = {timeout = 5, ["retries"] = 3, __index = nil}
+config.timeout = config.timeout + 1
+print(config["retries"], #config)
+local part = workspace.Baseplate
+part.Anchored = true
+local shared = {count = 0}
+register(shared)
+local lookup = {mode = "fast"}
+print(lookup["mo" .. "de"], "mode")
+return {exported = true}
+"#;
+        let keys = TableKeyAnalyzer::new(&["workspace".to_string()]).analyze_source(source).unwrap();
+
+        assert_eq!(keys.keys(), BTreeSet::from(["retries", "timeout"]));
+        assert_eq!(keys.sites.iter().filter(|s| s.key == "timeout").count(), 3);
+        assert!(keys
+            .sites
+            .iter()
+            .any(|s| s.key == "retries" && s.kind == KeySiteKind::Bracket && s.line == 3));
+
+        assert_eq!(reason(&keys, "__index"), Some(&KeepReason::Metamethod));
+        assert_eq!(reason(&keys, "Baseplate"), Some(&KeepReason::ForeignTable("workspace".to_string())));
+        assert_eq!(reason(&keys, "Anchored"), Some(&KeepReason::ForeignTable("part".to_string())));
+        assert_eq!(reason(&keys, "count"), Some(&KeepReason::Escapes("shared".to_string())));
+        assert_eq!(reason(&keys, "mode"), Some(&KeepReason::DynamicLookup));
+        assert_eq!(reason(&keys, "exported"), Some(&KeepReason::Exported("{...}".to_string())));
+    }
+
+    #[test]
+    fn test_methods_on_local_tables() {
+        let source = r#"local Service = {}
+function Service:start()
+    self.running = true
+    table.insert(self, 1)
+end
+function Service.stop(self)
+    self.stopped = true
+end
+Service:start()
+local Module = {}
+function Module.helper() end
+return Module
+"#;
+        let keys = TableKeyAnalyzer::new(&[]).analyze_source(source).unwrap();
+
+        assert_eq!(keys.keys(), BTreeSet::from(["running", "start", "stop"]));
+        assert!(keys
+            .sites
+            .iter()
+            .any(|s| s.key == "start" && s.kind == KeySiteKind::Declaration { method: true }));
+        assert!(keys
+            .sites
+            .iter()
+            .any(|s| s.key == "start" && s.kind == KeySiteKind::Method { plain: true }));
+        assert_eq!(reason(&keys, "stopped"), Some(&KeepReason::ForeignTable("self".to_string())));
+        assert_eq!(reason(&keys, "helper"), Some(&KeepReason::Exported("Module".to_string())));
+    }
+}
@@ -1,16 +1,30 @@
 //! Analysis engine module - Code analysis and metadata extraction
 
+mod api_db;
 mod controlflow;
+mod dynamic;
+mod folding;
+mod keys;
+mod paths;
 mod roblox;
 mod scope;
 
-pub use controlflow::{ControlFlowAnalyzer, ControlFlowGraph, BasicBlock};
+pub use api_db::{ApiDatabase, API_DB_FILE};
+pub use controlflow::{
+    BasicBlock, BlockStatement, ControlFlowAnalyzer, ControlFlowGraph, Edge, EdgeKind, Terminator,
+};
+pub use dynamic::{DynamicGlobal, DynamicGlobalDetector};
+pub use folding::{ConstantFolder, FoldedConstant, Folds};
+pub use keys::{KeepReason, KeySite, KeySiteKind, KeptKey, TableKeyAnalyzer, TableKeys};
+pub use paths::{InstancePath, InstancePathDetector};
 pub use roblox::{RobloxApiDetector, RobloxApiType};
-pub use scope::{ScopeAnalyzer, Scope, Variable, VariableType};
+pub use scope::{ScopeAnalyzer, Scope, ScopeKind, Upvalue, Variable, VariableType};
 
 use crate::parser::ParseResult;
+use crate::progress::{no_progress, SharedProgress, Stage};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
 
 /// Complete analysis result
 #[derive(Debug)]
@@ -23,51 +37,274 @@ pub struct AnalysisResult {
     pub roblox_apis: Vec<RobloxApiType>,
     /// Variables that should be preserved (Roblox globals, etc.)
     pub preserved_identifiers: Vec<String>,
+    /// `_G`, `shared` and `getfenv` accesses that kept names from renaming
+    pub dynamic_globals: Vec<DynamicGlobal>,
+    /// Constant string expressions to encrypt as one literal
+    pub folded_strings: Vec<FoldedConstant>,
+    /// Constant arithmetic expressions to obfuscate as one number
+    pub folded_numbers: Vec<FoldedConstant>,
+    /// String arguments of `GetService`, `WaitForChild`, `require`, ... that
+    /// must keep their exact value
+    pub instance_paths: Vec<InstancePath>,
+}
+
+/// Tunable analysis passes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalysisOptions {
+    /// Keep Roblox globals, services and datatypes from being renamed
+    pub preserve_roblox_apis: bool,
+    /// Report remote and bindable class names found in string literals
+    pub detect_remote_patterns: bool,
+    /// Build the control flow graph (flattening needs it)
+    pub build_cfg: bool,
+    /// Reject scripts whose scopes nest deeper than this
+    pub max_scope_depth: usize,
+    /// Keep `_`-prefixed globals (`_Config = ...`, `function _Init()`) as a
+    /// script's exports to other scripts
+    pub treat_underscore_globals_as_exports: bool,
+    /// Fail instead of keeping names reached through `_G`, `shared` or
+    /// `getfenv`
+    pub deny_dynamic_globals: bool,
+    /// Evaluate constant `..` chains, `string.format` calls and arithmetic
+    /// so they are obfuscated as one literal
+    pub fold_constants: bool,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            preserve_roblox_apis: true,
+            detect_remote_patterns: true,
+            build_cfg: true,
+            // Luau's own parser gives up on nesting around 200 levels
+            max_scope_depth: 200,
+            treat_underscore_globals_as_exports: false,
+            deny_dynamic_globals: false,
+            fold_constants: true,
+        }
+    }
 }
 
 /// Main analyzer that coordinates all analysis passes
 pub struct Analyzer {
-    preserve_roblox_apis: bool,
+    options: AnalysisOptions,
+    api_db: ApiDatabase,
+    progress: SharedProgress,
 }
 
+/// Passes `analyze` reports progress over
+const ANALYSIS_PASSES: usize = 5;
+
 impl Analyzer {
-    pub fn new() -> Self {
+    pub fn new(options: AnalysisOptions) -> Self {
+        Self::with_api_database(options, ApiDatabase::bundled())
+    }
+
+    /// Create an analyzer that preserves identifiers from a specific API database
+    pub fn with_api_database(options: AnalysisOptions, api_db: ApiDatabase) -> Self {
         Self {
-            preserve_roblox_apis: true,
+            options,
+            api_db,
+            progress: no_progress(),
         }
     }
 
+    /// Report each finished pass to `progress`
+    pub fn with_progress(mut self, progress: SharedProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn options(&self) -> &AnalysisOptions {
+        &self.options
+    }
+
     /// Run all analysis passes on parsed code
     pub fn analyze(&self, parse_result: &ParseResult) -> Result<AnalysisResult> {
         // Detect Roblox API usage
-        let mut roblox_detector = RobloxApiDetector::new();
-        let roblox_apis = roblox_detector.detect(parse_result)?;
+        let mut roblox_detector = RobloxApiDetector::with_database(&self.api_db);
+        let mut roblox_apis = roblox_detector.detect(parse_result)?;
+        if !self.options.detect_remote_patterns {
+            roblox_apis.retain(|api| !matches!(api, RobloxApiType::Remote(_)));
+        }
 
         // Build preserved identifiers list
         let mut preserved_identifiers = Vec::new();
-        if self.preserve_roblox_apis {
+        if self.options.preserve_roblox_apis {
             preserved_identifiers.extend(roblox_detector.get_preserved_names());
         }
+        // The settings block is written readable, under its own name
+        if let Some(block) = &parse_result.config_block {
+            preserved_identifiers.push(block.name.clone());
+        }
+        let done =
+            |pass: usize| self.progress.items_processed(Stage::Analyze, pass, ANALYSIS_PASSES);
+        done(1);
 
         // Analyze scopes
         let scope_analyzer = ScopeAnalyzer::new();
         let scopes = scope_analyzer.analyze(parse_result)?;
+        self.check_scope_depth(&scopes)?;
+        done(2);
+
+        if self.options.treat_underscore_globals_as_exports {
+            preserved_identifiers.extend(
+                scope_analyzer
+                    .global_names(parse_result)?
+                    .into_iter()
+                    .filter(|name| name.len() > 1 && name.starts_with('_')),
+            );
+        }
+
+        // Names reached through the environment must keep their text
+        let renamable: BTreeSet<String> = scopes
+            .iter()
+            .flat_map(|scope| scope.variables.keys())
+            .filter(|name| !preserved_identifiers.contains(name))
+            .cloned()
+            .collect();
+        let detector = DynamicGlobalDetector::new();
+        let dynamic_globals = detector.resolve(
+            detector.detect(parse_result)?,
+            &renamable,
+            &scope_analyzer.global_names(parse_result)?,
+        );
+        if self.options.deny_dynamic_globals {
+            if let Some(access) = dynamic_globals.first() {
+                return Err(ObfuscatorError::AnalysisError(format!(
+                    "Dynamic global access denied: {}",
+                    access
+                ))
+                .into());
+            }
+        }
+        for access in &dynamic_globals {
+            preserved_identifiers.extend(access.kept.iter().cloned());
+        }
+        done(3);
+
+        let folds = if self.options.fold_constants {
+            ConstantFolder::new().fold(parse_result, &scopes)?
+        } else {
+            Folds::default()
+        };
+        let instance_paths = InstancePathDetector::new().detect(parse_result)?;
+        done(4);
 
         // Build control flow graph
-        let cf_analyzer = ControlFlowAnalyzer::new();
-        let control_flow = cf_analyzer.analyze(parse_result)?;
+        let control_flow = if self.options.build_cfg {
+            ControlFlowAnalyzer::new().analyze(parse_result)?
+        } else {
+            ControlFlowGraph {
+                blocks: HashMap::new(),
+                entry_block: 0,
+                exit_blocks: Vec::new(),
+            }
+        };
+        done(5);
 
         Ok(AnalysisResult {
             control_flow,
             scopes,
             roblox_apis,
             preserved_identifiers,
+            dynamic_globals,
+            folded_strings: folds.strings,
+            folded_numbers: folds.numbers,
+            instance_paths,
         })
     }
+
+    fn check_scope_depth(&self, scopes: &[Scope]) -> Result<()> {
+        // Scopes are created parent-first, so each depth is known by the time
+        // its children are reached
+        let mut depths = vec![0usize; scopes.len()];
+        for scope in scopes {
+            if let Some(parent) = scope.parent {
+                depths[scope.id] = depths[parent] + 1;
+            }
+            if depths[scope.id] > self.options.max_scope_depth {
+                return Err(ObfuscatorError::AnalysisError(format!(
+                    "Scopes nest deeper than {} levels",
+                    self.options.max_scope_depth
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for Analyzer {
     fn default() -> Self {
-        Self::new()
+        Self::new(AnalysisOptions::default())
+    }
+}
+
+/// Public name for `Analyzer`
+pub type AnalysisEngine = Analyzer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LuauParser;
+
+    #[test]
+    fn test_options_tune_passes() {
+        let source = "local remote = \"RemoteEvent\"\n\
+                      _Shared = 1\n\
+                      if _Shared then print(remote) end\n";
+        let parse_result = LuauParser::new().parse(source).unwrap();
+
+        let full = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        assert!(!full.control_flow.blocks.is_empty());
+        assert!(full.roblox_apis.contains(&RobloxApiType::Remote("RemoteEvent".to_string())));
+        assert!(!full.preserved_identifiers.contains(&"_Shared".to_string()));
+
+        let options = AnalysisOptions {
+            detect_remote_patterns: false,
+            build_cfg: false,
+            treat_underscore_globals_as_exports: true,
+            ..Default::default()
+        };
+        let tuned = Analyzer::new(options).analyze(&parse_result).unwrap();
+        assert!(tuned.control_flow.blocks.is_empty());
+        assert!(tuned.roblox_apis.is_empty());
+        assert!(tuned.preserved_identifiers.contains(&"_Shared".to_string()));
+    }
+
+    #[test]
+    fn test_dynamic_globals_keep_names() {
+        let source = "function Handler() end\n\
+                      local count = 1\n\
+                      _G.Handler = Handler\n\
+                      print(_G.Missing, count)\n";
+        let parse_result = LuauParser::new().parse(source).unwrap();
+
+        let analysis = Analyzer::default().analyze(&parse_result).unwrap();
+        assert_eq!(analysis.dynamic_globals.len(), 1);
+        assert_eq!(analysis.dynamic_globals[0].kept, vec!["Handler".to_string()]);
+        assert!(analysis.preserved_identifiers.contains(&"Handler".to_string()));
+        assert!(!analysis.preserved_identifiers.contains(&"count".to_string()));
+
+        let deny = AnalysisOptions {
+            deny_dynamic_globals: true,
+            ..Default::default()
+        };
+        let err = Analyzer::new(deny).analyze(&parse_result).unwrap_err();
+        assert!(err.to_string().contains("`_G.Handler`"));
+    }
+
+    #[test]
+    fn test_max_scope_depth() {
+        let source = "do\n    do\n        do\n            print(1)\n        end\n    end\nend\n";
+        let parse_result = LuauParser::new().parse(source).unwrap();
+
+        let shallow = AnalysisOptions {
+            max_scope_depth: 2,
+            ..Default::default()
+        };
+        assert!(Analyzer::new(shallow).analyze(&parse_result).is_err());
+        assert!(Analyzer::default().analyze(&parse_result).is_ok());
     }
 }
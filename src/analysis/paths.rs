@@ -0,0 +1,155 @@
+//! Instance path detection
+//!
+//! `game:GetService("Players")`, `folder:WaitForChild("Remotes")` and
+//! `require("./Shared")` look their argument up by name at runtime, so the
+//! string must come back exactly as written. Such a literal may still be
+//! encrypted, but only wrapped in one decrypt call: it is never split into
+//! fragments and never shares an entry with a literal that was.
+
+use crate::parser::{scan_types, string_value, ParseResult};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::Serialize;
+
+/// Methods whose first argument names an Instance
+const PATH_METHODS: &[&str] = &[
+    "GetService",
+    "FindService",
+    "WaitForChild",
+    "FindFirstChild",
+    "FindFirstAncestor",
+];
+
+/// A string literal looked up by name at runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstancePath {
+    /// `GetService`, `WaitForChild`, ..., or `require`
+    pub call: String,
+    /// String the literal evaluates to, escapes resolved
+    pub value: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Finds the string arguments of Instance lookups and `require`
+pub struct InstancePathDetector;
+
+impl InstancePathDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Detect Instance path arguments in parsed code
+    ///
+    /// Streaming parses keep no AST and report none.
+    pub fn detect(&self, parse_result: &ParseResult) -> Result<Vec<InstancePath>> {
+        match &parse_result.ast {
+            Some(ast) => self.detect_source(&ast.nodes().to_string()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Detect Instance path arguments in Luau source
+    pub fn detect_source(&self, source: &str) -> Result<Vec<InstancePath>> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        let annotations = scan_types(source)?.annotations;
+        let mut annotation = annotations.iter().peekable();
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .filter(|t| {
+                let at = t.start_position().bytes();
+                while annotation.next_if(|span| span.end <= at).is_some() {}
+                !annotation.peek().is_some_and(|span| span.contains(&at))
+            })
+            .collect();
+
+        Ok((0..significant.len())
+            .filter_map(|i| path_argument(&significant, i))
+            .collect())
+    }
+}
+
+impl Default for InstancePathDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The path argument if token `i` is the first argument of a lookup
+///
+/// Both `x:WaitForChild("A")` and the call sugar `x:WaitForChild "A"` count.
+fn path_argument(tokens: &[&Token], i: usize) -> Option<InstancePath> {
+    let value = string_value(tokens[i])?;
+    let callee = match i.checked_sub(1).map(|j| symbol(tokens, j)) {
+        Some(Some(Symbol::LeftParen)) => i.checked_sub(2)?,
+        _ => i.checked_sub(1)?,
+    };
+    let name = identifier(tokens, callee)?;
+    let before = callee.checked_sub(1).and_then(|j| symbol(tokens, j));
+    let is_call = if PATH_METHODS.contains(&name) {
+        before == Some(Symbol::Colon)
+    } else {
+        // `require` itself, not a field or method of that name
+        name == "require" && !matches!(before, Some(Symbol::Dot) | Some(Symbol::Colon))
+    };
+    if !is_call {
+        return None;
+    }
+
+    let position = tokens[i].start_position();
+    Some(InstancePath {
+        call: name.to_string(),
+        value,
+        line: position.line(),
+        column: position.character(),
+    })
+}
+
+fn symbol(tokens: &[&Token], i: usize) -> Option<Symbol> {
+    match tokens.get(i)?.token_type() {
+        TokenType::Symbol { symbol } => Some(*symbol),
+        _ => None,
+    }
+}
+
+fn identifier<'a>(tokens: &[&'a Token], i: usize) -> Option<&'a str> {
+    match tokens.get(i)?.token_type() {
+        TokenType::Identifier { identifier } => Some(identifier.as_str()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_lookup_arguments() {
+        let source = r#"local Players = game:GetService("Players")
+local remotes = game.ReplicatedStorage:WaitForChild "Remotes"
+local shared = require("./Shared")
+local tab = "Tab\tName"
+local pane = gui:FindFirstChild("Pane\\Left", true)
+print(obj.WaitForChild("x"), config:Get("Players"), lib.require("y"))
+"#;
+        let paths = InstancePathDetector::new().detect_source(source).unwrap();
+        let summary: Vec<(&str, &str, usize)> = paths
+            .iter()
+            .map(|p| (p.call.as_str(), p.value.as_str(), p.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("GetService", "Players", 1),
+                ("WaitForChild", "Remotes", 2),
+                ("require", "./Shared", 3),
+                ("FindFirstChild", "Pane\\Left", 5),
+            ]
+        );
+        assert_eq!((paths[0].line, paths[0].column), (1, 33));
+    }
+}
@@ -1,5 +1,6 @@
 //! Roblox API detection and preservation
 
+use super::api_db::ApiDatabase;
 use crate::parser::ParseResult;
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
@@ -18,88 +19,49 @@ pub enum RobloxApiType {
     Remote(String),
 }
 
+impl RobloxApiType {
+    /// `global`, `service`, `datatype` or `remote`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RobloxApiType::Global(_) => "global",
+            RobloxApiType::Service(_) => "service",
+            RobloxApiType::Datatype(_) => "datatype",
+            RobloxApiType::Remote(_) => "remote",
+        }
+    }
+
+    /// Name of the global, service, datatype or remote class
+    pub fn name(&self) -> &str {
+        match self {
+            RobloxApiType::Global(name)
+            | RobloxApiType::Service(name)
+            | RobloxApiType::Datatype(name)
+            | RobloxApiType::Remote(name) => name,
+        }
+    }
+}
+
 /// Detects and catalogs Roblox API usage
 pub struct RobloxApiDetector {
     detected_apis: Vec<RobloxApiType>,
     preserved_names: HashSet<String>,
+    services: HashSet<String>,
+    remotes: HashSet<String>,
 }
 
 impl RobloxApiDetector {
+    /// Create a detector backed by the bundled API database
     pub fn new() -> Self {
-        let mut preserved_names = HashSet::new();
-
-        // Add Roblox global objects
-        preserved_names.extend([
-            "game".to_string(),
-            "workspace".to_string(),
-            "script".to_string(),
-            "plugin".to_string(),
-            "shared".to_string(),
-            "_G".to_string(),
-        ]);
-
-        // Add common Roblox services
-        preserved_names.extend([
-            "Players".to_string(),
-            "ReplicatedStorage".to_string(),
-            "ServerStorage".to_string(),
-            "ServerScriptService".to_string(),
-            "StarterPlayer".to_string(),
-            "StarterPack".to_string(),
-            "StarterGui".to_string(),
-            "Lighting".to_string(),
-            "MaterialService".to_string(),
-            "SoundService".to_string(),
-            "Chat".to_string(),
-            "Teams".to_string(),
-            "BadgeService".to_string(),
-            "HttpService".to_string(),
-            "RunService".to_string(),
-            "TweenService".to_string(),
-            "UserInputService".to_string(),
-            "ContextActionService".to_string(),
-            "CollectionService".to_string(),
-            "DataStoreService".to_string(),
-            "MarketplaceService".to_string(),
-            "PathfindingService".to_string(),
-            "TextService".to_string(),
-            "LocalizationService".to_string(),
-        ]);
-
-        // Add Roblox datatypes
-        preserved_names.extend([
-            "Vector3".to_string(),
-            "Vector2".to_string(),
-            "CFrame".to_string(),
-            "UDim".to_string(),
-            "UDim2".to_string(),
-            "Color3".to_string(),
-            "BrickColor".to_string(),
-            "Instance".to_string(),
-            "Enum".to_string(),
-            "Ray".to_string(),
-            "Region3".to_string(),
-            "NumberSequence".to_string(),
-            "ColorSequence".to_string(),
-            "NumberRange".to_string(),
-            "Rect".to_string(),
-            "Faces".to_string(),
-            "Axes".to_string(),
-            "TweenInfo".to_string(),
-            "Random".to_string(),
-        ]);
-
-        // Add Remote types
-        preserved_names.extend([
-            "RemoteEvent".to_string(),
-            "RemoteFunction".to_string(),
-            "BindableEvent".to_string(),
-            "BindableFunction".to_string(),
-        ]);
+        Self::with_database(&ApiDatabase::bundled())
+    }
 
+    /// Create a detector backed by a specific (e.g. refreshed) API database
+    pub fn with_database(db: &ApiDatabase) -> Self {
         Self {
             detected_apis: Vec::new(),
-            preserved_names,
+            preserved_names: db.preserved_names(),
+            services: db.services.iter().cloned().collect(),
+            remotes: db.remotes.iter().cloned().collect(),
         }
     }
 
@@ -127,32 +89,12 @@ impl RobloxApiDetector {
 
     /// Check if a name is a Roblox service
     fn is_service_name(&self, name: &str) -> bool {
-        matches!(
-            name,
-            "Players"
-                | "ReplicatedStorage"
-                | "ServerStorage"
-                | "ServerScriptService"
-                | "Lighting"
-                | "HttpService"
-                | "RunService"
-                | "TweenService"
-                | "UserInputService"
-                | "ContextActionService"
-                | "CollectionService"
-                | "DataStoreService"
-                | "MarketplaceService"
-                | "PathfindingService"
-                | "TextService"
-        )
+        self.services.contains(name)
     }
 
     /// Check if a name is a remote type
     fn is_remote_type(&self, name: &str) -> bool {
-        matches!(
-            name,
-            "RemoteEvent" | "RemoteFunction" | "BindableEvent" | "BindableFunction"
-        )
+        self.remotes.contains(name)
     }
 
     /// Check if an identifier should be preserved
@@ -195,6 +137,16 @@ mod tests {
         assert!(detector.should_preserve("Color3"));
     }
 
+    #[test]
+    fn test_refreshed_database_extends_services() {
+        let mut db = ApiDatabase::bundled();
+        db.services.push("BrandNewService".to_string());
+
+        let detector = RobloxApiDetector::with_database(&db);
+        assert!(detector.should_preserve("BrandNewService"));
+        assert!(detector.is_service_name("BrandNewService"));
+    }
+
     #[test]
     fn test_user_identifiers_not_preserved() {
         let detector = RobloxApiDetector::new();
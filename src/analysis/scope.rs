@@ -1,9 +1,14 @@
 //! Scope analysis and variable tracking
+//!
+//! Walks the token stream to build the scope tree: function and block
+//! scopes, the locals each one declares, and capture edges where a nested
+//! closure refers to a local of an enclosing function (an upvalue).
 
-use crate::parser::{ParseResult, FunctionInfo};
+use crate::parser::{scan_types, BlockNesting, FunctionInfo, ParseResult};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
-use std::collections::HashMap;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use std::collections::{BTreeSet, HashMap};
 
 /// Type of variable
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -21,6 +26,29 @@ pub struct Variable {
     pub var_type: VariableType,
     pub line: usize,
     pub can_rename: bool,
+    /// Closure scopes that reference this variable as an upvalue
+    pub captured_by: Vec<usize>,
+}
+
+/// What introduced a scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// The main chunk
+    Global,
+    /// A function body; the boundary of a closure environment
+    Function,
+    /// `do`, loop and `if` bodies
+    Block,
+}
+
+/// A variable from an enclosing function captured by a closure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upvalue {
+    pub name: String,
+    /// Scope declaring the captured variable
+    pub declared_in: usize,
+    /// Line of the first capturing reference
+    pub line: usize,
 }
 
 /// Scope information
@@ -28,8 +56,24 @@ pub struct Variable {
 pub struct Scope {
     pub id: usize,
     pub parent: Option<usize>,
+    pub kind: ScopeKind,
     pub variables: HashMap<String, Variable>,
     pub children: Vec<usize>,
+    /// Closure environment: outer locals this function captures
+    pub upvalues: Vec<Upvalue>,
+}
+
+impl Scope {
+    /// Variables declared here that nested closures capture, sorted by name
+    pub fn captured_variables(&self) -> Vec<&Variable> {
+        let mut captured: Vec<&Variable> = self
+            .variables
+            .values()
+            .filter(|v| !v.captured_by.is_empty())
+            .collect();
+        captured.sort_by(|a, b| a.name.cmp(&b.name));
+        captured
+    }
 }
 
 /// Analyzes variable scopes
@@ -37,6 +81,8 @@ pub struct ScopeAnalyzer {
     scopes: Vec<Scope>,
     current_scope: usize,
     next_scope_id: usize,
+    /// Names used without a local declaration in scope
+    globals: BTreeSet<String>,
 }
 
 impl ScopeAnalyzer {
@@ -45,32 +91,101 @@ impl ScopeAnalyzer {
         let global_scope = Scope {
             id: 0,
             parent: None,
+            kind: ScopeKind::Global,
             variables: HashMap::new(),
             children: Vec::new(),
+            upvalues: Vec::new(),
         };
 
         Self {
             scopes: vec![global_scope],
             current_scope: 0,
             next_scope_id: 1,
+            globals: BTreeSet::new(),
         }
     }
 
     /// Analyze scopes in parsed code
+    ///
+    /// Streaming parses keep no AST, so only function signatures are
+    /// available and no capture edges are recorded.
     pub fn analyze(&self, parse_result: &ParseResult) -> Result<Vec<Scope>> {
-        let mut analyzer = Self::new();
+        match &parse_result.ast {
+            Some(ast) => self.analyze_source(&ast.nodes().to_string()),
+            None => {
+                let mut analyzer = Self::new();
 
-        // Process all functions to create scope hierarchy
-        for func in &parse_result.functions {
-            analyzer.process_function(func)?;
+                // Process all functions to create scope hierarchy
+                for func in &parse_result.functions {
+                    analyzer.process_function(func)?;
+                }
+
+                Ok(analyzer.scopes)
+            }
         }
+    }
+
+    /// Analyze scopes, locals and upvalues directly from Luau source
+    pub fn analyze_source(&self, source: &str) -> Result<Vec<Scope>> {
+        Ok(self.walk(source)?.scopes)
+    }
+
+    /// Globals the script reads, assigns or declares (`function name()`)
+    ///
+    /// Empty for streaming parses, which keep no AST to walk.
+    pub fn global_names(&self, parse_result: &ParseResult) -> Result<BTreeSet<String>> {
+        match &parse_result.ast {
+            Some(ast) => self.global_names_in_source(&ast.nodes().to_string()),
+            None => Ok(BTreeSet::new()),
+        }
+    }
 
-        Ok(analyzer.scopes)
+    /// Globals of Luau source (see `global_names`)
+    pub fn global_names_in_source(&self, source: &str) -> Result<BTreeSet<String>> {
+        Ok(self.walk(source)?.globals)
+    }
+
+    fn walk(&self, source: &str) -> Result<ScopeAnalyzer> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        // Type annotations name types, not variables
+        let annotations = scan_types(source)?.annotations;
+        let mut annotation = annotations.iter().peekable();
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .filter(|t| {
+                let at = t.start_position().bytes();
+                while annotation.next_if(|span| span.end <= at).is_some() {}
+                !annotation.peek().is_some_and(|span| span.contains(&at))
+            })
+            .collect();
+
+        let mut nesting = BlockNesting::new();
+        let expression_at = significant
+            .iter()
+            .map(|token| {
+                let expression = nesting.in_expression();
+                nesting.advance(token);
+                expression
+            })
+            .collect();
+
+        let mut walker = ScopeWalker {
+            analyzer: Self::new(),
+            tokens: &significant,
+            expression_at,
+            openers: Vec::new(),
+            brackets: Vec::new(),
+        };
+        walker.run();
+        Ok(walker.analyzer)
     }
 
     fn process_function(&mut self, func: &FunctionInfo) -> Result<()> {
         // Create new scope for function
-        let scope_id = self.enter_scope();
+        self.enter_scope(ScopeKind::Function);
 
         // Add parameters as local variables
         for param in &func.parameters {
@@ -79,6 +194,7 @@ impl ScopeAnalyzer {
                 var_type: VariableType::Parameter,
                 line: func.line,
                 can_rename: true,
+                captured_by: Vec::new(),
             })?;
         }
 
@@ -92,6 +208,7 @@ impl ScopeAnalyzer {
                     var_type: VariableType::Function,
                     line: func.line,
                     can_rename: !func.is_local, // Local functions can be renamed
+                    captured_by: Vec::new(),
                 },
             );
         }
@@ -100,12 +217,14 @@ impl ScopeAnalyzer {
         Ok(())
     }
 
-    fn enter_scope(&mut self) -> usize {
+    fn enter_scope(&mut self, kind: ScopeKind) -> usize {
         let new_scope = Scope {
             id: self.next_scope_id,
             parent: Some(self.current_scope),
+            kind,
             variables: HashMap::new(),
             children: Vec::new(),
+            upvalues: Vec::new(),
         };
 
         let new_id = self.next_scope_id;
@@ -131,6 +250,51 @@ impl ScopeAnalyzer {
         Ok(())
     }
 
+    /// Resolve a reference from the current scope, recording capture edges
+    ///
+    /// Every function scope crossed on the way to the declaration captures
+    /// the variable.
+    fn reference(&mut self, name: &str, line: usize) {
+        let mut crossed = Vec::new();
+        let mut scope_id = self.current_scope;
+
+        loop {
+            if self.scopes[scope_id].variables.contains_key(name) {
+                break;
+            }
+            if self.scopes[scope_id].kind == ScopeKind::Function {
+                crossed.push(scope_id);
+            }
+            match self.scopes[scope_id].parent {
+                Some(parent) => scope_id = parent,
+                // Global access
+                None => {
+                    self.globals.insert(name.to_string());
+                    return;
+                }
+            }
+        }
+
+        for closure in crossed {
+            let upvalues = &mut self.scopes[closure].upvalues;
+            if !upvalues
+                .iter()
+                .any(|u| u.name == name && u.declared_in == scope_id)
+            {
+                upvalues.push(Upvalue {
+                    name: name.to_string(),
+                    declared_in: scope_id,
+                    line,
+                });
+            }
+
+            let variable = self.scopes[scope_id].variables.get_mut(name).unwrap();
+            if !variable.captured_by.contains(&closure) {
+                variable.captured_by.push(closure);
+            }
+        }
+    }
+
     /// Find a variable in the scope hierarchy
     pub fn find_variable(&self, name: &str, scope_id: usize) -> Option<&Variable> {
         let scope = &self.scopes[scope_id];
@@ -155,6 +319,252 @@ impl Default for ScopeAnalyzer {
     }
 }
 
+/// Construct waiting for its closing keyword
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opener {
+    /// `function`, `do`, loop or `then`/`else` body; closed by `end`
+    Block,
+    /// Statement `if` before its first `then`
+    If,
+    /// `if` used as an expression; has no `end`
+    IfExpression,
+    /// `for` header, whose `do` doesn't open another scope
+    For,
+    /// `repeat` body; closed by `until`
+    Repeat,
+}
+
+/// Token-level walk building the scope tree
+struct ScopeWalker<'a> {
+    analyzer: ScopeAnalyzer,
+    tokens: &'a [&'a Token],
+    /// Whether each token is where an expression is expected, so an `if`
+    /// there starts an if-expression
+    expression_at: Vec<bool>,
+    openers: Vec<Opener>,
+    /// Open `(`, `{` and `[`, to tell table keys from variable references
+    brackets: Vec<Symbol>,
+}
+
+impl<'a> ScopeWalker<'a> {
+    fn run(&mut self) {
+        let mut i = 0;
+
+        while i < self.tokens.len() {
+            i = match self.symbol(i) {
+                Some(Symbol::Local) if self.symbol(i + 1) == Some(Symbol::Function) => {
+                    if let Some(name) = self.identifier(i + 2) {
+                        self.declare(name, VariableType::Function, i + 2);
+                    }
+                    self.function_body(i + 3, false)
+                }
+                Some(Symbol::Local) => self.local_names(i + 1),
+                Some(Symbol::Function) => self.function(i + 1),
+                Some(Symbol::For) => {
+                    self.analyzer.enter_scope(ScopeKind::Block);
+                    self.openers.push(Opener::For);
+                    self.local_names(i + 1)
+                }
+                Some(Symbol::Do) => {
+                    match self.openers.last_mut() {
+                        Some(opener @ Opener::For) => *opener = Opener::Block,
+                        _ => self.open_block(),
+                    }
+                    i + 1
+                }
+                Some(Symbol::Repeat) => {
+                    self.analyzer.enter_scope(ScopeKind::Block);
+                    self.openers.push(Opener::Repeat);
+                    i + 1
+                }
+                Some(Symbol::If) => {
+                    let opener = if self.expression_at[i] {
+                        Opener::IfExpression
+                    } else {
+                        Opener::If
+                    };
+                    self.openers.push(opener);
+                    i + 1
+                }
+                Some(Symbol::Then) => {
+                    if self.openers.last() == Some(&Opener::If) {
+                        self.openers.pop();
+                        self.open_block();
+                    }
+                    i + 1
+                }
+                Some(Symbol::ElseIf) => {
+                    if self.openers.last() == Some(&Opener::Block) {
+                        self.close();
+                        self.openers.push(Opener::If);
+                    }
+                    i + 1
+                }
+                Some(Symbol::Else) => match self.openers.last() {
+                    // `else if` continues the same if-expression
+                    Some(Opener::IfExpression) if self.symbol(i + 1) == Some(Symbol::If) => i + 2,
+                    Some(Opener::IfExpression) => {
+                        self.openers.pop();
+                        i + 1
+                    }
+                    Some(Opener::Block) => {
+                        self.close();
+                        self.open_block();
+                        i + 1
+                    }
+                    _ => i + 1,
+                },
+                Some(Symbol::End) | Some(Symbol::Until) => {
+                    self.close();
+                    i + 1
+                }
+                Some(open @ (Symbol::LeftParen | Symbol::LeftBrace | Symbol::LeftBracket)) => {
+                    self.brackets.push(open);
+                    i + 1
+                }
+                Some(Symbol::RightParen | Symbol::RightBrace | Symbol::RightBracket) => {
+                    self.brackets.pop();
+                    i + 1
+                }
+                _ => {
+                    if let Some(name) = self.identifier(i) {
+                        if self.is_reference(i) {
+                            let line = self.tokens[i].start_position().line();
+                            self.analyzer.reference(name, line);
+                        }
+                    }
+                    i + 1
+                }
+            };
+        }
+    }
+
+    /// `function name.path:method(...)` statement or `function(...)` expression
+    fn function(&mut self, i: usize) -> usize {
+        let Some(name) = self.identifier(i) else {
+            return self.function_body(i, false);
+        };
+
+        let line = self.tokens[i].start_position().line();
+        let mut j = i + 1;
+        let mut method = false;
+        while matches!(self.symbol(j), Some(Symbol::Dot) | Some(Symbol::Colon))
+            && self.identifier(j + 1).is_some()
+        {
+            method = self.symbol(j) == Some(Symbol::Colon);
+            j += 2;
+        }
+
+        if self.analyzer.find_variable(name, self.analyzer.current_scope).is_some() {
+            self.analyzer.reference(name, line);
+        } else {
+            // `function name()` declares a global; `function name.field()`
+            // assigns into one
+            self.analyzer.globals.insert(name.to_string());
+            if j == i + 1 {
+                self.analyzer.scopes[0].variables.insert(
+                    name.to_string(),
+                    Variable {
+                        name: name.to_string(),
+                        var_type: VariableType::Function,
+                        line,
+                        can_rename: false,
+                        captured_by: Vec::new(),
+                    },
+                );
+            }
+        }
+
+        self.function_body(j, method)
+    }
+
+    /// Parameter list at `i` and the body scope; returns the index after `)`
+    fn function_body(&mut self, i: usize, method: bool) -> usize {
+        self.analyzer.enter_scope(ScopeKind::Function);
+        self.openers.push(Opener::Block);
+        if method {
+            self.declare("self", VariableType::Parameter, i);
+        }
+
+        if self.symbol(i) != Some(Symbol::LeftParen) {
+            return i;
+        }
+        let mut j = i + 1;
+        while j < self.tokens.len() && self.symbol(j) != Some(Symbol::RightParen) {
+            if let Some(name) = self.identifier(j) {
+                self.declare(name, VariableType::Parameter, j);
+            }
+            j += 1;
+        }
+        j + 1
+    }
+
+    /// `a, b <const>, c` after `local` or `for`; returns the index after the names
+    fn local_names(&mut self, i: usize) -> usize {
+        let mut j = i;
+        while let Some(name) = self.identifier(j) {
+            self.declare(name, VariableType::Local, j);
+            j += 1;
+            // Luau attributes: `<const>`, `<close>`
+            if self.symbol(j) == Some(Symbol::LessThan) {
+                j += 3;
+            }
+            if self.symbol(j) != Some(Symbol::Comma) {
+                break;
+            }
+            j += 1;
+        }
+        j
+    }
+
+    fn declare(&mut self, name: &str, var_type: VariableType, i: usize) {
+        let line = self.tokens[i].start_position().line();
+        let _ = self.analyzer.add_variable(Variable {
+            name: name.to_string(),
+            var_type,
+            line,
+            can_rename: true,
+            captured_by: Vec::new(),
+        });
+    }
+
+    fn open_block(&mut self) {
+        self.analyzer.enter_scope(ScopeKind::Block);
+        self.openers.push(Opener::Block);
+    }
+
+    fn close(&mut self) {
+        // A stray `end` in malformed input leaves the stack alone
+        if self.openers.pop().is_some() {
+            self.analyzer.exit_scope();
+        }
+    }
+
+    /// Whether the identifier at `i` names a variable (not a field or table key)
+    fn is_reference(&self, i: usize) -> bool {
+        if i > 0 && matches!(self.symbol(i - 1), Some(Symbol::Dot) | Some(Symbol::Colon)) {
+            return false;
+        }
+        let table_key = self.brackets.last() == Some(&Symbol::LeftBrace)
+            && self.symbol(i + 1) == Some(Symbol::Equal);
+        !table_key
+    }
+
+    fn symbol(&self, i: usize) -> Option<Symbol> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Symbol { symbol } => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn identifier(&self, i: usize) -> Option<&'a str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,18 +580,18 @@ mod tests {
     #[test]
     fn test_enter_exit_scope() {
         let mut analyzer = ScopeAnalyzer::new();
-        
-        let scope1 = analyzer.enter_scope();
+
+        let scope1 = analyzer.enter_scope(ScopeKind::Block);
         assert_eq!(scope1, 1);
         assert_eq!(analyzer.current_scope, 1);
-        
-        let scope2 = analyzer.enter_scope();
+
+        let scope2 = analyzer.enter_scope(ScopeKind::Block);
         assert_eq!(scope2, 2);
         assert_eq!(analyzer.current_scope, 2);
-        
+
         analyzer.exit_scope();
         assert_eq!(analyzer.current_scope, 1);
-        
+
         analyzer.exit_scope();
         assert_eq!(analyzer.current_scope, 0);
     }
@@ -189,15 +599,99 @@ mod tests {
     #[test]
     fn test_add_variable() {
         let mut analyzer = ScopeAnalyzer::new();
-        
+
         let var = Variable {
             name: "test".to_string(),
             var_type: VariableType::Local,
             line: 1,
             can_rename: true,
+            captured_by: Vec::new(),
         };
-        
+
         analyzer.add_variable(var).unwrap();
         assert!(analyzer.scopes[0].variables.contains_key("test"));
     }
+
+    #[test]
+    fn test_closures_capture_enclosing_locals() {
+        let source = "local count = 0\n\
+                      local function counter(step: number)\n\
+                          local unused = 1\n\
+                          return function()\n\
+                              count = count + step\n\
+                              return { count = count }\n\
+                          end\n\
+                      end\n\
+                      for i = 1, 3 do\n\
+                          task.delay(i, function() print(i) end)\n\
+                      end\n";
+        let scopes = ScopeAnalyzer::new().analyze_source(source).unwrap();
+
+        let global = &scopes[0];
+        let captured: Vec<&str> = global.captured_variables().iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(captured, vec!["count"]);
+
+        // The inner closure's environment holds `count` and `step`
+        let inner = scopes
+            .iter()
+            .find(|s| s.kind == ScopeKind::Function && s.upvalues.len() == 2)
+            .unwrap();
+        let mut names: Vec<&str> = inner.upvalues.iter().map(|u| u.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["count", "step"]);
+
+        let counter = &scopes[inner.parent.unwrap()];
+        let captured: Vec<&str> = counter.captured_variables().iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(captured, vec!["step"]);
+
+        // Loop variables are per-iteration block locals
+        let loop_scope = scopes
+            .iter()
+            .find(|s| s.kind == ScopeKind::Block && s.variables.contains_key("i"))
+            .unwrap();
+        assert_eq!(loop_scope.captured_variables()[0].line, 9);
+    }
+
+    #[test]
+    fn test_global_names() {
+        let source = "local cache = {}\n\
+                      function _Export(x)\n\
+                          _Counter = (_Counter or 0) + x\n\
+                          return cache[x]\n\
+                      end\n\
+                      function Module.helper() end\n";
+        let parse_result = crate::parser::LuauParser::new().parse(source).unwrap();
+        let globals = ScopeAnalyzer::new().global_names(&parse_result).unwrap();
+
+        let names: Vec<&str> = globals.iter().map(String::as_str).collect();
+        assert_eq!(names, vec!["Module", "_Counter", "_Export"]);
+    }
+
+    #[test]
+    fn test_if_expression_does_not_open_scope() {
+        let source = "local a = if x then 1 else 2\n\
+                      local function f()\n\
+                          return a\n\
+                      end\n";
+        let scopes = ScopeAnalyzer::new().analyze_source(source).unwrap();
+
+        assert_eq!(scopes[0].captured_variables().len(), 1);
+        assert_eq!(scopes[1].parent, Some(0));
+    }
+
+    #[test]
+    fn test_if_expression_in_loop_condition() {
+        let source = "while if ready then a else b do\n\
+                          local n = 1\n\
+                      end\n\
+                      local function f()\n\
+                          return n\n\
+                      end\n";
+        let scopes = ScopeAnalyzer::new().analyze_source(source).unwrap();
+
+        // Only the loop body and `f` open scopes
+        assert_eq!(scopes.len(), 3);
+        assert!(scopes[1].variables.contains_key("n"));
+        assert_eq!(scopes[2].parent, Some(0));
+    }
 }
@@ -104,6 +104,123 @@ impl ApiClient {
         })
     }
 
+    /// Seat limits and live sessions of a license, or of every license of a
+    /// script
+    pub fn license_seats(&self, request: LicenseSeatsRequest) -> Result<LicenseSeatsResponse> {
+        let url = format!("{}/api/v1/license-seats", self.base_url);
+        
+        debug!("Fetching license seats: {}", url);
+        
+        self.retry_request(|| {
+            let response = self.client
+                .post(&url)
+                .json(&request)
+                .send()
+                .context("Failed to send license seats request")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(ObfuscatorError::ApiError(
+                    format!("API returned error {}: {}", status, error_text)
+                ).into());
+            }
+
+            let result: LicenseSeatsResponse = response
+                .json()
+                .context("Failed to parse license seats response")?;
+
+            Ok(result)
+        })
+    }
+
+    /// Revoke a license key
+    pub fn revoke_license(&self, request: RevokeLicenseRequest) -> Result<RevokeLicenseResponse> {
+        let url = format!("{}/api/v1/revoke-license", self.base_url);
+        
+        debug!("Revoking license with API: {}", url);
+        
+        self.retry_request(|| {
+            let response = self.client
+                .post(&url)
+                .json(&request)
+                .send()
+                .context("Failed to send revoke license request")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(ObfuscatorError::ApiError(
+                    format!("API returned error {}: {}", status, error_text)
+                ).into());
+            }
+
+            let result: RevokeLicenseResponse = response
+                .json()
+                .context("Failed to parse revoke license response")?;
+
+            Ok(result)
+        })
+    }
+
+    /// Fetch a script's signed revocation list
+    pub fn revocation_list(&self, request: RevocationListRequest) -> Result<RevocationListResponse> {
+        let url = format!("{}/api/v1/revocation-list", self.base_url);
+        
+        debug!("Fetching revocation list: {}", url);
+        
+        self.retry_request(|| {
+            let response = self.client
+                .post(&url)
+                .json(&request)
+                .send()
+                .context("Failed to send revocation list request")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(ObfuscatorError::ApiError(
+                    format!("API returned error {}: {}", status, error_text)
+                ).into());
+            }
+
+            let result: RevocationListResponse = response
+                .json()
+                .context("Failed to parse revocation list response")?;
+
+            Ok(result)
+        })
+    }
+
+    /// Publish a signed payload version to a script's update channel
+    pub fn publish_payload(&self, request: PublishPayloadRequest) -> Result<PublishPayloadResponse> {
+        let url = format!("{}/api/v1/publish-payload", self.base_url);
+        
+        debug!("Publishing payload version {}: {}", request.version, url);
+        
+        self.retry_request(|| {
+            let response = self.client
+                .post(&url)
+                .json(&request)
+                .send()
+                .context("Failed to send publish payload request")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(ObfuscatorError::ApiError(
+                    format!("API returned error {}: {}", status, error_text)
+                ).into());
+            }
+
+            let result: PublishPayloadResponse = response
+                .json()
+                .context("Failed to parse publish payload response")?;
+
+            Ok(result)
+        })
+    }
+
     /// Track an obfuscation event
     pub fn track_obfuscation(&self, request: TrackObfuscationRequest) -> Result<TrackObfuscationResponse> {
         let url = format!("{}/api/v1/track-obfuscation", self.base_url);
@@ -132,6 +249,44 @@ impl ApiClient {
         Ok(result)
     }
 
+    /// Upload a batch of queued obfuscation events
+    ///
+    /// Not retried here: a failed batch stays in the queue for a later run.
+    pub fn track_obfuscation_batch(
+        &self,
+        events: &[TrackObfuscationRequest],
+    ) -> Result<TrackObfuscationBatchResponse> {
+        let url = format!("{}/api/v1/track-obfuscation/batch", self.base_url);
+        
+        debug!("Uploading {} obfuscation event(s): {}", events.len(), url);
+        
+        let request = TrackObfuscationBatchRequest {
+            events: events.to_vec(),
+        };
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .context("Failed to send obfuscation events")?;
+
+        if !response.status().is_success() {
+            return Err(ObfuscatorError::ApiError(
+                format!("API returned error {} for obfuscation events", response.status())
+            ).into());
+        }
+
+        let result: TrackObfuscationBatchResponse = response
+            .json()
+            .context("Failed to parse obfuscation events response")?;
+        if !result.success {
+            return Err(ObfuscatorError::ApiError(
+                "API did not record the obfuscation events".to_string()
+            ).into());
+        }
+
+        Ok(result)
+    }
+
     /// Retry a request with exponential backoff
     fn retry_request<T, F>(&self, mut f: F) -> Result<T>
     where
@@ -0,0 +1,303 @@
+//! Stored developer API key
+//!
+//! `auth login` keeps the key in the OS keychain when there is one (the
+//! `keychain` feature) and otherwise in `credentials.enc` in the config
+//! directory, encrypted under a random key kept next to it and readable by
+//! the owner only. Like the validation token cache, the file fallback keeps
+//! the key out of shell history and plain sight; it doesn't protect against
+//! someone who can read the config directory.
+
+use crate::crypto::{CryptoEngine, EncryptedData};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use log::debug;
+use rand::Rng;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Environment variable that overrides the stored key (for CI)
+pub const API_KEY_ENV: &str = "LUAU_OBFUSCATOR_API_KEY";
+
+/// Keychain service name the key is stored under
+#[cfg(feature = "keychain")]
+const KEYCHAIN_SERVICE: &str = "luau-obfuscator";
+
+/// Keychain account name the key is stored under
+#[cfg(feature = "keychain")]
+const KEYCHAIN_ACCOUNT: &str = "api-key";
+
+const KEY_FILE: &str = "credentials.key";
+const DATA_FILE: &str = "credentials.enc";
+
+/// Where an API key was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialSource {
+    /// [`API_KEY_ENV`]
+    Environment,
+    Keychain,
+    /// The encrypted file fallback
+    File,
+    /// `api_key` in config.toml
+    Config,
+}
+
+impl std::fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CredentialSource::Environment => API_KEY_ENV,
+            CredentialSource::Keychain => "OS keychain",
+            CredentialSource::File => "encrypted credentials file",
+            CredentialSource::Config => "config file",
+        })
+    }
+}
+
+/// An API key and where it came from
+#[derive(Clone)]
+pub struct ApiCredential {
+    pub api_key: Zeroizing<String>,
+    pub source: CredentialSource,
+}
+
+impl ApiCredential {
+    /// The key with all but its last four characters hidden, for display
+    pub fn masked(&self) -> String {
+        let chars: Vec<char> = self.api_key.chars().collect();
+        // Short keys keep at least half hidden
+        let hidden = chars.len().saturating_sub(4).max(chars.len() / 2);
+        format!("{}{}", "*".repeat(hidden), chars[hidden..].iter().collect::<String>())
+    }
+}
+
+/// Masks the key so it can't end up in logs
+impl std::fmt::Debug for ApiCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiCredential")
+            .field("api_key", &self.masked())
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+/// The keychain entry and, as a fallback, encrypted files in `dir`
+pub struct CredentialStore {
+    dir: PathBuf,
+    keychain: bool,
+}
+
+impl CredentialStore {
+    /// Store in `dir` (usually the config directory), trying the OS keychain
+    /// first
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            keychain: cfg!(feature = "keychain"),
+        }
+    }
+
+    /// Only use the encrypted file
+    pub fn without_keychain(mut self) -> Self {
+        self.keychain = false;
+        self
+    }
+
+    /// Save `api_key`, replacing any stored one; returns where it went
+    pub fn store(&self, api_key: &str) -> Result<CredentialSource> {
+        if api_key.trim().is_empty() {
+            return Err(ObfuscatorError::ConfigError("API key is empty".to_string()).into());
+        }
+        if self.keychain {
+            match keychain::store(api_key) {
+                Ok(()) => {
+                    // Don't leave an older key behind in the file
+                    self.remove_file()?;
+                    return Ok(CredentialSource::Keychain);
+                }
+                Err(e) => debug!("OS keychain unavailable, using the credentials file: {:#}", e),
+            }
+        }
+
+        let encrypted = CryptoEngine::default().encrypt(&self.file_key()?, api_key.as_bytes())?;
+        let path = self.dir.join(DATA_FILE);
+        write_private(&path, serde_json::to_string(&encrypted)?.as_bytes())?;
+        Ok(CredentialSource::File)
+    }
+
+    /// The stored key, if any
+    pub fn load(&self) -> Result<Option<ApiCredential>> {
+        if self.keychain {
+            match keychain::load() {
+                Ok(Some(api_key)) => {
+                    return Ok(Some(ApiCredential {
+                        api_key,
+                        source: CredentialSource::Keychain,
+                    }))
+                }
+                Ok(None) => {}
+                Err(e) => debug!("OS keychain unavailable: {:#}", e),
+            }
+        }
+
+        let path = self.dir.join(DATA_FILE);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Ok(None);
+        };
+        let encrypted: EncryptedData = serde_json::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid credentials file {:?}: {}", path, e))
+        })?;
+        let key = fs::read(self.dir.join(KEY_FILE))
+            .with_context(|| format!("Credentials key missing next to {:?}", path))?;
+        let plaintext = CryptoEngine::default()
+            .decrypt(&key, &encrypted)
+            .map_err(|_| {
+                ObfuscatorError::ConfigError(format!(
+                    "Credentials file {:?} can't be decrypted; run `auth login` again",
+                    path
+                ))
+            })?;
+        let api_key = String::from_utf8(plaintext.to_vec()).map_err(|_| {
+            ObfuscatorError::ConfigError(format!("Invalid credentials file {:?}", path))
+        })?;
+
+        Ok(Some(ApiCredential {
+            api_key: Zeroizing::new(api_key),
+            source: CredentialSource::File,
+        }))
+    }
+
+    /// Remove the stored key from the keychain and the file; whether there
+    /// was one
+    pub fn clear(&self) -> Result<bool> {
+        let mut removed = false;
+        if self.keychain {
+            match keychain::delete() {
+                Ok(deleted) => removed |= deleted,
+                Err(e) => debug!("OS keychain unavailable: {:#}", e),
+            }
+        }
+        removed |= self.remove_file()?;
+        Ok(removed)
+    }
+
+    fn remove_file(&self) -> Result<bool> {
+        let mut removed = false;
+        for name in [DATA_FILE, KEY_FILE] {
+            let path = self.dir.join(name);
+            match fs::remove_file(&path) {
+                Ok(()) => removed = true,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to remove {:?}", path)),
+            }
+        }
+        Ok(removed)
+    }
+
+    /// The file encryption key, created on first use
+    fn file_key(&self) -> Result<Zeroizing<Vec<u8>>> {
+        let path = self.dir.join(KEY_FILE);
+        match fs::read(&path) {
+            Ok(key) if key.len() == 32 => Ok(Zeroizing::new(key)),
+            _ => {
+                let mut rng = rand::thread_rng();
+                let key = Zeroizing::new((0..32).map(|_| rng.gen::<u8>()).collect::<Vec<u8>>());
+                write_private(&path, &key)?;
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// Write `contents` to `path`, readable by the owner only
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create credentials directory: {:?}", dir))?;
+    }
+    fs::write(path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict permissions of {:?}", path))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "keychain")]
+mod keychain {
+    use super::{KEYCHAIN_ACCOUNT, KEYCHAIN_SERVICE};
+    use anyhow::Result;
+    use zeroize::Zeroizing;
+
+    fn entry() -> Result<keyring::Entry> {
+        Ok(keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)?)
+    }
+
+    pub fn store(api_key: &str) -> Result<()> {
+        Ok(entry()?.set_password(api_key)?)
+    }
+
+    pub fn load() -> Result<Option<Zeroizing<String>>> {
+        match entry()?.get_password() {
+            Ok(api_key) => Ok(Some(Zeroizing::new(api_key))),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn delete() -> Result<bool> {
+        match entry()?.delete_password() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Stand-in when built without the `keychain` feature; never used since
+/// `CredentialStore::keychain` is then always false
+#[cfg(not(feature = "keychain"))]
+mod keychain {
+    use anyhow::{bail, Result};
+    use zeroize::Zeroizing;
+
+    pub fn store(_api_key: &str) -> Result<()> {
+        bail!("built without the keychain feature")
+    }
+
+    pub fn load() -> Result<Option<Zeroizing<String>>> {
+        Ok(None)
+    }
+
+    pub fn delete() -> Result<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_fallback_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CredentialStore::new(dir.path()).without_keychain();
+        assert!(store.load().unwrap().is_none());
+
+        assert_eq!(store.store("sk_live_abcdef1234").unwrap(), CredentialSource::File);
+        let on_disk = fs::read_to_string(dir.path().join(DATA_FILE)).unwrap();
+        assert!(!on_disk.contains("sk_live"));
+
+        let credential = store.load().unwrap().unwrap();
+        assert_eq!(credential.api_key.as_str(), "sk_live_abcdef1234");
+        assert_eq!(credential.masked(), "**************1234");
+        assert!(!format!("{:?}", credential).contains("sk_live"));
+
+        assert!(store.clear().unwrap());
+        assert!(store.load().unwrap().is_none());
+        assert!(!store.clear().unwrap());
+    }
+}
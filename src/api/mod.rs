@@ -4,14 +4,28 @@
 //! license validation and management API.
 
 mod client;
+mod credentials;
 mod models;
+mod roblox_cloud;
+mod telemetry;
+mod token;
 
 pub use client::ApiClient;
+pub use credentials::{ApiCredential, CredentialSource, CredentialStore, API_KEY_ENV};
 pub use models::{
     ErrorResponse, GenerateLicenseRequest, GenerateLicenseResponse, LicenseMetadata,
-    TrackObfuscationRequest, TrackObfuscationResponse, ValidateLicenseRequest,
-    ValidateLicenseResponse,
+    LicenseSeats, LicenseSeatsRequest, LicenseSeatsResponse, PublishPayloadRequest,
+    PublishPayloadResponse, RevocationListRequest, RevocationListResponse, RevokeLicenseRequest,
+    RevokeLicenseResponse, SeatLimits, SeatSession, TrackObfuscationBatchRequest,
+    TrackObfuscationBatchResponse, TrackObfuscationRequest, TrackObfuscationResponse,
+    ValidateLicenseRequest, ValidateLicenseResponse,
 };
+pub use roblox_cloud::{
+    PlaceOwner, RobloxCloudClient, Universe, VerifiedPlace, OPEN_CLOUD_API_KEY_ENV,
+    OPEN_CLOUD_ENDPOINT,
+};
+pub use telemetry::{EventQueue, FlushReport, QueuedEvent, BATCH_SIZE, MAX_ATTEMPTS};
+pub use token::{TokenCache, ValidationClaims, DEFAULT_TOKEN_TTL_SECONDS};
 
 use anyhow::Result;
 
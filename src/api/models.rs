@@ -1,7 +1,7 @@
 //! API request and response models
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Request to validate a license key
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +42,22 @@ pub struct LicenseMetadata {
     pub created_at: u64,
     /// License tier
     pub tier: String,
+    /// Concurrent-use limits
+    #[serde(flatten)]
+    pub seats: SeatLimits,
+}
+
+/// Concurrent-use limits of a license; `None` is unlimited
+///
+/// The generated script reports a heartbeat for its session (the server's
+/// JobId) and player, and the API rejects validation with the error
+/// `seat_limit_exceeded` once a new session or player would go over a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatLimits {
+    /// Game servers that may run the script at once
+    pub max_servers: Option<u32>,
+    /// Players (or places, for server scripts) that may run it at once
+    pub max_users: Option<u32>,
 }
 
 /// Request to generate a new license
@@ -59,6 +75,9 @@ pub struct GenerateLicenseRequest {
     pub tier: Option<String>,
     /// Optional HWID restrictions
     pub hwid_restrictions: Option<Vec<String>>,
+    /// Optional concurrent-use limits
+    #[serde(flatten)]
+    pub seats: SeatLimits,
 }
 
 /// Response from license generation
@@ -76,6 +95,132 @@ pub struct GenerateLicenseResponse {
     pub expiration: Option<u64>,
 }
 
+/// Request for the seat usage of one license, or of every license of a script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseSeatsRequest {
+    /// Developer API key
+    pub api_key: String,
+    /// License key to inspect
+    pub license_key: Option<String>,
+    /// Script whose licenses to inspect
+    pub script_id: Option<String>,
+}
+
+/// Response listing seat usage per license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseSeatsResponse {
+    pub licenses: Vec<LicenseSeats>,
+}
+
+/// Seat limits and live sessions of one license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseSeats {
+    /// License key
+    pub license_key: String,
+    /// Script identifier
+    pub script_id: String,
+    #[serde(flatten)]
+    pub limits: SeatLimits,
+    /// Sessions whose heartbeat has not lapsed
+    pub sessions: Vec<SeatSession>,
+}
+
+/// One running copy of a script, as last reported by its heartbeat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeatSession {
+    /// Server JobId (a random id in Studio)
+    pub session_id: String,
+    /// Player UserId, or the PlaceId for server scripts
+    pub hwid: String,
+    /// Last heartbeat timestamp (Unix epoch)
+    pub last_heartbeat: u64,
+}
+
+impl LicenseSeats {
+    /// Distinct servers currently running the script
+    pub fn active_servers(&self) -> usize {
+        let servers: HashSet<&str> = self.sessions.iter().map(|s| s.session_id.as_str()).collect();
+        servers.len()
+    }
+
+    /// Distinct players currently running the script
+    pub fn active_users(&self) -> usize {
+        let users: HashSet<&str> = self.sessions.iter().map(|s| s.hwid.as_str()).collect();
+        users.len()
+    }
+
+    /// Whether a new server or player would be rejected
+    pub fn is_full(&self) -> bool {
+        let full = |limit: Option<u32>, active: usize| limit.is_some_and(|n| active >= n as usize);
+        full(self.limits.max_servers, self.active_servers())
+            || full(self.limits.max_users, self.active_users())
+    }
+}
+
+/// Request to revoke a license key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeLicenseRequest {
+    /// Developer API key
+    pub api_key: String,
+    /// License key to revoke
+    pub license_key: String,
+    /// Why the key was revoked (kept with the license)
+    pub reason: Option<String>,
+}
+
+/// Response from revoking a license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeLicenseResponse {
+    /// Whether the key is now revoked
+    pub success: bool,
+    /// Script the key belonged to
+    pub script_id: String,
+    /// Revocation timestamp (Unix epoch)
+    pub revoked_at: u64,
+}
+
+/// Request for a script's signed revocation list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationListRequest {
+    /// Developer API key
+    pub api_key: String,
+    /// Script identifier
+    pub script_id: String,
+}
+
+/// Response carrying a signed revocation list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationListResponse {
+    /// `lrl1.payload.signature`, see `crypto::RevocationList`
+    pub list: String,
+}
+
+/// Request to publish a new payload version to a script's update channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishPayloadRequest {
+    /// Developer API key
+    pub api_key: String,
+    /// Script identifier
+    pub script_id: String,
+    /// Payload version; loaders only take versions above their own
+    pub version: u32,
+    /// Encrypted script (base64)
+    pub ct: String,
+    /// Nonce (base64)
+    pub nonce: String,
+    /// HMAC-SHA256 signature the loader checks (hex)
+    pub sig: String,
+}
+
+/// Response from publishing a payload version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishPayloadResponse {
+    /// Whether the version is now served
+    pub success: bool,
+    /// Highest version the update channel serves
+    pub latest_version: u32,
+}
+
 /// Request to track obfuscation event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackObfuscationRequest {
@@ -102,6 +247,21 @@ pub struct TrackObfuscationResponse {
     pub event_id: Option<String>,
 }
 
+/// Batch of queued obfuscation events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackObfuscationBatchRequest {
+    pub events: Vec<TrackObfuscationRequest>,
+}
+
+/// Response from uploading a batch of events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackObfuscationBatchResponse {
+    /// Whether the batch was recorded
+    pub success: bool,
+    /// Number of events recorded
+    pub accepted: usize,
+}
+
 /// Generic error response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorResponse {
@@ -160,10 +320,47 @@ mod tests {
             expiration: Some("2026-01-01T00:00:00Z".to_string()),
             tier: Some("premium".to_string()),
             hwid_restrictions: Some(vec!["userid:123".to_string()]),
+            seats: SeatLimits {
+                max_servers: Some(2),
+                max_users: None,
+            },
         };
 
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("my_script"));
         assert!(json.contains("987654321"));
+        assert!(json.contains("\"max_servers\":2"));
+    }
+
+    #[test]
+    fn test_license_seats_usage() {
+        let json = r#"{
+            "licenses": [{
+                "license_key": "XXXX-XXXX-XXXX-XXXX",
+                "script_id": "test_script",
+                "max_servers": 2,
+                "sessions": [
+                    {"session_id": "job-a", "hwid": "1", "last_heartbeat": 1700000000},
+                    {"session_id": "job-a", "hwid": "2", "last_heartbeat": 1700000030},
+                    {"session_id": "job-b", "hwid": "1", "last_heartbeat": 1700000045}
+                ]
+            }]
+        }"#;
+
+        let response: LicenseSeatsResponse = serde_json::from_str(json).unwrap();
+        let seats = &response.licenses[0];
+        assert_eq!(seats.limits.max_users, None);
+        assert_eq!(seats.active_servers(), 2);
+        assert_eq!(seats.active_users(), 2);
+        assert!(seats.is_full());
+
+        let spare = LicenseSeats {
+            limits: SeatLimits {
+                max_servers: Some(3),
+                max_users: None,
+            },
+            ..seats.clone()
+        };
+        assert!(!spare.is_full());
     }
 }
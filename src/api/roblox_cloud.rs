@@ -0,0 +1,220 @@
+//! Roblox Open Cloud client for place verification
+//!
+//! With `--verify-place`, `protect` looks up the bound PlaceId before
+//! obfuscating: the place must exist, and its experience must be owned by
+//! the configured user or group. A typo in the PlaceId then fails the build
+//! instead of shipping a script that refuses to run anywhere.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use log::debug;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// Environment variable holding the Open Cloud API key
+pub const OPEN_CLOUD_API_KEY_ENV: &str = "ROBLOX_OPEN_CLOUD_API_KEY";
+
+/// Default Open Cloud base URL
+pub const OPEN_CLOUD_ENDPOINT: &str = "https://apis.roblox.com";
+
+/// Who must own the experience a place belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "id", rename_all = "lowercase")]
+pub enum PlaceOwner {
+    User(u64),
+    Group(u64),
+}
+
+impl std::fmt::Display for PlaceOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaceOwner::User(id) => write!(f, "user {}", id),
+            PlaceOwner::Group(id) => write!(f, "group {}", id),
+        }
+    }
+}
+
+/// Response of the place → universe lookup
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaceUniverse {
+    universe_id: Option<u64>,
+}
+
+/// Open Cloud universe (experience) resource, as far as it's used here
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Universe {
+    /// Experience name
+    #[serde(default)]
+    pub display_name: String,
+    /// Owning user, as `users/<id>`
+    pub user: Option<String>,
+    /// Owning group, as `groups/<id>`
+    pub group: Option<String>,
+}
+
+impl Universe {
+    /// Owner of the experience, if the response names one
+    pub fn owner(&self) -> Option<PlaceOwner> {
+        let id = |path: &str, prefix: &str| path.strip_prefix(prefix)?.parse().ok();
+        match (&self.user, &self.group) {
+            (Some(user), _) => id(user, "users/").map(PlaceOwner::User),
+            (None, Some(group)) => id(group, "groups/").map(PlaceOwner::Group),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A place that passed verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifiedPlace {
+    pub place_id: u64,
+    pub universe_id: u64,
+    pub name: String,
+    pub owner: Option<PlaceOwner>,
+}
+
+/// Open Cloud client authenticated with an API key
+pub struct RobloxCloudClient {
+    client: Client,
+    base_url: String,
+    api_key: Zeroizing<String>,
+}
+
+impl RobloxCloudClient {
+    /// Create a client for the public Open Cloud endpoint
+    pub fn new(api_key: &str) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: OPEN_CLOUD_ENDPOINT.to_string(),
+            api_key: Zeroizing::new(api_key.to_string()),
+        })
+    }
+
+    /// Send requests to `base_url` instead (a proxy or a test server)
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Universe (experience) `place_id` belongs to, or `None` if there is no
+    /// such place
+    pub fn universe_of(&self, place_id: u64) -> Result<Option<u64>> {
+        let url = format!("{}/universes/v1/places/{}/universe", self.base_url, place_id);
+        debug!("Looking up the universe of place {}: {}", place_id, url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .context("Failed to reach Roblox Open Cloud")?;
+        match response.status() {
+            StatusCode::NOT_FOUND | StatusCode::BAD_REQUEST => return Ok(None),
+            status if !status.is_success() => {
+                return Err(ObfuscatorError::ApiError(format!(
+                    "Roblox returned error {} looking up place {}",
+                    status, place_id
+                ))
+                .into())
+            }
+            _ => {}
+        }
+
+        let lookup: PlaceUniverse = response
+            .json()
+            .context("Failed to parse the place lookup response")?;
+        Ok(lookup.universe_id)
+    }
+
+    /// Universe `universe_id`, read with the API key
+    pub fn universe(&self, universe_id: u64) -> Result<Universe> {
+        let url = format!("{}/cloud/v2/universes/{}", self.base_url, universe_id);
+        debug!("Reading universe {}: {}", universe_id, url);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("x-api-key", self.api_key.as_str())
+            .send()
+            .context("Failed to reach Roblox Open Cloud")?;
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err(ObfuscatorError::ApiError(format!(
+                "Open Cloud API key can't read universe {} ({}); give it the \
+                 universe:read scope for this experience",
+                universe_id, status
+            ))
+            .into());
+        }
+        if !status.is_success() {
+            return Err(ObfuscatorError::ApiError(format!(
+                "Roblox returned error {} reading universe {}",
+                status, universe_id
+            ))
+            .into());
+        }
+
+        response
+            .json()
+            .context("Failed to parse the universe response")
+    }
+
+    /// Check that `place_id` exists and, when `owner` is given, that its
+    /// experience belongs to `owner`
+    pub fn verify_place(&self, place_id: u64, owner: Option<PlaceOwner>) -> Result<VerifiedPlace> {
+        let universe_id = self.universe_of(place_id)?.ok_or_else(|| {
+            ObfuscatorError::ConfigError(format!("Place {} does not exist", place_id))
+        })?;
+        let universe = self.universe(universe_id)?;
+        let actual = universe.owner();
+
+        if let Some(expected) = owner {
+            if actual != Some(expected) {
+                let actual = actual.map_or("an unknown owner".to_string(), |o| o.to_string());
+                return Err(ObfuscatorError::ConfigError(format!(
+                    "Place {} ({}) belongs to {}, not {}",
+                    place_id, universe.display_name, actual, expected
+                ))
+                .into());
+            }
+        }
+
+        Ok(VerifiedPlace {
+            place_id,
+            universe_id,
+            name: universe.display_name,
+            owner: actual,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_universe_owner() {
+        let universe: Universe = serde_json::from_str(
+            r#"{"path": "universes/42", "displayName": "Obby", "user": "users/123"}"#,
+        )
+        .unwrap();
+        assert_eq!(universe.display_name, "Obby");
+        assert_eq!(universe.owner(), Some(PlaceOwner::User(123)));
+
+        let universe: Universe =
+            serde_json::from_str(r#"{"path": "universes/42", "group": "groups/77"}"#).unwrap();
+        assert_eq!(universe.owner(), Some(PlaceOwner::Group(77)));
+
+        let universe: Universe = serde_json::from_str(r#"{"user": "nobody"}"#).unwrap();
+        assert_eq!(universe.owner(), None);
+    }
+}
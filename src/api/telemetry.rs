@@ -0,0 +1,242 @@
+//! Queued usage events
+//!
+//! `protect` reports each obfuscation to the license API. Events are
+//! appended to a JSONL file in the config directory and uploaded in
+//! batches, so an upload that fails is retried on a later run (or by
+//! `telemetry flush`) instead of being lost. An event that keeps failing is
+//! dropped after [`MAX_ATTEMPTS`] uploads.
+
+use super::models::TrackObfuscationRequest;
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Failed uploads after which an event is dropped
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Most events sent in one request
+pub const BATCH_SIZE: usize = 50;
+
+/// One line of the queue file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedEvent {
+    /// API endpoint the event is reported to
+    pub endpoint: String,
+    /// Failed uploads so far
+    #[serde(default)]
+    pub attempts: u32,
+    pub event: TrackObfuscationRequest,
+}
+
+/// Outcome of uploading the queue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FlushReport {
+    pub sent: usize,
+    /// Events left queued for the next run
+    pub remaining: usize,
+    /// Events given up on after [`MAX_ATTEMPTS`] failed uploads
+    pub dropped: usize,
+}
+
+/// Persistent queue of usage events, one JSON object per line
+pub struct EventQueue {
+    path: PathBuf,
+}
+
+impl EventQueue {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Queue file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an event for `endpoint`
+    pub fn push(&self, endpoint: &str, event: TrackObfuscationRequest) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create event queue directory: {:?}", dir))?;
+        }
+        let queued = QueuedEvent {
+            endpoint: endpoint.to_string(),
+            attempts: 0,
+            event,
+        };
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open event queue: {:?}", self.path))?;
+        writeln!(file, "{}", serde_json::to_string(&queued)?)
+            .with_context(|| format!("Failed to write event queue: {:?}", self.path))
+    }
+
+    /// Queued events, oldest first; unreadable lines are skipped
+    pub fn pending(&self) -> Result<Vec<QueuedEvent>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read event queue: {:?}", self.path))
+            }
+        };
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    debug!("Skipping unreadable queued event: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Upload the queued events in batches of [`BATCH_SIZE`] per endpoint
+    ///
+    /// `upload` sends one batch to an endpoint. Events of a batch that fails
+    /// stay queued with one more attempt recorded.
+    pub fn flush(
+        &self,
+        mut upload: impl FnMut(&str, &[TrackObfuscationRequest]) -> Result<()>,
+    ) -> Result<FlushReport> {
+        let pending = self.pending()?;
+        let mut report = FlushReport::default();
+        if pending.is_empty() {
+            return Ok(report);
+        }
+
+        // Group by endpoint, keeping the order events were queued in
+        let mut by_endpoint: Vec<(String, Vec<QueuedEvent>)> = Vec::new();
+        for queued in pending {
+            match by_endpoint.iter_mut().find(|(endpoint, _)| *endpoint == queued.endpoint) {
+                Some((_, events)) => events.push(queued),
+                None => by_endpoint.push((queued.endpoint.clone(), vec![queued])),
+            }
+        }
+
+        let mut kept = Vec::new();
+        for (endpoint, events) in by_endpoint {
+            for batch in events.chunks(BATCH_SIZE) {
+                let requests: Vec<_> = batch.iter().map(|queued| queued.event.clone()).collect();
+                if let Err(e) = upload(&endpoint, &requests) {
+                    debug!("Uploading {} event(s) to {} failed: {:#}", batch.len(), endpoint, e);
+                    for queued in batch {
+                        let attempts = queued.attempts + 1;
+                        if attempts >= MAX_ATTEMPTS {
+                            report.dropped += 1;
+                        } else {
+                            kept.push(QueuedEvent {
+                                attempts,
+                                ..queued.clone()
+                            });
+                        }
+                    }
+                    continue;
+                }
+                report.sent += batch.len();
+            }
+        }
+
+        report.remaining = kept.len();
+        self.rewrite(&kept)?;
+        Ok(report)
+    }
+
+    fn rewrite(&self, events: &[QueuedEvent]) -> Result<()> {
+        if events.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e)
+                    .with_context(|| format!("Failed to clear event queue: {:?}", self.path)),
+                _ => Ok(()),
+            };
+        }
+
+        let mut content = String::new();
+        for queued in events {
+            content.push_str(&serde_json::to_string(queued)?);
+            content.push('\n');
+        }
+        // Replace the file in one step so a crash can't truncate the queue
+        let temp = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp, content)
+            .with_context(|| format!("Failed to write event queue: {:?}", temp))?;
+        fs::rename(&temp, &self.path)
+            .with_context(|| format!("Failed to replace event queue: {:?}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::errors::ObfuscatorError;
+
+    fn event(script_id: &str) -> TrackObfuscationRequest {
+        TrackObfuscationRequest {
+            api_key: String::new(),
+            script_id: script_id.to_string(),
+            license_key: "ABCD-1234-EFGH-5678".to_string(),
+            tier: "standard".to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_failed_uploads_stay_queued() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = EventQueue::new(dir.path().join("events.jsonl"));
+        queue.push("https://a.example.com", event("one")).unwrap();
+        queue.push("https://b.example.com", event("two")).unwrap();
+        queue.push("https://a.example.com", event("three")).unwrap();
+
+        let report = queue
+            .flush(|endpoint, _| match endpoint {
+                "https://a.example.com" => Ok(()),
+                _ => Err(ObfuscatorError::ApiError("offline".to_string()).into()),
+            })
+            .unwrap();
+        assert_eq!((report.sent, report.remaining, report.dropped), (2, 1, 0));
+
+        let pending = queue.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event.script_id, "two");
+        assert_eq!(pending[0].attempts, 1);
+
+        // The next run sends it, in one batch per endpoint
+        let mut batches = Vec::new();
+        let report = queue
+            .flush(|endpoint, events| {
+                batches.push((endpoint.to_string(), events.len()));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(report.sent, 1);
+        assert_eq!(batches, [("https://b.example.com".to_string(), 1)]);
+        assert!(!queue.path().exists());
+    }
+
+    #[test]
+    fn test_events_dropped_after_max_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = EventQueue::new(dir.path().join("events.jsonl"));
+        queue.push("https://a.example.com", event("one")).unwrap();
+
+        let failing = |_: &str, _: &[TrackObfuscationRequest]| -> Result<()> {
+            Err(ObfuscatorError::ApiError("offline".to_string()).into())
+        };
+        for _ in 1..MAX_ATTEMPTS {
+            assert_eq!(queue.flush(failing).unwrap().remaining, 1);
+        }
+        assert_eq!(queue.flush(failing).unwrap().dropped, 1);
+        assert!(queue.pending().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! Cached license validation tokens
+//!
+//! A successful online validation is recorded as a signed token
+//! (`header.payload.signature`, base64url, JWT-like) so repeat `protect`
+//! runs within the TTL skip the network round trip. Tokens are signed with
+//! a random per-machine key kept next to them, which makes edits to the
+//! cache file detectable; they are a convenience, not a security boundary.
+
+use super::models::ValidateLicenseRequest;
+use crate::crypto::constant_time_eq;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use log::debug;
+use rand::Rng;
+use ring::digest::{digest, SHA256};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How long a validation stays usable offline by default (24 hours)
+pub const DEFAULT_TOKEN_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+/// Fixed token header, identifying the signing scheme
+const TOKEN_HEADER: &str = r#"{"alg":"HS256","typ":"LVT"}"#;
+
+/// Claims carried by a validation token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationClaims {
+    /// SHA-256 of the license key (the key itself is never written to disk)
+    pub license: String,
+    pub script_id: String,
+    pub hwid: Option<String>,
+    /// Tier the license was validated at, when the API reported one
+    #[serde(default)]
+    pub tier: Option<String>,
+    /// Unix timestamps
+    pub issued_at: i64,
+    pub expires_at: i64,
+}
+
+impl ValidationClaims {
+    /// Claims for `request`, valid for `ttl_seconds` from now
+    pub fn for_request(
+        request: &ValidateLicenseRequest,
+        tier: Option<String>,
+        ttl_seconds: u64,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            license: license_fingerprint(&request.license_key),
+            script_id: request.script_id.clone(),
+            hwid: request.hwid.clone(),
+            tier,
+            issued_at: now,
+            expires_at: now + ttl_seconds as i64,
+        }
+    }
+
+    /// Whether these claims cover `request` at `now`
+    pub fn covers(&self, request: &ValidateLicenseRequest, now: i64) -> bool {
+        constant_time_eq(
+            self.license.as_bytes(),
+            license_fingerprint(&request.license_key).as_bytes(),
+        ) && self.script_id == request.script_id
+            && self.hwid == request.hwid
+            && self.issued_at <= now
+            && now < self.expires_at
+    }
+}
+
+/// On-disk cache of validation tokens, one file per license/script/HWID
+pub struct TokenCache {
+    dir: PathBuf,
+    key: hmac::Key,
+    ttl_seconds: u64,
+}
+
+impl TokenCache {
+    /// Open the cache in `dir`, creating it and its signing key if needed
+    pub fn open(dir: impl Into<PathBuf>, ttl_seconds: u64) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create token cache: {:?}", dir))?;
+
+        let key_path = dir.join("signing.key");
+        let secret = match fs::read(&key_path) {
+            Ok(secret) if secret.len() == 32 => secret,
+            _ => {
+                let mut rng = rand::thread_rng();
+                let secret: Vec<u8> = (0..32).map(|_| rng.gen::<u8>()).collect();
+                fs::write(&key_path, &secret)
+                    .with_context(|| format!("Failed to write token key: {:?}", key_path))?;
+                secret
+            }
+        };
+
+        Ok(Self {
+            dir,
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+            ttl_seconds,
+        })
+    }
+
+    /// Cached claims covering `request`, if present, untampered and unexpired
+    pub fn lookup(&self, request: &ValidateLicenseRequest) -> Option<ValidationClaims> {
+        let path = self.token_path(request);
+        let token = fs::read_to_string(&path).ok()?;
+
+        match self.verify(token.trim()) {
+            Ok(claims) if claims.covers(request, chrono::Utc::now().timestamp()) => Some(claims),
+            Ok(_) => {
+                debug!("Cached validation token expired: {:?}", path);
+                None
+            }
+            Err(e) => {
+                debug!("Ignoring cached validation token {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Record a successful validation of `request` for a license of `tier`
+    pub fn store(
+        &self,
+        request: &ValidateLicenseRequest,
+        tier: Option<String>,
+    ) -> Result<ValidationClaims> {
+        let claims = ValidationClaims::for_request(request, tier, self.ttl_seconds);
+        let path = self.token_path(request);
+        fs::write(&path, self.sign(&claims)?)
+            .with_context(|| format!("Failed to write validation token: {:?}", path))?;
+        Ok(claims)
+    }
+
+    /// Encode and sign claims as `header.payload.signature`
+    pub fn sign(&self, claims: &ValidationClaims) -> Result<String> {
+        let payload = serde_json::to_vec(claims)?;
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(TOKEN_HEADER),
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+        let tag = hmac::sign(&self.key, signing_input.as_bytes());
+        Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(tag.as_ref())))
+    }
+
+    /// Check a token's signature and decode its claims
+    pub fn verify(&self, token: &str) -> Result<ValidationClaims> {
+        let invalid = |reason: &str| {
+            ObfuscatorError::LicenseError(format!("Invalid validation token: {}", reason))
+        };
+
+        let (signing_input, signature) = token
+            .rsplit_once('.')
+            .ok_or_else(|| invalid("malformed"))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| invalid("malformed signature"))?;
+        hmac::verify(&self.key, signing_input.as_bytes(), &signature)
+            .map_err(|_| invalid("signature mismatch"))?;
+
+        let (header, payload) = signing_input
+            .split_once('.')
+            .ok_or_else(|| invalid("malformed"))?;
+        if URL_SAFE_NO_PAD.decode(header).ok().as_deref() != Some(TOKEN_HEADER.as_bytes()) {
+            return Err(invalid("unsupported header").into());
+        }
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| invalid("malformed payload"))?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+
+    /// Cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn token_path(&self, request: &ValidateLicenseRequest) -> PathBuf {
+        let id = format!(
+            "{}\0{}\0{}",
+            request.license_key,
+            request.script_id,
+            request.hwid.as_deref().unwrap_or_default()
+        );
+        self.dir.join(format!("{}.token", &license_fingerprint(&id)[..32]))
+    }
+}
+
+/// Hex SHA-256 of a license key
+fn license_fingerprint(license_key: &str) -> String {
+    hex::encode(digest(&SHA256, license_key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(hwid: Option<&str>) -> ValidateLicenseRequest {
+        ValidateLicenseRequest {
+            api_key: String::new(),
+            license_key: "ABCD-1234-EFGH-5678".to_string(),
+            script_id: "script".to_string(),
+            hwid: hwid.map(str::to_string),
+            watermark: None,
+        }
+    }
+
+    #[test]
+    fn test_cached_token_skips_revalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = TokenCache::open(dir.path(), 60).unwrap();
+        assert!(cache.lookup(&request(Some("1"))).is_none());
+
+        cache.store(&request(Some("1")), Some("basic".to_string())).unwrap();
+        let claims = cache.lookup(&request(Some("1"))).unwrap();
+        assert_eq!(claims.tier.as_deref(), Some("basic"));
+        // Bound to the HWID it was validated for
+        assert!(cache.lookup(&request(Some("2"))).is_none());
+
+        // Survives reopening with the same signing key
+        let reopened = TokenCache::open(dir.path(), 60).unwrap();
+        assert!(reopened.lookup(&request(Some("1"))).is_some());
+    }
+
+    #[test]
+    fn test_tampered_or_expired_tokens_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = TokenCache::open(dir.path(), 60).unwrap();
+
+        let mut claims = ValidationClaims::for_request(&request(None), None, 60);
+        let token = cache.sign(&claims).unwrap();
+        assert_eq!(cache.verify(&token).unwrap(), claims);
+
+        claims.expires_at += 3600;
+        let forged = cache.sign(&claims).unwrap();
+        let (_, forged_payload) = forged.rsplit_once('.').unwrap().0.split_once('.').unwrap();
+        let (header, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let spliced = format!("{}.{}.{}", header, forged_payload, signature);
+        assert!(cache.verify(&spliced).is_err());
+
+        let expired = ValidationClaims::for_request(&request(None), None, 0);
+        assert!(!expired.covers(&request(None), chrono::Utc::now().timestamp()));
+    }
+}
@@ -1,11 +1,14 @@
 //! CLI argument definitions using clap
 
+use super::watch::WatchTarget;
+use crate::crypto::KEYSTORE_PATH;
+use crate::utils::errors::EXIT_CODES_HELP;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-#[command(propagate_version = true)]
+#[command(propagate_version = true, after_help = EXIT_CODES_HELP)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
@@ -13,35 +16,390 @@ pub struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    /// Output format: human-readable text or JSON on stdout
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Write stage and pass timings to FILE in Chrome trace format
+    /// (open it in chrome://tracing or ui.perfetto.dev)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub trace_output: Option<PathBuf>,
+
+    /// Don't queue or upload usage events (or set `telemetry = false` in
+    /// the config)
+    #[arg(long, global = true)]
+    pub no_telemetry: bool,
 }
 
-#[derive(Subcommand, Debug)]
+#[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Protect a Luau script with obfuscation and encryption
     Protect {
         /// Input Luau script file
-        #[arg(value_name = "INPUT")]
-        input: PathBuf,
+        #[arg(value_name = "INPUT", required_unless_present_any = ["stdin", "bundle"])]
+        input: Option<PathBuf>,
+
+        /// Read the script from stdin instead of INPUT
+        #[arg(long, conflicts_with_all = ["input", "watch", "interactive"])]
+        stdin: bool,
+
+        /// Bundle a Rojo project (or a folder of scripts) into one script,
+        /// inlining the ModuleScripts it requires, and protect that
+        #[arg(long, value_name = "PROJECT", conflicts_with_all = ["input", "stdin", "watch"])]
+        bundle: Option<PathBuf>,
+
+        /// Script the bundle runs (instance path or file in the project;
+        /// defaults to the only Script or LocalScript)
+        #[arg(long, value_name = "SCRIPT", requires = "bundle")]
+        entry: Option<String>,
 
         /// Output file path
-        #[arg(short, long, value_name = "OUTPUT")]
-        output: PathBuf,
+        #[arg(
+            short,
+            long,
+            value_name = "OUTPUT",
+            required_unless_present_any = ["output_loader", "stdout"]
+        )]
+        output: Option<PathBuf>,
+
+        /// Write the protected script to stdout; everything else goes to stderr
+        #[arg(long, conflicts_with_all = ["output", "output_loader"])]
+        stdout: bool,
+
+        /// Split the output: write the loader (runtime and checks) here...
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with = "output",
+            requires = "output_payload"
+        )]
+        output_loader: Option<PathBuf>,
+
+        /// ...and the encrypted payload ModuleScript here (parent it to the loader as "Payload")
+        #[arg(long, value_name = "FILE", requires = "output_loader")]
+        output_payload: Option<PathBuf>,
 
         /// License key for the protected script
         #[arg(short, long, value_name = "KEY")]
         license_key: String,
 
+        /// Script id for license checks, the watermark and the keystore
+        /// (default: derived from the project name and the source)
+        #[arg(long, value_name = "ID")]
+        script_id: Option<String>,
+
+        /// Project name the script id is derived from (default: the input
+        /// file name)
+        #[arg(long, value_name = "NAME", conflicts_with = "script_id")]
+        project: Option<String>,
+
+        /// Derive the watermark from your customer's root for project NAME,
+        /// so leaked files of one project can be traced together
+        #[arg(long, value_name = "NAME")]
+        watermark_project: Option<String>,
+
+        /// Hardware ID (Roblox UserId) to bind to
+        #[arg(long, value_name = "HWID")]
+        hwid: Option<u64>,
+
+        /// PlaceId the script may only run in (with `--hwid`, both must match)
+        #[arg(long, value_name = "ID")]
+        place_id: Option<u64>,
+
+        /// Check with Roblox Open Cloud that the place exists and belongs to
+        /// the configured owner before protecting
+        #[arg(long, requires = "place_id")]
+        verify_place: bool,
+
+        /// Obfuscation tier: basic, standard, or premium
+        #[arg(short, long, value_name = "TIER", default_value = "standard")]
+        tier: ObfuscationTier,
+
+        /// When --tier is above the tier of the validated license: downgrade
+        /// to the licensed tier or fail (default: config's `tier_policy`)
+        #[arg(long, value_name = "POLICY", requires = "api_endpoint")]
+        tier_policy: Option<TierPolicy>,
+
+        /// Tune the tier for a kind of script: admin-panel, gun-system,
+        /// ui-library, anti-cheat, loader (or a preset from the config)
+        #[arg(long, value_name = "PRESET")]
+        preset: Option<String>,
+
+        /// API endpoint for license validation
+        #[arg(long, value_name = "URL")]
+        api_endpoint: Option<String>,
+
+        /// Always validate the license online: ignore cached validations and
+        /// fail instead of falling back to offline mode
+        #[arg(long, requires = "api_endpoint")]
+        require_online: bool,
+
+        /// Signed revocation list (from `license crl`): the build fails if the
+        /// license key is on it, and the license check rejects listed keys
+        /// even when the API can't be reached
+        #[arg(long, value_name = "FILE")]
+        revocation_list: Option<PathBuf>,
+
+        /// Seed for reproducible obfuscation (same input + seed = same names and
+        /// layout; encrypted strings still differ per run)
+        #[arg(long, value_name = "SEED")]
+        seed: Option<u64>,
+
+        /// Parse in bounded-memory chunks (for very large generated scripts)
+        #[arg(long)]
+        streaming: bool,
+
+        /// Remove Luau type annotations and type declarations from the output
+        #[arg(long)]
+        strip_types: bool,
+
+        /// Re-run whenever the input file changes
+        #[arg(long)]
+        watch: bool,
+
+        /// Review the strings, names and functions to transform in a terminal
+        /// UI, switching items off before the output is written
+        #[arg(long, conflicts_with = "watch")]
+        interactive: bool,
+
+        /// Don't embed encrypted strings; fetch them from this URL once
+        /// license validation succeeds
+        #[arg(long, value_name = "URL")]
+        payload_url: Option<String>,
+
+        /// Where to export the payload to serve (default: <OUTPUT>.payload.json)
+        #[arg(long, value_name = "FILE", requires = "payload_url")]
+        payload_output: Option<PathBuf>,
+
+        /// Have the split loader ask this URL for a newer signed version
+        /// (published with `publish-payload`) before running its payload
+        #[arg(long, value_name = "URL", requires_all = ["output_loader", "password"])]
+        update_url: Option<String>,
+
+        /// Version of the embedded payload; the loader only takes updates
+        /// with a higher version
+        #[arg(long, value_name = "N", default_value_t = 1, requires = "update_url")]
+        payload_version: u32,
+
+        /// Where to write the symbol map (default: <OUTPUT>.map.json; with
+        /// `--stdout` none is written unless this is given)
+        #[arg(long, value_name = "FILE")]
+        symbol_map: Option<PathBuf>,
+
+        /// Hot functions to keep fast: a list of names (one per line) or a
+        /// profiler CSV export. The rest of the script is obfuscated harder
+        #[arg(long, value_name = "FILE")]
+        hot_functions: Option<PathBuf>,
+
+        /// Cap dead code at this share of the input size (e.g. 25%)
+        #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+        max_overhead: Option<f32>,
+
+        /// Compress large strings and the payload chunk before encrypting;
+        /// the script gains a Luau decompressor
+        #[arg(long, value_name = "ALGORITHM", default_value = "none")]
+        compress: CompressionArg,
+
+        /// How embedded ciphertext is written (`auto` picks by size)
+        #[arg(long, value_name = "ENCODING", default_value = "auto")]
+        data_encoding: DataEncoding,
+
+        /// How renamed identifiers look (`score` shows how detectable each is)
+        #[arg(long, value_name = "STYLE", default_value = "hex")]
+        naming_style: NamingStyle,
+
+        /// Rewrite table keys and method names used only on the script's own
+        /// tables; the rest are listed with the reason they were kept
+        #[arg(long, value_name = "MODE")]
+        table_keys: Option<TableKeys>,
+
+        /// Fail when `_G`, `shared` or `getfenv` reach a name that would be
+        /// renamed, instead of keeping the name and warning
+        #[arg(long)]
+        deny_dynamic_globals: bool,
+
+        /// Skip the check of the output for APIs and compiler limits Roblox
+        /// doesn't allow (errors otherwise stop the build)
+        #[arg(long)]
+        no_lint: bool,
+
+        /// When a transform fails, skip it (or just the failing block) with a
+        /// warning instead of failing the whole run
+        #[arg(long)]
+        best_effort: bool,
+
+        /// Inline locals holding a literal that are read once, and remove
+        /// locals and local functions nobody reads, before protecting
+        #[arg(long)]
+        simplify: bool,
+
+        /// Fail, writing nothing, on diagnostics with this id, or on every
+        /// warning with `warnings` (repeatable)
+        #[arg(long, value_name = "ID")]
+        deny: Vec<String>,
+
+        /// Run the original and the protected build in an embedded Luau VM
+        /// with stand-ins for Roblox, and fail (writing nothing) unless their
+        /// output and return values match
+        #[arg(long)]
+        verify: bool,
+
+        /// Never encrypt strings matching PATTERN (a regex, or a glob after
+        /// `glob:`), in addition to the config's `plaintext_strings`; repeatable
+        #[arg(long, value_name = "PATTERN")]
+        plaintext: Vec<String>,
+
+        /// Keep the original comments (they are removed by default)
+        #[arg(long)]
+        keep_comments: bool,
+
+        /// What to do with input bytes that aren't UTF-8 (overrides the
+        /// config's `[input] invalid_bytes`)
+        #[arg(long, value_name = "MODE")]
+        invalid_bytes: Option<InvalidBytes>,
+
+        /// License/EULA template to put in the header (overrides the
+        /// config's `license_header`)
+        #[arg(long, value_name = "FILE")]
+        license_header: Option<PathBuf>,
+
+        /// Start the script with `--!optimize 2`, replacing any other level
+        /// (or set the config's `force_optimize`)
+        #[arg(long)]
+        force_optimize: bool,
+
+        /// Write the strings of the `--!obfuscate: config` settings block as
+        /// escapes (or set the config's `encode_config`)
+        #[arg(long)]
+        encode_config: bool,
+
+        /// Keep decrypted high-sensitivity strings masked at runtime so table
+        /// dumps don't show them (overrides the preset's `anti_dump`)
+        #[arg(long, value_name = "MODE")]
+        anti_dump: Option<AntiDump>,
+
+        /// Look for exploit executor globals at startup and block, degrade or
+        /// report (overrides the preset's `executor_check`)
+        #[arg(long, value_name = "RESPONSE")]
+        executor_check: Option<ExecutorResponse>,
+
+        /// Refuse to run on the other side of the client/server boundary
+        #[arg(long, value_name = "CONTEXT", default_value = "shared")]
+        context: RunContext,
+
+        /// Directory of templates replacing the built-in runtime, license
+        /// check, HWID binding or tamper response (overrides the config's
+        /// `templates_dir`)
+        #[arg(long, value_name = "DIR")]
+        templates: Option<PathBuf>,
+
+        /// Encryption password (random when omitted)
+        #[arg(short, long, value_name = "PASSWORD")]
+        password: Option<String>,
+
+        /// Argon2id cost for deriving the key from the password (defaults to
+        /// the keystore's for this script, else paranoid)
+        #[arg(long, value_name = "PROFILE")]
+        kdf_profile: Option<KdfProfile>,
+
+        /// Project keystore recording each script's salt and key version
+        #[arg(long, value_name = "FILE", default_value = KEYSTORE_PATH)]
+        keystore: PathBuf,
+
+        /// Encrypt the keystore with the password
+        #[arg(long, requires = "password")]
+        encrypt_keystore: bool,
+    },
+
+    /// Protect every script in a Rojo project or model, keeping cross-module references intact
+    ProtectProject {
+        /// Rojo project file, directory containing default.project.json, or
+        /// .rbxm/.rbxmx model
+        #[arg(value_name = "PROJECT")]
+        project: PathBuf,
+
+        /// Output directory for the transformed project tree (the protected
+        /// model file, in the input's format, for a model)
+        #[arg(short, long, value_name = "DIR")]
+        output: PathBuf,
+
+        /// License key every protected script checks
+        #[arg(short, long, value_name = "KEY")]
+        license_key: String,
+
+        /// Script id for license checks, the watermark and the keystore
+        /// (default: derived from the project name and its scripts)
+        #[arg(long, value_name = "ID")]
+        script_id: Option<String>,
+
         /// Hardware ID (Roblox UserId) to bind to
         #[arg(long, value_name = "HWID")]
         hwid: Option<u64>,
 
+        /// PlaceId the scripts may only run in (with `--hwid`, both must match)
+        #[arg(long, value_name = "ID")]
+        place_id: Option<u64>,
+
         /// Obfuscation tier: basic, standard, or premium
         #[arg(short, long, value_name = "TIER", default_value = "standard")]
         tier: ObfuscationTier,
 
+        /// When --tier is above the tier of the validated license: downgrade
+        /// to the licensed tier or fail (default: config's `tier_policy`)
+        #[arg(long, value_name = "POLICY", requires = "api_endpoint")]
+        tier_policy: Option<TierPolicy>,
+
         /// API endpoint for license validation
         #[arg(long, value_name = "URL")]
         api_endpoint: Option<String>,
+
+        /// Always validate the license online: ignore cached validations and
+        /// fail instead of falling back to offline mode
+        #[arg(long, requires = "api_endpoint")]
+        require_online: bool,
+
+        /// Seed for reproducible names and layout
+        #[arg(long, value_name = "SEED")]
+        seed: Option<u64>,
+
+        /// How renamed identifiers and export names look
+        #[arg(long, value_name = "STYLE", default_value = "hex")]
+        naming_style: NamingStyle,
+
+        /// Remove Luau type annotations and type declarations from the output
+        #[arg(long)]
+        strip_types: bool,
+
+        /// Rename RemoteEvent/RemoteFunction names consistently across scripts
+        #[arg(long)]
+        rename_remotes: bool,
+
+        /// Where to write the remote name mapping (default: <DIR>/remotes.map.json,
+        /// or next to a protected model)
+        #[arg(long, value_name = "FILE", requires = "rename_remotes")]
+        remote_map: Option<PathBuf>,
+
+        /// Re-run whenever a file in the project directory changes
+        #[arg(long)]
+        watch: bool,
+
+        /// Encryption password (random when omitted)
+        #[arg(short, long, value_name = "PASSWORD")]
+        password: Option<String>,
+
+        /// Argon2id cost for deriving the key from the password (defaults to
+        /// the keystore's for this script, else paranoid)
+        #[arg(long, value_name = "PROFILE")]
+        kdf_profile: Option<KdfProfile>,
+
+        /// Project keystore recording each script's salt and key version
+        #[arg(long, value_name = "FILE", default_value = KEYSTORE_PATH)]
+        keystore: PathBuf,
+
+        /// Encrypt the keystore with the password
+        #[arg(long, requires = "password")]
+        encrypt_keystore: bool,
     },
 
     /// Generate a new license key
@@ -51,16 +409,135 @@ pub enum Commands {
         script_id: String,
 
         /// Buyer's Roblox UserId
-        #[arg(long, value_name = "USERID")]
-        buyer_userid: u64,
+        #[arg(long, value_name = "USERID", required_unless_present = "batch")]
+        buyer_userid: Option<u64>,
 
-        /// Developer API key
+        /// Developer API key (defaults to the key saved with `auth login`)
         #[arg(long, value_name = "KEY")]
-        api_key: String,
+        api_key: Option<String>,
 
         /// License expiration date (optional)
         #[arg(long, value_name = "DATE")]
         expiration: Option<String>,
+
+        /// CSV file of buyers (buyer_userid[,expiration][,tier]) for bulk generation
+        #[arg(long, value_name = "CSV", conflicts_with = "buyer_userid")]
+        batch: Option<PathBuf>,
+
+        /// Output file for batch results (.json for JSON, otherwise CSV)
+        #[arg(short, long, value_name = "OUTPUT", requires = "batch")]
+        output: Option<PathBuf>,
+
+        /// Game servers that may run the script at once (unlimited if omitted)
+        #[arg(long, value_name = "N")]
+        max_servers: Option<u32>,
+
+        /// Players that may run the script at once (unlimited if omitted)
+        #[arg(long, value_name = "N")]
+        max_users: Option<u32>,
+    },
+
+    /// Build a per-customer protected copy of a script for every customer in a list
+    Release {
+        /// Input Luau script file
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// CSV file of customers (customer_id,license_key[,hwid])
+        #[arg(long, value_name = "CSV")]
+        customers: PathBuf,
+
+        /// Output directory for the builds, their symbol maps and manifest.json
+        #[arg(short, long, value_name = "DIR")]
+        output: PathBuf,
+
+        /// Obfuscation tier: basic, standard, or premium
+        #[arg(short, long, value_name = "TIER", default_value = "standard")]
+        tier: ObfuscationTier,
+
+        /// When --tier is above the tier of a customer's validated license:
+        /// downgrade the release to it or fail (default: config's
+        /// `tier_policy`)
+        #[arg(long, value_name = "POLICY", requires = "api_endpoint")]
+        tier_policy: Option<TierPolicy>,
+
+        /// API endpoint to validate every customer's license with
+        #[arg(long, value_name = "URL")]
+        api_endpoint: Option<String>,
+
+        /// Always validate the licenses online: ignore cached validations and
+        /// fail instead of falling back to offline mode
+        #[arg(long, requires = "api_endpoint")]
+        require_online: bool,
+
+        /// Tune the tier for a kind of script: admin-panel, gun-system,
+        /// ui-library, anti-cheat, loader (or a preset from the config)
+        #[arg(long, value_name = "PRESET")]
+        preset: Option<String>,
+
+        /// Seed the per-customer seeds derive from (for reproducible names and layout)
+        #[arg(long, value_name = "SEED")]
+        seed: Option<u64>,
+
+        /// Encryption password for every build (random per build when omitted)
+        #[arg(short, long, value_name = "PASSWORD")]
+        password: Option<String>,
+
+        /// Argon2id cost for deriving each build's key
+        #[arg(long, value_name = "PROFILE")]
+        kdf_profile: Option<KdfProfile>,
+
+        /// Builds to run at once (defaults to the number of CPUs)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<usize>,
+
+        /// Derive each customer's watermark from their root for project NAME
+        /// (use the same NAME for every file of a multi-file release)
+        #[arg(long, value_name = "NAME")]
+        watermark_project: Option<String>,
+    },
+
+    /// Estimate the runtime overhead of protecting a script, per tier
+    Estimate {
+        /// Luau script to estimate
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Only estimate this tier (defaults to comparing all tiers)
+        #[arg(short, long, value_name = "TIER")]
+        tier: Option<ObfuscationTier>,
+    },
+
+    /// Minify a script: strip comments and layout and rename locals, without
+    /// encryption or a license check (no license key needed)
+    Minify {
+        /// Luau script to minify
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Output file path
+        #[arg(short, long, value_name = "OUTPUT", required_unless_present = "stdout")]
+        output: Option<PathBuf>,
+
+        /// Write the minified script to stdout; everything else goes to stderr
+        #[arg(long, conflicts_with = "output")]
+        stdout: bool,
+
+        /// Keep every name; only strip comments and layout
+        #[arg(long)]
+        no_rename: bool,
+
+        /// How renamed locals look
+        #[arg(long, value_name = "STYLE", default_value = "sequential")]
+        naming_style: NamingStyle,
+    },
+
+    /// Show what protecting a script would work with: Roblox APIs, scopes,
+    /// control flow, string sensitivity and which names would be renamed
+    Analyze {
+        /// Luau script to analyze
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
     },
 
     /// Validate a protected script locally
@@ -68,7 +545,375 @@ pub enum Commands {
         /// Protected script file to validate
         #[arg(value_name = "FILE")]
         file: PathBuf,
+
+        /// Symbol map, to verify the signed metadata and encrypted data
+        #[arg(long, value_name = "MAP", requires = "password")]
+        symbol_map: Option<PathBuf>,
+
+        /// Password the script was protected with
+        #[arg(long, value_name = "PASSWORD", requires = "symbol_map")]
+        password: Option<String>,
+
+        /// Script id the watermark and license stub must name (defaults to
+        /// the symbol map's)
+        #[arg(long, value_name = "ID")]
+        script_id: Option<String>,
+    },
+
+    /// Audit a protected script against its original before shipping
+    Diff {
+        /// Original Luau script
+        #[arg(value_name = "ORIGINAL")]
+        original: PathBuf,
+
+        /// Protected output of ORIGINAL
+        #[arg(value_name = "PROTECTED")]
+        protected: PathBuf,
+    },
+
+    /// Score how recognizable a protected script's obfuscation is
+    Score {
+        /// Protected script file
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+
+    /// Trace leaked scripts back to the release builds they were copied
+    /// from, offline
+    InspectWatermark {
+        /// Directory of leaked scripts (.lua, .luau and .txt, searched
+        /// recursively)
+        #[arg(value_name = "DIR")]
+        leaks: PathBuf,
+
+        /// Release manifest or release output directory; repeatable
+        #[arg(long, value_name = "PATH")]
+        manifest: Vec<PathBuf>,
+
+        /// Customers CSV of a release, to verify watermarks against the
+        /// license keys; repeatable
+        #[arg(long, value_name = "FILE")]
+        customers: Vec<PathBuf>,
+
+        /// Write the report here: HTML for `.html`/`.htm`, JSON otherwise
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
     },
+
+    /// Recover readable source from your own protected script (owner only)
+    Recover {
+        /// Protected script file
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Symbol map written when the script was protected
+        #[arg(long, value_name = "MAP")]
+        symbol_map: PathBuf,
+
+        /// Password the script was protected with
+        #[arg(long, value_name = "PASSWORD")]
+        password: String,
+
+        /// Output file (defaults to <input>.recovered.lua)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+    },
+
+    /// Generate a Luau test script that checks a protected build in Studio
+    GenTests {
+        /// Protected script file
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Symbol map written when the script was protected
+        #[arg(long, value_name = "MAP")]
+        symbol_map: PathBuf,
+
+        /// Password the script was protected with
+        #[arg(long, value_name = "PASSWORD")]
+        password: String,
+
+        /// Output file (defaults to <input>.tests.lua)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+    },
+
+    /// Write deterministic watermark, encryption and stego test vectors, or
+    /// check that this build still computes a saved set
+    Vectors {
+        /// Write the vectors here instead of to stdout
+        #[arg(short, long, value_name = "OUTPUT", conflicts_with = "verify")]
+        output: Option<PathBuf>,
+
+        /// Vectors file to recompute; fails on any difference
+        #[arg(long, value_name = "FILE")]
+        verify: Option<PathBuf>,
+    },
+
+    /// Re-encrypt a protected script under a new password (key rotation)
+    Rekey {
+        /// Protected script file
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Symbol map written when the script was protected (rewritten with
+        /// the new salt unless --output is given)
+        #[arg(long, value_name = "MAP")]
+        symbol_map: PathBuf,
+
+        /// Password the script is currently protected with
+        #[arg(long, value_name = "PASSWORD")]
+        old_password: String,
+
+        /// Password to rotate to
+        #[arg(long, value_name = "PASSWORD")]
+        new_password: String,
+
+        /// Argon2id cost for the new key (defaults to the symbol map's)
+        #[arg(long, value_name = "PROFILE")]
+        kdf_profile: Option<KdfProfile>,
+
+        /// Output file (defaults to rewriting <input>); its symbol map goes
+        /// to <output>.map.json
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// Project keystore to record the new key version in
+        #[arg(long, value_name = "FILE", default_value = KEYSTORE_PATH)]
+        keystore: PathBuf,
+    },
+
+    /// Print the key share values a buyer must set (key delivery, see config)
+    Keygen {
+        /// Symbol map written when the script was protected
+        #[arg(long, value_name = "MAP")]
+        symbol_map: PathBuf,
+
+        /// Password the script was protected with
+        #[arg(long, value_name = "PASSWORD")]
+        password: String,
+
+        /// Buyer's license key the script was protected for
+        #[arg(short, long, value_name = "KEY")]
+        license_key: String,
+    },
+
+    /// Encrypt and sign a new version of a script for its loaders' update
+    /// channel, and upload it (or write it to a file)
+    PublishPayload {
+        /// Single-file protected build of the new version
+        #[arg(value_name = "SCRIPT")]
+        input: PathBuf,
+
+        /// Symbol map of the build whose loaders should take the update
+        #[arg(long, value_name = "MAP")]
+        symbol_map: PathBuf,
+
+        /// Password that build was protected with
+        #[arg(long, value_name = "PASSWORD")]
+        password: String,
+
+        /// Version number; must be above the version loaders embed
+        #[arg(long, value_name = "N")]
+        version: u32,
+
+        /// Write the signed envelope here for your own update endpoint
+        /// instead of uploading it
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Developer API key (defaults to the key saved with `auth login`)
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+    },
+
+    /// Refresh the Roblox API database used to preserve engine identifiers
+    UpdateApiDb {
+        /// Roblox version hash to fetch (defaults to the pinned or latest version)
+        #[arg(long, value_name = "VERSION")]
+        version: Option<String>,
+
+        /// Where to write the database (defaults to the cache directory)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+    },
+
+    /// Manage queued usage events
+    Telemetry {
+        #[command(subcommand)]
+        action: TelemetryAction,
+    },
+
+    /// Save, show or remove the developer API key
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+
+    /// Inspect issued licenses
+    License {
+        #[command(subcommand)]
+        action: LicenseAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TelemetryAction {
+    /// Upload the queued usage events now
+    Flush,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum AuthAction {
+    /// Save the API key in the OS keychain (or an encrypted file)
+    Login {
+        /// API key; read from stdin when omitted, which keeps it out of
+        /// shell history
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+    },
+    /// Show which API key API commands will use
+    Status,
+    /// Remove the saved API key
+    Logout,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum LicenseAction {
+    /// Show seat limits and the servers and players using a license
+    Seats {
+        /// License key to inspect
+        #[arg(value_name = "KEY", required_unless_present = "script_id")]
+        license_key: Option<String>,
+
+        /// Inspect every license of this script instead
+        #[arg(long, value_name = "ID", conflicts_with = "license_key")]
+        script_id: Option<String>,
+
+        /// Developer API key (defaults to the key saved with `auth login`)
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+
+        /// Only list licenses with no seat left
+        #[arg(long)]
+        full: bool,
+    },
+    /// Revoke a license key
+    Revoke {
+        /// License key to revoke
+        #[arg(value_name = "KEY")]
+        license_key: String,
+
+        /// Why the key is revoked (e.g. "leaked on a forum")
+        #[arg(long, value_name = "TEXT")]
+        reason: Option<String>,
+
+        /// Developer API key (defaults to the key saved with `auth login`)
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+    },
+    /// Download a script's signed revocation list for `protect --revocation-list`
+    Crl {
+        /// Script whose revoked keys to list
+        #[arg(long, value_name = "ID")]
+        script_id: String,
+
+        /// Where to write the list (defaults to `<script-id>.crl`)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// Developer API key (defaults to the key saved with `auth login`)
+        #[arg(long, value_name = "KEY")]
+        api_key: Option<String>,
+    },
+}
+
+impl Commands {
+    /// Subcommand name as typed on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Protect { .. } => "protect",
+            Commands::ProtectProject { .. } => "protect-project",
+            Commands::GenerateLicense { .. } => "generate-license",
+            Commands::Release { .. } => "release",
+            Commands::Estimate { .. } => "estimate",
+            Commands::Minify { .. } => "minify",
+            Commands::Analyze { .. } => "analyze",
+            Commands::Validate { .. } => "validate",
+            Commands::Diff { .. } => "diff",
+            Commands::Score { .. } => "score",
+            Commands::InspectWatermark { .. } => "inspect-watermark",
+            Commands::Recover { .. } => "recover",
+            Commands::GenTests { .. } => "gen-tests",
+            Commands::Vectors { .. } => "vectors",
+            Commands::Rekey { .. } => "rekey",
+            Commands::Keygen { .. } => "keygen",
+            Commands::PublishPayload { .. } => "publish-payload",
+            Commands::UpdateApiDb { .. } => "update-api-db",
+            Commands::Telemetry { .. } => "telemetry",
+            Commands::Auth { .. } => "auth",
+            Commands::License { .. } => "license",
+        }
+    }
+
+    /// Paths to watch if `--watch` was given
+    pub fn watch_target(&self) -> Option<WatchTarget> {
+        match self {
+            Commands::Protect {
+                input: Some(input),
+                output,
+                output_loader,
+                output_payload,
+                watch: true,
+                ..
+            } => Some(WatchTarget {
+                paths: vec![input.clone()],
+                ignore: [output, output_loader, output_payload]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect(),
+            }),
+            Commands::ProtectProject {
+                project,
+                output,
+                remote_map,
+                watch: true,
+                ..
+            } => {
+                let root = if project.is_dir() {
+                    project.clone()
+                } else {
+                    project.parent().map(PathBuf::from).unwrap_or_default()
+                };
+                Some(WatchTarget {
+                    paths: vec![root],
+                    ignore: std::iter::once(output).chain(remote_map).cloned().collect(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parse `25%` (or a bare fraction like `0.25`) into a fraction
+fn parse_percent(value: &str) -> Result<f32, String> {
+    let (number, scale) = match value.trim().strip_suffix('%') {
+        Some(percent) => (percent, 100.0),
+        None => (value.trim(), 1.0),
+    };
+    match number.trim().parse::<f32>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok(n / scale),
+        _ => Err(format!("expected a percentage like 25%, got {:?}", value)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// Structured JSON on stdout (status, stats, timing, warnings)
+    Json,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
@@ -81,6 +926,223 @@ pub enum ObfuscationTier {
     Premium,
 }
 
+impl From<ObfuscationTier> for crate::obfuscation::ObfuscationTier {
+    fn from(tier: ObfuscationTier) -> Self {
+        match tier {
+            ObfuscationTier::Basic => crate::obfuscation::ObfuscationTier::Basic,
+            ObfuscationTier::Standard => crate::obfuscation::ObfuscationTier::Standard,
+            ObfuscationTier::Premium => crate::obfuscation::ObfuscationTier::Premium,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum TierPolicy {
+    /// Build at the licensed tier, with a warning
+    Downgrade,
+    /// Fail with a license error
+    Fail,
+}
+
+impl From<TierPolicy> for crate::obfuscation::TierPolicy {
+    fn from(policy: TierPolicy) -> Self {
+        match policy {
+            TierPolicy::Downgrade => crate::obfuscation::TierPolicy::Downgrade,
+            TierPolicy::Fail => crate::obfuscation::TierPolicy::Fail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum KdfProfile {
+    /// 19 MB, 2 passes (fast; for local iteration)
+    Interactive,
+    /// 64 MB, 3 passes
+    Balanced,
+    /// 256 MB, 4 passes (release builds)
+    Paranoid,
+}
+
+impl From<KdfProfile> for crate::crypto::KdfParams {
+    fn from(profile: KdfProfile) -> Self {
+        match profile {
+            KdfProfile::Interactive => crate::crypto::KdfParams::interactive(),
+            KdfProfile::Balanced => crate::crypto::KdfParams::balanced(),
+            KdfProfile::Paranoid => crate::crypto::KdfParams::paranoid(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum NamingStyle {
+    /// `_0x1f` (compact, easy to spot)
+    Hex,
+    /// Ordinary-looking word pairs like `cachedHandler`
+    Dictionary,
+    /// ASCII emoticons like `_o_O_T_T`
+    Emoji,
+    /// Look-alike characters like `lIl1I1`
+    Confusable,
+    /// Shortest names: `a`, `b`, ..., `aa`
+    Sequential,
+    /// Engine-style names like `PlayersService2`
+    RobloxLike,
+}
+
+impl From<NamingStyle> for crate::obfuscation::NamingStyle {
+    fn from(style: NamingStyle) -> Self {
+        match style {
+            NamingStyle::Hex => crate::obfuscation::NamingStyle::Hex,
+            NamingStyle::Dictionary => crate::obfuscation::NamingStyle::Dictionary,
+            NamingStyle::Emoji => crate::obfuscation::NamingStyle::Emoji,
+            NamingStyle::Confusable => crate::obfuscation::NamingStyle::Confusable,
+            NamingStyle::Sequential => crate::obfuscation::NamingStyle::Sequential,
+            NamingStyle::RobloxLike => crate::obfuscation::NamingStyle::RobloxLike,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum TableKeys {
+    /// Rename keys like identifiers (`t.timeout` → `t._0x3`)
+    Mangle,
+    /// Look keys up through the encrypted string table (`t[_decrypt(3)]`)
+    Encrypt,
+}
+
+impl From<TableKeys> for crate::obfuscation::TableKeyMode {
+    fn from(mode: TableKeys) -> Self {
+        match mode {
+            TableKeys::Mangle => crate::obfuscation::TableKeyMode::Mangle,
+            TableKeys::Encrypt => crate::obfuscation::TableKeyMode::Encrypt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum CompressionArg {
+    None,
+    /// Small, fast decompressor
+    Lz4,
+    /// Better ratio, larger and slower decompressor
+    Zlib,
+}
+
+impl From<CompressionArg> for crate::crypto::Compression {
+    fn from(compression: CompressionArg) -> Self {
+        match compression {
+            CompressionArg::None => crate::crypto::Compression::None,
+            CompressionArg::Lz4 => crate::crypto::Compression::Lz4,
+            CompressionArg::Zlib => crate::crypto::Compression::Zlib,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum DataEncoding {
+    /// Escaped strings for small scripts, base85 for very large ones, else base64
+    Auto,
+    Base64,
+    /// Smallest, decoded by an extra runtime function
+    Base85,
+    /// `\ddd` escapes decoded by the Luau compiler; larger source, no decode at load
+    Escaped,
+    /// Byte arrays; largest, keeps ciphertext out of string literals
+    Numeric,
+}
+
+impl From<DataEncoding> for crate::codegen::BinaryEncoding {
+    fn from(encoding: DataEncoding) -> Self {
+        match encoding {
+            DataEncoding::Auto => crate::codegen::BinaryEncoding::Auto,
+            DataEncoding::Base64 => crate::codegen::BinaryEncoding::Base64,
+            DataEncoding::Base85 => crate::codegen::BinaryEncoding::Base85,
+            DataEncoding::Escaped => crate::codegen::BinaryEncoding::Escaped,
+            DataEncoding::Numeric => crate::codegen::BinaryEncoding::Numeric,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum AntiDump {
+    /// Cache decrypted strings as plaintext
+    Off,
+    /// Cache them XOR-masked, unmasked on every use
+    Masked,
+    /// Re-mask under a fresh key after every use
+    Rolling,
+}
+
+impl From<AntiDump> for crate::codegen::DumpProtection {
+    fn from(mode: AntiDump) -> Self {
+        match mode {
+            AntiDump::Off => crate::codegen::DumpProtection::Off,
+            AntiDump::Masked => crate::codegen::DumpProtection::Masked,
+            AntiDump::Rolling => crate::codegen::DumpProtection::Rolling,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExecutorResponse {
+    /// Stop through the tamper policy
+    Block,
+    /// Keep running with some decrypted values corrupted
+    Degrade,
+    /// Keep running and only send a violation report
+    Report,
+}
+
+impl From<ExecutorResponse> for crate::codegen::ExecutorResponse {
+    fn from(response: ExecutorResponse) -> Self {
+        match response {
+            ExecutorResponse::Block => crate::codegen::ExecutorResponse::Block,
+            ExecutorResponse::Degrade => crate::codegen::ExecutorResponse::Degrade,
+            ExecutorResponse::Report => crate::codegen::ExecutorResponse::Report,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum RunContext {
+    /// Server Scripts; refuses to run on a client
+    Server,
+    /// LocalScripts; refuses to run on the server
+    Client,
+    /// Runs on either side
+    Shared,
+}
+
+impl From<RunContext> for crate::codegen::RunContext {
+    fn from(context: RunContext) -> Self {
+        match context {
+            RunContext::Server => crate::codegen::RunContext::Server,
+            RunContext::Client => crate::codegen::RunContext::Client,
+            RunContext::Shared => crate::codegen::RunContext::Shared,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum InvalidBytes {
+    /// Stop with the line and column of the first one
+    Error,
+    /// Write each as a `\ddd` escape (string literals keep their bytes)
+    Escape,
+    /// Read each as a Latin-1 character
+    Latin1,
+}
+
+impl From<InvalidBytes> for crate::utils::source::InvalidBytes {
+    fn from(mode: InvalidBytes) -> Self {
+        match mode {
+            InvalidBytes::Error => crate::utils::source::InvalidBytes::Error,
+            InvalidBytes::Escape => crate::utils::source::InvalidBytes::Escape,
+            InvalidBytes::Latin1 => crate::utils::source::InvalidBytes::Latin1,
+        }
+    }
+}
+
 impl std::fmt::Display for ObfuscationTier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
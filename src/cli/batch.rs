@@ -0,0 +1,412 @@
+//! Bulk license generation from CSV buyer lists
+//!
+//! Every key is issued by the license API. There is no offline signer:
+//! protected scripts validate their key by asking the API, which only knows
+//! keys it issued, so a locally signed key would fail at runtime.
+
+use crate::api::{ApiClient, GenerateLicenseRequest, SeatLimits};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single buyer row read from the batch CSV
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuyerRecord {
+    /// Buyer's Roblox UserId
+    pub buyer_userid: u64,
+    /// Optional per-row expiration (ISO 8601)
+    pub expiration: Option<String>,
+    /// Optional per-row license tier
+    pub tier: Option<String>,
+    /// Line number in the source CSV (for error reporting)
+    pub line: usize,
+}
+
+/// Outcome of generating a license for one buyer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchEntry {
+    pub buyer_userid: u64,
+    pub license_key: Option<String>,
+    pub expiration: Option<String>,
+    pub tier: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Result of a full batch run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub script_id: String,
+    pub entries: Vec<BatchEntry>,
+}
+
+impl BatchReport {
+    /// Entries that produced a license key
+    pub fn succeeded(&self) -> impl Iterator<Item = &BatchEntry> {
+        self.entries.iter().filter(|e| e.error.is_none())
+    }
+
+    /// Entries that failed after all retries
+    pub fn failed(&self) -> impl Iterator<Item = &BatchEntry> {
+        self.entries.iter().filter(|e| e.error.is_some())
+    }
+}
+
+/// Parse a buyers CSV file
+///
+/// The first row may be a header (`buyer_userid,expiration,tier`). Columns
+/// after the UserId are optional; empty cells fall back to the command-line
+/// defaults. Fields may be quoted the way `write_retry_file` quotes them.
+pub fn parse_buyers_csv(content: &str) -> Result<Vec<BuyerRecord>> {
+    let mut records = Vec::new();
+
+    for (idx, row) in csv_rows(content)?.into_iter().enumerate() {
+        let fields = row.fields;
+
+        // Skip header row
+        if idx == 0 && fields[0].parse::<u64>().is_err() {
+            continue;
+        }
+
+        let buyer_userid = fields[0].parse::<u64>().map_err(|_| {
+            ObfuscatorError::ConfigError(format!(
+                "Invalid buyer UserId on line {}: {:?}",
+                row.line, fields[0]
+            ))
+        })?;
+
+        let optional = |i: usize| fields.get(i).filter(|f| !f.is_empty()).cloned();
+
+        records.push(BuyerRecord {
+            buyer_userid,
+            expiration: optional(1),
+            tier: optional(2),
+            line: row.line,
+        });
+    }
+
+    Ok(records)
+}
+
+/// One CSV record and the line it starts on
+struct CsvRow {
+    line: usize,
+    fields: Vec<String>,
+}
+
+/// Split CSV into records, skipping blank and `#` comment lines
+///
+/// Unquoted fields are trimmed. Quoted fields keep their content as is,
+/// with `""` standing for a quote and separators and newlines allowed.
+fn csv_rows(content: &str) -> Result<Vec<CsvRow>> {
+    let mut rows = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut row_line = 1;
+
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+
+        match c {
+            '"' if !quoted && field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+                in_quotes = true;
+            }
+            '#' if fields.is_empty() && !quoted && field.trim().is_empty() => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ',' => fields.push(end_field(&mut field, &mut quoted)),
+            '\n' => {
+                fields.push(end_field(&mut field, &mut quoted));
+                push_row(&mut rows, std::mem::take(&mut fields), row_line);
+                line += 1;
+                row_line = line;
+            }
+            '\r' => {}
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(ObfuscatorError::ConfigError(format!(
+            "Unterminated quoted field on line {}",
+            row_line
+        ))
+        .into());
+    }
+    if quoted || !field.is_empty() || !fields.is_empty() {
+        fields.push(end_field(&mut field, &mut quoted));
+        push_row(&mut rows, fields, row_line);
+    }
+
+    Ok(rows)
+}
+
+fn end_field(field: &mut String, quoted: &mut bool) -> String {
+    let value = std::mem::take(field);
+    if std::mem::take(quoted) {
+        value
+    } else {
+        value.trim().to_string()
+    }
+}
+
+fn push_row(rows: &mut Vec<CsvRow>, fields: Vec<String>, line: usize) {
+    if fields.len() > 1 || !fields[0].is_empty() {
+        rows.push(CsvRow { line, fields });
+    }
+}
+
+/// Generate a license for one buyer, recording failures instead of aborting
+pub fn generate_entry(
+    api_client: &ApiClient,
+    api_key: &str,
+    script_id: &str,
+    record: &BuyerRecord,
+    default_expiration: Option<&str>,
+    seats: SeatLimits,
+) -> BatchEntry {
+    let expiration = record
+        .expiration
+        .clone()
+        .or_else(|| default_expiration.map(|s| s.to_string()));
+
+    let request = GenerateLicenseRequest {
+        api_key: api_key.to_string(),
+        script_id: script_id.to_string(),
+        buyer_userid: record.buyer_userid,
+        expiration: expiration.clone(),
+        tier: record.tier.clone(),
+        hwid_restrictions: None,
+        seats,
+    };
+
+    // ApiClient already retries with backoff; anything left is a hard failure
+    match api_client.generate_license(request) {
+        Ok(response) => {
+            info!("✓ License generated for buyer {}", record.buyer_userid);
+            BatchEntry {
+                buyer_userid: record.buyer_userid,
+                license_key: Some(response.license_key),
+                expiration,
+                tier: record.tier.clone(),
+                error: None,
+            }
+        }
+        Err(e) => {
+            warn!(
+                "Failed to generate license for buyer {} (line {}): {}",
+                record.buyer_userid, record.line, e
+            );
+            BatchEntry {
+                buyer_userid: record.buyer_userid,
+                license_key: None,
+                expiration,
+                tier: record.tier.clone(),
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+/// Write the batch report as JSON (`.json` extension) or CSV (anything else)
+pub fn write_report(report: &BatchReport, path: &Path) -> Result<()> {
+    let is_json = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let content = if is_json {
+        serde_json::to_string_pretty(report).context("Failed to serialize batch report")?
+    } else {
+        let mut csv = String::from("buyer_userid,license_key,expiration,tier,error\n");
+        for entry in &report.entries {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                entry.buyer_userid,
+                csv_field(entry.license_key.as_deref()),
+                csv_field(entry.expiration.as_deref()),
+                csv_field(entry.tier.as_deref()),
+                csv_field(entry.error.as_deref()),
+            ));
+        }
+        csv
+    };
+
+    fs::write(path, content)
+        .with_context(|| format!("Failed to write batch report: {:?}", path))
+}
+
+/// Write failed rows back out in input format so they can be re-run with `--batch`
+pub fn write_retry_file(report: &BatchReport, output: &Path) -> Result<Option<PathBuf>> {
+    let failed: Vec<&BatchEntry> = report.failed().collect();
+    if failed.is_empty() {
+        return Ok(None);
+    }
+
+    let mut csv = String::from("buyer_userid,expiration,tier\n");
+    for entry in failed {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            entry.buyer_userid,
+            csv_field(entry.expiration.as_deref()),
+            csv_field(entry.tier.as_deref()),
+        ));
+    }
+
+    let retry_path = output.with_extension("retry.csv");
+    fs::write(&retry_path, csv)
+        .with_context(|| format!("Failed to write retry file: {:?}", retry_path))?;
+
+    Ok(Some(retry_path))
+}
+
+/// Quote a CSV field when it contains separators or quotes, or would lose
+/// surrounding whitespace when read back
+fn csv_field(value: Option<&str>) -> String {
+    match value {
+        None => String::new(),
+        Some(v) if v.contains([',', '"', '\n', '\r']) || v.trim() != v => {
+            format!("\"{}\"", v.replace('"', "\"\""))
+        }
+        Some(v) => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_buyers_with_header() {
+        let csv = "buyer_userid,expiration,tier\n\
+                   123,2026-12-31T00:00:00Z,premium\n\
+                   456,,\n";
+
+        let records = parse_buyers_csv(csv).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].buyer_userid, 123);
+        assert_eq!(records[0].tier.as_deref(), Some("premium"));
+        assert_eq!(records[1].expiration, None);
+        assert_eq!(records[1].line, 3);
+    }
+
+    #[test]
+    fn test_parse_buyers_without_header() {
+        let records = parse_buyers_csv("111\n# comment\n222\n").unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].buyer_userid, 222);
+    }
+
+    #[test]
+    fn test_parse_buyers_invalid_userid() {
+        let result = parse_buyers_csv("buyer_userid\n123\nnot-a-number\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field(None), "");
+        assert_eq!(csv_field(Some("plain")), "plain");
+        assert_eq!(csv_field(Some("a,b")), "\"a,b\"");
+        assert_eq!(csv_field(Some("say \"hi\"")), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_parse_buyers_quoted_fields() {
+        let csv = "buyer_userid,expiration,tier\n\
+                   \"123\", \"2026-12-31, noon\",\"gold \"\"plus\"\"\"\n\
+                   456,,\"two\nlines\"\n\
+                   789\n";
+
+        let records = parse_buyers_csv(csv).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].expiration.as_deref(), Some("2026-12-31, noon"));
+        assert_eq!(records[0].tier.as_deref(), Some("gold \"plus\""));
+        assert_eq!(records[1].tier.as_deref(), Some("two\nlines"));
+        assert_eq!(records[2].line, 5);
+        assert!(parse_buyers_csv("123,\"open\n").is_err());
+    }
+
+    #[test]
+    fn test_retry_file_round_trip() {
+        let failed = |buyer_userid, expiration: Option<&str>, tier: Option<&str>| BatchEntry {
+            buyer_userid,
+            license_key: None,
+            expiration: expiration.map(str::to_string),
+            tier: tier.map(str::to_string),
+            error: Some("timeout".to_string()),
+        };
+        let report = BatchReport {
+            script_id: "script".to_string(),
+            entries: vec![
+                failed(1, Some("2026-12-31"), Some("a, \"quoted\" tier")),
+                failed(2, None, Some("multi\nline")),
+                failed(3, None, None),
+            ],
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let retry = write_retry_file(&report, &dir.path().join("licenses.csv"))
+            .unwrap()
+            .unwrap();
+        let records = parse_buyers_csv(&fs::read_to_string(retry).unwrap()).unwrap();
+
+        assert_eq!(records.len(), report.entries.len());
+        for (record, entry) in records.iter().zip(&report.entries) {
+            assert_eq!(record.buyer_userid, entry.buyer_userid);
+            assert_eq!(record.expiration, entry.expiration);
+            assert_eq!(record.tier, entry.tier);
+        }
+    }
+
+    #[test]
+    fn test_report_partitions_failures() {
+        let report = BatchReport {
+            script_id: "script".to_string(),
+            entries: vec![
+                BatchEntry {
+                    buyer_userid: 1,
+                    license_key: Some("KEY".to_string()),
+                    expiration: None,
+                    tier: None,
+                    error: None,
+                },
+                BatchEntry {
+                    buyer_userid: 2,
+                    license_key: None,
+                    expiration: None,
+                    tier: None,
+                    error: Some("timeout".to_string()),
+                },
+            ],
+        };
+
+        assert_eq!(report.succeeded().count(), 1);
+        assert_eq!(report.failed().count(), 1);
+    }
+}
@@ -1,145 +1,905 @@
 //! Command execution logic
 
-use super::args::{Cli, Commands};
+use super::args::{
+    AuthAction, Cli, Commands, KdfProfile, LicenseAction, OutputFormat, TelemetryAction,
+    TierPolicy,
+};
+use super::batch;
+use super::output::{
+    AnalyzeResult, AuthResult, BarProgress, BatchResult, DiffResult, GenTestsResult,
+    InspectWatermarkResult, KeyShare, KeygenResult, LicenseResult, LicenseSeatsResult,
+    MinifyResult, ParseStats, ProtectResult, PublishPayloadResult, RekeyResult, ReleaseResult,
+    Reporter, RevocationListResult, RevokeResult, ScoreResult, SeatUsage, ValidateResult,
+    VectorsResult,
+};
+use super::review;
+use super::watch;
 use crate::api::{
-    create_client, ApiClient, GenerateLicenseRequest, TrackObfuscationRequest,
-    ValidateLicenseRequest,
+    create_client, ApiClient, ApiCredential, CredentialSource, CredentialStore, EventQueue,
+    FlushReport, GenerateLicenseRequest, LicenseSeatsRequest, PlaceOwner, PublishPayloadRequest,
+    RevocationListRequest, RevokeLicenseRequest, RobloxCloudClient, SeatLimits, TokenCache,
+    TrackObfuscationRequest, ValidateLicenseRequest, VerifiedPlace, API_KEY_ENV,
+    DEFAULT_TOKEN_TTL_SECONDS, MAX_ATTEMPTS, OPEN_CLOUD_API_KEY_ENV,
+};
+use crate::analysis::{AnalysisOptions, Analyzer, ApiDatabase, DynamicGlobal};
+use crate::codegen::{
+    load_template_dir, AntiDumpPolicy, CodeGenConfig, ExecutorCheck, PayloadFile, PayloadSource,
+    PayloadUpdate, UpdateChannel, UpdateKeys, PAYLOAD_MODULE,
+};
+use crate::obfuscation::{
+    HotFunctions, Minifier, ObfuscationTier, OverheadEstimator, ReviewPlan,
+    ASSUMED_ITERATIONS_PER_FRAME, SCRIPT_SOURCE_BUDGET,
 };
-use crate::parser::LuauParser;
+use crate::pipeline::{ObfuscationPipeline, PipelineSettings};
+use crate::parser::{LuauParser, Sensitivity, TypeAnnotations};
+use crate::crypto::{
+    derive_script_id, CryptoContext, KdfParams, Keystore, RevocationList, TestVectors,
+};
+use crate::diagnostics::DenyRules;
+use crate::project::{
+    Bundler, ModelFormat, ProjectObfuscator, RobloxModel, RojoProject, DEFAULT_PROJECT_FILE,
+};
+use crate::recovery::{
+    CheckStatus, DetectabilityAnalyzer, LeakInspector, Rekeyer, ScriptDiff, ScriptRecovery,
+    ScriptValidator, SymbolMap, TestHarness,
+};
+use crate::release::{parse_customers_csv, ManifestEntry, ReleaseManifest, ReleaseMatrix};
+use crate::utils::config::Config;
 use crate::utils::errors::ObfuscatorError;
+use crate::utils::source::{InputOptions, InvalidBytes, LineEnding, SourceFile};
+use crate::verify::Sandbox;
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
-use log::{info, warn};
+use log::{debug, info, warn};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::Zeroizing;
 
 /// Execute the CLI command
 pub fn execute(cli: Cli) -> Result<()> {
-    match cli.command {
+    // Flushes the trace when dropped, after the command (or the watch loop) ends
+    let _trace = cli.trace_output.as_deref().map(start_trace).transpose()?;
+    let telemetry = !cli.no_telemetry;
+    if let Some(target) = cli.command.watch_target() {
+        return watch::watch(target, || run_once(cli.command.clone(), cli.format, telemetry));
+    }
+    run_once(cli.command, cli.format, telemetry)
+}
+
+/// Record stage and pass spans to `path` in Chrome's trace event format
+fn start_trace(path: &Path) -> Result<tracing_chrome::FlushGuard> {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let file = fs::File::create(path)
+        .with_context(|| format!("Failed to create trace file {:?}", path))?;
+    let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(true)
+        .build();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .context("A tracing subscriber is already installed")?;
+    Ok(guard)
+}
+
+/// Run a command once, reporting its result in the requested format
+///
+/// `telemetry` is off with `--no-telemetry`.
+fn run_once(command: Commands, format: OutputFormat, telemetry: bool) -> Result<()> {
+    let mut reporter = Reporter::new(format);
+    let name = command.name();
+
+    let result = run_command(command, &mut reporter, telemetry);
+    if let Err(e) = &result {
+        reporter.fail(name, e)?;
+    }
+    result
+}
+
+fn run_command(command: Commands, reporter: &mut Reporter, telemetry: bool) -> Result<()> {
+    match command {
         Commands::Protect {
             input,
+            stdin,
+            bundle,
+            entry,
             output,
+            stdout,
+            output_loader,
+            output_payload,
             license_key,
+            script_id,
+            project,
+            watermark_project,
             hwid,
+            place_id,
+            verify_place,
             tier,
+            tier_policy,
+            preset,
             api_endpoint,
+            require_online,
+            revocation_list,
+            seed,
+            streaming,
+            strip_types,
+            watch: _,
+            interactive,
+            payload_url,
+            payload_output,
+            update_url,
+            payload_version,
+            symbol_map,
+            hot_functions,
+            max_overhead,
+            compress,
+            data_encoding,
+            naming_style,
+            table_keys,
+            deny_dynamic_globals,
+            no_lint,
+            best_effort,
+            simplify,
+            deny,
+            verify,
+            plaintext,
+            keep_comments,
+            invalid_bytes,
+            license_header,
+            force_optimize,
+            encode_config,
+            anti_dump,
+            executor_check,
+            context,
+            templates,
+            password,
+            kdf_profile,
+            keystore: keystore_path,
+            encrypt_keystore,
         } => {
+            let deny = DenyRules::parse(&deny)?;
+            // clap requires one of the three
+            let output = output_loader.or(output);
+            if output.is_none() && !stdout {
+                return Err(ObfuscatorError::ConfigError("No output path given".to_string()).into());
+            }
+            if stdout {
+                // stdout carries only the script
+                reporter.use_stderr();
+            }
+            let origin = input
+                .clone()
+                .or_else(|| bundle.clone())
+                .unwrap_or_else(|| PathBuf::from("<stdin>"));
+
             info!("Starting protection process");
-            info!("  Input: {:?}", input);
-            info!("  Output: {:?}", output);
+            info!("  Input: {:?}", origin);
+            match &output {
+                Some(path) => info!("  Output: {:?}", path),
+                None => info!("  Output: stdout"),
+            }
+            if let Some(ref path) = output_payload {
+                info!("  Payload chunk: {:?}", path);
+            }
             info!("  Tier: {}", tier);
+            if let Some(seed) = seed {
+                info!("  Deterministic seed: {}", seed);
+            }
+            let hot_functions = hot_functions
+                .as_deref()
+                .map(HotFunctions::load)
+                .transpose()?;
+            let preset = match &preset {
+                Some(name) => {
+                    let preset = Config::load()?.preset(name)?;
+                    info!("  Preset: {} ({})", name, preset.description);
+                    Some(preset)
+                }
+                None => None,
+            };
+            if verify_place {
+                // Before any work, so a mistyped PlaceId fails fast
+                let place_id = place_id.expect("clap requires --place-id");
+                let place = verify_roblox_place(place_id, &Config::load()?)?;
+                info!("  Place: {} ({}) in universe {}", place.name, place_id, place.universe_id);
+            }
 
             // Create progress bar
-            let pb = ProgressBar::new(6);
-            pb.set_style(
-                ProgressStyle::default_bar()
-                    .template("{msg} [{bar:40.cyan/blue}] {pos}/{len}")
-                    .unwrap()
-                    .progress_chars("#>-"),
-            );
+            let pb = reporter.progress_bar(6);
+
+            // Step 1: Read input file
+            pb.set_message("Reading input file...");
+            let mut input_options = Config::load()?.input;
+            if let Some(mode) = invalid_bytes {
+                input_options.invalid_bytes = mode.into();
+            }
+            let (source_file, bundled_project) = match (&bundle, &input) {
+                (Some(path), _) => {
+                    let (file, name) =
+                        bundle_project(path, entry.as_deref(), &input_options, reporter)?;
+                    (file, Some(name))
+                }
+                (None, Some(path)) => (SourceFile::read(path, &input_options)?, None),
+                (None, None) => (SourceFile::read_stdin(&input_options)?, None),
+            };
+            report_input(&source_file, &input_options, reporter);
+            let source = source_file.text.clone();
+            let input_bytes = source.len();
+            let script_id = script_id.unwrap_or_else(|| {
+                let project = project
+                    .or(bundled_project)
+                    .unwrap_or_else(|| project_name(input.as_deref()));
+                derive_script_id(&source, &project)
+            });
+            info!("  Script ID: {}", script_id);
+            pb.inc(1);
 
-            // Step 1: Validate license with API (if endpoint provided)
-            if let Some(ref endpoint) = api_endpoint {
+            // Only looked up when the API is used
+            let api_key = match api_endpoint {
+                Some(_) => api_key_or_empty(&Config::load()?)?,
+                None => Zeroizing::new(String::new()),
+            };
+
+            // Step 2: Validate license with API (if endpoint provided)
+            let licensed_tier = if let Some(ref endpoint) = api_endpoint {
                 pb.set_message("Validating license with API...");
                 
-                let api_client = create_client(endpoint)
-                    .context("Failed to create API client")?;
-                
                 let validation_request = ValidateLicenseRequest {
-                    api_key: "".to_string(), // TODO: Get from config
+                    api_key: api_key.to_string(),
                     license_key: license_key.clone(),
-                    script_id: "test_script".to_string(), // TODO: Generate from input file
+                    script_id: script_id.clone(),
                     hwid: hwid.map(|h| h.to_string()),
                     watermark: None,
                 };
-                
-                match api_client.validate_license(validation_request) {
-                    Ok(response) => {
-                        if !response.valid {
-                            return Err(ObfuscatorError::ApiError(
-                                format!("License validation failed: {}", 
-                                    response.error.unwrap_or_else(|| "Unknown error".to_string()))
-                            ).into());
-                        }
-                        info!("✓ License validated successfully");
-                    }
-                    Err(e) => {
-                        warn!("License validation failed (continuing anyway): {}", e);
-                        warn!("Running in offline mode");
-                    }
-                }
+                let licensed_tier =
+                    validate_license(endpoint, &validation_request, require_online, reporter)?;
                 
                 pb.inc(1);
+                licensed_tier
             } else {
                 info!("No API endpoint provided - skipping online validation");
                 pb.set_message("Skipping online validation...");
                 pb.inc(1);
-            }
+                None
+            };
 
-            // Step 2: Read input file
-            pb.set_message("Reading input file...");
-            let source = fs::read_to_string(&input)
-                .with_context(|| format!("Failed to read input file: {:?}", input))?;
-            pb.inc(1);
+            // Never build above the tier the license was sold at
+            let tier = entitled_tier(tier.into(), licensed_tier.as_deref(), tier_policy, reporter)?;
+
+            // Known-leaked keys: never build for one, and reject the rest offline
+            let revoked_keys = match &revocation_list {
+                Some(path) => load_revocation_list(path, &script_id, &license_key)?,
+                None => Vec::new(),
+            };
+            if !revoked_keys.is_empty() && api_endpoint.is_none() {
+                reporter.warn(
+                    "The revocation list is only embedded in the license check, which needs \
+                     --api-endpoint"
+                        .to_string(),
+                );
+            }
 
             // Step 3: Parse Luau script
             pb.set_message("Parsing Luau script...");
-            let parser = LuauParser::new();
-            let parse_result = parser
-                .parse(&source)
-                .map_err(|e| ObfuscatorError::ParseError(e.to_string()))?;
+            let config = Config::load()?;
+            let parser = LuauParser::with_sensitivity_rules(config.sensitivity_rules()?);
+            let parse_result = if streaming {
+                parser.parse_streaming(&source)
+            } else {
+                parser.parse(&source)
+            }
+            .map_err(|e| with_origin(e, &origin))?;
             pb.inc(1);
 
             info!("Parse successful:");
             info!("  - Strings found: {}", parse_result.strings.len());
             info!("  - Numbers found: {}", parse_result.numbers.len());
             info!("  - Functions found: {}", parse_result.functions.len());
+            info!("  - Type aliases found: {}", parse_result.type_aliases.len());
 
-            // Step 4: Analyze (placeholder)
-            pb.set_message("Analyzing code structure...");
-            // TODO: Implement analysis engine
-            pb.inc(1);
+            let license_header = match &license_header {
+                Some(path) => Some(fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read license header template: {:?}", path)
+                })?),
+                None => config.license_header.clone(),
+            };
+            let template_overrides = match templates.as_ref().or(config.templates_dir.as_ref()) {
+                Some(dir) => {
+                    let overrides = load_template_dir(dir)?;
+                    info!("  Template overrides: {} from {:?}", overrides.len(), dir);
+                    overrides
+                }
+                None => Default::default(),
+            };
 
-            // Step 5: Obfuscate (placeholder)
+            // Steps 4-5: Analyze and obfuscate
             pb.set_message("Applying obfuscation...");
-            // TODO: Implement obfuscation transformations
-            pb.inc(1);
+            let settings = PipelineSettings {
+                tier,
+                obfuscation: preset.as_ref().map(|preset| preset.settings(tier.settings())),
+                analysis: AnalysisOptions {
+                    deny_dynamic_globals,
+                    ..Default::default()
+                },
+                seed,
+                streaming,
+                hot_functions: hot_functions.unwrap_or_default(),
+                max_overhead,
+                split: output_payload.is_some(),
+                naming_style: naming_style.into(),
+                compression: compress.into(),
+                table_keys: table_keys.map(Into::into),
+                lint: !no_lint,
+                exclusions: match &preset {
+                    Some(preset) => preset.exclusions(&source)?,
+                    None => Default::default(),
+                },
+                plaintext_rules: config.plaintext_rules(&plaintext)?,
+                best_effort,
+                simplify,
+                api_db: api_database(&config),
+                ..Default::default()
+            };
+            let mut codegen_config = CodeGenConfig {
+                license_key: license_key.clone(),
+                script_id: script_id.clone(),
+                licensed_tier: licensed_tier.clone(),
+                api_endpoint: api_endpoint.clone().unwrap_or_default(),
+                hwid,
+                place_id,
+                binding_mode: match (hwid, place_id) {
+                    (Some(_), Some(_)) => "both",
+                    (None, Some(_)) => "placeid",
+                    _ => "userid",
+                }
+                .to_string(),
+                include_license: api_endpoint.is_some(),
+                revoked_keys,
+                include_hwid: hwid.is_some() || place_id.is_some(),
+                type_annotations: if strip_types {
+                    TypeAnnotations::Strip
+                } else {
+                    TypeAnnotations::Preserve
+                },
+                remote_payload: payload_url.clone().map(|url| PayloadSource::Url { url }),
+                update_channel: update_url
+                    .clone()
+                    .map(|url| UpdateChannel::new(url, payload_version)),
+                watermark_project,
+                key_delivery: config.key_delivery,
+                scrub_comments: !keep_comments,
+                license_header,
+                force_optimize: force_optimize || config.force_optimize,
+                encode_config: encode_config || config.encode_config,
+                run_context: context.into(),
+                binary_encoding: data_encoding.into(),
+                template_overrides,
+                ..Default::default()
+            };
+            if let Some(preset) = &preset {
+                preset.apply_codegen(&mut codegen_config);
+            }
+            if let Some(mode) = anti_dump {
+                codegen_config.anti_dump = AntiDumpPolicy::high_only(mode.into());
+            }
+            if let Some(response) = executor_check {
+                codegen_config.executor_check = Some(ExecutorCheck::new(response.into()));
+            }
+            let crypto_ctx = match password {
+                Some(password) => {
+                    let mut keystore = Keystore::load(&keystore_path, Some(&password))?;
+                    let crypto_ctx =
+                        keystore_context(&keystore, &script_id, &password, kdf_profile)?;
+                    keystore.record(&script_id, &crypto_ctx);
+                    if encrypt_keystore {
+                        keystore.seal(&password);
+                    }
+                    keystore.save(&keystore_path)?;
+                    crypto_ctx
+                }
+                // Nothing to record: a random password can't re-derive the key
+                None => CryptoContext::with_params(
+                    &random_password(),
+                    None,
+                    kdf_profile.map(Into::into).unwrap_or_default(),
+                )?,
+            };
+            let pipeline = ObfuscationPipeline::new(crypto_ctx.clone())
+                .with_progress(Arc::new(BarProgress(pb.clone())));
+            let mut protected =
+                pipeline.protect_parsed(&source, parse_result, &settings, codegen_config.clone())?;
+            if interactive {
+                if !reporter.is_text() {
+                    return Err(ObfuscatorError::ConfigError(
+                        "--interactive needs the text output format".to_string(),
+                    )
+                    .into());
+                }
+                let mut plan = ReviewPlan::new(&protected.parse_result, &protected.obfuscated);
+                if !pb.suspend(|| review::review(&mut plan, &origin))? {
+                    pb.abandon_with_message("Review cancelled, nothing was written");
+                    return Ok(());
+                }
+
+                let exclusions = plan.exclusions();
+                if !exclusions.is_empty() {
+                    info!(
+                        "  Review switched off {} string(s), {} name(s), {} function(s)",
+                        exclusions.strings.len(),
+                        exclusions.names.len(),
+                        exclusions.functions.len()
+                    );
+                    // On top of what the preset leaves alone
+                    let mut combined = settings.exclusions.clone();
+                    combined.extend(exclusions);
+                    let settings = PipelineSettings {
+                        exclusions: combined,
+                        ..settings.clone()
+                    };
+                    protected = pipeline.protect_parsed(
+                        &source,
+                        protected.parse_result,
+                        &settings,
+                        codegen_config,
+                    )?;
+                }
+            }
+            pb.inc(2);
+
+            reporter.diagnose(&protected.diagnostics);
+            pb.suspend(|| reporter.print_diagnostics());
+            deny.check(&protected.diagnostics).context("Nothing was written")?;
+
+            let stats = &protected.stats;
+            info!("Obfuscation complete:");
+            info!("  - Strings encrypted: {}", stats.encrypted_strings);
+            info!("  - Constants obfuscated: {}", stats.obfuscated_constants);
+            info!("  - Identifiers renamed: {}", stats.renamed_identifiers);
+            info!(
+                "  - Dead code snippets: {} ({} traps)",
+                stats.dead_code_snippets, stats.traps
+            );
+            if settings.simplify {
+                info!(
+                    "  - Locals simplified: {} inlined, {} removed",
+                    stats.inlined_locals, stats.removed_locals
+                );
+            }
+            if let Some(mode) = settings.table_keys {
+                info!(
+                    "  - Table keys ({}): {} obfuscated, {} kept",
+                    mode, stats.obfuscated_keys, stats.kept_keys
+                );
+            }
+            let hot = &settings.hot_functions;
+            if !hot.is_empty() {
+                info!(
+                    "  - Hot functions kept fast: {} of {} listed",
+                    hot.matching(&protected.parse_result.functions).count(),
+                    hot.len()
+                );
+            }
 
-            // Step 6: Write output (for now, just write original)
+            let verification = if verify {
+                pb.set_message("Comparing original and protected runs...");
+                let script = pipeline.verification_script(&protected)?;
+                let report = Sandbox::new().compare(&source, &script)?;
+                if let Some(mismatch) = &report.mismatch {
+                    return Err(ObfuscatorError::ObfuscationError(format!(
+                        "Verification failed, nothing was written: {}",
+                        mismatch
+                    ))
+                    .into());
+                }
+                info!(
+                    "  - Verified: {} output(s) and the result ({}) match",
+                    report.protected.events.len(),
+                    report.protected.outcome
+                );
+                Some(report.protected)
+            } else {
+                None
+            };
+
+            // Step 6: Write output
             pb.set_message("Writing protected script...");
-            fs::write(&output, &source)
-                .with_context(|| format!("Failed to write output file: {:?}", output))?;
+            let script = source_file.restore_line_endings(&protected.script, &input_options);
+            match &output {
+                Some(path) => fs::write(path, &script)
+                    .with_context(|| format!("Failed to write output file: {:?}", path))?,
+                None => {
+                    use std::io::Write;
+                    let mut stdout = std::io::stdout().lock();
+                    stdout
+                        .write_all(script.as_bytes())
+                        .and_then(|()| stdout.flush())
+                        .context("Failed to write the protected script to stdout")?;
+                }
+            }
+            if let (Some(path), Some(chunk)) = (&output_payload, &protected.payload) {
+                fs::write(path, source_file.restore_line_endings(chunk, &input_options))
+                    .with_context(|| format!("Failed to write payload chunk: {:?}", path))?;
+            }
+            let symbol_map =
+                symbol_map.or_else(|| Some(output.as_ref()?.with_extension("map.json")));
+            match &symbol_map {
+                Some(path) => {
+                    SymbolMap::from_script(&script_id, &crypto_ctx, &protected.obfuscated)
+                        .save(path)?
+                }
+                None => info!("  No symbol map written (pass --symbol-map to keep one)"),
+            }
+
+            let payload = match payload_url {
+                Some(url) => {
+                    info!("  Remote payload: {}", url);
+                    let path = payload_output
+                        .or_else(|| Some(output.as_ref()?.with_extension("payload.json")))
+                        .ok_or_else(|| {
+                            ObfuscatorError::ConfigError(
+                                "--payload-url with --stdout needs --payload-output".to_string(),
+                            )
+                        })?;
+                    let codegen = &protected.codegen_config;
+                    let payload = PayloadFile::new(&script_id, &protected.string_table)
+                        .with_dump_flags(&codegen.anti_dump.flags(&codegen.string_sensitivity));
+                    fs::write(&path, payload.to_json()?)
+                        .with_context(|| format!("Failed to write payload file: {:?}", path))?;
+                    Some(path)
+                }
+                None => None,
+            };
             pb.inc(1);
 
             pb.finish_with_message("✓ Protection complete!");
 
-            println!("\n✓ Protected script written to: {:?}", output);
-            println!("  License: {}", license_key);
-            if let Some(hwid) = hwid {
-                println!("  Bound to HWID: {}", hwid);
+            // No summary with --stdout, where it would run into the script
+            if let Some(output) = output.as_ref().filter(|_| reporter.is_text()) {
+                println!("\n✓ Protected script written to: {:?}", output);
+                println!("  Script ID: {}", script_id);
+                println!("  License: {}", license_key);
+                if let Some(hwid) = hwid {
+                    println!("  Bound to HWID: {}", hwid);
+                }
+                if let Some(ref path) = output_payload {
+                    println!("  Payload chunk: {:?}", path);
+                }
+                if let Some(ref url) = update_url {
+                    println!("  Update channel: {} (embedded version {})", url, payload_version);
+                }
+                if let Some(ref path) = payload {
+                    println!("  Payload (serve from your backend): {:?}", path);
+                }
+                if let Some(ref path) = symbol_map {
+                    println!("  Symbol map (keep private): {:?}", path);
+                }
+                if let Some(verified) = &verification {
+                    println!(
+                        "  Verified: {} output(s) and the result match the original",
+                        verified.events.len()
+                    );
+                }
+
+                let kept = &protected.obfuscated.kept_keys;
+                if !kept.is_empty() {
+                    println!("\n🔑 Table keys left as written");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    for key in kept {
+                        println!("  {:<20} line {:<6} {}", key.key, key.line, key.reason);
+                    }
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                }
+
+                let plaintext = &protected.obfuscated.plaintext_strings;
+                if !plaintext.is_empty() {
+                    println!("\n🔤 Strings left as plaintext");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    for string in plaintext {
+                        println!("  line {:<6} {:?} ({})", string.line, string.value, string.rule);
+                    }
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                }
+
+                let size = &stats.size;
+                println!("\n📦 Size");
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  {:<12} {:>10} bytes", "Input", size.input_bytes);
+                for delta in &size.deltas {
+                    println!("  {:<12} {:>+10} bytes", delta.transform, delta.bytes);
+                }
+                println!(
+                    "  {:<12} {:>10} bytes ({:.1}x)",
+                    "Output",
+                    size.output_bytes,
+                    size.growth()
+                );
+                println!("  {:<12} {:>10} bytes", "Gzipped", size.compressed_bytes);
+                if !settings.compression.is_none() {
+                    println!(
+                        "  {:<12} {:>10} bytes saved ({}, {} strings)",
+                        "Compression",
+                        stats.compression_saved,
+                        settings.compression,
+                        stats.compressed_strings
+                    );
+                }
+                println!(
+                    "  {:<12} {:>9.1}% of {} KB",
+                    "Budget",
+                    size.budget_percent(),
+                    SCRIPT_SOURCE_BUDGET / 1000
+                );
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+                let memory = &stats.memory;
+                if !memory.is_empty() {
+                    println!("\n🧮 Memory");
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    for usage in &memory.stages {
+                        println!(
+                            "  {:<12} {:>8.1} MB allocated ({} allocations), {:.1} MB peak",
+                            usage.stage.to_string(),
+                            megabytes(usage.allocated_bytes),
+                            usage.allocations,
+                            megabytes(usage.peak_bytes)
+                        );
+                    }
+                    println!("  {:<12} {:>8.1} MB", "Peak", megabytes(memory.peak_bytes()));
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                }
             }
 
-            // Track obfuscation event (if API endpoint provided)
-            if let Some(ref endpoint) = api_endpoint {
-                let api_client = create_client(endpoint)?;
+            // Queue the obfuscation event (if API endpoint provided) and
+            // upload it with any left over from earlier runs
+            let telemetry = telemetry && config.telemetry;
+            if let Some(endpoint) = api_endpoint.as_ref().filter(|_| telemetry) {
+                let queue = EventQueue::new(Config::telemetry_queue_path());
                 let tracking_request = TrackObfuscationRequest {
-                    api_key: "".to_string(), // TODO: Get from config
-                    script_id: "test_script".to_string(),
+                    // Added at upload, so the key is never written to the queue
+                    api_key: String::new(),
+                    script_id: script_id.clone(),
                     license_key: license_key.clone(),
-                    tier: tier.to_string(),
+                    tier: tier.name().to_string(),
                     timestamp: chrono::Utc::now().to_rfc3339(),
                     metadata: None,
                 };
 
-                match api_client.track_obfuscation(tracking_request) {
-                    Ok(response) => {
-                        if response.success {
-                            info!("✓ Obfuscation event tracked");
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Failed to track obfuscation event: {}", e);
+                if let Err(e) = queue.push(endpoint, tracking_request) {
+                    reporter.warn(format!("Failed to queue obfuscation event: {:#}", e));
+                }
+                match upload_events(&queue, &api_key) {
+                    Ok(report) if report.remaining > 0 => info!(
+                        "  {} usage event(s) queued for the next run",
+                        report.remaining
+                    ),
+                    Ok(report) if report.sent > 0 => info!("✓ Obfuscation event tracked"),
+                    Ok(_) => {}
+                    Err(e) => reporter.warn(format!("Failed to upload usage events: {:#}", e)),
+                }
+            }
+
+            reporter.finish(
+                "protect",
+                ProtectResult {
+                    input,
+                    output_bytes: protected.script.len(),
+                    output,
+                    script_id,
+                    tier: tier.name().to_string(),
+                    licensed_tier,
+                    license_key,
+                    hwid,
+                    seed,
+                    input_bytes,
+                    compressed_bytes: protected.stats.size.compressed_bytes,
+                    budget_percent: protected.stats.size.budget_percent(),
+                    size_deltas: protected.stats.size.deltas.clone(),
+                    compression: settings.compression,
+                    compression_saved: protected.stats.compression_saved,
+                    output_payload,
+                    payload,
+                    symbol_map,
+                    kept_keys: protected.obfuscated.kept_keys.clone(),
+                    plaintext_strings: protected.obfuscated.plaintext_strings.clone(),
+                    verification,
+                    stats: ParseStats {
+                        strings: protected.parse_result.strings.len(),
+                        numbers: protected.parse_result.numbers.len(),
+                        functions: protected.parse_result.functions.len(),
+                        type_aliases: protected.parse_result.type_aliases.len(),
+                    },
+                    memory: protected.stats.memory.clone(),
+                },
+            )
+        }
+
+        Commands::ProtectProject {
+            project,
+            output,
+            license_key,
+            script_id,
+            hwid,
+            place_id,
+            tier,
+            tier_policy,
+            api_endpoint,
+            require_online,
+            seed,
+            naming_style,
+            strip_types,
+            rename_remotes,
+            remote_map,
+            watch: _,
+            password,
+            kdf_profile,
+            keystore: keystore_path,
+            encrypt_keystore,
+        } => {
+            let model = match ModelFormat::from_path(&project) {
+                Some(_) => {
+                    info!("Loading Roblox model: {:?}", project);
+                    Some(RobloxModel::load(&project)?)
+                }
+                None => None,
+            };
+            let rojo = match &model {
+                Some(model) => model.project(),
+                None => {
+                    info!("Loading Rojo project: {:?}", project);
+                    RojoProject::load(&project)?
+                }
+            };
+            info!("  {} scripts in project {:?}", rojo.files.len(), rojo.name);
+
+            let config = Config::load()?;
+            // One id for the whole project, so one license covers every script
+            let script_id = match script_id {
+                Some(id) => id,
+                None => {
+                    let sources = match &model {
+                        Some(model) => model.sources(),
+                        None => rojo
+                            .files
+                            .iter()
+                            .map(|file| {
+                                Ok(SourceFile::read(&rojo.source_path(file), &config.input)?.text)
+                            })
+                            .collect::<Result<Vec<_>>>()?,
+                    };
+                    derive_script_id(&sources.join("\n"), &rojo.name)
+                }
+            };
+            info!("  Script ID: {}", script_id);
+
+            let licensed_tier = match &api_endpoint {
+                Some(endpoint) => {
+                    let request = ValidateLicenseRequest {
+                        api_key: api_key_or_empty(&config)?.to_string(),
+                        license_key: license_key.clone(),
+                        script_id: script_id.clone(),
+                        hwid: hwid.map(|h| h.to_string()),
+                        watermark: None,
+                    };
+                    validate_license(endpoint, &request, require_online, reporter)?
+                }
+                None => {
+                    info!("No API endpoint provided - skipping online validation");
+                    None
+                }
+            };
+            let tier = entitled_tier(tier.into(), licensed_tier.as_deref(), tier_policy, reporter)?;
+            info!("  Tier: {}", tier.name());
+
+            let crypto_ctx = match password {
+                Some(password) => {
+                    let mut keystore = Keystore::load(&keystore_path, Some(&password))?;
+                    let crypto_ctx =
+                        keystore_context(&keystore, &script_id, &password, kdf_profile)?;
+                    keystore.record(&script_id, &crypto_ctx);
+                    if encrypt_keystore {
+                        keystore.seal(&password);
                     }
+                    keystore.save(&keystore_path)?;
+                    crypto_ctx
+                }
+                None => CryptoContext::with_params(
+                    &random_password(),
+                    None,
+                    kdf_profile.map(Into::into).unwrap_or_default(),
+                )?,
+            };
+            let pipeline = ObfuscationPipeline::new(crypto_ctx)
+                .with_parser(LuauParser::with_sensitivity_rules(config.sensitivity_rules()?));
+            let settings = PipelineSettings {
+                tier,
+                seed,
+                naming_style: naming_style.into(),
+                plaintext_rules: config.plaintext_rules(&[])?,
+                api_db: api_database(&config),
+                ..Default::default()
+            };
+            let codegen_config = CodeGenConfig {
+                license_key: license_key.clone(),
+                script_id: script_id.clone(),
+                licensed_tier,
+                api_endpoint: api_endpoint.clone().unwrap_or_default(),
+                hwid,
+                place_id,
+                binding_mode: match (hwid, place_id) {
+                    (Some(_), Some(_)) => "both",
+                    (None, Some(_)) => "placeid",
+                    _ => "userid",
                 }
+                .to_string(),
+                include_license: api_endpoint.is_some(),
+                include_hwid: hwid.is_some() || place_id.is_some(),
+                type_annotations: if strip_types {
+                    TypeAnnotations::Strip
+                } else {
+                    TypeAnnotations::Preserve
+                },
+                watermark_project: Some(rojo.name.clone()),
+                key_delivery: config.key_delivery,
+                license_header: config.license_header.clone(),
+                force_optimize: config.force_optimize,
+                encode_config: config.encode_config,
+                ..Default::default()
+            };
+            let obfuscator = ProjectObfuscator::new(pipeline, settings, codegen_config)
+                .with_remote_renaming(rename_remotes)
+                .with_input_options(config.input);
+
+            let report = match &model {
+                Some(model) => obfuscator.run_model(model, &output)?,
+                None => obfuscator.run(&rojo, &output)?,
+            };
+
+            for unresolved in &report.unresolved {
+                warn!(
+                    "Could not resolve require in {} (line {})",
+                    rojo.files[unresolved.file].display_path(),
+                    unresolved.line
+                );
+            }
+
+            let remote_map = if rename_remotes {
+                for remote in report.remote_mappings.iter().filter(|r| !r.is_paired()) {
+                    let scripts: Vec<&str> =
+                        remote.server.iter().chain(&remote.client).map(String::as_str).collect();
+                    warn!(
+                        "Remote {:?} is only used on one side ({}); check the other side \
+                         doesn't look it up another way",
+                        remote.original,
+                        scripts.join(", ")
+                    );
+                }
+                let path = remote_map.unwrap_or_else(|| match &model {
+                    Some(_) => output.with_extension("remotes.map.json"),
+                    None => output.join("remotes.map.json"),
+                });
+                fs::write(&path, serde_json::to_string_pretty(&report.remote_mappings)?)
+                    .with_context(|| format!("Failed to write remote map: {:?}", path))?;
+                Some(path)
+            } else {
+                None
+            };
+
+            let protected = if model.is_some() { "Model" } else { "Project" };
+            println!("\n✓ {} protected: {:?}", protected, output);
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Scripts:             {}", report.scripts);
+            println!("  Script ID:           {}", script_id);
+            println!("  Tier:                {}", tier.name());
+            println!("  Strings encrypted:   {}", report.encrypted_strings);
+            println!("  Shared modules:      {}", report.modules);
+            println!("  Renamed exports:     {}", report.shared_mappings.len());
+            println!("  Unresolved requires: {}", report.unresolved.len());
+            if let Some(path) = &remote_map {
+                println!("  Renamed remotes:     {}", report.remote_mappings.len());
+                println!("  Remote map:          {:?}", path);
+            }
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            if remote_map.is_some() && !report.remote_mappings.is_empty() {
+                println!("Rename the remote Instances in your place to match the remote map.");
             }
 
             Ok(())
@@ -150,72 +910,1822 @@ pub fn execute(cli: Cli) -> Result<()> {
             buyer_userid,
             api_key,
             expiration,
+            batch,
+            output,
+            max_servers,
+            max_users,
         } => {
-            info!("Generating license key");
-            info!("  Script ID: {}", script_id);
-            info!("  Buyer: {}", buyer_userid);
+            let api_key = required_api_key(api_key)?;
+            let seats = SeatLimits {
+                max_servers,
+                max_users,
+            };
 
             // Use default API endpoint
             let api_client = create_client(crate::api::DEFAULT_API_ENDPOINT)
                 .context("Failed to create API client")?;
 
+            if let Some(batch_file) = batch {
+                return generate_license_batch(
+                    reporter,
+                    &api_client,
+                    &api_key,
+                    &script_id,
+                    &batch_file,
+                    output,
+                    expiration.as_deref(),
+                    seats,
+                );
+            }
+
+            // clap guarantees buyer_userid is present when --batch is absent
+            let buyer_userid = buyer_userid.ok_or_else(|| {
+                ObfuscatorError::ConfigError("--buyer-userid is required".to_string())
+            })?;
+
+            info!("Generating license key");
+            info!("  Script ID: {}", script_id);
+            info!("  Buyer: {}", buyer_userid);
+
             let request = GenerateLicenseRequest {
-                api_key: api_key.clone(),
+                api_key: api_key.to_string(),
                 script_id: script_id.clone(),
                 buyer_userid,
                 expiration,
                 tier: None,
                 hwid_restrictions: None,
+                seats,
             };
 
-            println!("\n🔄 Generating license...");
+            if reporter.is_text() {
+                println!("\n🔄 Generating license...");
+            }
 
             match api_client.generate_license(request) {
                 Ok(response) => {
-                    println!("\n✓ License generated successfully!");
-                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                    println!("  License Key: {}", response.license_key);
-                    println!("  Script ID:   {}", response.script_id);
-                    println!("  Buyer ID:    {}", response.buyer_userid);
-                    if let Some(exp) = response.expiration {
-                        println!("  Expires:     {}", 
-                            chrono::DateTime::<chrono::Utc>::from_timestamp(exp as i64, 0)
-                                .map(|dt| dt.to_rfc3339())
-                                .unwrap_or_else(|| "Invalid timestamp".to_string())
-                        );
+                    let expires = response.expiration.map(|exp| {
+                        chrono::DateTime::<chrono::Utc>::from_timestamp(exp as i64, 0)
+                            .map(|dt| dt.to_rfc3339())
+                            .unwrap_or_else(|| "Invalid timestamp".to_string())
+                    });
+
+                    if reporter.is_text() {
+                        println!("\n✓ License generated successfully!");
+                        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                        println!("  License Key: {}", response.license_key);
+                        println!("  Script ID:   {}", response.script_id);
+                        println!("  Buyer ID:    {}", response.buyer_userid);
+                        if let Some(expires) = &expires {
+                            println!("  Expires:     {}", expires);
+                        }
+                        if let Some(max_servers) = seats.max_servers {
+                            println!("  Max Servers: {}", max_servers);
+                        }
+                        if let Some(max_users) = seats.max_users {
+                            println!("  Max Players: {}", max_users);
+                        }
+                        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
                     }
-                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+                    reporter.finish(
+                        "generate-license",
+                        LicenseResult {
+                            license_key: response.license_key,
+                            script_id: response.script_id,
+                            buyer_userid: response.buyer_userid,
+                            expires,
+                            seats,
+                        },
+                    )
                 }
                 Err(e) => {
-                    eprintln!("\n❌ Failed to generate license: {}", e);
-                    return Err(e);
+                    if reporter.is_text() {
+                        eprintln!("\n❌ Failed to generate license: {}", e);
+                    }
+                    Err(e)
                 }
             }
-
-            Ok(())
         }
 
-        Commands::Validate { file } => {
+        Commands::Validate {
+            file,
+            symbol_map,
+            password,
+            script_id,
+        } => {
             info!("Validating protected script: {:?}", file);
 
             let content = fs::read_to_string(&file)
                 .with_context(|| format!("Failed to read file: {:?}", file))?;
 
-            // TODO: Implement validation logic
-            warn!("Validation not yet implemented - Phase 8");
+            let mut validator = ScriptValidator::new();
+            if let (Some(symbol_map), Some(password)) = (symbol_map, password) {
+                let map = SymbolMap::load(&symbol_map)?;
+                validator = validator
+                    .with_crypto(map.crypto_context(&password)?)
+                    .with_script_id(map.script_id);
+            }
+            if let Some(script_id) = script_id {
+                validator = validator.with_script_id(script_id);
+            }
+            let report = validator.validate(&content);
+
+            if reporter.is_text() {
+                println!("\n🔍 Validation of {:?}", file);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                for check in &report.checks {
+                    let mark = match check.status {
+                        CheckStatus::Pass => "✓ pass",
+                        CheckStatus::Fail => "✗ FAIL",
+                        CheckStatus::Skip => "- skip",
+                    };
+                    println!("  {:<12} {}  {}", check.name, mark, check.detail);
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                if report.passed() {
+                    println!("  ✓ No problems found");
+                } else {
+                    println!("  ✗ {} check(s) failed", report.failures().count());
+                }
+            }
+            for check in report.failures() {
+                reporter.warn(format!("{}: {}", check.name, check.detail));
+            }
 
-            println!("\n⚠️  Validation will be fully implemented in Phase 8");
-            println!("  File: {:?}", file);
-            println!("  Size: {} bytes", content.len());
+            reporter.finish(
+                "validate",
+                ValidateResult {
+                    file,
+                    size_bytes: content.len(),
+                    valid: report.passed(),
+                    script_id: report.script_id,
+                    checks: report.checks,
+                },
+            )
+        }
 
-            // Basic checks
-            if content.contains("Protected by Luau Obfuscator") {
-                println!("  ✓ Appears to be a protected script");
-            } else {
-                println!("  ⚠️  May not be a protected script (missing header)");
+        Commands::Diff {
+            original,
+            protected,
+        } => {
+            let source = SourceFile::read(&original, &Config::load()?.input)?.text;
+            let protected_code = fs::read_to_string(&protected)
+                .with_context(|| format!("Failed to read protected script: {:?}", protected))?;
+            let parse_result = LuauParser::with_sensitivity_rules(Config::load()?.sensitivity_rules()?)
+                .parse(&source)
+                .map_err(|e| with_origin(e, &original))?;
+
+            let report = ScriptDiff::new(&source, &parse_result).compare(&protected_code)?;
+            let leaks = report.leaks().count();
+
+            if reporter.is_text() {
+                println!("\n🔎 {:?} → {:?}", original, protected);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!(
+                    "  Plaintext literals:   {} of {}",
+                    report.surviving_literals.len(),
+                    report.literals
+                );
+                println!(
+                    "  Original identifiers: {} of {} kept ({:.0}% renamed)",
+                    report.retained_identifiers.len(),
+                    report.identifiers,
+                    report.renamed_ratio() * 100.0
+                );
+                println!(
+                    "  Entropy per line:     {:.2} → {:.2} bits/byte",
+                    report.original_entropy, report.protected_entropy
+                );
+                let mut flattest = report.line_entropy.clone();
+                flattest.sort_by(|a, b| a.entropy.total_cmp(&b.entropy));
+                if !flattest.is_empty() {
+                    println!("  Least random lines of the protected code:");
+                    for line in flattest.iter().take(5) {
+                        println!("    line {:<6} {:.2}", line.line, line.entropy);
+                    }
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                if leaks == 0 {
+                    println!("  ✓ No sensitive literals in plaintext");
+                } else {
+                    println!("  ✗ {} sensitive literal(s) in plaintext", leaks);
+                }
+            }
+            for leak in report.leaks() {
+                reporter.warn(format!(
+                    "Sensitive string on line {} is not encrypted: {:?}",
+                    leak.line, leak.value
+                ));
             }
 
-            Ok(())
+            reporter.finish(
+                "diff",
+                DiffResult {
+                    original,
+                    protected,
+                    literals: report.literals,
+                    leaks,
+                    surviving_literals: report.surviving_literals,
+                    identifiers: report.identifiers,
+                    retained_identifiers: report.retained_identifiers,
+                    original_entropy: report.original_entropy,
+                    protected_entropy: report.protected_entropy,
+                    line_entropy: report.line_entropy,
+                },
+            )
         }
-    }
+
+        Commands::Score { file } => {
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read file: {:?}", file))?;
+            let report = DetectabilityAnalyzer::new().analyze(&content)?;
+
+            if reporter.is_text() {
+                println!("\n🕵️  Detectability of {:?}", file);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Score:                 {}/100", report.score);
+                println!(
+                    "  Encoded-looking lines: {} of {} (mean entropy {:.2} bits/byte)",
+                    report.high_entropy_lines, report.lines, report.mean_entropy
+                );
+                println!(
+                    "  Decrypt calls:         {} of {} calls",
+                    report.decrypt_calls, report.calls
+                );
+                println!(
+                    "  Generated-looking ids: {} of {}",
+                    report.suspicious_identifiers, report.identifiers
+                );
+                for hit in &report.patterns {
+                    println!("    {:<12} {}", hit.pattern, hit.count);
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                if report.recommendations.is_empty() {
+                    println!("  ✓ Nothing stands out");
+                }
+                for advice in &report.recommendations {
+                    println!("  → {}", advice);
+                }
+            }
+
+            reporter.finish(
+                "score",
+                ScoreResult {
+                    file,
+                    decrypt_call_ratio: report.decrypt_call_ratio(),
+                    report,
+                },
+            )
+        }
+
+        Commands::InspectWatermark {
+            leaks,
+            manifest,
+            customers,
+            output,
+        } => {
+            if manifest.is_empty() && customers.is_empty() {
+                return Err(ObfuscatorError::ConfigError(
+                    "Nothing to match against: pass --manifest and/or --customers".to_string(),
+                )
+                .into());
+            }
+            let mut inspector = LeakInspector::new();
+            for path in &customers {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read customers file: {:?}", path))?;
+                inspector.add_customers(&parse_customers_csv(&content)?);
+            }
+            let mut builds = 0;
+            for path in &manifest {
+                let added = inspector.add_manifest(path)?;
+                info!("Loaded {} build(s) from {:?}", added, path);
+                builds += added;
+            }
+            let report = inspector.inspect_dir(&leaks)?;
+
+            if let Some(path) = &output {
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                let html = matches!(extension.to_ascii_lowercase().as_str(), "html" | "htm");
+                let content = if html {
+                    report.to_html()
+                } else {
+                    serde_json::to_string_pretty(&report)?
+                };
+                fs::write(path, content)
+                    .with_context(|| format!("Failed to write report: {:?}", path))?;
+            }
+
+            if reporter.is_text() {
+                println!(
+                    "\n🔎 Leak report for {:?} ({} file(s), {} build(s))",
+                    leaks,
+                    report.scripts.len(),
+                    builds
+                );
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                if report.suspects.is_empty() {
+                    println!("  No file matched a known customer");
+                }
+                for suspect in &report.suspects {
+                    println!(
+                        "  {:<20} {:>4.0}%  {} file(s)",
+                        suspect.customer_id,
+                        suspect.confidence * 100.0,
+                        suspect.files.len()
+                    );
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                for script in &report.scripts {
+                    match script.suspects.first() {
+                        Some(suspect) => {
+                            let evidence: Vec<String> =
+                                suspect.evidence.iter().map(ToString::to_string).collect();
+                            println!(
+                                "  {} → {} ({:.0}%: {})",
+                                script.path.display(),
+                                suspect.customer_id,
+                                suspect.confidence * 100.0,
+                                evidence.join(", ")
+                            );
+                        }
+                        None => println!("  {} → no match", script.path.display()),
+                    }
+                }
+                if let Some(path) = &output {
+                    println!("\n✓ Report written to: {:?}", path);
+                }
+            }
+
+            reporter.finish(
+                "inspect-watermark",
+                InspectWatermarkResult {
+                    leaks,
+                    builds,
+                    output,
+                    report,
+                },
+            )
+        }
+
+        Commands::Release {
+            input,
+            customers,
+            output,
+            tier,
+            tier_policy,
+            api_endpoint,
+            require_online,
+            preset,
+            seed,
+            password,
+            kdf_profile,
+            jobs,
+            watermark_project,
+        } => {
+            let config = Config::load()?;
+            let source_file = SourceFile::read(&input, &config.input)?;
+            report_input(&source_file, &config.input, reporter);
+            let source = source_file.text.clone();
+            let content = fs::read_to_string(&customers)
+                .with_context(|| format!("Failed to read customers file: {:?}", customers))?;
+            let customer_list = parse_customers_csv(&content)?;
+            if customer_list.is_empty() {
+                return Err(ObfuscatorError::ConfigError(format!(
+                    "No customers found in: {:?}",
+                    customers
+                ))
+                .into());
+            }
+
+            let script_id = input
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "script".to_string());
+            info!("Building release of {} for {} customers", script_id, customer_list.len());
+            info!("  Output: {:?}", output);
+
+            // Every copy is built at one tier, so no customer's license may
+            // be below it
+            let api_key = match api_endpoint {
+                Some(_) => api_key_or_empty(&config)?,
+                None => Zeroizing::new(String::new()),
+            };
+            let mut tier: ObfuscationTier = tier.into();
+            for customer in &customer_list {
+                let licensed_tier = match &api_endpoint {
+                    Some(endpoint) => {
+                        let request = ValidateLicenseRequest {
+                            api_key: api_key.to_string(),
+                            license_key: customer.license_key.clone(),
+                            script_id: script_id.clone(),
+                            hwid: customer.hwid.map(|h| h.to_string()),
+                            watermark: None,
+                        };
+                        validate_license(endpoint, &request, require_online, reporter)
+                    }
+                    None => Ok(None),
+                };
+                tier = licensed_tier
+                    .and_then(|licensed| {
+                        entitled_tier(tier, licensed.as_deref(), tier_policy, reporter)
+                    })
+                    .with_context(|| {
+                        format!("License of {} (line {})", customer.id, customer.line)
+                    })?;
+            }
+            info!("  Tier: {}", tier.name());
+
+            let preset = preset.map(|name| config.preset(&name)).transpose()?;
+            let settings = PipelineSettings {
+                tier,
+                obfuscation: preset.as_ref().map(|preset| preset.settings(tier.settings())),
+                seed,
+                exclusions: match &preset {
+                    Some(preset) => preset.exclusions(&source)?,
+                    None => Default::default(),
+                },
+                plaintext_rules: config.plaintext_rules(&[])?,
+                api_db: api_database(&config),
+                ..Default::default()
+            };
+            let mut codegen_config = CodeGenConfig {
+                script_id: script_id.clone(),
+                api_endpoint: api_endpoint
+                    .unwrap_or_else(|| CodeGenConfig::default().api_endpoint),
+                watermark_project,
+                key_delivery: config.key_delivery,
+                license_header: config.license_header.clone(),
+                force_optimize: config.force_optimize,
+                encode_config: config.encode_config,
+                template_overrides: match &config.templates_dir {
+                    Some(dir) => load_template_dir(dir)?,
+                    None => Default::default(),
+                },
+                ..Default::default()
+            };
+            if let Some(preset) = &preset {
+                preset.apply_codegen(&mut codegen_config);
+            }
+            let mut matrix = ReleaseMatrix::new(settings, codegen_config)
+                .with_kdf_params(kdf_profile.map(Into::into).unwrap_or_default());
+            match password {
+                Some(password) => matrix = matrix.with_password(password),
+                None => reporter.warn(
+                    "No --password given: each build gets a random key, so builds can't be \
+                     recovered or rekeyed later",
+                ),
+            }
+            if let Some(jobs) = jobs {
+                matrix = matrix.with_jobs(jobs);
+            }
+
+            fs::create_dir_all(&output)
+                .with_context(|| format!("Failed to create output directory: {:?}", output))?;
+
+            let pb = reporter.progress_bar(customer_list.len() as u64);
+            pb.set_message("Building customer copies...");
+            let builds = matrix.build_with_progress(&source, &customer_list, |_| pb.inc(1));
+            pb.finish_with_message("✓ Release built");
+
+            let mut entries = Vec::with_capacity(builds.len());
+            for (customer, build) in customer_list.iter().zip(builds) {
+                let entry = build.and_then(|build| {
+                    let script_path = output.join(format!("{}.{}.lua", script_id, customer.id));
+                    let script = source_file.restore_line_endings(&build.script, &config.input);
+                    fs::write(&script_path, script).with_context(|| {
+                        format!("Failed to write output file: {:?}", script_path)
+                    })?;
+                    let payload_path = match &build.payload {
+                        Some(payload) => {
+                            let path =
+                                output.join(format!("{}.{}.payload.lua", script_id, customer.id));
+                            let payload = source_file.restore_line_endings(payload, &config.input);
+                            fs::write(&path, payload).with_context(|| {
+                                format!("Failed to write payload chunk: {:?}", path)
+                            })?;
+                            Some(path)
+                        }
+                        None => None,
+                    };
+                    let map_path = script_path.with_extension("map.json");
+                    build.symbol_map.save(&map_path)?;
+                    Ok(ManifestEntry {
+                        customer_id: customer.id.clone(),
+                        hwid: customer.hwid,
+                        seed: build.seed,
+                        output: Some(script_path),
+                        payload: payload_path,
+                        symbol_map: Some(map_path),
+                        watermark_digest: Some(build.watermark_digest),
+                        error: None,
+                    })
+                });
+                entries.push(entry.unwrap_or_else(|e| {
+                    reporter.warn(format!(
+                        "Build for {} (line {}) failed: {:#}",
+                        customer.id, customer.line, e
+                    ));
+                    ManifestEntry {
+                        customer_id: customer.id.clone(),
+                        hwid: customer.hwid,
+                        seed: 0,
+                        output: None,
+                        payload: None,
+                        symbol_map: None,
+                        watermark_digest: None,
+                        error: Some(format!("{:#}", e)),
+                    }
+                }));
+            }
+
+            let manifest = ReleaseManifest {
+                script_id: script_id.clone(),
+                tier: tier.name().to_string(),
+                builds: entries,
+            };
+            let manifest_path = output.join("manifest.json");
+            fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+                .with_context(|| format!("Failed to write manifest: {:?}", manifest_path))?;
+
+            let failed = manifest.builds.iter().filter(|b| b.error.is_some()).count();
+            let built = manifest.builds.len() - failed;
+
+            if reporter.is_text() {
+                println!("\n✓ Release of {} written to: {:?}", script_id, output);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Built:    {}", built);
+                println!("  Failed:   {}", failed);
+                println!("  Manifest (keep private): {:?}", manifest_path);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            }
+
+            if built == 0 {
+                return Err(ObfuscatorError::CodeGenError(format!(
+                    "All {} customer builds failed",
+                    failed
+                ))
+                .into());
+            }
+
+            reporter.finish(
+                "release",
+                ReleaseResult {
+                    script_id,
+                    built,
+                    failed,
+                    manifest: manifest_path,
+                },
+            )
+        }
+
+        Commands::Estimate { input, tier } => {
+            let source = SourceFile::read(&input, &Config::load()?.input)?.text;
+            let parse_result = LuauParser::with_sensitivity_rules(Config::load()?.sensitivity_rules()?)
+                .parse(&source)
+                .map_err(|e| with_origin(e, &input))?;
+
+            let tiers = match tier {
+                Some(tier) => vec![tier.into()],
+                None => vec![
+                    ObfuscationTier::Basic,
+                    ObfuscationTier::Standard,
+                    ObfuscationTier::Premium,
+                ],
+            };
+            let estimates = tiers
+                .into_iter()
+                .map(|tier| OverheadEstimator::new(tier).estimate(&source, &parse_result))
+                .collect::<Result<Vec<_>>>()?;
+
+            if reporter.is_text() {
+                println!("\n📊 Estimated overhead for {:?}", input);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!(
+                    "  {:<10} {:>10} {:>12} {:>12} {:>10} {:>10}",
+                    "Tier", "Startup", "First use", "Per frame", "Frame %", "Size"
+                );
+                for estimate in &estimates {
+                    println!(
+                        "  {:<10} {:>8.2}ms {:>10.2}ms {:>10.3}ms {:>9.2}% {:>8}KB",
+                        estimate.tier,
+                        estimate.startup_ms,
+                        estimate.first_use_ms,
+                        estimate.per_frame_ms,
+                        estimate.frame_budget_percent(),
+                        estimate.output_bytes.div_ceil(1024)
+                    );
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                for estimate in &estimates {
+                    println!(
+                        "  {}: {} decrypts at load, {} on first use, {} inlined constants, \
+                         {} flattened loops, {} bytes of dead code",
+                        estimate.tier,
+                        estimate.load_decrypts,
+                        estimate.lazy_decrypts,
+                        estimate.obfuscated_constants,
+                        estimate.flattened_loops,
+                        estimate.dead_code_bytes
+                    );
+                }
+                println!(
+                    "\n  Per-frame figures assume each line runs once per frame and each loop \
+                     runs {} iterations.",
+                    ASSUMED_ITERATIONS_PER_FRAME
+                );
+            }
+
+            reporter.finish("estimate", estimates)
+        }
+
+        Commands::Minify {
+            input,
+            output,
+            stdout,
+            no_rename,
+            naming_style,
+        } => {
+            if stdout {
+                // stdout carries only the script
+                reporter.use_stderr();
+            }
+            let input_options = Config::load()?.input;
+            let source_file = SourceFile::read(&input, &input_options)?;
+            report_input(&source_file, &input_options, reporter);
+            let parse_result = LuauParser::new()
+                .parse(&source_file.text)
+                .map_err(|e| with_origin(e, &input))?;
+
+            let minified = Minifier::new()
+                .with_renaming(!no_rename)
+                .with_naming_style(naming_style.into())
+                .minify(&source_file.text, &parse_result)?;
+            let diagnostics: Vec<_> =
+                minified.dynamic_globals.iter().map(DynamicGlobal::diagnostic).collect();
+            reporter.diagnose(&diagnostics);
+            reporter.print_diagnostics();
+
+            let script = source_file.restore_line_endings(&minified.code, &input_options);
+            match &output {
+                Some(path) => fs::write(path, &script)
+                    .with_context(|| format!("Failed to write output file: {:?}", path))?,
+                None => {
+                    use std::io::Write;
+                    let mut stdout = std::io::stdout().lock();
+                    stdout
+                        .write_all(script.as_bytes())
+                        .and_then(|()| stdout.flush())
+                        .context("Failed to write the minified script to stdout")?;
+                }
+            }
+
+            let result = MinifyResult {
+                input,
+                output,
+                original_bytes: source_file.text.len(),
+                minified_bytes: minified.code.len(),
+                renamed: minified.renamed,
+            };
+            // No summary with --stdout, where it would run into the script
+            if let Some(output) = result.output.as_ref().filter(|_| reporter.is_text()) {
+                println!("\n✓ Minified script written to: {:?}", output);
+                println!(
+                    "  Size: {} → {} bytes ({:.0}% smaller)",
+                    result.original_bytes,
+                    result.minified_bytes,
+                    (1.0 - result.minified_bytes as f64 / result.original_bytes.max(1) as f64)
+                        * 100.0
+                );
+                println!("  Locals renamed: {}", result.renamed);
+            }
+
+            reporter.finish("minify", result)
+        }
+
+        Commands::Analyze { input } => {
+            let config = Config::load()?;
+            let source = SourceFile::read(&input, &config.input)?.text;
+            let parse_result = LuauParser::with_sensitivity_rules(config.sensitivity_rules()?)
+                .parse(&source)
+                .map_err(|e| with_origin(e, &input))?;
+            let analysis =
+                Analyzer::with_api_database(AnalysisOptions::default(), api_database(&config))
+                    .analyze(&parse_result)?;
+            let result = AnalyzeResult::new(input, &parse_result, &analysis);
+
+            if reporter.is_text() {
+                println!("\n🔍 Analysis of {:?}", result.input);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Roblox APIs:");
+                for api in &result.roblox_apis {
+                    println!("    {:<10} {}", api.kind, api.name);
+                }
+                let functions = result.scopes.iter().filter(|s| s.kind == "function").count();
+                println!(
+                    "  Scopes: {} ({} functions, {} captured upvalues)",
+                    result.scopes.len(),
+                    functions,
+                    result.scopes.iter().map(|s| s.upvalues.len()).sum::<usize>()
+                );
+                let cfg = &result.control_flow;
+                println!(
+                    "  Control flow: {} blocks, {} edges, {} branches, {} returns",
+                    cfg.blocks, cfg.edges, cfg.branches, cfg.returns
+                );
+                let strings = &result.strings;
+                println!(
+                    "  Strings: {} high, {} medium, {} low sensitivity",
+                    strings.high, strings.medium, strings.low
+                );
+                for literal in strings.literals.iter().filter(|s| s.sensitivity == Sensitivity::High) {
+                    println!("    line {:<6} {:?}", literal.line, literal.value);
+                }
+                let renamed = result.names.iter().filter(|n| n.renamed).count();
+                println!("  Names: {} renamed, {} kept", renamed, result.names.len() - renamed);
+                for name in &result.names {
+                    if let Some(reason) = &name.reason {
+                        let action = if name.renamed { "rename" } else { "keep" };
+                        println!("    {:<6} {:<20} {}", action, name.name, reason);
+                    }
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            }
+
+            reporter.finish("analyze", result)
+        }
+
+        Commands::Recover {
+            input,
+            symbol_map,
+            password,
+            output,
+        } => {
+            let map = SymbolMap::load(&symbol_map)?;
+            let protected = fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read protected script: {:?}", input))?;
+
+            info!("Re-deriving key for script {}", map.script_id);
+            let crypto_ctx = map.crypto_context(&password)?;
+            let recovered = ScriptRecovery::new(&crypto_ctx, &map).recover(&protected)?;
+
+            if recovered.failures > 0 && recovered.constants_recovered == 0 {
+                return Err(ObfuscatorError::CryptoError(
+                    "No encrypted values could be decrypted (wrong password or symbol map?)"
+                        .to_string(),
+                )
+                .into());
+            }
+
+            let output = output.unwrap_or_else(|| input.with_extension("recovered.lua"));
+            fs::write(&output, &recovered.code)
+                .with_context(|| format!("Failed to write output file: {:?}", output))?;
+
+            println!("\n✓ Recovered script written to: {:?}", output);
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Constants decrypted: {}", recovered.constants_recovered);
+            println!("  Names restored:      {}", recovered.names_restored);
+            if recovered.failures > 0 {
+                println!("  ⚠️  Failed entries:  {}", recovered.failures);
+            }
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+            Ok(())
+        }
+
+        Commands::GenTests {
+            input,
+            symbol_map,
+            password,
+            output,
+        } => {
+            let map = SymbolMap::load(&symbol_map)?;
+            let protected = fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read protected script: {:?}", input))?;
+
+            info!("Re-deriving key for script {}", map.script_id);
+            let crypto_ctx = map.crypto_context(&password)?;
+            let harness = TestHarness::new(&crypto_ctx, &map).generate(&protected)?;
+
+            let output = output.unwrap_or_else(|| input.with_extension("tests.lua"));
+            fs::write(&output, &harness.code)
+                .with_context(|| format!("Failed to write output file: {:?}", output))?;
+
+            if reporter.is_text() {
+                println!("\n✓ Test harness written to: {:?}", output);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Strings checked:   {}", harness.strings);
+                println!("  Constants checked: {}", harness.constants);
+                println!(
+                    "  License check:     {}",
+                    if harness.license_check { "yes" } else { "no license stub" }
+                );
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            }
+            reporter.warn(format!(
+                "{:?} contains the decryption key; keep it out of the shipped build",
+                output
+            ));
+
+            reporter.finish(
+                "gen-tests",
+                GenTestsResult {
+                    output,
+                    script_id: map.script_id,
+                    strings: harness.strings,
+                    constants: harness.constants,
+                    license_check: harness.license_check,
+                },
+            )
+        }
+
+        Commands::Vectors { output, verify } => {
+            if let Some(file) = verify {
+                let content = fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read test vectors: {:?}", file))?;
+                let vectors: TestVectors = serde_json::from_str(&content)
+                    .with_context(|| format!("Invalid test vectors: {:?}", file))?;
+                let mismatches = vectors.verify()?;
+                let total = vectors.watermarks.len()
+                    + vectors.ciphertexts.len()
+                    + vectors.stego_patterns.len();
+
+                if reporter.is_text() {
+                    println!("\n🔍 Test vectors {:?}", file);
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!("  Watermarks:     {}", vectors.watermarks.len());
+                    println!("  Ciphertexts:    {}", vectors.ciphertexts.len());
+                    println!("  Stego patterns: {}", vectors.stego_patterns.len());
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                }
+                for mismatch in &mismatches {
+                    reporter.warn(format!(
+                        "{}: expected {}, got {}",
+                        mismatch.field, mismatch.expected, mismatch.actual
+                    ));
+                }
+                if !mismatches.is_empty() {
+                    return Err(ObfuscatorError::CryptoError(format!(
+                        "{} value(s) in {} test vectors differ from this build",
+                        mismatches.len(),
+                        total
+                    ))
+                    .into());
+                }
+                if reporter.is_text() {
+                    println!("  ✓ All {} vectors match this build", total);
+                }
+
+                return reporter.finish(
+                    "vectors",
+                    VectorsResult {
+                        file: Some(file),
+                        watermarks: vectors.watermarks.len(),
+                        ciphertexts: vectors.ciphertexts.len(),
+                        stego_patterns: vectors.stego_patterns.len(),
+                        vectors: None,
+                    },
+                );
+            }
+
+            let vectors = TestVectors::generate()?;
+            let json = serde_json::to_string_pretty(&vectors)?;
+            if let Some(output) = &output {
+                fs::write(output, format!("{}\n", json))
+                    .with_context(|| format!("Failed to write output file: {:?}", output))?;
+                if reporter.is_text() {
+                    println!("\n✓ Test vectors written to: {:?}", output);
+                }
+            } else if reporter.is_text() {
+                println!("{}", json);
+            }
+
+            reporter.finish(
+                "vectors",
+                VectorsResult {
+                    watermarks: vectors.watermarks.len(),
+                    ciphertexts: vectors.ciphertexts.len(),
+                    stego_patterns: vectors.stego_patterns.len(),
+                    vectors: output.is_none().then_some(vectors),
+                    file: output,
+                },
+            )
+        }
+
+        Commands::Rekey {
+            input,
+            symbol_map,
+            old_password,
+            new_password,
+            kdf_profile,
+            output,
+            keystore: keystore_path,
+        } => {
+            let map = SymbolMap::load(&symbol_map)?;
+            let protected = fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read protected script: {:?}", input))?;
+
+            info!("Re-deriving key version {} for script {}", map.key_version, map.script_id);
+            let old_ctx = map.crypto_context(&old_password)?;
+            let params = kdf_profile.map(Into::into).unwrap_or(map.kdf);
+            let new_ctx = CryptoContext::with_params(&new_password, None, params)?
+                .with_key_version(map.key_version + 1);
+            let rekeyed = Rekeyer::new(&old_ctx, &new_ctx).rekey(&protected)?;
+
+            // Write the map first: a script without its map can't be rotated again
+            let (output, map_path) = match output {
+                Some(output) => {
+                    let map_path = output.with_extension("map.json");
+                    (output, map_path)
+                }
+                None => (input, symbol_map),
+            };
+            map.rekeyed(&new_ctx).save(&map_path)?;
+            let mut keystore = Keystore::load(&keystore_path, Some(&old_password))?;
+            keystore.record(&map.script_id, &new_ctx);
+            keystore.save(&keystore_path)?;
+            fs::write(&output, &rekeyed.code)
+                .with_context(|| format!("Failed to write output file: {:?}", output))?;
+
+            if reporter.is_text() {
+                println!("\n✓ Script rotated to key version {}: {:?}", new_ctx.key_version(), output);
+                println!("  Entries re-encrypted: {}", rekeyed.entries);
+                println!("  Symbol map (keep private): {:?}", map_path);
+            }
+
+            reporter.finish(
+                "rekey",
+                RekeyResult {
+                    script_id: map.script_id,
+                    output,
+                    symbol_map: map_path,
+                    key_version: new_ctx.key_version(),
+                    entries: rekeyed.entries,
+                },
+            )
+        }
+
+        Commands::Keygen {
+            symbol_map,
+            password,
+            license_key,
+        } => {
+            let delivery = Config::load()?.key_delivery.ok_or_else(|| {
+                ObfuscatorError::ConfigError(
+                    "No key_delivery sources in the config file".to_string(),
+                )
+            })?;
+            let map = SymbolMap::load(&symbol_map)?;
+
+            info!("Re-deriving key for script {}", map.script_id);
+            let crypto_ctx = map.crypto_context(&password)?;
+            let shares: Vec<KeyShare> = delivery
+                .share_values(&crypto_ctx, &license_key)
+                .into_iter()
+                .map(|(source, value)| KeyShare {
+                    source: source.clone(),
+                    value,
+                })
+                .collect();
+
+            if reporter.is_text() {
+                println!("\n✓ Key shares for license {}", license_key);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                for share in &shares {
+                    println!("  {}", share.source);
+                    println!("    {}", share.value);
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Only valid for scripts protected with this password and license key");
+            }
+
+            reporter.finish(
+                "keygen",
+                KeygenResult {
+                    script_id: map.script_id,
+                    license_key,
+                    shares,
+                },
+            )
+        }
+
+        Commands::PublishPayload {
+            input,
+            symbol_map,
+            password,
+            version,
+            output,
+            api_key,
+        } => {
+            let map = SymbolMap::load(&symbol_map)?;
+            let script = fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read script: {:?}", input))?;
+            if script.contains(&format!("script.{}", PAYLOAD_MODULE)) {
+                reporter.warn(format!(
+                    "{:?} looks like a split loader; updates run as one file without a payload \
+                     module",
+                    input
+                ));
+            }
+
+            info!("Re-deriving update keys for script {}", map.script_id);
+            let crypto_ctx = map.crypto_context(&password)?;
+            let keys = UpdateKeys::derive(&crypto_ctx, &map.script_id);
+            let update = PayloadUpdate::seal(&keys, &map.script_id, version, &script)?;
+
+            let latest_version = match &output {
+                Some(path) => {
+                    fs::write(path, update.to_json()?)
+                        .with_context(|| format!("Failed to write payload update: {:?}", path))?;
+                    None
+                }
+                None => {
+                    let api_key = required_api_key(api_key)?;
+                    let api_client = create_client(crate::api::DEFAULT_API_ENDPOINT)
+                        .context("Failed to create API client")?;
+                    let response = api_client.publish_payload(PublishPayloadRequest {
+                        api_key: api_key.to_string(),
+                        script_id: update.script_id.clone(),
+                        version: update.version,
+                        ct: update.ct.clone(),
+                        nonce: update.nonce.clone(),
+                        sig: update.sig.clone(),
+                    })?;
+                    if !response.success {
+                        return Err(ObfuscatorError::ApiError(format!(
+                            "API did not publish version {} of {}",
+                            version, map.script_id
+                        ))
+                        .into());
+                    }
+                    Some(response.latest_version)
+                }
+            };
+
+            if reporter.is_text() {
+                println!("\n✓ Payload version {} signed for {}", version, map.script_id);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                if let Some(path) = &output {
+                    println!("  Written to:     {:?}", path);
+                }
+                if let Some(latest) = latest_version {
+                    println!("  Latest served:  {}", latest);
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Loaders built with an older --payload-version run it on next start");
+            }
+
+            reporter.finish(
+                "publish-payload",
+                PublishPayloadResult {
+                    script_id: map.script_id,
+                    version,
+                    output,
+                    latest_version,
+                },
+            )
+        }
+
+        Commands::UpdateApiDb { version, output } => {
+            let config = Config::load()?;
+            let version = version.or(config.api_db_version.clone());
+
+            match &version {
+                Some(v) => info!("Fetching pinned Roblox API version {}", v),
+                None => info!("Fetching latest Roblox API version"),
+            }
+
+            let db = ApiDatabase::fetch(version.as_deref())?;
+            let path = output
+                .unwrap_or_else(|| ApiDatabase::default_path(&config.resolved_cache_dir()));
+            db.save(&path)?;
+
+            println!("\n✓ Roblox API database updated");
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("  Version:  {}", db.version);
+            println!("  Services: {}", db.services.len());
+            println!("  Classes:  {}", db.classes.len());
+            println!("  Enums:    {}", db.enums.len());
+            println!("  Written:  {:?}", path);
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+            if config.api_db_version.is_none() {
+                println!(
+                    "  Pin this version with api_db_version = \"{}\" in your config",
+                    db.version
+                );
+            }
+
+            Ok(())
+        }
+
+        Commands::Telemetry {
+            action: TelemetryAction::Flush,
+        } => {
+            let queue = EventQueue::new(Config::telemetry_queue_path());
+            let report = upload_events(&queue, &api_key_or_empty(&Config::load()?)?)?;
+
+            if reporter.is_text() {
+                println!("\n✓ Usage events flushed");
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Sent:      {}", report.sent);
+                println!("  Remaining: {}", report.remaining);
+                if report.dropped > 0 {
+                    println!("  Dropped:   {} (failed {} times)", report.dropped, MAX_ATTEMPTS);
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            }
+            if report.remaining > 0 {
+                reporter.warn(format!(
+                    "{} usage event(s) could not be uploaded and stay queued in {:?}",
+                    report.remaining,
+                    queue.path()
+                ));
+            }
+
+            reporter.finish("telemetry", report)
+        }
+
+        Commands::Auth { action } => {
+            let store = credential_store();
+            let credential = match action {
+                AuthAction::Login { api_key } => {
+                    let api_key = match api_key {
+                        Some(api_key) => Zeroizing::new(api_key),
+                        None => {
+                            if reporter.is_text() {
+                                eprint!("API key: ");
+                            }
+                            let mut line = Zeroizing::new(String::new());
+                            std::io::stdin()
+                                .read_line(&mut line)
+                                .context("Failed to read the API key from stdin")?;
+                            Zeroizing::new(line.trim().to_string())
+                        }
+                    };
+                    let source = store.store(&api_key)?;
+                    if reporter.is_text() {
+                        println!("\n✓ API key saved in the {}", source);
+                    }
+                    Some(ApiCredential { api_key, source })
+                }
+                AuthAction::Status => {
+                    let credential = api_credential(&Config::load()?)?;
+                    if reporter.is_text() {
+                        match &credential {
+                            Some(credential) => println!(
+                                "✓ Using API key {} from the {}",
+                                credential.masked(),
+                                credential.source
+                            ),
+                            None => println!("✗ No API key saved; run `auth login`"),
+                        }
+                    }
+                    credential
+                }
+                AuthAction::Logout => {
+                    let removed = store.clear()?;
+                    if reporter.is_text() {
+                        if removed {
+                            println!("✓ Saved API key removed");
+                        } else {
+                            println!("No API key was saved");
+                        }
+                    }
+                    // The environment or config may still provide one
+                    let credential = api_credential(&Config::load()?)?;
+                    if let Some(credential) = &credential {
+                        reporter.warn(format!(
+                            "API commands still use the key from the {}",
+                            credential.source
+                        ));
+                    }
+                    credential
+                }
+            };
+
+            reporter.finish(
+                "auth",
+                AuthResult {
+                    logged_in: credential.is_some(),
+                    source: credential.as_ref().map(|c| c.source),
+                    api_key: credential.as_ref().map(ApiCredential::masked),
+                },
+            )
+        }
+
+        Commands::License {
+            action:
+                LicenseAction::Seats {
+                    license_key,
+                    script_id,
+                    api_key,
+                    full,
+                },
+        } => {
+            let api_key = required_api_key(api_key)?;
+            let api_client = create_client(crate::api::DEFAULT_API_ENDPOINT)
+                .context("Failed to create API client")?;
+            let request = LicenseSeatsRequest {
+                api_key: api_key.to_string(),
+                license_key,
+                script_id,
+            };
+            let mut licenses: Vec<SeatUsage> = api_client
+                .license_seats(request)?
+                .licenses
+                .into_iter()
+                .map(SeatUsage::from)
+                .collect();
+            if full {
+                licenses.retain(|usage| usage.full);
+            }
+
+            if reporter.is_text() {
+                println!("\n🎫 License seats");
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                if licenses.is_empty() {
+                    println!("  No licenses found");
+                }
+                for usage in &licenses {
+                    println!(
+                        "  {}  servers {}  players {}{}",
+                        usage.seats.license_key,
+                        seat_usage(usage.active_servers, usage.seats.limits.max_servers),
+                        seat_usage(usage.active_users, usage.seats.limits.max_users),
+                        if usage.full { "  (full)" } else { "" }
+                    );
+                    for session in &usage.seats.sessions {
+                        let seen = chrono::DateTime::<chrono::Utc>::from_timestamp(
+                            session.last_heartbeat as i64,
+                            0,
+                        )
+                        .map(|dt| dt.to_rfc3339())
+                        .unwrap_or_else(|| "Invalid timestamp".to_string());
+                        println!("    {:<38} {:<12} {}", session.session_id, session.hwid, seen);
+                    }
+                }
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            }
+
+            reporter.finish("license", LicenseSeatsResult { licenses })
+        }
+
+        Commands::License {
+            action:
+                LicenseAction::Revoke {
+                    license_key,
+                    reason,
+                    api_key,
+                },
+        } => {
+            let api_key = required_api_key(api_key)?;
+            let api_client = create_client(crate::api::DEFAULT_API_ENDPOINT)
+                .context("Failed to create API client")?;
+            let response = api_client.revoke_license(RevokeLicenseRequest {
+                api_key: api_key.to_string(),
+                license_key: license_key.clone(),
+                reason,
+            })?;
+            if !response.success {
+                return Err(ObfuscatorError::ApiError(format!(
+                    "API did not revoke license {}",
+                    license_key
+                ))
+                .into());
+            }
+            let revoked_at =
+                chrono::DateTime::<chrono::Utc>::from_timestamp(response.revoked_at as i64, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_else(|| "Invalid timestamp".to_string());
+
+            if reporter.is_text() {
+                println!("\n✓ License revoked");
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  License Key: {}", license_key);
+                println!("  Script ID:   {}", response.script_id);
+                println!("  Revoked:     {}", revoked_at);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!(
+                    "  Builds check it online now; run `license crl --script-id {}` to reject it \
+                     offline in new builds",
+                    response.script_id
+                );
+            }
+
+            reporter.finish(
+                "license",
+                RevokeResult {
+                    license_key,
+                    script_id: response.script_id,
+                    revoked_at,
+                },
+            )
+        }
+
+        Commands::License {
+            action:
+                LicenseAction::Crl {
+                    script_id,
+                    output,
+                    api_key,
+                },
+        } => {
+            let api_key = required_api_key(api_key)?;
+            let api_client = create_client(crate::api::DEFAULT_API_ENDPOINT)
+                .context("Failed to create API client")?;
+            let signed = api_client
+                .revocation_list(RevocationListRequest {
+                    api_key: api_key.to_string(),
+                    script_id: script_id.clone(),
+                })?
+                .list;
+            // Checked now so a bad download fails here rather than at build time
+            let list = RevocationList::verify(&signed, &api_key)?;
+            if list.script_id != script_id {
+                return Err(ObfuscatorError::LicenseError(format!(
+                    "API returned the revocation list of script {}, not {}",
+                    list.script_id, script_id
+                ))
+                .into());
+            }
+
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.crl", script_id)));
+            fs::write(&output, format!("{}\n", signed.trim()))
+                .with_context(|| format!("Failed to write revocation list: {:?}", output))?;
+            let issued_at =
+                chrono::DateTime::<chrono::Utc>::from_timestamp(list.issued_at as i64, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_else(|| "Invalid timestamp".to_string());
+
+            if reporter.is_text() {
+                println!("\n✓ Revocation list saved");
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                println!("  Script ID: {}", script_id);
+                println!("  Revoked:   {} key(s)", list.revoked.len());
+                println!("  Issued:    {}", issued_at);
+                println!("  Written:   {:?}", output);
+                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            }
+
+            reporter.finish(
+                "license",
+                RevocationListResult {
+                    script_id,
+                    output,
+                    revoked: list.revoked.len(),
+                    issued_at,
+                },
+            )
+        }
+    }
+}
+
+/// `license seats` usage column: `2/5`, or `2` when unlimited
+fn seat_usage(active: usize, limit: Option<u32>) -> String {
+    match limit {
+        Some(limit) => format!("{}/{}", active, limit),
+        None => active.to_string(),
+    }
+}
+
+/// Upload queued usage events, one batch request at a time
+fn upload_events(queue: &EventQueue, api_key: &str) -> Result<FlushReport> {
+    queue.flush(|endpoint, events| {
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| TrackObfuscationRequest {
+                api_key: api_key.to_string(),
+                ..event.clone()
+            })
+            .collect();
+        create_client(endpoint)?.track_obfuscation_batch(&events)?;
+        Ok(())
+    })
+}
+
+/// Project a script id is derived from when none is given: the input's
+/// file name without extension
+fn project_name(input: Option<&Path>) -> String {
+    input
+        .and_then(Path::file_stem)
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "stdin".to_string())
+}
+
+/// Where `auth login` keeps the API key
+fn credential_store() -> CredentialStore {
+    CredentialStore::new(Config::config_dir())
+}
+
+/// The developer API key: `LUAU_OBFUSCATOR_API_KEY`, then the key saved
+/// with `auth login`, then the config's `api_key`
+fn api_credential(config: &Config) -> Result<Option<ApiCredential>> {
+    if let Some(api_key) = std::env::var(API_KEY_ENV).ok().filter(|k| !k.trim().is_empty()) {
+        return Ok(Some(ApiCredential {
+            api_key: Zeroizing::new(api_key),
+            source: CredentialSource::Environment,
+        }));
+    }
+    if let Some(credential) = credential_store().load()? {
+        return Ok(Some(credential));
+    }
+    Ok(config.api_key.clone().map(|api_key| ApiCredential {
+        api_key: Zeroizing::new(api_key),
+        source: CredentialSource::Config,
+    }))
+}
+
+/// `--api-key`, or the key `api_credential` finds, for endpoints that
+/// require one
+fn required_api_key(api_key: Option<String>) -> Result<Zeroizing<String>> {
+    if let Some(api_key) = api_key {
+        return Ok(Zeroizing::new(api_key));
+    }
+    Ok(api_credential(&Config::load()?)?
+        .ok_or_else(|| {
+            ObfuscatorError::ConfigError(format!(
+                "No API key: pass --api-key, run `auth login` or set {}",
+                API_KEY_ENV
+            ))
+        })?
+        .api_key)
+}
+
+/// Fingerprints from the signed revocation list at `path`, which must be
+/// `script_id`'s and must not revoke `license_key`
+fn load_revocation_list(path: &Path, script_id: &str, license_key: &str) -> Result<Vec<String>> {
+    let signed = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read revocation list: {:?}", path))?;
+    let list = RevocationList::verify(&signed, &required_api_key(None)?)
+        .with_context(|| format!("Failed to load revocation list: {:?}", path))?;
+    if list.script_id != script_id {
+        return Err(ObfuscatorError::LicenseError(format!(
+            "Revocation list {:?} is for script {}, not {}",
+            path, list.script_id, script_id
+        ))
+        .into());
+    }
+    if list.contains(license_key) {
+        return Err(ObfuscatorError::LicenseError(format!(
+            "License key {} is revoked",
+            license_key
+        ))
+        .into());
+    }
+    info!("Loaded {} revoked key(s) from {:?}", list.revoked.len(), path);
+    Ok(list.revoked)
+}
+
+/// The API key for endpoints that also accept anonymous requests
+fn api_key_or_empty(config: &Config) -> Result<Zeroizing<String>> {
+    match api_credential(config)? {
+        Some(credential) => {
+            debug!("Using the API key from the {}", credential.source);
+            Ok(credential.api_key)
+        }
+        None => {
+            info!("No API key saved (run `auth login`); sending requests without one");
+            Ok(Zeroizing::new(String::new()))
+        }
+    }
+}
+
+/// Look `place_id` up with Roblox Open Cloud, checking it against the
+/// configured owner
+fn verify_roblox_place(place_id: u64, config: &Config) -> Result<VerifiedPlace> {
+    let api_key = std::env::var(OPEN_CLOUD_API_KEY_ENV)
+        .ok()
+        .filter(|k| !k.trim().is_empty())
+        .or_else(|| config.roblox_api_key.clone())
+        .map(Zeroizing::new)
+        .ok_or_else(|| {
+            ObfuscatorError::ConfigError(format!(
+                "--verify-place needs an Open Cloud API key: set {} or roblox_api_key in the \
+                 config",
+                OPEN_CLOUD_API_KEY_ENV
+            ))
+        })?;
+    let owner = match (config.place_owner_user, config.place_owner_group) {
+        (Some(_), Some(_)) => {
+            return Err(ObfuscatorError::ConfigError(
+                "Set only one of place_owner_user and place_owner_group".to_string(),
+            )
+            .into())
+        }
+        (Some(user), None) => Some(PlaceOwner::User(user)),
+        (None, Some(group)) => Some(PlaceOwner::Group(group)),
+        (None, None) => {
+            info!("No place owner configured; only checking that the place exists");
+            None
+        }
+    };
+
+    RobloxCloudClient::new(&api_key)?.verify_place(place_id, owner)
+}
+
+/// Note what the loader changed while reading a script
+fn report_input(file: &SourceFile, options: &InputOptions, reporter: &mut Reporter) {
+    if let Some(bom) = file.bom {
+        info!("  Input: removed a {} byte order mark", bom);
+    }
+    if file.line_ending == LineEnding::Crlf {
+        match file.output_line_ending(options) {
+            LineEnding::Crlf => info!("  Input: CRLF line endings (kept in the output)"),
+            LineEnding::Lf => info!("  Input: CRLF line endings (output uses LF)"),
+        }
+    }
+    if file.invalid_bytes > 0 {
+        let handled = match options.invalid_bytes {
+            InvalidBytes::Latin1 => "read as Latin-1",
+            _ => "written as \\ddd escapes",
+        };
+        reporter.warn(format!(
+            "{} byte(s) in the input aren't UTF-8 and were {}",
+            file.invalid_bytes, handled
+        ));
+    }
+}
+
+/// Bundle the project or folder at `path` for `protect --bundle`, returning
+/// the bundle and the project's name
+fn bundle_project(
+    path: &Path,
+    entry: Option<&str>,
+    options: &InputOptions,
+    reporter: &mut Reporter,
+) -> Result<(SourceFile, String)> {
+    let project = if path.is_file() || path.join(DEFAULT_PROJECT_FILE).is_file() {
+        RojoProject::load(path)?
+    } else {
+        RojoProject::from_folder(path)?
+    };
+    let bundle = Bundler::new()
+        .with_input_options(options.clone())
+        .bundle(&project, entry)?;
+
+    info!(
+        "  Bundle: {} with {} module(s)",
+        project.files[bundle.entry].display_path(),
+        bundle.modules.len()
+    );
+    for module in &bundle.modules {
+        debug!("    {}", project.files[*module].display_path());
+    }
+    for require in &bundle.unresolved {
+        reporter.warn(format!(
+            "{}:{}: require left as written; it couldn't be matched to a project script",
+            project.files[require.file].relative_path.display(),
+            require.line
+        ));
+    }
+    for reference in &bundle.script_references {
+        reporter.warn(format!(
+            "{}:{}: `script` in a bundled module refers to the bundle's script",
+            project.files[reference.file].relative_path.display(),
+            reference.line
+        ));
+    }
+
+    let file = SourceFile {
+        text: bundle.code,
+        line_ending: bundle.line_ending,
+        ..SourceFile::default()
+    };
+    Ok((file, project.name))
+}
+
+/// Attach the input file name to a parse diagnostic
+fn with_origin(error: anyhow::Error, input: &Path) -> anyhow::Error {
+    match error.downcast::<ObfuscatorError>() {
+        Ok(ObfuscatorError::SyntaxError(diagnostic)) => ObfuscatorError::SyntaxError(Box::new(
+            diagnostic.with_origin(input.display().to_string()),
+        ))
+        .into(),
+        Ok(other) => other.into(),
+        Err(other) => ObfuscatorError::ParseError(format!("{:#}", other)).into(),
+    }
+}
+
+/// Password for a run without `--password`; the key can't be recovered later
+/// Key for `script_id`: the keystore's salt and key version when it has an
+/// entry, a fresh salt otherwise
+///
+/// Choosing a different KDF profile than recorded starts the next key version.
+fn keystore_context(
+    keystore: &Keystore,
+    script_id: &str,
+    password: &str,
+    kdf_profile: Option<KdfProfile>,
+) -> Result<CryptoContext> {
+    let params: Option<KdfParams> = kdf_profile.map(Into::into);
+    match keystore.get(script_id) {
+        Some(entry) if params.map_or(true, |params| params == entry.kdf) => {
+            info!("Reusing keystore salt for {} (key version {})", script_id, entry.key_version);
+            entry.crypto_context(password)
+        }
+        Some(entry) => Ok(CryptoContext::with_params(password, None, params.unwrap_or(entry.kdf))?
+            .with_key_version(entry.key_version + 1)),
+        None => CryptoContext::with_params(password, None, params.unwrap_or_default()),
+    }
+}
+
+/// The tier to build at when `requested` is asked for under a license sold
+/// at `licensed`, warning when it is lowered
+fn entitled_tier(
+    requested: ObfuscationTier,
+    licensed: Option<&str>,
+    tier_policy: Option<TierPolicy>,
+    reporter: &mut Reporter,
+) -> Result<ObfuscationTier> {
+    let Some(licensed) = licensed else {
+        return Ok(requested);
+    };
+    let licensed = ObfuscationTier::from_name(licensed).ok_or_else(|| {
+        ObfuscatorError::LicenseError(format!("License has an unknown tier: {:?}", licensed))
+    })?;
+    let policy = match tier_policy {
+        Some(policy) => policy.into(),
+        None => Config::load()?.tier_policy,
+    };
+    let enforced = requested.entitled(licensed, policy)?;
+    if enforced != requested {
+        reporter.warn(format!(
+            "The license covers the {} tier; protecting at {} instead of {}",
+            licensed.name(),
+            enforced.name(),
+            requested.name()
+        ));
+    }
+    Ok(enforced)
+}
+
+/// The Roblox API database `update-api-db` refreshed, unless the config pins
+/// a different version
+fn api_database(config: &Config) -> ApiDatabase {
+    ApiDatabase::load(Some(&config.resolved_cache_dir()), config.api_db_version.as_deref())
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn random_password() -> String {
+    use rand::distributions::{Alphanumeric, DistString};
+    Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+}
+
+/// Validate a license online, reusing a cached validation when allowed,
+/// and return the tier it was sold at if the API reported one
+///
+/// Without `require_online`, a validation cached within the TTL with a tier
+/// skips the request, and an unreachable API only produces a warning (and no
+/// tier, so the requested one is kept).
+fn validate_license(
+    endpoint: &str,
+    request: &ValidateLicenseRequest,
+    require_online: bool,
+    reporter: &mut Reporter,
+) -> Result<Option<String>> {
+    let config = Config::load()?;
+    let ttl = config
+        .validation_cache_ttl
+        .unwrap_or(DEFAULT_TOKEN_TTL_SECONDS);
+    let cache = match TokenCache::open(Config::validation_token_dir(), ttl) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            warn!("Validation cache unavailable: {:#}", e);
+            None
+        }
+    };
+
+    if !require_online {
+        // A validation cached before the API reported tiers can't vouch for one
+        let cached = cache.as_ref().and_then(|c| c.lookup(request));
+        if let Some(claims) = cached.filter(|claims| claims.tier.is_some()) {
+            let expires = chrono::DateTime::from_timestamp(claims.expires_at, 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default();
+            info!("✓ License validated (cached until {})", expires);
+            return Ok(claims.tier);
+        }
+    }
+
+    let api_client = create_client(endpoint).context("Failed to create API client")?;
+    match api_client.validate_license(request.clone()) {
+        Ok(response) => {
+            if !response.valid {
+                return Err(ObfuscatorError::LicenseError(format!(
+                    "License validation failed: {}",
+                    response.error.unwrap_or_else(|| "Unknown error".to_string())
+                ))
+                .into());
+            }
+            info!("✓ License validated successfully");
+            let tier = response.metadata.map(|metadata| metadata.tier);
+            if let Some(cache) = &cache {
+                if let Err(e) = cache.store(request, tier.clone()) {
+                    warn!("Failed to cache license validation: {:#}", e);
+                }
+            }
+            Ok(tier)
+        }
+        Err(e) if require_online => Err(e.context("License validation failed (--require-online)")),
+        Err(e) => {
+            reporter.warn(format!(
+                "License validation failed, running in offline mode: {}",
+                e
+            ));
+            Ok(None)
+        }
+    }
+}
+
+/// Generate licenses for every buyer listed in a CSV file
+fn generate_license_batch(
+    reporter: &mut Reporter,
+    api_client: &ApiClient,
+    api_key: &str,
+    script_id: &str,
+    batch_file: &Path,
+    output: Option<PathBuf>,
+    default_expiration: Option<&str>,
+    seats: SeatLimits,
+) -> Result<()> {
+    info!("Generating licenses in batch mode");
+    info!("  Script ID: {}", script_id);
+    info!("  Buyers file: {:?}", batch_file);
+
+    let content = fs::read_to_string(batch_file)
+        .with_context(|| format!("Failed to read batch file: {:?}", batch_file))?;
+    let records = batch::parse_buyers_csv(&content)?;
+
+    if records.is_empty() {
+        return Err(ObfuscatorError::ConfigError(format!(
+            "No buyers found in batch file: {:?}",
+            batch_file
+        ))
+        .into());
+    }
+
+    let pb = reporter.progress_bar(records.len() as u64);
+    pb.set_message("Generating licenses...");
+
+    let mut entries = Vec::with_capacity(records.len());
+    for record in &records {
+        entries.push(batch::generate_entry(
+            api_client,
+            api_key,
+            script_id,
+            record,
+            default_expiration,
+            seats,
+        ));
+        pb.inc(1);
+    }
+    pb.finish_with_message("✓ Batch complete");
+
+    let report = batch::BatchReport {
+        script_id: script_id.to_string(),
+        entries,
+    };
+
+    let output = output.unwrap_or_else(|| batch_file.with_extension("licenses.csv"));
+    batch::write_report(&report, &output)?;
+
+    let succeeded = report.succeeded().count();
+    let failed = report.failed().count();
+
+    let retry_file = batch::write_retry_file(&report, &output)?;
+
+    if reporter.is_text() {
+        println!("\n✓ Batch license generation finished");
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("  Generated: {}", succeeded);
+        println!("  Failed:    {}", failed);
+        println!("  Results:   {:?}", output);
+        if let Some(retry_path) = &retry_file {
+            println!("  Retry with: --batch {:?}", retry_path);
+        }
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    }
+
+    if retry_file.is_some() && succeeded == 0 {
+        return Err(ObfuscatorError::ApiError(format!(
+            "All {} license requests failed",
+            failed
+        ))
+        .into());
+    }
+
+    reporter.finish(
+        "generate-license",
+        BatchResult {
+            script_id: script_id.to_string(),
+            generated: succeeded,
+            failed,
+            results_file: output,
+            retry_file,
+        },
+    )
 }
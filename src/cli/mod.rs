@@ -1,7 +1,11 @@
 //! CLI module - Command-line interface implementation
 
 mod args;
+mod batch;
 mod commands;
+mod output;
+mod review;
+mod watch;
 
 pub use args::Cli;
 
@@ -0,0 +1,740 @@
+//! Human vs machine-readable command output
+
+use super::args::OutputFormat;
+use crate::analysis::{AnalysisResult, DynamicGlobal, KeptKey, ScopeKind, Terminator, VariableType};
+use crate::api::{CredentialSource, LicenseSeats, SeatLimits};
+use crate::codegen::KeySource;
+use crate::crypto::{Compression, TestVectors};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::memstats::MemoryStats;
+use crate::obfuscation::{PlaintextString, TransformDelta};
+use crate::parser::{ParseResult, Sensitivity, StringLiteral};
+use crate::progress::{ProgressSink, Stage};
+use crate::recovery::{
+    DetectabilityReport, LeakReport, LineEntropy, SurvivingLiteral, ValidationCheck,
+};
+use crate::utils::errors::exit_code;
+use crate::verify::Observation;
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::warn;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Envelope printed on stdout in `--format json` mode
+#[derive(Debug, Serialize)]
+struct JsonReport<'a, T: Serialize> {
+    command: &'a str,
+    status: &'a str,
+    duration_ms: u64,
+    warnings: &'a [String],
+    /// Structured findings of the run, see `DIAGNOSTIC_IDS`
+    #[serde(skip_serializing_if = "<[Diagnostic]>::is_empty")]
+    diagnostics: &'a [Diagnostic],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Process exit code on failure, see `EXIT_CODES_HELP`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+}
+
+/// `protect` result
+#[derive(Debug, Serialize)]
+pub struct ProtectResult {
+    /// `None` when the script was read from stdin
+    pub input: Option<PathBuf>,
+    /// `None` when the script was written to stdout
+    pub output: Option<PathBuf>,
+    /// Given with `--script-id` or derived from the project and source
+    pub script_id: String,
+    /// Tier the script was protected at, after enforcing the license's
+    pub tier: String,
+    /// Tier the validated license was sold at, when the API reported one
+    pub licensed_tier: Option<String>,
+    pub license_key: String,
+    pub hwid: Option<u64>,
+    pub seed: Option<u64>,
+    pub input_bytes: usize,
+    pub output_bytes: usize,
+    /// Output (loader and payload together when split) after gzip
+    pub compressed_bytes: usize,
+    /// Output as a share of the Roblox script size budget, in percent
+    pub budget_percent: f64,
+    /// Bytes each transform added, in the order they were applied
+    pub size_deltas: Vec<TransformDelta>,
+    pub compression: Compression,
+    /// Bytes compression saved, net of the decompressor it added
+    pub compression_saved: i64,
+    /// Encrypted payload chunk of a split build (`output` is the loader)
+    pub output_payload: Option<PathBuf>,
+    /// Exported remote string payload, if any
+    pub payload: Option<PathBuf>,
+    /// Symbol map for `recover` and `keygen`
+    pub symbol_map: Option<PathBuf>,
+    /// Table keys `--table-keys` left as written, with the reason
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kept_keys: Vec<KeptKey>,
+    /// Strings a plaintext rule kept unencrypted, with the rule
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plaintext_strings: Vec<PlaintextString>,
+    /// What the protected build did under `--verify` (the same as the original)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Observation>,
+    pub stats: ParseStats,
+    /// Allocations per stage; only in `memstats` builds
+    #[serde(skip_serializing_if = "MemoryStats::is_empty")]
+    pub memory: MemoryStats,
+}
+
+/// Literal and declaration counts from parsing
+#[derive(Debug, Serialize)]
+pub struct ParseStats {
+    pub strings: usize,
+    pub numbers: usize,
+    pub functions: usize,
+    pub type_aliases: usize,
+}
+
+/// `validate` result
+#[derive(Debug, Serialize)]
+pub struct ValidateResult {
+    pub file: PathBuf,
+    pub size_bytes: usize,
+    /// No check failed
+    pub valid: bool,
+    pub script_id: Option<String>,
+    pub checks: Vec<ValidationCheck>,
+}
+
+/// `diff` result
+#[derive(Debug, Serialize)]
+pub struct DiffResult {
+    pub original: PathBuf,
+    pub protected: PathBuf,
+    /// String literals checked (at least 3 characters)
+    pub literals: usize,
+    pub surviving_literals: Vec<SurvivingLiteral>,
+    /// Surviving literals classified as sensitive
+    pub leaks: usize,
+    pub identifiers: usize,
+    pub retained_identifiers: Vec<String>,
+    pub original_entropy: f64,
+    pub protected_entropy: f64,
+    pub line_entropy: Vec<LineEntropy>,
+}
+
+/// `score` result
+#[derive(Debug, Serialize)]
+pub struct ScoreResult {
+    pub file: PathBuf,
+    pub decrypt_call_ratio: f64,
+    #[serde(flatten)]
+    pub report: DetectabilityReport,
+}
+
+/// `inspect-watermark` result
+#[derive(Debug, Serialize)]
+pub struct InspectWatermarkResult {
+    pub leaks: PathBuf,
+    pub builds: usize,
+    /// Report file, when written
+    pub output: Option<PathBuf>,
+    #[serde(flatten)]
+    pub report: LeakReport,
+}
+
+/// `minify` result
+#[derive(Debug, Serialize)]
+pub struct MinifyResult {
+    pub input: PathBuf,
+    /// `None` when written to stdout
+    pub output: Option<PathBuf>,
+    pub original_bytes: usize,
+    pub minified_bytes: usize,
+    /// Locals renamed
+    pub renamed: usize,
+}
+
+/// `analyze` result: what protecting the script would work with
+#[derive(Debug, Serialize)]
+pub struct AnalyzeResult {
+    pub input: PathBuf,
+    /// Services, globals, datatypes and remote classes the script uses
+    pub roblox_apis: Vec<ApiUse>,
+    pub scopes: Vec<ScopeSummary>,
+    pub control_flow: ControlFlowSummary,
+    pub strings: StringBreakdown,
+    /// Every declared name, and whether it would be renamed
+    pub names: Vec<NameDecision>,
+    /// `_G`, `shared` and `getfenv` accesses that keep names
+    pub dynamic_globals: Vec<DynamicGlobal>,
+}
+
+/// One Roblox API the script uses
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ApiUse {
+    /// `global`, `service`, `datatype` or `remote`
+    pub kind: &'static str,
+    pub name: String,
+}
+
+/// One scope and the names it declares
+#[derive(Debug, Serialize)]
+pub struct ScopeSummary {
+    pub id: usize,
+    pub parent: Option<usize>,
+    /// `global`, `function` or `block`
+    pub kind: &'static str,
+    pub variables: Vec<String>,
+    /// Outer locals a function captures
+    pub upvalues: Vec<String>,
+}
+
+/// Shape of the top-level chunk's control flow graph
+#[derive(Debug, Serialize)]
+pub struct ControlFlowSummary {
+    pub blocks: usize,
+    pub edges: usize,
+    pub statements: usize,
+    /// Blocks ending in a condition
+    pub branches: usize,
+    /// Blocks ending in `return`
+    pub returns: usize,
+    pub exits: usize,
+}
+
+/// String literals by sensitivity
+#[derive(Debug, Serialize)]
+pub struct StringBreakdown {
+    pub high: usize,
+    pub medium: usize,
+    pub low: usize,
+    pub literals: Vec<StringLiteral>,
+}
+
+/// Whether obfuscation would rename a declared name
+#[derive(Debug, Serialize)]
+pub struct NameDecision {
+    pub name: String,
+    /// First declaration
+    pub line: usize,
+    pub renamed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl AnalyzeResult {
+    pub fn new(input: PathBuf, parse_result: &ParseResult, analysis: &AnalysisResult) -> Self {
+        let mut roblox_apis: Vec<ApiUse> = analysis
+            .roblox_apis
+            .iter()
+            .map(|api| ApiUse {
+                kind: api.kind(),
+                name: api.name().to_string(),
+            })
+            .collect();
+        roblox_apis.sort();
+        roblox_apis.dedup();
+
+        let scopes = analysis
+            .scopes
+            .iter()
+            .map(|scope| {
+                let mut variables: Vec<String> = scope.variables.keys().cloned().collect();
+                variables.sort();
+                ScopeSummary {
+                    id: scope.id,
+                    parent: scope.parent,
+                    kind: match scope.kind {
+                        ScopeKind::Global => "global",
+                        ScopeKind::Function => "function",
+                        ScopeKind::Block => "block",
+                    },
+                    variables,
+                    upvalues: scope.upvalues.iter().map(|u| u.name.clone()).collect(),
+                }
+            })
+            .collect();
+
+        let cfg = &analysis.control_flow;
+        let control_flow = ControlFlowSummary {
+            blocks: cfg.blocks.len(),
+            edges: cfg.blocks.values().map(|b| b.successors.len()).sum(),
+            statements: cfg.blocks.values().map(|b| b.statements.len()).sum(),
+            branches: cfg
+                .blocks
+                .values()
+                .filter(|b| matches!(b.terminator, Terminator::Branch(_)))
+                .count(),
+            returns: cfg
+                .blocks
+                .values()
+                .filter(|b| matches!(b.terminator, Terminator::Return(_)))
+                .count(),
+            exits: cfg.exit_blocks.len(),
+        };
+
+        let count = |level: Sensitivity| {
+            parse_result.strings.iter().filter(|s| s.sensitivity == level).count()
+        };
+        let strings = StringBreakdown {
+            high: count(Sensitivity::High),
+            medium: count(Sensitivity::Medium),
+            low: count(Sensitivity::Low),
+            literals: parse_result.strings.clone(),
+        };
+
+        // Same rules as the name mangler: preserved names keep their text,
+        // and function names are only renamed from the standard tier up
+        let mut declared: BTreeMap<&str, (usize, bool)> = BTreeMap::new();
+        for var in analysis.scopes.iter().flat_map(|scope| scope.variables.values()) {
+            let entry = declared
+                .entry(var.name.as_str())
+                .or_insert((var.line, false));
+            entry.0 = entry.0.min(var.line);
+            entry.1 |= var.var_type == VariableType::Function;
+        }
+        let mut names: Vec<NameDecision> = declared
+            .into_iter()
+            .map(|(name, (line, function))| {
+                let dynamic = analysis
+                    .dynamic_globals
+                    .iter()
+                    .find(|access| access.kept.iter().any(|kept| kept == name));
+                let (renamed, reason) = if let Some(access) = dynamic {
+                    (false, Some(format!("reached through `{}` on line {}", access.via, access.line)))
+                } else if analysis.preserved_identifiers.iter().any(|kept| kept == name) {
+                    (false, Some("Roblox API".to_string()))
+                } else if function {
+                    (true, Some("function names are kept at the basic tier".to_string()))
+                } else {
+                    (true, None)
+                };
+                NameDecision {
+                    name: name.to_string(),
+                    line,
+                    renamed,
+                    reason,
+                }
+            })
+            .collect();
+        names.sort_by_key(|decision| decision.line);
+
+        Self {
+            input,
+            roblox_apis,
+            scopes,
+            control_flow,
+            strings,
+            names,
+            dynamic_globals: analysis.dynamic_globals.clone(),
+        }
+    }
+}
+
+/// `generate-license` result for a single buyer
+#[derive(Debug, Serialize)]
+pub struct LicenseResult {
+    pub license_key: String,
+    pub script_id: String,
+    pub buyer_userid: u64,
+    /// Expiration as RFC 3339
+    pub expires: Option<String>,
+    #[serde(flatten)]
+    pub seats: SeatLimits,
+}
+
+/// `license revoke` result
+#[derive(Debug, Serialize)]
+pub struct RevokeResult {
+    pub license_key: String,
+    pub script_id: String,
+    /// Revocation time as RFC 3339
+    pub revoked_at: String,
+}
+
+/// `license crl` result
+#[derive(Debug, Serialize)]
+pub struct RevocationListResult {
+    pub script_id: String,
+    pub output: PathBuf,
+    /// Keys on the list
+    pub revoked: usize,
+    /// Issue time as RFC 3339
+    pub issued_at: String,
+}
+
+/// `license seats` result
+#[derive(Debug, Serialize)]
+pub struct LicenseSeatsResult {
+    pub licenses: Vec<SeatUsage>,
+}
+
+/// One license's seat limits, live sessions and usage
+#[derive(Debug, Serialize)]
+pub struct SeatUsage {
+    #[serde(flatten)]
+    pub seats: LicenseSeats,
+    pub active_servers: usize,
+    pub active_users: usize,
+    /// A new server or player would be rejected
+    pub full: bool,
+}
+
+impl From<LicenseSeats> for SeatUsage {
+    fn from(seats: LicenseSeats) -> Self {
+        Self {
+            active_servers: seats.active_servers(),
+            active_users: seats.active_users(),
+            full: seats.is_full(),
+            seats,
+        }
+    }
+}
+
+/// `rekey` result
+#[derive(Debug, Serialize)]
+pub struct RekeyResult {
+    pub script_id: String,
+    pub output: PathBuf,
+    pub symbol_map: PathBuf,
+    pub key_version: u32,
+    /// Encrypted strings and pool constants re-encrypted
+    pub entries: usize,
+}
+
+/// `keygen` result: one entry per configured key source
+#[derive(Debug, Serialize)]
+pub struct KeygenResult {
+    pub script_id: String,
+    pub license_key: String,
+    pub shares: Vec<KeyShare>,
+}
+
+/// `publish-payload` result
+#[derive(Debug, Serialize)]
+pub struct PublishPayloadResult {
+    pub script_id: String,
+    pub version: u32,
+    /// Where the envelope was written, when not uploaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// Highest version the API serves, when uploaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_version: Option<u32>,
+}
+
+/// `auth` result: the API key API commands will use
+#[derive(Debug, Serialize)]
+pub struct AuthResult {
+    pub logged_in: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<CredentialSource>,
+    /// Masked to the last four characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+/// `gen-tests` result
+#[derive(Debug, Serialize)]
+pub struct GenTestsResult {
+    pub output: PathBuf,
+    pub script_id: String,
+    pub strings: usize,
+    pub constants: usize,
+    pub license_check: bool,
+}
+
+/// `vectors` result
+#[derive(Debug, Serialize)]
+pub struct VectorsResult {
+    /// Vectors file written or verified
+    pub file: Option<PathBuf>,
+    pub watermarks: usize,
+    pub ciphertexts: usize,
+    pub stego_patterns: usize,
+    /// The vectors themselves, when not written to a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vectors: Option<TestVectors>,
+}
+
+/// Value a buyer must set on one key source
+#[derive(Debug, Serialize)]
+pub struct KeyShare {
+    pub source: KeySource,
+    pub value: String,
+}
+
+/// `generate-license --batch` result
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub script_id: String,
+    pub generated: usize,
+    pub failed: usize,
+    pub results_file: PathBuf,
+    pub retry_file: Option<PathBuf>,
+}
+
+/// `release` result
+#[derive(Debug, Serialize)]
+pub struct ReleaseResult {
+    pub script_id: String,
+    pub built: usize,
+    pub failed: usize,
+    pub manifest: PathBuf,
+}
+
+/// Shows the pipeline's current stage and pass as a progress bar message
+pub struct BarProgress(pub ProgressBar);
+
+impl ProgressSink for BarProgress {
+    fn stage_started(&self, stage: Stage) {
+        self.0.set_message(format!("{}...", stage));
+    }
+
+    fn items_processed(&self, stage: Stage, done: usize, total: usize) {
+        self.0.set_message(format!("{} ({}/{})...", stage, done, total));
+    }
+}
+
+/// Collects warnings and timing for a command and renders its final result
+pub struct Reporter {
+    format: OutputFormat,
+    started: Instant,
+    warnings: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    /// Print the structured result to stderr
+    stderr: bool,
+}
+
+impl Reporter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            started: Instant::now(),
+            warnings: Vec::new(),
+            diagnostics: Vec::new(),
+            stderr: false,
+        }
+    }
+
+    /// Print the structured result to stderr, for commands that write
+    /// their output to stdout
+    pub fn use_stderr(&mut self) {
+        self.stderr = true;
+    }
+
+    /// Whether human-readable text should be printed
+    pub fn is_text(&self) -> bool {
+        self.format == OutputFormat::Text
+    }
+
+    /// Log a warning and include it in the structured result
+    pub fn warn(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        warn!("{}", message);
+        self.warnings.push(message);
+    }
+
+    /// Include diagnostics in the structured result; warnings and errors are
+    /// listed in `warnings` too
+    pub fn diagnose(&mut self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            if diagnostic.severity >= Severity::Warning {
+                self.warnings.push(diagnostic.to_string());
+            }
+        }
+        self.diagnostics.extend_from_slice(diagnostics);
+    }
+
+    /// Print the diagnostics as a table (text mode only)
+    pub fn print_diagnostics(&self) {
+        if !self.is_text() || self.diagnostics.is_empty() {
+            return;
+        }
+
+        let mut table = String::from("\n⚠️  Diagnostics\n");
+        table.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+        for diagnostic in &self.diagnostics {
+            let span = diagnostic.span.map(|s| s.to_string()).unwrap_or_default();
+            table.push_str(&format!(
+                "  {:<8} {:<19} {:<12} {}\n",
+                diagnostic.severity.to_string(),
+                diagnostic.id,
+                span,
+                diagnostic.message
+            ));
+            if let Some(suggestion) = &diagnostic.suggestion {
+                table.push_str(&format!("  {:<41} → {}\n", "", suggestion));
+            }
+        }
+        table.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if self.stderr {
+            eprintln!("{}", table);
+        } else {
+            println!("{}", table);
+        }
+    }
+
+    /// Progress bar in text mode; hidden so stdout stays valid JSON otherwise
+    pub fn progress_bar(&self, len: u64) -> ProgressBar {
+        if !self.is_text() {
+            return ProgressBar::hidden();
+        }
+
+        let pb = ProgressBar::new(len);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{msg} [{bar:40.cyan/blue}] {pos}/{len}")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+        pb
+    }
+
+    /// Print the structured success result (no-op in text mode)
+    pub fn finish<T: Serialize>(&self, command: &str, result: T) -> Result<()> {
+        self.print(command, "success", Some(result), None, None)
+    }
+
+    /// Print the structured failure result (no-op in text mode)
+    pub fn fail(&self, command: &str, error: &anyhow::Error) -> Result<()> {
+        self.print::<()>(
+            command,
+            "error",
+            None,
+            Some(format!("{:#}", error)),
+            Some(exit_code(error)),
+        )
+    }
+
+    fn print<T: Serialize>(
+        &self,
+        command: &str,
+        status: &str,
+        result: Option<T>,
+        error: Option<String>,
+        exit_code: Option<i32>,
+    ) -> Result<()> {
+        if self.is_text() {
+            return Ok(());
+        }
+
+        let report = JsonReport {
+            command,
+            status,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            warnings: &self.warnings,
+            diagnostics: &self.diagnostics,
+            result,
+            error,
+            exit_code,
+        };
+        let report = serde_json::to_string_pretty(&report)?;
+        if self.stderr {
+            eprintln!("{}", report);
+        } else {
+            println!("{}", report);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_envelope_shape() {
+        let report = JsonReport {
+            command: "validate",
+            status: "success",
+            duration_ms: 3,
+            warnings: &["careful".to_string()],
+            diagnostics: &[],
+            result: Some(serde_json::json!({ "protected": true })),
+            error: None,
+            exit_code: None,
+        };
+
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["status"], "success");
+        assert_eq!(value["result"]["protected"], true);
+        assert_eq!(value["warnings"][0], "careful");
+        assert!(value.get("error").is_none());
+        assert!(value.get("exit_code").is_none());
+        assert!(value.get("diagnostics").is_none());
+    }
+
+    #[test]
+    fn test_analyze_result_reports_decisions() {
+        let source = r#"local Players = game:GetService("Players")
+local apiKey = "sk_live_0123456789abcdef"
+local function grant(player)
+    _G.bonus = player
+end
+if apiKey then
+    grant(Players.LocalPlayer)
+end
+"#;
+        let parsed = crate::parser::LuauParser::new().parse(source).unwrap();
+        let analysis = crate::analysis::Analyzer::new(Default::default())
+            .analyze(&parsed)
+            .unwrap();
+        let result = AnalyzeResult::new(PathBuf::from("a.lua"), &parsed, &analysis);
+
+        assert!(result.roblox_apis.contains(&ApiUse {
+            kind: "service",
+            name: "Players".to_string()
+        }));
+        assert_eq!(result.strings.high, 1);
+        assert_eq!(result.strings.literals.len(), 2);
+        assert_eq!(result.scopes[0].kind, "global");
+        assert_eq!(result.control_flow.branches, 1);
+
+        let decision = |name: &str| result.names.iter().find(|d| d.name == name).unwrap();
+        assert!(decision("apiKey").renamed && decision("apiKey").reason.is_none());
+        assert!(!decision("Players").renamed);
+        assert!(decision("grant").renamed && decision("grant").reason.is_some());
+
+        let value = serde_json::to_value(&result).unwrap();
+        assert_eq!(value["names"][0]["name"], "Players");
+        assert!(value["names"][1].get("reason").is_none());
+    }
+
+    #[test]
+    fn test_warnings_are_collected() {
+        let mut reporter = Reporter::new(OutputFormat::Json);
+        reporter.warn("offline mode");
+
+        assert!(!reporter.is_text());
+        assert_eq!(reporter.warnings, vec!["offline mode"]);
+    }
+
+    #[test]
+    fn test_diagnostics_are_exported() {
+        use crate::diagnostics::Span;
+
+        let mut reporter = Reporter::new(OutputFormat::Json);
+        reporter.diagnose(&[
+            Diagnostic::warning("dynamic-global", "`_G.Handler` reads a global by name")
+                .at(Span::line(4)),
+            Diagnostic::new("flatten-limit", Severity::Note, "block too large")
+                .at(Span::lines(9, 80)),
+        ]);
+        assert_eq!(
+            reporter.warnings,
+            vec!["warning[dynamic-global] line 4: `_G.Handler` reads a global by name"]
+        );
+
+        let value = serde_json::to_value(&reporter.diagnostics).unwrap();
+        assert_eq!(value[0]["id"], "dynamic-global");
+        assert_eq!(value[0]["severity"], "warning");
+        assert_eq!(value[1]["span"]["end_line"], 80);
+        assert!(value[1].get("suggestion").is_none());
+    }
+}
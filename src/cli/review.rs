@@ -0,0 +1,129 @@
+//! `protect --interactive`: review transformation decisions in a terminal UI
+//!
+//! Lists every encrypted string, renamed identifier and flattened function
+//! of a first run. Items switched off here are left alone when the output
+//! is generated again.
+
+use crate::obfuscation::{ReviewKind, ReviewPlan};
+use anyhow::Result;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::crossterm::ExecutableCommand;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Terminal;
+use std::io::{stdout, Stdout};
+use std::path::Path;
+
+const HELP: &str = "↑/↓ move  space toggle  s/n/f toggle all strings/names/functions  \
+                    enter write  q cancel";
+
+/// Longest string value shown before it is cut
+const MAX_LABEL: usize = 48;
+
+/// Restores the terminal however the review ends
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = stdout().execute(LeaveAlternateScreen);
+    }
+}
+
+/// Let the user toggle `plan`'s items; `false` if they cancelled
+pub fn review(plan: &mut ReviewPlan, input: &Path) -> Result<bool> {
+    enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    stdout().execute(EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+
+    let mut state = ListState::default();
+    state.select(Some(0));
+    loop {
+        draw(&mut terminal, plan, &mut state, input)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let selected = state.selected().unwrap_or(0);
+        let last = plan.items.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down | KeyCode::Char('j') => state.select(Some((selected + 1).min(last))),
+            KeyCode::PageUp => state.select(Some(selected.saturating_sub(20))),
+            KeyCode::PageDown => state.select(Some((selected + 20).min(last))),
+            KeyCode::Home => state.select(Some(0)),
+            KeyCode::End => state.select(Some(last)),
+            KeyCode::Char(' ') => plan.toggle(selected),
+            KeyCode::Char('s') => toggle_all(plan, ReviewKind::String),
+            KeyCode::Char('n') => toggle_all(plan, ReviewKind::Name),
+            KeyCode::Char('f') => toggle_all(plan, ReviewKind::Function),
+            KeyCode::Enter => return Ok(true),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+/// Switch every item of `kind` off, or back on if they all are off
+fn toggle_all(plan: &mut ReviewPlan, kind: ReviewKind) {
+    let any_enabled = plan.items.iter().any(|item| item.kind == kind && item.enabled);
+    plan.set_all(kind, !any_enabled);
+}
+
+fn draw(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    plan: &ReviewPlan,
+    state: &mut ListState,
+    input: &Path,
+) -> Result<()> {
+    let items: Vec<ListItem> = plan
+        .items
+        .iter()
+        .map(|item| {
+            let label = match item.kind {
+                ReviewKind::String => {
+                    let mut value: String = item.label.chars().take(MAX_LABEL).collect();
+                    if item.label.chars().count() > MAX_LABEL {
+                        value.push('…');
+                    }
+                    format!("{:?}  line {}:{}", value, item.line, item.column)
+                }
+                ReviewKind::Name => format!("{} → {}", item.label, item.detail),
+                ReviewKind::Function => format!("{}  {}", item.label, item.detail),
+            };
+            let check = if item.enabled { "[x]" } else { "[ ]" };
+            ListItem::new(Line::from(format!("{} {:<8} {}", check, item.kind, label)))
+        })
+        .collect();
+    let kept = plan.items.iter().filter(|item| item.enabled).count();
+    let title = format!(
+        " Review {} — {} of {} transformations kept ",
+        input.display(),
+        kept,
+        plan.items.len()
+    );
+
+    terminal.draw(|frame| {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.size());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, areas[0], state);
+        frame.render_widget(Paragraph::new(HELP), areas[1]);
+    })?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+//! `--watch` mode: re-run a command whenever its inputs change
+//!
+//! Polls modification times instead of using OS notifications so it behaves
+//! the same on every platform and with editors that save by renaming.
+
+use anyhow::Result;
+use log::{error, info};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often watched paths are scanned
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Quiet period after a change before re-running, so a burst of saves
+/// (format-on-save, Rojo syncs) triggers a single build
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Files and directories to watch
+#[derive(Debug, Clone)]
+pub struct WatchTarget {
+    pub paths: Vec<PathBuf>,
+    /// Paths never reported as changed (the command's own output)
+    pub ignore: Vec<PathBuf>,
+}
+
+/// Modification time and size of a watched file
+type FileStamp = (Option<SystemTime>, u64);
+
+/// Polling file watcher
+pub struct FileWatcher {
+    target: WatchTarget,
+    snapshot: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl FileWatcher {
+    /// Create a watcher, recording the current state of the target
+    pub fn new(target: WatchTarget) -> Self {
+        let mut watcher = Self {
+            target,
+            snapshot: BTreeMap::new(),
+        };
+        watcher.snapshot = watcher.scan();
+        watcher
+    }
+
+    /// Paths added, removed or modified since the last call
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let current = self.scan();
+        let mut changed: Vec<PathBuf> = current
+            .iter()
+            .filter(|(path, stamp)| self.snapshot.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            self.snapshot
+                .keys()
+                .filter(|path| !current.contains_key(*path))
+                .cloned(),
+        );
+        self.snapshot = current;
+        changed
+    }
+
+    /// Block until something changes and the target has settled
+    pub fn wait_for_change(&mut self) -> Vec<PathBuf> {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let mut changed = self.changed();
+            if changed.is_empty() {
+                continue;
+            }
+
+            loop {
+                thread::sleep(DEBOUNCE);
+                let more = self.changed();
+                if more.is_empty() {
+                    break;
+                }
+                changed.extend(more);
+            }
+            changed.sort();
+            changed.dedup();
+            return changed;
+        }
+    }
+
+    fn scan(&self) -> BTreeMap<PathBuf, FileStamp> {
+        let ignore: Vec<PathBuf> = self.target.ignore.iter().map(|p| absolute(p)).collect();
+        let mut files = BTreeMap::new();
+        for path in &self.target.paths {
+            collect(&absolute(path), &ignore, &mut files);
+        }
+        files
+    }
+}
+
+/// Run `command` now and again after every change to `target`
+///
+/// Failures are reported and the watch continues, so a syntax error in the
+/// middle of an edit doesn't end the session. Stops only when interrupted.
+pub fn watch(target: WatchTarget, mut command: impl FnMut() -> Result<()>) -> Result<()> {
+    let mut watcher = FileWatcher::new(target);
+
+    loop {
+        match command() {
+            Ok(()) => info!("✓ Build up to date"),
+            Err(e) => error!("Build failed: {:#}", e),
+        }
+
+        info!("Watching for changes (Ctrl+C to stop)...");
+        let changed = watcher.wait_for_change();
+        for path in &changed {
+            info!("  Changed: {:?}", path);
+        }
+    }
+}
+
+/// Record `path`, recursing into directories and skipping hidden entries
+fn collect(path: &Path, ignore: &[PathBuf], files: &mut BTreeMap<PathBuf, FileStamp>) {
+    if ignore.iter().any(|i| path.starts_with(i)) {
+        return;
+    }
+
+    let Ok(metadata) = fs::metadata(path) else {
+        // Missing for now (mid-save); its reappearance counts as a change
+        return;
+    };
+
+    if metadata.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden {
+                collect(&entry.path(), ignore, files);
+            }
+        }
+    } else {
+        files.insert(
+            path.to_path_buf(),
+            (metadata.modified().ok(), metadata.len()),
+        );
+    }
+}
+
+/// Canonical path if it exists, otherwise resolved against the working directory
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| match env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_changes_outside_ignored_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let out = dir.path().join("out");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&out).unwrap();
+        fs::write(src.join("Main.lua"), "print(1)").unwrap();
+
+        let mut watcher = FileWatcher::new(WatchTarget {
+            paths: vec![dir.path().to_path_buf()],
+            ignore: vec![out.clone()],
+        });
+        assert!(watcher.changed().is_empty());
+
+        fs::write(out.join("Main.lua"), "obfuscated").unwrap();
+        assert!(watcher.changed().is_empty());
+
+        fs::write(src.join("Main.lua"), "print(12345)").unwrap();
+        fs::write(src.join("Util.lua"), "return {}").unwrap();
+        let changed = watcher.changed();
+        assert_eq!(changed.len(), 2);
+        assert!(changed[0].ends_with("src/Main.lua"));
+
+        fs::remove_file(src.join("Util.lua")).unwrap();
+        assert_eq!(watcher.changed().len(), 1);
+    }
+}
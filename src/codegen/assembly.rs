@@ -1,16 +1,200 @@
 //! Final script assembly
 
-use crate::crypto::{EncryptedData, Watermark};
+use super::encoding::{BinaryEncoding, BASE85_DECODER};
+use super::formatting::OutputFormatter;
+use super::metadata::{MetadataSigner, ScriptMetadata, WATERMARK_HEADER};
+use super::scrub::render_license_header;
+use super::shuffle::{TableShuffle, POOL_ORDER, SHUFFLE_FUNCTION, STRING_ORDER};
+use super::split::{self, SplitOutput};
+use super::vault::VAULT_TABLE;
+use super::{
+    CodeGenConfig, ExecutorCheck, RunContext, RuntimeGenerator, TamperPolicy, ViolationWebhook,
+    CORRUPT_FUNCTION, DECOMPRESS_FUNCTION, GUARD_FUNCTION, TAMPER_HANDLER,
+};
+use crate::crypto::{Compression, EncryptedData, Watermark};
+use crate::obfuscation::{
+    ConstantPool, DecryptionMode, PoolEntryKind, ANCHOR_HASH_INIT, ENVIRONMENT_HASH,
+    ENVIRONMENT_KEY, POOL_ACCESSOR, TRAP_FUNCTION,
+};
+use crate::parser::{strip_types, ConfigBlock, TypeAnnotations};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
 use base64::Engine;
 
+/// Header line recording the key generation a script is encrypted under
+pub const KEY_VERSION_HEADER: &str = "-- Key version: ";
+
+/// Header line naming the encoding of embedded `ct`/`nonce` fields; scripts
+/// without one use base64
+pub const ENCODING_HEADER: &str = "-- Data encoding: ";
+
+/// Script parts in single-file order; a split build divides them
+struct Sections {
+    /// Banner and watermark comments (never reformatted)
+    header: String,
+    /// Settings block the buyer may edit (never reformatted)
+    config: String,
+    /// Runtime, license and binding checks, key delivery, remote strings
+    head: String,
+    /// Embedded encrypted strings and `_decrypt`
+    data: String,
+    /// Constant pool, violation reporter and startup validation
+    tail: String,
+    /// Obfuscated original code
+    code: String,
+    /// Encoding the embedded tables were written in
+    encoding: BinaryEncoding,
+    /// The string table is shuffled, so `data` calls `_unshuffle`
+    strings_shuffled: bool,
+}
+
 /// Assembles the final protected Luau script
-pub struct ScriptAssembler;
+pub struct ScriptAssembler {
+    formatter: OutputFormatter,
+    decryption_mode: DecryptionMode,
+    type_annotations: TypeAnnotations,
+    /// Re-run the environment guard before decrypting
+    anti_hook: bool,
+    /// Pass decrypted values through `_corrupt` (time-bomb tamper policy or
+    /// degrading executor check)
+    corrupt_values: bool,
+    /// Report violations to this webhook (URL read from the pool)
+    violation_webhook: Option<ViolationWebhook>,
+    /// Startup checks for exploit executors and the client/server side
+    executor_check: Option<ExecutorCheck>,
+    run_context: RunContext,
+    tamper_policy: TamperPolicy,
+    /// `_load_payload()` for remote payload mode; strings aren't embedded
+    payload_loader: Option<String>,
+    /// `_key` rebuilt from delivered shares
+    key_delivery: Option<String>,
+    /// `_fetch_update()` the split loader tries before its payload chunk
+    update_channel: Option<String>,
+    /// Key generation recorded in the header
+    key_version: u32,
+    /// Script id and tiers recorded in the signed metadata header
+    script_id: String,
+    tier: String,
+    licensed_tier: Option<String>,
+    /// Signs the metadata header; unsigned scripts have none
+    metadata_signer: Option<MetadataSigner>,
+    /// License/EULA template rendered below the metadata header
+    license_header: Option<String>,
+    /// Strings may be compressed; `_decrypt` decompresses flagged entries
+    compression: Compression,
+    /// Encoding of embedded ciphertext and nonces (resolved per script)
+    binary_encoding: BinaryEncoding,
+    /// Write the string table and constant pool in a license-bound order
+    shuffle_tables: bool,
+    /// The code calls `_license_ok` from honeypot traps
+    honeypot_traps: bool,
+    /// Settings block written above the code, strings escaped if `encode_config`
+    config_block: Option<ConfigBlock>,
+    encode_config: bool,
+    /// Anti-dump `m` flag of each string table entry; flagged strings are
+    /// kept in `_vault`
+    dump_flags: Vec<Option<u8>>,
+}
 
 impl ScriptAssembler {
     pub fn new() -> Self {
-        Self
+        Self {
+            formatter: OutputFormatter::default(),
+            decryption_mode: DecryptionMode::Eager,
+            type_annotations: TypeAnnotations::Preserve,
+            anti_hook: false,
+            corrupt_values: false,
+            violation_webhook: None,
+            executor_check: None,
+            run_context: RunContext::Shared,
+            tamper_policy: TamperPolicy::default(),
+            payload_loader: None,
+            key_delivery: None,
+            update_channel: None,
+            key_version: 0,
+            script_id: String::new(),
+            tier: String::new(),
+            licensed_tier: None,
+            metadata_signer: None,
+            license_header: None,
+            compression: Compression::None,
+            binary_encoding: BinaryEncoding::Base64,
+            shuffle_tables: false,
+            honeypot_traps: false,
+            config_block: None,
+            encode_config: false,
+            dump_flags: Vec::new(),
+        }
+    }
+
+    /// Create an assembler using the layout and runtime options from a config
+    pub fn from_config(config: &CodeGenConfig) -> Self {
+        Self {
+            formatter: OutputFormatter::new(
+                config.output_formatting,
+                config.misleading_indentation,
+            ),
+            decryption_mode: config.string_decryption,
+            type_annotations: config.type_annotations,
+            anti_hook: config.anti_hook,
+            corrupt_values: config.tamper_policy.corrupts_values()
+                || config
+                    .executor_check
+                    .as_ref()
+                    .is_some_and(ExecutorCheck::corrupts_values),
+            violation_webhook: config.violation_webhook.clone(),
+            executor_check: config.executor_check.clone(),
+            run_context: config.run_context,
+            tamper_policy: config.tamper_policy.clone(),
+            payload_loader: None,
+            key_delivery: None,
+            update_channel: None,
+            key_version: 0,
+            script_id: config.script_id.clone(),
+            tier: config.tier.clone(),
+            licensed_tier: config.licensed_tier.clone(),
+            metadata_signer: None,
+            license_header: config.license_header.clone(),
+            compression: config.compression,
+            binary_encoding: config.binary_encoding,
+            shuffle_tables: config.shuffle_tables,
+            honeypot_traps: config.honeypot_traps,
+            config_block: config.config_block.clone(),
+            encode_config: config.encode_config,
+            dump_flags: config.anti_dump.flags(&config.string_sensitivity),
+        }
+    }
+
+    /// Fetch encrypted strings with `loader` after startup validation
+    /// instead of embedding them
+    pub fn with_payload_loader(mut self, loader: String) -> Self {
+        self.payload_loader = Some(loader);
+        self
+    }
+
+    /// Define `_key` with `key_code` before anything is decrypted
+    pub fn with_key_delivery(mut self, key_code: String) -> Self {
+        self.key_delivery = Some(key_code);
+        self
+    }
+
+    /// Let split loaders replace their payload chunk with the newer version
+    /// `fetch_code` (`_fetch_update()`) returns
+    pub fn with_update_channel(mut self, fetch_code: String) -> Self {
+        self.update_channel = Some(fetch_code);
+        self
+    }
+
+    /// Record that the embedded data is encrypted under key generation `version`
+    pub fn with_key_version(mut self, version: u32) -> Self {
+        self.key_version = version;
+        self
+    }
+
+    /// Emit a metadata header signed by `signer`
+    pub fn with_metadata_signer(mut self, signer: MetadataSigner) -> Self {
+        self.metadata_signer = Some(signer);
+        self
     }
     
     /// Assemble final script from all components
@@ -20,90 +204,552 @@ impl ScriptAssembler {
         license: Option<String>,
         hwid: Option<String>,
         encrypted_strings: &[(String, EncryptedData)],
+        constant_pool: Option<&ConstantPool>,
         obfuscated_code: &str,
         watermark: Watermark,
     ) -> Result<String> {
-        let mut output = String::new();
+        let sections = self.render_sections(
+            runtime,
+            license,
+            hwid,
+            encrypted_strings,
+            constant_pool,
+            obfuscated_code,
+            watermark,
+        )?;
+        
+        let mut body = sections.head;
+        body.push_str(&sections.data);
+        body.push_str(&sections.tail);
+        body.push_str(&sections.code);
+        
+        // Anti-beautifier pass (header, watermark and settings stay readable)
+        let mut output = sections.header;
+        output.push_str(&sections.config);
+        output.push_str(&self.formatter.format(&body)?);
+        
+        Ok(output)
+    }
+    
+    /// Assemble a loader and the plaintext payload chunk it runs
+    ///
+    /// The loader keeps the runtime, checks and constant pool; embedded
+    /// strings and the obfuscated code move to the chunk, which receives the
+    /// loader's locals as `...`. The chunk still has to be sealed.
+    pub fn assemble_split(
+        &self,
+        runtime: Option<String>,
+        license: Option<String>,
+        hwid: Option<String>,
+        encrypted_strings: &[(String, EncryptedData)],
+        constant_pool: Option<&ConstantPool>,
+        obfuscated_code: &str,
+        watermark: Watermark,
+    ) -> Result<SplitOutput> {
+        let sections = self.render_sections(
+            runtime,
+            license,
+            hwid,
+            encrypted_strings,
+            constant_pool,
+            obfuscated_code,
+            watermark,
+        )?;
+        
+        let mut body = sections.head;
+        body.push_str(&sections.tail);
+        if let Some(fetch_code) = &self.update_channel {
+            body.push_str("-- [STAGE] Update Channel\n");
+            body.push_str(fetch_code);
+        }
+        body.push_str("-- [STAGE] Payload Chunk\n");
+        let helpers = self.chunk_helpers(sections.encoding, sections.strings_shuffled);
+        body.push_str(&split::render_stage(&helpers, self.update_channel.is_some())?);
+        let mut loader = sections.header;
+        loader.push_str(&sections.config);
+        loader.push_str(&self.formatter.format(&body)?);
+        
+        let mut chunk = split::render_imports(&helpers);
+        chunk.push('\n');
+        chunk.push_str(&sections.data);
+        chunk.push_str(&sections.code);
+        
+        Ok(SplitOutput {
+            loader,
+            payload: self.formatter.format(&chunk)?,
+            payload_saved: 0,
+        })
+    }
+    
+    /// Render every part of the script, in single-file order
+    fn render_sections(
+        &self,
+        runtime: Option<String>,
+        license: Option<String>,
+        hwid: Option<String>,
+        encrypted_strings: &[(String, EncryptedData)],
+        constant_pool: Option<&ConstantPool>,
+        obfuscated_code: &str,
+        watermark: Watermark,
+    ) -> Result<Sections> {
+        // Remote strings arrive as base64 in the payload file; only embedded
+        // data decides the encoding
+        let mut binary_bytes: usize = constant_pool.map_or(0, |pool| {
+            pool.entries()
+                .iter()
+                .map(|e| e.encrypted.encrypted_data.len() + e.encrypted.nonce.len())
+                .sum()
+        });
+        if self.payload_loader.is_none() {
+            binary_bytes += encrypted_strings
+                .iter()
+                .map(|(_, e)| e.ciphertext.len() + e.nonce.len())
+                .sum::<usize>();
+        }
+        let encoding = self.binary_encoding.resolve(binary_bytes);
+        
+        // Remote strings keep the payload file's order; a single entry has
+        // nothing to shuffle
+        let shuffle = |table: &str, len: usize| {
+            (self.shuffle_tables && len > 1).then(|| TableShuffle::derive(&watermark, table, len))
+        };
+        let string_shuffle = match &self.payload_loader {
+            Some(_) => None,
+            None => shuffle("strings", encrypted_strings.len()),
+        };
+        let pool_shuffle = shuffle("pool", constant_pool.map_or(0, |pool| pool.entries().len()));
+        
+        let mut header = String::new();
         
         // Header
-        output.push_str("-- Protected by Luau Obfuscator\n");
-        output.push_str("-- https://github.com/danila-permogorskii/luau-obfuscator\n");
-        output.push_str("\n");
+        header.push_str("-- Protected by Luau Obfuscator\n");
+        header.push_str("-- https://github.com/danila-permogorskii/luau-obfuscator\n");
+        header.push_str("\n");
         
         // Watermark (hidden in comment)
-        output.push_str(&format!("-- Watermark: {}\n", self.encode_watermark(&watermark)));
-        output.push_str("\n");
+        let encoded_watermark = self.encode_watermark(&watermark);
+        header.push_str(&format!("{}{}\n", WATERMARK_HEADER, encoded_watermark));
+        header.push_str(&format!("{}{}\n", KEY_VERSION_HEADER, self.key_version));
+        if encoding != BinaryEncoding::Base64 {
+            header.push_str(&format!("{}{}\n", ENCODING_HEADER, encoding));
+        }
+        if let Some(signer) = &self.metadata_signer {
+            let metadata = ScriptMetadata {
+                script_id: self.script_id.clone(),
+                tier: self.tier.clone(),
+                licensed_tier: self.licensed_tier.clone(),
+                timestamp: watermark.timestamp,
+                key_version: self.key_version,
+                watermark_digest: ScriptMetadata::watermark_digest(&encoded_watermark),
+            };
+            header.push_str(&signer.sign(&metadata)?);
+            header.push('\n');
+        }
+        if let Some(template) = &self.license_header {
+            header.push_str(&render_license_header(
+                template,
+                &encoded_watermark,
+                &self.script_id,
+                &self.tier,
+            )?);
+        }
+        header.push_str("\n");
+        
+        // Customer Settings
+        let mut config = String::new();
+        if let Some(block) = &self.config_block {
+            let text = block.render(self.encode_config)?;
+            config.push_str("-- [CONFIG] Settings (safe to edit)\n");
+            match self.type_annotations {
+                TypeAnnotations::Preserve => config.push_str(&text),
+                TypeAnnotations::Strip => config.push_str(&strip_types(&text)?),
+            }
+            config.push_str("\n\n");
+        }
+        
+        let mut head = String::new();
         
         // ChaCha20 Runtime
-        if let Some(runtime_code) = runtime {
-            output.push_str("-- [RUNTIME] ChaCha20 Decryption\n");
-            output.push_str(&runtime_code);
-            output.push_str("\n\n");
+        if let Some(runtime_code) = &runtime {
+            head.push_str("-- [RUNTIME] ChaCha20 Decryption\n");
+            head.push_str(&runtime_code);
+            head.push_str("\n\n");
+            if let Some(decoder) = RuntimeGenerator::new().generate_decoder(encoding)? {
+                head.push_str(&decoder);
+                head.push_str("\n\n");
+            }
+        }
+        
+        // Table order
+        if string_shuffle.is_some() || pool_shuffle.is_some() {
+            head.push_str(&RuntimeGenerator::new().generate_unshuffle()?);
+            head.push_str("\n\n");
+        }
+        
+        // Masked decrypted strings
+        if self.vaults_strings() {
+            head.push_str(&RuntimeGenerator::new().generate_string_vault()?);
+            head.push_str("\n\n");
         }
         
         // License Validation
-        if let Some(license_code) = license {
-            output.push_str("-- [LICENSE] License Validation\n");
-            output.push_str(&license_code);
-            output.push_str("\n\n");
+        if let Some(license_code) = &license {
+            head.push_str("-- [LICENSE] License Validation\n");
+            head.push_str(&license_code);
+            head.push_str("\n\n");
         }
         
         // HWID Binding
-        if let Some(hwid_code) = hwid {
-            output.push_str("-- [HWID] Hardware ID Binding\n");
-            output.push_str(&hwid_code);
-            output.push_str("\n\n");
+        if let Some(hwid_code) = &hwid {
+            head.push_str("-- [HWID] Hardware ID Binding\n");
+            head.push_str(&hwid_code);
+            head.push_str("\n\n");
+        }
+        
+        // Key Delivery
+        if let Some(key_code) = &self.key_delivery {
+            head.push_str("-- [KEY] Delivered Key Shares\n");
+            head.push_str(key_code);
+            head.push_str("\n\n");
         }
         
         // Encrypted Data Structures
-        if !encrypted_strings.is_empty() {
-            output.push_str("-- [DATA] Encrypted Strings\n");
-            output.push_str("local _encrypted_data = {\n");
+        let mut data = String::new();
+        if let Some(loader) = &self.payload_loader {
+            head.push_str("-- [DATA] Remote Encrypted Strings\n");
+            head.push_str("local _encrypted_data = {}\n\n");
+            head.push_str(loader);
+            head.push('\n');
+            head.push_str(&self.render_decrypt_helper(BinaryEncoding::Base64, false));
+            head.push('\n');
+        } else if !encrypted_strings.is_empty() {
+            data.push_str("-- [DATA] Encrypted Strings\n");
+            data.push_str("local _encrypted_data = {\n");
             
+            let mut entries = Vec::with_capacity(encrypted_strings.len());
             for (i, (original, encrypted)) in encrypted_strings.iter().enumerate() {
-                let ciphertext_b64 = base64::engine::general_purpose::STANDARD
-                    .encode(&encrypted.ciphertext);
-                let nonce_b64 = base64::engine::general_purpose::STANDARD
-                    .encode(&encrypted.nonce);
+                let mut flags = match encrypted.compression {
+                    Compression::None => String::new(),
+                    compression => format!(", z = \"{}\"", compression),
+                };
+                if let Some(Some(mode)) = self.dump_flags.get(i) {
+                    flags.push_str(&format!(", m = {}", mode));
+                }
+                let position = string_shuffle.as_ref().map_or(i + 1, |s| s.position(i + 1));
                 
-                output.push_str(&format!(
-                    "    [{}] = {{ct = \"{}\", nonce = \"{}\"}},\n",
-                    i + 1,
-                    ciphertext_b64,
-                    nonce_b64
+                entries.push((
+                    position,
+                    render_entry(
+                        encoding,
+                        position,
+                        &encrypted.ciphertext,
+                        &encrypted.nonce,
+                        &flags,
+                    ),
                 ));
             }
+            push_sorted(&mut data, entries);
             
-            output.push_str("}\n\n");
+            data.push_str("}\n");
+            if let Some(shuffle) = &string_shuffle {
+                data.push_str(&shuffle.render_order(STRING_ORDER));
+            }
+            data.push('\n');
             
             // Decryption helper
-            output.push_str("-- Decrypt string by index\n");
-            output.push_str("local function _decrypt(index)\n");
-            output.push_str("    local data = _encrypted_data[index]\n");
-            output.push_str("    if not data then return nil end\n");
-            output.push_str("    return ChaCha20.decrypt_string(data.ct, _key, data.nonce)\n");
-            output.push_str("end\n\n");
+            data.push_str(&self.render_decrypt_helper(encoding, string_shuffle.is_some()));
+            data.push_str("\n");
+        }
+        
+        let mut tail = String::new();
+        
+        // Constant Pool
+        if let Some(pool) = constant_pool.filter(|p| !p.is_empty()) {
+            tail.push_str("-- [POOL] Constant Pool\n");
+            tail.push_str(&self.render_constant_pool(pool, encoding, pool_shuffle.as_ref()));
+            tail.push_str("\n");
+        }
+        
+        // Violation Reporting
+        if let Some(webhook) = &self.violation_webhook {
+            let url = constant_pool
+                .and_then(|pool| pool.accessor(PoolEntryKind::String, &webhook.url))
+                .ok_or_else(|| {
+                    ObfuscatorError::CodeGenError(
+                        "Violation webhook URL missing from constant pool".to_string(),
+                    )
+                })?;
+            tail.push_str("-- [REPORT] Violation Webhook\n");
+            tail.push_str(&webhook.render_reporter(&url, &self.encode_watermark(&watermark)));
+            tail.push_str("\n");
         }
         
         // Validation Startup
-        output.push_str("-- [INIT] Startup Validation\n");
-        output.push_str("do\n");
+        tail.push_str("-- [INIT] Startup Validation\n");
+        tail.push_str("do\n");
+        tail.push_str(&self.run_context.render());
+        if let Some(check) = &self.executor_check {
+            tail.push_str(&check.render(&self.tamper_policy));
+        }
         if license.is_some() {
-            output.push_str("    local license_module = require(script.License)\n");
-            output.push_str("    assert(license_module.validate(), \"License validation failed\")\n");
+            tail.push_str("    local license_module = require(script.License)\n");
+            tail.push_str(&format!(
+                "    if not license_module.validate() then {}(\"license\") end\n",
+                TAMPER_HANDLER
+            ));
         }
         if hwid.is_some() {
-            output.push_str("    local hwid_module = require(script.HWID)\n");
-            output.push_str("    assert(hwid_module.validate(), \"HWID validation failed\")\n");
+            tail.push_str("    local hwid_module = require(script.HWID)\n");
+            tail.push_str(&format!(
+                "    if not hwid_module.validate() then {}(\"hwid\") end\n",
+                TAMPER_HANDLER
+            ));
+        }
+        if self.payload_loader.is_some() {
+            tail.push_str(&format!(
+                "    if not _load_payload() then {}(\"payload\") end\n",
+                TAMPER_HANDLER
+            ));
         }
-        output.push_str("end\n\n");
+        tail.push_str("end\n\n");
         
         // Obfuscated Original Code
-        output.push_str("-- [CODE] Protected Script\n");
-        output.push_str(obfuscated_code);
-        output.push_str("\n");
+        let mut code = String::from("-- [CODE] Protected Script\n");
+        match self.type_annotations {
+            TypeAnnotations::Preserve => code.push_str(obfuscated_code),
+            TypeAnnotations::Strip => code.push_str(&strip_types(obfuscated_code)?),
+        }
+        code.push_str("\n");
         
-        Ok(output)
+        Ok(Sections {
+            header,
+            config,
+            head,
+            data,
+            tail,
+            code,
+            encoding,
+            strings_shuffled: string_shuffle.is_some(),
+        })
+    }
+    
+    /// Runtime helpers the payload chunk's embedded data needs from the
+    /// loader, and the trap handler and settings block its code reads
+    fn chunk_helpers(&self, encoding: BinaryEncoding, strings_shuffled: bool) -> Vec<&str> {
+        let mut helpers = Vec::new();
+        if !self.compression.is_none() {
+            helpers.push(DECOMPRESS_FUNCTION);
+        }
+        if encoding == BinaryEncoding::Base85 {
+            helpers.push(BASE85_DECODER);
+        }
+        if strings_shuffled {
+            helpers.push(SHUFFLE_FUNCTION);
+        }
+        if self.vaults_strings() && self.payload_loader.is_none() {
+            helpers.push(VAULT_TABLE);
+        }
+        if self.honeypot_traps {
+            helpers.push(TRAP_FUNCTION);
+        }
+        if let Some(block) = &self.config_block {
+            helpers.push(block.name.as_str());
+        }
+        helpers
+    }
+    
+    /// Render `_decrypt(index)` for the configured decryption mode, reading
+    /// entries written in `encoding` (at their `_encrypted_order` position
+    /// when `shuffled`)
+    fn render_decrypt_helper(&self, encoding: BinaryEncoding, shuffled: bool) -> String {
+        let mut code = String::new();
+        let slot = if shuffled {
+            format!("{}[index]", STRING_ORDER)
+        } else {
+            "index".to_string()
+        };
+        
+        // Remote strings only arrive after startup validation
+        let mode = if self.payload_loader.is_some() {
+            DecryptionMode::Lazy
+        } else {
+            self.decryption_mode
+        };
+        
+        match mode {
+            DecryptionMode::Eager => {
+                code.push_str("-- Decrypt all strings at load\n");
+                code.push_str(&self.render_guard_call(""));
+                code.push_str("local _strings = {}\n");
+                code.push_str("for index, data in pairs(_encrypted_data) do\n");
+                let decrypt = encoding.render_decrypt("data");
+                if self.vaults_strings() {
+                    code.push_str(&format!("    local value = {}\n", decrypt));
+                    code.push_str(&self.render_decompress("    ", "value"));
+                    code.push_str(&self.render_store("    ", "_strings"));
+                } else {
+                    code.push_str(&format!("    _strings[index] = {}\n", decrypt));
+                    code.push_str(&self.render_decompress("    ", "_strings[index]"));
+                }
+                code.push_str("end\n");
+                code.push_str("local function _decrypt(index)\n");
+                let value = if self.vaults_strings() {
+                    format!("_strings[{s}] or {}.get({s})", VAULT_TABLE, s = slot)
+                } else {
+                    format!("_strings[{}]", slot)
+                };
+                code.push_str(&self.render_return("    ", &value));
+                code.push_str("end\n");
+            }
+            DecryptionMode::Lazy => {
+                // Plaintext only exists once a string has actually been used
+                code.push_str("-- Decrypt string by index on first use\n");
+                code.push_str("local _decrypt = (function()\n");
+                code.push_str("    local cache = {}\n");
+                code.push_str("    return function(index)\n");
+                if self.vaults_strings() {
+                    code.push_str(&format!(
+                        "        local value = cache[index] or {}.get(index)\n",
+                        VAULT_TABLE
+                    ));
+                } else {
+                    code.push_str("        local value = cache[index]\n");
+                }
+                code.push_str("        if value == nil then\n");
+                code.push_str(&format!("            local data = _encrypted_data[{}]\n", slot));
+                code.push_str("            if not data then return nil end\n");
+                code.push_str(&self.render_guard_call("            "));
+                code.push_str(&format!("            value = {}\n", encoding.render_decrypt("data")));
+                code.push_str(&self.render_decompress("            ", "value"));
+                code.push_str(&self.render_store("            ", "cache"));
+                code.push_str("        end\n");
+                code.push_str(&self.render_return("        ", "value"));
+                code.push_str("    end\n");
+                code.push_str("end)()\n");
+            }
+        }
+        
+        code
+    }
+    
+    /// Render the encrypted constant pool and its memoizing accessor
+    fn render_constant_pool(
+        &self,
+        pool: &ConstantPool,
+        encoding: BinaryEncoding,
+        shuffle: Option<&TableShuffle>,
+    ) -> String {
+        let mut code = String::from("local _K_pool = {\n");
+        
+        let mut entries = Vec::with_capacity(pool.entries().len());
+        for entry in pool.entries() {
+            let number_flag = match entry.kind {
+                PoolEntryKind::Number => ", n = true",
+                PoolEntryKind::String => "",
+            };
+            let position = shuffle.map_or(entry.slot, |s| s.position(entry.slot));
+            
+            entries.push((
+                position,
+                render_entry(
+                    encoding,
+                    position,
+                    &entry.encrypted.encrypted_data,
+                    &entry.encrypted.nonce,
+                    number_flag,
+                ),
+            ));
+        }
+        push_sorted(&mut code, entries);
+        
+        code.push_str("}\n");
+        let slot = match shuffle {
+            Some(shuffle) => {
+                code.push_str(&shuffle.render_order(POOL_ORDER));
+                format!("{}[i]", POOL_ORDER)
+            }
+            None => "i".to_string(),
+        };
+        code.push_str("local _K_cache = {}\n");
+        code.push_str(&format!("local function {}(i)\n", POOL_ACCESSOR));
+        code.push_str("    local v = _K_cache[i]\n");
+        code.push_str("    if v == nil then\n");
+        code.push_str(&format!("        local e = _K_pool[{}]\n", slot));
+        code.push_str(&self.render_guard_call("        "));
+        code.push_str(&format!("        v = {}\n", encoding.render_decrypt("e")));
+        code.push_str("        if e.n then v = tonumber(v) end\n");
+        code.push_str("        _K_cache[i] = v\n");
+        code.push_str("    end\n");
+        code.push_str(&self.render_return("    ", "v"));
+        code.push_str("end\n");
+        code
+    }
+    
+    /// Line keeping the decrypted `value` of entry `data` at `index`: in
+    /// `cache`, or masked in `_vault` when the entry has an `m` flag
+    fn render_store(&self, indent: &str, cache: &str) -> String {
+        if self.vaults_strings() {
+            format!(
+                "{}if data.m then {}.put(index, value, data.m) else {}[index] = value end\n",
+                indent, VAULT_TABLE, cache
+            )
+        } else {
+            format!("{}{}[index] = value\n", indent, cache)
+        }
+    }
+    
+    /// Whether any string table entry is kept in `_vault`
+    fn vaults_strings(&self) -> bool {
+        self.dump_flags.iter().any(Option::is_some)
+    }
+    
+    /// `return <expr>` line, filtered through `_corrupt` under the time-bomb policy
+    fn render_return(&self, indent: &str, expr: &str) -> String {
+        if self.corrupt_values {
+            format!("{}return {}({})\n", indent, CORRUPT_FUNCTION, expr)
+        } else {
+            format!("{}return {}\n", indent, expr)
+        }
+    }
+    
+    /// Decompress `var` when its entry is flagged, or nothing when strings
+    /// are never compressed
+    fn render_decompress(&self, indent: &str, var: &str) -> String {
+        if self.compression.is_none() {
+            return String::new();
+        }
+        format!(
+            "{indent}if data.z then {var} = {f}({var}) end\n",
+            indent = indent,
+            var = var,
+            f = DECOMPRESS_FUNCTION
+        )
+    }
+    
+    /// `_guard()` call line at `indent`, or nothing when the guard is off
+    fn render_guard_call(&self, indent: &str) -> String {
+        if self.anti_hook {
+            format!("{}{}()\n", indent, GUARD_FUNCTION)
+        } else {
+            String::new()
+        }
+    }
+    
+    /// Render the `_EK`/`_EH` locals environment-derived constants read
+    ///
+    /// The hash is computed by a loop at load time so it never appears as a
+    /// literal in the output.
+    pub fn render_environment_anchor(&self, anchor: &str) -> String {
+        let mut code = format!("local {} = \"{}\"\n", ENVIRONMENT_KEY, anchor.escape_default());
+        code.push_str(&format!("local {} = {}\n", ENVIRONMENT_HASH, ANCHOR_HASH_INIT));
+        code.push_str(&format!("for i = 1, #{} do\n", ENVIRONMENT_KEY));
+        code.push_str(&format!(
+            "    {h} = bit32.bxor(bit32.lrotate({h}, 5), string.byte({k}, i))\n",
+            h = ENVIRONMENT_HASH,
+            k = ENVIRONMENT_KEY
+        ));
+        code.push_str("end\n");
+        code
     }
     
     /// Encode watermark for embedding
@@ -114,6 +760,32 @@ impl ScriptAssembler {
     }
 }
 
+/// One `[index] = {ct = ..., nonce = ...<flags>},` table line
+fn render_entry(
+    encoding: BinaryEncoding,
+    index: usize,
+    ciphertext: &[u8],
+    nonce: &[u8],
+    flags: &str,
+) -> String {
+    format!(
+        "    [{}] = {{ct = {}, nonce = {}{}}},\n",
+        index,
+        encoding.encode(ciphertext),
+        encoding.encode(nonce),
+        flags
+    )
+}
+
+/// Table lines in position order, so the line order doesn't give away the
+/// shuffle
+fn push_sorted(code: &mut String, mut entries: Vec<(usize, String)>) {
+    entries.sort_by_key(|(position, _)| *position);
+    for (_, line) in entries {
+        code.push_str(&line);
+    }
+}
+
 impl Default for ScriptAssembler {
     fn default() -> Self {
         Self::new()
@@ -123,6 +795,8 @@ impl Default for ScriptAssembler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codegen::{AntiDumpPolicy, DumpProtection, ExecutorResponse};
+    use crate::parser::Sensitivity;
 
     #[test]
     fn test_assembly_basic() {
@@ -138,6 +812,7 @@ mod tests {
             None,
             None,
             &[],
+            None,
             "print('Hello, World!')",
             watermark,
         );
@@ -147,4 +822,435 @@ mod tests {
         assert!(script.contains("Protected by Luau Obfuscator"));
         assert!(script.contains("print('Hello, World!')"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_assembly_strips_types() {
+        let config = CodeGenConfig {
+            type_annotations: TypeAnnotations::Strip,
+            ..CodeGenConfig::default()
+        };
+        let assembler = ScriptAssembler::from_config(&config);
+        let watermark = Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+        
+        let script = assembler
+            .assemble(
+                None,
+                None,
+                None,
+                &[],
+                None,
+                "type Id = number\nlocal id: Id = 5\nprint(id)",
+                watermark,
+            )
+            .unwrap();
+        
+        assert!(!script.contains("type Id"));
+        assert!(script.contains("local id = 5"));
+    }
+
+    #[test]
+    fn test_lazy_decrypt_helper_memoizes() {
+        let config = CodeGenConfig {
+            string_decryption: DecryptionMode::Lazy,
+            ..CodeGenConfig::default()
+        };
+        let assembler = ScriptAssembler::from_config(&config);
+        let helper = assembler.render_decrypt_helper(BinaryEncoding::Base64, false);
+        
+        assert!(helper.contains("local cache = {}"));
+        assert!(helper.contains("cache[index] = value"));
+        assert!(!helper.contains("pairs(_encrypted_data)"));
+    }
+    
+    #[test]
+    fn test_guard_runs_before_lazy_decryption() {
+        let config = CodeGenConfig {
+            string_decryption: DecryptionMode::Lazy,
+            ..CodeGenConfig::default()
+        };
+        let helper = ScriptAssembler::from_config(&config).render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(helper.contains("            _guard()\n            value = ChaCha20"));
+        
+        let unguarded = CodeGenConfig {
+            anti_hook: false,
+            ..config
+        };
+        let helper = ScriptAssembler::from_config(&unguarded).render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(!helper.contains("_guard()"));
+    }
+    
+    #[test]
+    fn test_time_bomb_filters_decrypted_values() {
+        let config = CodeGenConfig {
+            tamper_policy: crate::codegen::TamperPolicy::TimeBomb { delay_seconds: 60 },
+            ..CodeGenConfig::default()
+        };
+        let helper = ScriptAssembler::from_config(&config).render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(helper.contains("    return _corrupt(_strings[index])\n"));
+        
+        let helper = ScriptAssembler::new().render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(helper.contains("    return _strings[index]\n"));
+    }
+
+    #[test]
+    fn test_environment_checks_run_at_startup() {
+        let config = CodeGenConfig {
+            executor_check: Some(ExecutorCheck::new(ExecutorResponse::Degrade)),
+            run_context: RunContext::Server,
+            ..CodeGenConfig::default()
+        };
+        let assembler = ScriptAssembler::from_config(&config);
+        let watermark = Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+
+        let script = assembler
+            .assemble(None, None, None, &[], None, "print(1)", watermark)
+            .unwrap();
+        let init = script.find("-- [INIT]").unwrap();
+        let context = script.find(":IsServer() then _tamper(\"context: not server\")").unwrap();
+        let executor = script.find("if identifyexecutor ~= nil then").unwrap();
+        assert!(init < context && context < executor);
+        assert!(script.contains("_tamper_state.degraded = true"));
+        // Degrading needs decrypted values filtered
+        let helper = assembler.render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(helper.contains("    return _corrupt(_strings[index])\n"));
+    }
+
+    #[test]
+    fn test_decrypt_helper_decompresses_flagged_entries() {
+        let config = CodeGenConfig {
+            compression: Compression::Zlib,
+            string_decryption: DecryptionMode::Lazy,
+            ..CodeGenConfig::default()
+        };
+        let helper = ScriptAssembler::from_config(&config).render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(helper.contains("            if data.z then value = _decompress(value) end\n"));
+        
+        let helper = ScriptAssembler::new().render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(!helper.contains("_decompress"));
+    }
+    
+    #[test]
+    fn test_violation_webhook_requires_pooled_url() {
+        let config = CodeGenConfig {
+            violation_webhook: Some(ViolationWebhook::new("https://example.com/hook")),
+            ..CodeGenConfig::default()
+        };
+        let assembler = ScriptAssembler::from_config(&config);
+        let watermark = Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+        
+        let result = assembler.assemble(None, None, None, &[], None, "print(1)", watermark);
+        assert!(result.is_err());
+    }
+    
+    #[test]
+    fn test_split_moves_code_and_strings_to_chunk() {
+        let assembler = ScriptAssembler::new();
+        let watermark = Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+        let encrypted = vec![(
+            "secret".to_string(),
+            EncryptedData {
+                ciphertext: vec![1, 2, 3],
+                nonce: vec![0; 12],
+                tag_len: 16,
+                key_version: 0,
+                compression: Compression::None,
+            },
+        )];
+
+        let split = assembler
+            .assemble_split(
+                Some("local ChaCha20 = {}".to_string()),
+                None,
+                None,
+                &encrypted,
+                None,
+                "print(_decrypt(1))",
+                watermark,
+            )
+            .unwrap();
+
+        assert!(split.loader.contains("-- [RUNTIME] ChaCha20 Decryption"));
+        assert!(split.loader.contains("-- [INIT] Startup Validation"));
+        assert!(split.loader.contains("require, script.Payload"));
+        assert!(!split.loader.contains("print(_decrypt(1))"));
+        assert!(!split.loader.contains("ct = \"AQID\""));
+
+        assert!(split.payload.starts_with("local ChaCha20, _key,"));
+        assert!(split.payload.contains("ct = \"AQID\""));
+        assert!(split.payload.contains("print(_decrypt(1))"));
+    }
+
+    #[test]
+    fn test_config_block_written_above_code() {
+        let settings = "local CONFIG = {\n    Title = \"Hi\", -- shown on join\n}";
+        let source = format!("--!obfuscate: config\n{}\n", settings);
+        let config = CodeGenConfig {
+            config_block: crate::parser::scan_config_block(&source).unwrap(),
+            output_formatting: crate::codegen::OutputFormatting::Packed,
+            ..CodeGenConfig::default()
+        };
+        let watermark = || Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+
+        let script = ScriptAssembler::from_config(&config)
+            .assemble(None, None, None, &[], None, "print(CONFIG.Title)", watermark())
+            .unwrap();
+        // Kept as written, comments included, ahead of the reformatted body
+        let at = script.find(settings).unwrap();
+        assert!(script[..at].ends_with("-- [CONFIG] Settings (safe to edit)\n"));
+        assert!(at < script.find("print(CONFIG.Title)").unwrap());
+
+        let split = ScriptAssembler::from_config(&config)
+            .assemble_split(None, None, None, &[], None, "print(CONFIG.Title)", watermark())
+            .unwrap();
+        assert!(split.loader.contains(settings));
+        assert!(split.payload.lines().next().unwrap().ends_with(", CONFIG = ..."));
+    }
+
+    #[test]
+    fn test_entries_written_in_resolved_encoding() {
+        let watermark = || Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+        let encrypted = vec![(
+            "secret".to_string(),
+            EncryptedData {
+                ciphertext: vec![1, 2, 3],
+                nonce: vec![0; 12],
+                tag_len: 16,
+                key_version: 0,
+                compression: Compression::None,
+            },
+        )];
+        let assemble = |binary_encoding| {
+            let config = CodeGenConfig {
+                binary_encoding,
+                ..CodeGenConfig::default()
+            };
+            ScriptAssembler::from_config(&config)
+                .assemble(
+                    Some("local ChaCha20 = {}".to_string()),
+                    None,
+                    None,
+                    &encrypted,
+                    None,
+                    "print(_decrypt(1))",
+                    watermark(),
+                )
+                .unwrap()
+        };
+
+        // A few bytes resolve to escaped strings, decoded by the compiler
+        let script = assemble(BinaryEncoding::Auto);
+        assert!(script.contains("-- Data encoding: escaped\n"));
+        assert!(script.contains("[1] = {ct = \"\\1\\2\\3\", nonce = \""));
+        assert!(script.contains(
+            "ChaCha20.decrypt_bytes(ChaCha20.string_to_bytes(data.ct), _key,"
+        ));
+
+        let script = assemble(BinaryEncoding::Base85);
+        assert!(script.contains("local _b85decode = (function()"));
+        assert!(script.contains(
+            "ChaCha20.decrypt_bytes(_b85decode(data.ct), _key, _b85decode(data.nonce))"
+        ));
+
+        let script = assemble(BinaryEncoding::Numeric);
+        assert!(script.contains(
+            "[1] = {ct = {1,2,3}, nonce = {0,0,0,0,0,0,0,0,0,0,0,0}},"
+        ));
+        assert!(!script.contains("_b85decode"));
+        assert!(full_moon::parse(&script).is_ok());
+
+        let script = assemble(BinaryEncoding::Base64);
+        assert!(!script.contains("-- Data encoding:"));
+        assert!(script.contains("ct = \"AQID\""));
+    }
+
+    #[test]
+    fn test_tables_written_in_license_bound_order() {
+        let watermark = crate::crypto::WatermarkGenerator::new().generate("KEY-1", "script456");
+        let encrypted: Vec<(String, EncryptedData)> = (1..=6u8)
+            .map(|i| {
+                let data = EncryptedData {
+                    ciphertext: vec![i; 3],
+                    nonce: vec![0; 12],
+                    tag_len: 16,
+                    key_version: 0,
+                    compression: Compression::None,
+                };
+                (format!("string {}", i), data)
+            })
+            .collect();
+        let config = CodeGenConfig {
+            binary_encoding: BinaryEncoding::Numeric,
+            ..CodeGenConfig::default()
+        };
+
+        let script = ScriptAssembler::from_config(&config)
+            .assemble(
+                Some("local ChaCha20 = {}".to_string()),
+                None,
+                None,
+                &encrypted,
+                None,
+                "print(_decrypt(1))",
+                watermark.clone(),
+            )
+            .unwrap();
+
+        let shuffle = TableShuffle::derive(&watermark, "strings", encrypted.len());
+        assert!(script.contains("local function _unshuffle(seed, n)"));
+        assert!(script.contains(&shuffle.render_order(STRING_ORDER)));
+        assert!(script.contains("return _strings[_encrypted_order[index]]"));
+        // Every string sits at its shuffled position, and lines follow positions
+        let mut lines: Vec<(usize, usize)> = (1..=encrypted.len())
+            .map(|i| {
+                let line = format!("[{}] = {{ct = {{{i},{i},{i}}},", shuffle.position(i), i = i);
+                (shuffle.position(i), script.find(&line).unwrap())
+            })
+            .collect();
+        lines.sort_unstable();
+        assert!(lines.windows(2).all(|pair| pair[0].1 < pair[1].1));
+        assert!(full_moon::parse(&script).is_ok());
+
+        let unshuffled = CodeGenConfig {
+            shuffle_tables: false,
+            ..config
+        };
+        let script = ScriptAssembler::from_config(&unshuffled)
+            .assemble(None, None, None, &encrypted, None, "print(_decrypt(1))", watermark)
+            .unwrap();
+        assert!(script.contains("[2] = {ct = {2,2,2},"));
+        assert!(!script.contains("_unshuffle"));
+    }
+
+    #[test]
+    fn test_remote_payload_fetched_after_validation() {
+        let assembler = ScriptAssembler::new()
+            .with_payload_loader("local function _load_payload() return true end\n".to_string());
+        let watermark = Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+        let encrypted = vec![(
+            "secret".to_string(),
+            EncryptedData {
+                ciphertext: vec![1, 2, 3],
+                nonce: vec![0; 12],
+                tag_len: 16,
+                key_version: 0,
+                compression: Compression::None,
+            },
+        )];
+        
+        let script = assembler
+            .assemble(None, None, None, &encrypted, None, "print(_decrypt(1))", watermark)
+            .unwrap();
+        
+        assert!(script.contains("local _encrypted_data = {}"));
+        assert!(!script.contains("ct = \"AQID\""));
+        // Eager decryption would run before the payload exists
+        assert!(!script.contains("pairs(_encrypted_data)"));
+        let load = script.find("if not _load_payload() then").unwrap();
+        assert!(load > script.find("-- [INIT]").unwrap());
+    }
+    
+    #[test]
+    fn test_eager_decrypt_helper_decrypts_at_load() {
+        let assembler = ScriptAssembler::new();
+        let helper = assembler.render_decrypt_helper(BinaryEncoding::Base64, false);
+        
+        assert!(helper.contains("pairs(_encrypted_data)"));
+    }
+
+    #[test]
+    fn test_anti_dump_keeps_flagged_strings_in_vault() {
+        let encrypted: Vec<(String, EncryptedData)> = (1..=2u8)
+            .map(|i| {
+                let data = EncryptedData {
+                    ciphertext: vec![i; 3],
+                    nonce: vec![0; 12],
+                    tag_len: 16,
+                    key_version: 0,
+                    compression: Compression::None,
+                };
+                (format!("string {}", i), data)
+            })
+            .collect();
+        let config = CodeGenConfig {
+            string_decryption: DecryptionMode::Lazy,
+            anti_dump: AntiDumpPolicy::high_only(DumpProtection::Rolling),
+            string_sensitivity: vec![Sensitivity::High, Sensitivity::Low],
+            binary_encoding: BinaryEncoding::Numeric,
+            shuffle_tables: false,
+            ..CodeGenConfig::default()
+        };
+        let watermark = Watermark {
+            customer_id: "test123".to_string(),
+            script_id: "script456".to_string(),
+            hash: vec![1, 2, 3, 4],
+        };
+
+        let script = ScriptAssembler::from_config(&config)
+            .assemble(None, None, None, &encrypted, None, "print(_decrypt(1))", watermark)
+            .unwrap();
+
+        assert!(script.contains("local _vault = (function()"));
+        let nonce = "nonce = {0,0,0,0,0,0,0,0,0,0,0,0}";
+        assert!(script.contains(&format!("[1] = {{ct = {{1,1,1}}, {}, m = 2}},", nonce)));
+        assert!(script.contains(&format!("[2] = {{ct = {{2,2,2}}, {}}},", nonce)));
+        assert!(script.contains("local value = cache[index] or _vault.get(index)"));
+        assert!(script.contains(
+            "if data.m then _vault.put(index, value, data.m) else cache[index] = value end"
+        ));
+        assert!(full_moon::parse(&script).is_ok());
+
+        let eager = ScriptAssembler::from_config(&CodeGenConfig {
+            string_decryption: DecryptionMode::Eager,
+            ..config.clone()
+        })
+        .render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(eager.contains("    return _strings[index] or _vault.get(index)\n"));
+
+        // Nothing flagged, nothing emitted
+        let plain = ScriptAssembler::from_config(&CodeGenConfig {
+            anti_dump: Default::default(),
+            ..config
+        })
+        .render_decrypt_helper(BinaryEncoding::Base64, false);
+        assert!(!plain.contains("_vault"));
+    }
+
+    #[test]
+    fn test_environment_anchor_computes_hash_at_load() {
+        let assembler = ScriptAssembler::new();
+        let code = assembler.render_environment_anchor("ABC1-2345");
+        
+        assert!(code.starts_with("local _EK = \"ABC1-2345\"\n"));
+        assert!(code.contains("bit32.lrotate(_EH, 5)"));
+        let hash = crate::obfuscation::anchor_hash("ABC1-2345");
+        assert!(!code.contains(&hash.to_string()));
+    }
+}
@@ -0,0 +1,143 @@
+//! Binding policies for HWID/place/universe checks
+//!
+//! A policy is a tree of id checks combined with `all`/`any`, rendered as a
+//! single Luau boolean expression for `templates/hwid_binding.lua`.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Where a protected script is allowed to run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BindingPolicy {
+    /// The local player's UserId is one of `ids`
+    UserId { ids: Vec<u64> },
+    /// `game.PlaceId` is one of `ids`
+    PlaceId { ids: Vec<u64> },
+    /// `game.GameId` (the universe) is one of `ids`
+    UniverseId { ids: Vec<u64> },
+    /// The experience is owned by the group `group_id`
+    GroupOwner { group_id: u64 },
+    /// Every nested policy holds
+    All { policies: Vec<BindingPolicy> },
+    /// At least one nested policy holds
+    Any { policies: Vec<BindingPolicy> },
+}
+
+impl BindingPolicy {
+    /// Policy equivalent to the single-id `binding_mode` settings
+    pub fn from_mode(
+        mode: &str,
+        user_id: Option<u64>,
+        place_id: Option<u64>,
+        authorized_users: &[u64],
+    ) -> Result<Self> {
+        let users = BindingPolicy::UserId {
+            ids: user_id.into_iter().collect(),
+        };
+        let places = BindingPolicy::PlaceId {
+            ids: place_id.into_iter().collect(),
+        };
+
+        match mode {
+            "userid" => Ok(users),
+            "placeid" => Ok(places),
+            "both" => Ok(BindingPolicy::All {
+                policies: vec![users, places],
+            }),
+            "whitelist" => Ok(BindingPolicy::UserId {
+                ids: authorized_users.to_vec(),
+            }),
+            other => Err(ObfuscatorError::ConfigError(format!(
+                "Unknown binding mode '{}' (expected userid, placeid, both or whitelist)",
+                other
+            ))
+            .into()),
+        }
+    }
+
+    /// Luau expression that is true when the policy holds
+    ///
+    /// Uses the `one_of`, `get_user_id` and `owned_by_group` helpers defined
+    /// by the binding template. An empty `all` holds; an empty `any` or id
+    /// list never does.
+    pub fn to_lua(&self) -> String {
+        match self {
+            BindingPolicy::UserId { ids } => format!("one_of(get_user_id(), {})", id_list(ids)),
+            BindingPolicy::PlaceId { ids } => format!("one_of(game.PlaceId, {})", id_list(ids)),
+            BindingPolicy::UniverseId { ids } => format!("one_of(game.GameId, {})", id_list(ids)),
+            BindingPolicy::GroupOwner { group_id } => format!("owned_by_group({})", group_id),
+            BindingPolicy::All { policies } => combine(policies, "and", "true"),
+            BindingPolicy::Any { policies } => combine(policies, "or", "false"),
+        }
+    }
+}
+
+fn id_list(ids: &[u64]) -> String {
+    let ids: Vec<String> = ids.iter().map(u64::to_string).collect();
+    format!("{{{}}}", ids.join(", "))
+}
+
+fn combine(policies: &[BindingPolicy], operator: &str, empty: &str) -> String {
+    match policies {
+        [] => empty.to_string(),
+        [single] => single.to_lua(),
+        _ => {
+            let parts: Vec<String> = policies.iter().map(BindingPolicy::to_lua).collect();
+            format!("({})", parts.join(&format!(" {} ", operator)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_tree_renders_nested_expression() {
+        let policy = BindingPolicy::Any {
+            policies: vec![
+                BindingPolicy::UniverseId { ids: vec![111, 222] },
+                BindingPolicy::All {
+                    policies: vec![
+                        BindingPolicy::GroupOwner { group_id: 42 },
+                        BindingPolicy::PlaceId { ids: vec![7] },
+                    ],
+                },
+            ],
+        };
+
+        assert_eq!(
+            policy.to_lua(),
+            "(one_of(game.GameId, {111, 222}) or (owned_by_group(42) and one_of(game.PlaceId, {7})))"
+        );
+        assert_eq!(BindingPolicy::Any { policies: vec![] }.to_lua(), "false");
+    }
+
+    #[test]
+    fn test_legacy_modes_map_to_policies() {
+        let both = BindingPolicy::from_mode("both", Some(1), Some(2), &[]).unwrap();
+        assert_eq!(
+            both.to_lua(),
+            "(one_of(get_user_id(), {1}) and one_of(game.PlaceId, {2}))"
+        );
+
+        let whitelist = BindingPolicy::from_mode("whitelist", None, None, &[5, 6]).unwrap();
+        assert_eq!(whitelist, BindingPolicy::UserId { ids: vec![5, 6] });
+
+        assert!(BindingPolicy::from_mode("hwid", None, None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_policy_deserializes_from_config() {
+        let policy: BindingPolicy = serde_json::from_str(
+            r#"{"type": "any", "policies": [
+                {"type": "universe_id", "ids": [123]},
+                {"type": "group_owner", "group_id": 9}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.to_lua(), "(one_of(game.GameId, {123}) or owned_by_group(9))");
+    }
+}
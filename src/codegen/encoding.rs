@@ -0,0 +1,272 @@
+//! Encodings for ciphertext and nonces embedded in the script
+//!
+//! Base64 is compact but every byte goes through a Luau decode loop at load.
+//! Escaped byte strings are decoded by the Luau compiler itself, at the cost
+//! of a larger source; numeric arrays avoid string literals entirely but are
+//! the largest and slowest to parse. Base85 is the most compact and needs
+//! its own decoder in the runtime.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Embedded binary data up to this size is emitted as escaped strings:
+/// the source grows, but nothing is decoded at load
+pub const ESCAPED_LIMIT: usize = 2 * 1024;
+
+/// Embedded binary data past this size is emitted as base85, which keeps
+/// large tables about 6% smaller than base64
+pub const BASE85_THRESHOLD: usize = 64 * 1024;
+
+/// Luau function the base85 decoder defines
+pub const BASE85_DECODER: &str = "_b85decode";
+
+/// Z85 alphabet: no quotes or backslashes, so encoded data never needs escaping
+const Z85: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// How `ct` and `nonce` fields are written into the script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryEncoding {
+    /// Pick by the amount of embedded data (see `resolve`)
+    #[default]
+    Auto,
+    Base64,
+    Base85,
+    /// String literals with `\ddd` escapes for unprintable bytes
+    Escaped,
+    /// `{12,0,255}` byte arrays
+    Numeric,
+}
+
+impl BinaryEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryEncoding::Auto => "auto",
+            BinaryEncoding::Base64 => "base64",
+            BinaryEncoding::Base85 => "base85",
+            BinaryEncoding::Escaped => "escaped",
+            BinaryEncoding::Numeric => "numeric",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(BinaryEncoding::Auto),
+            "base64" => Some(BinaryEncoding::Base64),
+            "base85" => Some(BinaryEncoding::Base85),
+            "escaped" => Some(BinaryEncoding::Escaped),
+            "numeric" => Some(BinaryEncoding::Numeric),
+            _ => None,
+        }
+    }
+
+    /// The concrete encoding for `binary_bytes` of embedded data
+    ///
+    /// `Auto` never picks numeric arrays: they are only worth it to keep
+    /// ciphertext out of string literals.
+    pub fn resolve(self, binary_bytes: usize) -> Self {
+        match self {
+            BinaryEncoding::Auto if binary_bytes <= ESCAPED_LIMIT => BinaryEncoding::Escaped,
+            BinaryEncoding::Auto if binary_bytes > BASE85_THRESHOLD => BinaryEncoding::Base85,
+            BinaryEncoding::Auto => BinaryEncoding::Base64,
+            encoding => encoding,
+        }
+    }
+
+    /// `bytes` as a Luau expression
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Auto | BinaryEncoding::Base64 => format!(
+                "\"{}\"",
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            ),
+            BinaryEncoding::Base85 => format!("\"{}\"", encode_base85(bytes)),
+            BinaryEncoding::Escaped => format!("\"{}\"", escape_bytes(bytes)),
+            BinaryEncoding::Numeric => {
+                let values: Vec<String> = bytes.iter().map(u8::to_string).collect();
+                format!("{{{}}}", values.join(","))
+            }
+        }
+    }
+
+    /// Bytes of a Luau expression written by `encode`
+    pub fn decode(self, expr: &str) -> Option<Vec<u8>> {
+        if self == BinaryEncoding::Numeric {
+            let values = expr.trim().strip_prefix('{')?.strip_suffix('}')?;
+            return values
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse().ok())
+                .collect();
+        }
+
+        let text = expr.trim().strip_prefix('"')?.strip_suffix('"')?;
+        match self {
+            BinaryEncoding::Auto | BinaryEncoding::Base64 => {
+                base64::engine::general_purpose::STANDARD.decode(text).ok()
+            }
+            BinaryEncoding::Base85 => decode_base85(text),
+            BinaryEncoding::Escaped => unescape_bytes(text),
+            BinaryEncoding::Numeric => unreachable!(),
+        }
+    }
+
+    /// `ChaCha20` call decrypting the `ct`/`nonce` fields of `var`
+    pub fn render_decrypt(self, var: &str) -> String {
+        let decode = |field: &str| match self {
+            BinaryEncoding::Base85 => format!("{}({}.{})", BASE85_DECODER, var, field),
+            BinaryEncoding::Escaped => format!("ChaCha20.string_to_bytes({}.{})", var, field),
+            _ => format!("{}.{}", var, field),
+        };
+        match self {
+            BinaryEncoding::Auto | BinaryEncoding::Base64 => format!(
+                "ChaCha20.decrypt_string({}.ct, _key, {}.nonce)",
+                var, var
+            ),
+            _ => format!(
+                "ChaCha20.decrypt_bytes({}, _key, {})",
+                decode("ct"),
+                decode("nonce")
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for BinaryEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Z85 digits for `bytes`; a trailing group of `n` bytes takes `n + 1` digits
+fn encode_base85(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(4) * 5);
+    for group in bytes.chunks(4) {
+        let mut padded = [0u8; 4];
+        padded[..group.len()].copy_from_slice(group);
+        let mut value = u32::from_be_bytes(padded);
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = Z85[(value % 85) as usize];
+            value /= 85;
+        }
+        output.extend(digits[..group.len() + 1].iter().map(|&d| d as char));
+    }
+    output
+}
+
+/// Inverse of `encode_base85`; a trailing partial group is padded with the
+/// highest digit, which leaves its leading bytes intact
+fn decode_base85(text: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(text.len() / 5 * 4 + 3);
+    for group in text.as_bytes().chunks(5) {
+        if group.len() < 2 {
+            return None;
+        }
+        let mut value: u64 = 0;
+        for i in 0..5 {
+            let digit = match group.get(i) {
+                Some(c) => Z85.iter().position(|z| z == c)? as u64,
+                None => 84,
+            };
+            value = value * 85 + digit;
+        }
+        let value = u32::try_from(value).ok()?;
+        output.extend_from_slice(&value.to_be_bytes()[..group.len() - 1]);
+    }
+    Some(output)
+}
+
+/// Printable ASCII as is, everything else (and `"`, `\`) as `\ddd`
+fn escape_bytes(bytes: &[u8]) -> String {
+    let printable = |b: u8| (0x20..=0x7E).contains(&b) && b != b'"' && b != b'\\';
+    let mut output = String::with_capacity(bytes.len() * 3);
+    for (i, &byte) in bytes.iter().enumerate() {
+        if printable(byte) {
+            output.push(byte as char);
+        } else if bytes.get(i + 1).is_some_and(|&next| next.is_ascii_digit()) {
+            // A digit right after a short escape would be read as part of it
+            output.push_str(&format!("\\{:03}", byte));
+        } else {
+            output.push_str(&format!("\\{}", byte));
+        }
+    }
+    output
+}
+
+/// Bytes of an escaped string literal's contents
+fn unescape_bytes(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            output.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let escape = *bytes.get(i + 1)?;
+        if escape.is_ascii_digit() {
+            let digits = bytes[i + 1..]
+                .iter()
+                .take(3)
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            let value: u16 = text[i + 1..i + 1 + digits].parse().ok()?;
+            output.push(u8::try_from(value).ok()?);
+            i += 1 + digits;
+        } else {
+            output.push(match escape {
+                b'n' => b'\n',
+                b'r' => b'\r',
+                b't' => b'\t',
+                other => other,
+            });
+            i += 2;
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_encoding_round_trips() {
+        let data: Vec<u8> = (0..=255u8).chain([b'"', b'\\', b'7', 0, b'1']).collect();
+        for encoding in [
+            BinaryEncoding::Base64,
+            BinaryEncoding::Base85,
+            BinaryEncoding::Escaped,
+            BinaryEncoding::Numeric,
+        ] {
+            for len in [0, 1, 2, 3, 4, 5, 12, data.len()] {
+                let expr = encoding.encode(&data[..len]);
+                assert_eq!(
+                    encoding.decode(&expr).as_deref(),
+                    Some(&data[..len]),
+                    "{} of {} bytes",
+                    encoding,
+                    len
+                );
+            }
+            let expr = encoding.encode(&data);
+            assert!(full_moon::parse(&format!("local x = {}", expr)).is_ok());
+        }
+
+        // `\0` followed by `1` must not read as `\01`
+        assert_eq!(BinaryEncoding::Escaped.encode(&[0, b'1']), "\"\\0001\"");
+        assert_eq!(encode_base85(&[0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B]), "HelloWorld");
+    }
+
+    #[test]
+    fn test_auto_resolves_by_size() {
+        assert_eq!(BinaryEncoding::Auto.resolve(100), BinaryEncoding::Escaped);
+        assert_eq!(BinaryEncoding::Auto.resolve(10_000), BinaryEncoding::Base64);
+        assert_eq!(BinaryEncoding::Auto.resolve(100_000), BinaryEncoding::Base85);
+        assert_eq!(BinaryEncoding::Numeric.resolve(100_000), BinaryEncoding::Numeric);
+    }
+}
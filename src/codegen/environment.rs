@@ -0,0 +1,213 @@
+//! Exploit environment and run context checks
+//!
+//! Startup validation can look for globals only exploit executors define
+//! (`hookfunction`, `getgenv`, `syn`, ...) and ask `identifyexecutor` for
+//! the executor's name. What a hit does is up to the seller: stop through
+//! the tamper policy, keep running with corrupted decrypted values, or only
+//! report. Separately, a script built for one side of the client/server
+//! boundary refuses to run on the other.
+
+use super::tamper::{lua_string, CORRUPT_VALUE};
+use super::{TamperPolicy, CORRUPT_FUNCTION, TAMPER_HANDLER};
+use serde::{Deserialize, Serialize};
+
+/// Globals checked by default
+pub const DEFAULT_EXECUTOR_FINGERPRINTS: &[&str] = &[
+    "identifyexecutor",
+    "hookfunction",
+    "getgenv",
+    "syn",
+    "fluxus",
+];
+
+/// Fingerprint that is called for the executor's name rather than only
+/// tested for
+const IDENTIFY_EXECUTOR: &str = "identifyexecutor";
+
+/// Chance a decrypted value is corrupted once the script is degraded
+const DEGRADE_CHANCE: f32 = 0.25;
+
+/// What the script does when it finds an exploit executor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutorResponse {
+    /// Stop through the tamper policy
+    #[default]
+    Block,
+    /// Keep running, but corrupt some decrypted values; with the
+    /// `time_bomb` tamper policy this starts the time bomb instead
+    Degrade,
+    /// Keep running normally and only send a violation report
+    Report,
+}
+
+/// Exploit executor fingerprint check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutorCheck {
+    #[serde(default)]
+    pub response: ExecutorResponse,
+    /// Globals whose presence gives an executor away
+    #[serde(default = "default_fingerprints")]
+    pub globals: Vec<String>,
+}
+
+fn default_fingerprints() -> Vec<String> {
+    DEFAULT_EXECUTOR_FINGERPRINTS
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+impl ExecutorCheck {
+    pub fn new(response: ExecutorResponse) -> Self {
+        Self {
+            response,
+            globals: default_fingerprints(),
+        }
+    }
+
+    /// Whether decrypted values must pass through `_corrupt`
+    pub fn corrupts_values(&self) -> bool {
+        self.response == ExecutorResponse::Degrade
+    }
+
+    /// `_corrupt`, defined after `_tamper` for the degrade response; empty
+    /// when `policy` already defines it
+    pub fn setup(&self, policy: &TamperPolicy) -> String {
+        if !self.corrupts_values() || policy.corrupts_values() {
+            return String::new();
+        }
+        let mut code = format!("local {} = function(value)\n", CORRUPT_FUNCTION);
+        code.push_str(&format!(
+            "    if not _tamper_state.degraded or math.random() > {} then return value end\n",
+            DEGRADE_CHANCE
+        ));
+        code.push_str(CORRUPT_VALUE);
+        code
+    }
+
+    /// Lines for the startup validation block
+    pub fn render(&self, policy: &TamperPolicy) -> String {
+        if self.globals.is_empty() {
+            return String::new();
+        }
+        let mut code = String::from("    local executor = nil\n");
+        for (i, name) in self.globals.iter().enumerate() {
+            let keyword = if i == 0 { "if" } else { "elseif" };
+            code.push_str(&format!("    {} {} ~= nil then\n", keyword, name));
+            if name == IDENTIFY_EXECUTOR {
+                code.push_str(&format!("        local ok, name = pcall({})\n", name));
+                code.push_str(&format!(
+                    "        executor = ok and tostring(name) or {}\n",
+                    lua_string(name)
+                ));
+            } else {
+                code.push_str(&format!("        executor = {}\n", lua_string(name)));
+            }
+        }
+        code.push_str("    end\n");
+        code.push_str("    if executor then\n");
+        let reason = "\"executor: \" .. executor";
+        let report = format!(
+            "        if _tamper_state.report then\n            \
+             task.spawn(pcall, _tamper_state.report, {})\n        end\n",
+            reason
+        );
+        match self.response {
+            ExecutorResponse::Block => {
+                code.push_str(&format!("        {}({})\n", TAMPER_HANDLER, reason));
+            }
+            // The time bomb is already a slow degradation
+            ExecutorResponse::Degrade if policy.corrupts_values() => {
+                code.push_str(&format!("        {}({})\n", TAMPER_HANDLER, reason));
+            }
+            ExecutorResponse::Degrade => {
+                code.push_str("        _tamper_state.degraded = true\n");
+                code.push_str(&report);
+            }
+            ExecutorResponse::Report => code.push_str(&report),
+        }
+        code.push_str("    end\n");
+        code
+    }
+}
+
+/// Side of the client/server boundary a script is built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunContext {
+    /// Runs anywhere (no check)
+    #[default]
+    Shared,
+    /// Refuses to run on a client
+    Server,
+    /// Refuses to run on the server
+    Client,
+}
+
+impl RunContext {
+    /// Line for the startup validation block, or nothing for `Shared`
+    pub fn render(&self) -> String {
+        let (check, side) = match self {
+            RunContext::Shared => return String::new(),
+            RunContext::Server => ("IsServer", "server"),
+            RunContext::Client => ("IsClient", "client"),
+        };
+        format!(
+            "    if not game:GetService(\"RunService\"):{}() then {}(\"context: not {}\") end\n",
+            check, TAMPER_HANDLER, side
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executor_check_per_response() {
+        let policy = TamperPolicy::default();
+        let block = ExecutorCheck::new(ExecutorResponse::Block).render(&policy);
+        assert!(
+            block.starts_with("    local executor = nil\n    if identifyexecutor ~= nil then\n")
+        );
+        assert!(block.contains("        local ok, name = pcall(identifyexecutor)\n"));
+        assert!(block.contains("    elseif syn ~= nil then\n        executor = \"syn\"\n"));
+        assert!(block.contains("        _tamper(\"executor: \" .. executor)\n"));
+        assert!(full_moon::parse(&format!("do\n{}end\n", block)).is_ok());
+
+        let report = ExecutorCheck::new(ExecutorResponse::Report).render(&policy);
+        assert!(
+            report.contains("task.spawn(pcall, _tamper_state.report, \"executor: \" .. executor)")
+        );
+        assert!(!report.contains("_tamper("));
+
+        let degrade = ExecutorCheck::new(ExecutorResponse::Degrade);
+        assert!(degrade
+            .render(&policy)
+            .contains("_tamper_state.degraded = true"));
+        assert!(degrade
+            .setup(&policy)
+            .starts_with("local _corrupt = function(value)\n"));
+        // The time bomb's own `_corrupt` takes over
+        let time_bomb = TamperPolicy::TimeBomb { delay_seconds: 60 };
+        assert!(degrade.setup(&time_bomb).is_empty());
+        assert!(degrade
+            .render(&time_bomb)
+            .contains("_tamper(\"executor: \" .. executor)"));
+
+        let check: ExecutorCheck = serde_json::from_str(r#"{"response": "degrade"}"#).unwrap();
+        assert_eq!(check, degrade);
+    }
+
+    #[test]
+    fn test_run_context_checks_side() {
+        assert!(RunContext::Shared.render().is_empty());
+        assert_eq!(
+            RunContext::Server.render(),
+            "    if not game:GetService(\"RunService\"):IsServer() then \
+             _tamper(\"context: not server\") end\n"
+        );
+        assert!(RunContext::Client.render().contains(":IsClient()"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Anti-beautifier output formatting
+//!
+//! Final pass over emitted Luau: strips comments and collapses whitespace so
+//! that running the result through a beautifier recovers as little of the
+//! original layout as possible.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, TokenType};
+use serde::{Deserialize, Serialize};
+
+/// Target width for packed output lines
+const PACKED_LINE_WIDTH: usize = 2000;
+
+/// How emitted code is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormatting {
+    /// Strip comments and indentation, one statement line per source line
+    Minify,
+    /// Strip comments and pack statements onto long lines
+    Packed,
+    /// Leave emitted code untouched (for debugging)
+    #[default]
+    Readable,
+}
+
+impl std::fmt::Display for OutputFormatting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormatting::Minify => write!(f, "minify"),
+            OutputFormatting::Packed => write!(f, "packed"),
+            OutputFormatting::Readable => write!(f, "readable"),
+        }
+    }
+}
+
+/// Formats emitted code according to an `OutputFormatting` style
+pub struct OutputFormatter {
+    style: OutputFormatting,
+    misleading_indentation: bool,
+}
+
+impl OutputFormatter {
+    pub fn new(style: OutputFormatting, misleading_indentation: bool) -> Self {
+        Self {
+            style,
+            misleading_indentation,
+        }
+    }
+
+    /// Reformat Luau source
+    pub fn format(&self, code: &str) -> Result<String> {
+        if self.style == OutputFormatting::Readable {
+            return Ok(code.to_string());
+        }
+
+        let tokens = tokens(code).map_err(|e| {
+            ObfuscatorError::CodeGenError(format!("Failed to tokenize output: {}", e))
+        })?;
+
+        let mut output = String::with_capacity(code.len());
+        let mut prev: Option<String> = None;
+        let mut saw_newline = false;
+        let mut line_len = 0;
+        let mut emitted = 0usize;
+
+        for token in &tokens {
+            match token.token_type() {
+                TokenType::Whitespace { .. } => {
+                    if token.to_string().contains('\n') {
+                        saw_newline = true;
+                    }
+                    continue;
+                }
+                TokenType::SingleLineComment { .. } | TokenType::MultiLineComment { .. } => {
+                    continue
+                }
+                TokenType::Eof => break,
+                _ => {}
+            }
+
+            let text = token.to_string();
+
+            if let Some(prev_text) = &prev {
+                // A newline before `(` must survive: joining would turn the
+                // previous expression into a call
+                let opens_paren = text.starts_with('(');
+
+                let separator = match self.style {
+                    OutputFormatting::Minify if saw_newline => self.line_break(emitted),
+                    OutputFormatting::Packed if saw_newline && opens_paren => {
+                        self.line_break(emitted)
+                    }
+                    OutputFormatting::Packed
+                        if !opens_paren
+                            && (line_len > PACKED_LINE_WIDTH
+                                || (self.misleading_indentation && emitted % 23 == 11)) =>
+                    {
+                        self.line_break(emitted)
+                    }
+                    _ if Self::needs_space(prev_text, &text) => " ".to_string(),
+                    _ => String::new(),
+                };
+
+                if separator.starts_with('\n') {
+                    line_len = separator.len() - 1;
+                } else {
+                    line_len += separator.len();
+                }
+                output.push_str(&separator);
+            }
+
+            output.push_str(&text);
+            line_len += text.len();
+            emitted += 1;
+            saw_newline = false;
+            prev = Some(text);
+        }
+
+        output.push('\n');
+        Ok(output)
+    }
+
+    /// Newline followed by (possibly misleading) indentation
+    fn line_break(&self, position: usize) -> String {
+        if self.misleading_indentation {
+            // Indentation unrelated to block depth
+            let depth = (position.wrapping_mul(7) + 3) % 9;
+            format!("\n{}", "\t".repeat(depth))
+        } else {
+            "\n".to_string()
+        }
+    }
+
+    /// Whether two adjacent tokens would merge into a different token without a space
+    pub(crate) fn needs_space(prev: &str, next: &str) -> bool {
+        let (Some(a), Some(b)) = (prev.chars().last(), next.chars().next()) else {
+            return false;
+        };
+
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let is_operator = |c: char| "+-*/%^#&~|<>=.:".contains(c);
+
+        (is_word(a) && is_word(b))
+            || (is_operator(a) && is_operator(b))
+            || (a == '[' && (b == '[' || b == '='))
+            || (a.is_ascii_digit() && b == '.')
+    }
+}
+
+impl Default for OutputFormatter {
+    fn default() -> Self {
+        Self::new(OutputFormatting::Readable, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "-- header comment\n\
+                          local x = 1 -- trailing\n\
+                          --[[ block\n comment ]]\n\
+                          if x == 1 then\n    print(\"a -- not a comment\")\n end\n";
+
+    #[test]
+    fn test_readable_is_untouched() {
+        let formatter = OutputFormatter::new(OutputFormatting::Readable, false);
+        assert_eq!(formatter.format(SAMPLE).unwrap(), SAMPLE);
+    }
+
+    #[test]
+    fn test_minify_strips_comments_and_indentation() {
+        let formatter = OutputFormatter::new(OutputFormatting::Minify, false);
+        let output = formatter.format(SAMPLE).unwrap();
+
+        assert!(!output.contains("header comment"));
+        assert!(!output.contains("block"));
+        assert!(output.contains("\"a -- not a comment\""));
+        assert!(output.contains("local x=1\n"));
+        assert!(!output.contains("    "));
+    }
+
+    #[test]
+    fn test_packed_single_line() {
+        let formatter = OutputFormatter::new(OutputFormatting::Packed, false);
+        let output = formatter.format(SAMPLE).unwrap();
+
+        assert_eq!(output.trim_end().lines().count(), 1);
+        assert!(output.contains("local x=1 if x==1 then"));
+    }
+
+    #[test]
+    fn test_packed_keeps_newline_before_paren() {
+        let formatter = OutputFormatter::new(OutputFormatting::Packed, false);
+        let output = formatter.format("local a = b\n(f)()\n").unwrap();
+
+        assert!(output.contains("b\n(f)"));
+    }
+
+    #[test]
+    fn test_needs_space_guards_token_merging() {
+        assert!(OutputFormatter::needs_space("local", "x"));
+        assert!(OutputFormatter::needs_space("-", "-"));
+        assert!(OutputFormatter::needs_space("[", "["));
+        assert!(OutputFormatter::needs_space("1", ".."));
+        assert!(!OutputFormatter::needs_space("x", "="));
+        assert!(!OutputFormatter::needs_space(")", "."));
+    }
+
+    #[test]
+    fn test_misleading_indentation_parses_back() {
+        let source = (0..50)
+            .map(|i| format!("local v{} = v{} + {}\n", i, i, i))
+            .collect::<String>();
+
+        let formatter = OutputFormatter::new(OutputFormatting::Packed, true);
+        let output = formatter.format(&source).unwrap();
+
+        assert!(output.contains('\t'));
+        assert!(full_moon::parse(&output).is_ok());
+    }
+}
@@ -0,0 +1,181 @@
+//! Key delivery through instance attributes
+//!
+//! Some sellers hand out per-customer key material through a licensing
+//! plugin that sets Attributes or StringValues on the script. The master key
+//! is split into one share per configured source plus an embedded share; the
+//! script XORs them back together at load, so a copy without the buyer's
+//! instances can't decrypt anything.
+
+use super::tamper::lua_string;
+use super::templates::{bundled_template, TemplateProcessor, TemplateValue, TemplateVars};
+use super::TAMPER_HANDLER;
+use crate::crypto::CryptoContext;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where one key share is read from at runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeySource {
+    /// `instance:GetAttribute(name)`
+    Attribute {
+        name: String,
+        /// Luau expression for the instance holding the attribute
+        #[serde(default = "default_instance")]
+        instance: String,
+    },
+    /// `.Value` of the StringValue `instance:WaitForChild(name)`
+    StringValue {
+        name: String,
+        #[serde(default = "default_instance")]
+        instance: String,
+    },
+}
+
+fn default_instance() -> String {
+    "script".to_string()
+}
+
+impl KeySource {
+    /// Luau function returning the share string
+    fn render_reader(&self) -> String {
+        match self {
+            KeySource::Attribute { name, instance } => format!(
+                "function() return {}:GetAttribute({}) end",
+                instance,
+                lua_string(name)
+            ),
+            KeySource::StringValue { name, instance } => format!(
+                "function() return {}:WaitForChild({}, 10).Value end",
+                instance,
+                lua_string(name)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Attribute { name, instance } => {
+                write!(f, "attribute {:?} on {}", name, instance)
+            }
+            KeySource::StringValue { name, instance } => {
+                write!(f, "StringValue {}.{}", instance, name)
+            }
+        }
+    }
+}
+
+/// Sources the script reads key shares from, in order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyDelivery {
+    pub sources: Vec<KeySource>,
+}
+
+impl KeyDelivery {
+    /// Base64 share each source must hold for `license_key`
+    ///
+    /// Shares are derived from the master key, so the same password, salt and
+    /// license key always give the same values.
+    pub fn share_values(
+        &self,
+        crypto: &CryptoContext,
+        license_key: &str,
+    ) -> Vec<(&KeySource, String)> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        self.shares(crypto, license_key)
+            .iter()
+            .zip(&self.sources)
+            .map(|(share, source)| (source, engine.encode(share)))
+            .collect()
+    }
+
+    /// Render the `_key` local rebuilt from the embedded and delivered shares
+    pub fn render(&self, crypto: &CryptoContext, license_key: &str) -> Result<String> {
+        if self.sources.is_empty() {
+            return Err(ObfuscatorError::ConfigError(
+                "Key delivery needs at least one source".to_string(),
+            )
+            .into());
+        }
+
+        let embedded = crypto.masked_key(&self.shares(crypto, license_key));
+        let sources: Vec<TemplateVars> = self
+            .sources
+            .iter()
+            .map(|source| {
+                TemplateVars::from([("READER".to_string(), source.render_reader().into())])
+            })
+            .collect();
+
+        let vars = TemplateVars::from([
+            (
+                "EMBEDDED_SHARE".to_string(),
+                base64::engine::general_purpose::STANDARD.encode(embedded).into(),
+            ),
+            ("SOURCES".to_string(), TemplateValue::List(sources)),
+            ("TAMPER".to_string(), TAMPER_HANDLER.into()),
+        ]);
+
+        TemplateProcessor::new()?.render(bundled_template("key_delivery.lua")?, &vars)
+    }
+
+    fn shares(&self, crypto: &CryptoContext, license_key: &str) -> Vec<Vec<u8>> {
+        (0..self.sources.len())
+            .map(|i| crypto.key_share(&format!("key-delivery/{}/{}", license_key, i)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery() -> KeyDelivery {
+        KeyDelivery {
+            sources: vec![
+                KeySource::Attribute {
+                    name: "LicenseShare".to_string(),
+                    instance: default_instance(),
+                },
+                KeySource::StringValue {
+                    name: "KeyPart".to_string(),
+                    instance: "game.ServerStorage".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_shares_are_per_license_and_reproducible() {
+        let ctx = CryptoContext::new("test_password", None).unwrap();
+        let delivery = delivery();
+
+        let first = delivery.share_values(&ctx, "KEY-1");
+        assert_eq!(first.len(), 2);
+        assert_ne!(first[0].1, first[1].1);
+
+        let again = CryptoContext::new("test_password", Some(ctx.salt())).unwrap();
+        assert_eq!(delivery.share_values(&again, "KEY-1"), first);
+        assert_ne!(delivery.share_values(&ctx, "KEY-2")[0].1, first[0].1);
+    }
+
+    #[test]
+    fn test_render_reads_every_source() {
+        let ctx = CryptoContext::new("test_password", None).unwrap();
+        let code = delivery().render(&ctx, "KEY-1").unwrap();
+
+        assert!(code.contains("local _key = (function()"));
+        assert!(code.contains("script:GetAttribute(\"LicenseShare\")"));
+        assert!(code.contains("game.ServerStorage:WaitForChild(\"KeyPart\", 10).Value"));
+        assert!(code.contains("_tamper(\"key\")"));
+        assert!(!code.contains("{{"));
+
+        let empty = KeyDelivery { sources: vec![] };
+        assert!(empty.render(&ctx, "KEY-1").is_err());
+    }
+}
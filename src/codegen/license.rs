@@ -1,9 +1,8 @@
 //! License validation code generation
 
-use crate::utils::errors::ObfuscatorError;
+use super::templates::{TemplateProcessor, TemplateValue, TemplateVars};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 /// License configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,42 +11,49 @@ pub struct LicenseConfig {
     pub script_id: String,
     pub api_endpoint: String,
     pub watermark: String,
+    /// `revocation_fingerprint` of each key the check rejects offline
+    #[serde(default)]
+    pub revoked: Vec<String>,
 }
 
 /// Generates license validation code
-pub struct LicenseValidator;
+pub struct LicenseValidator {
+    templates: TemplateProcessor,
+}
 
 impl LicenseValidator {
     pub fn new() -> Self {
-        Self
+        Self {
+            templates: TemplateProcessor::default(),
+        }
+    }
+    
+    /// Load the license check through `templates` (which may override it)
+    pub fn with_templates(mut self, templates: TemplateProcessor) -> Self {
+        self.templates = templates;
+        self
     }
     
     /// Generate license validation code from template
     pub fn generate(&self, config: LicenseConfig) -> Result<String> {
-        // Load template
-        let template_path = std::env::current_dir()?
-            .join("templates")
-            .join("license_validation.lua");
-        
-        let template = std::fs::read_to_string(&template_path)
-            .map_err(|e| ObfuscatorError::CodeGenError(
-                format!("Failed to load license validation template: {}", e)
-            ))?;
+        let template = self.templates.load_template("license_validation.lua")?;
         
         // Process template variables
-        let mut vars = HashMap::new();
-        vars.insert("LICENSE_KEY".to_string(), config.license_key);
-        vars.insert("SCRIPT_ID".to_string(), config.script_id);
-        vars.insert("API_ENDPOINT".to_string(), config.api_endpoint);
-        vars.insert("WATERMARK".to_string(), config.watermark);
-        
-        let mut result = template;
-        for (key, value) in vars {
-            let placeholder = format!("{{{{{}}}}}", key);
-            result = result.replace(&placeholder, &value);
-        }
+        let revoked: Vec<TemplateVars> = config
+            .revoked
+            .into_iter()
+            .map(|fingerprint| {
+                TemplateVars::from([("FINGERPRINT".to_string(), fingerprint.into())])
+            })
+            .collect();
+        let mut vars = TemplateVars::new();
+        vars.insert("LICENSE_KEY".to_string(), config.license_key.into());
+        vars.insert("SCRIPT_ID".to_string(), config.script_id.into());
+        vars.insert("API_ENDPOINT".to_string(), config.api_endpoint.into());
+        vars.insert("WATERMARK".to_string(), config.watermark.into());
+        vars.insert("REVOKED".to_string(), TemplateValue::List(revoked));
         
-        Ok(result)
+        self.templates.render(&template, &vars)
     }
 }
 
@@ -62,7 +68,6 @@ mod tests {
     use super::*;
 
     #[test]
-    #[ignore] // Requires templates directory
     fn test_license_generation() {
         let validator = LicenseValidator::new();
         let config = LicenseConfig {
@@ -70,9 +75,46 @@ mod tests {
             script_id: "test_script".to_string(),
             api_endpoint: "https://api.example.com".to_string(),
             watermark: "abc123".to_string(),
+            revoked: Vec::new(),
         };
         
         let license = validator.generate(config);
         assert!(license.is_ok());
     }
+
+    #[test]
+    fn test_license_reports_seat_heartbeat() {
+        let license = LicenseValidator::new()
+            .generate(LicenseConfig {
+                license_key: "TEST-1234-5678-9012".to_string(),
+                script_id: "test_script".to_string(),
+                api_endpoint: "https://api.example.com".to_string(),
+                watermark: "abc123".to_string(),
+                revoked: Vec::new(),
+            })
+            .unwrap();
+
+        assert!(license.contains("&session=%s"));
+        assert!(license.contains("\"%s/heartbeat?key=%s&script=%s&hwid=%s&session=%s%s\""));
+        assert!(license.contains("local SEAT_LIMIT_EXCEEDED = \"seat_limit_exceeded\""));
+        assert!(full_moon::parse(&license).is_ok());
+    }
+
+    #[test]
+    fn test_revoked_keys_embedded() {
+        let license = LicenseValidator::new()
+            .generate(LicenseConfig {
+                license_key: "TEST-1234-5678-9012".to_string(),
+                script_id: "test_script".to_string(),
+                api_endpoint: "https://api.example.com".to_string(),
+                watermark: "abc123".to_string(),
+                revoked: vec!["e15fa58947da3efd".to_string(), "0123456789abcdef".to_string()],
+            })
+            .unwrap();
+
+        assert!(license.contains("    [\"e15fa58947da3efd\"] = true,\n"));
+        assert!(license.contains("    [\"0123456789abcdef\"] = true,\n"));
+        assert!(license.contains("if REVOKED_KEYS[key_fingerprint(LICENSE_KEY)] then"));
+        assert!(full_moon::parse(&license).is_ok());
+    }
 }
\ No newline at end of file
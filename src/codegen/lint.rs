@@ -0,0 +1,337 @@
+//! Roblox compatibility lint of generated scripts
+//!
+//! Most of a protected script is code its author never wrote (runtime,
+//! dead code, renamed locals), and it can parse cleanly yet still fail in
+//! Studio. The lint checks the output before it is written for:
+//! - libraries Roblox leaves out (`io`, `os.exit`, `dofile`) and
+//!   `loadstring`, which clients can't use and servers only with
+//!   `ServerScriptService.LoadStringEnabled`
+//! - more than 200 locals alive at once in one function, or 200 upvalues
+//!   captured by one closure
+//! - scopes nested deeper than Luau's parser follows
+//! - more distinct constants than a chunk should hold
+//!
+//! The compiler limits are errors, since the script would not load at all.
+//! Missing APIs are warnings: a script may only reach them outside Roblox.
+//! The constant count is a warning too, as it is taken over the whole chunk
+//! while Luau counts per function.
+
+use crate::analysis::{ScopeAnalyzer, ScopeKind};
+use crate::diagnostics::{Diagnostic, Severity, Span};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// Locals one function may have alive at once
+pub const MAX_LOCALS: usize = 200;
+
+/// Upvalues one closure may capture
+pub const MAX_UPVALUES: usize = 200;
+
+/// Scope nesting Luau's parser follows before giving up
+pub const MAX_NESTING: usize = 200;
+
+/// Distinct constants (strings, numbers, field names) one chunk should hold
+pub const MAX_CONSTANTS: usize = 65_535;
+
+/// Globals Roblox scripts don't have, reached through a call or an index
+const MISSING_GLOBALS: &[&str] = &["io", "dofile", "loadfile"];
+
+/// `os` members Roblox leaves out
+const MISSING_OS: &[&str] = &["exit", "execute", "getenv", "remove", "rename", "tmpname"];
+
+/// How a finding affects the script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// May fail at run time, depending on where the script runs
+    Warning,
+    /// The script won't load in Roblox
+    Error,
+}
+
+/// One problem found in a generated script
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    /// `missing-api`, `loadstring`, `locals`, `upvalues`, `nesting` or `constants`
+    pub rule: &'static str,
+    pub level: LintLevel,
+    /// Line in the generated script
+    pub line: usize,
+    pub message: String,
+}
+
+impl LintFinding {
+    /// The finding as a diagnostic whose id is its rule; its span is in the
+    /// generated script
+    pub fn diagnostic(&self) -> Diagnostic {
+        let severity = match self.level {
+            LintLevel::Warning => Severity::Warning,
+            LintLevel::Error => Severity::Error,
+        };
+        let suggestion = match self.rule {
+            "missing-api" => "only reach it where the API exists, or remove the call",
+            "loadstring" => "run it on the server with LoadStringEnabled, or avoid loadstring",
+            _ => "split the script or lower the tier",
+        };
+        let message = format!("Roblox compatibility: {}", self.message);
+        Diagnostic::new(self.rule, severity, message)
+            .at(Span::line(self.line))
+            .with_suggestion(suggestion)
+    }
+}
+
+impl std::fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Checks generated Luau against what Roblox accepts
+pub struct RobloxLint;
+
+impl RobloxLint {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Lint `code`, errors first
+    pub fn check(&self, code: &str) -> Result<Vec<LintFinding>> {
+        let mut findings = self.check_limits(code)?;
+        findings.extend(self.check_apis(code)?);
+        findings.sort_by_key(|finding| (finding.level != LintLevel::Error, finding.line));
+        Ok(findings)
+    }
+
+    /// Locals, upvalues and nesting, from the scope tree
+    fn check_limits(&self, code: &str) -> Result<Vec<LintFinding>> {
+        let scopes = ScopeAnalyzer::new().analyze_source(code)?;
+        // Last declaration in a scope, or in the closest enclosing one with any
+        let last_line = |mut id: usize| loop {
+            if let Some(line) = scopes[id].variables.values().map(|v| v.line).max() {
+                return line;
+            }
+            match scopes[id].parent {
+                Some(parent) => id = parent,
+                None => return 1,
+            }
+        };
+
+        let mut findings = Vec::new();
+        // Scopes are created parent-first, so each depth is known by the time
+        // its children are reached
+        let mut depths = vec![0usize; scopes.len()];
+        for scope in &scopes {
+            if let Some(parent) = scope.parent {
+                depths[scope.id] = depths[parent] + 1;
+                if depths[scope.id] == MAX_NESTING + 1 {
+                    findings.push(LintFinding {
+                        rule: "nesting",
+                        level: LintLevel::Error,
+                        line: last_line(scope.id),
+                        message: format!("scopes nest deeper than {} levels", MAX_NESTING),
+                    });
+                }
+            }
+
+            // Alive at a block's last declaration: its own locals and those its
+            // enclosing blocks declared before it, up to the function
+            if let Some(line) = scope.variables.values().map(|v| v.line).max() {
+                let mut alive = scope.variables.len();
+                let mut current = scope;
+                while current.kind == ScopeKind::Block {
+                    let Some(parent) = current.parent else { break };
+                    current = &scopes[parent];
+                    alive += current.variables.values().filter(|v| v.line < line).count();
+                }
+                if alive > MAX_LOCALS {
+                    findings.push(LintFinding {
+                        rule: "locals",
+                        level: LintLevel::Error,
+                        line,
+                        message: format!(
+                            "{} locals alive at once, over Luau's limit of {} per function",
+                            alive, MAX_LOCALS
+                        ),
+                    });
+                }
+            }
+
+            if scope.upvalues.len() > MAX_UPVALUES {
+                findings.push(LintFinding {
+                    rule: "upvalues",
+                    level: LintLevel::Error,
+                    line: scope.upvalues.iter().map(|u| u.line).min().unwrap_or(1),
+                    message: format!(
+                        "a function captures {} upvalues, over Luau's limit of {}",
+                        scope.upvalues.len(),
+                        MAX_UPVALUES
+                    ),
+                });
+            }
+        }
+        Ok(findings)
+    }
+
+    /// Missing libraries, `loadstring` and the constant count, from the tokens
+    fn check_apis(&self, code: &str) -> Result<Vec<LintFinding>> {
+        let all_tokens = tokens(code)
+            .map_err(|e| ObfuscatorError::CodeGenError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .collect();
+        // `local io = ...` isn't the library
+        let globals = ScopeAnalyzer::new().global_names_in_source(code)?;
+
+        // API → (first line, uses); one finding per API
+        let mut uses: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        let mut constants: HashSet<String> = HashSet::new();
+        for (i, token) in significant.iter().enumerate() {
+            let symbol = |j: usize| significant.get(j).and_then(|t| symbol_of(t));
+            let member = i > 0 && matches!(symbol(i - 1), Some(Symbol::Dot | Symbol::Colon));
+            match token.token_type() {
+                TokenType::StringLiteral { literal, .. } => {
+                    constants.insert(format!("s{}", literal));
+                }
+                TokenType::Number { text } => {
+                    constants.insert(format!("n{}", text));
+                }
+                TokenType::Identifier { identifier } if member => {
+                    constants.insert(format!("s{}", identifier));
+                }
+                TokenType::Identifier { identifier } if globals.contains(identifier.as_str()) => {
+                    let called = matches!(symbol(i + 1), Some(Symbol::LeftParen | Symbol::LeftBrace))
+                        || matches!(
+                            significant.get(i + 1).map(|t| t.token_type()),
+                            Some(TokenType::StringLiteral { .. })
+                        );
+                    let indexed = matches!(
+                        symbol(i + 1),
+                        Some(Symbol::Dot | Symbol::Colon | Symbol::LeftBracket)
+                    );
+                    let api = match identifier.as_str() {
+                        // The environment guard only reads it
+                        "loadstring" if called => Some("loadstring".to_string()),
+                        "os" if symbol(i + 1) == Some(Symbol::Dot) => {
+                            match significant.get(i + 2).map(|t| t.token_type()) {
+                                Some(TokenType::Identifier { identifier: field })
+                                    if MISSING_OS.contains(&field.as_str()) =>
+                                {
+                                    Some(format!("os.{}", field))
+                                }
+                                _ => None,
+                            }
+                        }
+                        name if MISSING_GLOBALS.contains(&name) && (called || indexed) => {
+                            Some(name.to_string())
+                        }
+                        _ => None,
+                    };
+                    if let Some(api) = api {
+                        let line = token.start_position().line();
+                        uses.entry(api).or_insert((line, 0)).1 += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut findings: Vec<LintFinding> = uses
+            .into_iter()
+            .map(|(api, (line, count))| {
+                let (rule, reason) = match api.as_str() {
+                    "loadstring" => (
+                        "loadstring",
+                        "only works on the server with ServerScriptService.LoadStringEnabled",
+                    ),
+                    _ => ("missing-api", "does not exist in Roblox"),
+                };
+                LintFinding {
+                    rule,
+                    level: LintLevel::Warning,
+                    line,
+                    message: format!("`{}` {} ({} use(s))", api, reason, count),
+                }
+            })
+            .collect();
+        if constants.len() > MAX_CONSTANTS {
+            findings.push(LintFinding {
+                rule: "constants",
+                level: LintLevel::Warning,
+                line: 1,
+                message: format!(
+                    "{} distinct constants; a function holding more than {} may not compile",
+                    constants.len(),
+                    MAX_CONSTANTS
+                ),
+            });
+        }
+        Ok(findings)
+    }
+}
+
+impl Default for RobloxLint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn symbol_of(token: &Token) -> Option<Symbol> {
+    match token.token_type() {
+        TokenType::Symbol { symbol } => Some(*symbol),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_missing_apis_once_each() {
+        let code = r#"local env = {loadstring = loadstring}
+io.write("a")
+io.write("b")
+local run = loadstring("return 1")
+os.exit(1)
+print(os.time(), t.io.x)
+"#;
+        let findings = RobloxLint::new().check(code).unwrap();
+        let summary: Vec<(&str, usize)> = findings.iter().map(|f| (f.rule, f.line)).collect();
+        assert_eq!(
+            summary,
+            vec![("missing-api", 2), ("loadstring", 4), ("missing-api", 5)]
+        );
+        assert!(findings.iter().all(|f| f.level == LintLevel::Warning));
+        assert!(findings[0].to_string().contains("`io` does not exist in Roblox (2 use(s))"));
+
+        // A local standing in for the library is fine
+        let shimmed = "local io = require(script.Io)\nio.write(\"a\")\n";
+        assert!(RobloxLint::new().check(shimmed).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_local_limit_counts_enclosing_blocks() {
+        let declare = |prefix: &str, count: usize| {
+            (0..count).map(|i| format!("local {}{} = {}\n", prefix, i, i)).collect::<String>()
+        };
+        // 150 + 40 alive at once is fine, a function starts over
+        let fits = format!(
+            "{}do\n{}end\nlocal function f()\n{}end\n",
+            declare("a", 150),
+            declare("b", 40),
+            declare("c", 100)
+        );
+        assert!(RobloxLint::new().check(&fits).unwrap().is_empty());
+
+        let over = format!("{}do\n{}end\n", declare("a", 150), declare("b", 60));
+        let findings = RobloxLint::new().check(&over).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "locals");
+        assert_eq!(findings[0].level, LintLevel::Error);
+        assert_eq!(findings[0].line, 211);
+    }
+}
@@ -0,0 +1,178 @@
+//! Signed metadata header
+//!
+//! The banner and watermark comments are plain text anyone can edit or
+//! strip. Protected scripts also carry a `-- Metadata:` line (script id,
+//! tier and licensed tier, timestamp, key version and a digest of the
+//! watermark line) signed with HMAC-SHA256 under a key derived from the
+//! seller's master key, so `validate` can tell an untouched header from a
+//! forged one.
+
+use super::assembly::KEY_VERSION_HEADER;
+use crate::crypto::CryptoContext;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Header line carrying the signed metadata
+pub const METADATA_HEADER: &str = "-- Metadata: ";
+
+/// Header line carrying the encoded watermark
+pub const WATERMARK_HEADER: &str = "-- Watermark: ";
+
+/// Key share label for the signing key
+const SIGNING_LABEL: &str = "metadata:hmac";
+
+/// What the metadata header vouches for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptMetadata {
+    pub script_id: String,
+    /// Tier the script was protected at
+    pub tier: String,
+    /// Tier of the validated license `tier` was held to; `None` when the
+    /// license wasn't checked online
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub licensed_tier: Option<String>,
+    /// Unix timestamp of the watermark
+    pub timestamp: u64,
+    pub key_version: u32,
+    /// Hex SHA-256 of the encoded watermark in the `-- Watermark:` line
+    pub watermark_digest: String,
+}
+
+impl ScriptMetadata {
+    /// Digest recorded for an encoded watermark
+    pub fn watermark_digest(encoded_watermark: &str) -> String {
+        hex::encode(digest(&SHA256, encoded_watermark.as_bytes()))
+    }
+
+    /// Metadata header of `protected`, without checking the signature
+    ///
+    /// Anyone can write such a header; only leak tracing, which matches it
+    /// against recorded builds, reads it unverified.
+    pub fn read_unsigned(protected: &str) -> Option<Self> {
+        let (payload, _) = header_value(protected, METADATA_HEADER)?.split_once('.')?;
+        let json = URL_SAFE_NO_PAD.decode(payload).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+/// Signs and verifies metadata headers for one master key
+#[derive(Clone)]
+pub struct MetadataSigner {
+    key: hmac::Key,
+}
+
+impl MetadataSigner {
+    pub fn new(crypto: &CryptoContext) -> Self {
+        let key_bytes = Zeroizing::new(crypto.key_share(SIGNING_LABEL));
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &key_bytes),
+        }
+    }
+
+    /// The full `-- Metadata:` line for `metadata` (no trailing newline)
+    pub fn sign(&self, metadata: &ScriptMetadata) -> Result<String> {
+        let json = serde_json::to_vec(metadata).map_err(|e| {
+            ObfuscatorError::CodeGenError(format!("Metadata encoding failed: {}", e))
+        })?;
+        let payload = URL_SAFE_NO_PAD.encode(json);
+        let signature = hmac::sign(&self.key, payload.as_bytes());
+        Ok(format!(
+            "{}{}.{}",
+            METADATA_HEADER,
+            payload,
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ))
+    }
+
+    /// Check the metadata header of `protected` and that the watermark and
+    /// key version lines still match it
+    pub fn verify(&self, protected: &str) -> Result<ScriptMetadata> {
+        let tampered = |reason: &str| -> anyhow::Error {
+            ObfuscatorError::CryptoError(format!("Tampering detected: {}", reason)).into()
+        };
+
+        let line = header_value(protected, METADATA_HEADER)
+            .ok_or_else(|| tampered("no signed metadata header"))?;
+        let (payload, signature) = line
+            .split_once('.')
+            .ok_or_else(|| tampered("malformed metadata header"))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| tampered("malformed metadata signature"))?;
+        hmac::verify(&self.key, payload.as_bytes(), &signature).map_err(|_| {
+            tampered("metadata signature is invalid (edited header or wrong password)")
+        })?;
+
+        let metadata: ScriptMetadata = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| tampered("malformed metadata payload"))?;
+
+        let watermark = header_value(protected, WATERMARK_HEADER)
+            .ok_or_else(|| tampered("watermark line was removed"))?;
+        if ScriptMetadata::watermark_digest(watermark) != metadata.watermark_digest {
+            return Err(tampered("watermark line was changed"));
+        }
+        let key_version = header_value(protected, KEY_VERSION_HEADER)
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        if key_version != metadata.key_version {
+            return Err(tampered("key version line was changed"));
+        }
+        Ok(metadata)
+    }
+}
+
+/// Value of the first `prefix` comment line
+fn header_value<'s>(protected: &'s str, prefix: &str) -> Option<&'s str> {
+    protected
+        .lines()
+        .find_map(|line| line.strip_prefix(prefix))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(watermark: &str) -> ScriptMetadata {
+        ScriptMetadata {
+            script_id: "test_script".to_string(),
+            tier: "premium".to_string(),
+            licensed_tier: Some("premium".to_string()),
+            timestamp: 1_700_000_000,
+            key_version: 0,
+            watermark_digest: ScriptMetadata::watermark_digest(watermark),
+        }
+    }
+
+    #[test]
+    fn test_signed_header_verifies_and_detects_edits() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let signer = MetadataSigner::new(&crypto);
+        let header = signer.sign(&metadata("V0FURVJNQVJL")).unwrap();
+        let script = format!(
+            "-- Watermark: V0FURVJNQVJL\n-- Key version: 0\n{}\n\nprint(1)\n",
+            header
+        );
+
+        assert_eq!(signer.verify(&script).unwrap(), metadata("V0FURVJNQVJL"));
+
+        let forged = script.replace("-- Watermark: V0FURVJNQVJL", "-- Watermark: Rk9SR0VE");
+        assert!(signer.verify(&forged).is_err());
+        let bumped = script.replace("-- Key version: 0", "-- Key version: 3");
+        assert!(signer.verify(&bumped).is_err());
+        let stripped = script.replace(&header, "");
+        assert!(signer.verify(&stripped).is_err());
+
+        let other = CryptoContext::new("other_password", None).unwrap();
+        assert!(MetadataSigner::new(&other).verify(&script).is_err());
+    }
+}
@@ -6,22 +6,73 @@
 //! - HWID binding checks
 //! - Encrypted data structures
 //! - Obfuscated original code
+//! - Anti-beautifier output formatting
+//! - Comment scrubbing and an optional license header
+//! - A Luau decompressor when payloads are compressed before encryption
+//! - Embedded ciphertext in base64, base85, escaped strings or byte arrays
+//! - A lint of the output for what Roblox doesn't allow
+//!
+//! Scripts are emitted as a single file, or split into a loader and an
+//! encrypted payload chunk (`CodeGenerator::generate_split`).
 
 mod assembly;
+mod binding;
+mod encoding;
+mod environment;
+mod formatting;
+mod key_delivery;
 mod license;
+mod lint;
+mod metadata;
+mod payload;
 mod runtime;
+mod scrub;
+mod shuffle;
+mod split;
+mod tamper;
 mod templates;
+mod update;
+mod vault;
+mod webhook;
 
-pub use assembly::ScriptAssembler;
+pub use assembly::{ScriptAssembler, ENCODING_HEADER, KEY_VERSION_HEADER};
+pub use binding::BindingPolicy;
+pub use encoding::{BinaryEncoding, BASE85_DECODER, BASE85_THRESHOLD, ESCAPED_LIMIT};
+pub use environment::{ExecutorCheck, ExecutorResponse, RunContext, DEFAULT_EXECUTOR_FINGERPRINTS};
+pub use formatting::{OutputFormatter, OutputFormatting};
+pub use key_delivery::{KeyDelivery, KeySource};
 pub use license::{LicenseConfig, LicenseValidator};
-pub use runtime::RuntimeGenerator;
-pub use templates::TemplateProcessor;
+pub use lint::{
+    LintFinding, LintLevel, RobloxLint, MAX_CONSTANTS, MAX_LOCALS, MAX_NESTING, MAX_UPVALUES,
+};
+pub use metadata::{MetadataSigner, ScriptMetadata, METADATA_HEADER, WATERMARK_HEADER};
+pub use payload::{PayloadEntry, PayloadFile, PayloadSource, PAYLOAD_VERSION};
+pub use runtime::{
+    RuntimeGenerator, DECOMPRESS_FUNCTION, DEFAULT_GUARDED_GLOBALS, EXECUTOR_GLOBALS,
+    GUARD_FUNCTION, TAMPER_HANDLER,
+};
+pub use scrub::{
+    normalize_line_endings, render_license_header, scrub_comments, take_directives, Directives,
+};
+pub use shuffle::{TableShuffle, POOL_ORDER, SHUFFLE_FUNCTION, STRING_ORDER};
+pub use split::{SplitOutput, PAYLOAD_MODULE};
+pub use tamper::{trap_handler, TamperPolicy, CORRUPT_FUNCTION};
+pub use templates::{
+    load_template_dir, validate_override, TemplateProcessor, TemplateValue, TemplateVars,
+};
+pub use update::{PayloadUpdate, UpdateChannel, UpdateKeys};
+pub use vault::{AntiDumpPolicy, DumpProtection, VAULT_TABLE};
+pub use webhook::{ViolationWebhook, WebhookFormat};
 
-use crate::crypto::{CryptoContext, EncryptedData};
-use crate::obfuscation::ObfuscationResult;
+use crate::crypto::{encoded_len, Compression, CryptoContext, EncryptedData, Watermark};
+use crate::obfuscation::{ConstantPool, DecryptionMode, ObfuscationResult, StringObfuscator};
+use crate::parser::{ConfigBlock, Sensitivity, TypeAnnotations};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use split::PayloadKey;
+use std::collections::HashMap;
 
 /// Configuration for code generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +83,13 @@ pub struct CodeGenConfig {
     /// Script identifier
     pub script_id: String,
     
+    /// Obfuscation tier name, recorded in the signed metadata header
+    pub tier: String,
+    
+    /// Tier of the validated license `tier` was checked against, recorded
+    /// next to it
+    pub licensed_tier: Option<String>,
+    
     /// API endpoint for license validation
     pub api_endpoint: String,
     
@@ -47,14 +105,108 @@ pub struct CodeGenConfig {
     /// Whitelist of authorized UserIds (for multi-user licenses)
     pub authorized_users: Vec<u64>,
     
+    /// Binding policy tree (place lists, universes, group ownership);
+    /// overrides `binding_mode`, `hwid`, `place_id` and `authorized_users`
+    pub binding: Option<BindingPolicy>,
+    
     /// Include ChaCha20 runtime (for decryption)
     pub include_runtime: bool,
     
     /// Include license validation
     pub include_license: bool,
     
+    /// `revocation_fingerprint` of each revoked key the license check
+    /// rejects without going online
+    pub revoked_keys: Vec<String>,
+    
     /// Include HWID binding
     pub include_hwid: bool,
+    
+    /// Layout of the emitted code (minify / packed / readable)
+    pub output_formatting: OutputFormatting,
+    
+    /// Break packed lines with indentation unrelated to block depth
+    pub misleading_indentation: bool,
+    
+    /// Decrypt strings at load (eager) or on first use (lazy)
+    pub string_decryption: DecryptionMode,
+    
+    /// Keep decrypted strings masked in `_vault`, by sensitivity
+    pub anti_dump: AntiDumpPolicy,
+    
+    /// Sensitivity of each string table entry
+    /// (`ObfuscatedScript::string_sensitivity`)
+    pub string_sensitivity: Vec<Sensitivity>,
+    
+    /// Keep or strip Luau type annotations in the protected code
+    pub type_annotations: TypeAnnotations,
+    
+    /// Emit the environment guard that detects hooked globals
+    pub anti_hook: bool,
+    
+    /// Globals the guard snapshots at load and re-checks before decryption
+    pub guarded_globals: Vec<String>,
+    
+    /// What the script does when a license, HWID or environment check fails
+    pub tamper_policy: TamperPolicy,
+    
+    /// Webhook notified when a check fails, in addition to the tamper policy
+    pub violation_webhook: Option<ViolationWebhook>,
+    
+    /// Look for exploit executor globals at startup
+    pub executor_check: Option<ExecutorCheck>,
+    
+    /// Refuse to run on the client (`Server`) or on the server (`Client`)
+    pub run_context: RunContext,
+    
+    /// Fetch encrypted strings from here after validation instead of
+    /// embedding them (export them with `CodeGenerator::export_payload`)
+    pub remote_payload: Option<PayloadSource>,
+    
+    /// Read part of the decryption key from instance attributes at runtime
+    pub key_delivery: Option<KeyDelivery>,
+    
+    /// Split loaders ask this endpoint for a newer signed version before
+    /// running the embedded payload chunk
+    pub update_channel: Option<UpdateChannel>,
+    
+    /// Remove the original comments and shebang, normalize line endings
+    pub scrub_comments: bool,
+    
+    /// License/EULA template emitted as header comments
+    /// (`{{WATERMARK}}`, `{{SCRIPT_ID}}` and `{{TIER}}` are substituted)
+    pub license_header: Option<String>,
+    
+    /// Start the script with `--!optimize 2`, replacing any other level
+    pub force_optimize: bool,
+    
+    /// The code contains honeypot traps; emit the `_license_ok` they call
+    pub honeypot_traps: bool,
+    
+    /// Settings block written readable above the protected code
+    pub config_block: Option<ConfigBlock>,
+    
+    /// Write the settings block's strings as `\ddd` escapes
+    pub encode_config: bool,
+    
+    /// Compression of the payload chunk before sealing; strings compressed
+    /// by the obfuscator need the same setting for their decompressor
+    pub compression: Compression,
+    
+    /// How embedded ciphertext and nonces are written (`Auto` picks by size)
+    pub binary_encoding: BinaryEncoding,
+    
+    /// Write the string table and constant pool in an order derived from
+    /// the license key and watermark
+    pub shuffle_tables: bool,
+    
+    /// Derive the watermark from the customer's root for this project, so
+    /// every file of a multi-file project traces to the same buyer
+    pub watermark_project: Option<String>,
+    
+    /// Replacements for the runtime, license, HWID and tamper templates,
+    /// by file name (see `load_template_dir`)
+    pub template_overrides: HashMap<String, String>,
 }
 
 impl Default for CodeGenConfig {
@@ -62,18 +214,60 @@ impl Default for CodeGenConfig {
         Self {
             license_key: String::new(),
             script_id: String::new(),
+            tier: "standard".to_string(),
+            licensed_tier: None,
             api_endpoint: "https://api.example.com".to_string(),
             hwid: None,
             place_id: None,
             binding_mode: "userid".to_string(),
             authorized_users: Vec::new(),
+            binding: None,
             include_runtime: true,
             include_license: true,
+            revoked_keys: Vec::new(),
             include_hwid: true,
+            output_formatting: OutputFormatting::Readable,
+            misleading_indentation: false,
+            string_decryption: DecryptionMode::Eager,
+            anti_dump: AntiDumpPolicy::default(),
+            string_sensitivity: Vec::new(),
+            type_annotations: TypeAnnotations::Preserve,
+            anti_hook: true,
+            guarded_globals: DEFAULT_GUARDED_GLOBALS.iter().map(|g| g.to_string()).collect(),
+            tamper_policy: TamperPolicy::default(),
+            violation_webhook: None,
+            executor_check: None,
+            run_context: RunContext::Shared,
+            remote_payload: None,
+            key_delivery: None,
+            update_channel: None,
+            scrub_comments: true,
+            license_header: None,
+            force_optimize: false,
+            honeypot_traps: false,
+            config_block: None,
+            encode_config: false,
+            compression: Compression::None,
+            binary_encoding: BinaryEncoding::Auto,
+            shuffle_tables: true,
+            watermark_project: None,
+            template_overrides: HashMap::new(),
         }
     }
 }
 
+/// Generated components handed to the assembler
+struct Parts {
+    /// Shebang and hot comments, re-emitted above everything else
+    directives: Directives,
+    runtime: Option<String>,
+    license: Option<String>,
+    hwid: Option<String>,
+    constant_pool: Option<ConstantPool>,
+    code: String,
+    watermark: Watermark,
+}
+
 /// Main code generator
 pub struct CodeGenerator {
     config: CodeGenConfig,
@@ -87,10 +281,24 @@ pub struct CodeGenerator {
 impl CodeGenerator {
     /// Create a new code generator
     pub fn new(config: CodeGenConfig, crypto: CryptoContext) -> Result<Self> {
-        let template_processor = TemplateProcessor::new()?;
-        let runtime_generator = RuntimeGenerator::new();
-        let license_validator = LicenseValidator::new();
-        let assembler = ScriptAssembler::new();
+        let template_processor =
+            TemplateProcessor::new()?.with_overrides(&config.template_overrides)?;
+        let runtime_generator = RuntimeGenerator::new().with_templates(template_processor.clone());
+        let license_validator = LicenseValidator::new().with_templates(template_processor.clone());
+        let mut assembler = ScriptAssembler::from_config(&config)
+            .with_key_version(crypto.key_version())
+            .with_metadata_signer(MetadataSigner::new(&crypto));
+        if let Some(source) = &config.remote_payload {
+            assembler = assembler
+                .with_payload_loader(source.render_loader(&config.script_id, &config.license_key)?);
+        }
+        if let Some(delivery) = &config.key_delivery {
+            assembler = assembler.with_key_delivery(delivery.render(&crypto, &config.license_key)?);
+        }
+        if let Some(channel) = &config.update_channel {
+            let keys = UpdateKeys::derive(&crypto, &config.script_id);
+            assembler = assembler.with_update_channel(channel.render(&keys, &config.script_id)?);
+        }
         
         Ok(Self {
             config,
@@ -102,31 +310,145 @@ impl CodeGenerator {
         })
     }
     
+    /// Define `_key` as the master key itself, as `gen-tests` harnesses do
+    ///
+    /// For builds run locally (`ObfuscationPipeline::verification_script`);
+    /// never ship one, it carries the key in plain sight.
+    pub fn with_embedded_key(mut self) -> Self {
+        let key = base64::engine::general_purpose::STANDARD.encode(self.crypto.masked_key(&[]));
+        self.assembler = self
+            .assembler
+            .with_key_delivery(format!("local _key = \"{}\"\n", key));
+        self
+    }
+    
     /// Generate protected script from obfuscated code
     pub fn generate(
         &self,
         obfuscated: &ObfuscationResult,
         encrypted_strings: &[(String, EncryptedData)],
     ) -> Result<String> {
+        let parts = self.generate_parts(obfuscated, false)?;
+        
+        // Assemble final script
+        let script = self.assembler.assemble(
+            parts.runtime,
+            parts.license,
+            parts.hwid,
+            encrypted_strings,
+            parts.constant_pool.as_ref(),
+            &parts.code,
+            parts.watermark,
+        )?;
+        Ok(parts.directives.render() + &script)
+    }
+    
+    /// Generate a loader and its encrypted payload module
+    ///
+    /// The payload key is fresh for every call and only recoverable for the
+    /// configured HWID, so ship both files from the same call. With an
+    /// `update_channel` the loader first tries a newer published version.
+    pub fn generate_split(
+        &self,
+        obfuscated: &ObfuscationResult,
+        encrypted_strings: &[(String, EncryptedData)],
+    ) -> Result<SplitOutput> {
+        let parts = self.generate_parts(obfuscated, true)?;
+        
+        let split = self.assembler.assemble_split(
+            parts.runtime,
+            parts.license,
+            parts.hwid,
+            encrypted_strings,
+            parts.constant_pool.as_ref(),
+            &parts.code,
+            parts.watermark,
+        )?;
+        
+        let key = PayloadKey::generate(self.config.hwid);
+        let chunk = split.payload.as_bytes();
+        let compressed = self.config.compression.shrink(chunk)?;
+        let (payload, payload_saved) = match &compressed {
+            Some(compressed) => (
+                key.seal(compressed, self.config.compression)?,
+                encoded_len(chunk.len()) - encoded_len(compressed.len()),
+            ),
+            None => (key.seal(chunk, Compression::None)?, 0),
+        };
+        // Directives only matter to the script Roblox runs
+        Ok(SplitOutput {
+            loader: parts.directives.render() + &split.loader,
+            payload,
+            payload_saved,
+        })
+    }
+    
+    /// Generate every component except the encrypted strings
+    ///
+    /// `split` loaders always need `_tamper` for a payload that fails to load.
+    fn generate_parts(&self, obfuscated: &ObfuscationResult, split: bool) -> Result<Parts> {
         // Generate watermark
-        let watermark = self.crypto.generate_watermark(
-            &self.config.license_key,
-            &self.config.script_id,
-        );
-        
-        // Generate components
-        let runtime = if self.config.include_runtime {
-            Some(self.runtime_generator.generate()?)
-        } else {
-            None
+        let watermark = match &self.config.watermark_project {
+            Some(project) => self.crypto.generate_project_watermark(
+                &self.config.license_key,
+                project,
+                &self.config.script_id,
+            ),
+            None => self
+                .crypto
+                .generate_watermark(&self.config.license_key, &self.config.script_id),
         };
         
+        // Generate components (the guard runs before anything is decrypted)
+        let mut runtime = String::new();
+        if split
+            || self.config.anti_hook
+            || self.config.include_license
+            || self.config.include_hwid
+            || self.config.violation_webhook.is_some()
+            || self.config.remote_payload.is_some()
+            || self.config.key_delivery.is_some()
+            || self.config.honeypot_traps
+            || self.config.executor_check.is_some()
+            || self.config.run_context != RunContext::Shared
+        {
+            runtime.push_str(&self.generate_tamper_response()?);
+            runtime.push('\n');
+        }
+        if let Some(check) = &self.config.executor_check {
+            runtime.push_str(&check.setup(&self.config.tamper_policy));
+        }
+        if self.config.honeypot_traps {
+            runtime.push_str(&trap_handler());
+            runtime.push('\n');
+        }
+        if self.config.anti_hook {
+            runtime.push_str(
+                &self
+                    .runtime_generator
+                    .generate_anti_hook(&self.config.guarded_globals),
+            );
+            runtime.push('\n');
+        }
+        if self.config.include_runtime {
+            runtime.push_str(&self.runtime_generator.generate()?);
+        }
+        if let Some(decompressor) = self
+            .runtime_generator
+            .generate_decompressor(self.config.compression)?
+        {
+            runtime.push('\n');
+            runtime.push_str(&decompressor);
+        }
+        let runtime = Some(runtime).filter(|r| !r.is_empty());
+        
         let license = if self.config.include_license {
             let license_config = LicenseConfig {
                 license_key: self.config.license_key.clone(),
                 script_id: self.config.script_id.clone(),
                 api_endpoint: self.config.api_endpoint.clone(),
                 watermark: watermark.to_string(),
+                revoked: self.config.revoked_keys.clone(),
             };
             Some(self.license_validator.generate(license_config)?)
         } else {
@@ -139,45 +461,86 @@ impl CodeGenerator {
             None
         };
         
-        // Assemble final script
-        self.assembler.assemble(
+        // Luau only honours directives at the top, above our banner
+        let (mut directives, code) = take_directives(&obfuscated.code);
+        if self.config.force_optimize {
+            directives.force_optimize();
+        }
+        let code = if self.config.scrub_comments {
+            scrub_comments(&code)?
+        } else {
+            code
+        };
+        
+        // Environment-derived constants read the anchor locals
+        let code = match &obfuscated.environment_anchor {
+            Some(anchor) => format!(
+                "{}\n{}",
+                self.assembler.render_environment_anchor(anchor),
+                code
+            ),
+            None => code,
+        };
+        
+        // The webhook URL is only ever stored encrypted in the pool
+        let mut constant_pool = obfuscated.constant_pool.clone();
+        if let Some(webhook) = &self.config.violation_webhook {
+            let strings = StringObfuscator::new(&self.crypto);
+            constant_pool
+                .get_or_insert_with(ConstantPool::default)
+                .insert_string(&strings, &webhook.url)?;
+        }
+        
+        Ok(Parts {
+            directives,
             runtime,
             license,
             hwid,
-            encrypted_strings,
-            &obfuscated.code,
+            constant_pool,
+            code,
             watermark,
-        )
+        })
     }
     
-    /// Generate HWID binding code
-    fn generate_hwid_binding(&self) -> Result<String> {
-        let template = self.template_processor.load_template("hwid_binding.lua")?;
+    /// Encrypted strings as the payload file served in remote payload mode
+    ///
+    /// `encrypted_strings` must be the same slice passed to `generate`.
+    pub fn export_payload(&self, encrypted_strings: &[(String, EncryptedData)]) -> Result<String> {
+        let flags = self.config.anti_dump.flags(&self.config.string_sensitivity);
+        PayloadFile::new(&self.config.script_id, encrypted_strings)
+            .with_dump_flags(&flags)
+            .to_json()
+    }
+    
+    /// Generate `_tamper(reason)` for the configured policy
+    fn generate_tamper_response(&self) -> Result<String> {
+        let template = self.template_processor.load_template("tamper_response.lua")?;
+        let policy = &self.config.tamper_policy;
         
         let mut vars = std::collections::HashMap::new();
-        vars.insert("BINDING_MODE".to_string(), self.config.binding_mode.clone());
+        vars.insert("POLICY".to_string(), policy.name().to_string());
+        vars.insert("SETUP".to_string(), policy.setup());
+        vars.insert("RESPONSE".to_string(), policy.response());
         
-        if let Some(hwid) = self.config.hwid {
-            vars.insert("AUTHORIZED_USERID".to_string(), hwid.to_string());
-        } else {
-            vars.insert("AUTHORIZED_USERID".to_string(), "nil".to_string());
-        }
+        self.template_processor.process(&template, vars)
+    }
+    
+    /// Generate HWID binding code
+    fn generate_hwid_binding(&self) -> Result<String> {
+        let template = self.template_processor.load_template("hwid_binding.lua")?;
         
-        if let Some(place_id) = self.config.place_id {
-            vars.insert("AUTHORIZED_PLACEID".to_string(), place_id.to_string());
-        } else {
-            vars.insert("AUTHORIZED_PLACEID".to_string(), "nil".to_string());
-        }
+        let policy = match &self.config.binding {
+            Some(policy) => policy.clone(),
+            None => BindingPolicy::from_mode(
+                &self.config.binding_mode,
+                self.config.hwid,
+                self.config.place_id,
+                &self.config.authorized_users,
+            )?,
+        };
         
-        // Generate whitelist
-        let whitelist = self
-            .config
-            .authorized_users
-            .iter()
-            .map(|id| id.to_string())
-            .collect::<Vec<_>>()
-            .join(", ");
-        vars.insert("AUTHORIZED_USERS_LIST".to_string(), whitelist);
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("BINDING_POLICY".to_string(), policy.to_lua());
         
         self.template_processor.process(&template, vars)
     }
@@ -194,5 +557,45 @@ mod tests {
         assert!(config.include_runtime);
         assert!(config.include_license);
         assert!(config.include_hwid);
+        assert!(config.anti_hook);
+        assert_eq!(config.output_formatting, OutputFormatting::Readable);
+    }
+
+    #[test]
+    fn test_template_overrides_replace_bundled() {
+        let license = "local KEY, ID, MARK = \"{{LICENSE_KEY}}\", \"{{SCRIPT_ID}}\", \"{{WATERMARK}}\"\n\
+                       warn(\"Contact Acme support\")\n\
+                       return {validate = function() return true end}\n";
+        let config = CodeGenConfig {
+            license_key: "KEY-1".to_string(),
+            include_hwid: false,
+            template_overrides: HashMap::from([(
+                "license_validation.lua".to_string(),
+                license.to_string(),
+            )]),
+            ..Default::default()
+        };
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let obfuscated = ObfuscationResult {
+            code: "print(1)".to_string(),
+            constant_pool: None,
+            environment_anchor: None,
+        };
+
+        let script = CodeGenerator::new(config.clone(), crypto.clone())
+            .unwrap()
+            .generate(&obfuscated, &[])
+            .unwrap();
+        assert!(script.contains("Contact Acme support"));
+        assert!(script.contains("local KEY, ID, MARK = \"KEY-1\""));
+
+        let broken = CodeGenConfig {
+            template_overrides: HashMap::from([(
+                "license_validation.lua".to_string(),
+                "return {}".to_string(),
+            )]),
+            ..config
+        };
+        assert!(CodeGenerator::new(broken, crypto).is_err());
     }
 }
@@ -0,0 +1,179 @@
+//! Remote string payloads (keyless client mode)
+//!
+//! Instead of embedding `_encrypted_data`, the protected script fetches it
+//! after license and binding checks pass. The payload file is exported next
+//! to the script and served by the developer's backend, which can refuse
+//! revoked keys, so a leaked copy of the script alone has no strings.
+
+use super::templates::{bundled_template, TemplateProcessor, TemplateVars};
+use crate::crypto::{Compression, EncryptedData};
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Payload format version checked by the loader
+pub const PAYLOAD_VERSION: u32 = 1;
+
+/// Where the loader fetches the payload from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PayloadSource {
+    /// POST the request to `url`; the response body is the payload JSON
+    Url { url: String },
+    /// Publish the request on `topic` and collect the reply from
+    /// `<topic>/<JobId>` as `{i, n, data}` chunks (MessagingService messages
+    /// are limited to 1 KB)
+    Messaging {
+        topic: String,
+        #[serde(default = "default_timeout")]
+        timeout_seconds: u64,
+    },
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl PayloadSource {
+    /// Render `_load_payload()`, which fills `_encrypted_data` and returns success
+    pub fn render_loader(&self, script_id: &str, license_key: &str) -> Result<String> {
+        let mut vars = TemplateVars::new();
+        vars.insert("SCRIPT_ID".to_string(), script_id.into());
+        vars.insert("LICENSE_KEY".to_string(), license_key.into());
+        vars.insert("VERSION".to_string(), PAYLOAD_VERSION.to_string().into());
+        match self {
+            PayloadSource::Url { url } => {
+                vars.insert("URL".to_string(), format!("{:?}", url).into());
+            }
+            PayloadSource::Messaging {
+                topic,
+                timeout_seconds,
+            } => {
+                vars.insert("MESSAGING".to_string(), true.into());
+                vars.insert("TOPIC".to_string(), format!("{:?}", topic).into());
+                vars.insert("TIMEOUT".to_string(), timeout_seconds.to_string().into());
+            }
+        }
+
+        TemplateProcessor::new()?.render(bundled_template("remote_payload.lua")?, &vars)
+    }
+}
+
+/// One encrypted string, base64-encoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadEntry {
+    pub ct: String,
+    pub nonce: String,
+    /// Compression applied before encryption, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z: Option<String>,
+    /// Anti-dump mode the decrypted string is kept under, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub m: Option<u8>,
+}
+
+/// The exported payload, indexed like `_decrypt(i)` (1-based)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadFile {
+    pub version: u32,
+    pub script_id: String,
+    pub strings: Vec<PayloadEntry>,
+}
+
+impl PayloadFile {
+    pub fn new(script_id: &str, encrypted_strings: &[(String, EncryptedData)]) -> Self {
+        let engine = base64::engine::general_purpose::STANDARD;
+        Self {
+            version: PAYLOAD_VERSION,
+            script_id: script_id.to_string(),
+            strings: encrypted_strings
+                .iter()
+                .map(|(_, encrypted)| PayloadEntry {
+                    ct: engine.encode(&encrypted.ciphertext),
+                    nonce: engine.encode(&encrypted.nonce),
+                    z: Some(encrypted.compression)
+                        .filter(|c| !c.is_none())
+                        .map(|c| c.name().to_string()),
+                    m: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Flag entries with their anti-dump mode (`AntiDumpPolicy::flags`)
+    pub fn with_dump_flags(mut self, flags: &[Option<u8>]) -> Self {
+        for (entry, flag) in self.strings.iter_mut().zip(flags) {
+            entry.m = *flag;
+        }
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize payload")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_matches_embedded_indices() {
+        let encrypted = vec![
+            (
+                "first".to_string(),
+                EncryptedData {
+                    ciphertext: vec![1, 2, 3],
+                    nonce: vec![0; 12],
+                    tag_len: 16,
+                    key_version: 0,
+                    compression: Compression::None,
+                },
+            ),
+            (
+                "second".to_string(),
+                EncryptedData {
+                    ciphertext: vec![4, 5],
+                    nonce: vec![1; 12],
+                    tag_len: 16,
+                    key_version: 0,
+                    compression: Compression::None,
+                },
+            ),
+        ];
+
+        let payload = PayloadFile::new("script-1", &encrypted);
+        assert_eq!(payload.strings.len(), 2);
+        assert_eq!(payload.strings[0].ct, "AQID");
+
+        let json = payload.to_json().unwrap();
+        let parsed: PayloadFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, payload);
+        // Plaintext never leaves the generator
+        assert!(!json.contains("first"));
+    }
+
+    #[test]
+    fn test_loader_renders_source() {
+        let url = PayloadSource::Url {
+            url: "https://payloads.example.com/fetch".to_string(),
+        }
+        .render_loader("script-1", "KEY-1")
+        .unwrap();
+        assert!(url.contains("local function _load_payload()"));
+        assert!(url.contains("PostAsync(\"https://payloads.example.com/fetch\", request"));
+        assert!(url.contains("payload.script_id ~= \"script-1\""));
+        assert!(!url.contains("{{"));
+        assert!(!url.contains("MessagingService"));
+
+        let messaging = PayloadSource::Messaging {
+            topic: "payloads".to_string(),
+            timeout_seconds: 5,
+        }
+        .render_loader("script-1", "KEY-1")
+        .unwrap();
+        assert!(messaging.contains("SubscribeAsync(\"payloads\" .. \"/\" .. game.JobId"));
+        assert!(messaging.contains("os.clock() + 5"));
+        assert!(!messaging.contains("PostAsync"));
+    }
+}
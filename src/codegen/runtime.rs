@@ -1,27 +1,113 @@
 //! ChaCha20 runtime code generation
+//!
+//! Also emits the environment guard: exploit executors replace functions
+//! like `loadstring` or `string.char` to log decrypted strings, so the
+//! runtime snapshots them at load and re-checks before every decryption,
+//! the decompressor and decoder for compressed or base85 payloads, and the
+//! vault holding anti-dump protected strings.
 
-use crate::utils::errors::ObfuscatorError;
+use super::templates::{bundled_template, TemplateProcessor, TemplateVars};
+use super::encoding::BinaryEncoding;
+use crate::crypto::Compression;
 use anyhow::Result;
 
+/// Globals whose references are snapshotted and verified by the guard
+pub const DEFAULT_GUARDED_GLOBALS: &[&str] = &[
+    "loadstring",
+    "getfenv",
+    "setfenv",
+    "require",
+    "pcall",
+    "xpcall",
+    "error",
+    "tostring",
+    "rawequal",
+    "rawget",
+    "setmetatable",
+    "getmetatable",
+    "string.char",
+    "string.byte",
+    "string.sub",
+    "table.concat",
+    "bit32.bxor",
+    "debug.info",
+];
+
+/// Globals only defined inside exploit executors
+pub const EXECUTOR_GLOBALS: &[&str] = &[
+    "hookfunction",
+    "hookmetamethod",
+    "newcclosure",
+    "getrawmetatable",
+    "getgenv",
+    "checkcaller",
+];
+
+/// Guard function called before decryption
+pub const GUARD_FUNCTION: &str = "_guard";
+
+/// Function the guard calls with a reason when a check fails
+pub const TAMPER_HANDLER: &str = "_tamper";
+
+/// Decompresses a decrypted string (defined by the decompressor templates)
+pub const DECOMPRESS_FUNCTION: &str = "_decompress";
+
 /// Generates Luau ChaCha20 runtime code
-pub struct RuntimeGenerator;
+pub struct RuntimeGenerator {
+    templates: TemplateProcessor,
+}
 
 impl RuntimeGenerator {
     pub fn new() -> Self {
-        Self
+        Self {
+            templates: TemplateProcessor::default(),
+        }
+    }
+    
+    /// Load the runtime through `templates` (which may override it)
+    pub fn with_templates(mut self, templates: TemplateProcessor) -> Self {
+        self.templates = templates;
+        self
     }
     
     /// Generate ChaCha20 runtime from template
     pub fn generate(&self) -> Result<String> {
-        // Load template
-        let template_path = std::env::current_dir()?
-            .join("templates")
-            .join("chacha20_runtime.lua");
-        
-        std::fs::read_to_string(&template_path)
-            .map_err(|e| ObfuscatorError::CodeGenError(
-                format!("Failed to load ChaCha20 runtime template: {}", e)
-            ).into())
+        self.templates.load_template("chacha20_runtime.lua")
+    }
+    
+    /// Generate `_decompress(data)` for `compression`, if it needs one
+    pub fn generate_decompressor(&self, compression: Compression) -> Result<Option<String>> {
+        let template = match compression {
+            Compression::None => return Ok(None),
+            Compression::Lz4 => "lz4_decompress.lua",
+            Compression::Zlib => "zlib_inflate.lua",
+        };
+        self.templates
+            .render(bundled_template(template)?, &TemplateVars::new())
+            .map(Some)
+    }
+    
+    /// Generate the decoder for embedded data in `encoding`, if it needs one
+    ///
+    /// Base64 is decoded by the ChaCha20 runtime itself, escaped strings by
+    /// the Luau compiler and byte arrays need no decoding.
+    pub fn generate_decoder(&self, encoding: BinaryEncoding) -> Result<Option<String>> {
+        match encoding {
+            BinaryEncoding::Base85 => {
+                bundled_template("base85_decode.lua").map(|code| Some(code.to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+    
+    /// Generate `_unshuffle(seed, n)`, which rebuilds a shuffled table's order
+    pub fn generate_unshuffle(&self) -> Result<String> {
+        bundled_template("unshuffle.lua").map(str::to_string)
+    }
+    
+    /// Generate `_vault`, which keeps anti-dump protected strings masked
+    pub fn generate_string_vault(&self) -> Result<String> {
+        bundled_template("string_vault.lua").map(str::to_string)
     }
     
     /// Generate optimized runtime (with minification)
@@ -39,6 +125,64 @@ impl RuntimeGenerator {
         
         Ok(lines.join("\n"))
     }
+    
+    /// Generate the environment guard for `globals` (dotted names allowed)
+    ///
+    /// Defines `_guard()` and runs it once at load. A check fails when a
+    /// guarded function was replaced after load (`rawequal` against the
+    /// snapshot), is not a native function (`debug.info(f, "s") ~= "[C]"`),
+    /// when the string metatable was swapped, or when executor-only globals
+    /// exist. Failures call `_tamper(reason)`, which must already be defined.
+    pub fn generate_anti_hook(&self, globals: &[String]) -> String {
+        let mut code = String::new();
+        
+        code.push_str(&format!("local {} = (function()\n", GUARD_FUNCTION));
+        code.push_str("    local info, rawequal, type, ipairs = debug.info, rawequal, type, ipairs\n");
+        code.push_str("    local string_meta = getmetatable(\"\")\n");
+        code.push_str("    local watched = {\n");
+        for name in globals {
+            code.push_str(&format!(
+                "        {{\"{}\", function() return {} end}},\n",
+                name, name
+            ));
+        }
+        code.push_str("    }\n");
+        code.push_str("    local snapshot = {}\n");
+        code.push_str("    for i, entry in ipairs(watched) do\n");
+        code.push_str("        snapshot[i] = entry[2]()\n");
+        code.push_str("    end\n");
+        code.push_str("    local function native(f)\n");
+        code.push_str("        return type(f) ~= \"function\" or info(f, \"s\") == \"[C]\"\n");
+        code.push_str("    end\n");
+        code.push_str("    return function()\n");
+        code.push_str(&format!(
+            "        if {} then\n",
+            EXECUTOR_GLOBALS
+                .iter()
+                .map(|name| format!("{} ~= nil", name))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        ));
+        code.push_str(&format!("            return {}(\"executor\")\n", TAMPER_HANDLER));
+        code.push_str("        end\n");
+        code.push_str("        if not native(info) then\n");
+        code.push_str(&format!("            return {}(\"debug.info\")\n", TAMPER_HANDLER));
+        code.push_str("        end\n");
+        code.push_str("        for i, entry in ipairs(watched) do\n");
+        code.push_str("            local current = entry[2]()\n");
+        code.push_str("            if not rawequal(current, snapshot[i]) or not native(current) then\n");
+        code.push_str(&format!("                return {}(entry[1])\n", TAMPER_HANDLER));
+        code.push_str("            end\n");
+        code.push_str("        end\n");
+        code.push_str("        if not rawequal(getmetatable(\"\"), string_meta) then\n");
+        code.push_str(&format!("            return {}(\"string metatable\")\n", TAMPER_HANDLER));
+        code.push_str("        end\n");
+        code.push_str("    end\n");
+        code.push_str("end)()\n");
+        code.push_str(&format!("{}()\n", GUARD_FUNCTION));
+        
+        code
+    }
 }
 
 impl Default for RuntimeGenerator {
@@ -52,10 +196,56 @@ mod tests {
     use super::*;
 
     #[test]
-    #[ignore] // Requires templates directory
     fn test_runtime_generation() {
         let generator = RuntimeGenerator::new();
         let runtime = generator.generate();
         assert!(runtime.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decompressor_per_compression() {
+        let generator = RuntimeGenerator::new();
+        assert!(generator.generate_decompressor(Compression::None).unwrap().is_none());
+        for compression in [Compression::Lz4, Compression::Zlib] {
+            let code = generator.generate_decompressor(compression).unwrap().unwrap();
+            assert!(code.contains(&format!("local function {}(input)", DECOMPRESS_FUNCTION))
+                || code.contains(&format!("local {} = (function()", DECOMPRESS_FUNCTION)));
+            assert!(full_moon::parse(&code).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_decoder_only_for_base85() {
+        let generator = RuntimeGenerator::new();
+        let decoder = generator.generate_decoder(BinaryEncoding::Base85).unwrap().unwrap();
+        assert!(decoder.contains("local _b85decode = (function()"));
+        assert!(full_moon::parse(&decoder).is_ok());
+        for encoding in [BinaryEncoding::Base64, BinaryEncoding::Escaped, BinaryEncoding::Numeric] {
+            assert!(generator.generate_decoder(encoding).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_string_vault_masks_under_separate_keys() {
+        let vault = RuntimeGenerator::new().generate_string_vault().unwrap();
+        assert!(vault.contains("local _vault = (function()"));
+        assert!(vault.contains("function vault.put(index, value, mode)"));
+        assert!(vault.contains("cells[index] = xor(value, key)"));
+        assert!(full_moon::parse(&vault).is_ok());
+    }
+
+    #[test]
+    fn test_anti_hook_snapshots_guarded_globals() {
+        let generator = RuntimeGenerator::new();
+        let globals: Vec<String> = DEFAULT_GUARDED_GLOBALS.iter().map(|g| g.to_string()).collect();
+        let guard = generator.generate_anti_hook(&globals);
+        
+        assert!(guard.contains("{\"string.char\", function() return string.char end}"));
+        assert!(guard.contains("rawequal(current, snapshot[i])"));
+        assert!(guard.contains("info(f, \"s\") == \"[C]\""));
+        assert!(guard.contains("hookfunction ~= nil"));
+        assert!(guard.ends_with("_guard()\n"));
+        // Every failure path goes through the tamper handler
+        assert_eq!(guard.matches("return _tamper(").count(), 4);
+    }
+}
@@ -0,0 +1,227 @@
+//! Comment scrubbing and license headers
+//!
+//! Original comments often carry author names, TODOs and webhook URLs.
+//! `scrub_comments` removes them (and any shebang) from the obfuscated code
+//! before assembly, whatever the output formatting, and normalizes line
+//! endings. Line numbers are kept so error remapping still lines up.
+//! `take_directives` lifts the shebang and Luau hot comments off the top of
+//! the script so the assembler can put them back above the banner, where
+//! Luau still honours them. `render_license_header` turns a configured license/EULA template into
+//! header comment lines.
+
+use super::templates::TemplateProcessor;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, TokenType};
+use std::collections::HashMap;
+
+/// Remove comments and a leading shebang from `code`, with `\n` line endings
+///
+/// Luau hot comments (`--!native`, `--!optimize 2`) change how the script is
+/// compiled and are kept.
+pub fn scrub_comments(code: &str) -> Result<String> {
+    let mut normalized = normalize_line_endings(code);
+    if normalized.starts_with("#!") {
+        let end = normalized.find('\n').unwrap_or(normalized.len());
+        normalized.replace_range(..end, "");
+    }
+
+    let all_tokens = tokens(&normalized)
+        .map_err(|e| ObfuscatorError::CodeGenError(format!("Failed to tokenize code: {}", e)))?;
+
+    let mut output = String::with_capacity(normalized.len());
+    for token in &all_tokens {
+        match token.token_type() {
+            TokenType::SingleLineComment { comment } if is_hot_comment(comment.as_str()) => {
+                output.push_str(&token.to_string());
+            }
+            TokenType::SingleLineComment { .. } => {}
+            // Keep the lines a block comment spanned, or at least a space
+            // so `a--[[x]]b` doesn't become `ab`
+            TokenType::MultiLineComment { comment, .. } => {
+                match comment.as_str().matches('\n').count() {
+                    0 => output.push(' '),
+                    lines => output.push_str(&"\n".repeat(lines)),
+                }
+            }
+            TokenType::Eof => break,
+            _ => output.push_str(&token.to_string()),
+        }
+    }
+
+    Ok(output)
+}
+
+/// Shebang and Luau hot comments (`--!strict`, `--!native`, `--!optimize 2`)
+/// heading a script
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directives {
+    pub shebang: Option<String>,
+    /// Hot comment lines, in source order
+    pub hot_comments: Vec<String>,
+}
+
+impl Directives {
+    pub fn is_empty(&self) -> bool {
+        self.shebang.is_none() && self.hot_comments.is_empty()
+    }
+
+    /// Compile with `--!optimize 2`, replacing any other level
+    pub fn force_optimize(&mut self) {
+        self.hot_comments.retain(|comment| !comment.starts_with(OPTIMIZE_DIRECTIVE));
+        self.hot_comments.push(format!("{} 2", OPTIMIZE_DIRECTIVE));
+    }
+
+    /// Lines for the very top of the protected script
+    pub fn render(&self) -> String {
+        let mut lines = String::new();
+        for line in self.shebang.iter().chain(&self.hot_comments) {
+            lines.push_str(line);
+            lines.push('\n');
+        }
+        lines
+    }
+}
+
+const OPTIMIZE_DIRECTIVE: &str = "--!optimize";
+
+/// Take the shebang and hot comments before the first statement of `code`
+///
+/// Their lines are left blank so line numbers don't change. Ordinary
+/// comments and blank lines between them are skipped over; a block comment
+/// ends the search.
+pub fn take_directives(code: &str) -> (Directives, String) {
+    let mut directives = Directives::default();
+    let mut rest = String::with_capacity(code.len());
+    let mut lines = code.split_inclusive('\n').enumerate();
+
+    for (index, line) in lines.by_ref() {
+        let trimmed = line.trim();
+        let directive = if index == 0 && trimmed.starts_with("#!") {
+            directives.shebang = Some(trimmed.to_string());
+            true
+        } else if trimmed.strip_prefix("--").is_some_and(is_hot_comment) {
+            directives.hot_comments.push(trimmed.to_string());
+            true
+        } else if trimmed.is_empty()
+            || (trimmed.starts_with("--") && !trimmed.starts_with("--["))
+        {
+            false
+        } else {
+            rest.push_str(line);
+            break;
+        };
+
+        if directive {
+            rest.push_str(&line[line.trim_end_matches(['\r', '\n']).len()..]);
+        } else {
+            rest.push_str(line);
+        }
+    }
+    for (_, line) in lines {
+        rest.push_str(line);
+    }
+
+    (directives, rest)
+}
+
+/// `\r\n` and lone `\r` to `\n`
+pub fn normalize_line_endings(code: &str) -> String {
+    code.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// `--!native` and friends; `--!obfuscate:` directives are ours and were
+/// already applied
+fn is_hot_comment(comment: &str) -> bool {
+    comment.starts_with('!') && !comment.starts_with("!obfuscate")
+}
+
+/// Header comment lines for a license template
+///
+/// `{{WATERMARK}}`, `{{SCRIPT_ID}}` and `{{TIER}}` are substituted. Lines
+/// that aren't comments already get a `-- ` prefix.
+pub fn render_license_header(
+    template: &str,
+    watermark: &str,
+    script_id: &str,
+    tier: &str,
+) -> Result<String> {
+    let vars = HashMap::from([
+        ("WATERMARK".to_string(), watermark.to_string()),
+        ("SCRIPT_ID".to_string(), script_id.to_string()),
+        ("TIER".to_string(), tier.to_string()),
+    ]);
+    let rendered = TemplateProcessor::new()?.process(&normalize_line_endings(template), vars)?;
+
+    let mut header = String::new();
+    for line in rendered.trim_end().lines() {
+        let line = line.trim_end();
+        if line.starts_with("--") {
+            header.push_str(line);
+        } else if line.is_empty() {
+            header.push_str("--");
+        } else {
+            header.push_str("-- ");
+            header.push_str(line);
+        }
+        header.push('\n');
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_removes_comments_and_keeps_lines() {
+        let code = "#!/usr/bin/env luau\r\n--!native\r\n-- author: someone@example.com\r\n\
+                    local a = 1 -- TODO remove\r\n--[[ webhook:\r\nhttps://discord.com/api/webhooks/1 ]]\r\n\
+                    local b = a--[[x]]+1\r\nprint(\"-- not a comment\", b)\r\n";
+
+        let scrubbed = scrub_comments(code).unwrap();
+
+        assert!(!scrubbed.contains('\r'));
+        assert!(!scrubbed.contains("author"));
+        assert!(!scrubbed.contains("TODO"));
+        assert!(!scrubbed.contains("webhooks"));
+        assert!(!scrubbed.contains("#!"));
+        assert!(scrubbed.contains("--!native"));
+        assert!(scrubbed.contains("local b = a +1"));
+        assert!(scrubbed.contains("\"-- not a comment\""));
+        assert_eq!(scrubbed.lines().count(), code.lines().count());
+        assert!(full_moon::parse(&scrubbed).is_ok());
+    }
+
+    #[test]
+    fn test_take_directives_keeps_lines() {
+        let code = "#!/usr/bin/env luau\n--!strict\n-- helpers\n\n--!optimize 1\n\
+                    --!obfuscate: skip\nlocal a = 1\n--!native\n";
+
+        let (mut directives, rest) = take_directives(code);
+
+        assert_eq!(directives.shebang.as_deref(), Some("#!/usr/bin/env luau"));
+        assert_eq!(directives.hot_comments, ["--!strict", "--!optimize 1"]);
+        assert_eq!(rest, "\n\n-- helpers\n\n\n--!obfuscate: skip\nlocal a = 1\n--!native\n");
+
+        directives.force_optimize();
+        assert_eq!(directives.render(), "#!/usr/bin/env luau\n--!strict\n--!optimize 2\n");
+        assert!(take_directives("local a = 1\n").0.is_empty());
+    }
+
+    #[test]
+    fn test_license_header_substitutes_placeholders() {
+        let header = render_license_header(
+            "Licensed to {{SCRIPT_ID}} ({{TIER}})\n\n-- Copy {{WATERMARK}}\n",
+            "V0FURVJNQVJL",
+            "admin_commands",
+            "premium",
+        )
+        .unwrap();
+
+        assert_eq!(
+            header,
+            "-- Licensed to admin_commands (premium)\n--\n-- Copy V0FURVJNQVJL\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! License-bound shuffling of the embedded tables
+//!
+//! The encrypted string table and the constant pool are written in an
+//! order permuted by a seed derived from the watermark, which hashes the
+//! license key. `_unshuffle(seed, n)` rebuilds the permutation at load, so
+//! two customers' builds of the same script lay their tables out
+//! differently, and the layout fingerprints the build.
+
+use crate::crypto::Watermark;
+use ring::digest::{Context, SHA256};
+
+/// Luau function rebuilding a table's order from its seed
+pub const SHUFFLE_FUNCTION: &str = "_unshuffle";
+
+/// Order of the shuffled `_encrypted_data` table
+pub const STRING_ORDER: &str = "_encrypted_order";
+
+/// Order of the shuffled `_K_pool` table
+pub const POOL_ORDER: &str = "_K_order";
+
+/// Where every entry of one shuffled table is written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableShuffle {
+    seed: u32,
+    /// `positions[i - 1]` holds entry `i`
+    positions: Vec<usize>,
+}
+
+impl TableShuffle {
+    /// Shuffle of the `len`-entry table `table`, bound to `watermark`
+    pub fn derive(watermark: &Watermark, table: &str, len: usize) -> Self {
+        let mut context = Context::new(&SHA256);
+        context.update(&watermark.primary_hash);
+        context.update(&watermark.secondary_hash);
+        context.update(table.as_bytes());
+        let digest = context.finish();
+        let mut seed = [0u8; 4];
+        seed.copy_from_slice(&digest.as_ref()[..4]);
+
+        // xorshift32 never leaves zero
+        Self::from_seed(u32::from_le_bytes(seed).max(1), len)
+    }
+
+    /// The order `_unshuffle(seed, len)` builds
+    pub fn from_seed(seed: u32, len: usize) -> Self {
+        let mut positions: Vec<usize> = (1..=len).collect();
+        let mut state = seed;
+        for i in (2..=len).rev() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let j = (state as usize) % i + 1;
+            positions.swap(i - 1, j - 1);
+        }
+        Self { seed, positions }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Position entry `index` (1-based) is written at
+    pub fn position(&self, index: usize) -> usize {
+        self.positions[index - 1]
+    }
+
+    /// `local <name> = _unshuffle(seed, len)`
+    pub fn render_order(&self, name: &str) -> String {
+        format!(
+            "local {} = {}({}, {})\n",
+            name,
+            SHUFFLE_FUNCTION,
+            self.seed,
+            self.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::WatermarkGenerator;
+
+    #[test]
+    fn test_shuffle_is_a_license_bound_permutation() {
+        let generator = WatermarkGenerator::new();
+        let first = generator.generate("KEY-1", "script");
+        let second = generator.generate("KEY-2", "script");
+
+        let shuffle = TableShuffle::derive(&first, "strings", 50);
+        let mut positions: Vec<usize> = (1..=50).map(|i| shuffle.position(i)).collect();
+        assert_ne!(positions, (1..=50).collect::<Vec<_>>());
+        positions.sort_unstable();
+        assert_eq!(positions, (1..=50).collect::<Vec<_>>());
+
+        assert_eq!(TableShuffle::derive(&first, "strings", 50), shuffle);
+        assert_eq!(TableShuffle::from_seed(shuffle.seed(), 50), shuffle);
+        assert_ne!(TableShuffle::derive(&second, "strings", 50), shuffle);
+        assert_ne!(TableShuffle::derive(&first, "pool", 50), shuffle);
+        assert_eq!(
+            shuffle.render_order(STRING_ORDER),
+            format!("local _encrypted_order = _unshuffle({}, 50)\n", shuffle.seed())
+        );
+    }
+}
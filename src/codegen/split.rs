@@ -0,0 +1,190 @@
+//! Loader/payload split output
+//!
+//! The loader keeps the runtime, license and binding checks; the embedded
+//! strings and obfuscated code move to a payload ModuleScript encrypted with
+//! a per-build key. The key is stored XOR-masked with a keystream derived
+//! from the bound UserId, so the loader only recovers it for that user.
+//! The chunk may be compressed before sealing; the loader then decompresses
+//! it after decryption.
+
+use super::templates::{bundled_template, TemplateProcessor, TemplateVars};
+use super::{CORRUPT_FUNCTION, DECOMPRESS_FUNCTION, GUARD_FUNCTION, TAMPER_HANDLER};
+use crate::crypto::{AesEncryption, Compression};
+use crate::obfuscation::POOL_ACCESSOR;
+use anyhow::Result;
+use base64::Engine;
+use rand::Rng;
+
+/// Name of the payload ModuleScript the loader requires from `script`
+pub const PAYLOAD_MODULE: &str = "Payload";
+
+/// Loader locals the payload chunk receives as `...`
+const PAYLOAD_IMPORTS: &[&str] = &[
+    "ChaCha20",
+    "_key",
+    TAMPER_HANDLER,
+    GUARD_FUNCTION,
+    CORRUPT_FUNCTION,
+    POOL_ACCESSOR,
+    "_decrypt",
+];
+
+/// xorshift32 seed mixed into the UserId (never zero)
+const KEYSTREAM_SEED: u32 = 0x9E37_79B9;
+
+/// Two-file output: the loader script and the encrypted payload module
+#[derive(Debug, Clone)]
+pub struct SplitOutput {
+    pub loader: String,
+    pub payload: String,
+    /// Encoded bytes compression took off the sealed payload
+    pub payload_saved: usize,
+}
+
+/// Loader locals the chunk receives, plus the runtime `helpers` its
+/// embedded data needs (`_decompress`, a decoder)
+fn imports(helpers: &[&str]) -> String {
+    let mut imports = PAYLOAD_IMPORTS.to_vec();
+    imports.extend_from_slice(helpers);
+    imports.join(", ")
+}
+
+/// `local ChaCha20, _key, ... = ...` opening the payload chunk
+pub(crate) fn render_imports(helpers: &[&str]) -> String {
+    format!("local {} = ...\n", imports(helpers))
+}
+
+/// Loader stage that decrypts and runs the payload chunk; a chunk sealed
+/// compressed is decompressed when `_decompress` is among the `helpers`.
+/// With `updates`, a version from `_fetch_update()` runs instead when there
+/// is one
+pub(crate) fn render_stage(helpers: &[&str], updates: bool) -> Result<String> {
+    let mut vars = TemplateVars::new();
+    vars.insert("MODULE".to_string(), PAYLOAD_MODULE.into());
+    vars.insert("SEED".to_string(), format!("0x{:08X}", KEYSTREAM_SEED).into());
+    vars.insert("IMPORTS".to_string(), imports(helpers).into());
+    vars.insert("TAMPER".to_string(), TAMPER_HANDLER.into());
+    vars.insert(
+        "DECOMPRESS".to_string(),
+        helpers.contains(&DECOMPRESS_FUNCTION).into(),
+    );
+    vars.insert("UPDATES".to_string(), updates.into());
+
+    TemplateProcessor::new()?.render(bundled_template("split_loader.lua")?, &vars)
+}
+
+/// Per-build payload key, bound to a UserId (0 when unbound)
+pub(crate) struct PayloadKey {
+    key: [u8; 32],
+    hwid: u64,
+}
+
+impl PayloadKey {
+    pub fn generate(hwid: Option<u64>) -> Self {
+        Self {
+            key: rand::thread_rng().gen(),
+            hwid: hwid.unwrap_or(0),
+        }
+    }
+
+    /// The key as stored in the payload module
+    fn mask(&self) -> Vec<u8> {
+        self.key
+            .iter()
+            .zip(hwid_keystream(self.hwid))
+            .map(|(k, s)| k ^ s)
+            .collect()
+    }
+
+    /// Encrypt `chunk` (already compressed with `compression`) into the
+    /// payload module source
+    pub fn seal(&self, chunk: &[u8], compression: Compression) -> Result<String> {
+        let encrypted = AesEncryption::new(&self.key)?.encrypt(chunk)?;
+        let engine = base64::engine::general_purpose::STANDARD;
+        let compression_flag = match compression {
+            Compression::None => String::new(),
+            compression => format!(", z = \"{}\"", compression),
+        };
+
+        Ok(format!(
+            "-- Protected payload (decrypted by the loader)\nreturn {{mask = \"{}\", ct = \"{}\", nonce = \"{}\"{}}}\n",
+            engine.encode(self.mask()),
+            engine.encode(&encrypted.ciphertext),
+            engine.encode(&encrypted.nonce),
+            compression_flag
+        ))
+    }
+}
+
+/// Keystream the loader regenerates from `Players.LocalPlayer.UserId`
+fn hwid_keystream(hwid: u64) -> impl Iterator<Item = u8> {
+    let mut state = (hwid as u32) ^ ((hwid >> 32) as u32) ^ KEYSTREAM_SEED;
+    if state == 0 {
+        state = KEYSTREAM_SEED;
+    }
+    std::iter::repeat_with(move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_only_unmasks_for_bound_user() {
+        let key = PayloadKey::generate(Some(123456789));
+        let mask = key.mask();
+
+        let unmask = |hwid| -> Vec<u8> {
+            mask.iter()
+                .zip(hwid_keystream(hwid))
+                .map(|(m, s)| m ^ s)
+                .collect()
+        };
+        assert_eq!(unmask(123456789), key.key);
+        assert_ne!(unmask(987654321), key.key);
+    }
+
+    #[test]
+    fn test_sealed_payload_hides_chunk() {
+        let key = PayloadKey::generate(None);
+        let module = key.seal(b"print(\"secret\")", Compression::None).unwrap();
+
+        assert!(module.contains("return {mask = "));
+        assert!(!module.contains("secret"));
+        assert!(!module.contains("z = "));
+
+        let module = key.seal(b"compressed", Compression::Lz4).unwrap();
+        assert!(module.contains(", z = \"lz4\"}"));
+    }
+
+    #[test]
+    fn test_stage_passes_imports() {
+        let stage = render_stage(&[], false).unwrap();
+        assert!(stage.contains("require, script.Payload"));
+        assert!(stage.contains("_chunk(ChaCha20, _key, _tamper, _guard, _corrupt, _K, _decrypt)"));
+        assert!(!stage.contains("{{"));
+        assert!(!stage.contains("_decompress"));
+        assert!(!stage.contains("_fetch_update"));
+        assert_eq!(
+            render_imports(&[]),
+            "local ChaCha20, _key, _tamper, _guard, _corrupt, _K, _decrypt = ...\n"
+        );
+
+        let helpers = [DECOMPRESS_FUNCTION, "_b85decode"];
+        let stage = render_stage(&helpers, false).unwrap();
+        assert!(stage.contains("source = _decompress(source)"));
+        assert!(stage.contains("_K, _decrypt, _decompress, _b85decode)"));
+        assert!(render_imports(&helpers).ends_with("_decrypt, _decompress, _b85decode = ...\n"));
+
+        // An update is a whole script and runs without the imports
+        let stage = render_stage(&[], true).unwrap();
+        assert!(stage.contains("local update = _fetch_update()"));
+        assert!(stage.contains("elseif _updated then\n    _chunk()\n"));
+        assert!(full_moon::parse(&stage).is_ok());
+    }
+}
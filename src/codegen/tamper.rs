@@ -0,0 +1,175 @@
+//! Tamper response policies
+//!
+//! Decides what `_tamper(reason)` does in the generated script when a
+//! license, HWID or environment check fails. The policy's Luau snippets are
+//! substituted into `templates/tamper_response.lua`.
+
+use super::TAMPER_HANDLER;
+use crate::obfuscation::TRAP_FUNCTION;
+use serde::{Deserialize, Serialize};
+
+/// Value filter applied to decrypted constants under `TamperPolicy::TimeBomb`
+pub const CORRUPT_FUNCTION: &str = "_corrupt";
+
+/// Tail of a `_corrupt` body: a slightly wrong copy of `value`
+pub(crate) const CORRUPT_VALUE: &str = "    if type(value) == \"number\" then
+        return value + math.random(-3, 3)
+    elseif type(value) == \"string\" and #value > 1 then
+        return string.reverse(value)
+    end
+    return value
+end
+";
+
+/// Halts the current thread without an error message
+const HALT: &str = "    while true do\n        coroutine.yield()\n    end\n";
+
+/// What the protected script does when a check fails
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum TamperPolicy {
+    /// Stop the script without any output
+    SilentExit,
+    /// Raise an error with a custom message
+    Error { message: String },
+    /// Keep running, but corrupt decrypted values with growing probability
+    /// once `delay_seconds` have passed, so the breakage is hard to trace
+    TimeBomb { delay_seconds: u32 },
+    /// Call a customer-supplied Luau function `function(reason) ... end`, then stop
+    Callback { source: String },
+}
+
+impl Default for TamperPolicy {
+    fn default() -> Self {
+        TamperPolicy::Error {
+            message: "Integrity check failed".to_string(),
+        }
+    }
+}
+
+impl TamperPolicy {
+    /// Policy name shown in the template header
+    pub fn name(&self) -> &'static str {
+        match self {
+            TamperPolicy::SilentExit => "silent_exit",
+            TamperPolicy::Error { .. } => "error",
+            TamperPolicy::TimeBomb { .. } => "time_bomb",
+            TamperPolicy::Callback { .. } => "callback",
+        }
+    }
+
+    /// Whether decrypted values must pass through `_corrupt`
+    pub fn corrupts_values(&self) -> bool {
+        matches!(self, TamperPolicy::TimeBomb { .. })
+    }
+
+    /// Locals defined before `_tamper` (`{{SETUP}}`)
+    pub fn setup(&self) -> String {
+        match self {
+            TamperPolicy::TimeBomb { delay_seconds } => {
+                let mut code = format!("local {} = function(value)\n", CORRUPT_FUNCTION);
+                code.push_str("    local at = _tamper_state.at\n");
+                code.push_str("    if at == nil then return value end\n");
+                code.push_str(&format!(
+                    "    local elapsed = os.clock() - at - {}\n",
+                    delay_seconds
+                ));
+                code.push_str("    if elapsed <= 0 then return value end\n");
+                // Chance of corruption ramps up to certainty over another delay period
+                code.push_str(&format!(
+                    "    if math.random() > elapsed / {} then return value end\n",
+                    (*delay_seconds).max(1)
+                ));
+                code.push_str(CORRUPT_VALUE);
+                code
+            }
+            TamperPolicy::Callback { source } => {
+                format!("local _on_tamper = {}\n", source.trim())
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Body of `_tamper(reason)` (`{{RESPONSE}}`)
+    pub fn response(&self) -> String {
+        match self {
+            TamperPolicy::SilentExit => HALT.to_string(),
+            TamperPolicy::Error { message } => {
+                format!("    error({}, 0)\n", lua_string(message))
+            }
+            // Only the trigger time is recorded; `_corrupt` does the rest
+            TamperPolicy::TimeBomb { .. } => String::new(),
+            TamperPolicy::Callback { .. } => {
+                let mut code = String::from("    if first then\n");
+                code.push_str("        pcall(_on_tamper, reason)\n");
+                code.push_str("    end\n");
+                code.push_str(HALT);
+                code
+            }
+        }
+    }
+}
+
+/// `_license_ok(...)`, which honeypot traps call
+///
+/// It answers like a passing check and defers the tamper response to the
+/// next resumption, so whoever called the trap carries on and the response
+/// isn't traced back to it.
+pub fn trap_handler() -> String {
+    let mut code = String::from("local _license_checked = false\n");
+    code.push_str(&format!("local function {}(...)\n", TRAP_FUNCTION));
+    code.push_str("    if not _license_checked then\n");
+    code.push_str("        _license_checked = true\n");
+    code.push_str(&format!(
+        "        task.defer({}, \"trap\")\n",
+        TAMPER_HANDLER
+    ));
+    code.push_str("    end\n");
+    code.push_str("    return true\n");
+    code.push_str("end\n");
+    code
+}
+
+/// Quote `value` as a Luau string literal
+pub(crate) fn lua_string(value: &str) -> String {
+    format!("\"{}\"", value.escape_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_policy_escapes_message() {
+        let policy = TamperPolicy::Error {
+            message: "Nope \"bad\"\nbye".to_string(),
+        };
+        assert_eq!(policy.response(), "    error(\"Nope \\\"bad\\\"\\nbye\", 0)\n");
+        assert!(policy.setup().is_empty());
+    }
+
+    #[test]
+    fn test_time_bomb_defines_corrupt_filter() {
+        let policy = TamperPolicy::TimeBomb { delay_seconds: 300 };
+        assert!(policy.corrupts_values());
+        assert!(policy.setup().starts_with("local _corrupt = function(value)"));
+        assert!(policy.setup().contains("os.clock() - at - 300"));
+        assert!(policy.response().is_empty());
+    }
+
+    #[test]
+    fn test_trap_handler_passes_and_defers_tamper() {
+        let handler = trap_handler();
+        assert!(handler.contains("local function _license_ok(...)"));
+        assert!(handler.contains("task.defer(_tamper, \"trap\")"));
+        assert!(handler.trim_end().ends_with("return true\nend"));
+    }
+
+    #[test]
+    fn test_policy_deserializes_from_tagged_config() {
+        let policy: TamperPolicy =
+            serde_json::from_str(r#"{"policy": "time_bomb", "delay_seconds": 60}"#).unwrap();
+        assert_eq!(policy, TamperPolicy::TimeBomb { delay_seconds: 60 });
+        assert_eq!(policy.name(), "time_bomb");
+    }
+}
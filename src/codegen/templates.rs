@@ -1,57 +1,565 @@
 //! Template processing system
+//!
+//! Templates are compiled into the binary so code generation works from any
+//! working directory and without a filesystem (wasm32).
+//!
+//! Besides `{{NAME}}` substitution, templates have conditional blocks
+//! (`{{#if NAME}} ... {{else}} ... {{/if}}`, `{{#unless NAME}}`), loops over
+//! lists (`{{#each NAME}} ... {{/each}}`, with each item's names and
+//! `@index`, `@first` and `@last` in scope) and partial includes
+//! (`{{> partials/name.lua}}`). A block tag or include alone on its line
+//! takes the line with it; an include is indented like its tag. Anything
+//! else between double braces, like a Luau `{{1, 2}}` table, is left alone.
+//!
+//! The runtime, license check, HWID binding and tamper response can be
+//! replaced with the user's own templates (`load_template_dir`); a
+//! replacement must keep the placeholders and names the rest of the
+//! script relies on.
 
+use super::scrub::normalize_line_endings;
 use crate::utils::errors::ObfuscatorError;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
+use std::path::Path;
+
+/// Luau templates from `templates/`, by file name
+const BUNDLED_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "chacha20_runtime.lua",
+        include_str!("../../templates/chacha20_runtime.lua"),
+    ),
+    (
+        "license_validation.lua",
+        include_str!("../../templates/license_validation.lua"),
+    ),
+    (
+        "hwid_binding.lua",
+        include_str!("../../templates/hwid_binding.lua"),
+    ),
+    (
+        "tamper_response.lua",
+        include_str!("../../templates/tamper_response.lua"),
+    ),
+    (
+        "remote_payload.lua",
+        include_str!("../../templates/remote_payload.lua"),
+    ),
+    (
+        "split_loader.lua",
+        include_str!("../../templates/split_loader.lua"),
+    ),
+    (
+        "key_delivery.lua",
+        include_str!("../../templates/key_delivery.lua"),
+    ),
+    (
+        "lz4_decompress.lua",
+        include_str!("../../templates/lz4_decompress.lua"),
+    ),
+    (
+        "zlib_inflate.lua",
+        include_str!("../../templates/zlib_inflate.lua"),
+    ),
+    (
+        "base85_decode.lua",
+        include_str!("../../templates/base85_decode.lua"),
+    ),
+    (
+        "unshuffle.lua",
+        include_str!("../../templates/unshuffle.lua"),
+    ),
+    (
+        "string_vault.lua",
+        include_str!("../../templates/string_vault.lua"),
+    ),
+    (
+        "hmac_sha256.lua",
+        include_str!("../../templates/hmac_sha256.lua"),
+    ),
+    (
+        "update_channel.lua",
+        include_str!("../../templates/update_channel.lua"),
+    ),
+    (
+        "partials/join_bytes.lua",
+        include_str!("../../templates/partials/join_bytes.lua"),
+    ),
+];
+
+/// Bundled templates that can be overridden, with what a replacement must
+/// contain: its placeholders and the names generated code calls
+const OVERRIDABLE_TEMPLATES: &[(&str, &[&str])] = &[
+    (
+        "chacha20_runtime.lua",
+        &[
+            "ChaCha20.crypt",
+            "ChaCha20.base64_decode",
+            "ChaCha20.bytes_to_string",
+            "ChaCha20.string_to_bytes",
+            "ChaCha20.parse_nonce",
+            "ChaCha20.decrypt_string",
+            "ChaCha20.decrypt_bytes",
+        ],
+    ),
+    (
+        "license_validation.lua",
+        &["{{LICENSE_KEY}}", "{{SCRIPT_ID}}", "{{WATERMARK}}", "validate"],
+    ),
+    ("hwid_binding.lua", &["{{BINDING_POLICY}}", "validate"]),
+    (
+        "tamper_response.lua",
+        &["{{SETUP}}", "{{RESPONSE}}", "function _tamper"],
+    ),
+];
+
+/// Look up a bundled template by file name
+pub(crate) fn bundled_template(name: &str) -> Result<&'static str> {
+    BUNDLED_TEMPLATES
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, content)| *content)
+        .ok_or_else(|| ObfuscatorError::CodeGenError(format!("Unknown template: {}", name)).into())
+}
+
+/// Check `template` as a replacement for the bundled template `name`
+pub fn validate_override(name: &str, template: &str) -> Result<()> {
+    let required = OVERRIDABLE_TEMPLATES
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, required)| *required)
+        .ok_or_else(|| {
+            let names: Vec<&str> = OVERRIDABLE_TEMPLATES.iter().map(|(file, _)| *file).collect();
+            ObfuscatorError::ConfigError(format!(
+                "{} is not a template that can be overridden (expected one of: {})",
+                name,
+                names.join(", ")
+            ))
+        })?;
+
+    let missing: Vec<&str> = required
+        .iter()
+        .copied()
+        .filter(|marker| !template.contains(marker))
+        .collect();
+    if !missing.is_empty() {
+        return Err(ObfuscatorError::ConfigError(format!(
+            "Template {} is missing {}",
+            name,
+            missing.join(", ")
+        ))
+        .into());
+    }
+
+    parse_block(&mut lex(template).into_iter(), None)
+        .with_context(|| format!("Template {} doesn't parse", name))?;
+    Ok(())
+}
+
+/// Read template overrides from `dir`
+///
+/// Each `.lua` file replaces the bundled template with the same file name
+/// and is validated as it is read; other files are ignored.
+pub fn load_template_dir(dir: &Path) -> Result<HashMap<String, String>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read template directory: {:?}", dir))?;
+
+    let mut overrides = HashMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("lua") {
+            continue;
+        }
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Invalid template file name: {:?}", path))?
+            .to_string();
+        let template = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read template: {:?}", path))?;
+        let template = normalize_line_endings(&template);
+        validate_override(&name, &template)
+            .with_context(|| format!("Invalid template override: {:?}", path))?;
+        overrides.insert(name, template);
+    }
+    Ok(overrides)
+}
+
+/// Includes nested deeper than this are assumed to be recursive
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Value bound to a template name
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateValue {
+    Text(String),
+    /// Only meaningful to `{{#if}}`/`{{#unless}}`
+    Flag(bool),
+    /// Items for `{{#each}}`, each with its own names
+    List(Vec<TemplateVars>),
+}
+
+/// Names a template is rendered with
+pub type TemplateVars = HashMap<String, TemplateValue>;
+
+impl TemplateValue {
+    /// Empty text, `false` and empty lists are false
+    fn is_truthy(&self) -> bool {
+        match self {
+            TemplateValue::Text(text) => !text.is_empty(),
+            TemplateValue::Flag(flag) => *flag,
+            TemplateValue::List(items) => !items.is_empty(),
+        }
+    }
+}
+
+impl From<String> for TemplateValue {
+    fn from(text: String) -> Self {
+        TemplateValue::Text(text)
+    }
+}
+
+impl From<&str> for TemplateValue {
+    fn from(text: &str) -> Self {
+        TemplateValue::Text(text.to_string())
+    }
+}
+
+impl From<bool> for TemplateValue {
+    fn from(flag: bool) -> Self {
+        TemplateValue::Flag(flag)
+    }
+}
+
+impl From<Vec<TemplateVars>> for TemplateValue {
+    fn from(items: Vec<TemplateVars>) -> Self {
+        TemplateValue::List(items)
+    }
+}
+
+/// A `{{...}}` tag
+#[derive(Debug, Clone, PartialEq)]
+enum Tag {
+    Var(String),
+    /// `#if`, `#unless` or `#each`
+    Open { block: String, name: String },
+    Else,
+    Close(String),
+    Include { name: String, indent: String },
+}
+
+impl Tag {
+    /// `None` when `inner` isn't tag syntax
+    fn parse(inner: &str) -> Option<Self> {
+        let is_name = |name: &str| {
+            let mut chars = name.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '@')
+                && chars.all(|c| c.is_ascii_alphanumeric() || "_@./-".contains(c))
+        };
+
+        if inner == "else" {
+            return Some(Tag::Else);
+        }
+        if let Some(name) = inner.strip_prefix('>') {
+            let name = name.trim();
+            return is_name(name).then(|| Tag::Include {
+                name: name.to_string(),
+                indent: String::new(),
+            });
+        }
+        if let Some(block) = inner.strip_prefix('/') {
+            return ["if", "unless", "each"]
+                .contains(&block)
+                .then(|| Tag::Close(block.to_string()));
+        }
+        if let Some(open) = inner.strip_prefix('#') {
+            let (block, name) = open.split_once(char::is_whitespace)?;
+            let name = name.trim();
+            return (["if", "unless", "each"].contains(&block) && is_name(name)).then(|| {
+                Tag::Open {
+                    block: block.to_string(),
+                    name: name.to_string(),
+                }
+            });
+        }
+        is_name(inner).then(|| Tag::Var(inner.to_string()))
+    }
+
+    /// Block tags and includes alone on a line take the line with them
+    fn is_standalone_kind(&self) -> bool {
+        !matches!(self, Tag::Var(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    Tag(Tag),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var(String),
+    If {
+        name: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        name: String,
+        body: Vec<Node>,
+    },
+    Include {
+        name: String,
+        indent: String,
+    },
+}
+
+/// Split `template` into text and tags
+fn lex(template: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    // Whether `text` starts at the beginning of a line
+    let mut line_start = true;
+
+    while let Some(open) = rest.find("{{") {
+        let Some(len) = rest[open + 2..].find("}}") else {
+            break;
+        };
+        let Some(mut tag) = Tag::parse(rest[open + 2..open + 2 + len].trim()) else {
+            // Not a tag (a Luau table, say); keep the braces as text
+            text.push_str(&rest[..open + 2]);
+            rest = &rest[open + 2..];
+            continue;
+        };
+        text.push_str(&rest[..open]);
+        rest = &rest[open + 2 + len + 2..];
+
+        let (indent_start, at_line_start) = match text.rfind('\n') {
+            Some(i) => (i + 1, true),
+            None => (0, line_start),
+        };
+        let line_end = rest.find('\n');
+        let standalone = tag.is_standalone_kind()
+            && at_line_start
+            && text[indent_start..].chars().all(|c| c == ' ' || c == '\t')
+            && rest[..line_end.unwrap_or(rest.len())].trim().is_empty();
+        if standalone {
+            if let Tag::Include { indent, .. } = &mut tag {
+                *indent = text[indent_start..].to_string();
+            }
+            text.truncate(indent_start);
+            rest = line_end.map_or("", |i| &rest[i + 1..]);
+        }
+        line_start = standalone;
+
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(Piece::Tag(tag));
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+/// Nodes up to the end of input or the tag closing `block`; returns the
+/// nodes and the `{{else}}` branch, if one was seen
+fn parse_block(
+    pieces: &mut std::vec::IntoIter<Piece>,
+    block: Option<&str>,
+) -> Result<(Vec<Node>, Option<Vec<Node>>)> {
+    let mut nodes = Vec::new();
+    let mut otherwise: Option<Vec<Node>> = None;
+
+    while let Some(piece) = pieces.next() {
+        let node = match piece {
+            Piece::Text(text) => Node::Text(text),
+            Piece::Tag(Tag::Var(name)) => Node::Var(name),
+            Piece::Tag(Tag::Include { name, indent }) => Node::Include { name, indent },
+            Piece::Tag(Tag::Open { block: open, name }) => {
+                let (body, else_body) = parse_block(pieces, Some(&open))?;
+                match open.as_str() {
+                    "each" => {
+                        if else_body.is_some() {
+                            return Err(template_error("{{else}} inside {{#each}}"));
+                        }
+                        Node::Each { name, body }
+                    }
+                    _ => Node::If {
+                        name,
+                        negate: open == "unless",
+                        then: body,
+                        otherwise: else_body.unwrap_or_default(),
+                    },
+                }
+            }
+            Piece::Tag(Tag::Else) => {
+                if !matches!(block, Some("if" | "unless")) || otherwise.is_some() {
+                    return Err(template_error("{{else}} outside {{#if}}"));
+                }
+                otherwise = Some(Vec::new());
+                continue;
+            }
+            Piece::Tag(Tag::Close(close)) => {
+                if block != Some(close.as_str()) {
+                    return Err(template_error(&format!("unexpected {{{{/{}}}}}", close)));
+                }
+                return Ok((nodes, otherwise));
+            }
+        };
+        match &mut otherwise {
+            Some(otherwise) => otherwise.push(node),
+            None => nodes.push(node),
+        }
+    }
+
+    match block {
+        Some(block) => Err(template_error(&format!("{{{{#{}}}}} is never closed", block))),
+        None => Ok((nodes, None)),
+    }
+}
+
+fn template_error(message: &str) -> anyhow::Error {
+    ObfuscatorError::CodeGenError(format!("Invalid template: {}", message)).into()
+}
 
 /// Template processor for Luau code generation
+#[derive(Debug, Clone, Default)]
 pub struct TemplateProcessor {
-    templates_dir: std::path::PathBuf,
+    /// Replacements for bundled templates, by file name
+    overrides: HashMap<String, String>,
 }
 
 impl TemplateProcessor {
     pub fn new() -> Result<Self> {
-        // Templates are in the templates/ directory at project root
-        let templates_dir = std::env::current_dir()?
-            .join("templates");
-        
-        if !templates_dir.exists() {
-            return Err(ObfuscatorError::CodeGenError(
-                format!("Templates directory not found: {:?}", templates_dir)
-            ).into());
+        Ok(Self::default())
+    }
+    
+    /// Use `overrides` (file name → template) in place of the bundled templates
+    pub fn with_overrides(mut self, overrides: &HashMap<String, String>) -> Result<Self> {
+        for (name, template) in overrides {
+            validate_override(name, template)?;
+            self.overrides.insert(name.clone(), template.clone());
         }
-        
-        Ok(Self { templates_dir })
+        Ok(self)
     }
     
-    /// Load a template file
+    /// Load a template by file name, preferring an override
     pub fn load_template(&self, name: &str) -> Result<String> {
-        let path = self.templates_dir.join(name);
-        std::fs::read_to_string(&path)
-            .with_context(|| format!("Failed to load template: {:?}", path))
+        match self.overrides.get(name) {
+            Some(template) => Ok(template.clone()),
+            None => bundled_template(name).map(str::to_string),
+        }
     }
     
     /// Process template with variable substitution
     pub fn process(&self, template: &str, vars: HashMap<String, String>) -> Result<String> {
-        let mut result = template.to_string();
-        
-        for (key, value) in vars {
-            let placeholder = format!("{{{{{}}}}}", key);
-            result = result.replace(&placeholder, &value);
-        }
+        let vars: TemplateVars = vars
+            .into_iter()
+            .map(|(name, value)| (name, TemplateValue::Text(value)))
+            .collect();
+        self.render(template, &vars)
+    }
+    
+    /// Render a template with blocks, loops and includes
+    pub fn render(&self, template: &str, vars: &TemplateVars) -> Result<String> {
+        let mut output = String::with_capacity(template.len());
+        self.render_into(template, &[vars], 0, &mut output)?;
+        Ok(output)
+    }
+    
+    fn render_into(
+        &self,
+        template: &str,
+        scopes: &[&TemplateVars],
+        depth: usize,
+        output: &mut String,
+    ) -> Result<()> {
+        let (nodes, _) = parse_block(&mut lex(template).into_iter(), None)?;
+        self.render_nodes(&nodes, scopes, depth, output)
+    }
+    
+    fn render_nodes(
+        &self,
+        nodes: &[Node],
+        scopes: &[&TemplateVars],
+        depth: usize,
+        output: &mut String,
+    ) -> Result<()> {
+        // Innermost scope first, so loop items shadow outer names
+        let lookup = |name: &str| scopes.iter().rev().find_map(|scope| scope.get(name));
         
-        // Check for unprocessed placeholders
-        if result.contains("{{") && result.contains("}}") {
-            log::warn!("Template contains unprocessed placeholders");
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Var(name) => match lookup(name) {
+                    Some(TemplateValue::Text(text)) => output.push_str(text),
+                    Some(TemplateValue::Flag(flag)) => output.push_str(&flag.to_string()),
+                    Some(TemplateValue::List(_)) => {
+                        return Err(template_error(&format!("{} is a list", name)));
+                    }
+                    None => {
+                        log::warn!("Template contains unprocessed placeholder {{{{{}}}}}", name);
+                        output.push_str(&format!("{{{{{}}}}}", name));
+                    }
+                },
+                Node::If {
+                    name,
+                    negate,
+                    then,
+                    otherwise,
+                } => {
+                    let holds = lookup(name).is_some_and(TemplateValue::is_truthy) != *negate;
+                    let branch = if holds { then } else { otherwise };
+                    self.render_nodes(branch, scopes, depth, output)?;
+                }
+                Node::Each { name, body } => {
+                    let items = match lookup(name) {
+                        Some(TemplateValue::List(items)) => items.as_slice(),
+                        None => &[],
+                        Some(_) => {
+                            return Err(template_error(&format!("{} is not a list", name)));
+                        }
+                    };
+                    for (i, item) in items.iter().enumerate() {
+                        let mut item = item.clone();
+                        item.insert("@index".to_string(), (i + 1).to_string().into());
+                        item.insert("@first".to_string(), (i == 0).into());
+                        item.insert("@last".to_string(), (i + 1 == items.len()).into());
+                        let mut inner = scopes.to_vec();
+                        inner.push(&item);
+                        self.render_nodes(body, &inner, depth, output)?;
+                    }
+                }
+                Node::Include { name, indent } => {
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(template_error(&format!("{} includes itself", name)));
+                    }
+                    let partial = self.load_template(name)?;
+                    let mut rendered = String::new();
+                    self.render_into(&partial, scopes, depth + 1, &mut rendered)?;
+                    if indent.is_empty() {
+                        output.push_str(&rendered);
+                    } else {
+                        for line in rendered.split_inclusive('\n') {
+                            if !line.trim().is_empty() {
+                                output.push_str(indent);
+                            }
+                            output.push_str(line);
+                        }
+                    }
+                }
+            }
         }
         
-        Ok(result)
-    }
-}
-
-impl Default for TemplateProcessor {
-    fn default() -> Self {
-        Self::new().expect("Failed to create TemplateProcessor")
+        Ok(())
     }
 }
 
@@ -71,4 +579,82 @@ mod tests {
         let result = processor.process(template, vars).unwrap();
         assert_eq!(result, "Hello Alice, you are 30 years old");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_conditionals_and_loops() {
+        let processor = TemplateProcessor::new().unwrap();
+        let template = "local ids = {\n\
+                        {{#each USERS}}\n\
+                        \x20   {{ID}}{{#unless @last}},{{/unless}}\n\
+                        {{/each}}\n\
+                        }\n\
+                        {{#if STRICT}}\n\
+                        strict()\n\
+                        {{else}}\n\
+                        lenient({{MODE}})\n\
+                        {{/if}}\n\
+                        return {{1, 2}}\n";
+        let user = |id: &str| TemplateVars::from([("ID".to_string(), id.into())]);
+
+        let mut vars = TemplateVars::from([
+            ("USERS".to_string(), vec![user("1"), user("2")].into()),
+            ("STRICT".to_string(), false.into()),
+            ("MODE".to_string(), "\"warn\"".into()),
+        ]);
+        assert_eq!(
+            processor.render(template, &vars).unwrap(),
+            "local ids = {\n    1,\n    2\n}\nlenient(\"warn\")\nreturn {{1, 2}}\n"
+        );
+
+        vars.insert("USERS".to_string(), TemplateValue::List(Vec::new()));
+        vars.insert("STRICT".to_string(), true.into());
+        assert_eq!(
+            processor.render(template, &vars).unwrap(),
+            "local ids = {\n}\nstrict()\nreturn {{1, 2}}\n"
+        );
+    }
+
+    #[test]
+    fn test_includes_are_indented() {
+        let processor = TemplateProcessor::new().unwrap();
+        let template = "local function f(out, n)\n    {{> partials/join_bytes.lua}}\nend\n";
+
+        let rendered = processor.render(template, &TemplateVars::new()).unwrap();
+
+        assert!(rendered.contains("\n    local parts = {}\n"));
+        assert!(rendered.ends_with("    return table.concat(parts)\nend\n"));
+        assert!(full_moon::parse(&rendered).is_ok());
+        assert!(processor.render("{{> missing.lua}}", &TemplateVars::new()).is_err());
+    }
+
+    #[test]
+    fn test_unbalanced_blocks_are_errors() {
+        let processor = TemplateProcessor::new().unwrap();
+        let vars = TemplateVars::new();
+        assert!(processor.render("{{#if A}}x", &vars).is_err());
+        assert!(processor.render("x{{/each}}", &vars).is_err());
+        assert!(processor.render("{{#if A}}x{{/each}}", &vars).is_err());
+        assert!(processor.render("{{else}}", &vars).is_err());
+    }
+
+    #[test]
+    fn test_templates_are_bundled() {
+        let processor = TemplateProcessor::new().unwrap();
+        assert!(processor.load_template("hwid_binding.lua").is_ok());
+        assert!(processor.load_template("missing.lua").is_err());
+    }
+
+    #[test]
+    fn test_overrides_are_validated() {
+        let tamper = "local function _tamper(reason)\n{{SETUP}}\n{{RESPONSE}}\nend\n";
+        let overrides = HashMap::from([("tamper_response.lua".to_string(), tamper.to_string())]);
+        let processor = TemplateProcessor::new().unwrap().with_overrides(&overrides).unwrap();
+        assert_eq!(processor.load_template("tamper_response.lua").unwrap(), tamper);
+        assert!(processor.load_template("hwid_binding.lua").unwrap().contains("{{BINDING_POLICY}}"));
+
+        assert!(validate_override("tamper_response.lua", "local function _tamper() end").is_err());
+        assert!(validate_override("split_loader.lua", tamper).is_err());
+        let unbalanced = format!("{}{{{{#if STRICT}}}}", tamper);
+        assert!(validate_override("tamper_response.lua", &unbalanced).is_err());
+    }
+}
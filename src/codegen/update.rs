@@ -0,0 +1,195 @@
+//! Update channel for split loaders
+//!
+//! A loader built with an update channel asks the developer's endpoint for a
+//! newer version of the script before it runs the embedded payload chunk.
+//! Updates are complete single-file builds, encrypted and signed with keys
+//! derived from the script's master key (`publish-payload`). The loader runs
+//! one only when the signature checks out and its version is above the
+//! embedded one; anything else (no answer, bad JSON, a bad signature) falls
+//! back to the embedded chunk.
+//!
+//! Every loader holds both keys. The signature keeps a hijacked or spoofed
+//! endpoint from pushing code, not a buyer who pulls the key out of their
+//! own loader.
+
+use super::templates::{bundled_template, TemplateProcessor, TemplateVars};
+use crate::crypto::{constant_time_eq, AesEncryption, CryptoContext};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use base64::Engine;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+
+/// Where a loader looks for updates, and the version it embeds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateChannel {
+    /// Queried with `?script=<id>&version=<embedded version>`; answers with
+    /// the JSON envelope `publish-payload` writes
+    pub url: String,
+    /// Version of the embedded payload; only higher versions replace it
+    #[serde(default = "default_version")]
+    pub version: u32,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+impl UpdateChannel {
+    pub fn new(url: impl Into<String>, version: u32) -> Self {
+        Self {
+            url: url.into(),
+            version,
+        }
+    }
+
+    /// Render `_fetch_update()`, which returns the newer version's function
+    /// or `nil`
+    pub fn render(&self, keys: &UpdateKeys, script_id: &str) -> Result<String> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        let mut vars = TemplateVars::new();
+        vars.insert(
+            "URL".to_string(),
+            format!("{:?}", format!("{}{}", self.url, separator)).into(),
+        );
+        vars.insert("SCRIPT_ID".to_string(), script_id.into());
+        vars.insert("VERSION".to_string(), self.version.to_string().into());
+        vars.insert(
+            "ENCRYPT_KEY".to_string(),
+            engine.encode(keys.encrypt).into(),
+        );
+        vars.insert("SIGN_KEY".to_string(), engine.encode(keys.sign).into());
+
+        TemplateProcessor::new()?.render(bundled_template("update_channel.lua")?, &vars)
+    }
+}
+
+/// Update keys of one script, re-derivable from its password and symbol map
+pub struct UpdateKeys {
+    encrypt: [u8; 32],
+    sign: [u8; 32],
+}
+
+impl UpdateKeys {
+    pub fn derive(crypto: &CryptoContext, script_id: &str) -> Self {
+        let share = |purpose: &str| -> [u8; 32] {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&crypto.key_share(&format!("update/{}/{}", purpose, script_id)));
+            key
+        };
+        Self {
+            encrypt: share("encrypt"),
+            sign: share("sign"),
+        }
+    }
+
+    fn signature(&self, script_id: &str, version: u32, nonce: &str, ct: &str) -> String {
+        let signed = format!("{}\n{}\n{}\n{}", script_id, version, nonce, ct);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.sign);
+        hex::encode(hmac::sign(&key, signed.as_bytes()))
+    }
+}
+
+/// A published version as served to loaders
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadUpdate {
+    pub script_id: String,
+    pub version: u32,
+    /// Encrypted script, base64
+    pub ct: String,
+    pub nonce: String,
+    /// HMAC-SHA256 of `script_id`, `version`, `nonce` and `ct` (one per
+    /// line) under the signing key, hex
+    pub sig: String,
+}
+
+impl PayloadUpdate {
+    /// Encrypt and sign `script`, a single-file protected build
+    pub fn seal(keys: &UpdateKeys, script_id: &str, version: u32, script: &str) -> Result<Self> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let encrypted = AesEncryption::new(&keys.encrypt)?.encrypt(script.as_bytes())?;
+        let ct = engine.encode(&encrypted.ciphertext);
+        let nonce = engine.encode(&encrypted.nonce);
+        Ok(Self {
+            sig: keys.signature(script_id, version, &nonce, &ct),
+            script_id: script_id.to_string(),
+            version,
+            ct,
+            nonce,
+        })
+    }
+
+    /// Check the signature as the loader does
+    pub fn verify(&self, keys: &UpdateKeys) -> Result<()> {
+        let expected = keys.signature(&self.script_id, self.version, &self.nonce, &self.ct);
+        if !constant_time_eq(expected.as_bytes(), self.sig.as_bytes()) {
+            return Err(ObfuscatorError::CryptoError(format!(
+                "Update {} for {} is not signed with this script's key",
+                self.version, self.script_id
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize payload update")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_is_signed_per_script() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let keys = UpdateKeys::derive(&crypto, "admin");
+        let update = PayloadUpdate::seal(&keys, "admin", 2, "print('v2')").unwrap();
+        assert!(update.verify(&keys).is_ok());
+        assert_eq!(update.sig.len(), 64);
+
+        let bumped = PayloadUpdate {
+            version: 3,
+            ..update.clone()
+        };
+        assert!(bumped.verify(&keys).is_err());
+        assert!(update
+            .verify(&UpdateKeys::derive(&crypto, "other"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_render_checks_version_and_signature() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let keys = UpdateKeys::derive(&crypto, "admin");
+        let channel = UpdateChannel::new("https://example.com/updates?channel=beta", 4);
+        let code = channel.render(&keys, "admin").unwrap();
+
+        assert!(code.contains("local _hmac_sha256 = (function()"));
+        assert!(code.contains("\"https://example.com/updates?channel=beta&\" .. \"script=\""));
+        assert!(code.contains("or update.version <= 4\n"));
+        assert!(!code.contains("{{"));
+        assert!(full_moon::parse(&code).is_ok());
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_luau_hmac_matches_ring() {
+        use crate::verify::{Event, Sandbox};
+
+        let sign = [7u8; 32];
+        let message = "admin\n2\nbm9uY2U=\nY3Q=".repeat(5);
+        let source = format!(
+            "{}\nprint(_hmac_sha256(string.rep(\"\\7\", 32), {:?}))\n",
+            bundled_template("hmac_sha256.lua").unwrap(),
+            message
+        );
+        let observation = Sandbox::new().run(&source).unwrap();
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &sign);
+        let expected = hex::encode(hmac::sign(&key, message.as_bytes()));
+        assert_eq!(observation.events, vec![Event::Print(expected)]);
+    }
+}
@@ -0,0 +1,95 @@
+//! Anti-dump protection for decrypted strings
+//!
+//! Exploiters dump a running script's tables to collect every string it has
+//! decrypted. Strings given a `DumpProtection` are kept in `_vault` (see
+//! `RuntimeGenerator::generate_string_vault`) instead of the plain decrypt
+//! cache: XOR-masked under a random key and unmasked on every access.
+//! `Rolling` strings are re-masked under a fresh key after each access, so
+//! two dumps never agree. Each entry of `_encrypted_data` carries its mode as
+//! `m = 1` (masked) or `m = 2` (rolling).
+
+use crate::parser::Sensitivity;
+use serde::{Deserialize, Serialize};
+
+/// Table holding masked strings, defined by the string vault template
+pub const VAULT_TABLE: &str = "_vault";
+
+/// How a decrypted string is kept once decrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DumpProtection {
+    /// Cached as plaintext (fastest)
+    #[default]
+    Off,
+    /// Cached XOR-masked, unmasked on every access
+    Masked,
+    /// Cached XOR-masked and re-masked under a new key after every access
+    Rolling,
+}
+
+impl DumpProtection {
+    /// The entry's `m` flag, or `None` for plaintext caching
+    pub fn flag(self) -> Option<u8> {
+        match self {
+            DumpProtection::Off => None,
+            DumpProtection::Masked => Some(1),
+            DumpProtection::Rolling => Some(2),
+        }
+    }
+}
+
+/// Protection of decrypted strings by sensitivity level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AntiDumpPolicy {
+    pub high: DumpProtection,
+    pub medium: DumpProtection,
+    pub low: DumpProtection,
+}
+
+impl AntiDumpPolicy {
+    /// `protection` for high-sensitivity strings only
+    pub fn high_only(protection: DumpProtection) -> Self {
+        Self {
+            high: protection,
+            ..Default::default()
+        }
+    }
+
+    /// Protection of strings classified `level`
+    pub fn for_level(&self, level: Sensitivity) -> DumpProtection {
+        match level {
+            Sensitivity::High => self.high,
+            Sensitivity::Medium => self.medium,
+            Sensitivity::Low => self.low,
+        }
+    }
+
+    /// `m` flag of each string table entry, from each entry's sensitivity
+    pub fn flags(&self, sensitivity: &[Sensitivity]) -> Vec<Option<u8>> {
+        sensitivity
+            .iter()
+            .map(|&level| self.for_level(level).flag())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_flags_by_sensitivity() {
+        let policy: AntiDumpPolicy =
+            toml::from_str("high = \"rolling\"\nmedium = \"masked\"\n").unwrap();
+        assert_eq!(
+            policy.flags(&[Sensitivity::High, Sensitivity::Medium, Sensitivity::Low]),
+            vec![Some(2), Some(1), None]
+        );
+        assert_eq!(
+            AntiDumpPolicy::high_only(DumpProtection::Masked).for_level(Sensitivity::Medium),
+            DumpProtection::Off
+        );
+        assert!(toml::from_str::<AntiDumpPolicy>("critical = \"masked\"").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Violation reports from generated scripts
+//!
+//! When any check fails, the script POSTs a report to a seller-configured
+//! webhook through `HttpService`. The URL lives encrypted in the constant
+//! pool and is only decrypted when a report is actually sent.
+
+use super::tamper::lua_string;
+use serde::{Deserialize, Serialize};
+
+/// `shared` key holding the last report time, so every protected script on
+/// a server shares one rate limit
+const LAST_REPORT_KEY: &str = "__lo_last_report";
+
+/// Payload shape expected by the receiving endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// Discord webhook message (`{"content": ...}`)
+    #[default]
+    Discord,
+    /// Flat JSON object for custom endpoints
+    Json,
+}
+
+/// Webhook notified on license, HWID and integrity violations
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViolationWebhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Minimum seconds between reports from one server
+    #[serde(default = "default_min_interval")]
+    pub min_interval_seconds: u32,
+}
+
+fn default_min_interval() -> u32 {
+    60
+}
+
+impl ViolationWebhook {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            format: WebhookFormat::default(),
+            min_interval_seconds: default_min_interval(),
+        }
+    }
+
+    /// Install `_tamper_state.report(reason)`
+    ///
+    /// `url_expr` is the Luau expression yielding the decrypted URL (a pool
+    /// accessor call); `watermark` identifies the buyer in the report.
+    pub fn render_reporter(&self, url_expr: &str, watermark: &str) -> String {
+        let mut code = String::from("_tamper_state.report = function(reason)\n");
+        code.push_str(&format!(
+            "    local last = shared.{}\n",
+            LAST_REPORT_KEY
+        ));
+        code.push_str(&format!(
+            "    if last and os.time() - last < {} then return end\n",
+            self.min_interval_seconds
+        ));
+        code.push_str(&format!("    shared.{} = os.time()\n", LAST_REPORT_KEY));
+        code.push_str("    local players = game:GetService(\"Players\")\n");
+        code.push_str("    local user_id = players.LocalPlayer and players.LocalPlayer.UserId or game.CreatorId\n");
+        code.push_str("    local http = game:GetService(\"HttpService\")\n");
+        code.push_str(&format!("    local watermark = {}\n", lua_string(watermark)));
+        match self.format {
+            WebhookFormat::Discord => {
+                code.push_str("    local body = {content = string.format(\n");
+                code.push_str("        \"License violation: %s\\nPlace: %d\\nUser: %d\\nWatermark: %s\",\n");
+                code.push_str("        reason, game.PlaceId, user_id, watermark\n");
+                code.push_str("    )}\n");
+            }
+            WebhookFormat::Json => {
+                code.push_str("    local body = {\n");
+                code.push_str("        event = \"license_violation\",\n");
+                code.push_str("        reason = reason,\n");
+                code.push_str("        placeId = game.PlaceId,\n");
+                code.push_str("        jobId = game.JobId,\n");
+                code.push_str("        userId = user_id,\n");
+                code.push_str("        watermark = watermark,\n");
+                code.push_str("    }\n");
+            }
+        }
+        code.push_str(&format!(
+            "    http:PostAsync({}, http:JSONEncode(body))\n",
+            url_expr
+        ));
+        code.push_str("end\n");
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reporter_reads_url_from_pool() {
+        let webhook = ViolationWebhook::new("https://discord.com/api/webhooks/1/abc");
+        let code = webhook.render_reporter("_K(7)", "d2F0ZXJtYXJr");
+
+        assert!(code.contains("http:PostAsync(_K(7), http:JSONEncode(body))"));
+        assert!(code.contains("os.time() - last < 60"));
+        assert!(code.contains("local watermark = \"d2F0ZXJtYXJr\""));
+        // The URL itself never appears in plaintext
+        assert!(!code.contains("discord.com"));
+    }
+
+    #[test]
+    fn test_json_format_includes_offender() {
+        let webhook: ViolationWebhook = serde_json::from_str(
+            r#"{"url": "https://example.com/report", "format": "json", "min_interval_seconds": 5}"#,
+        )
+        .unwrap();
+        let code = webhook.render_reporter("_K(1)", "wm");
+
+        assert!(code.contains("userId = user_id,"));
+        assert!(code.contains("placeId = game.PlaceId,"));
+        assert!(code.contains("os.time() - last < 5"));
+    }
+}
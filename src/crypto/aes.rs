@@ -1,5 +1,6 @@
 //! AES-256-GCM encryption
 
+use super::Compression;
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
 use ring::{
@@ -8,6 +9,7 @@ use ring::{
     rand::{SecureRandom, SystemRandom},
 };
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
 
 /// Encrypted data with nonce
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +20,13 @@ pub struct EncryptedData {
     pub nonce: Vec<u8>,
     /// Authentication tag (16 bytes, included in ciphertext by ring)
     pub tag_len: usize,
+    /// Master key generation this was encrypted under (0 until the first
+    /// `rekey`)
+    #[serde(default)]
+    pub key_version: u32,
+    /// Compression applied to the plaintext before encryption
+    #[serde(default)]
+    pub compression: Compression,
 }
 
 /// Custom nonce sequence for ring
@@ -48,8 +57,11 @@ impl NonceSequence for CounterNonceSequence {
 }
 
 /// AES-256-GCM encryption
+///
+/// The key is wiped from memory when this is dropped.
+#[derive(Clone)]
 pub struct AesEncryption {
-    key: Vec<u8>,
+    key: Zeroizing<Vec<u8>>,
     rng: SystemRandom,
 }
 
@@ -64,11 +76,16 @@ impl AesEncryption {
         }
 
         Ok(Self {
-            key: key.to_vec(),
+            key: Zeroizing::new(key.to_vec()),
             rng: SystemRandom::new(),
         })
     }
 
+    /// Raw key bytes
+    pub(super) fn key(&self) -> &[u8] {
+        &self.key
+    }
+
     /// Encrypt plaintext
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<EncryptedData> {
         // Generate random nonce
@@ -76,13 +93,24 @@ impl AesEncryption {
         self.rng
             .fill(&mut nonce_bytes)
             .map_err(|_| ObfuscatorError::CryptoError("Failed to generate nonce".to_string()))?;
+        self.encrypt_with_nonce(plaintext, &nonce_bytes)
+    }
 
+    /// Encrypt plaintext under a given nonce
+    ///
+    /// Only for test vectors: reusing a nonce with the same key breaks
+    /// AES-GCM, so everything else goes through `encrypt`.
+    pub(super) fn encrypt_with_nonce(
+        &self,
+        plaintext: &[u8],
+        nonce: &[u8],
+    ) -> Result<EncryptedData> {
         // Create unbound key
         let unbound_key = UnboundKey::new(&AES_256_GCM, &self.key)
             .map_err(|_| ObfuscatorError::CryptoError("Invalid key".to_string()))?;
 
         // Create nonce sequence
-        let nonce_sequence = CounterNonceSequence::new(&nonce_bytes)?;
+        let nonce_sequence = CounterNonceSequence::new(nonce)?;
 
         // Create sealing key
         let mut sealing_key = SealingKey::new(unbound_key, nonce_sequence);
@@ -97,13 +125,18 @@ impl AesEncryption {
 
         Ok(EncryptedData {
             ciphertext: in_out,
-            nonce: nonce_bytes.to_vec(),
+            nonce: nonce.to_vec(),
             tag_len: AES_256_GCM.tag_len(),
+            key_version: 0,
+            compression: Compression::None,
         })
     }
 
     /// Decrypt ciphertext
-    pub fn decrypt(&self, encrypted: &EncryptedData) -> Result<Vec<u8>> {
+    ///
+    /// The returned buffer is the only copy of the plaintext, and is wiped
+    /// when dropped.
+    pub fn decrypt(&self, encrypted: &EncryptedData) -> Result<Zeroizing<Vec<u8>>> {
         // Create unbound key
         let unbound_key = UnboundKey::new(&AES_256_GCM, &self.key)
             .map_err(|_| ObfuscatorError::CryptoError("Invalid key".to_string()))?;
@@ -115,16 +148,19 @@ impl AesEncryption {
         let mut opening_key = OpeningKey::new(unbound_key, nonce_sequence);
 
         // Prepare data for decryption
-        let mut in_out = encrypted.ciphertext.clone();
+        let mut in_out = Zeroizing::new(encrypted.ciphertext.clone());
 
-        // Open (decrypt + verify)
-        let plaintext = opening_key
+        // Open (decrypt + verify), then drop the tag so the buffer itself
+        // becomes the plaintext
+        let plaintext_len = opening_key
             .open_in_place(Aad::empty(), &mut in_out)
             .map_err(|_| {
                 ObfuscatorError::CryptoError("Decryption failed (wrong key or corrupted data)".to_string())
-            })?;
+            })?
+            .len();
+        in_out.truncate(plaintext_len);
 
-        Ok(plaintext.to_vec())
+        Ok(in_out)
     }
 
     /// Encrypt multiple items (batch operation)
@@ -235,7 +271,7 @@ mod tests {
         let encrypted = aes.encrypt(&plaintext).unwrap();
         let decrypted = aes.decrypt(&encrypted).unwrap();
 
-        assert_eq!(plaintext, decrypted);
+        assert_eq!(plaintext, *decrypted);
     }
 
     #[test]
@@ -0,0 +1,118 @@
+//! Compression applied to plaintext before encryption
+//!
+//! Ciphertext doesn't compress, so large strings and payload chunks are
+//! compressed first and decompressed by the Luau runtime after decryption.
+//! LZ4 blocks carry their uncompressed size as a 4-byte little-endian
+//! prefix; zlib streams are standard (header, raw deflate, Adler-32).
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Compression of encrypted payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    /// Fast to decompress in Luau, weaker ratio
+    Lz4,
+    /// Better ratio, slower to inflate
+    Zlib,
+}
+
+impl Compression {
+    /// Name used in config files and in the `z` field of emitted entries
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zlib => "zlib",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Compression::None),
+            "lz4" => Some(Compression::Lz4),
+            "zlib" => Some(Compression::Zlib),
+            _ => None,
+        }
+    }
+
+    pub fn is_none(self) -> bool {
+        self == Compression::None
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(data).map_err(|e| {
+                ObfuscatorError::CryptoError(format!("LZ4 decompression failed: {}", e)).into()
+            }),
+            Compression::Zlib => {
+                let mut output = Vec::new();
+                ZlibDecoder::new(data).read_to_end(&mut output).map_err(|e| {
+                    ObfuscatorError::CryptoError(format!("zlib decompression failed: {}", e))
+                })?;
+                Ok(output)
+            }
+        }
+    }
+
+    /// `data` compressed, or `None` when compressing wouldn't make it smaller
+    pub fn shrink(self, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        if self.is_none() {
+            return Ok(None);
+        }
+        let compressed = self.compress(data)?;
+        Ok(Some(compressed).filter(|c| c.len() < data.len()))
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Length of `len` bytes once base64-encoded, as they are in the output
+pub fn encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_shrink() {
+        let text = "local config = {enabled = true, retries = 3}\n".repeat(40);
+        for compression in [Compression::Lz4, Compression::Zlib] {
+            let compressed = compression.shrink(text.as_bytes()).unwrap().unwrap();
+            assert!(compressed.len() < text.len() / 4);
+            assert_eq!(compression.decompress(&compressed).unwrap(), text.as_bytes());
+            assert_eq!(Compression::from_name(compression.name()), Some(compression));
+        }
+
+        // Short strings grow once framed, so they stay uncompressed
+        assert!(Compression::Zlib.shrink(b"hi").unwrap().is_none());
+        assert!(Compression::None.shrink(text.as_bytes()).unwrap().is_none());
+        assert!(Compression::Lz4.decompress(b"\xff\xff\xff\x7f").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Key-per-call cryptographic primitives
+
+use super::{AesEncryption, EncryptedData, KdfParams, KeyDerivation, Watermark, WatermarkGenerator};
+use anyhow::Result;
+use zeroize::Zeroizing;
+
+/// Stateless counterpart to `CryptoContext` for callers that manage their
+/// own keys: derive a key once, then pass it to every encrypt/decrypt call
+#[derive(Clone)]
+pub struct CryptoEngine {
+    kdf: KeyDerivation,
+    watermark_gen: WatermarkGenerator,
+}
+
+impl CryptoEngine {
+    pub fn new(params: KdfParams) -> Self {
+        Self {
+            kdf: KeyDerivation::with_params(params),
+            watermark_gen: WatermarkGenerator::new(),
+        }
+    }
+
+    /// Argon2id parameters keys are derived with
+    pub fn params(&self) -> KdfParams {
+        self.kdf.params()
+    }
+
+    /// Derive a 32-byte key (salt must be at least 16 bytes), wiped when
+    /// dropped
+    pub fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        self.kdf.derive_key(password.as_bytes(), salt)
+    }
+
+    /// AES-256-GCM encrypt `plaintext` under `key`
+    pub fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Result<EncryptedData> {
+        AesEncryption::new(key)?.encrypt(plaintext)
+    }
+
+    /// Decrypt and authenticate `encrypted` under `key`, wiped when dropped
+    pub fn decrypt(&self, key: &[u8], encrypted: &EncryptedData) -> Result<Zeroizing<Vec<u8>>> {
+        AesEncryption::new(key)?.decrypt(encrypted)
+    }
+
+    /// Generate watermark for customer
+    pub fn generate_watermark(&self, customer_id: &str, script_id: &str) -> Watermark {
+        self.watermark_gen.generate(customer_id, script_id)
+    }
+
+    /// Verify watermark
+    pub fn verify_watermark(&self, watermark: &Watermark, customer_id: &str) -> bool {
+        self.watermark_gen.verify(watermark, customer_id)
+    }
+}
+
+impl Default for CryptoEngine {
+    fn default() -> Self {
+        Self::new(KdfParams::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_params() -> KdfParams {
+        KdfParams {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_round_trip_with_derived_key() {
+        let engine = CryptoEngine::new(fast_params());
+        let key = engine.derive_key("password", b"0123456789abcdef").unwrap();
+
+        let encrypted = engine.encrypt(&key, b"payload").unwrap();
+        assert_eq!(*engine.decrypt(&key, &encrypted).unwrap(), b"payload");
+
+        let other = engine.derive_key("other", b"0123456789abcdef").unwrap();
+        assert!(engine.decrypt(&other, &encrypted).is_err());
+    }
+}
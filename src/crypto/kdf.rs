@@ -2,38 +2,97 @@
 
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
-use argon2::{
-    password_hash::{PasswordHasher, SaltString},
-    Argon2, ParamsBuilder, Version,
-};
+use argon2::{Argon2, ParamsBuilder, Version};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Derived key length in bytes
+const KEY_LEN: usize = 32;
+
+/// Argon2id cost parameters
+///
+/// Stored next to the salt in symbol maps: the same password only re-derives
+/// the key with the same parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub memory_cost: u32,
+    /// Iterations
+    pub time_cost: u32,
+    /// Parallel lanes
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// 19 MB, 2 passes: well under a second, for quick local iteration
+    pub const fn interactive() -> Self {
+        Self {
+            memory_cost: 19456,
+            time_cost: 2,
+            parallelism: 1,
+        }
+    }
+
+    /// 64 MB, 3 passes (RFC 9106's recommendation for constrained memory)
+    pub const fn balanced() -> Self {
+        Self {
+            memory_cost: 65536,
+            time_cost: 3,
+            parallelism: 4,
+        }
+    }
+
+    /// 256 MB, 4 passes: the default, for release builds
+    pub const fn paranoid() -> Self {
+        Self {
+            memory_cost: 262144,
+            time_cost: 4,
+            parallelism: 2,
+        }
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::paranoid()
+    }
+}
 
 /// Key derivation using Argon2id
+#[derive(Clone)]
 pub struct KeyDerivation {
-    argon2: Argon2<'static>,
+    params: KdfParams,
 }
 
 impl KeyDerivation {
     /// Create new KDF with high-security parameters
     pub fn new() -> Self {
-        // High-security parameters for service-side use
+        Self::with_params(KdfParams::default())
+    }
+
+    /// Create a KDF with explicit cost parameters (checked on first use)
+    pub fn with_params(params: KdfParams) -> Self {
+        Self { params }
+    }
+
+    /// Cost parameters keys are derived with
+    pub fn params(&self) -> KdfParams {
+        self.params
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>> {
         let params = ParamsBuilder::new()
-            .m_cost(262144) // 256 MB memory
-            .t_cost(4) // 4 iterations
-            .p_cost(2) // 2 parallel threads
+            .m_cost(self.params.memory_cost)
+            .t_cost(self.params.time_cost)
+            .p_cost(self.params.parallelism)
             .build()
-            .expect("Failed to build Argon2 params");
+            .map_err(|e| ObfuscatorError::CryptoError(format!("Invalid Argon2 parameters: {}", e)))?;
 
-        let argon2 = Argon2::new(
-            argon2::Algorithm::Argon2id,
-            Version::V0x13,
-            params,
-        );
-
-        Self { argon2 }
+        Ok(Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, params))
     }
 
-    /// Derive 32-byte key from password and salt
-    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<Vec<u8>> {
+    /// Derive 32-byte key from password and salt (wiped when dropped)
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
         // Ensure salt is at least 16 bytes
         if salt.len() < 16 {
             return Err(ObfuscatorError::CryptoError(
@@ -42,26 +101,23 @@ impl KeyDerivation {
             .into());
         }
 
-        // Create salt string (argon2 expects base64)
-        let salt_string = SaltString::encode_b64(salt)
-            .map_err(|e| ObfuscatorError::CryptoError(format!("Salt encoding failed: {}", e)))?;
-
-        // Hash password with salt
-        let hash = self
-            .argon2
-            .hash_password(password, &salt_string)
+        // Hash straight into the key buffer: the same bytes a PHC hash
+        // string would carry, without an intermediate copy left behind
+        let mut key = Zeroizing::new(vec![0u8; KEY_LEN]);
+        self.argon2()?
+            .hash_password_into(password, salt, &mut key)
             .map_err(|e| ObfuscatorError::CryptoError(format!("Key derivation failed: {}", e)))?;
 
-        // Extract the hash bytes (first 32 bytes)
-        let hash_bytes = hash
-            .hash
-            .ok_or_else(|| ObfuscatorError::CryptoError("No hash produced".to_string()))?;
-
-        Ok(hash_bytes.as_bytes().to_vec())
+        Ok(key)
     }
 
     /// Derive key with timing information (for benchmarking)
-    pub fn derive_key_timed(&self, password: &[u8], salt: &[u8]) -> Result<(Vec<u8>, std::time::Duration)> {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn derive_key_timed(
+        &self,
+        password: &[u8],
+        salt: &[u8],
+    ) -> Result<(Zeroizing<Vec<u8>>, std::time::Duration)> {
         let start = std::time::Instant::now();
         let key = self.derive_key(password, salt)?;
         let duration = start.elapsed();
@@ -123,6 +179,15 @@ mod tests {
         assert_ne!(key1, key2); // Different salts = different keys
     }
 
+    #[test]
+    fn test_invalid_params_rejected() {
+        let kdf = KeyDerivation::with_params(KdfParams {
+            memory_cost: 0,
+            ..KdfParams::default()
+        });
+        assert!(kdf.derive_key(b"password", b"0123456789abcdef").is_err());
+    }
+
     #[test]
     fn test_salt_too_short() {
         let kdf = KeyDerivation::new();
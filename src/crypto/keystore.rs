@@ -0,0 +1,281 @@
+//! Project keystore
+//!
+//! Records the salt, Argon2id parameters and key version used for each
+//! script id, so protecting the same script again with the same password
+//! derives the same master key. Salts aren't secret, but the file can be
+//! sealed with a password to keep the list of scripts private.
+
+use super::{constant_time_eq, Compression, CryptoContext, EncryptedData, KdfParams};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use base64::Engine;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Default keystore location, relative to the project directory
+pub const KEYSTORE_PATH: &str = ".luauobf/keystore.json";
+
+/// Current keystore format version
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// Key share label for the password check value
+const CHECK_LABEL: &str = "keystore:check";
+
+/// Key material recorded for one script id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreEntry {
+    /// Hex-encoded KDF salt
+    pub salt: String,
+    pub kdf: KdfParams,
+    pub key_version: u32,
+    /// Hex-encoded check value, to spot a mistyped password before
+    /// protecting under the wrong key
+    pub check: String,
+}
+
+impl KeystoreEntry {
+    pub fn from_context(crypto_ctx: &CryptoContext) -> Self {
+        Self {
+            salt: hex::encode(crypto_ctx.salt()),
+            kdf: crypto_ctx.kdf_params(),
+            key_version: crypto_ctx.key_version(),
+            check: check_value(crypto_ctx),
+        }
+    }
+
+    /// Re-derive the recorded key, failing if `password` isn't the one it
+    /// was recorded with
+    pub fn crypto_context(&self, password: &str) -> Result<CryptoContext> {
+        let salt = hex::decode(&self.salt).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid salt in keystore: {}", e))
+        })?;
+        let crypto_ctx = CryptoContext::with_params(password, Some(&salt), self.kdf)?
+            .with_key_version(self.key_version);
+
+        if !constant_time_eq(check_value(&crypto_ctx).as_bytes(), self.check.as_bytes()) {
+            return Err(ObfuscatorError::CryptoError(
+                "Password does not match the keystore entry (use `rekey` to change it)"
+                    .to_string(),
+            )
+            .into());
+        }
+        Ok(crypto_ctx)
+    }
+}
+
+/// Salts, KDF parameters and key versions per script id
+///
+/// Not `Debug`: it may hold the seal password.
+#[derive(Clone, Default)]
+pub struct Keystore {
+    entries: BTreeMap<String, KeystoreEntry>,
+    /// Password the file is sealed with on save
+    seal: Option<Zeroizing<String>>,
+}
+
+/// On-disk layout: entries in the clear, or sealed
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    scripts: BTreeMap<String, KeystoreEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<SealedEntries>,
+}
+
+/// Entries encrypted under a key derived from the seal password
+#[derive(Serialize, Deserialize)]
+struct SealedEntries {
+    /// Hex-encoded KDF salt
+    salt: String,
+    /// Base64 ciphertext and nonce
+    ct: String,
+    nonce: String,
+}
+
+impl Keystore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the keystore at `path`, or an empty one if it doesn't exist yet
+    ///
+    /// A sealed keystore needs `password`; it stays sealed with it on save.
+    pub fn load(path: &Path, password: Option<&str>) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read keystore: {:?}", path))?;
+        let file: KeystoreFile = serde_json::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid keystore {:?}: {}", path, e))
+        })?;
+        if file.version != KEYSTORE_VERSION {
+            return Err(ObfuscatorError::ConfigError(format!(
+                "Unsupported keystore version {} (expected {})",
+                file.version, KEYSTORE_VERSION
+            ))
+            .into());
+        }
+
+        let Some(sealed) = file.sealed else {
+            return Ok(Self {
+                entries: file.scripts,
+                seal: None,
+            });
+        };
+        let password = password.ok_or_else(|| {
+            ObfuscatorError::ConfigError(format!(
+                "Keystore {:?} is encrypted; a password is required",
+                path
+            ))
+        })?;
+        Ok(Self {
+            entries: sealed.open(password)?,
+            seal: Some(Zeroizing::new(password.to_string())),
+        })
+    }
+
+    /// Write the keystore to `path`, creating its directory
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = match &self.seal {
+            Some(password) => KeystoreFile {
+                version: KEYSTORE_VERSION,
+                scripts: BTreeMap::new(),
+                sealed: Some(SealedEntries::seal(&self.entries, password)?),
+            },
+            None => KeystoreFile {
+                version: KEYSTORE_VERSION,
+                scripts: self.entries.clone(),
+                sealed: None,
+            },
+        };
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create keystore directory: {:?}", dir))?;
+        }
+        let json = serde_json::to_string_pretty(&file).context("Failed to serialize keystore")?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write keystore: {:?}", path))
+    }
+
+    /// Encrypt the file with `password` from now on
+    pub fn seal(&mut self, password: &str) {
+        self.seal = Some(Zeroizing::new(password.to_string()));
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.seal.is_some()
+    }
+
+    pub fn get(&self, script_id: &str) -> Option<&KeystoreEntry> {
+        self.entries.get(script_id)
+    }
+
+    /// Record (or replace) the key material `script_id` is protected with
+    pub fn record(&mut self, script_id: &str, crypto_ctx: &CryptoContext) {
+        self.entries
+            .insert(script_id.to_string(), KeystoreEntry::from_context(crypto_ctx));
+    }
+
+    /// Recorded script ids, sorted
+    pub fn script_ids(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+}
+
+impl SealedEntries {
+    fn seal(entries: &BTreeMap<String, KeystoreEntry>, password: &str) -> Result<Self> {
+        let salt: Vec<u8> = {
+            let mut rng = rand::thread_rng();
+            (0..32).map(|_| rng.gen::<u8>()).collect()
+        };
+        // The entries only guard salts, so the fast profile is enough
+        let crypto_ctx =
+            CryptoContext::with_params(password, Some(&salt), KdfParams::interactive())?;
+        let json = Zeroizing::new(
+            serde_json::to_vec(entries).context("Failed to serialize keystore")?,
+        );
+        let encrypted = crypto_ctx.encrypt(&json)?;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        Ok(Self {
+            salt: hex::encode(salt),
+            ct: engine.encode(&encrypted.ciphertext),
+            nonce: engine.encode(&encrypted.nonce),
+        })
+    }
+
+    fn open(&self, password: &str) -> Result<BTreeMap<String, KeystoreEntry>> {
+        let invalid =
+            |e: String| ObfuscatorError::ConfigError(format!("Invalid sealed keystore: {}", e));
+        let engine = base64::engine::general_purpose::STANDARD;
+        let salt = hex::decode(&self.salt).map_err(|e| invalid(e.to_string()))?;
+        let encrypted = EncryptedData {
+            ciphertext: engine.decode(&self.ct).map_err(|e| invalid(e.to_string()))?,
+            nonce: engine.decode(&self.nonce).map_err(|e| invalid(e.to_string()))?,
+            tag_len: 16,
+            key_version: 0,
+            compression: Compression::None,
+        };
+
+        let crypto_ctx =
+            CryptoContext::with_params(password, Some(&salt), KdfParams::interactive())?;
+        let json = crypto_ctx.decrypt(&encrypted).map_err(|_| {
+            ObfuscatorError::CryptoError("Wrong password for the encrypted keystore".to_string())
+        })?;
+        serde_json::from_slice(&json).map_err(|e| invalid(e.to_string()).into())
+    }
+}
+
+/// First 8 bytes of a key share, enough to tell passwords apart
+fn check_value(crypto_ctx: &CryptoContext) -> String {
+    hex::encode(&crypto_ctx.key_share(CHECK_LABEL)[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crypto_ctx(password: &str) -> CryptoContext {
+        CryptoContext::with_params(password, None, KdfParams::interactive()).unwrap()
+    }
+
+    #[test]
+    fn test_entry_rederives_same_key() {
+        let original = crypto_ctx("test_password").with_key_version(2);
+        let entry = KeystoreEntry::from_context(&original);
+
+        let again = entry.crypto_context("test_password").unwrap();
+        assert_eq!(again.salt(), original.salt());
+        assert_eq!(again.key_version(), 2);
+        let encrypted = original.encrypt(b"secret").unwrap();
+        assert_eq!(*again.decrypt(&encrypted).unwrap(), b"secret");
+
+        assert!(entry.crypto_context("wrong_password").is_err());
+    }
+
+    #[test]
+    fn test_sealed_keystore_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KEYSTORE_PATH);
+
+        let mut keystore = Keystore::new();
+        keystore.record("main", &crypto_ctx("test_password"));
+        keystore.seal("test_password");
+        keystore.save(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("main"));
+        assert!(Keystore::load(&path, None).is_err());
+        assert!(Keystore::load(&path, Some("wrong_password")).is_err());
+
+        let loaded = Keystore::load(&path, Some("test_password")).unwrap();
+        assert!(loaded.is_sealed());
+        assert_eq!(loaded.get("main"), keystore.get("main"));
+    }
+}
@@ -1,26 +1,62 @@
 //! Cryptography module - Key derivation, encryption, and watermarking
+//!
+//! `TestVectors` pins the outputs of all three across releases.
 
 mod aes;
+mod compress;
+mod engine;
 mod kdf;
+mod keystore;
+mod revocation;
+mod script_id;
+mod vectors;
 mod watermark;
 
 pub use aes::{AesEncryption, EncryptedData};
-pub use kdf::KeyDerivation;
-pub use watermark::{Watermark, WatermarkGenerator};
+pub use compress::{encoded_len, Compression};
+pub use engine::CryptoEngine;
+pub use kdf::{KdfParams, KeyDerivation};
+pub use keystore::{Keystore, KeystoreEntry, KEYSTORE_PATH, KEYSTORE_VERSION};
+pub use revocation::{revocation_fingerprint, RevocationList, REVOCATION_LIST_PREFIX};
+pub use script_id::derive_script_id;
+pub use vectors::{
+    CiphertextVector, StegoVector, TestVectors, VectorMismatch, WatermarkVector, VECTORS_VERSION,
+};
+pub use watermark::{Watermark, WatermarkGenerator, WatermarkRoot};
 
+use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
 use rand::Rng;
+use ring::hmac;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+/// Compare hashes, check values and other secrets-derived bytes without
+/// leaking where they first differ through timing (lengths aren't secret)
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
 
 /// Master cryptographic context
+///
+/// The master key lives only inside the AES context and is wiped on drop.
+#[derive(Clone)]
 pub struct CryptoContext {
     kdf: KeyDerivation,
     aes: AesEncryption,
     watermark_gen: WatermarkGenerator,
+    salt: Vec<u8>,
+    key_version: u32,
 }
 
 impl CryptoContext {
     /// Create new crypto context with password
     pub fn new(password: &str, salt: Option<&[u8]>) -> Result<Self> {
+        Self::with_params(password, salt, KdfParams::default())
+    }
+
+    /// Create a crypto context whose master key is derived with `params`
+    pub fn with_params(password: &str, salt: Option<&[u8]>, params: KdfParams) -> Result<Self> {
         // Generate salt if not provided
         let salt = match salt {
             Some(s) => s.to_vec(),
@@ -30,8 +66,8 @@ impl CryptoContext {
             }
         };
 
-        // Derive master key using Argon2id
-        let kdf = KeyDerivation::new();
+        // Derive master key using Argon2id (wiped once copied into AES)
+        let kdf = KeyDerivation::with_params(params);
         let master_key = kdf.derive_key(password.as_bytes(), &salt)?;
 
         // Initialize AES-256-GCM
@@ -44,17 +80,75 @@ impl CryptoContext {
             kdf,
             aes,
             watermark_gen,
+            salt,
+            key_version: 0,
         })
     }
 
+    /// Salt the master key was derived with (needed to re-derive it later)
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// Argon2id parameters the master key was derived with
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf.params()
+    }
+
+    /// Tag this context's output as key generation `version`
+    ///
+    /// Rotating the password bumps the version, so data encrypted under an
+    /// older key is rejected up front instead of failing authentication.
+    pub fn with_key_version(mut self, version: u32) -> Self {
+        self.key_version = version;
+        self
+    }
+
+    pub fn key_version(&self) -> u32 {
+        self.key_version
+    }
+
     /// Encrypt data
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<EncryptedData> {
-        self.aes.encrypt(plaintext)
+        let mut encrypted = self.aes.encrypt(plaintext)?;
+        encrypted.key_version = self.key_version;
+        Ok(encrypted)
+    }
+
+    /// Decrypt data, undoing any compression applied before encryption (the
+    /// plaintext is wiped when dropped)
+    pub fn decrypt(&self, encrypted: &EncryptedData) -> Result<Zeroizing<Vec<u8>>> {
+        if encrypted.key_version != self.key_version {
+            return Err(ObfuscatorError::CryptoError(format!(
+                "Data was encrypted with key version {}, this key is version {}",
+                encrypted.key_version, self.key_version
+            ))
+            .into());
+        }
+        let plaintext = self.aes.decrypt(encrypted)?;
+        match encrypted.compression {
+            Compression::None => Ok(plaintext),
+            compression => compression.decompress(&plaintext).map(Zeroizing::new),
+        }
     }
 
-    /// Decrypt data
-    pub fn decrypt(&self, encrypted: &EncryptedData) -> Result<Vec<u8>> {
-        self.aes.decrypt(encrypted)
+    /// 32 bytes of key material derived from the master key for `label`
+    ///
+    /// Deterministic, so key shares handed to buyers can be re-derived later.
+    pub fn key_share(&self, label: &str) -> Vec<u8> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.aes.key());
+        hmac::sign(&key, label.as_bytes()).as_ref().to_vec()
+    }
+
+    /// The master key XOR-ed with every share in `shares`
+    pub fn masked_key(&self, shares: &[Vec<u8>]) -> Vec<u8> {
+        let mut masked = self.aes.key().to_vec();
+        for share in shares {
+            for (byte, s) in masked.iter_mut().zip(share) {
+                *byte ^= s;
+            }
+        }
+        masked
     }
 
     /// Generate watermark for customer
@@ -62,6 +156,16 @@ impl CryptoContext {
         self.watermark_gen.generate(customer_id, script_id)
     }
 
+    /// Generate watermark for one file of a customer's copy of `project`
+    pub fn generate_project_watermark(
+        &self,
+        customer_id: &str,
+        project: &str,
+        script_id: &str,
+    ) -> Watermark {
+        self.watermark_gen.generate_for_project(customer_id, project, script_id)
+    }
+
     /// Verify watermark
     pub fn verify_watermark(&self, watermark: &Watermark, customer_id: &str) -> bool {
         self.watermark_gen.verify(watermark, customer_id)
@@ -89,6 +193,71 @@ mod tests {
         assert_eq!(plaintext, decrypted.as_slice());
     }
 
+    #[test]
+    fn test_salt_rederives_same_key() {
+        let ctx = CryptoContext::new("test_password", None).unwrap();
+        let encrypted = ctx.encrypt(b"secret").unwrap();
+
+        let again = CryptoContext::new("test_password", Some(ctx.salt())).unwrap();
+        assert_eq!(*again.decrypt(&encrypted).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_params_are_part_of_the_key() {
+        let ctx = CryptoContext::with_params("test_password", None, KdfParams::interactive())
+            .unwrap();
+        let encrypted = ctx.encrypt(b"secret").unwrap();
+        assert_eq!(ctx.kdf_params(), KdfParams::interactive());
+
+        let balanced =
+            CryptoContext::with_params("test_password", Some(ctx.salt()), KdfParams::balanced())
+                .unwrap();
+        assert!(balanced.decrypt(&encrypted).is_err());
+
+        let again =
+            CryptoContext::with_params("test_password", Some(ctx.salt()), ctx.kdf_params())
+                .unwrap();
+        assert_eq!(*again.decrypt(&encrypted).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_key_version_mismatch_rejected() {
+        let ctx = CryptoContext::new("test_password", None).unwrap();
+        let encrypted = ctx.encrypt(b"secret").unwrap();
+        assert_eq!(encrypted.key_version, 0);
+
+        let rotated = CryptoContext::new("new_password", None)
+            .unwrap()
+            .with_key_version(1);
+        let reencrypted = rotated.encrypt(&ctx.decrypt(&encrypted).unwrap()).unwrap();
+        assert_eq!(reencrypted.key_version, 1);
+        assert!(ctx.decrypt(&reencrypted).is_err());
+        assert_eq!(*rotated.decrypt(&reencrypted).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_key_shares_unmask_master_key() {
+        let ctx = CryptoContext::new("test_password", None).unwrap();
+        let shares = vec![ctx.key_share("a"), ctx.key_share("b")];
+        assert_eq!(shares[0], ctx.key_share("a"));
+        assert_ne!(shares[0], shares[1]);
+
+        let mut unmasked = ctx.masked_key(&shares);
+        for share in &shares {
+            for (byte, s) in unmasked.iter_mut().zip(share) {
+                *byte ^= s;
+            }
+        }
+        assert_eq!(unmasked, ctx.aes.key());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"same bytes", b"same bytes"));
+        assert!(!constant_time_eq(b"same bytes", b"same bytez"));
+        assert!(!constant_time_eq(b"short", b"longer"));
+    }
+
     #[test]
     fn test_watermark_generation() {
         let ctx = CryptoContext::new("test_password", None).unwrap();
@@ -0,0 +1,160 @@
+//! Signed revocation lists
+//!
+//! The license API hands out a script's revoked keys as a compact signed
+//! list, `lrl1.<payload>.<signature>` in base64url: the payload is JSON with
+//! the script id, issue time and one fingerprint per revoked key, signed with
+//! HMAC-SHA256 under the developer API key. Builds embed the fingerprints so
+//! the license check turns a leaked key away before, and without, going
+//! online.
+//!
+//! Fingerprints are two 32-bit multiplicative hashes of `script_id:key`,
+//! cheap enough to recompute in Luau (see `license_validation.lua`). They
+//! keep the keys themselves out of builds; they are not a cryptographic
+//! commitment.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+
+/// Version tag every signed list starts with
+pub const REVOCATION_LIST_PREFIX: &str = "lrl1";
+
+/// A script's revoked license keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub script_id: String,
+    /// Unix timestamp
+    pub issued_at: u64,
+    /// `revocation_fingerprint` of each revoked key
+    pub revoked: Vec<String>,
+}
+
+impl RevocationList {
+    /// Empty list for `script_id`
+    pub fn new(script_id: impl Into<String>, issued_at: u64) -> Self {
+        Self {
+            script_id: script_id.into(),
+            issued_at,
+            revoked: Vec::new(),
+        }
+    }
+
+    /// Add `license_key` to the list
+    pub fn revoke(&mut self, license_key: &str) {
+        let fingerprint = revocation_fingerprint(&self.script_id, license_key);
+        if !self.revoked.contains(&fingerprint) {
+            self.revoked.push(fingerprint);
+        }
+    }
+
+    /// Whether `license_key` is revoked
+    pub fn contains(&self, license_key: &str) -> bool {
+        self.revoked
+            .contains(&revocation_fingerprint(&self.script_id, license_key))
+    }
+
+    /// Encode and sign as `lrl1.payload.signature`
+    pub fn sign(&self, api_key: &str) -> Result<String> {
+        let payload = serde_json::to_vec(self)?;
+        let signing_input = format!(
+            "{}.{}",
+            REVOCATION_LIST_PREFIX,
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+        let tag = hmac::sign(&signing_key(api_key), signing_input.as_bytes());
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(tag.as_ref())
+        ))
+    }
+
+    /// Check a signed list's signature and decode it
+    pub fn verify(signed: &str, api_key: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            ObfuscatorError::LicenseError(format!("Invalid revocation list: {}", reason))
+        };
+
+        let (signing_input, signature) = signed
+            .trim()
+            .rsplit_once('.')
+            .ok_or_else(|| invalid("malformed"))?;
+        let (prefix, payload) = signing_input
+            .split_once('.')
+            .ok_or_else(|| invalid("malformed"))?;
+        if prefix != REVOCATION_LIST_PREFIX {
+            return Err(invalid("unsupported version").into());
+        }
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| invalid("malformed signature"))?;
+        hmac::verify(&signing_key(api_key), signing_input.as_bytes(), &signature)
+            .map_err(|_| invalid("signature mismatch (signed for another API key?)"))?;
+
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| invalid("malformed payload"))?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+}
+
+fn signing_key(api_key: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, api_key.as_bytes())
+}
+
+/// Fingerprint of a license key in `script_id`'s revocation list: 16 hex
+/// digits, the same the license check computes at runtime
+pub fn revocation_fingerprint(script_id: &str, license_key: &str) -> String {
+    let (mut a, mut b) = (2_166_136_261u32, 5381u32);
+    for byte in format!("{}:{}", script_id, license_key).bytes() {
+        a = a.wrapping_mul(33).wrapping_add(byte as u32);
+        b = b.wrapping_mul(131).wrapping_add(byte as u32);
+    }
+    format!("{:08x}{:08x}", a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_matches_runtime() {
+        // Computed by the license template's `key_fingerprint` in Luau
+        assert_eq!(
+            revocation_fingerprint("admin", "ABCD-1234"),
+            "e15fa58947da3efd"
+        );
+        assert_ne!(
+            revocation_fingerprint("other", "ABCD-1234"),
+            "e15fa58947da3efd"
+        );
+    }
+
+    #[test]
+    fn test_signed_list_roundtrip() {
+        let mut list = RevocationList::new("admin", 1_700_000_000);
+        list.revoke("ABCD-1234");
+        list.revoke("ABCD-1234");
+        assert_eq!(list.revoked.len(), 1);
+        assert!(list.contains("ABCD-1234"));
+        assert!(!list.contains("EFGH-5678"));
+
+        let signed = list.sign("dev_key").unwrap();
+        assert!(signed.starts_with("lrl1."));
+        assert!(!signed.contains("ABCD"));
+        assert_eq!(RevocationList::verify(&signed, "dev_key").unwrap(), list);
+        assert!(RevocationList::verify(&signed, "other_key").is_err());
+
+        // A key dropped from the payload breaks the signature
+        let emptied = RevocationList::new("admin", 1_700_000_000)
+            .sign("dev_key")
+            .unwrap();
+        let (_, payload) = emptied.rsplit_once('.').unwrap().0.split_once('.').unwrap();
+        let signature = signed.rsplit_once('.').unwrap().1;
+        let spliced = format!("lrl1.{}.{}", payload, signature);
+        assert!(RevocationList::verify(&spliced, "dev_key").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Script ids derived from the source
+//!
+//! A script id names a protected script in license requests, the watermark,
+//! the keystore and the symbol map. Without `--script-id` it is derived from
+//! the project name and the source: BLAKE3 (in key derivation mode, so the
+//! hash is specific to this use) over both, after normalizing line endings,
+//! a byte order mark and trailing whitespace. The same script in the same
+//! project always gets the same id; any other edit gives it a new one.
+
+/// BLAKE3 context string; change it and every derived id changes
+const CONTEXT: &str = "luau-obfuscator script id v1";
+
+/// Hex digits of the hash kept in an id (64 bits)
+const HASH_DIGITS: usize = 16;
+
+/// Longest project name prefix kept in an id
+const MAX_PREFIX: usize = 32;
+
+/// Script id for `source` in `project`, like `admin_commands-3fa2c1d9e8b7a650`
+pub fn derive_script_id(source: &str, project: &str) -> String {
+    let mut hasher = blake3::Hasher::new_derive_key(CONTEXT);
+    hasher.update(project.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(normalize_source(source).as_bytes());
+    let hash = hasher.finalize().to_hex();
+
+    let prefix: String = project
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .take(MAX_PREFIX)
+        .collect();
+    let prefix = prefix.trim_matches('_');
+    if prefix.is_empty() {
+        hash[..HASH_DIGITS].to_string()
+    } else {
+        format!("{}-{}", prefix, &hash[..HASH_DIGITS])
+    }
+}
+
+/// Source as hashed: `\n` line endings, no BOM, no trailing whitespace
+fn normalize_source(source: &str) -> String {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let source = source.replace("\r\n", "\n").replace('\r', "\n");
+    let mut normalized = String::with_capacity(source.len());
+    for line in source.trim_end().lines() {
+        normalized.push_str(line.trim_end());
+        normalized.push('\n');
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_id_ignores_formatting_noise() {
+        let id = derive_script_id("local a = 1\nprint(a)\n", "Admin Commands");
+        assert!(id.starts_with("admin_commands-"));
+        assert_eq!(id.len(), "admin_commands-".len() + HASH_DIGITS);
+
+        let noisy = "\u{feff}local a = 1   \r\nprint(a)\r\n\r\n";
+        assert_eq!(derive_script_id(noisy, "Admin Commands"), id);
+    }
+
+    #[test]
+    fn test_script_id_changes_with_source_and_project() {
+        let id = derive_script_id("print(1)", "shop");
+        assert_ne!(derive_script_id("print(2)", "shop"), id);
+        assert_ne!(derive_script_id("print(1)", "inventory"), id);
+        assert_eq!(derive_script_id("print(1)", "").len(), HASH_DIGITS);
+    }
+}
@@ -0,0 +1,355 @@
+//! Cross-release test vectors
+//!
+//! Fixed inputs and what this build computes from them: watermark hashes
+//! (plain and derived from a project root), AES-256-GCM ciphertexts under a
+//! key derived from a fixed password and salt and a fixed nonce, and stego
+//! patterns. `vectors` writes them as JSON and `vectors --verify` recomputes
+//! every entry of such a file from its inputs, so vectors saved from one
+//! release hold the next one, the hosted API and the Luau runtime to the same
+//! outputs.
+
+use super::{AesEncryption, KdfParams, KeyDerivation, WatermarkGenerator, WatermarkRoot};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Format version of the vectors file; readers reject newer ones
+pub const VECTORS_VERSION: u32 = 1;
+
+/// `(customer, script, timestamp, project)`; the reversed secondary hash
+/// input makes a multi-byte customer id worth a vector of its own
+const WATERMARK_INPUTS: &[(&str, &str, u64, Option<&str>)] = &[
+    ("customer123", "script456", 1_700_000_000, None),
+    ("1234567890", "combat_system", 0, None),
+    ("Kūnlé_ø", "gui_controller", 1_735_689_600, None),
+    (
+        "customer123",
+        "inventory_system",
+        1_700_000_000,
+        Some("tycoon"),
+    ),
+];
+
+/// `(password, salt, nonce, plaintext)`
+const CIPHERTEXT_INPUTS: &[(&str, &[u8; 16], &[u8; 12], &str)] = &[
+    ("test_password", b"vector-salt-0001", &[0; 12], ""),
+    (
+        "test_password",
+        b"vector-salt-0001",
+        &[1; 12],
+        "Hello, World!",
+    ),
+    (
+        "correct horse battery staple",
+        b"vector-salt-0002",
+        b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b",
+        "local API_KEY = \"sk_live_1234567890\" -- ключ",
+    ),
+];
+
+/// `(customer, script, timestamp, length)`
+const STEGO_INPUTS: &[(&str, &str, u64, usize)] = &[
+    ("customer123", "script456", 1_700_000_000, 64),
+    ("customer123", "script456", 1_700_000_000, 257),
+    ("1234567890", "combat_system", 0, 1000),
+];
+
+/// Every vector of one build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u32,
+    pub watermarks: Vec<WatermarkVector>,
+    pub ciphertexts: Vec<CiphertextVector>,
+    pub stego_patterns: Vec<StegoVector>,
+}
+
+/// Watermark hashes of one customer's copy of a script
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatermarkVector {
+    pub customer_id: String,
+    pub script_id: String,
+    pub timestamp: u64,
+    /// Project the watermark derives from, if any
+    pub project: Option<String>,
+    /// Hex
+    pub primary_hash: String,
+    /// Hex
+    pub secondary_hash: String,
+    /// Public tag of the project root
+    pub root_tag: Option<String>,
+}
+
+/// One string encrypted the way string tables and payloads are
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiphertextVector {
+    pub password: String,
+    /// Hex
+    pub salt: String,
+    pub kdf: KdfParams,
+    /// Hex; the Argon2id key the password and salt derive
+    pub key: String,
+    /// Hex, 12 bytes
+    pub nonce: String,
+    pub plaintext: String,
+    /// Hex, with the 16-byte tag appended
+    pub ciphertext: String,
+}
+
+/// A stego pattern as a string of `0`s and `1`s
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StegoVector {
+    pub customer_id: String,
+    pub script_id: String,
+    pub timestamp: u64,
+    pub pattern: String,
+}
+
+/// A vector this build computes differently
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VectorMismatch {
+    /// `watermarks[2].primary_hash`, ...
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl TestVectors {
+    /// The vectors of this build, from the built-in inputs
+    ///
+    /// Keys are derived with `KdfParams::interactive()` to keep this quick;
+    /// every other parameter set runs the same Argon2id code.
+    pub fn generate() -> Result<Self> {
+        let watermarks = WATERMARK_INPUTS
+            .iter()
+            .map(|&(customer, script, timestamp, project)| {
+                WatermarkVector::compute(customer, script, timestamp, project)
+            })
+            .collect();
+        let ciphertexts = CIPHERTEXT_INPUTS
+            .iter()
+            .map(|&(password, salt, nonce, plaintext)| {
+                CiphertextVector::compute(
+                    password,
+                    salt,
+                    KdfParams::interactive(),
+                    nonce,
+                    plaintext,
+                )
+            })
+            .collect::<Result<_>>()?;
+        let stego_patterns = STEGO_INPUTS
+            .iter()
+            .map(|&(customer, script, timestamp, length)| {
+                StegoVector::compute(customer, script, timestamp, length)
+            })
+            .collect();
+
+        Ok(Self {
+            version: VECTORS_VERSION,
+            watermarks,
+            ciphertexts,
+            stego_patterns,
+        })
+    }
+
+    /// Recompute every vector from its inputs; empty when all match
+    pub fn verify(&self) -> Result<Vec<VectorMismatch>> {
+        if self.version > VECTORS_VERSION {
+            return Err(ObfuscatorError::CryptoError(format!(
+                "Test vectors are format version {}, this build reads up to {}",
+                self.version, VECTORS_VERSION
+            ))
+            .into());
+        }
+
+        let mut mismatches = Vec::new();
+        let mut compare = |field: String, expected: &str, actual: &str| {
+            if expected != actual {
+                mismatches.push(VectorMismatch {
+                    field,
+                    expected: expected.to_string(),
+                    actual: actual.to_string(),
+                });
+            }
+        };
+
+        for (i, vector) in self.watermarks.iter().enumerate() {
+            let actual = WatermarkVector::compute(
+                &vector.customer_id,
+                &vector.script_id,
+                vector.timestamp,
+                vector.project.as_deref(),
+            );
+            let field = |name: &str| format!("watermarks[{}].{}", i, name);
+            compare(
+                field("primary_hash"),
+                &vector.primary_hash,
+                &actual.primary_hash,
+            );
+            compare(
+                field("secondary_hash"),
+                &vector.secondary_hash,
+                &actual.secondary_hash,
+            );
+            compare(
+                field("root_tag"),
+                vector.root_tag.as_deref().unwrap_or_default(),
+                actual.root_tag.as_deref().unwrap_or_default(),
+            );
+        }
+
+        for (i, vector) in self.ciphertexts.iter().enumerate() {
+            let salt = decode_hex(&vector.salt, "salt")?;
+            let nonce = decode_hex(&vector.nonce, "nonce")?;
+            let actual = CiphertextVector::compute(
+                &vector.password,
+                &salt,
+                vector.kdf,
+                &nonce,
+                &vector.plaintext,
+            )?;
+            let field = |name: &str| format!("ciphertexts[{}].{}", i, name);
+            compare(field("key"), &vector.key, &actual.key);
+            compare(field("ciphertext"), &vector.ciphertext, &actual.ciphertext);
+        }
+
+        for (i, vector) in self.stego_patterns.iter().enumerate() {
+            let actual = StegoVector::compute(
+                &vector.customer_id,
+                &vector.script_id,
+                vector.timestamp,
+                vector.pattern.len(),
+            );
+            compare(
+                format!("stego_patterns[{}].pattern", i),
+                &vector.pattern,
+                &actual.pattern,
+            );
+        }
+
+        Ok(mismatches)
+    }
+}
+
+impl WatermarkVector {
+    fn compute(customer: &str, script: &str, timestamp: u64, project: Option<&str>) -> Self {
+        let generator = WatermarkGenerator::new();
+        let watermark = match project {
+            Some(project) => {
+                WatermarkRoot::new(customer, project).derive_at(&generator, script, timestamp)
+            }
+            None => generator.generate_at(customer, script, timestamp),
+        };
+        Self {
+            customer_id: customer.to_string(),
+            script_id: script.to_string(),
+            timestamp,
+            project: project.map(str::to_string),
+            primary_hash: hex::encode(&watermark.primary_hash),
+            secondary_hash: hex::encode(&watermark.secondary_hash),
+            root_tag: watermark.root_tag().map(str::to_string),
+        }
+    }
+}
+
+impl CiphertextVector {
+    fn compute(
+        password: &str,
+        salt: &[u8],
+        kdf: KdfParams,
+        nonce: &[u8],
+        plaintext: &str,
+    ) -> Result<Self> {
+        let key = KeyDerivation::with_params(kdf).derive_key(password.as_bytes(), salt)?;
+        let encrypted =
+            AesEncryption::new(&key)?.encrypt_with_nonce(plaintext.as_bytes(), nonce)?;
+        Ok(Self {
+            password: password.to_string(),
+            salt: hex::encode(salt),
+            kdf,
+            key: hex::encode(&*key),
+            nonce: hex::encode(nonce),
+            plaintext: plaintext.to_string(),
+            ciphertext: hex::encode(&encrypted.ciphertext),
+        })
+    }
+}
+
+impl StegoVector {
+    fn compute(customer: &str, script: &str, timestamp: u64, length: usize) -> Self {
+        let generator = WatermarkGenerator::new();
+        let watermark = generator.generate_at(customer, script, timestamp);
+        let pattern = generator
+            .generate_stego_pattern(&watermark, length)
+            .into_iter()
+            .map(|bit| if bit { '1' } else { '0' })
+            .collect();
+        Self {
+            customer_id: customer.to_string(),
+            script_id: script.to_string(),
+            timestamp,
+            pattern,
+        }
+    }
+}
+
+fn decode_hex(value: &str, what: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|e| {
+        ObfuscatorError::CryptoError(format!("Invalid {} in test vectors: {}", what, e)).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Compression, EncryptedData};
+
+    #[test]
+    fn test_generated_vectors_verify() {
+        let vectors = TestVectors::generate().unwrap();
+        assert_eq!(vectors.watermarks.len(), WATERMARK_INPUTS.len());
+        assert!(vectors.watermarks[3].root_tag.is_some());
+        assert_eq!(vectors.ciphertexts[0].ciphertext.len(), 32); // Tag only
+        assert_eq!(vectors.stego_patterns[1].pattern.len(), 257);
+        assert!(vectors.verify().unwrap().is_empty());
+
+        // Survives the round trip through the file
+        let json = serde_json::to_string(&vectors).unwrap();
+        let parsed: TestVectors = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, vectors);
+    }
+
+    #[test]
+    fn test_ciphertext_decrypts() {
+        let vector = &TestVectors::generate().unwrap().ciphertexts[1];
+        let aes = AesEncryption::new(&hex::decode(&vector.key).unwrap()).unwrap();
+        let encrypted = EncryptedData {
+            ciphertext: hex::decode(&vector.ciphertext).unwrap(),
+            nonce: hex::decode(&vector.nonce).unwrap(),
+            tag_len: 16,
+            key_version: 0,
+            compression: Compression::None,
+        };
+        assert_eq!(
+            *aes.decrypt(&encrypted).unwrap(),
+            vector.plaintext.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_changed_outputs_are_reported() {
+        let mut vectors = TestVectors::generate().unwrap();
+        vectors.ciphertexts.clear();
+        vectors.watermarks[0].primary_hash = "00".repeat(32);
+        vectors.stego_patterns[0].pattern.replace_range(0..1, "x");
+        let mismatches = vectors.verify().unwrap();
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["watermarks[0].primary_hash", "stego_patterns[0].pattern"]
+        );
+
+        vectors.version = VECTORS_VERSION + 1;
+        assert!(vectors.verify().is_err());
+    }
+}
@@ -2,7 +2,14 @@
 //!
 //! Provides robust, undetectable watermarks embedded in obfuscated scripts
 //! to trace leaked copies back to original purchasers.
+//!
+//! Files of a multi-file project can share a per-customer [`WatermarkRoot`]:
+//! each file's watermark is derived from the root, and all of them carry the
+//! root's public tag. Leaked files with the same tag come from one buyer, and
+//! any subset of them identifies that buyer (see
+//! [`WatermarkGenerator::identify_leaker_from_files`]).
 
+use super::constant_time_eq;
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
 use ring::digest::{Context, SHA256};
@@ -31,7 +38,78 @@ pub struct Watermark {
     pub metadata: HashMap<String, String>,
 }
 
+/// Metadata key naming the project of a root-derived watermark
+const PROJECT_KEY: &str = "project";
+
+/// Metadata key holding the public tag of the root a watermark derives from
+const ROOT_TAG_KEY: &str = "root_tag";
+
+impl Watermark {
+    /// Public tag of the project root this watermark derives from, if any
+    pub fn root_tag(&self) -> Option<&str> {
+        self.metadata.get(ROOT_TAG_KEY).map(String::as_str)
+    }
+}
+
+/// Per-customer, per-project secret that file watermarks are derived from
+#[derive(Clone)]
+pub struct WatermarkRoot {
+    customer_id: String,
+    project: String,
+    root: Vec<u8>,
+}
+
+impl WatermarkRoot {
+    /// Root of `customer_id`'s copy of `project`
+    pub fn new(customer_id: &str, project: &str) -> Self {
+        let data = format!("watermark-root||{}||{}", customer_id, project);
+        Self {
+            customer_id: customer_id.to_string(),
+            project: project.to_string(),
+            root: WatermarkGenerator::sha256(data.as_bytes()),
+        }
+    }
+
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    /// Tag shared by every file watermark of this root
+    ///
+    /// Groups leaked files by buyer without revealing who the buyer is.
+    pub fn tag(&self) -> String {
+        let data = [b"root-tag||".as_slice(), self.root.as_slice()].concat();
+        let digest = WatermarkGenerator::sha256(&data);
+        hex::encode(&digest[..8])
+    }
+
+    /// Watermark for file `script_id` of the project
+    pub fn derive(&self, generator: &WatermarkGenerator, script_id: &str) -> Watermark {
+        self.derive_at(generator, script_id, chrono::Utc::now().timestamp() as u64)
+    }
+
+    /// Watermark for file `script_id` as if generated at `timestamp`
+    pub fn derive_at(
+        &self,
+        generator: &WatermarkGenerator,
+        script_id: &str,
+        timestamp: u64,
+    ) -> Watermark {
+        let mut watermark = generator.build(&self.customer_id, script_id, timestamp, Some(self));
+        watermark.metadata.insert(PROJECT_KEY.to_string(), self.project.clone());
+        watermark.metadata.insert(ROOT_TAG_KEY.to_string(), self.tag());
+        watermark
+    }
+
+    /// Primary hash of file `script_id`: SHA-256(root || script_id || timestamp)
+    fn primary_hash(&self, script_id: &str, timestamp: u64) -> Vec<u8> {
+        let data = format!("{}||{}||{}", hex::encode(&self.root), script_id, timestamp);
+        WatermarkGenerator::sha256(data.as_bytes())
+    }
+}
+
 /// Watermark generator
+#[derive(Clone)]
 pub struct WatermarkGenerator {
     version: u32,
 }
@@ -51,14 +129,44 @@ impl WatermarkGenerator {
     /// # Returns
     /// Cryptographically secure watermark containing multiple identification layers
     pub fn generate(&self, customer_id: &str, script_id: &str) -> Watermark {
-        // Generate primary hash: SHA-256(customer_id || script_id || timestamp)
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        let primary_data = format!("{}||{}||{}", customer_id, script_id, timestamp);
-        let primary_hash = Self::sha256(primary_data.as_bytes());
+        // chrono reads the clock through JS on wasm32, where SystemTime panics
+        let timestamp = chrono::Utc::now().timestamp() as u64;
+        self.build(customer_id, script_id, timestamp, None)
+    }
+
+    /// Watermark as if generated at `timestamp` (seconds since the epoch),
+    /// for reproducible output such as test vectors
+    pub fn generate_at(&self, customer_id: &str, script_id: &str, timestamp: u64) -> Watermark {
+        self.build(customer_id, script_id, timestamp, None)
+    }
+
+    /// Watermark for file `script_id` of `project`, derived from the
+    /// customer's project root so leaked files can be correlated
+    pub fn generate_for_project(
+        &self,
+        customer_id: &str,
+        project: &str,
+        script_id: &str,
+    ) -> Watermark {
+        WatermarkRoot::new(customer_id, project).derive(self, script_id)
+    }
+
+    fn build(
+        &self,
+        customer_id: &str,
+        script_id: &str,
+        timestamp: u64,
+        root: Option<&WatermarkRoot>,
+    ) -> Watermark {
+        // Generate primary hash: SHA-256(customer_id || script_id || timestamp),
+        // or from the project root when there is one
+        let primary_hash = match root {
+            Some(root) => root.primary_hash(script_id, timestamp),
+            None => {
+                let primary_data = format!("{}||{}||{}", customer_id, script_id, timestamp);
+                Self::sha256(primary_data.as_bytes())
+            }
+        };
 
         // Generate secondary hash: SHA-256(reversed customer_id || script_id)
         // This provides redundancy if primary watermark is partially damaged
@@ -92,13 +200,26 @@ impl WatermarkGenerator {
     /// # Returns
     /// `true` if watermark matches customer, `false` otherwise
     pub fn verify(&self, watermark: &Watermark, customer_id: &str) -> bool {
-        // Regenerate primary hash with stored timestamp
-        let primary_data = format!("{}||{}||{}", 
-            customer_id, 
-            watermark.script_id, 
-            watermark.timestamp
-        );
-        let expected_primary = Self::sha256(primary_data.as_bytes());
+        // Regenerate primary hash with stored timestamp (from the project
+        // root for root-derived watermarks)
+        let expected_primary = match watermark.metadata.get(PROJECT_KEY) {
+            Some(project) => {
+                let root = WatermarkRoot::new(customer_id, project);
+                let tag = watermark.metadata.get(ROOT_TAG_KEY).map(String::as_str);
+                if tag != Some(root.tag().as_str()) {
+                    return false;
+                }
+                root.primary_hash(&watermark.script_id, watermark.timestamp)
+            }
+            None => {
+                let primary_data = format!("{}||{}||{}", 
+                    customer_id, 
+                    watermark.script_id, 
+                    watermark.timestamp
+                );
+                Self::sha256(primary_data.as_bytes())
+            }
+        };
 
         // Regenerate secondary hash
         let secondary_data = format!("{}||{}", 
@@ -107,9 +228,22 @@ impl WatermarkGenerator {
         );
         let expected_secondary = Self::sha256(secondary_data.as_bytes());
 
-        // Verify both hashes match
-        watermark.primary_hash == expected_primary 
-            && watermark.secondary_hash == expected_secondary
+        // Verify both hashes match (both are always compared, in constant time)
+        let primary = constant_time_eq(&watermark.primary_hash, &expected_primary);
+        let secondary = constant_time_eq(&watermark.secondary_hash, &expected_secondary);
+        primary & secondary
+    }
+
+    /// Check only the secondary hash against a customer ID
+    ///
+    /// The secondary hash doesn't depend on the timestamp or project root,
+    /// so it still identifies the customer when those were damaged.
+    pub fn verify_secondary(&self, watermark: &Watermark, customer_id: &str) -> bool {
+        let secondary_data = format!("{}||{}",
+            customer_id.chars().rev().collect::<String>(),
+            watermark.script_id
+        );
+        constant_time_eq(&watermark.secondary_hash, &Self::sha256(secondary_data.as_bytes()))
     }
 
     /// Extract customer ID candidates from leaked watermark
@@ -135,6 +269,43 @@ impl WatermarkGenerator {
             .collect()
     }
 
+    /// Identify the buyer of a set of leaked files
+    ///
+    /// `watermarks` may be any subset of a project's files, including files
+    /// protected without a shared root. Returns the customers every file
+    /// traces back to.
+    pub fn identify_leaker_from_files(
+        &self,
+        watermarks: &[Watermark],
+        known_customers: &[String],
+    ) -> Vec<String> {
+        if watermarks.is_empty() {
+            return Vec::new();
+        }
+        known_customers
+            .iter()
+            .filter(|customer_id| watermarks.iter().all(|wm| self.verify(wm, customer_id)))
+            .cloned()
+            .collect()
+    }
+
+    /// Group leaked files by the project root they derive from
+    ///
+    /// Works without knowing any customer: files in one group were protected
+    /// for the same buyer. Files without a root each form their own group.
+    /// Returns indices into `watermarks`.
+    pub fn correlate(&self, watermarks: &[Watermark]) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(Option<&String>, Vec<usize>)> = Vec::new();
+        for (index, watermark) in watermarks.iter().enumerate() {
+            let tag = watermark.metadata.get(ROOT_TAG_KEY);
+            match groups.iter_mut().find(|(group_tag, _)| tag.is_some() && *group_tag == tag) {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((tag, vec![index])),
+            }
+        }
+        groups.into_iter().map(|(_, indices)| indices).collect()
+    }
+
     /// Encode watermark as base64 string for embedding in code
     pub fn encode(&self, watermark: &Watermark) -> Result<String> {
         let json = serde_json::to_string(watermark)
@@ -269,6 +440,40 @@ mod tests {
         assert_eq!(leakers[0], "leaker@example.com");
     }
 
+    #[test]
+    fn test_project_watermarks_identify_buyer_from_any_subset() {
+        let gen = WatermarkGenerator::new();
+        let files = ["client", "server", "shared"];
+        let leaked: Vec<Watermark> = files
+            .iter()
+            .map(|file| gen.generate_for_project("leaker@example.com", "combat_kit", file))
+            .collect();
+        let other = gen.generate_for_project("user1@example.com", "combat_kit", "client");
+
+        let known_customers = vec![
+            "user1@example.com".to_string(),
+            "leaker@example.com".to_string(),
+        ];
+
+        assert!(gen.verify(&leaked[0], "leaker@example.com"));
+        assert!(!gen.verify(&leaked[0], "user1@example.com"));
+        for subset in [&leaked[..1], &leaked[1..], &leaked[..]] {
+            let leakers = gen.identify_leaker_from_files(subset, &known_customers);
+            assert_eq!(leakers, ["leaker@example.com"]);
+        }
+
+        // Files from two buyers match no single customer, but group apart
+        let mixed = [leaked[1].clone(), other.clone()];
+        assert!(gen.identify_leaker_from_files(&mixed, &known_customers).is_empty());
+        let all = [leaked[0].clone(), other, leaked[2].clone()];
+        assert_eq!(gen.correlate(&all), vec![vec![0, 2], vec![1]]);
+
+        // The root tag can't be swapped for another buyer's
+        let mut forged = leaked[0].clone();
+        forged.metadata.insert(ROOT_TAG_KEY.to_string(), "0".repeat(16));
+        assert!(!gen.verify(&forged, "leaker@example.com"));
+    }
+
     #[test]
     fn test_watermark_encoding_decoding() {
         let gen = WatermarkGenerator::new();
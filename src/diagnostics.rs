@@ -0,0 +1,258 @@
+//! Structured diagnostics
+//!
+//! Stages that find something risky but keep going (a `_G` access that
+//! keeps names readable, a block too large to flatten, a pass skipped in
+//! best-effort mode, output Roblox may refuse) report it as a
+//! [`Diagnostic`] with a stable id from [`DIAGNOSTIC_IDS`]. A run collects
+//! them in `ProtectedScript::diagnostics`; [`DenyRules`] turns chosen ids,
+//! or every warning, into a failure for CI.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Every diagnostic id, with what it reports
+pub const DIAGNOSTIC_IDS: &[(&str, &str)] = &[
+    (
+        "dynamic-global",
+        "`_G`, `shared` or `getfenv` reach a global by name, which stays readable",
+    ),
+    (
+        "flatten-limit",
+        "a block was left unflattened to keep its function within Luau's limits",
+    ),
+    (
+        "transform-skipped",
+        "a pass or block failed and was skipped (`--best-effort`)",
+    ),
+    (
+        "unsupported-syntax",
+        "a statement the parser doesn't inspect; its literals stay as written",
+    ),
+    (
+        "script-budget",
+        "the output is over Roblox's script source budget",
+    ),
+    (
+        "missing-api",
+        "the output calls a library Roblox leaves out",
+    ),
+    (
+        "loadstring",
+        "the output calls `loadstring`, which needs LoadStringEnabled",
+    ),
+    (
+        "constants",
+        "the output holds more constants than a chunk should",
+    ),
+    ("locals", "a function has too many locals alive at once"),
+    ("upvalues", "a closure captures too many upvalues"),
+    ("nesting", "scopes nest deeper than Luau's parser follows"),
+];
+
+/// `--deny` value that denies every warning
+pub const DENY_WARNINGS: &str = "warnings";
+
+/// How much a diagnostic matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing; protection is unaffected
+    Note,
+    /// Protection is weaker, or the script may fail where it runs
+    Warning,
+    /// The run fails
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Lines a diagnostic points at, in the input or (for lint findings) in the
+/// generated script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl Span {
+    pub fn line(line: usize) -> Self {
+        Self {
+            start_line: line,
+            end_line: line,
+        }
+    }
+
+    pub fn lines(start_line: usize, end_line: usize) -> Self {
+        Self {
+            start_line,
+            end_line,
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start_line == self.end_line {
+            write!(f, "line {}", self.start_line)
+        } else {
+            write!(f, "lines {}-{}", self.start_line, self.end_line)
+        }
+    }
+}
+
+/// One finding of a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// One of [`DIAGNOSTIC_IDS`]
+    pub id: &'static str,
+    pub severity: Severity,
+    /// `None` when the finding isn't tied to lines
+    pub span: Option<Span>,
+    pub message: String,
+    /// How to make the finding go away, if there is a known way
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(id: &'static str, severity: Severity, message: impl Into<String>) -> Self {
+        debug_assert!(DIAGNOSTIC_IDS.iter().any(|(known, _)| *known == id));
+        Self {
+            id,
+            severity,
+            span: None,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    pub fn warning(id: &'static str, message: impl Into<String>) -> Self {
+        Self::new(id, Severity::Warning, message)
+    }
+
+    pub fn at(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]", self.severity, self.id)?;
+        if let Some(span) = &self.span {
+            write!(f, " {}", span)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Diagnostics that fail a run (`--deny warnings`, `--deny <ID>`)
+///
+/// Errors always fail it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DenyRules {
+    warnings: bool,
+    ids: BTreeSet<&'static str>,
+}
+
+impl DenyRules {
+    /// Rules from `--deny` values: `warnings` or diagnostic ids
+    pub fn parse<S: AsRef<str>>(values: &[S]) -> Result<Self> {
+        let mut rules = Self::default();
+        for value in values {
+            let value = value.as_ref();
+            if value == DENY_WARNINGS {
+                rules.warnings = true;
+                continue;
+            }
+            let id = DIAGNOSTIC_IDS
+                .iter()
+                .map(|(id, _)| *id)
+                .find(|id| *id == value)
+                .ok_or_else(|| {
+                    let ids: Vec<&str> = DIAGNOSTIC_IDS.iter().map(|(id, _)| *id).collect();
+                    ObfuscatorError::ConfigError(format!(
+                        "Unknown diagnostic id {:?} (expected `{}` or one of: {})",
+                        value,
+                        DENY_WARNINGS,
+                        ids.join(", ")
+                    ))
+                })?;
+            rules.ids.insert(id);
+        }
+        Ok(rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.warnings && self.ids.is_empty()
+    }
+
+    /// Whether `diagnostic` fails the run
+    pub fn denies(&self, diagnostic: &Diagnostic) -> bool {
+        match diagnostic.severity {
+            Severity::Error => true,
+            Severity::Warning if self.warnings => true,
+            _ => self.ids.contains(diagnostic.id),
+        }
+    }
+
+    /// Fail if any of `diagnostics` is denied
+    pub fn check(&self, diagnostics: &[Diagnostic]) -> Result<()> {
+        let denied: Vec<&Diagnostic> = diagnostics.iter().filter(|d| self.denies(d)).collect();
+        match denied.first() {
+            None => Ok(()),
+            Some(first) => Err(ObfuscatorError::DeniedDiagnostic(format!(
+                "{} diagnostic(s) denied, first: {}",
+                denied.len(),
+                first
+            ))
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deny_rules() {
+        let skipped = Diagnostic::warning("transform-skipped", "Rename pass skipped: boom");
+        let note = Diagnostic::new("flatten-limit", Severity::Note, "over 1000 statements")
+            .at(Span::lines(3, 40));
+        assert_eq!(
+            note.to_string(),
+            "note[flatten-limit] lines 3-40: over 1000 statements"
+        );
+
+        assert!(DenyRules::default()
+            .check(&[skipped.clone(), note.clone()])
+            .is_ok());
+
+        let warnings = DenyRules::parse(&["warnings"]).unwrap();
+        assert!(warnings.denies(&skipped));
+        assert!(!warnings.denies(&note));
+        let error = warnings.check(&[note.clone(), skipped]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("1 diagnostic(s) denied, first: warning[transform-skipped]"));
+
+        assert!(DenyRules::parse(&["flatten-limit"]).unwrap().denies(&note));
+        assert!(DenyRules::parse(&["no-such-id"]).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! Luau Obfuscator library
+//!
+//! The protection pipeline, usable without the CLI:
+//!
+//! ```text
+//! LuauParser::parse → Analyzer::analyze → Obfuscator::obfuscate
+//!     → SourceRewriter::rewrite → CodeGenerator::generate
+//! ```
+//!
+//! `ObfuscationPipeline::run` runs all of it in one call, and `ReleaseMatrix`
+//! runs it once per customer. `ObfuscationPipeline::with_progress` reports
+//! each stage and pass to a `ProgressSink`. With the `memstats` feature the
+//! CLI also reports how much each stage allocated (see [`memstats`]).
+//!
+//! `ObfuscationEngine` and `AnalysisEngine` are the public names for
+//! `Obfuscator` and `Analyzer`. `CryptoEngine` takes explicit `KdfParams` and
+//! keys, where `CryptoContext` derives and holds one master key.
+//!
+//! Every stage works on in-memory strings, so the library builds for
+//! `wasm32-unknown-unknown` with `--no-default-features`. Filesystem helpers
+//! (config, symbol maps, Rojo projects) compile there too but are only useful
+//! on native targets. The license API client needs the `network` feature,
+//! differential execution in an embedded Luau VM ([`verify`]) needs `verify`,
+//! and the command-line front end needs `cli`.
+
+#[cfg(all(feature = "network", target_arch = "wasm32"))]
+compile_error!("the `network` feature uses a blocking HTTP client and is not available on wasm32; build with --no-default-features");
+
+pub mod analysis;
+#[cfg(feature = "network")]
+pub mod api;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod codegen;
+pub mod crypto;
+pub mod diagnostics;
+pub mod memstats;
+pub mod obfuscation;
+pub mod parser;
+pub mod pipeline;
+pub mod progress;
+pub mod project;
+pub mod recovery;
+pub mod release;
+pub mod utils;
+#[cfg(feature = "verify")]
+pub mod verify;
+
+pub use analysis::{AnalysisEngine, AnalysisOptions, AnalysisResult, Analyzer};
+pub use codegen::{CodeGenConfig, CodeGenerator};
+pub use crypto::{CryptoContext, CryptoEngine, KdfParams};
+pub use diagnostics::{DenyRules, Diagnostic, Severity};
+pub use obfuscation::{ObfuscatedScript, ObfuscationEngine, ObfuscationTier, Obfuscator};
+pub use parser::{LuauParser, ParseResult};
+pub use pipeline::{ObfuscationPipeline, PipelineSettings, ProtectedScript};
+pub use progress::{ProgressSink, Stage};
+pub use release::{Customer, ReleaseMatrix};
+pub use utils::errors::ObfuscatorError;
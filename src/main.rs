@@ -3,22 +3,22 @@
 //! A commercial-grade CLI tool for obfuscating Luau/Roblox scripts with
 //! cryptographic protection and license management.
 
-mod analysis;
-mod api;
-mod cli;
-mod codegen;
-mod crypto;
-mod obfuscation;
-mod parser;
-mod utils;
-
-use anyhow::Result;
 use env_logger::Env;
+use luau_obfuscator::cli;
+use luau_obfuscator::utils::errors::exit_code;
+
+#[cfg(feature = "memstats")]
+#[global_allocator]
+static ALLOCATOR: luau_obfuscator::memstats::CountingAllocator =
+    luau_obfuscator::memstats::CountingAllocator;
 
-fn main() -> Result<()> {
+fn main() {
     // Initialize logging
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
     // Parse CLI arguments and execute command
-    cli::run()
+    if let Err(e) = cli::run() {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(exit_code(&e));
+    }
 }
@@ -0,0 +1,181 @@
+//! Heap usage per pipeline stage
+//!
+//! With the `memstats` feature the CLI installs [`CountingAllocator`] as the
+//! global allocator, and the pipeline records how much each stage allocated
+//! and how high the live heap got while it ran. That is how we check the
+//! <100 MB target on 10k-line scripts. Without the feature (or without the
+//! allocator installed) the report stays empty.
+//!
+//! The counters are process-wide: runs on other threads at the same time
+//! count towards each other's figures.
+
+use crate::progress::Stage;
+use serde::Serialize;
+
+/// Allocations made while one stage ran
+#[derive(Debug, Clone, Serialize)]
+pub struct StageMemory {
+    pub stage: Stage,
+    /// Bytes requested, including memory freed again before the stage ended
+    pub allocated_bytes: u64,
+    pub allocations: u64,
+    /// Highest live heap size during the stage
+    pub peak_bytes: u64,
+}
+
+/// Allocations of one run, stage by stage
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryStats {
+    pub stages: Vec<StageMemory>,
+}
+
+impl MemoryStats {
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Highest live heap size of the run
+    pub fn peak_bytes(&self) -> u64 {
+        self.stages.iter().map(|s| s.peak_bytes).max().unwrap_or(0)
+    }
+
+    /// Run `work` and record its allocations as `stage`'s
+    pub fn record<T>(&mut self, stage: Stage, work: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "memstats")]
+        {
+            let before = counters::start_stage();
+            let result = work();
+            if let Some(usage) = counters::finish_stage(before) {
+                self.stages.push(StageMemory {
+                    stage,
+                    allocated_bytes: usage.allocated_bytes,
+                    allocations: usage.allocations,
+                    peak_bytes: usage.peak_bytes,
+                });
+            }
+            result
+        }
+        #[cfg(not(feature = "memstats"))]
+        {
+            let _ = stage;
+            work()
+        }
+    }
+}
+
+#[cfg(feature = "memstats")]
+pub use counters::CountingAllocator;
+
+#[cfg(feature = "memstats")]
+mod counters {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static LIVE: AtomicU64 = AtomicU64::new(0);
+    static PEAK: AtomicU64 = AtomicU64::new(0);
+    static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+    static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+    /// The system allocator, counting what goes through it
+    ///
+    /// Install it in the binary:
+    ///
+    /// ```ignore
+    /// #[global_allocator]
+    /// static ALLOCATOR: CountingAllocator = CountingAllocator;
+    /// ```
+    pub struct CountingAllocator;
+
+    fn allocated(size: usize) {
+        let size = size as u64;
+        ALLOCATED.fetch_add(size, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn freed(size: usize) {
+        LIVE.fetch_sub(size as u64, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                allocated(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+            if !ptr.is_null() {
+                allocated(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            freed(layout.size());
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new = System.realloc(ptr, layout, new_size);
+            if !new.is_null() {
+                freed(layout.size());
+                allocated(new_size);
+            }
+            new
+        }
+    }
+
+    /// Counter values when a stage started
+    pub(super) struct Snapshot {
+        allocated: u64,
+        allocations: u64,
+    }
+
+    pub(super) struct Usage {
+        pub allocated_bytes: u64,
+        pub allocations: u64,
+        pub peak_bytes: u64,
+    }
+
+    pub(super) fn start_stage() -> Snapshot {
+        PEAK.store(LIVE.load(Ordering::Relaxed), Ordering::Relaxed);
+        Snapshot {
+            allocated: ALLOCATED.load(Ordering::Relaxed),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// `None` when nothing was counted, i.e. the allocator isn't installed
+    pub(super) fn finish_stage(before: Snapshot) -> Option<Usage> {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before.allocations;
+        if allocations == 0 && before.allocations == 0 {
+            return None;
+        }
+        Some(Usage {
+            allocated_bytes: ALLOCATED.load(Ordering::Relaxed) - before.allocated,
+            allocations,
+            peak_bytes: PEAK.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_returns_the_stage_result() {
+        let mut memory = MemoryStats::default();
+        let value = memory.record(Stage::Parse, || "x".repeat(4096).len());
+
+        assert_eq!(value, 4096);
+        // The test binary doesn't install the counting allocator
+        assert!(memory.is_empty());
+        assert_eq!(memory.peak_bytes(), 0);
+    }
+}
@@ -1,19 +1,82 @@
 //! Constant obfuscation using mathematical expressions
+//!
+//! Plain arithmetic (`(40 + 2)`) folds back to the literal under any static
+//! simplifier. When an environment anchor is bound, integers are instead
+//! derived from values only known by running the script: bytes of the anchor
+//! string and a hash computed over it in a loop at load time.
 
+use super::rng::{self, BoxedRng};
 use super::ObfuscatedConstant;
-use crate::parser::NumericLiteral;
+use crate::parser::{parse_number, safe_integer, NumericLiteral, Sensitivity, StringLiteral};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use rand::distributions::Alphanumeric;
 use rand::Rng;
+use std::cell::RefCell;
+
+/// Local holding the environment anchor string in generated code
+pub const ENVIRONMENT_KEY: &str = "_EK";
+
+/// Local holding the load-time hash of the environment anchor
+pub const ENVIRONMENT_HASH: &str = "_EH";
+
+/// Initial state of the anchor hash
+pub const ANCHOR_HASH_INIT: u32 = 0x811C_9DC5;
+
+/// Largest magnitude expressed through 32-bit `bit32` operations
+const ENVIRONMENT_MAX: u64 = 1 << 31;
+
+/// Hash of the anchor string
+///
+/// Mirrors the generated Luau loop:
+/// `h = bit32.bxor(bit32.lrotate(h, 5), string.byte(anchor, i))`.
+pub fn anchor_hash(anchor: &str) -> u32 {
+    anchor
+        .bytes()
+        .fold(ANCHOR_HASH_INIT, |h, b| h.rotate_left(5) ^ u32::from(b))
+}
 
 /// Constant obfuscator
 pub struct ConstantObfuscator {
     complexity: usize,
+    rng: RefCell<BoxedRng>,
+    /// Anchor string for runtime-derived integers
+    environment: Option<String>,
 }
 
 impl ConstantObfuscator {
     pub fn new() -> Self {
-        Self { complexity: 2 }
+        Self::with_rng(rng::entropy())
+    }
+
+    /// Create a constant obfuscator with a fixed seed for reproducible output
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(rng::seeded(seed))
+    }
+
+    /// Create a constant obfuscator drawing from `rng`
+    pub fn with_rng(rng: BoxedRng) -> Self {
+        Self {
+            complexity: 2,
+            rng: RefCell::new(rng),
+            environment: None,
+        }
+    }
+
+    /// Derive integers from the environment anchor instead of plain arithmetic
+    ///
+    /// The generated script must define `_EK`/`_EH` for the same anchor
+    /// (see `ScriptAssembler::render_environment_anchor`).
+    pub fn bind_environment(mut self, anchor: &str) -> Self {
+        self.environment = Some(anchor.to_string()).filter(|a| !a.is_empty());
+        self
+    }
+
+    /// Random anchor string for scripts without a caller-supplied one
+    pub fn generate_anchor(&self) -> String {
+        let mut rng = self.rng.borrow_mut();
+        let len = rng.gen_range(16..32);
+        (0..len).map(|_| char::from(rng.sample(Alphanumeric))).collect()
     }
 
     /// Obfuscate numeric constants
@@ -24,6 +87,23 @@ impl ConstantObfuscator {
             .collect()
     }
 
+    /// Convert numeric literals into pool-ready literals
+    ///
+    /// The pool stores numbers as encrypted text decoded with `tonumber` at
+    /// runtime, so Luau-only syntax such as digit separators and binary
+    /// literals is normalized (to decimal, so `0xFF` and `255` share a slot).
+    pub fn pool_literals(&self, numbers: &[NumericLiteral]) -> Vec<StringLiteral> {
+        numbers
+            .iter()
+            .map(|num_lit| StringLiteral {
+                value: num_lit.normalized(),
+                line: num_lit.line,
+                column: num_lit.column,
+                sensitivity: Sensitivity::Medium,
+            })
+            .collect()
+    }
+
     /// Obfuscate a single number
     fn obfuscate_number(&self, num_lit: &NumericLiteral) -> Result<ObfuscatedConstant> {
         let value = if num_lit.is_float {
@@ -36,16 +116,21 @@ impl ConstantObfuscator {
             original: num_lit.value.clone(),
             obfuscated_expr: value,
             line: num_lit.line,
+            column: num_lit.column,
         })
     }
 
     /// Obfuscate an integer
     fn obfuscate_integer(&self, value: &str) -> Result<String> {
-        let num: i64 = value
-            .parse()
-            .map_err(|e| ObfuscatorError::ObfuscationError(format!("Invalid integer: {}", e)))?;
+        let num = integer_value(value)?;
+
+        if let Some(anchor) = &self.environment {
+            if num.unsigned_abs() < ENVIRONMENT_MAX {
+                return Ok(self.environment_integer(num, anchor));
+            }
+        }
 
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         
         // Generate random operations that result in the target number
         match rng.gen_range(0..4) {
@@ -72,13 +157,53 @@ impl ConstantObfuscator {
         }
     }
 
+    /// Express an integer through load-time values of the anchor
+    fn environment_integer(&self, num: i64, anchor: &str) -> String {
+        let mut rng = self.rng.borrow_mut();
+        let magnitude = num.unsigned_abs() as u32;
+        let hash = anchor_hash(anchor);
+
+        let expr = match rng.gen_range(0..3) {
+            0 => {
+                // bit32.bxor(m ~ h, _EH)
+                format!("bit32.bxor({}, {})", magnitude ^ hash, ENVIRONMENT_HASH)
+            }
+            1 => {
+                // (m - b) + string.byte(_EK, k)
+                let bytes = anchor.as_bytes();
+                let k = rng.gen_range(0..bytes.len());
+                format!(
+                    "({} + string.byte({}, {}))",
+                    i64::from(magnitude) - i64::from(bytes[k]),
+                    ENVIRONMENT_KEY,
+                    k + 1
+                )
+            }
+            _ => {
+                // bit32.bxor((m + n) ~ h, _EH) - #"<n chars>"
+                let len = rng.gen_range(3..12);
+                let filler: String = (0..len).map(|_| char::from(rng.sample(Alphanumeric))).collect();
+                format!(
+                    "(bit32.bxor({}, {}) - #\"{}\")",
+                    (magnitude + len) ^ hash,
+                    ENVIRONMENT_HASH,
+                    filler
+                )
+            }
+        };
+
+        if num < 0 {
+            format!("(-{})", expr)
+        } else {
+            expr
+        }
+    }
+
     /// Obfuscate a float
     fn obfuscate_float(&self, value: &str) -> Result<String> {
-        let num: f64 = value
-            .parse()
-            .map_err(|e| ObfuscatorError::ObfuscationError(format!("Invalid float: {}", e)))?;
+        let num = float_value(value)?;
 
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         
         // For floats, use simpler operations to avoid precision loss
         match rng.gen_range(0..2) {
@@ -107,12 +232,13 @@ impl ConstantObfuscator {
             original: num_lit.value.clone(),
             obfuscated_expr: value,
             line: num_lit.line,
+            column: num_lit.column,
         })
     }
 
     fn obfuscate_integer_complex(&self, value: &str) -> Result<String> {
-        let num: i64 = value.parse().unwrap();
-        let mut rng = rand::thread_rng();
+        let num = integer_value(value)?;
+        let mut rng = self.rng.borrow_mut();
         
         // Multi-layer obfuscation
         let r1 = rng.gen_range(1..100);
@@ -130,8 +256,8 @@ impl ConstantObfuscator {
     }
 
     fn obfuscate_float_complex(&self, value: &str) -> Result<String> {
-        let num: f64 = value.parse().unwrap();
-        let mut rng = rand::thread_rng();
+        let num = float_value(value)?;
+        let mut rng = self.rng.borrow_mut();
         
         let r1 = rng.gen_range(1.0..50.0);
         let r2 = rng.gen_range(2.0..5.0);
@@ -140,6 +266,20 @@ impl ConstantObfuscator {
     }
 }
 
+/// Value of an integer literal in any Luau notation (`42`, `0xFF`, `1e5`)
+fn integer_value(value: &str) -> Result<i64> {
+    parse_number(value).and_then(safe_integer).ok_or_else(|| {
+        ObfuscatorError::ObfuscationError(format!("Invalid integer: {}", value)).into()
+    })
+}
+
+/// Value of a number literal in any Luau notation
+fn float_value(value: &str) -> Result<f64> {
+    parse_number(value).ok_or_else(|| {
+        ObfuscatorError::ObfuscationError(format!("Invalid float: {}", value)).into()
+    })
+}
+
 impl Default for ConstantObfuscator {
     fn default() -> Self {
         Self::new()
@@ -185,6 +325,28 @@ mod tests {
         assert!(obfuscated.obfuscated_expr.contains('('));
     }
 
+    #[test]
+    fn test_hex_and_scientific_literals() {
+        let obfuscator = ConstantObfuscator::new();
+
+        let literals = [
+            ("0xFF", false),
+            ("0b1010", false),
+            ("1e5", false),
+            ("2.5e-3", true),
+            // Past 2^53 integers aren't exact
+            ("1e20", true),
+        ];
+        for (text, is_float) in literals {
+            let num_lit = NumericLiteral::new(text, 1, 0);
+            assert_eq!(num_lit.is_float, is_float, "{}", text);
+            let obfuscated = obfuscator.obfuscate_number(&num_lit).unwrap();
+            assert_eq!(obfuscated.original, text);
+        }
+        assert_eq!(NumericLiteral::new("0xFF", 1, 0).normalized(), "255");
+        assert_eq!(NumericLiteral::new("1e5", 1, 0).normalized(), "100000");
+    }
+
     #[test]
     fn test_complex_obfuscation() {
         let obfuscator = ConstantObfuscator::new();
@@ -203,6 +365,104 @@ mod tests {
         assert!(op_count >= 3);
     }
 
+    #[test]
+    fn test_seeded_obfuscation_is_deterministic() {
+        let num_lit = NumericLiteral {
+            value: "1337".to_string(),
+            line: 1,
+            column: 0,
+            is_float: false,
+        };
+
+        let a = ConstantObfuscator::with_seed(42).obfuscate_number(&num_lit).unwrap();
+        let b = ConstantObfuscator::with_seed(42).obfuscate_number(&num_lit).unwrap();
+
+        assert_eq!(a.obfuscated_expr, b.obfuscated_expr);
+    }
+
+    /// Evaluate an environment expression the way the Luau runtime would
+    fn eval_environment(expr: &str, anchor: &str) -> i64 {
+        let negated = expr
+            .strip_prefix("(-")
+            .filter(|e| e.starts_with('(') || e.starts_with("bit32"))
+            .and_then(|e| e.strip_suffix(')'));
+        if let Some(inner) = negated {
+            return -eval_environment(inner, anchor);
+        }
+        let hash = i64::from(anchor_hash(anchor));
+        let masked = |e: &str| -> i64 {
+            let arg = e.trim_start_matches("bit32.bxor(").split(',').next().unwrap();
+            arg.parse::<i64>().unwrap() ^ hash
+        };
+
+        if expr.starts_with("bit32.bxor(") {
+            masked(expr)
+        } else if let Some(rest) = expr.strip_prefix("(bit32.bxor(") {
+            let filler = rest.split('"').nth(1).unwrap();
+            masked(rest) - filler.len() as i64
+        } else {
+            let inner = expr.trim_start_matches('(');
+            let base: i64 = inner.split(' ').next().unwrap().parse().unwrap();
+            let k: usize = inner
+                .rsplit(", ")
+                .next()
+                .unwrap()
+                .trim_end_matches(')')
+                .parse()
+                .unwrap();
+            base + i64::from(anchor.as_bytes()[k - 1])
+        }
+    }
+
+    #[test]
+    fn test_environment_integers_use_anchor() {
+        let anchor = "k3yAnch0r";
+        let obfuscator = ConstantObfuscator::with_seed(7).bind_environment(anchor);
+
+        for value in [0i64, 1, 42, -17, 65535, 2_000_000_000] {
+            let num_lit = NumericLiteral {
+                value: value.to_string(),
+                line: 1,
+                column: 0,
+                is_float: false,
+            };
+            for _ in 0..8 {
+                let expr = obfuscator.obfuscate_number(&num_lit).unwrap().obfuscated_expr;
+                assert!(
+                    expr.contains(ENVIRONMENT_HASH) || expr.contains(ENVIRONMENT_KEY),
+                    "{} should depend on the anchor: {}",
+                    value,
+                    expr
+                );
+                assert_eq!(eval_environment(&expr, anchor), value, "{}", expr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_anchor_hash_matches_luau_loop() {
+        // h = bit32.bxor(bit32.lrotate(h, 5), byte) over "ab"
+        let h1 = ANCHOR_HASH_INIT.rotate_left(5) ^ 0x61;
+        let h2 = h1.rotate_left(5) ^ 0x62;
+        assert_eq!(anchor_hash("ab"), h2);
+        assert_eq!(anchor_hash(""), ANCHOR_HASH_INIT);
+    }
+
+    #[test]
+    fn test_pool_literals_normalize_separators() {
+        let obfuscator = ConstantObfuscator::new();
+        let numbers = vec![NumericLiteral {
+            value: "1_000_000".to_string(),
+            line: 3,
+            column: 0,
+            is_float: false,
+        }];
+
+        let literals = obfuscator.pool_literals(&numbers);
+        assert_eq!(literals[0].value, "1000000");
+        assert_eq!(literals[0].line, 3);
+    }
+
     #[test]
     fn test_batch_obfuscation() {
         let obfuscator = ConstantObfuscator::new();
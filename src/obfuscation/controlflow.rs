@@ -1,38 +1,241 @@
 //! Control flow flattening transformation
+//!
+//! Every flattened block becomes a case of one dispatcher, so the function
+//! around it gets all their statements, locals and nesting at once. The
+//! flattener measures each case and leaves a block as written when adding
+//! it would take the dispatcher past [`FlatteningLimits`], which keep well
+//! under what Luau compiles for one function.
 
+use super::rng::{self, BoxedRng};
 use super::FlattenedBlock;
-use crate::analysis::{BasicBlock, ControlFlowGraph};
+use crate::analysis::{BasicBlock, ControlFlowGraph, ScopeAnalyzer, Terminator};
+use crate::diagnostics::{Diagnostic, Severity, Span};
+use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use rand::seq::SliceRandom;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
+/// Statements one dispatcher may hold, dispatch included
+pub const MAX_DISPATCH_STATEMENTS: usize = 1_000;
+
+/// Locals one dispatcher may declare, leaving room under Luau's 200
+/// registers for temporaries
+pub const MAX_DISPATCH_LOCALS: usize = 150;
+
+/// Scope nesting a case may reach, dispatch loop included
+pub const MAX_DISPATCH_DEPTH: usize = 100;
+
+/// Size of a flattened function, or of one of its cases
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunctionMetrics {
+    /// Statements, counting the case test and the state update
+    pub statements: usize,
+    /// Locals declared, the state variable included for a whole function
+    pub locals: usize,
+    /// Deepest scope nesting, the dispatch loop included
+    pub depth: usize,
+}
+
+impl FunctionMetrics {
+    /// The dispatcher with `case` added
+    fn with(self, case: FunctionMetrics) -> Self {
+        Self {
+            statements: self.statements + case.statements,
+            locals: self.locals + case.locals,
+            depth: self.depth.max(case.depth),
+        }
+    }
+
+    /// Which limit these metrics break, if any
+    fn exceeded(&self, limits: &FlatteningLimits) -> Option<String> {
+        if self.statements > limits.max_statements {
+            Some(format!("over {} statements", limits.max_statements))
+        } else if self.locals > limits.max_locals {
+            Some(format!("over {} locals", limits.max_locals))
+        } else if self.depth > limits.max_depth {
+            Some(format!("nested over {} levels", limits.max_depth))
+        } else {
+            None
+        }
+    }
+}
+
+/// How large one dispatcher may grow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlatteningLimits {
+    pub max_statements: usize,
+    pub max_locals: usize,
+    pub max_depth: usize,
+}
+
+impl Default for FlatteningLimits {
+    fn default() -> Self {
+        Self {
+            max_statements: MAX_DISPATCH_STATEMENTS,
+            max_locals: MAX_DISPATCH_LOCALS,
+            max_depth: MAX_DISPATCH_DEPTH,
+        }
+    }
+}
+
+/// Block left as written because flattening it would break a limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedBlock {
+    pub block_id: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub reason: String,
+}
+
+impl SkippedBlock {
+    /// The block as a `flatten-limit` note; it still gets every other pass
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("flatten-limit", Severity::Note, self.reason.clone())
+            .at(Span::lines(self.start_line, self.end_line))
+            .with_suggestion("split the function so each part stays within the limits")
+    }
+}
+
+impl std::fmt::Display for SkippedBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lines {}-{}: {}", self.start_line, self.end_line, self.reason)
+    }
+}
+
+/// A flattened function: its cases, what it left out and its final size
+#[derive(Debug, Clone, Default)]
+pub struct Flattening {
+    pub blocks: Vec<FlattenedBlock>,
+    pub skipped: Vec<SkippedBlock>,
+    /// Blocks that failed to flatten in best-effort mode, with the error
+    pub failed: Vec<SkippedBlock>,
+    pub metrics: FunctionMetrics,
+}
+
 /// Control flow flattener
 pub struct ControlFlowFlattener {
     state_var_name: String,
+    rng: RefCell<BoxedRng>,
+    limits: FlatteningLimits,
+    best_effort: bool,
 }
 
 impl ControlFlowFlattener {
     pub fn new() -> Self {
+        Self::with_rng(rng::entropy())
+    }
+
+    /// Create a flattener with a fixed seed for reproducible case ordering
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(rng::seeded(seed))
+    }
+
+    /// Create a flattener ordering cases with `rng`
+    pub fn with_rng(rng: BoxedRng) -> Self {
         Self {
             state_var_name: "_state".to_string(),
+            rng: RefCell::new(rng),
+            limits: FlatteningLimits::default(),
+            best_effort: false,
         }
     }
 
+    /// Replace the default dispatcher limits
+    pub fn with_limits(mut self, limits: FlatteningLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Leave a block that fails to flatten as written instead of failing
+    pub fn with_best_effort(mut self, best_effort: bool) -> Self {
+        self.best_effort = best_effort;
+        self
+    }
+
     /// Flatten control flow graph into state machine
-    pub fn flatten(&self, cfg: &ControlFlowGraph) -> Result<Vec<FlattenedBlock>> {
-        let mut flattened = Vec::new();
+    ///
+    /// Blocks that would take the dispatcher past its limits are skipped.
+    pub fn flatten(&self, cfg: &ControlFlowGraph) -> Result<Flattening> {
+        let mut flattening = Flattening {
+            // The state variable
+            metrics: FunctionMetrics { locals: 1, ..Default::default() },
+            ..Default::default()
+        };
+
+        // Visit blocks in id order so output doesn't depend on HashMap iteration
+        let mut block_ids: Vec<&usize> = cfg.blocks.keys().collect();
+        block_ids.sort();
 
         // Convert each basic block into a state machine case
-        for (block_id, block) in &cfg.blocks {
-            let state_code = self.generate_state_case(*block_id, block, cfg)?;
-            
-            flattened.push(FlattenedBlock {
+        for block_id in block_ids {
+            let block = &cfg.blocks[block_id];
+            let state_code = match self.generate_state_case(*block_id, block, cfg) {
+                Ok(code) => code,
+                Err(e) if self.best_effort => {
+                    flattening.failed.push(SkippedBlock {
+                        block_id: *block_id,
+                        start_line: block.start_line,
+                        end_line: block.end_line,
+                        reason: format!("{:#}", e),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let measured = self.measure_case(block, &state_code);
+            let exceeded = match measured {
+                Some(case) => flattening.metrics.with(case).exceeded(&self.limits),
+                None => Some("case could not be measured".to_string()),
+            };
+            if let Some(reason) = exceeded {
+                tracing::debug!("Leaving block {} as written: {}", block_id, reason);
+                flattening.skipped.push(SkippedBlock {
+                    block_id: *block_id,
+                    start_line: block.start_line,
+                    end_line: block.end_line,
+                    reason: format!("dispatcher would grow {}", reason),
+                });
+                continue;
+            }
+            flattening.metrics = flattening.metrics.with(measured.unwrap_or_default());
+
+            flattening.blocks.push(FlattenedBlock {
                 block_id: *block_id,
+                start_line: block.start_line,
+                end_line: block.end_line,
                 state_machine_code: state_code,
             });
         }
 
-        log::debug!("Flattened {} control flow blocks", flattened.len());
-        Ok(flattened)
+        tracing::debug!(
+            "Flattened {} control flow blocks ({} skipped, {:?})",
+            flattening.blocks.len(),
+            flattening.skipped.len(),
+            flattening.metrics
+        );
+        Ok(flattening)
+    }
+
+    /// Statements, locals and nesting `case` adds to the dispatcher
+    fn measure_case(&self, block: &BasicBlock, case: &str) -> Option<FunctionMetrics> {
+        let scopes = ScopeAnalyzer::new().analyze_source(case).ok()?;
+        // Scopes are created parent-first; the chunk is depth 0 and the case's
+        // `if` body depth 1
+        let mut depths = vec![0usize; scopes.len()];
+        for scope in &scopes {
+            if let Some(parent) = scope.parent {
+                depths[scope.id] = depths[parent] + 1;
+            }
+        }
+        let body = scopes.iter().find(|scope| scope.parent == Some(0))?;
+        Some(FunctionMetrics {
+            statements: block.statements.len() + 2,
+            locals: body.variables.len(),
+            // Plus the dispatch loop
+            depth: depths.iter().max().copied().unwrap_or(0) + 1,
+        })
     }
 
     /// Generate state machine case for a basic block
@@ -44,20 +247,39 @@ impl ControlFlowFlattener {
     ) -> Result<String> {
         let mut code = format!("if {} == {} then\n", self.state_var_name, block_id);
         
-        // Add block body placeholder
-        code.push_str(&format!("    -- Block {} body goes here\n", block_id));
+        // Block body
+        for statement in &block.statements {
+            for line in statement.code.lines() {
+                code.push_str(&format!("    {}\n", line));
+            }
+        }
         
         // Determine next state
-        if block.successors.is_empty() {
+        if let Terminator::Return(ret) = &block.terminator {
+            // Must be the last statement of the case
+            code.push_str(&format!("    {}\n", ret));
+        } else if let (Terminator::Branch(condition), Some((if_true, if_false))) =
+            (&block.terminator, cfg.branch_targets(block_id))
+        {
+            code.push_str(&format!(
+                "    if {} then\n        {} = {}\n    else\n        {} = {}\n    end\n",
+                condition, self.state_var_name, if_true, self.state_var_name, if_false
+            ));
+        } else if let Terminator::Branch(condition) = &block.terminator {
+            // Falling through to one successor would drop the other branch
+            return Err(ObfuscatorError::ObfuscationError(format!(
+                "Conditional block {} (`{}`) has no recorded branch targets",
+                block_id, condition
+            ))
+            .into());
+        } else if block.successors.is_empty() {
             // Terminal block
             code.push_str(&format!("    {} = -1 -- exit\n", self.state_var_name));
         } else if block.successors.len() == 1 {
             // Single successor (unconditional jump)
             code.push_str(&format!("    {} = {}\n", self.state_var_name, block.successors[0]));
         } else {
-            // Multiple successors (conditional jump)
-            // For now, just pick first successor
-            // In a full implementation, this would preserve branch conditions
+            // Multiple successors without a recorded condition
             code.push_str(&format!(
                 "    {} = {} -- conditional branch\n",
                 self.state_var_name, block.successors[0]
@@ -82,8 +304,12 @@ impl ControlFlowFlattener {
         // Create state machine loop
         code.push_str(&format!("while {} >= 0 do\n", self.state_var_name));
         
+        // Shuffle case order so dispatch order doesn't mirror source order
+        let mut cases: Vec<&FlattenedBlock> = flattened.iter().collect();
+        cases.shuffle(&mut *self.rng.borrow_mut());
+        
         // Add all state cases
-        for flattened_block in flattened {
+        for flattened_block in cases {
             code.push_str("    ");
             code.push_str(&flattened_block.state_machine_code);
         }
@@ -124,6 +350,7 @@ mod tests {
                 end_line: 5,
                 successors: vec![1],
                 predecessors: vec![],
+                ..Default::default()
             },
         );
         
@@ -135,6 +362,7 @@ mod tests {
                 end_line: 10,
                 successors: vec![2, 3],
                 predecessors: vec![0],
+                ..Default::default()
             },
         );
         
@@ -146,6 +374,7 @@ mod tests {
                 end_line: 15,
                 successors: vec![],
                 predecessors: vec![1],
+                ..Default::default()
             },
         );
 
@@ -161,7 +390,7 @@ mod tests {
         let flattener = ControlFlowFlattener::new();
         let cfg = create_test_cfg();
         
-        let flattened = flattener.flatten(&cfg).unwrap();
+        let flattened = flattener.flatten(&cfg).unwrap().blocks;
         
         assert_eq!(flattened.len(), 3);
         assert!(flattened[0].state_machine_code.contains("if _state == 0"));
@@ -171,7 +400,7 @@ mod tests {
     fn test_state_machine_generation() {
         let flattener = ControlFlowFlattener::new();
         let cfg = create_test_cfg();
-        let flattened = flattener.flatten(&cfg).unwrap();
+        let flattened = flattener.flatten(&cfg).unwrap().blocks;
         
         let state_machine = flattener.generate_state_machine(&flattened, 0);
         
@@ -180,6 +409,19 @@ mod tests {
         assert!(state_machine.contains("end"));
     }
 
+    #[test]
+    fn test_seeded_state_machine_is_deterministic() {
+        let cfg = create_test_cfg();
+
+        let first = ControlFlowFlattener::with_seed(99);
+        let second = ControlFlowFlattener::with_seed(99);
+
+        let a = first.generate_state_machine(&first.flatten(&cfg).unwrap().blocks, 0);
+        let b = second.generate_state_machine(&second.flatten(&cfg).unwrap().blocks, 0);
+
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn test_terminal_block() {
         let flattener = ControlFlowFlattener::new();
@@ -193,6 +435,7 @@ mod tests {
                 end_line: 5,
                 successors: vec![], // No successors = terminal
                 predecessors: vec![],
+                ..Default::default()
             },
         );
 
@@ -202,11 +445,67 @@ mod tests {
             exit_blocks: vec![0],
         };
         
-        let flattened = flattener.flatten(&cfg).unwrap();
+        let flattened = flattener.flatten(&cfg).unwrap().blocks;
         
         assert!(flattened[0].state_machine_code.contains("_state = -1"));
     }
 
+    #[test]
+    fn test_flattened_branches_keep_conditions() {
+        let source = "local x = tick()\nif x > 5 then\n    print(\"big\")\nelse\n    return\nend\nprint(x)\n";
+        let parsed = crate::parser::LuauParser::new().parse(source).unwrap();
+        let cfg = crate::analysis::ControlFlowAnalyzer::new().analyze(&parsed).unwrap();
+
+        let flattener = ControlFlowFlattener::with_seed(1);
+        let flattened = flattener.flatten(&cfg).unwrap().blocks;
+        let entry = &flattened[cfg.entry_block].state_machine_code;
+        let (if_true, if_false) = cfg.branch_targets(cfg.entry_block).unwrap();
+
+        assert!(entry.contains("    local x = tick()\n"));
+        assert!(entry.contains(&format!(
+            "    if x > 5 then\n        _state = {}\n    else\n        _state = {}\n    end\n",
+            if_true, if_false
+        )));
+        assert!(flattened[if_false].state_machine_code.ends_with("    return\nend\n"));
+    }
+
+    #[test]
+    fn test_skips_blocks_past_dispatcher_limits() {
+        let declare = |prefix: &str, count: usize| {
+            (0..count).map(|i| format!("local {}{} = {}\n", prefix, i, i)).collect::<String>()
+        };
+        let source = format!(
+            "{}if tick() > 5 then\n    for i = 1, 2 do for j = 1, 2 do for k = 1, 2 do print(k) end end end\nend\n{}print(a0, b0)\n",
+            declare("a", 3),
+            declare("b", 2)
+        );
+        let parsed = crate::parser::LuauParser::new().parse(&source).unwrap();
+        let cfg = crate::analysis::ControlFlowAnalyzer::new().analyze(&parsed).unwrap();
+
+        // Everything fits by default
+        let unlimited = ControlFlowFlattener::with_seed(1).flatten(&cfg).unwrap();
+        assert!(unlimited.skipped.is_empty());
+        assert_eq!(unlimited.blocks.len(), cfg.blocks.len());
+        assert_eq!(unlimited.metrics.locals, 6);
+
+        let limited = ControlFlowFlattener::with_seed(1)
+            .with_limits(FlatteningLimits {
+                max_locals: 4,
+                max_depth: 4,
+                ..Default::default()
+            })
+            .flatten(&cfg)
+            .unwrap();
+        // The entry block's three locals fit, the later two would not, and
+        // the loop nest is too deep; the rest is still flattened
+        assert!(limited.metrics.locals <= 4 && limited.metrics.depth <= 4);
+        assert_eq!(limited.blocks.len() + limited.skipped.len(), cfg.blocks.len());
+        assert!(limited.blocks.iter().any(|b| b.block_id == cfg.entry_block));
+        let reasons: Vec<&str> = limited.skipped.iter().map(|s| s.reason.as_str()).collect();
+        assert!(reasons.contains(&"dispatcher would grow over 4 locals"));
+        assert!(reasons.contains(&"dispatcher would grow nested over 4 levels"));
+    }
+
     #[test]
     fn test_opaque_predicate() {
         let flattener = ControlFlowFlattener::new();
@@ -216,4 +515,22 @@ mod tests {
         assert!(predicate.contains("_state = 1"));
         assert!(predicate.contains("_state = 2"));
     }
+
+    #[test]
+    fn test_best_effort_leaves_failing_blocks_as_written() {
+        let mut cfg = create_test_cfg();
+        // A branch whose edges were lost can't be dispatched correctly
+        cfg.blocks.get_mut(&0).unwrap().terminator = Terminator::Branch("ready".to_string());
+
+        assert!(ControlFlowFlattener::with_seed(1).flatten(&cfg).is_err());
+
+        let flattening = ControlFlowFlattener::with_seed(1)
+            .with_best_effort(true)
+            .flatten(&cfg)
+            .unwrap();
+        assert_eq!(flattening.failed.len(), 1);
+        assert_eq!(flattening.failed[0].block_id, 0);
+        assert!(flattening.failed[0].reason.contains("no recorded branch targets"));
+        assert_eq!(flattening.blocks.len(), 2);
+    }
 }
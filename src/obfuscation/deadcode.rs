@@ -1,48 +1,253 @@
 //! Dead code injection for analysis confusion
+//!
+//! With an `OverheadBudget`, snippets are picked round-robin across kinds
+//! (traps, opaque predicates and fake functions first) until the budget is
+//! spent, so a tight budget still gets a mix of every kind.
+//!
+//! Traps are honeypots: local functions named like license bypass points
+//! (`isLicensed`, `skipLicenseCheck`, ...) that report success but call
+//! `_license_ok`, which fires the tamper response (and its violation report)
+//! on the next resumption. Nothing in the script calls them; they are mixed
+//! in among the fake functions so they read like the real thing. Names the
+//! script already uses are never taken, so a trap can't shadow anything.
 
+use super::rng::{self, BoxedRng};
 use crate::parser::ParseResult;
+use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use full_moon::tokenizer::{tokens, TokenType};
+use rand::seq::SliceRandom;
 use rand::Rng;
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+/// Runtime function traps call; it returns `true` and triggers `_tamper`
+pub const TRAP_FUNCTION: &str = "_license_ok";
+
+/// Names a cracker looks for when hunting for the license check
+const TRAP_NAMES: &[&str] = &[
+    "isLicensed",
+    "checkLicense",
+    "validateKey",
+    "verifyPurchase",
+    "isWhitelisted",
+    "hasPremium",
+    "isOwner",
+    "skipLicenseCheck",
+    "bypassAuth",
+    "setDebugMode",
+];
+
+/// Cap on the bytes dead code may add, as a share of the input size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverheadBudget {
+    /// e.g. 0.25 for `--max-overhead 25%`
+    pub max_overhead: f32,
+    pub input_bytes: usize,
+}
+
+impl OverheadBudget {
+    pub fn new(max_overhead: f32, input_bytes: usize) -> Self {
+        Self {
+            max_overhead: max_overhead.max(0.0),
+            input_bytes,
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        (self.input_bytes as f64 * self.max_overhead as f64) as usize
+    }
+}
+
+/// Kinds of snippet, most confusing per byte first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnippetKind {
+    Trap,
+    OpaquePredicate,
+    Function,
+    Calculation,
+    Loop,
+    Assignment,
+}
+
+const SNIPPET_PRIORITY: [SnippetKind; 6] = [
+    SnippetKind::Trap,
+    SnippetKind::OpaquePredicate,
+    SnippetKind::Function,
+    SnippetKind::Calculation,
+    SnippetKind::Loop,
+    SnippetKind::Assignment,
+];
 
 /// Dead code injector
 pub struct DeadCodeInjector {
     density: f32, // 0.0 to 1.0
+    /// Honeypot functions to mix in
+    traps: usize,
+    rng: RefCell<BoxedRng>,
 }
 
 impl DeadCodeInjector {
     pub fn new(density: f32) -> Self {
+        Self::with_rng(density, rng::entropy())
+    }
+
+    /// Create a dead code injector with a fixed seed for reproducible output
+    pub fn with_seed(density: f32, seed: u64) -> Self {
+        Self::with_rng(density, rng::seeded(seed))
+    }
+
+    /// Create a dead code injector drawing from `rng`
+    pub fn with_rng(density: f32, rng: BoxedRng) -> Self {
         Self {
             density: density.clamp(0.0, 1.0),
+            traps: 0,
+            rng: RefCell::new(rng),
         }
     }
 
+    /// Mix up to `count` trap functions in with the snippets (fewer if the
+    /// script already uses the bait names)
+    pub fn with_traps(mut self, count: usize) -> Self {
+        self.traps = count;
+        self
+    }
+
     /// Generate dead code snippets
     pub fn generate(&self, parse_result: &ParseResult) -> Result<Vec<String>> {
+        let snippets: Vec<String> = self
+            .generate_kinds(parse_result)?
+            .into_iter()
+            .map(|(_, snippet)| snippet)
+            .collect();
+
+        tracing::debug!("Generated {} dead code snippets", snippets.len());
+        Ok(snippets)
+    }
+
+    /// Generate dead code snippets totalling at most `max_bytes`
+    ///
+    /// Each round takes the next snippet of every kind that still fits, in
+    /// priority order; kept snippets stay in generation order so they are
+    /// spread over the script as before.
+    pub fn generate_within(
+        &self,
+        parse_result: &ParseResult,
+        max_bytes: usize,
+    ) -> Result<Vec<String>> {
+        let candidates = self.generate_kinds(parse_result)?;
+        let total = candidates.len();
+
+        let mut queues: Vec<Vec<usize>> = SNIPPET_PRIORITY
+            .iter()
+            .map(|kind| {
+                (0..candidates.len())
+                    .rev()
+                    .filter(|&i| candidates[i].0 == *kind)
+                    .collect()
+            })
+            .collect();
+        let mut keep = vec![false; candidates.len()];
+        let mut used = 0;
+        loop {
+            let mut progressed = false;
+            for queue in &mut queues {
+                let Some(&i) = queue.last() else {
+                    continue;
+                };
+                let size = candidates[i].1.len() + 1;
+                if used + size <= max_bytes {
+                    used += size;
+                    keep[i] = true;
+                    queue.pop();
+                    progressed = true;
+                } else {
+                    // A kind whose next snippet doesn't fit is done
+                    queue.clear();
+                }
+            }
+            if !progressed {
+                break;
+            }
+        }
+
+        let snippets: Vec<String> = candidates
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, keep)| *keep)
+            .map(|((_, snippet), _)| snippet)
+            .collect();
+        tracing::debug!(
+            "Kept {} of {} dead code snippets within {} bytes",
+            snippets.len(),
+            total,
+            max_bytes
+        );
+        Ok(snippets)
+    }
+
+    fn generate_kinds(&self, parse_result: &ParseResult) -> Result<Vec<(SnippetKind, String)>> {
         let num_snippets = (parse_result.strings.len() as f32 * self.density) as usize;
         
         let mut snippets = Vec::with_capacity(num_snippets);
-        let mut rng = rand::thread_rng();
         
         for _ in 0..num_snippets {
-            let snippet_type = rng.gen_range(0..5);
+            let snippet_type = self.rng.borrow_mut().gen_range(0..5);
             let snippet = match snippet_type {
-                0 => self.generate_fake_calculation(),
-                1 => self.generate_fake_condition(),
-                2 => self.generate_fake_loop(),
-                3 => self.generate_fake_function(),
-                _ => self.generate_fake_assignment(),
+                0 => (SnippetKind::Calculation, self.generate_fake_calculation()),
+                1 => (SnippetKind::OpaquePredicate, self.generate_fake_condition()),
+                2 => (SnippetKind::Loop, self.generate_fake_loop()),
+                3 => (SnippetKind::Function, self.generate_fake_function()),
+                _ => (SnippetKind::Assignment, self.generate_fake_assignment()),
             };
             
             snippets.push(snippet);
         }
-        
-        log::debug!("Generated {} dead code snippets", snippets.len());
+
+        // Traps go anywhere among the rest, so they sit between fake functions
+        if self.traps > 0 {
+            let used = script_names(parse_result)?;
+            let mut names: Vec<&str> = TRAP_NAMES
+                .iter()
+                .copied()
+                .filter(|name| !used.contains(*name))
+                .collect();
+            names.shuffle(&mut *self.rng.borrow_mut());
+            for name in names.into_iter().take(self.traps) {
+                let trap = self.generate_trap(name);
+                let at = self.rng.borrow_mut().gen_range(0..=snippets.len());
+                snippets.insert(at, (SnippetKind::Trap, trap));
+            }
+        }
+
         Ok(snippets)
     }
 
+    /// Generate a bait function `name` that trips the tamper response
+    fn generate_trap(&self, name: &str) -> String {
+        let mut rng = self.rng.borrow_mut();
+        let param = format!("_p{}", rng.gen_range(100..999));
+        let result = format!("_ok{}", rng.gen_range(1000..9999));
+
+        match rng.gen_range(0..3) {
+            0 => format!(
+                "local function {}({}) return {}({}) end",
+                name, param, TRAP_FUNCTION, param
+            ),
+            1 => format!(
+                "local function {}(...) local {} = {}(...) return {} end",
+                name, result, TRAP_FUNCTION, result
+            ),
+            _ => format!(
+                "local function {}({}) return {}({}) == true end",
+                name, param, TRAP_FUNCTION, param
+            ),
+        }
+    }
+
     /// Generate fake calculation that never executes
     fn generate_fake_calculation(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let var1 = format!("_tmp{}", rng.gen_range(1000..9999));
         let var2 = format!("_tmp{}", rng.gen_range(1000..9999));
         let val1 = rng.gen_range(1..100);
@@ -56,7 +261,7 @@ impl DeadCodeInjector {
 
     /// Generate fake conditional that never executes
     fn generate_fake_condition(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let val = rng.gen_range(1..100);
         
         format!(
@@ -67,7 +272,7 @@ impl DeadCodeInjector {
 
     /// Generate fake loop that never executes
     fn generate_fake_loop(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let var = format!("_i{}", rng.gen_range(1000..9999));
         
         format!(
@@ -78,7 +283,7 @@ impl DeadCodeInjector {
 
     /// Generate fake function that never gets called
     fn generate_fake_function(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let fn_name = format!("_fn{}", rng.gen_range(1000..9999));
         let param = format!("_p{}", rng.gen_range(100..999));
         
@@ -90,7 +295,7 @@ impl DeadCodeInjector {
 
     /// Generate fake assignment
     fn generate_fake_assignment(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let var = format!("_var{}", rng.gen_range(1000..9999));
         let val = rng.gen_range(1..100);
         
@@ -99,7 +304,7 @@ impl DeadCodeInjector {
 
     /// Generate fake table operations
     pub fn generate_fake_table(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let tbl_name = format!("_tbl{}", rng.gen_range(1000..9999));
         
         format!(
@@ -110,7 +315,7 @@ impl DeadCodeInjector {
 
     /// Generate fake string operations
     pub fn generate_fake_string(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let var = format!("_str{}", rng.gen_range(1000..9999));
         let chars = ["a", "b", "c", "x", "y", "z"];
         let char = chars[rng.gen_range(0..chars.len())];
@@ -123,7 +328,7 @@ impl DeadCodeInjector {
 
     /// Generate fake metamethod
     pub fn generate_fake_metamethod(&self) -> String {
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let tbl = format!("_mt{}", rng.gen_range(1000..9999));
         
         format!(
@@ -133,6 +338,42 @@ impl DeadCodeInjector {
     }
 }
 
+/// Name of the trap function `snippet` declares, if it is a trap
+pub fn trap_name(snippet: &str) -> Option<&str> {
+    if !snippet.contains(TRAP_FUNCTION) {
+        return None;
+    }
+    snippet.strip_prefix("local function ")?.split('(').next()
+}
+
+/// Every identifier the script uses (names and parameters of its functions
+/// when it was parsed without an AST)
+fn script_names(parse_result: &ParseResult) -> Result<HashSet<String>> {
+    let Some(ast) = &parse_result.ast else {
+        return Ok(parse_result
+            .functions
+            .iter()
+            .flat_map(|f| f.name.iter().flat_map(|name| name.split(['.', ':'])))
+            .chain(
+                parse_result
+                    .functions
+                    .iter()
+                    .flat_map(|f| f.parameters.iter().map(String::as_str)),
+            )
+            .map(str::to_string)
+            .collect());
+    };
+    let all_tokens = tokens(&ast.nodes().to_string())
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+    Ok(all_tokens
+        .iter()
+        .filter_map(|token| match token.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.to_string()),
+            _ => None,
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,6 +398,10 @@ mod tests {
             ],
             numbers: vec![],
             functions: vec![],
+            type_aliases: vec![],
+            directives: vec![],
+            config_block: None,
+            unsupported_statements: vec![],
         }
     }
 
@@ -225,6 +470,78 @@ mod tests {
         assert!(tbl.contains("[1] = nil"));
     }
 
+    #[test]
+    fn test_seeded_generation_is_deterministic() {
+        let parse_result = create_test_parse_result();
+
+        let a = DeadCodeInjector::with_seed(1.0, 7).generate(&parse_result).unwrap();
+        let b = DeadCodeInjector::with_seed(1.0, 7).generate(&parse_result).unwrap();
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_budget_keeps_a_mix_of_kinds() {
+        let mut parse_result = create_test_parse_result();
+        for i in 3..=40 {
+            parse_result.strings.push(StringLiteral {
+                value: format!("test{}", i),
+                line: i,
+                column: 0,
+                sensitivity: crate::parser::Sensitivity::Low,
+            });
+        }
+
+        let all = DeadCodeInjector::with_seed(1.0, 3).generate(&parse_result).unwrap();
+        let budget = OverheadBudget::new(0.25, 1000).max_bytes();
+        let kept = DeadCodeInjector::with_seed(1.0, 3)
+            .generate_within(&parse_result, budget)
+            .unwrap();
+
+        assert!(kept.len() < all.len());
+        assert!(kept.iter().map(|s| s.len() + 1).sum::<usize>() <= budget);
+        // Kept snippets are a subsequence of the unbudgeted output
+        let mut rest = all.iter();
+        assert!(kept.iter().all(|k| rest.any(|s| s == k)));
+        for marker in ["~=", "local function", "= 1, 0"] {
+            assert!(kept.iter().any(|s| s.contains(marker)), "no {} snippet kept", marker);
+        }
+
+        let none = DeadCodeInjector::with_seed(1.0, 3)
+            .generate_within(&parse_result, 0)
+            .unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_traps_blend_in_without_shadowing() {
+        let source = "local function isLicensed() return true end\nprint(hasPremium)\n";
+        let parse_result = crate::parser::LuauParser::new().parse(source).unwrap();
+        let snippets = DeadCodeInjector::with_seed(0.0, 5)
+            .with_traps(TRAP_NAMES.len())
+            .generate(&parse_result)
+            .unwrap();
+
+        let names: Vec<&str> = snippets.iter().filter_map(|s| trap_name(s)).collect();
+        assert_eq!(names.len(), TRAP_NAMES.len() - 2);
+        assert!(!names.contains(&"isLicensed"));
+        assert!(!names.contains(&"hasPremium"));
+        for snippet in &snippets {
+            assert!(snippet.starts_with("local function "));
+            assert!(full_moon::parse(snippet).is_ok(), "{}", snippet);
+        }
+
+        // Traps take their place among the other snippets, first in a budget
+        let mut parse_result = create_test_parse_result();
+        parse_result.strings.extend(parse_result.strings.clone());
+        let snippets = DeadCodeInjector::with_seed(1.0, 5)
+            .with_traps(1)
+            .generate_within(&parse_result, 60)
+            .unwrap();
+        assert_eq!(snippets.iter().filter(|s| trap_name(s).is_some()).count(), 1);
+        assert!(trap_name(&DeadCodeInjector::new(0.1).generate_fake_function()).is_none());
+    }
+
     #[test]
     fn test_zero_density() {
         let injector = DeadCodeInjector::new(0.0);
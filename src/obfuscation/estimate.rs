@@ -0,0 +1,293 @@
+//! Static runtime overhead estimates for a tier
+//!
+//! Models what the protected script will do at load and per frame from the
+//! parse result and tier settings alone, without encrypting anything, so a
+//! customer can compare tiers before buying.
+//!
+//! Costs are rough calibrations from a mid-range Roblox client and are only
+//! meant for comparing settings against each other.
+
+use super::{DecryptionMode, LineSettings, ObfuscationTier};
+use crate::parser::{ParseResult, Sensitivity};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, TokenType};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Frame budget at 60 FPS
+pub const FRAME_BUDGET_MS: f64 = 1000.0 / 60.0;
+
+/// Iterations each loop is assumed to run per frame
+pub const ASSUMED_ITERATIONS_PER_FRAME: usize = 100;
+
+/// Decrypt setup (base64 decode, key schedule), per value
+const DECRYPT_BASE_US: f64 = 12.0;
+/// One 64-byte ChaCha20 block in Luau
+const DECRYPT_PER_BLOCK_US: f64 = 6.0;
+/// Evaluating one inlined constant expression
+const CONSTANT_EVAL_US: f64 = 0.05;
+/// Inlined constant derived from the environment anchor (`bit32` calls)
+const ENVIRONMENT_CONSTANT_EVAL_US: f64 = 0.15;
+/// State-machine dispatch for one iteration of a flattened loop
+const DISPATCH_US: f64 = 0.08;
+/// Compiling one KiB of emitted source
+const COMPILE_US_PER_KB: f64 = 25.0;
+/// Snapshotting globals for the environment guard
+const GUARD_US: f64 = 20.0;
+/// ChaCha20 runtime, guard and tamper handler emitted with every script
+const RUNTIME_BYTES: usize = 6_000;
+/// Average size of one dead code snippet
+const DEAD_CODE_SNIPPET_BYTES: usize = 60;
+/// Table entry overhead around each encrypted value
+const ENCRYPTED_ENTRY_BYTES: usize = 40;
+
+/// Estimated runtime cost of protecting a script with one tier
+#[derive(Debug, Clone, Serialize)]
+pub struct OverheadEstimate {
+    pub tier: String,
+    /// Encrypted values decrypted before the script body runs
+    pub load_decrypts: usize,
+    /// Encrypted values decrypted (once) on first use
+    pub lazy_decrypts: usize,
+    /// Numbers replaced by inline expressions
+    pub obfuscated_constants: usize,
+    /// Loops whose body goes through the flattening dispatcher
+    pub flattened_loops: usize,
+    /// Bytes of never-executed code added
+    pub dead_code_bytes: usize,
+    /// Estimated size of the protected script
+    pub output_bytes: usize,
+    /// Extra time before the script body starts
+    pub startup_ms: f64,
+    /// Total decryption time spread over first uses
+    pub first_use_ms: f64,
+    /// Steady-state extra time per frame
+    pub per_frame_ms: f64,
+}
+
+impl OverheadEstimate {
+    /// Per-frame overhead as a share of the 60 FPS frame budget
+    pub fn frame_budget_percent(&self) -> f64 {
+        self.per_frame_ms / FRAME_BUDGET_MS * 100.0
+    }
+}
+
+/// Estimates overhead for a tier, honoring `--!obfuscate:` directives
+pub struct OverheadEstimator {
+    tier: ObfuscationTier,
+}
+
+impl OverheadEstimator {
+    pub fn new(tier: ObfuscationTier) -> Self {
+        Self { tier }
+    }
+
+    /// Estimate the cost of protecting `source` (already parsed into `parse_result`)
+    pub fn estimate(&self, source: &str, parse_result: &ParseResult) -> Result<OverheadEstimate> {
+        let base = self.tier.settings();
+        let lines = LineSettings::new(&base, &parse_result.directives);
+
+        // Encrypted values and their sizes in bytes
+        let mut encrypted: Vec<usize> = Vec::new();
+        for lit in &parse_result.strings {
+            let settings = lines.at(lit.line);
+            let sensitive = matches!(lit.sensitivity, Sensitivity::High | Sensitivity::Medium);
+            if !settings.encrypt_strings || !(settings.encrypt_all_strings || sensitive) {
+                continue;
+            }
+            match base.string_fragment_size {
+                Some(size) if size > 0 => {
+                    let chars: Vec<char> = lit.value.chars().collect();
+                    encrypted.extend(
+                        chars
+                            .chunks(size)
+                            .map(|chunk| chunk.iter().map(|c| c.len_utf8()).sum::<usize>()),
+                    );
+                }
+                _ => encrypted.push(lit.value.len()),
+            }
+        }
+
+        let constants = parse_result
+            .numbers
+            .iter()
+            .filter(|n| lines.at(n.line).obfuscate_constants)
+            .count();
+
+        let (load, lazy, obfuscated_constants) = if base.use_constant_pool {
+            // Strings and numbers share one lazily decrypted, deduplicated pool
+            let unique_strings: HashSet<&str> = parse_result
+                .strings
+                .iter()
+                .filter(|s| lines.at(s.line).encrypt_strings)
+                .map(|s| s.value.as_str())
+                .collect();
+            let unique_numbers: HashSet<&str> = parse_result
+                .numbers
+                .iter()
+                .filter(|n| lines.at(n.line).obfuscate_constants)
+                .map(|n| n.value.as_str())
+                .collect();
+            let pooled: Vec<usize> = unique_strings
+                .iter()
+                .chain(unique_numbers.iter())
+                .map(|v| v.len())
+                .collect();
+            (Vec::new(), pooled, 0)
+        } else if base.decryption_mode == DecryptionMode::Eager {
+            (encrypted, Vec::new(), constants)
+        } else {
+            (Vec::new(), encrypted, constants)
+        };
+
+        let flattened_loops = count_loops(source)?
+            .into_iter()
+            .filter(|line| lines.at(*line).flatten_control_flow)
+            .count();
+
+        let dead_code_bytes = if base.inject_dead_code {
+            (parse_result.strings.len() as f32 * base.dead_code_density) as usize
+                * DEAD_CODE_SNIPPET_BYTES
+        } else {
+            0
+        };
+
+        let encrypted_bytes: usize = load
+            .iter()
+            .chain(lazy.iter())
+            .map(|len| len.div_ceil(3) * 4 + ENCRYPTED_ENTRY_BYTES)
+            .sum();
+        let output_bytes = source.len() + RUNTIME_BYTES + encrypted_bytes + dead_code_bytes;
+
+        let constant_cost = if base.environment_constants {
+            ENVIRONMENT_CONSTANT_EVAL_US
+        } else {
+            CONSTANT_EVAL_US
+        };
+        let startup_us = output_bytes as f64 / 1024.0 * COMPILE_US_PER_KB
+            + GUARD_US
+            + load.iter().map(|len| decrypt_cost(*len)).sum::<f64>();
+        let first_use_us: f64 = lazy.iter().map(|len| decrypt_cost(*len)).sum();
+        // Every line is assumed to run once per frame; loops run many times
+        let per_frame_us = obfuscated_constants as f64 * constant_cost
+            + (flattened_loops * ASSUMED_ITERATIONS_PER_FRAME) as f64 * DISPATCH_US;
+
+        Ok(OverheadEstimate {
+            tier: format!("{:?}", self.tier).to_lowercase(),
+            load_decrypts: load.len(),
+            lazy_decrypts: lazy.len(),
+            obfuscated_constants,
+            flattened_loops,
+            dead_code_bytes,
+            output_bytes,
+            startup_ms: startup_us / 1000.0,
+            first_use_ms: first_use_us / 1000.0,
+            per_frame_ms: per_frame_us / 1000.0,
+        })
+    }
+}
+
+fn decrypt_cost(len: usize) -> f64 {
+    DECRYPT_BASE_US + len.div_ceil(64).max(1) as f64 * DECRYPT_PER_BLOCK_US
+}
+
+/// Lines of every `for`, `while` and `repeat` loop
+fn count_loops(source: &str) -> Result<Vec<usize>> {
+    let all_tokens = tokens(source)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+    Ok(all_tokens
+        .iter()
+        .filter(|t| {
+            matches!(
+                t.token_type(),
+                TokenType::Symbol {
+                    symbol: Symbol::For | Symbol::While | Symbol::Repeat
+                }
+            )
+        })
+        .map(|t| t.start_position().line())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{scan_directives, NumericLiteral, StringLiteral};
+
+    const SOURCE: &str = r#"
+local secret = "API_KEY_VALUE"
+--!obfuscate: none
+local function onFrame(dt)
+    for i = 1, 10 do
+        print("tick", i)
+    end
+end
+while true do
+    print(42)
+end
+"#;
+
+    fn parse_result() -> ParseResult {
+        let string = |value: &str, line| StringLiteral {
+            value: value.to_string(),
+            line,
+            column: 0,
+            sensitivity: Sensitivity::classify(value),
+        };
+        ParseResult {
+            ast: None,
+            strings: vec![string("API_KEY_VALUE", 2), string("tick", 6)],
+            numbers: ["1", "10", "42"]
+                .iter()
+                .zip([5, 5, 10])
+                .map(|(value, line)| NumericLiteral {
+                    value: value.to_string(),
+                    line,
+                    column: 0,
+                    is_float: false,
+                })
+                .collect(),
+            functions: vec![],
+            type_aliases: vec![],
+            directives: scan_directives(SOURCE).unwrap(),
+            config_block: None,
+            unsupported_statements: vec![],
+        }
+    }
+
+    #[test]
+    fn test_tiers_trade_startup_for_protection() {
+        let parsed = parse_result();
+        let basic = OverheadEstimator::new(ObfuscationTier::Basic)
+            .estimate(SOURCE, &parsed)
+            .unwrap();
+        let standard = OverheadEstimator::new(ObfuscationTier::Standard)
+            .estimate(SOURCE, &parsed)
+            .unwrap();
+
+        // Basic decrypts its one sensitive string at load and adds no per-frame cost
+        assert_eq!((basic.load_decrypts, basic.lazy_decrypts), (1, 0));
+        assert_eq!(basic.per_frame_ms, 0.0);
+
+        // Standard decrypts lazily; the annotated hot path is left alone
+        assert_eq!((standard.load_decrypts, standard.lazy_decrypts), (0, 1));
+        assert_eq!(standard.obfuscated_constants, 1);
+        assert_eq!(standard.flattened_loops, 1);
+        assert!(standard.per_frame_ms > 0.0);
+        assert!(standard.frame_budget_percent() < 100.0);
+    }
+
+    #[test]
+    fn test_premium_pools_everything_lazily() {
+        let estimate = OverheadEstimator::new(ObfuscationTier::Premium)
+            .estimate(SOURCE, &parse_result())
+            .unwrap();
+
+        assert_eq!(estimate.load_decrypts, 0);
+        // One string and one number outside the annotated function
+        assert_eq!(estimate.lazy_decrypts, 2);
+        assert_eq!(estimate.obfuscated_constants, 0);
+        assert!(estimate.output_bytes > SOURCE.len());
+    }
+}
@@ -0,0 +1,121 @@
+//! Table key obfuscation
+//!
+//! Rewrites the keys `TableKeyAnalyzer` proves local to the script. Mangling
+//! renames a key at every site (`config.timeout` → `config._0x3`), so the
+//! rename has to reach `t["key"]` literals too. Encrypting keeps the key and
+//! only hides it: each site becomes a lookup through the string table
+//! (`config[_decrypt(3)]`).
+
+use super::NameMangler;
+use crate::analysis::{KeySiteKind, TableKeys};
+use crate::parser::{Sensitivity, StringLiteral};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// How rewritable table keys are obfuscated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableKeyMode {
+    /// Rename keys like identifiers (`t.timeout` → `t._0x3`)
+    Mangle,
+    /// Look keys up through the string table (`t[_decrypt(3)]`)
+    Encrypt,
+}
+
+impl TableKeyMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            TableKeyMode::Mangle => "mangle",
+            TableKeyMode::Encrypt => "encrypt",
+        }
+    }
+}
+
+impl std::fmt::Display for TableKeyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Plans the rewrite of the keys in a `TableKeys`
+pub struct TableKeyObfuscator {
+    mode: TableKeyMode,
+}
+
+impl TableKeyObfuscator {
+    pub fn new(mode: TableKeyMode) -> Self {
+        Self { mode }
+    }
+
+    /// New name of every key, drawn from `mangler` (empty when encrypting)
+    pub fn mappings(&self, keys: &TableKeys, mangler: &mut NameMangler) -> HashMap<String, String> {
+        match self.mode {
+            TableKeyMode::Mangle => {
+                let names: BTreeSet<String> = keys.keys().into_iter().map(str::to_string).collect();
+                mangler.map_shared_names(&names)
+            }
+            TableKeyMode::Encrypt => HashMap::new(),
+        }
+    }
+
+    /// Bring the string literals in line with the rewritten keys
+    ///
+    /// Mangled keys rename their `t["key"]` literals. Encrypted keys mark
+    /// those literals sensitive, so the string pass always takes them, and
+    /// add one at every other site the rewriter turns into `t[...]`.
+    pub fn update_strings(
+        &self,
+        keys: &TableKeys,
+        mappings: &HashMap<String, String>,
+        strings: &mut Vec<StringLiteral>,
+    ) {
+        let brackets: HashMap<(usize, usize), &str> = keys
+            .sites
+            .iter()
+            .filter(|site| site.kind == KeySiteKind::Bracket)
+            .map(|site| ((site.line, site.column), site.key.as_str()))
+            .collect();
+
+        for literal in strings.iter_mut() {
+            let Some(key) = brackets.get(&(literal.line, literal.column)) else {
+                continue;
+            };
+            match self.mode {
+                TableKeyMode::Mangle => {
+                    if let Some(mangled) = mappings.get(*key) {
+                        literal.value = mangled.clone();
+                    }
+                }
+                TableKeyMode::Encrypt => literal.sensitivity = Sensitivity::High,
+            }
+        }
+
+        if self.mode == TableKeyMode::Encrypt {
+            strings.extend(
+                keys.sites
+                    .iter()
+                    .filter(|site| encryptable(site.kind))
+                    .map(|site| StringLiteral {
+                        value: site.key.clone(),
+                        line: site.line,
+                        column: site.column,
+                        sensitivity: Sensitivity::High,
+                    }),
+            );
+        }
+    }
+}
+
+/// Whether the rewriter can turn a site of `kind` into `t[...]`
+///
+/// `t:key "x"` and the inner names of `function t.a.b()` have no indexed
+/// form, so they stay as written (the key itself is unchanged).
+fn encryptable(kind: KeySiteKind) -> bool {
+    matches!(
+        kind,
+        KeySiteKind::Field
+            | KeySiteKind::Method { plain: true }
+            | KeySiteKind::Constructor
+            | KeySiteKind::Declaration { .. }
+    )
+}
@@ -0,0 +1,250 @@
+//! Minification without protection
+//!
+//! Runs the same analysis and rewriter as a protected build, but the only
+//! pass is local renaming: no strings are encrypted and no runtime, license
+//! check or key is emitted, so the output needs nothing to run. Layout is
+//! then compacted token by token.
+//!
+//! Only names the script declares as locals (and local functions) are
+//! renamed. Globals, `function name()` declarations and preserved Roblox
+//! APIs keep their names, so other scripts still find them.
+//!
+//! A `(` after a newline is kept on its own line: Luau reads `a = b\n(f)()`
+//! as ambiguous and joining it would quietly turn it into a call. A `;` is
+//! dropped unless the next statement starts with `(` (`a = b; (f)()` stays as
+//! written) or it separates table fields.
+
+use super::{NameMangler, NamingStyle, ObfuscatedScript, SourceRewriter};
+use crate::analysis::{AnalysisOptions, Analyzer, DynamicGlobal};
+use crate::codegen::{take_directives, OutputFormatter};
+use crate::parser::ParseResult;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, TokenType};
+
+/// Line width after which minified output starts a new line
+const LINE_WIDTH: usize = 2000;
+
+/// A minified script
+#[derive(Debug, Clone)]
+pub struct Minified {
+    pub code: String,
+    /// Locals renamed
+    pub renamed: usize,
+    /// `_G`, `shared` and `getfenv` accesses found (renaming only touches
+    /// locals, so these are reported, not fatal)
+    pub dynamic_globals: Vec<DynamicGlobal>,
+}
+
+/// Strips comments and layout and renames locals
+pub struct Minifier {
+    rename_locals: bool,
+    naming_style: NamingStyle,
+}
+
+impl Minifier {
+    pub fn new() -> Self {
+        Self {
+            rename_locals: true,
+            naming_style: NamingStyle::Sequential,
+        }
+    }
+
+    /// Rename locals (the default) or only compact the layout
+    pub fn with_renaming(mut self, rename_locals: bool) -> Self {
+        self.rename_locals = rename_locals;
+        self
+    }
+
+    /// Generate renamed locals in `style` (shortest names by default)
+    pub fn with_naming_style(mut self, style: NamingStyle) -> Self {
+        self.naming_style = style;
+        self
+    }
+
+    /// Minify `source` (already parsed into `parse_result`)
+    pub fn minify(&self, source: &str, parse_result: &ParseResult) -> Result<Minified> {
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(parse_result)?;
+
+        let mut script = ObfuscatedScript::new();
+        if self.rename_locals {
+            // Globals (including `function name()` declarations) stay reachable
+            let preserved: Vec<String> = analysis
+                .preserved_identifiers
+                .iter()
+                .cloned()
+                .chain(
+                    analysis
+                        .scopes
+                        .iter()
+                        .flat_map(|scope| scope.variables.values())
+                        .filter(|var| !var.can_rename)
+                        .map(|var| var.name.clone()),
+                )
+                .collect();
+            // Seeded so a script always minifies the same way
+            let mut mangler =
+                NameMangler::with_seed(&preserved, true, 0).with_naming_style(self.naming_style);
+            script.name_mappings = mangler.generate_mappings(&analysis)?;
+            mangler.map_type_aliases(&parse_result.type_aliases, &mut script.name_mappings);
+        }
+        let renamed = script.name_mappings.len();
+
+        let rewritten = SourceRewriter::new(&script).rewrite(source, parse_result)?;
+        let (directives, body) = take_directives(&rewritten.code);
+        let mut code = directives.render();
+        code.push_str(&compact(&body)?);
+
+        Ok(Minified {
+            code,
+            renamed,
+            dynamic_globals: analysis.dynamic_globals,
+        })
+    }
+}
+
+impl Default for Minifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drop comments, whitespace and statement semicolons from `code`
+fn compact(code: &str) -> Result<String> {
+    let tokens = tokens(code)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+    let mut output = String::with_capacity(code.len());
+    let mut prev: Option<String> = None;
+    let mut saw_newline = false;
+    let mut semicolon = false;
+    let mut line_len = 0;
+    // Open `{`; a `;` inside one may separate fields
+    let mut braces = 0usize;
+
+    for token in &tokens {
+        match token.token_type() {
+            TokenType::Whitespace { .. } => {
+                saw_newline |= token.to_string().contains('\n');
+                continue;
+            }
+            TokenType::SingleLineComment { .. } => continue,
+            // Luau's ambiguity check is by line, so a line break inside a
+            // comment counts
+            TokenType::MultiLineComment { .. } => {
+                saw_newline |= token.to_string().contains('\n');
+                continue;
+            }
+            TokenType::Eof => break,
+            _ => {}
+        }
+
+        let text = token.to_string();
+        if text == ";" && braces == 0 {
+            semicolon = true;
+            continue;
+        }
+        match text.as_str() {
+            "{" => braces += 1,
+            "}" => braces = braces.saturating_sub(1),
+            _ => {}
+        }
+
+        if let Some(prev_text) = &prev {
+            let opens_paren = text.starts_with('(');
+            let separator = if opens_paren && semicolon {
+                ";"
+            } else if opens_paren && saw_newline {
+                "\n"
+            } else if !opens_paren && line_len > LINE_WIDTH {
+                "\n"
+            } else if OutputFormatter::needs_space(prev_text, &text) {
+                " "
+            } else {
+                ""
+            };
+            if separator == "\n" {
+                line_len = 0;
+            } else {
+                line_len += separator.len();
+            }
+            output.push_str(separator);
+        }
+
+        output.push_str(&text);
+        line_len += text.len();
+        saw_newline = false;
+        semicolon = false;
+        prev = Some(text);
+    }
+
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LuauParser;
+
+    fn minify(minifier: &Minifier, source: &str) -> Minified {
+        let parsed = LuauParser::new().parse(source).unwrap();
+        minifier.minify(source, &parsed).unwrap()
+    }
+
+    #[test]
+    fn test_minify_renames_locals_and_strips_layout() {
+        let source = r#"--!strict
+-- Inventory helpers
+local Players = game:GetService("Players")
+
+local function countItems(inventory)
+    local total = 0 -- running count
+    for _, stack in ipairs(inventory) do
+        total += stack.amount
+    end
+    return total
+end
+
+function Describe(inventory)
+    return "items: " .. countItems(inventory)
+end
+"#;
+        let minified = minify(&Minifier::new(), source);
+
+        assert!(minified.code.starts_with("--!strict\n"));
+        assert!(!minified.code.contains("running count"));
+        assert!(!minified.code.contains("countItems"));
+        assert!(!minified.code.contains("inventory"));
+        assert!(minified.code.contains("function Describe("));
+        assert!(minified.code.contains("GetService(\"Players\")"));
+        assert!(minified.code.contains(".amount"));
+        assert!(minified.renamed >= 4);
+        assert_eq!(minified.code.trim_end().lines().count(), 2);
+        assert!(full_moon::parse(&minified.code).is_ok());
+    }
+
+    #[test]
+    fn test_statement_start_parens() {
+        let minifier = Minifier::new().with_renaming(false);
+
+        // One statement: the parenthesis calls `b`
+        let call = minify(&minifier, "local a = b (f)()\n");
+        assert_eq!(call.code, "local a=b(f)()\n");
+
+        // Two statements: the semicolon has to stay
+        let separate = minify(&minifier, "local a = b; (f)()\n");
+        assert_eq!(separate.code, "local a=b;(f)()\n");
+
+        // Other semicolons go, except between table fields
+        let plain = minify(&minifier, "f(); g();\nlocal t = {1; 2}\n");
+        assert_eq!(plain.code, "f()g()local t={1;2}\n");
+    }
+
+    #[test]
+    fn test_compact_keeps_line_before_paren() {
+        assert_eq!(compact("x = b\n(f)()\n").unwrap(), "x=b\n(f)()\n");
+        assert_eq!(compact("x = b --[[\n]] (f)()\n").unwrap(), "x=b\n(f)()\n");
+        assert_eq!(compact("local s = 1 .. 2\n").unwrap(), "local s=1 ..2\n");
+    }
+}
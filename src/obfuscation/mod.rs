@@ -6,27 +6,81 @@
 //! - Name mangling
 //! - Control flow flattening
 //! - Dead code injection
+//! - Constant pool extraction
+//! - Table key mangling or encryption
+//! - Function outlining
+//!
+//! `OverheadEstimator` models the runtime cost of a tier without running it.
+//! `HotFunctions` exempts profiled hot paths from the costliest passes.
+//! `SourceRewriter` applies a run to the source text for code generation.
+//! `PassScheduler` orders the passes by the artifacts they read and invalidate.
+//! `SizeStats` attributes the growth of the output to each transform.
+//! `Minifier` renames locals and compacts a script without protecting it.
+//! `Simplifier` inlines single-use literal locals and drops unread ones.
+//! `RngMode` hands every pass its random generator, seeded or not.
 
 mod constants;
 mod controlflow;
 mod deadcode;
+mod estimate;
+mod keys;
+mod minify;
 mod names;
+mod outline;
+mod pool;
+mod profile;
+mod review;
+mod rewrite;
+mod rng;
+mod schedule;
+mod simplify;
+mod size;
 mod strings;
 
-pub use constants::ConstantObfuscator;
-pub use controlflow::ControlFlowFlattener;
-pub use deadcode::DeadCodeInjector;
-pub use names::NameMangler;
-pub use strings::StringObfuscator;
+pub use constants::{anchor_hash, ConstantObfuscator, ANCHOR_HASH_INIT, ENVIRONMENT_HASH, ENVIRONMENT_KEY};
+pub use controlflow::{
+    ControlFlowFlattener, Flattening, FlatteningLimits, FunctionMetrics, SkippedBlock,
+    MAX_DISPATCH_DEPTH, MAX_DISPATCH_LOCALS, MAX_DISPATCH_STATEMENTS,
+};
+pub use deadcode::{trap_name, DeadCodeInjector, OverheadBudget, TRAP_FUNCTION};
+pub use estimate::{
+    OverheadEstimate, OverheadEstimator, ASSUMED_ITERATIONS_PER_FRAME, FRAME_BUDGET_MS,
+};
+pub use keys::{TableKeyMode, TableKeyObfuscator};
+pub use minify::{Minified, Minifier};
+pub use names::{NameMangler, NamingStyle};
+pub use outline::{FunctionOutliner, OutlinedFunction, OutlinedHelper};
+pub use pool::{ConstantPool, PoolEntry, PoolEntryKind, POOL_ACCESSOR};
+pub use profile::{event_handlers, HotFunctions, HOT_TIME_SHARE};
+pub use review::{Exclusions, ReviewItem, ReviewKind, ReviewPlan};
+pub use rewrite::SourceRewriter;
+pub use rng::{BoxedRng, ObfRng, RngMode};
+pub use schedule::{Artifact, Pass, PassScheduler, PassSpec, Step};
+pub use simplify::{Simplified, Simplifier};
+pub use size::{gzip_size, SizeStats, TransformDelta, SCRIPT_SOURCE_BUDGET};
+pub use strings::{
+    DecryptionMode, PlaintextRule, PlaintextRules, PlaintextString, StringObfuscator,
+};
 
-use crate::analysis::AnalysisResult;
-use crate::crypto::CryptoContext;
-use crate::parser::ParseResult;
+use crate::analysis::{
+    AnalysisResult, ControlFlowAnalyzer, ControlFlowGraph, FoldedConstant, KeepReason, KeySite,
+    KeptKey, ScopeKind, TableKeyAnalyzer,
+};
+use crate::crypto::{encoded_len, Compression, CryptoContext, EncryptedData};
+use crate::diagnostics::{Diagnostic, Span};
+use crate::memstats::MemoryStats;
+use crate::parser::{
+    ConfigBlock, FunctionDirective, FunctionInfo, NumericLiteral, ParseResult, ProtectionLevel,
+    Sensitivity, StringLiteral,
+};
+use crate::progress::{no_progress, SharedProgress, Stage};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
 
-/// Obfuscation tier levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Obfuscation tier levels, ordered from lightest to strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ObfuscationTier {
     /// Basic obfuscation (fast, light overhead)
     Basic,
@@ -37,6 +91,46 @@ pub enum ObfuscationTier {
 }
 
 impl ObfuscationTier {
+    /// Lowercase name, as in `--tier` and license metadata
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObfuscationTier::Basic => "basic",
+            ObfuscationTier::Standard => "standard",
+            ObfuscationTier::Premium => "premium",
+        }
+    }
+
+    /// Tier called `name`, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "basic" => Some(ObfuscationTier::Basic),
+            "standard" => Some(ObfuscationTier::Standard),
+            "premium" => Some(ObfuscationTier::Premium),
+            _ => None,
+        }
+    }
+
+    /// The tier a build runs at when this one is requested under a license
+    /// for `licensed`
+    ///
+    /// Tiers up to the licensed one are kept. A higher one is lowered to it,
+    /// or fails with a license error, as `policy` says.
+    pub fn entitled(self, licensed: ObfuscationTier, policy: TierPolicy) -> Result<Self> {
+        if self <= licensed {
+            return Ok(self);
+        }
+        match policy {
+            TierPolicy::Downgrade => Ok(licensed),
+            TierPolicy::Fail => Err(ObfuscatorError::LicenseError(format!(
+                "The {} tier needs a {} license, but this license is for the {} tier",
+                self.name(),
+                self.name(),
+                licensed.name()
+            ))
+            .into()),
+        }
+    }
+
     /// Get recommended settings for this tier
     pub fn settings(&self) -> ObfuscationSettings {
         match self {
@@ -44,47 +138,242 @@ impl ObfuscationTier {
                 encrypt_strings: true,
                 encrypt_all_strings: false,
                 obfuscate_constants: false,
+                environment_constants: false,
                 mangle_names: true,
                 mangle_functions: false,
                 flatten_control_flow: false,
+                outline_functions: false,
                 inject_dead_code: false,
                 dead_code_density: 0.0,
+                honeypot_traps: 0,
+                use_constant_pool: false,
+                string_fragment_size: None,
+                decryption_mode: DecryptionMode::Eager,
             },
             ObfuscationTier::Standard => ObfuscationSettings {
                 encrypt_strings: true,
                 encrypt_all_strings: true,
                 obfuscate_constants: true,
+                environment_constants: true,
                 mangle_names: true,
                 mangle_functions: true,
                 flatten_control_flow: true,
+                outline_functions: false,
                 inject_dead_code: true,
                 dead_code_density: 0.1,
+                honeypot_traps: 0,
+                use_constant_pool: false,
+                string_fragment_size: None,
+                decryption_mode: DecryptionMode::Lazy,
             },
             ObfuscationTier::Premium => ObfuscationSettings {
                 encrypt_strings: true,
                 encrypt_all_strings: true,
                 obfuscate_constants: true,
+                // Numbers live in the encrypted pool instead
+                environment_constants: false,
                 mangle_names: true,
                 mangle_functions: true,
                 flatten_control_flow: true,
+                outline_functions: true,
                 inject_dead_code: true,
                 dead_code_density: 0.3,
+                honeypot_traps: 2,
+                use_constant_pool: true,
+                string_fragment_size: Some(16),
+                decryption_mode: DecryptionMode::Lazy,
             },
         }
     }
 }
 
+/// What `protect` does when the requested tier is above the license's
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TierPolicy {
+    /// Build at the licensed tier, with a warning
+    #[default]
+    Downgrade,
+    /// Fail the build
+    Fail,
+}
+
+impl From<ProtectionLevel> for ObfuscationSettings {
+    fn from(level: ProtectionLevel) -> Self {
+        match level {
+            ProtectionLevel::None => ObfuscationSettings::disabled(),
+            ProtectionLevel::Basic => ObfuscationTier::Basic.settings(),
+            ProtectionLevel::Standard => ObfuscationTier::Standard.settings(),
+            ProtectionLevel::Max => ObfuscationTier::Premium.settings(),
+        }
+    }
+}
+
 /// Obfuscation settings
 #[derive(Debug, Clone)]
 pub struct ObfuscationSettings {
     pub encrypt_strings: bool,
     pub encrypt_all_strings: bool,
     pub obfuscate_constants: bool,
+    /// Derive inlined integers from load-time values (anchor bytes and hash)
+    pub environment_constants: bool,
     pub mangle_names: bool,
     pub mangle_functions: bool,
     pub flatten_control_flow: bool,
+    /// Split large functions into chains of helpers
+    pub outline_functions: bool,
     pub inject_dead_code: bool,
     pub dead_code_density: f32,
+    /// Trap functions baited as license bypasses, mixed into the dead code
+    pub honeypot_traps: usize,
+    /// Move constants into an encrypted pool instead of inlining expressions
+    pub use_constant_pool: bool,
+    /// Split strings longer than this into separately encrypted fragments
+    pub string_fragment_size: Option<usize>,
+    /// Decrypt all strings at load or lazily on first use
+    pub decryption_mode: DecryptionMode,
+}
+
+impl ObfuscationSettings {
+    /// Settings with every transformation turned off
+    pub fn disabled() -> Self {
+        Self {
+            encrypt_strings: false,
+            encrypt_all_strings: false,
+            obfuscate_constants: false,
+            environment_constants: false,
+            mangle_names: false,
+            mangle_functions: false,
+            flatten_control_flow: false,
+            outline_functions: false,
+            inject_dead_code: false,
+            dead_code_density: 0.0,
+            honeypot_traps: 0,
+            use_constant_pool: false,
+            string_fragment_size: None,
+            decryption_mode: DecryptionMode::Eager,
+        }
+    }
+
+    /// Without the passes that cost time on every call
+    pub fn for_hot_path(&self) -> Self {
+        Self {
+            flatten_control_flow: false,
+            outline_functions: false,
+            inject_dead_code: false,
+            ..self.clone()
+        }
+    }
+
+    /// With flattening and dead code at full strength
+    pub fn maximized(&self) -> Self {
+        let premium = ObfuscationTier::Premium.settings();
+        Self {
+            flatten_control_flow: true,
+            inject_dead_code: true,
+            dead_code_density: self.dead_code_density.max(premium.dead_code_density),
+            ..self.clone()
+        }
+    }
+}
+
+/// Settings in effect at each source line, after `--!obfuscate:` overrides
+struct LineSettings<'a> {
+    base: &'a ObfuscationSettings,
+    /// Line range of each overridden function and its settings
+    overrides: Vec<(RangeInclusive<usize>, ObfuscationSettings)>,
+}
+
+impl<'a> LineSettings<'a> {
+    fn new(base: &'a ObfuscationSettings, directives: &[FunctionDirective]) -> Self {
+        Self {
+            base,
+            overrides: directives
+                .iter()
+                .map(|d| (d.start_line..=d.end_line, d.level.into()))
+                .collect(),
+        }
+    }
+
+    /// Apply `settings` to the hot functions in `functions`
+    ///
+    /// Directives on the same function still win: among overrides starting on
+    /// the same line the last one counts, so these go first.
+    fn with_hot_functions(
+        mut self,
+        hot: &HotFunctions,
+        functions: &[FunctionInfo],
+        settings: &ObfuscationSettings,
+    ) -> Self {
+        let hot_overrides: Vec<_> = hot
+            .matching(functions)
+            .map(|f| (f.line..=f.end_line, settings.clone()))
+            .collect();
+        self.overrides.splice(0..0, hot_overrides);
+        self
+    }
+
+    /// Leave the functions starting on `starts` unflattened
+    ///
+    /// These go last, so they win over a directive on the same function.
+    fn without_flattening(mut self, functions: &[FunctionInfo], starts: &BTreeSet<usize>) -> Self {
+        for function in functions.iter().filter(|f| starts.contains(&f.line)) {
+            let settings = ObfuscationSettings {
+                flatten_control_flow: false,
+                ..self.at(function.line).clone()
+            };
+            self.overrides.push((function.line..=function.end_line, settings));
+        }
+        self
+    }
+
+    /// Leave the settings block as written, functions inside it included
+    ///
+    /// Its keys then stay readable wherever the protected code uses them.
+    fn without_config_block(mut self, block: Option<&ConfigBlock>) -> Self {
+        if let Some(block) = block {
+            for (lines, settings) in &mut self.overrides {
+                if block.covers(*lines.start()) {
+                    *settings = ObfuscationSettings::disabled();
+                }
+            }
+            self.overrides.push((
+                block.start_line..=block.end_line,
+                ObfuscationSettings::disabled(),
+            ));
+        }
+        self
+    }
+
+    /// Settings at `line`; the innermost annotated function wins
+    fn at(&self, line: usize) -> &ObfuscationSettings {
+        self.overrides
+            .iter()
+            .filter(|(lines, _)| lines.contains(&line))
+            .max_by_key(|(lines, _)| *lines.start())
+            .map_or(self.base, |(_, settings)| settings)
+    }
+
+    /// Whether `enabled` holds anywhere in the script
+    fn anywhere(&self, enabled: impl Fn(&ObfuscationSettings) -> bool) -> bool {
+        enabled(self.base) || self.overrides.iter().any(|(_, settings)| enabled(settings))
+    }
+
+    /// Whether `enabled` holds at `start` and in every annotated function
+    /// overlapping `start..=end`
+    fn throughout(
+        &self,
+        start: usize,
+        end: usize,
+        enabled: impl Fn(&ObfuscationSettings) -> bool,
+    ) -> bool {
+        enabled(self.at(start))
+            && self
+                .overrides
+                .iter()
+                .filter(|(lines, _)| *lines.start() <= end && start <= *lines.end())
+                .all(|(_, settings)| enabled(settings))
+    }
 }
 
 /// Main obfuscator coordinator
@@ -92,6 +381,17 @@ pub struct Obfuscator {
     tier: ObfuscationTier,
     settings: ObfuscationSettings,
     crypto_ctx: CryptoContext,
+    rng: RngMode,
+    environment_anchor: Option<String>,
+    hot_functions: HotFunctions,
+    overhead_budget: Option<OverheadBudget>,
+    naming_style: NamingStyle,
+    compression: Compression,
+    table_keys: Option<TableKeyMode>,
+    exclusions: Exclusions,
+    plaintext_rules: PlaintextRules,
+    progress: SharedProgress,
+    best_effort: bool,
 }
 
 impl Obfuscator {
@@ -102,9 +402,138 @@ impl Obfuscator {
             tier,
             settings,
             crypto_ctx,
+            rng: RngMode::Entropy,
+            environment_anchor: None,
+            hot_functions: HotFunctions::default(),
+            overhead_budget: None,
+            naming_style: NamingStyle::default(),
+            compression: Compression::None,
+            table_keys: None,
+            exclusions: Exclusions::default(),
+            plaintext_rules: PlaintextRules::default(),
+            progress: no_progress(),
+            best_effort: false,
+        }
+    }
+
+    /// Create a deterministic obfuscator
+    ///
+    /// Every randomized pass is seeded from `seed`, so the same input and
+    /// seed produce the same names and layout. Encrypted strings still
+    /// differ, since nonces come from the system CSPRNG.
+    pub fn with_seed(tier: ObfuscationTier, crypto_ctx: CryptoContext, seed: u64) -> Self {
+        let settings = tier.settings();
+        Self {
+            tier,
+            settings,
+            crypto_ctx,
+            rng: RngMode::Seeded(seed),
+            environment_anchor: None,
+            hot_functions: HotFunctions::default(),
+            overhead_budget: None,
+            naming_style: NamingStyle::default(),
+            compression: Compression::None,
+            table_keys: None,
+            exclusions: Exclusions::default(),
+            plaintext_rules: PlaintextRules::default(),
+            progress: no_progress(),
+            best_effort: false,
+        }
+    }
+
+    /// Take every pass's randomness from `mode`
+    pub fn set_rng(&mut self, mode: RngMode) {
+        self.rng = mode;
+    }
+
+    /// Use `anchor` (e.g. the license key) for environment-derived constants
+    ///
+    /// Without one, a random anchor is generated per script.
+    pub fn bind_environment(&mut self, anchor: impl Into<String>) {
+        self.environment_anchor = Some(anchor.into());
+    }
+
+    /// Keep `hot` functions fast and spend the saved budget elsewhere
+    ///
+    /// Hot functions skip control flow flattening and dead code injection;
+    /// the rest of the script gets both at Premium strength whatever the tier.
+    pub fn set_hot_functions(&mut self, hot: HotFunctions) {
+        self.hot_functions = hot;
+    }
+
+    /// Use `settings` instead of the tier's (e.g. a preset's tuning of them)
+    pub fn set_settings(&mut self, settings: ObfuscationSettings) {
+        self.settings = settings;
+    }
+
+    /// Trim dead code to fit `budget` instead of injecting it by density alone
+    pub fn set_overhead_budget(&mut self, budget: OverheadBudget) {
+        self.overhead_budget = Some(budget);
+    }
+
+    /// Generate mangled identifiers in `style`
+    pub fn set_naming_style(&mut self, style: NamingStyle) {
+        self.naming_style = style;
+    }
+
+    /// Compress encrypted strings with `compression` where it saves space
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Obfuscate the table keys proven local to the script
+    ///
+    /// Keys that can't be proven safe are left alone and listed in
+    /// `ObfuscatedScript::kept_keys`.
+    pub fn set_table_keys(&mut self, mode: TableKeyMode) {
+        self.table_keys = Some(mode);
+    }
+
+    /// Leave the strings, names and functions a review switched off alone
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) {
+        self.exclusions = exclusions;
+    }
+
+    /// Never encrypt strings matching `rules`; the ones kept end up in
+    /// `ObfuscatedScript::plaintext_strings`
+    pub fn set_plaintext_rules(&mut self, rules: PlaintextRules) {
+        self.plaintext_rules = rules;
+    }
+
+    /// Report each finished pass to `progress`
+    pub fn set_progress(&mut self, progress: SharedProgress) {
+        self.progress = progress;
+    }
+
+    /// Skip a pass (or a flattened block) that fails instead of failing the
+    /// run; the failures end up in `ObfuscatedScript::failed_transforms`
+    pub fn set_best_effort(&mut self, best_effort: bool) {
+        self.best_effort = best_effort;
+    }
+
+    /// `result`'s value, or `None` once its error is recorded against `pass`
+    /// in best-effort mode
+    fn isolate<T>(
+        &self,
+        pass: Pass,
+        result: Result<T>,
+        failures: &mut Vec<TransformFailure>,
+    ) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.best_effort => {
+                failures.push(TransformFailure {
+                    pass,
+                    lines: None,
+                    error: format!("{:#}", e),
+                });
+                Ok(None)
+            }
+            Err(e) => Err(e),
         }
     }
 
+
     /// Apply all obfuscation transformations
     pub fn obfuscate(
         &self,
@@ -113,48 +542,457 @@ impl Obfuscator {
     ) -> Result<ObfuscatedScript> {
         log::info!("Starting obfuscation with tier: {:?}", self.tier);
 
-        // Step 1: String obfuscation
         let mut obfuscated = ObfuscatedScript::new();
-        
-        if self.settings.encrypt_strings {
-            log::debug!("Encrypting strings...");
-            let string_obfuscator = StringObfuscator::new(&self.crypto_ctx);
-            obfuscated.encrypted_strings = string_obfuscator.obfuscate(
-                &parse_result.strings,
-                self.settings.encrypt_all_strings,
-            )?;
+        obfuscated.key_version = self.crypto_ctx.key_version();
+        obfuscated.config_block = parse_result.config_block.clone();
+        let base = if self.hot_functions.is_empty() {
+            self.settings.clone()
+        } else {
+            self.settings.maximized()
+        };
+        let lines = LineSettings::new(&base, &parse_result.directives)
+            .with_hot_functions(
+                &self.hot_functions,
+                &parse_result.functions,
+                &self.settings.for_hot_path(),
+            )
+            .without_flattening(&parse_result.functions, &self.exclusions.functions)
+            .without_config_block(parse_result.config_block.as_ref());
+        if !self.hot_functions.is_empty() {
+            tracing::debug!(
+                "Reducing obfuscation in {} hot functions",
+                self.hot_functions.matching(&parse_result.functions).count()
+            );
         }
-
-        // Step 2: Constant obfuscation
-        if self.settings.obfuscate_constants {
-            log::debug!("Obfuscating constants...");
-            let const_obfuscator = ConstantObfuscator::new();
-            obfuscated.obfuscated_constants = const_obfuscator.obfuscate(&parse_result.numbers)?;
+        if !parse_result.directives.is_empty() {
+            tracing::debug!(
+                "Applying {} per-function obfuscation directives",
+                parse_result.directives.len()
+            );
         }
+        
+        // Literals in functions annotated `--!obfuscate: none`, those a
+        // review switched off and those matching a plaintext rule are left alone
+        let reviewed =
+            |line: usize, column: usize| !self.exclusions.strings.contains(&(line, column));
+        let plaintext = |value: &str| self.plaintext_rules.matching(value).is_some();
+        obfuscated.plaintext_strings = self.plaintext_rules.kept(
+            parse_result
+                .strings
+                .iter()
+                .filter(|s| lines.at(s.line).encrypt_strings && reviewed(s.line, s.column)),
+        );
+        // Instance paths are encrypted whole, never split into fragments
+        let instance_paths: BTreeSet<(usize, usize)> = analysis
+            .instance_paths
+            .iter()
+            .map(|path| (path.line, path.column))
+            .collect();
+        let mut strings: Vec<StringLiteral> = parse_result
+            .strings
+            .iter()
+            .filter(|s| lines.at(s.line).encrypt_strings && reviewed(s.line, s.column))
+            .filter(|s| !plaintext(&s.value))
+            .cloned()
+            .collect();
+        let mut numbers: Vec<NumericLiteral> = parse_result
+            .numbers
+            .iter()
+            .filter(|n| lines.at(n.line).obfuscate_constants)
+            .cloned()
+            .collect();
 
-        // Step 3: Name mangling
-        if self.settings.mangle_names {
-            log::debug!("Mangling names...");
-            let name_mangler = NameMangler::new(
-                &analysis.preserved_identifiers,
-                self.settings.mangle_functions,
+        // A folded expression is encrypted as one literal in place of its operands
+        obfuscated.folded_strings = analysis
+            .folded_strings
+            .iter()
+            .filter(|fold| lines.at(fold.line).encrypt_strings && reviewed(fold.line, fold.column))
+            .filter(|fold| !plaintext(&fold.value))
+            .cloned()
+            .collect();
+        if !obfuscated.folded_strings.is_empty() {
+            tracing::debug!(
+                "Folding {} constant string expressions",
+                obfuscated.folded_strings.len()
             );
-            obfuscated.name_mappings = name_mangler.generate_mappings(analysis)?;
+            let folded = |line: usize, column: usize| {
+                obfuscated.folded_strings.iter().any(|fold| fold.covers(line, column))
+            };
+            let sensitivity = |fold: &FoldedConstant| {
+                let parts = parse_result.strings.iter().filter(|s| fold.covers(s.line, s.column));
+                parts
+                    .map(|s| s.sensitivity)
+                    .chain([Sensitivity::classify(&fold.value)])
+                    .min_by_key(|level| match level {
+                        Sensitivity::High => 0,
+                        Sensitivity::Medium => 1,
+                        Sensitivity::Low => 2,
+                    })
+                    .unwrap_or(Sensitivity::Medium)
+            };
+            let folds: Vec<StringLiteral> = obfuscated
+                .folded_strings
+                .iter()
+                .map(|fold| StringLiteral {
+                    value: fold.value.clone(),
+                    line: fold.line,
+                    column: fold.column,
+                    sensitivity: sensitivity(fold),
+                })
+                .collect();
+            strings.retain(|s| !folded(s.line, s.column));
+            numbers.retain(|n| !folded(n.line, n.column));
+            strings.extend(folds);
         }
-
-        // Step 4: Control flow flattening
-        if self.settings.flatten_control_flow {
-            log::debug!("Flattening control flow...");
-            let cf_flattener = ControlFlowFlattener::new();
-            obfuscated.flattened_blocks = cf_flattener.flatten(&analysis.control_flow)?;
+        obfuscated.folded_numbers = analysis
+            .folded_numbers
+            .iter()
+            .filter(|fold| lines.at(fold.line).obfuscate_constants)
+            .filter(|fold| {
+                !obfuscated.folded_strings.iter().any(|s| s.covers(fold.line, fold.column))
+            })
+            .cloned()
+            .collect();
+        if !obfuscated.folded_numbers.is_empty() {
+            tracing::debug!(
+                "Folding {} constant numeric expressions",
+                obfuscated.folded_numbers.len()
+            );
+            numbers.retain(|n| {
+                !obfuscated.folded_numbers.iter().any(|fold| fold.covers(n.line, n.column))
+            });
+            numbers.extend(
+                obfuscated
+                    .folded_numbers
+                    .iter()
+                    .map(|fold| NumericLiteral::new(&fold.value, fold.line, fold.column)),
+            );
         }
+        
+        // Passes run in dependency order (see `schedule`)
+        let enabled = [
+            (Pass::Strings, lines.anywhere(|s| s.encrypt_strings)),
+            (Pass::Constants, lines.anywhere(|s| s.obfuscate_constants)),
+            (Pass::Names, self.settings.mangle_names),
+            (Pass::ControlFlow, lines.anywhere(|s| s.flatten_control_flow)),
+            (Pass::DeadCode, base.inject_dead_code || base.honeypot_traps > 0),
+            (Pass::TableKeys, self.table_keys.is_some()),
+            (Pass::Outline, lines.anywhere(|s| s.outline_functions)),
+        ];
+        let passes: Vec<Pass> = enabled
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(pass, _)| *pass)
+            .collect();
+        let mut cfg: ControlFlowGraph = analysis.control_flow.clone();
+
+        let steps = PassScheduler::new(&passes).schedule()?;
+        let total = steps.iter().filter(|step| matches!(step, Step::Run(_))).count();
+        let mut done = 0;
+        let mut failures = Vec::new();
+        for step in steps {
+            let ran = matches!(step, Step::Run(_));
+            let _span = match step {
+                Step::Run(pass) => tracing::debug_span!("pass", ?pass).entered(),
+                Step::Rebuild(artifact) => tracing::debug_span!("rebuild", ?artifact).entered(),
+            };
+            // A pass that fails in best-effort mode breaks out, leaving its
+            // results empty
+            'step: {
+                match step {
+                    Step::Run(Pass::Strings) => {
+                        let string_obfuscator = match self.settings.string_fragment_size {
+                            Some(size) => {
+                                StringObfuscator::with_fragment_size(&self.crypto_ctx, size)
+                            }
+                            None => StringObfuscator::new(&self.crypto_ctx),
+                        }
+                        .with_compression(self.compression)
+                        .with_plaintext_rules(self.plaintext_rules.clone())
+                        .with_whole_strings(instance_paths.clone())
+                        .with_rng(self.rng.stream(9));
+                        // One pass so identical values are interned across directive regions
+                        let selected: Vec<StringLiteral> = strings
+                            .iter()
+                            .filter(|s| {
+                                lines.at(s.line).encrypt_all_strings
+                                    || matches!(
+                                        s.sensitivity,
+                                        Sensitivity::High | Sensitivity::Medium
+                                    )
+                            })
+                            .cloned()
+                            .collect();
+                        let encrypted = string_obfuscator.obfuscate(&selected, true);
+                        let Some(encrypted) =
+                            self.isolate(Pass::Strings, encrypted, &mut failures)?
+                        else {
+                            break 'step;
+                        };
+                        obfuscated.encrypted_strings = encrypted;
+                    }
+                    Step::Run(Pass::Constants) => {
+                        let mut const_obfuscator = ConstantObfuscator::with_rng(self.rng.stream(1));
+
+                        if self.settings.use_constant_pool {
+                            // Pool replaces both inline expressions and per-string decrypt calls
+                            let string_obfuscator = StringObfuscator::new(&self.crypto_ctx)
+                                .with_rng(self.rng.stream(10));
+                            let pool = ConstantPool::build(
+                                &string_obfuscator,
+                                &const_obfuscator,
+                                &strings,
+                                &numbers,
+                                &mut *self.rng.stream(5),
+                            );
+                            let Some(pool) = self.isolate(Pass::Constants, pool, &mut failures)?
+                            else {
+                                break 'step;
+                            };
+
+                            obfuscated.obfuscated_constants = numbers
+                                .iter()
+                                .filter_map(|num_lit| {
+                                    pool.accessor(PoolEntryKind::Number, &num_lit.normalized())
+                                        .map(|expr| ObfuscatedConstant {
+                                            original: num_lit.value.clone(),
+                                            obfuscated_expr: expr,
+                                            line: num_lit.line,
+                                            column: num_lit.column,
+                                        })
+                                })
+                                .collect();
+                            obfuscated.pooled_strings = strings
+                                .iter()
+                                .filter_map(|string_lit| {
+                                    pool.accessor(PoolEntryKind::String, &string_lit.value)
+                                        .map(|expr| ObfuscatedConstant {
+                                            original: string_lit.value.clone(),
+                                            obfuscated_expr: expr,
+                                            line: string_lit.line,
+                                            column: string_lit.column,
+                                        })
+                                })
+                                .collect();
+                            obfuscated.encrypted_strings.clear();
+                            obfuscated.constant_pool = Some(pool);
+                        } else {
+                            if self.settings.environment_constants {
+                                let anchor = self
+                                    .environment_anchor
+                                    .clone()
+                                    .filter(|a| !a.is_empty())
+                                    .unwrap_or_else(|| const_obfuscator.generate_anchor());
+                                const_obfuscator = const_obfuscator.bind_environment(&anchor);
+                                obfuscated.environment_anchor = Some(anchor);
+                            }
+                            let constants = const_obfuscator.obfuscate(&numbers);
+                            let Some(constants) =
+                                self.isolate(Pass::Constants, constants, &mut failures)?
+                            else {
+                                obfuscated.environment_anchor = None;
+                                break 'step;
+                            };
+                            obfuscated.obfuscated_constants = constants;
+                        }
+                    }
+                    Step::Run(Pass::Names) => {
+                        let preserved: Vec<String> = analysis
+                            .preserved_identifiers
+                            .iter()
+                            .chain(&self.exclusions.names)
+                            .cloned()
+                            .collect();
+                        let mut name_mangler = NameMangler::with_rng(
+                            &preserved,
+                            self.settings.mangle_functions,
+                            self.rng.stream(2),
+                        )
+                        .with_naming_style(self.naming_style);
+                        // Renamed keys show up as identifiers too; keep the two apart
+                        name_mangler.reserve(obfuscated.key_mappings.values().cloned());
+                        let mappings = name_mangler.generate_mappings(analysis);
+                        let Some(mappings) = self.isolate(Pass::Names, mappings, &mut failures)?
+                        else {
+                            break 'step;
+                        };
+                        obfuscated.name_mappings = mappings;
+                        name_mangler.map_type_aliases(
+                            &parse_result.type_aliases,
+                            &mut obfuscated.name_mappings,
+                        );
+                    }
+                    Step::Run(Pass::ControlFlow) => {
+                        let cf_flattener = ControlFlowFlattener::with_rng(self.rng.stream(3))
+                            .with_best_effort(self.best_effort);
+                        // Hoisting a captured loop or block local into the dispatcher would
+                        // share one upvalue between closures that each expect their own
+                        let captured_lines: Vec<usize> = analysis
+                            .scopes
+                            .iter()
+                            .filter(|scope| scope.kind == ScopeKind::Block)
+                            .flat_map(|scope| scope.captured_variables())
+                            .map(|var| var.line)
+                            .collect();
+
+                        // Blocks reaching into a function that opted out stay as they are
+                        let mut flattenable = cfg.clone();
+                        flattenable.blocks.retain(|_, block| {
+                            lines.throughout(block.start_line, block.end_line, |s| {
+                                s.flatten_control_flow
+                            })
+                                && !captured_lines
+                                    .iter()
+                                    .any(|line| (block.start_line..=block.end_line).contains(line))
+                        });
+                        let flattening = cf_flattener.flatten(&flattenable);
+                        let Some(flattening) =
+                            self.isolate(Pass::ControlFlow, flattening, &mut failures)?
+                        else {
+                            break 'step;
+                        };
+                        failures.extend(flattening.failed.iter().map(|block| TransformFailure {
+                            pass: Pass::ControlFlow,
+                            lines: Some((block.start_line, block.end_line)),
+                            error: block.reason.clone(),
+                        }));
+                        obfuscated.flattened_blocks = flattening.blocks;
+                        obfuscated.unflattened_blocks = flattening.skipped;
+                        obfuscated.flattening_metrics = flattening.metrics;
+                    }
+                    Step::Run(Pass::DeadCode) => {
+                        // Traps come without dead code if that is switched off
+                        let density = if base.inject_dead_code {
+                            base.dead_code_density
+                        } else {
+                            0.0
+                        };
+                        let dead_code_injector =
+                            DeadCodeInjector::with_rng(density, self.rng.stream(4))
+                                .with_traps(base.honeypot_traps);
+                        let snippets = match self.overhead_budget {
+                            Some(budget) => {
+                                dead_code_injector.generate_within(parse_result, budget.max_bytes())
+                            }
+                            None => dead_code_injector.generate(parse_result),
+                        };
+                        let Some(snippets) =
+                            self.isolate(Pass::DeadCode, snippets, &mut failures)?
+                        else {
+                            break 'step;
+                        };
+                        obfuscated.traps = snippets
+                            .iter()
+                            .filter_map(|snippet| trap_name(snippet))
+                            .map(str::to_string)
+                            .collect();
+                        obfuscated.dead_code_snippets = snippets;
+                    }
+                    Step::Run(Pass::TableKeys) => {
+                        let Some(mode) = self.table_keys else {
+                            continue;
+                        };
+                        tracing::debug!("Obfuscating table keys ({})...", mode);
+                        let keys = TableKeyAnalyzer::new(&analysis.preserved_identifiers)
+                            .analyze(parse_result);
+                        let Some(mut keys) = self.isolate(Pass::TableKeys, keys, &mut failures)?
+                        else {
+                            break 'step;
+                        };
+
+                        // A key is rewritten at every site or at none
+                        let excluded: Vec<(String, usize)> = keys
+                            .sites
+                            .iter()
+                            .filter(|site| {
+                                let settings = lines.at(site.line);
+                                match mode {
+                                    TableKeyMode::Mangle => !settings.mangle_names,
+                                    TableKeyMode::Encrypt => !settings.encrypt_strings,
+                                }
+                            })
+                            .map(|site| (site.key.clone(), site.line))
+                            .collect();
+                        for (key, line) in excluded {
+                            keys.keep(&key, KeepReason::Excluded, line);
+                        }
+
+                        let mut key_mangler = NameMangler::with_rng(
+                            &analysis.preserved_identifiers,
+                            true,
+                            self.rng.stream(6),
+                        )
+                        .with_naming_style(self.naming_style);
+                        // Never rename a key onto one that keeps its name, or onto a variable
+                        key_mangler.reserve(keys.kept.iter().map(|kept| kept.key.clone()));
+                        key_mangler.reserve(
+                            analysis
+                                .scopes
+                                .iter()
+                                .flat_map(|scope| scope.variables.keys().cloned()),
+                        );
 
-        // Step 5: Dead code injection
-        if self.settings.inject_dead_code {
-            log::debug!("Injecting dead code...");
-            let dead_code_injector = DeadCodeInjector::new(self.settings.dead_code_density);
-            obfuscated.dead_code_snippets = dead_code_injector.generate(parse_result)?;
+                        let key_obfuscator = TableKeyObfuscator::new(mode);
+                        obfuscated.key_mappings = key_obfuscator.mappings(&keys, &mut key_mangler);
+                        key_obfuscator.update_strings(
+                            &keys,
+                            &obfuscated.key_mappings,
+                            &mut strings,
+                        );
+                        obfuscated.table_keys = Some(mode);
+                        obfuscated.key_sites = keys.sites;
+                        obfuscated.kept_keys = keys.kept;
+                    }
+                    Step::Run(Pass::Outline) => {
+                        // Helper names must not collide with any name the
+                        // script, the renamed locals or the renamed keys use
+                        let mut helper_names = NameMangler::with_rng(
+                            &analysis.preserved_identifiers,
+                            true,
+                            self.rng.stream(7),
+                        )
+                        .with_naming_style(self.naming_style);
+                        helper_names.reserve(
+                            analysis
+                                .scopes
+                                .iter()
+                                .flat_map(|scope| scope.variables.keys().cloned()),
+                        );
+                        helper_names.reserve(obfuscated.name_mappings.values().cloned());
+                        helper_names.reserve(obfuscated.key_mappings.values().cloned());
+
+                        let mut outliner = FunctionOutliner::with_rng(self.rng.stream(8));
+                        let eligible = |start, end| {
+                            lines.throughout(start, end, |s| s.outline_functions)
+                                && !self.exclusions.functions.contains(&start)
+                        };
+                        let outlined = outliner.outline(parse_result, &mut helper_names, eligible);
+                        let Some(outlined) = self.isolate(Pass::Outline, outlined, &mut failures)?
+                        else {
+                            break 'step;
+                        };
+                        obfuscated.outlined_functions = outlined;
+                    }
+                    Step::Rebuild(Artifact::Cfg) => {
+                        if !analysis.control_flow.blocks.is_empty() {
+                            cfg = ControlFlowAnalyzer::new().analyze(parse_result)?;
+                        }
+                    }
+                    Step::Rebuild(artifact) => {
+                        return Err(ObfuscatorError::ObfuscationError(format!(
+                            "No way to rebuild {:?}",
+                            artifact
+                        ))
+                        .into());
+                    }
+                }
+            }
+            if ran {
+                done += 1;
+                self.progress.items_processed(Stage::Obfuscate, done, total);
+            }
         }
+        obfuscated.failed_transforms = failures;
 
         log::info!("Obfuscation complete");
         Ok(obfuscated)
@@ -165,10 +1003,53 @@ impl Obfuscator {
         ObfuscationStats {
             tier: self.tier,
             encrypted_strings: obfuscated.encrypted_strings.len(),
+            deduplicated_strings: obfuscated
+                .encrypted_strings
+                .iter()
+                .filter(|e| !matches!(&e.fragment, Some(f) if f.index > 0))
+                .map(|e| e.sites.len().saturating_sub(1))
+                .sum(),
+            dedup_bytes_saved: obfuscated
+                .encrypted_strings
+                .iter()
+                .map(|e| e.sites.len().saturating_sub(1) * (e.encrypted_data.len() + e.nonce.len()))
+                .sum(),
             obfuscated_constants: obfuscated.obfuscated_constants.len(),
             renamed_identifiers: obfuscated.name_mappings.len(),
             flattened_blocks: obfuscated.flattened_blocks.len(),
             dead_code_snippets: obfuscated.dead_code_snippets.len(),
+            traps: obfuscated.traps.len(),
+            obfuscated_keys: obfuscated
+                .key_sites
+                .iter()
+                .map(|site| site.key.as_str())
+                .collect::<std::collections::BTreeSet<_>>()
+                .len(),
+            kept_keys: obfuscated.kept_keys.len(),
+            compressed_strings: obfuscated
+                .encrypted_strings
+                .iter()
+                .filter(|e| !e.compression.is_none())
+                .count(),
+            compression_saved: obfuscated
+                .encrypted_strings
+                .iter()
+                .filter(|e| !e.compression.is_none())
+                .map(|e| {
+                    encoded_len(e.original.len() + 16) as i64
+                        - encoded_len(e.encrypted_data.len()) as i64
+                })
+                .sum(),
+            outlined_functions: obfuscated.outlined_functions.len(),
+            outline_helpers: obfuscated
+                .outlined_functions
+                .iter()
+                .map(|function| function.helpers.len())
+                .sum(),
+            inlined_locals: 0,
+            removed_locals: 0,
+            size: SizeStats::default(),
+            memory: MemoryStats::default(),
         }
     }
 }
@@ -180,7 +1061,77 @@ pub struct ObfuscatedScript {
     pub obfuscated_constants: Vec<ObfuscatedConstant>,
     pub name_mappings: std::collections::HashMap<String, String>,
     pub flattened_blocks: Vec<FlattenedBlock>,
+    /// Blocks left as written so the dispatcher stays within its limits
+    pub unflattened_blocks: Vec<SkippedBlock>,
+    /// Size of the dispatcher the flattened blocks make up
+    pub flattening_metrics: FunctionMetrics,
     pub dead_code_snippets: Vec<String>,
+    /// Bait functions among the dead code snippets
+    pub traps: Vec<String>,
+    pub constant_pool: Option<ConstantPool>,
+    /// String literals replaced by constant pool accessors
+    pub pooled_strings: Vec<ObfuscatedConstant>,
+    /// Anchor the inlined constants were derived from, if any
+    pub environment_anchor: Option<String>,
+    /// Key generation the strings and pool are encrypted under
+    pub key_version: u32,
+    /// How table keys were obfuscated, if they were
+    pub table_keys: Option<TableKeyMode>,
+    /// Original → mangled table key (empty unless mangling keys)
+    pub key_mappings: std::collections::HashMap<String, String>,
+    /// Sites of the rewritten table keys
+    pub key_sites: Vec<KeySite>,
+    /// Table keys left readable, and why
+    pub kept_keys: Vec<KeptKey>,
+    /// String literals a plaintext rule kept unencrypted
+    pub plaintext_strings: Vec<PlaintextString>,
+    /// Constant string expressions replaced by one literal
+    pub folded_strings: Vec<FoldedConstant>,
+    /// Constant arithmetic expressions replaced by one number
+    pub folded_numbers: Vec<FoldedConstant>,
+    /// Passes and blocks skipped in best-effort mode because they failed
+    pub failed_transforms: Vec<TransformFailure>,
+    /// Settings block lifted out of the code, to be written above it
+    pub config_block: Option<ConfigBlock>,
+    /// Functions split into chains of helpers
+    pub outlined_functions: Vec<OutlinedFunction>,
+}
+
+/// A transform skipped in best-effort mode because it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformFailure {
+    pub pass: Pass,
+    /// Lines the failure is confined to; `None` when the whole pass was skipped
+    pub lines: Option<(usize, usize)>,
+    pub error: String,
+}
+
+impl std::fmt::Display for TransformFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.lines {
+            Some((start, end)) => write!(
+                f,
+                "{:?} pass skipped lines {}-{}: {}",
+                self.pass, start, end, self.error
+            ),
+            None => write!(f, "{:?} pass skipped: {}", self.pass, self.error),
+        }
+    }
+}
+
+impl TransformFailure {
+    /// The failure as a `transform-skipped` diagnostic
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::warning(
+            "transform-skipped",
+            format!("{:?} pass skipped: {}", self.pass, self.error),
+        )
+        .with_suggestion("the output is weaker here; fix the input or report the failure");
+        match self.lines {
+            Some((start, end)) => diagnostic.at(Span::lines(start, end)),
+            None => diagnostic,
+        }
+    }
 }
 
 impl ObfuscatedScript {
@@ -190,16 +1141,77 @@ impl ObfuscatedScript {
             obfuscated_constants: Vec::new(),
             name_mappings: std::collections::HashMap::new(),
             flattened_blocks: Vec::new(),
+            unflattened_blocks: Vec::new(),
+            flattening_metrics: FunctionMetrics::default(),
             dead_code_snippets: Vec::new(),
+            traps: Vec::new(),
+            constant_pool: None,
+            pooled_strings: Vec::new(),
+            environment_anchor: None,
+            key_version: 0,
+            table_keys: None,
+            key_mappings: std::collections::HashMap::new(),
+            key_sites: Vec::new(),
+            kept_keys: Vec::new(),
+            plaintext_strings: Vec::new(),
+            folded_strings: Vec::new(),
+            folded_numbers: Vec::new(),
+            failed_transforms: Vec::new(),
+            config_block: None,
+            outlined_functions: Vec::new(),
         }
     }
+
+    /// Encrypted strings as `CodeGenerator::generate` takes them
+    ///
+    /// Entry `i` is what `_decrypt(i + 1)` in the rewritten code refers to.
+    pub fn string_table(&self) -> Vec<(String, EncryptedData)> {
+        self.encrypted_strings
+            .iter()
+            .map(|e| {
+                (
+                    e.original.clone(),
+                    EncryptedData {
+                        ciphertext: e.encrypted_data.clone(),
+                        nonce: e.nonce.clone(),
+                        tag_len: 16,
+                        key_version: self.key_version,
+                        compression: e.compression,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Sensitivity of each `string_table` entry, in the same order
+    pub fn string_sensitivity(&self) -> Vec<Sensitivity> {
+        self.encrypted_strings.iter().map(|e| e.sensitivity).collect()
+    }
+
+    /// Source line and column of an encrypted string, by id or split-string id
+    ///
+    /// Lets a runtime error raised at a decrypt site be reported against the
+    /// original source.
+    pub fn source_location(&self, id: &str) -> Option<(usize, usize)> {
+        self.encrypted_strings
+            .iter()
+            .find(|s| s.id == id || s.fragment.as_ref().is_some_and(|f| f.parent_id == id))
+            .map(|s| (s.line, s.column))
+    }
 }
 
+/// Public name for `Obfuscator`
+pub type ObfuscationEngine = Obfuscator;
+
 /// Obfuscation result for code generation
 #[derive(Debug, Clone)]
 pub struct ObfuscationResult {
     /// The obfuscated code ready for final assembly
     pub code: String,
+    /// Encrypted constant pool referenced by `_K(i)` accessor calls
+    pub constant_pool: Option<ConstantPool>,
+    /// Anchor that `_EK`/`_EH` must be defined from
+    pub environment_anchor: Option<String>,
 }
 
 /// Encrypted string with metadata
@@ -209,7 +1221,27 @@ pub struct EncryptedString {
     pub encrypted_data: Vec<u8>,
     pub nonce: Vec<u8>,
     pub line: usize,
+    pub column: usize,
+    /// Every `(line, column)` using this value; identical literals share one entry
+    pub sites: Vec<(usize, usize)>,
     pub id: String,
+    /// Set when this entry is one piece of a longer split string
+    pub fragment: Option<StringFragment>,
+    /// Compression applied before encryption (only when it saved space)
+    pub compression: Compression,
+    /// Sensitivity of the literal, which decides its anti-dump protection
+    pub sensitivity: Sensitivity,
+}
+
+/// Position of a fragment within a split string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringFragment {
+    /// Shared identifier of the original string
+    pub parent_id: String,
+    /// Zero-based fragment position
+    pub index: usize,
+    /// Total fragments for the original string
+    pub count: usize,
 }
 
 /// Obfuscated constant
@@ -218,12 +1250,16 @@ pub struct ObfuscatedConstant {
     pub original: String,
     pub obfuscated_expr: String,
     pub line: usize,
+    pub column: usize,
 }
 
 /// Flattened control flow block
 #[derive(Debug, Clone)]
 pub struct FlattenedBlock {
     pub block_id: usize,
+    /// Source lines the block covers
+    pub start_line: usize,
+    pub end_line: usize,
     pub state_machine_code: String,
 }
 
@@ -232,10 +1268,35 @@ pub struct FlattenedBlock {
 pub struct ObfuscationStats {
     pub tier: ObfuscationTier,
     pub encrypted_strings: usize,
+    /// String uses served by an existing entry instead of a new ciphertext
+    pub deduplicated_strings: usize,
+    /// Ciphertext and nonce bytes not emitted thanks to interning
+    pub dedup_bytes_saved: usize,
     pub obfuscated_constants: usize,
     pub renamed_identifiers: usize,
     pub flattened_blocks: usize,
     pub dead_code_snippets: usize,
+    /// Honeypot functions among the dead code snippets
+    pub traps: usize,
+    /// Distinct table keys rewritten
+    pub obfuscated_keys: usize,
+    /// Table keys left readable (see `ObfuscatedScript::kept_keys`)
+    pub kept_keys: usize,
+    /// Encrypted strings stored compressed
+    pub compressed_strings: usize,
+    /// Output bytes compression saved; the pipeline adds the payload chunk
+    /// and subtracts the decompressor, so there it is the net saving
+    pub compression_saved: i64,
+    /// Functions split into helpers, and the helpers they were split into
+    pub outlined_functions: usize,
+    pub outline_helpers: usize,
+    /// Locals `--simplify` inlined and removed, filled in by the pipeline
+    pub inlined_locals: usize,
+    pub removed_locals: usize,
+    /// Filled in by the pipeline once the script is generated
+    pub size: SizeStats,
+    /// Allocations per stage, filled in by the pipeline (`memstats` feature)
+    pub memory: MemoryStats,
 }
 
 impl std::fmt::Display for ObfuscationStats {
@@ -243,17 +1304,42 @@ impl std::fmt::Display for ObfuscationStats {
         write!(
             f,
             "Obfuscation Statistics (Tier: {:?}):\n\
-             - Encrypted strings: {}\n\
+             - Encrypted strings: {} ({} duplicate uses interned, {} bytes saved)\n\
              - Obfuscated constants: {}\n\
              - Renamed identifiers: {}\n\
              - Flattened blocks: {}\n\
-             - Dead code snippets: {}",
+             - Dead code snippets: {} ({} traps)\n\
+             - Table keys: {} obfuscated, {} kept\n\
+             - Compressed strings: {} ({} bytes saved)\n\
+             - Outlined functions: {} ({} helpers)\n\
+             - Simplified locals: {} inlined, {} removed\n\
+             - Size: {} → {} bytes ({:.1}x, {} gzipped, {:.1}% of script budget)",
             self.tier,
             self.encrypted_strings,
+            self.deduplicated_strings,
+            self.dedup_bytes_saved,
             self.obfuscated_constants,
             self.renamed_identifiers,
             self.flattened_blocks,
-            self.dead_code_snippets
-        )
+            self.dead_code_snippets,
+            self.traps,
+            self.obfuscated_keys,
+            self.kept_keys,
+            self.compressed_strings,
+            self.compression_saved,
+            self.outlined_functions,
+            self.outline_helpers,
+            self.inlined_locals,
+            self.removed_locals,
+            self.size.input_bytes,
+            self.size.output_bytes,
+            self.size.growth(),
+            self.size.compressed_bytes,
+            self.size.budget_percent()
+        )?;
+        for delta in &self.size.deltas {
+            write!(f, "\n   {:<10} {:+}", delta.transform, delta.bytes)?;
+        }
+        Ok(())
     }
 }
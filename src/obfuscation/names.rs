@@ -1,45 +1,172 @@
 //! Identifier name mangling
 
-use crate::analysis::AnalysisResult;
+use super::rng::{self, BoxedRng};
+use crate::analysis::{AnalysisResult, Variable, VariableType};
+use crate::parser::TypeAlias;
 use anyhow::Result;
+use rand::seq::SliceRandom;
 use rand::{distributions::Alphanumeric, Rng};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Words for `NamingStyle::Dictionary`, always joined as `adjectiveNoun`
+const ADJECTIVES: &[&str] = &[
+    "active", "base", "cached", "current", "default", "empty", "final", "global", "inner",
+    "last", "live", "main", "max", "min", "next", "old", "outer", "pending", "prev", "queued",
+    "raw", "root", "shared", "source", "target", "temp", "total", "valid",
+];
+const NOUNS: &[&str] = &[
+    "Buffer", "Cache", "Callback", "Count", "Data", "Entry", "Flag", "Handler", "Index", "Info",
+    "Item", "Key", "List", "Map", "Node", "Offset", "Options", "Result", "Size", "Slot", "State",
+    "Table", "Task", "Value",
+];
+
+/// Engine-flavoured stems and suffixes for `NamingStyle::RobloxLike`
+const ROBLOX_STEMS: &[&str] = &[
+    "Players", "Workspace", "Lighting", "ReplicatedStorage", "ServerStorage", "RunService",
+    "TweenService", "UserInput", "Humanoid", "Character", "Camera", "Sound", "Tool", "Remote",
+    "Gui", "Physics", "Network", "Asset", "Content", "Teleport", "Badge", "Marketplace",
+    "DataStore", "Collection",
+];
+const ROBLOX_SUFFIXES: &[&str] = &[
+    "Service", "Module", "Handler", "Controller", "Signal", "Connection", "Event", "Folder",
+    "Instance", "Info", "Manager", "Provider",
+];
+
+/// Reserved words a generated name must never be
+const LUAU_KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+    "continue", "export", "type", "typeof",
+];
+
+/// Letters for `NamingStyle::Sequential`, in order
+const SEQUENTIAL_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Faces for `NamingStyle::Emoji`
+const EMOTICONS: &[&str] = &[
+    "o_O", "O_o", "o_o", "O_O", "T_T", "x_x", "X_X", "u_u", "n_n", "v_v", "e_e", "z_z", "q_p",
+    "Q_Q", "U_U", "V_V",
+];
+
+/// Look-alike characters for `NamingStyle::Confusable` (the first is never a digit)
+const CONFUSABLE_FIRST: [char; 2] = ['I', 'l'];
+const CONFUSABLE_REST: [char; 3] = ['I', 'l', '1'];
+const CONFUSABLE_MIN_LEN: usize = 6;
+
+/// How mangled identifiers look
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NamingStyle {
+    /// `_0x1f`: compact, but an obvious obfuscator signature
+    #[default]
+    Hex,
+    /// Ordinary-looking word pairs (`cachedHandler`)
+    Dictionary,
+    /// ASCII emoticons (`_o_O_T_T`); Luau identifiers can't hold real emoji
+    Emoji,
+    /// Runs of look-alike characters (`lIl1I1`)
+    Confusable,
+    /// Shortest possible names: `a`, `b`, ..., `Z`, `aa`, ...
+    Sequential,
+    /// Plausible engine-style names (`PlayersService2`)
+    RobloxLike,
+}
 
 /// Name mangler for identifier obfuscation
 pub struct NameMangler {
     preserved_names: Vec<String>,
     mangle_functions: bool,
     counter: usize,
+    rng: BoxedRng,
+    style: NamingStyle,
+    /// Names generated or taken by the script, never handed out again
+    used: HashSet<String>,
+    /// Word lists for the word-based styles, shuffled per seed
+    adjectives: Vec<&'static str>,
+    nouns: Vec<&'static str>,
 }
 
 impl NameMangler {
     pub fn new(preserved_names: &[String], mangle_functions: bool) -> Self {
+        Self::with_rng(preserved_names, mangle_functions, rng::entropy())
+    }
+
+    /// Create a name mangler with a fixed seed for reproducible output
+    pub fn with_seed(preserved_names: &[String], mangle_functions: bool, seed: u64) -> Self {
+        Self::with_rng(preserved_names, mangle_functions, rng::seeded(seed))
+    }
+
+    /// Create a name mangler drawing from `rng`
+    pub fn with_rng(preserved_names: &[String], mangle_functions: bool, rng: BoxedRng) -> Self {
         Self {
             preserved_names: preserved_names.to_vec(),
             mangle_functions,
             counter: 0,
+            rng,
+            style: NamingStyle::default(),
+            used: HashSet::new(),
+            adjectives: ADJECTIVES.to_vec(),
+            nouns: NOUNS.to_vec(),
         }
     }
 
+    /// Generate names in `style`
+    pub fn with_naming_style(mut self, style: NamingStyle) -> Self {
+        self.style = style;
+        match style {
+            NamingStyle::Dictionary => {
+                self.adjectives = ADJECTIVES.to_vec();
+                self.nouns = NOUNS.to_vec();
+            }
+            NamingStyle::RobloxLike => {
+                self.adjectives = ROBLOX_STEMS.to_vec();
+                self.nouns = ROBLOX_SUFFIXES.to_vec();
+            }
+            _ => return self,
+        }
+        // Shuffled once so the word order isn't the same in every script
+        self.adjectives.shuffle(&mut self.rng);
+        self.nouns.shuffle(&mut self.rng);
+        self
+    }
+
     /// Generate name mappings for all identifiers
+    ///
+    /// Mappings are by name, so an upvalue reference inside a closure is
+    /// renamed exactly like the captured declaration. A name kept for one
+    /// binding is therefore kept for every binding of it; otherwise a
+    /// captured local could be renamed while a same-named function stays.
     pub fn generate_mappings(&mut self, analysis: &AnalysisResult) -> Result<HashMap<String, String>> {
         let mut mappings = HashMap::new();
 
-        // Collect all identifiers from scopes
+        // A generated name must not shadow or capture one the script uses
+        self.used.extend(
+            analysis
+                .scopes
+                .iter()
+                .flat_map(|scope| scope.variables.keys().cloned()),
+        );
+
+        let kept: BTreeSet<&str> = analysis
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.variables.values())
+            .filter(|var| self.should_keep(var))
+            .map(|var| var.name.as_str())
+            .collect();
+
+        // Collect all identifiers from scopes (sorted so mapping order is stable)
         for scope in &analysis.scopes {
-            for (var_name, var) in &scope.variables {
-                // Skip preserved identifiers (Roblox APIs, etc.)
-                if self.should_preserve(var_name) {
-                    continue;
-                }
+            let mut variables: Vec<_> = scope.variables.iter().collect();
+            variables.sort_by(|a, b| a.0.cmp(b.0));
 
-                // Skip function names if not mangling functions
-                if !self.mangle_functions
-                    && matches!(
-                        var.var_type,
-                        crate::analysis::VariableType::Function
-                    )
-                {
+            for (var_name, var) in variables {
+                if kept.contains(var_name.as_str()) {
+                    if !var.captured_by.is_empty() && !self.should_keep(var) {
+                        tracing::debug!(
+                            "Keeping captured variable '{}': the name is preserved elsewhere",
+                            var_name
+                        );
+                    }
                     continue;
                 }
 
@@ -51,10 +178,66 @@ impl NameMangler {
             }
         }
 
-        log::debug!("Generated {} name mappings", mappings.len());
+        tracing::debug!("Generated {} name mappings", mappings.len());
         Ok(mappings)
     }
 
+    /// Whether a variable keeps its original name
+    fn should_keep(&self, var: &Variable) -> bool {
+        // Skip preserved identifiers (Roblox APIs, etc.)
+        if self.should_preserve(&var.name) {
+            return true;
+        }
+
+        // Skip function names if not mangling functions
+        !self.mangle_functions && matches!(var.var_type, VariableType::Function)
+    }
+
+    /// Add mappings for declared type aliases
+    ///
+    /// Aliases share the identifier mapping so the declaration and every
+    /// annotation that references it are renamed identically. Exported types
+    /// are part of a module's public surface and keep their names.
+    pub fn map_type_aliases(
+        &mut self,
+        aliases: &[TypeAlias],
+        mappings: &mut HashMap<String, String>,
+    ) {
+        for alias in aliases {
+            if alias.exported
+                || self.should_preserve(&alias.name)
+                || mappings.contains_key(&alias.name)
+            {
+                continue;
+            }
+            let mangled = self.generate_mangled_name();
+            mappings.insert(alias.name.clone(), mangled);
+        }
+    }
+
+    /// Never hand out `names` (e.g. names another mangler already generated)
+    pub fn reserve(&mut self, names: impl IntoIterator<Item = String>) {
+        self.used.extend(names);
+    }
+
+    /// Map names shared between modules (e.g. ModuleScript exports)
+    ///
+    /// The same mapping is applied to every module so cross-module accesses
+    /// keep resolving after renaming.
+    pub fn map_shared_names(&mut self, names: &BTreeSet<String>) -> HashMap<String, String> {
+        names
+            .iter()
+            .filter(|name| !self.should_preserve(name))
+            .map(|name| (name.clone(), self.generate_mangled_name()))
+            .collect()
+    }
+
+    /// A name for an identifier a pass adds (e.g. outlined helpers), unlike
+    /// any generated or reserved so far
+    pub fn fresh_name(&mut self) -> String {
+        self.generate_mangled_name()
+    }
+
     /// Check if a name should be preserved
     fn should_preserve(&self, name: &str) -> bool {
         self.preserved_names.contains(&name.to_string())
@@ -62,16 +245,43 @@ impl NameMangler {
 
     /// Generate a mangled name
     fn generate_mangled_name(&mut self) -> String {
-        // Use short hex-style identifiers for compactness
-        let mangled = format!("_0x{:x}", self.counter);
-        self.counter += 1;
-        mangled
+        loop {
+            let mangled = match self.style {
+                // Short hex-style identifiers for compactness
+                NamingStyle::Hex => format!("_0x{:x}", self.counter),
+                NamingStyle::Dictionary | NamingStyle::RobloxLike => {
+                    self.dictionary_name(self.counter)
+                }
+                NamingStyle::Emoji => emoticon_name(self.counter),
+                NamingStyle::Confusable => confusable_name(self.counter),
+                NamingStyle::Sequential => sequential_name(self.counter),
+            };
+            self.counter += 1;
+            if !LUAU_KEYWORDS.contains(&mangled.as_str())
+                && !self.should_preserve(&mangled)
+                && self.used.insert(mangled.clone())
+            {
+                return mangled;
+            }
+        }
+    }
+
+    /// Two words joined (`adjectiveNoun`, `StemSuffix`), numbered once every
+    /// pair is taken
+    fn dictionary_name(&self, index: usize) -> String {
+        let pairs = self.adjectives.len() * self.nouns.len();
+        let adjective = self.adjectives[index % self.adjectives.len()];
+        let noun = self.nouns[(index / self.adjectives.len()) % self.nouns.len()];
+        match index / pairs {
+            0 => format!("{}{}", adjective, noun),
+            round => format!("{}{}{}", adjective, noun, round + 1),
+        }
     }
 
     /// Generate random-style mangled name (alternative strategy)
     #[allow(dead_code)]
-    fn generate_random_name() -> String {
-        let random_str: String = rand::thread_rng()
+    fn generate_random_name(&mut self) -> String {
+        let random_str: String = (&mut self.rng)
             .sample_iter(&Alphanumeric)
             .take(8)
             .map(|c| c.to_ascii_lowercase())
@@ -95,17 +305,61 @@ impl NameMangler {
     }
 }
 
+/// `index` as a bijective base-52 numeral: `a`..`Z`, then `aa`, `ab`, ...
+fn sequential_name(mut index: usize) -> String {
+    let base = SEQUENTIAL_ALPHABET.len();
+    let mut letters = Vec::new();
+    loop {
+        letters.push(SEQUENTIAL_ALPHABET[index % base]);
+        index /= base;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).expect("ASCII alphabet")
+}
+
+/// `index` in base `EMOTICONS.len()`, one face per digit
+fn emoticon_name(mut index: usize) -> String {
+    let mut faces = Vec::new();
+    loop {
+        faces.push(EMOTICONS[index % EMOTICONS.len()]);
+        index /= EMOTICONS.len();
+        if index == 0 {
+            break;
+        }
+    }
+    format!("_{}", faces.join("_"))
+}
+
+/// `index` in look-alike digits, padded so every name looks the same length
+fn confusable_name(index: usize) -> String {
+    let mut name = String::from(CONFUSABLE_FIRST[index % CONFUSABLE_FIRST.len()]);
+    let mut rest = index / CONFUSABLE_FIRST.len();
+    let mut digits = Vec::new();
+    while rest > 0 || digits.len() + 1 < CONFUSABLE_MIN_LEN {
+        digits.push(CONFUSABLE_REST[rest % CONFUSABLE_REST.len()]);
+        rest /= CONFUSABLE_REST.len();
+    }
+    name.extend(digits);
+    name
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::analysis::{Scope, Variable, VariableType};
+    use crate::analysis::{Scope, ScopeKind};
 
     fn create_test_analysis() -> AnalysisResult {
         let mut scope = Scope {
             id: 0,
             parent: None,
+            kind: ScopeKind::Global,
             variables: HashMap::new(),
             children: Vec::new(),
+            upvalues: Vec::new(),
         };
 
         scope.variables.insert(
@@ -115,6 +369,7 @@ mod tests {
                 var_type: VariableType::Local,
                 line: 1,
                 can_rename: true,
+                captured_by: Vec::new(),
             },
         );
 
@@ -125,6 +380,7 @@ mod tests {
                 var_type: VariableType::Global,
                 line: 1,
                 can_rename: false,
+                captured_by: Vec::new(),
             },
         );
 
@@ -137,6 +393,10 @@ mod tests {
             scopes: vec![scope],
             roblox_apis: vec![],
             preserved_identifiers: vec!["game".to_string()],
+            dynamic_globals: vec![],
+            folded_strings: vec![],
+            folded_numbers: vec![],
+            instance_paths: vec![],
         }
     }
 
@@ -156,6 +416,67 @@ mod tests {
         assert!(!mappings.contains_key("game"));
     }
 
+    #[test]
+    fn test_captured_name_kept_when_function_kept() {
+        let mut analysis = create_test_analysis();
+        let mut closure = Scope {
+            id: 1,
+            parent: Some(0),
+            kind: ScopeKind::Function,
+            variables: HashMap::new(),
+            children: Vec::new(),
+            upvalues: Vec::new(),
+        };
+        closure.variables.insert(
+            "callback".to_string(),
+            Variable {
+                name: "callback".to_string(),
+                var_type: VariableType::Function,
+                line: 2,
+                can_rename: false,
+                captured_by: Vec::new(),
+            },
+        );
+        analysis.scopes[0].variables.insert(
+            "callback".to_string(),
+            Variable {
+                name: "callback".to_string(),
+                var_type: VariableType::Local,
+                line: 1,
+                can_rename: true,
+                captured_by: vec![1],
+            },
+        );
+        analysis.scopes.push(closure);
+
+        let mappings = NameMangler::new(&[], false)
+            .generate_mappings(&analysis)
+            .unwrap();
+
+        // Renaming only the captured local would split it from the kept function
+        assert!(!mappings.contains_key("callback"));
+        assert!(mappings.contains_key("myVar"));
+    }
+
+    #[test]
+    fn test_type_alias_mapping() {
+        let mut mangler = NameMangler::new(&[], true);
+        let mut mappings = HashMap::new();
+        mappings.insert("Point".to_string(), "_0x9".to_string());
+
+        let aliases = vec![
+            TypeAlias { name: "Point".to_string(), line: 1, exported: false },
+            TypeAlias { name: "Config".to_string(), line: 2, exported: false },
+            TypeAlias { name: "Public".to_string(), line: 3, exported: true },
+        ];
+        mangler.map_type_aliases(&aliases, &mut mappings);
+
+        // Existing mapping is reused so values and annotations stay consistent
+        assert_eq!(mappings["Point"], "_0x9");
+        assert!(mappings["Config"].starts_with("_0x"));
+        assert!(!mappings.contains_key("Public"));
+    }
+
     #[test]
     fn test_sequential_naming() {
         let mut mangler = NameMangler::new(&[], true);
@@ -182,11 +503,84 @@ mod tests {
 
     #[test]
     fn test_random_name_generation() {
-        let name1 = NameMangler::generate_random_name();
-        let name2 = NameMangler::generate_random_name();
+        let mut mangler = NameMangler::new(&[], true);
+        let name1 = mangler.generate_random_name();
+        let name2 = mangler.generate_random_name();
         
         assert!(name1.starts_with('_'));
         assert!(name2.starts_with('_'));
         assert_ne!(name1, name2); // Should be different (very high probability)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_naming_styles_are_unique_and_deterministic() {
+        for style in [
+            NamingStyle::Dictionary,
+            NamingStyle::Emoji,
+            NamingStyle::Confusable,
+            NamingStyle::Sequential,
+            NamingStyle::RobloxLike,
+        ] {
+            let mut a = NameMangler::with_seed(&[], true, 7).with_naming_style(style);
+            let mut b = NameMangler::with_seed(&[], true, 7).with_naming_style(style);
+            let names: Vec<String> = (0..2000).map(|_| a.generate_mangled_name()).collect();
+
+            assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+            assert!(names.iter().all(|name| {
+                let first = name.chars().next().unwrap();
+                (first.is_ascii_alphabetic() || first == '_')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }));
+            assert_eq!(names[..10], (0..10).map(|_| b.generate_mangled_name()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_sequential_names_skip_keywords_and_preserved() {
+        let preserved = vec!["b".to_string()];
+        let mut mangler =
+            NameMangler::with_seed(&preserved, true, 3).with_naming_style(NamingStyle::Sequential);
+        let names: Vec<String> = (0..60).map(|_| mangler.generate_mangled_name()).collect();
+
+        assert_eq!(names[..3], ["a", "c", "d"]);
+        assert!(names.contains(&"aa".to_string()));
+        assert!(!names.iter().any(|n| LUAU_KEYWORDS.contains(&n.as_str())));
+        assert_eq!(sequential_name(52), "aa");
+        assert_eq!(sequential_name(51), "Z");
+    }
+
+    #[test]
+    fn test_generated_names_avoid_script_names() {
+        let mut analysis = create_test_analysis();
+        let taken = NameMangler::with_seed(&[], true, 1)
+            .with_naming_style(NamingStyle::Dictionary)
+            .dictionary_name(0);
+        analysis.scopes[0].variables.insert(
+            taken.clone(),
+            Variable {
+                name: taken.clone(),
+                var_type: VariableType::Global,
+                line: 3,
+                can_rename: false,
+                captured_by: Vec::new(),
+            },
+        );
+        analysis.preserved_identifiers.push(taken.clone());
+
+        let mappings = NameMangler::with_seed(&analysis.preserved_identifiers, true, 1)
+            .with_naming_style(NamingStyle::Dictionary)
+            .generate_mappings(&analysis)
+            .unwrap();
+
+        assert!(!mappings.contains_key(&taken));
+        assert!(mappings.values().all(|name| *name != taken));
+    }
+
+    #[test]
+    fn test_seeded_random_names_are_deterministic() {
+        let mut a = NameMangler::with_seed(&[], true, 1234);
+        let mut b = NameMangler::with_seed(&[], true, 1234);
+
+        assert_eq!(a.generate_random_name(), b.generate_random_name());
+    }
+}
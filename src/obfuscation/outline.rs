@@ -0,0 +1,584 @@
+//! Function outlining
+//!
+//! Splits large functions into a chain of helpers. The body is cut between
+//! top-level statements: the first part stays and ends in `return h1(...)`,
+//! and every later part becomes a helper ending in a call to the next one.
+//! Helpers are declared as locals just before the function and assigned
+//! right after it, in shuffled order, and each takes its arguments in a
+//! shuffled order too:
+//!
+//! ```lua
+//! local _0x2, _0x1; local function f(a, b)
+//!     local c = a + b
+//!     return _0x1(b, c, a)
+//! end
+//! _0x2 = function(c, a) ... end;
+//! _0x1 = function(b, c, a) ...
+//!     return _0x2(c, a)
+//! end;
+//! ```
+//!
+//! Helpers sit in the function's own scope, so they see the same upvalues
+//! and globals it does. Only its parameters (and `self`, and `...`) and the
+//! locals declared at the top of its body are passed explicitly, by value,
+//! so a cut is only made where none of those the rest of the body uses is
+//! ever assigned after its declaration, by the function or a closure in it.
+//! A returned value travels back through the tail calls unchanged.
+//!
+//! Only `local function` and `function name()` statements are split, not
+//! function expressions, and never a function calling `getfenv` or
+//! `setfenv`, whose environment the helpers wouldn't share.
+
+use super::rng::{self, BoxedRng, ObfRng};
+use super::NameMangler;
+use crate::parser::ParseResult;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::ast::{Block, FunctionBody, Parameter, Stmt};
+use full_moon::node::Node;
+use full_moon::tokenizer::{tokens, Token, TokenType};
+use rand::seq::SliceRandom;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Range;
+
+/// Functions with fewer top-level statements are left whole
+const MIN_STATEMENTS: usize = 6;
+
+/// Top-level statements per part, roughly
+const STATEMENTS_PER_PART: usize = 3;
+
+/// Helpers one function is split into, at most
+const MAX_HELPERS: usize = 4;
+
+/// Locals a block may hold once its helpers are declared; Luau allows 200
+/// and dead code adds some of its own
+const MAX_BLOCK_LOCALS: usize = 150;
+
+/// Globals reaching the environment of the calling function
+const ENVIRONMENT_FUNCTIONS: &[&str] = &["getfenv", "setfenv"];
+
+/// Tokens after a name that assign to it
+const ASSIGNMENTS: &[&str] = &["=", "+=", "-=", "*=", "/=", "//=", "%=", "^=", "..="];
+
+/// A function split into a chain of helpers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlinedFunction {
+    /// Source lines of the function
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Byte offset of the function statement, where the helpers are declared
+    pub statement: usize,
+    /// Bytes of the function's closing `end`
+    pub end: Range<usize>,
+    /// Helpers in call order; each runs up to the next one's start
+    pub helpers: Vec<OutlinedHelper>,
+    /// Indices into `helpers` in the order they are written out
+    pub order: Vec<usize>,
+}
+
+/// One part of an outlined function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlinedHelper {
+    pub name: String,
+    /// Byte offset of its first statement
+    pub start: usize,
+    /// Variables passed in, in argument order, as named in the source
+    pub parameters: Vec<String>,
+    /// Whether the function's `...` is passed on
+    pub varargs: bool,
+}
+
+impl OutlinedFunction {
+    /// Source bytes moved into the helpers, the closing `end` included
+    pub fn moved(&self) -> Range<usize> {
+        self.helpers[0].start..self.end.end
+    }
+
+    /// Source bytes of the helper at `index`
+    pub fn helper_range(&self, index: usize) -> Range<usize> {
+        let end = self
+            .helpers
+            .get(index + 1)
+            .map_or(self.end.start, |next| next.start);
+        self.helpers[index].start..end
+    }
+}
+
+/// Plans the outlining of large functions
+pub struct FunctionOutliner {
+    rng: BoxedRng,
+}
+
+impl FunctionOutliner {
+    pub fn new() -> Self {
+        Self::with_rng(rng::entropy())
+    }
+
+    /// Create an outliner with a fixed seed for reproducible output
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(rng::seeded(seed))
+    }
+
+    /// Create an outliner drawing from `rng`
+    pub fn with_rng(rng: BoxedRng) -> Self {
+        Self { rng }
+    }
+
+    /// Plan the split of every function `eligible` accepts (by its first and
+    /// last line), naming helpers with `names`
+    ///
+    /// Needs the AST; a streamed parse outlines nothing. Functions nested in
+    /// a part that moves are left whole.
+    pub fn outline(
+        &mut self,
+        parse_result: &ParseResult,
+        names: &mut NameMangler,
+        eligible: impl Fn(usize, usize) -> bool,
+    ) -> Result<Vec<OutlinedFunction>> {
+        let Some(ast) = &parse_result.ast else {
+            return Ok(Vec::new());
+        };
+        let source = ast.nodes().to_string();
+        let all_tokens = tokens(&source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .collect();
+
+        let mut planner = Planner {
+            source: &source,
+            tokens: &significant,
+            names,
+            rng: &mut *self.rng,
+            eligible: &eligible,
+            planned: Vec::new(),
+        };
+        planner.block(ast.nodes());
+        tracing::debug!("Outlining {} functions", planner.planned.len());
+        Ok(planner.planned)
+    }
+}
+
+impl Default for FunctionOutliner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A place the body could be cut
+struct Cut {
+    /// Top-level statement the cut comes before
+    statement: usize,
+    start: usize,
+    /// Parameters and body locals the rest of the body uses
+    used: Vec<String>,
+    varargs: bool,
+}
+
+struct Planner<'a, F> {
+    source: &'a str,
+    tokens: &'a [&'a Token],
+    names: &'a mut NameMangler,
+    rng: &'a mut dyn ObfRng,
+    eligible: &'a F,
+    planned: Vec<OutlinedFunction>,
+}
+
+impl<F: Fn(usize, usize) -> bool> Planner<'_, F> {
+    fn block(&mut self, block: &Block) {
+        let mut locals: usize = block.stmts().map(|stmt| declared(stmt).len()).sum();
+        for stmt in block.stmts() {
+            let function = match stmt {
+                Stmt::LocalFunction(function) => Some((function.func_body(), false)),
+                Stmt::FunctionDeclaration(function) => Some((
+                    function.func_body(),
+                    function.name().method_name().is_some(),
+                )),
+                _ => None,
+            };
+            if let Some((body, method)) = function {
+                let room = MAX_BLOCK_LOCALS.saturating_sub(locals);
+                if let Some(planned) = self.function(stmt, body, method, room) {
+                    locals += planned.helpers.len();
+                    self.planned.push(planned);
+                }
+            }
+            for nested in nested_blocks(stmt) {
+                self.block(nested);
+            }
+        }
+    }
+
+    /// The plan for the function `stmt` declares, if it is worth splitting
+    /// into at most `room` helpers
+    fn function(
+        &mut self,
+        stmt: &Stmt,
+        body: &FunctionBody,
+        method: bool,
+        room: usize,
+    ) -> Option<OutlinedFunction> {
+        let start = stmt.start_position()?;
+        let end = body.end_position()?;
+        let statement = start.bytes();
+        let end = end.bytes().checked_sub(3)?..end.bytes();
+        if self.source.get(end.clone()) != Some("end")
+            || !(self.eligible)(start.line(), body.end_position()?.line())
+            || self
+                .planned
+                .iter()
+                .any(|planned| overlaps(&planned.moved(), &(statement..end.end)))
+        {
+            return None;
+        }
+
+        let block = body.block();
+        let mut starts = Vec::new();
+        let mut declarations = Vec::new();
+        for stmt in block.stmts() {
+            starts.push(stmt.start_position()?.bytes());
+            declarations.push(declared(stmt));
+        }
+        if let Some(last) = block.last_stmt() {
+            starts.push(last.start_position()?.bytes());
+            declarations.push(Vec::new());
+        }
+        let helpers = (starts.len() / STATEMENTS_PER_PART)
+            .saturating_sub(1)
+            .min(MAX_HELPERS)
+            .min(room);
+        if starts.len() < MIN_STATEMENTS || helpers == 0 {
+            return None;
+        }
+
+        let mut parameters = Vec::new();
+        let mut varargs = false;
+        if method {
+            parameters.push("self".to_string());
+        }
+        for parameter in body.parameters() {
+            match parameter {
+                Parameter::Name(name) => parameters.push(name.token().to_string()),
+                Parameter::Ellipse(_) => varargs = true,
+                _ => return None,
+            }
+        }
+
+        let body_end = self.index(end.start);
+        let function_tokens = &self.tokens[self.index(statement)..body_end];
+        if function_tokens
+            .iter()
+            .any(|t| identifier(t).is_some_and(|name| ENVIRONMENT_FUNCTIONS.contains(&name)))
+        {
+            return None;
+        }
+        let written = written_names(function_tokens);
+
+        let mut cuts = Vec::new();
+        for (k, &cut_start) in starts.iter().enumerate().skip(1) {
+            let visible: BTreeSet<&str> = parameters
+                .iter()
+                .chain(declarations[..k].iter().flatten())
+                .map(String::as_str)
+                .collect();
+            let rest = &self.tokens[self.index(cut_start)..body_end];
+            let used: BTreeSet<&str> = variables(rest)
+                .filter(|name| visible.contains(name))
+                .collect();
+            if used.iter().any(|name| written.contains(*name)) {
+                continue;
+            }
+            cuts.push(Cut {
+                statement: k,
+                start: cut_start,
+                used: used.into_iter().map(str::to_string).collect(),
+                varargs: varargs && rest.iter().any(|t| t.to_string() == "..."),
+            });
+        }
+
+        // Cuts as evenly spaced as the valid ones allow
+        let mut chosen: Vec<Cut> = Vec::new();
+        for part in 1..=helpers {
+            let target = part * starts.len() / (helpers + 1);
+            let after = chosen.last().map_or(0, |cut| cut.statement);
+            let Some(index) = cuts
+                .iter()
+                .enumerate()
+                .filter(|(_, cut)| cut.statement > after)
+                .min_by_key(|(_, cut)| cut.statement.abs_diff(target))
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+            chosen.push(cuts.remove(index));
+        }
+        if chosen.is_empty() {
+            return None;
+        }
+
+        let helpers: Vec<OutlinedHelper> = chosen
+            .into_iter()
+            .map(|cut| {
+                let mut parameters = cut.used;
+                parameters.shuffle(&mut *self.rng);
+                OutlinedHelper {
+                    name: self.names.fresh_name(),
+                    start: cut.start,
+                    parameters,
+                    varargs: cut.varargs,
+                }
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..helpers.len()).collect();
+        order.shuffle(&mut *self.rng);
+
+        Some(OutlinedFunction {
+            start_line: start.line(),
+            end_line: body.end_position()?.line(),
+            statement,
+            end,
+            helpers,
+            order,
+        })
+    }
+
+    /// Index of the first token starting at or after byte `offset`
+    fn index(&self, offset: usize) -> usize {
+        self.tokens
+            .partition_point(|t| t.start_position().bytes() < offset)
+    }
+}
+
+/// Locals a statement declares in its block
+fn declared(stmt: &Stmt) -> Vec<String> {
+    match stmt {
+        Stmt::LocalAssignment(local) => local
+            .names()
+            .iter()
+            .map(|name| name.token().to_string())
+            .collect(),
+        Stmt::LocalFunction(function) => vec![function.name().token().to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// Blocks directly inside a statement
+fn nested_blocks(stmt: &Stmt) -> Vec<&Block> {
+    match stmt {
+        Stmt::LocalFunction(function) => vec![function.func_body().block()],
+        Stmt::FunctionDeclaration(function) => vec![function.func_body().block()],
+        Stmt::Do(do_block) => vec![do_block.block()],
+        Stmt::While(while_loop) => vec![while_loop.block()],
+        Stmt::Repeat(repeat) => vec![repeat.block()],
+        Stmt::NumericFor(numeric_for) => vec![numeric_for.block()],
+        Stmt::GenericFor(generic_for) => vec![generic_for.block()],
+        Stmt::If(if_stmt) => std::iter::once(if_stmt.block())
+            .chain(if_stmt.else_if().into_iter().flatten().map(|e| e.block()))
+            .chain(if_stmt.else_block())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Names `tokens` may assign to
+///
+/// Targets of `=` and compound assignments outside table constructors,
+/// names before a `,` outside brackets (assignment lists, but also
+/// expression lists) and `function name()`. It may list too many, never
+/// too few, so nested closures count as well.
+fn written_names(tokens: &[&Token]) -> HashSet<String> {
+    let mut open: Vec<String> = Vec::new();
+    let mut written = HashSet::new();
+    // Inside `local a, b` or `for k, v`, expecting a name (or else a `,`)
+    let mut listing: Option<bool> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let text = token.to_string();
+        match text.as_str() {
+            "(" | "{" | "[" | "function" | "do" | "then" | "repeat" => open.push(text.clone()),
+            ")" => pop_until(&mut open, &["("]),
+            "}" => pop_until(&mut open, &["{"]),
+            "]" => pop_until(&mut open, &["["]),
+            "end" | "until" | "elseif" => {
+                pop_until(&mut open, &["function", "do", "then", "repeat"])
+            }
+            _ => {}
+        }
+
+        let name = identifier(token);
+        listing = match (listing, name.is_some(), text == ",") {
+            (Some(true), true, _) | (Some(false), _, true) => Some(!listing.unwrap_or(true)),
+            _ if matches!(text.as_str(), "local" | "for" | "return") => Some(true),
+            _ => None,
+        };
+        let Some(name) = name else {
+            continue;
+        };
+        // A declared name, or the name after it
+        if listing == Some(false) {
+            continue;
+        }
+
+        let previous = i.checked_sub(1).map(|p| tokens[p].to_string());
+        if matches!(previous.as_deref(), Some("." | ":")) {
+            continue;
+        }
+        let next = tokens.get(i + 1).map(|t| t.to_string()).unwrap_or_default();
+        let top = open.last().map(String::as_str);
+        let assigned = ASSIGNMENTS.contains(&next.as_str()) && top != Some("{");
+        let listed = next == "," && !matches!(top, Some("(" | "{" | "["));
+        let declared_function = previous.as_deref() == Some("function")
+            && next == "("
+            && i.checked_sub(2).map(|p| tokens[p].to_string()).as_deref() != Some("local");
+        if assigned || listed || declared_function {
+            written.insert(name.to_string());
+        }
+    }
+    written
+}
+
+/// Pop `open` up to and including the innermost of `openers`
+fn pop_until(open: &mut Vec<String>, openers: &[&str]) {
+    while let Some(top) = open.pop() {
+        if openers.contains(&top.as_str()) {
+            break;
+        }
+    }
+}
+
+/// Names in `tokens` that may refer to a variable (not `a.b` or `a:b`)
+fn variables<'a>(tokens: &'a [&'a Token]) -> impl Iterator<Item = &'a str> {
+    tokens.iter().enumerate().filter_map(move |(i, token)| {
+        let member = i > 0 && matches!(tokens[i - 1].to_string().as_str(), "." | ":");
+        identifier(token).filter(|_| !member)
+    })
+}
+
+fn identifier<'a>(token: &'a Token) -> Option<&'a str> {
+    match token.token_type() {
+        TokenType::Identifier { identifier } => Some(identifier.as_str()),
+        _ => None,
+    }
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obfuscation::{ObfuscatedScript, SourceRewriter};
+    use crate::parser::LuauParser;
+
+    const LARGE: &str = "local function f(a, b)
+    local c = a + b
+    print(c)
+    local d = c * 2
+    print(d, a)
+    local e = d - b
+    print(e)
+    print(a, b, c, d, e)
+    return e
+end
+print(f(1, 2))
+";
+
+    fn outline(source: &str, eligible: impl Fn(usize, usize) -> bool) -> Vec<OutlinedFunction> {
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let mut names = NameMangler::with_seed(&[], true, 1);
+        FunctionOutliner::with_seed(1)
+            .outline(&parse_result, &mut names, eligible)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_outlines_large_function() {
+        let outlined = outline(LARGE, |_, _| true);
+        assert_eq!(outlined.len(), 1);
+        let function = &outlined[0];
+        assert_eq!((function.start_line, function.end_line), (1, 10));
+        assert!(!function.helpers.is_empty());
+        let mut order = function.order.clone();
+        order.sort();
+        assert_eq!(order, (0..function.helpers.len()).collect::<Vec<_>>());
+        for helper in &function.helpers {
+            assert!(helper
+                .parameters
+                .iter()
+                .all(|p| ["a", "b", "c", "d", "e"].contains(&p.as_str())));
+            assert!(!helper.varargs);
+        }
+
+        let parse_result = LuauParser::new().parse(LARGE).unwrap();
+        let mut script = ObfuscatedScript::new();
+        script.outlined_functions = outlined.clone();
+        let code = SourceRewriter::new(&script)
+            .rewrite(LARGE, &parse_result)
+            .unwrap()
+            .code;
+        assert!(full_moon::parse(&code).is_ok(), "{}", code);
+        assert_eq!(code.lines().count(), LARGE.lines().count());
+        let declared: Vec<&str> = function
+            .order
+            .iter()
+            .map(|&i| function.helpers[i].name.as_str())
+            .collect();
+        assert!(code.starts_with(&format!(
+            "local {}; local function f(a, b)",
+            declared.join(", ")
+        )));
+        for helper in &function.helpers {
+            assert!(code.contains(&format!(" {} = function(", helper.name)));
+        }
+        assert!(code.ends_with("print(f(1, 2))\n"));
+    }
+
+    #[test]
+    fn test_written_locals_are_not_passed() {
+        let source = "local function count(a, ...)
+    local total = a
+    total = total + 1
+    print(total)
+    total = total + 1
+    print(total)
+    print(select('#', ...))
+    return total
+end
+";
+        for function in outline(source, |_, _| true) {
+            for helper in &function.helpers {
+                assert!(!helper.parameters.contains(&"total".to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_leaves_functions_whole() {
+        // Too small
+        assert!(outline(
+            "local function f(a)\n    print(a)\n    return a\nend\n",
+            |_, _| true
+        )
+        .is_empty());
+        // Not eligible
+        assert!(outline(LARGE, |_, _| false).is_empty());
+        // Reaching its environment
+        let source = LARGE.replace("print(c)", "setfenv(1, {})");
+        assert!(outline(&source, |_, _| true).is_empty());
+    }
+
+    #[test]
+    fn test_written_names() {
+        let source = "local x, y = 1, 2\nx = 3\nt.k = y\nlocal t2 = {y = 1}\nz += 1\n\
+                      function g() end\nprint(a, b)\n";
+        let all_tokens = tokens(source).unwrap();
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .collect();
+        let mut written: Vec<String> = written_names(&significant).into_iter().collect();
+        written.sort();
+        assert_eq!(written, ["g", "x", "z"]);
+    }
+}
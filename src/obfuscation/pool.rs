@@ -0,0 +1,249 @@
+//! Constant pool extraction and indexed lookup
+//!
+//! Moves string and numeric constants into a single shuffled, encrypted
+//! table emitted at the top of the script. Usages become accessor calls
+//! like `_K(17)`, which leaves nothing for a constant-folding deobfuscator
+//! to fold.
+
+use super::{ConstantObfuscator, EncryptedString, ObfRng, StringObfuscator};
+use crate::parser::{NumericLiteral, Sensitivity, StringLiteral};
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+/// Name of the generated pool accessor function
+pub const POOL_ACCESSOR: &str = "_K";
+
+/// Kind of value stored in a pool slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolEntryKind {
+    String,
+    Number,
+}
+
+/// A single encrypted pool slot
+#[derive(Debug, Clone)]
+pub struct PoolEntry {
+    /// 1-based slot index in the emitted table
+    pub slot: usize,
+    pub kind: PoolEntryKind,
+    pub encrypted: EncryptedString,
+}
+
+/// Shuffled, deduplicated table of encrypted constants
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPool {
+    entries: Vec<PoolEntry>,
+    slots: HashMap<(PoolEntryKind, String), usize>,
+}
+
+impl ConstantPool {
+    /// Build a pool from string and numeric literals
+    ///
+    /// Identical constants share one slot. Slot numbers are shuffled so pool
+    /// order reveals nothing about source order.
+    pub fn build(
+        string_obfuscator: &StringObfuscator,
+        constant_obfuscator: &ConstantObfuscator,
+        strings: &[StringLiteral],
+        numbers: &[NumericLiteral],
+        rng: &mut dyn ObfRng,
+    ) -> Result<Self> {
+        let mut unique: Vec<(PoolEntryKind, StringLiteral)> = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+
+        let tagged_strings = strings
+            .iter()
+            .cloned()
+            .map(|lit| (PoolEntryKind::String, lit));
+        let tagged_numbers = constant_obfuscator
+            .pool_literals(numbers)
+            .into_iter()
+            .map(|lit| (PoolEntryKind::Number, lit));
+
+        for (kind, lit) in tagged_strings.chain(tagged_numbers) {
+            if seen.insert((kind, lit.value.clone())) {
+                unique.push((kind, lit));
+            }
+        }
+
+        let mut slot_numbers: Vec<usize> = (1..=unique.len()).collect();
+        slot_numbers.shuffle(rng);
+
+        let mut pool = ConstantPool::default();
+        for ((kind, lit), slot) in unique.into_iter().zip(slot_numbers) {
+            let encrypted = string_obfuscator.encrypt_literal(&lit)?;
+            pool.slots.insert((kind, lit.value), slot);
+            pool.entries.push(PoolEntry {
+                slot,
+                kind,
+                encrypted,
+            });
+        }
+
+        // Emit in slot order so the table literal itself is shuffled relative to source
+        pool.entries.sort_by_key(|e| e.slot);
+
+        tracing::debug!("Built constant pool with {} entries", pool.entries.len());
+        Ok(pool)
+    }
+
+    /// Add a string after the pool was built, returning its slot
+    ///
+    /// Used for values the generated runtime needs but the source never
+    /// mentions, such as the violation webhook URL. New strings take the
+    /// next free slot.
+    pub fn insert_string(
+        &mut self,
+        string_obfuscator: &StringObfuscator,
+        value: &str,
+    ) -> Result<usize> {
+        if let Some(slot) = self.slot_of(PoolEntryKind::String, value) {
+            return Ok(slot);
+        }
+
+        let literal = StringLiteral {
+            value: value.to_string(),
+            line: 0,
+            column: 0,
+            sensitivity: Sensitivity::High,
+        };
+        let slot = self.entries.len() + 1;
+        let encrypted = string_obfuscator.encrypt_literal(&literal)?;
+        self.slots.insert((PoolEntryKind::String, literal.value), slot);
+        self.entries.push(PoolEntry {
+            slot,
+            kind: PoolEntryKind::String,
+            encrypted,
+        });
+        Ok(slot)
+    }
+
+    /// Pool entries in slot order
+    pub fn entries(&self) -> &[PoolEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Slot holding a constant, if pooled
+    pub fn slot_of(&self, kind: PoolEntryKind, value: &str) -> Option<usize> {
+        self.slots.get(&(kind, value.to_string())).copied()
+    }
+
+    /// Accessor expression replacing a pooled constant
+    pub fn accessor(&self, kind: PoolEntryKind, value: &str) -> Option<String> {
+        self.slot_of(kind, value)
+            .map(|slot| format!("{}({})", POOL_ACCESSOR, slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoContext;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn string_lit(value: &str) -> StringLiteral {
+        StringLiteral {
+            value: value.to_string(),
+            line: 1,
+            column: 0,
+            sensitivity: Sensitivity::Medium,
+        }
+    }
+
+    fn number_lit(value: &str) -> NumericLiteral {
+        NumericLiteral::new(value, 1, 0)
+    }
+
+    #[test]
+    fn test_pool_deduplicates_and_indexes() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let strings = StringObfuscator::new(&crypto_ctx);
+        let constants = ConstantObfuscator::new();
+
+        let pool = ConstantPool::build(
+            &strings,
+            &constants,
+            &[string_lit("Players"), string_lit("Players"), string_lit("42")],
+            &[number_lit("42"), number_lit("3.5")],
+            &mut StdRng::seed_from_u64(1),
+        )
+        .unwrap();
+
+        // "42" as a string and 42 as a number are distinct slots
+        assert_eq!(pool.len(), 4);
+
+        let slot = pool.slot_of(PoolEntryKind::String, "Players").unwrap();
+        assert!((1..=4).contains(&slot));
+        assert_eq!(
+            pool.accessor(PoolEntryKind::Number, "3.5"),
+            Some(format!("_K({})", pool.slot_of(PoolEntryKind::Number, "3.5").unwrap()))
+        );
+        assert!(pool.accessor(PoolEntryKind::Number, "7").is_none());
+    }
+
+    #[test]
+    fn test_pool_entries_sorted_by_slot() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let strings = StringObfuscator::new(&crypto_ctx);
+        let constants = ConstantObfuscator::new();
+
+        let literals: Vec<StringLiteral> = (0..10).map(|i| string_lit(&format!("s{}", i))).collect();
+        let pool =
+            ConstantPool::build(&strings, &constants, &literals, &[], &mut StdRng::from_entropy())
+                .unwrap();
+
+        let slots: Vec<usize> = pool.entries().iter().map(|e| e.slot).collect();
+        assert_eq!(slots, (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_seeded_pool_layout_is_deterministic() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let strings = StringObfuscator::new(&crypto_ctx);
+        let constants = ConstantObfuscator::new();
+
+        let literals: Vec<StringLiteral> = (0..10).map(|i| string_lit(&format!("s{}", i))).collect();
+        let build = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            ConstantPool::build(&strings, &constants, &literals, &[], &mut rng).unwrap()
+        };
+        let (a, b) = (build(5), build(5));
+
+        for lit in &literals {
+            assert_eq!(
+                a.slot_of(PoolEntryKind::String, &lit.value),
+                b.slot_of(PoolEntryKind::String, &lit.value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_string_appends_after_existing_slots() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let strings = StringObfuscator::new(&crypto_ctx);
+        let constants = ConstantObfuscator::new();
+
+        let literals = [string_lit("a"), string_lit("b")];
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut pool = ConstantPool::build(&strings, &constants, &literals, &[], &mut rng).unwrap();
+
+        let slot = pool.insert_string(&strings, "https://example.com/hook").unwrap();
+        assert_eq!(slot, 3);
+        assert_eq!(pool.insert_string(&strings, "https://example.com/hook").unwrap(), 3);
+        assert_eq!(
+            pool.insert_string(&strings, "a").unwrap(),
+            pool.slot_of(PoolEntryKind::String, "a").unwrap()
+        );
+        assert_eq!(pool.entries().last().unwrap().slot, 3);
+    }
+}
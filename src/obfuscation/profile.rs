@@ -0,0 +1,238 @@
+//! Hot function lists for per-function obfuscation budgets
+//!
+//! Functions named in a profile skip the passes that cost time on every call
+//! (control flow flattening and dead code), and the rest of the script gets
+//! both at full strength. A profile is either a plain list of names, one per
+//! line with `#` comments, or a CSV export (e.g. from the MicroProfiler) with
+//! a name column and a time column; from a CSV, every function taking at
+//! least `HOT_TIME_SHARE` of the total time counts as hot.
+//!
+//! `event_handlers` finds the functions connected to per-frame signals, for
+//! presets that leave them unflattened.
+
+use crate::parser::FunctionInfo;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Share of profiled time that makes a function hot
+pub const HOT_TIME_SHARE: f64 = 0.05;
+
+/// CSV headers accepted for the function name column
+const NAME_COLUMNS: &[&str] = &["name", "label", "function", "scope"];
+
+/// CSV headers accepted for the time column
+const TIME_COLUMNS: &[&str] = &["time", "total", "total_ms", "time_ms", "ms", "inclusive"];
+
+/// Signal methods that connect a handler
+const CONNECT_METHODS: &[&str] = &["Connect", "ConnectParallel", "Once"];
+
+/// Function names to keep fast
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HotFunctions {
+    names: BTreeSet<String>,
+}
+
+impl HotFunctions {
+    pub fn new<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            names: names.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Load a profile; `.csv` files are read as profiler exports
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read hot function list: {:?}", path))?;
+        let is_csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+        if is_csv {
+            Self::parse_csv(&content)
+        } else {
+            Ok(Self::parse_list(&content))
+        }
+    }
+
+    /// One function name per line; blank lines and `#` comments are skipped
+    pub fn parse_list(content: &str) -> Self {
+        Self::new(
+            content
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|line| !line.is_empty()),
+        )
+    }
+
+    /// Profiler CSV export with a header row
+    pub fn parse_csv(content: &str) -> Result<Self> {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<String> = lines
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .map(|column| column.trim().trim_matches('"').to_ascii_lowercase())
+            .collect();
+
+        let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+        let (Some(name_col), Some(time_col)) = (column(NAME_COLUMNS), column(TIME_COLUMNS)) else {
+            return Err(ObfuscatorError::ConfigError(format!(
+                "Profile CSV needs a name column ({}) and a time column ({})",
+                NAME_COLUMNS.join("/"),
+                TIME_COLUMNS.join("/")
+            ))
+            .into());
+        };
+
+        let mut samples: Vec<(String, f64)> = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim().trim_matches('"')).collect();
+            let (Some(name), Some(time)) = (fields.get(name_col), fields.get(time_col)) else {
+                continue;
+            };
+            let time: f64 = time.parse().map_err(|_| {
+                ObfuscatorError::ConfigError(format!(
+                    "Profile CSV row {}: invalid time {:?}",
+                    i + 2,
+                    time
+                ))
+            })?;
+            samples.push((name.to_string(), time));
+        }
+
+        let total: f64 = samples.iter().map(|(_, time)| time).sum();
+        if total <= 0.0 {
+            return Ok(Self::default());
+        }
+        Ok(Self::new(
+            samples
+                .into_iter()
+                .filter(|(_, time)| time / total >= HOT_TIME_SHARE)
+                .map(|(name, _)| name),
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether `name` is listed, either in full (`Module:update`) or by its
+    /// last segment (`update`)
+    pub fn is_hot(&self, name: &str) -> bool {
+        let short = name.rsplit(['.', ':']).next().unwrap_or(name);
+        self.names.contains(name) || self.names.contains(short)
+    }
+
+    /// Named functions in `functions` that are hot
+    pub fn matching<'a>(
+        &'a self,
+        functions: &'a [FunctionInfo],
+    ) -> impl Iterator<Item = &'a FunctionInfo> {
+        functions
+            .iter()
+            .filter(|f| f.name.as_deref().is_some_and(|name| self.is_hot(name)))
+    }
+}
+
+/// Start lines of the functions connected to one of `events`
+///
+/// Finds `signal.Event:Connect(function ... end)` (or `ConnectParallel`,
+/// `Once`) and, for `signal.Event:Connect(onStep)`, the definition of
+/// `onStep` in the same script (`[local] function onStep` or
+/// `local onStep = function`).
+pub fn event_handlers(source: &str, events: &[String]) -> Result<BTreeSet<usize>> {
+    let all_tokens = tokens(source)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+    let significant: Vec<&Token> = all_tokens
+        .iter()
+        .filter(|t| !t.token_type().is_trivia())
+        .collect();
+    let symbol = |i: usize| match significant.get(i).map(|t| t.token_type()) {
+        Some(TokenType::Symbol { symbol }) => Some(*symbol),
+        _ => None,
+    };
+    let identifier = |i: usize| match significant.get(i).map(|t| t.token_type()) {
+        Some(TokenType::Identifier { identifier }) => Some(identifier.as_str()),
+        _ => None,
+    };
+    let line = |i: usize| significant[i].start_position().line();
+
+    let mut lines = BTreeSet::new();
+    let mut named = BTreeSet::new();
+    for i in 0..significant.len() {
+        let connected = symbol(i) == Some(Symbol::Dot)
+            && identifier(i + 1).is_some_and(|event| events.iter().any(|e| e == event))
+            && symbol(i + 2) == Some(Symbol::Colon)
+            && identifier(i + 3).is_some_and(|method| CONNECT_METHODS.contains(&method))
+            && symbol(i + 4) == Some(Symbol::LeftParen);
+        if !connected {
+            continue;
+        }
+        if symbol(i + 5) == Some(Symbol::Function) {
+            lines.insert(line(i + 5));
+        } else if let Some(handler) = identifier(i + 5) {
+            named.insert(handler);
+        }
+    }
+
+    for i in 0..significant.len() {
+        if !identifier(i).is_some_and(|name| named.contains(name)) {
+            continue;
+        }
+        if i > 0 && symbol(i - 1) == Some(Symbol::Function) {
+            lines.insert(line(i - 1));
+        } else if symbol(i + 1) == Some(Symbol::Equal) && symbol(i + 2) == Some(Symbol::Function) {
+            lines.insert(line(i + 2));
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_matches_full_and_short_names() {
+        let hot = HotFunctions::parse_list("# per-frame work\nupdate\n\nRender.step  # camera\n");
+        assert_eq!(hot.len(), 2);
+        assert!(hot.is_hot("update"));
+        assert!(hot.is_hot("Module:update"));
+        assert!(hot.is_hot("Render.step"));
+        assert!(!hot.is_hot("step"));
+        assert!(!hot.is_hot("init"));
+    }
+
+    #[test]
+    fn test_csv_keeps_functions_above_time_share() {
+        let csv = "Label,Calls,Time\nupdate,600,80.0\n\"render\",600,17.5\ninit,1,2.5\n";
+        let hot = HotFunctions::parse_csv(csv).unwrap();
+        assert_eq!(hot, HotFunctions::new(["update", "render"]));
+
+        assert!(HotFunctions::parse_csv("a,b\n1,2\n").is_err());
+        assert!(HotFunctions::parse_csv("name,time\nupdate,fast\n").is_err());
+    }
+
+    #[test]
+    fn test_event_handlers_finds_connected_functions() {
+        let source = "local RunService = game:GetService(\"RunService\")\n\
+                      local function onStep(dt)\n\
+                          print(dt)\n\
+                      end\n\
+                      RunService.Heartbeat:Connect(onStep)\n\
+                      RunService.RenderStepped:Connect(function(dt)\n\
+                          print(dt)\n\
+                      end)\n\
+                      workspace.ChildAdded:Connect(function(child) end)\n";
+        let events = ["RenderStepped".to_string(), "Heartbeat".to_string()];
+        let lines: Vec<usize> = event_handlers(source, &events).unwrap().into_iter().collect();
+        assert_eq!(lines, [2, 6]);
+    }
+}
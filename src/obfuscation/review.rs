@@ -0,0 +1,257 @@
+//! Reviewable transformation decisions
+//!
+//! A review plan lists what an obfuscation run did — each encrypted string
+//! site, each renamed identifier and each function the flattener reached —
+//! so a user can switch items off before the output is written. The items
+//! left off become [`Exclusions`] for the next run.
+
+use super::ObfuscatedScript;
+use crate::parser::ParseResult;
+use std::collections::BTreeSet;
+
+/// What a review item stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReviewKind {
+    /// A string literal that is encrypted
+    String,
+    /// An identifier that is renamed, everywhere it appears
+    Name,
+    /// A function whose control flow is flattened
+    Function,
+}
+
+impl std::fmt::Display for ReviewKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReviewKind::String => "string",
+            ReviewKind::Name => "name",
+            ReviewKind::Function => "function",
+        })
+    }
+}
+
+/// One transformation decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewItem {
+    pub kind: ReviewKind,
+    /// String value, original name or function name
+    pub label: String,
+    /// What the item becomes: the mangled name, or empty
+    pub detail: String,
+    pub line: usize,
+    pub column: usize,
+    /// Whether the transformation is kept
+    pub enabled: bool,
+}
+
+/// Decisions of one run, in kind then source order
+#[derive(Debug, Clone, Default)]
+pub struct ReviewPlan {
+    pub items: Vec<ReviewItem>,
+}
+
+impl ReviewPlan {
+    /// Everything `obfuscated` changed in the script `parse_result` describes
+    pub fn new(parse_result: &ParseResult, obfuscated: &ObfuscatedScript) -> Self {
+        let mut items = Vec::new();
+
+        // One item per site: identical literals share an entry but not a decision
+        let mut sites: BTreeSet<(usize, usize)> = BTreeSet::new();
+        for entry in &obfuscated.encrypted_strings {
+            if entry.fragment.as_ref().is_some_and(|f| f.index > 0) {
+                continue;
+            }
+            sites.extend(entry.sites.iter().copied());
+            sites.insert((entry.line, entry.column));
+        }
+        sites.extend(obfuscated.pooled_strings.iter().map(|p| (p.line, p.column)));
+        for (line, column) in sites {
+            let label = parse_result
+                .strings
+                .iter()
+                .find(|s| s.line == line && s.column == column)
+                .map(|s| s.value.clone())
+                .or_else(|| {
+                    obfuscated
+                        .folded_strings
+                        .iter()
+                        .find(|fold| fold.line == line && fold.column == column)
+                        .map(|fold| fold.value.clone())
+                })
+                .unwrap_or_default();
+            items.push(ReviewItem {
+                kind: ReviewKind::String,
+                label,
+                detail: String::new(),
+                line,
+                column,
+                enabled: true,
+            });
+        }
+
+        let mut names: Vec<(&String, &String)> = obfuscated.name_mappings.iter().collect();
+        names.sort();
+        for (name, mangled) in names {
+            items.push(ReviewItem {
+                kind: ReviewKind::Name,
+                label: name.clone(),
+                detail: mangled.clone(),
+                line: 0,
+                column: 0,
+                enabled: true,
+            });
+        }
+
+        for function in &parse_result.functions {
+            let flattened = obfuscated.flattened_blocks.iter().any(|block| {
+                block.start_line <= function.end_line && function.line <= block.end_line
+            });
+            if flattened {
+                items.push(ReviewItem {
+                    kind: ReviewKind::Function,
+                    label: function.name.clone().unwrap_or_else(|| "<anonymous>".to_string()),
+                    detail: format!("lines {}-{}", function.line, function.end_line),
+                    line: function.line,
+                    column: function.column,
+                    enabled: true,
+                });
+            }
+        }
+
+        Self { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Switch item `index` on or off
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(item) = self.items.get_mut(index) {
+            item.enabled = !item.enabled;
+        }
+    }
+
+    /// Switch every item of `kind` on or off
+    pub fn set_all(&mut self, kind: ReviewKind, enabled: bool) {
+        for item in self.items.iter_mut().filter(|item| item.kind == kind) {
+            item.enabled = enabled;
+        }
+    }
+
+    /// The items switched off, for the next run to leave alone
+    pub fn exclusions(&self) -> Exclusions {
+        let mut exclusions = Exclusions::default();
+        for item in self.items.iter().filter(|item| !item.enabled) {
+            match item.kind {
+                ReviewKind::String => {
+                    exclusions.strings.insert((item.line, item.column));
+                }
+                ReviewKind::Name => {
+                    exclusions.names.insert(item.label.clone());
+                }
+                ReviewKind::Function => {
+                    exclusions.functions.insert(item.line);
+                }
+            }
+        }
+        exclusions
+    }
+}
+
+/// Transformations to skip, as a review left them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Exclusions {
+    /// `(line, column)` of string literals to leave readable
+    pub strings: BTreeSet<(usize, usize)>,
+    /// Identifiers to keep
+    pub names: BTreeSet<String>,
+    /// Start lines of functions to leave unflattened
+    pub functions: BTreeSet<usize>,
+}
+
+impl Exclusions {
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty() && self.names.is_empty() && self.functions.is_empty()
+    }
+
+    /// Also skip everything `other` skips
+    pub fn extend(&mut self, other: Exclusions) {
+        self.strings.extend(other.strings);
+        self.names.extend(other.names);
+        self.functions.extend(other.functions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{AnalysisOptions, Analyzer};
+    use crate::crypto::CryptoContext;
+    use crate::obfuscation::{ObfuscationTier, Obfuscator};
+    use crate::parser::LuauParser;
+
+    const SOURCE: &str = r#"local secret = "sk_live_0123456789abcdef"
+local function greet(name)
+    print("Hello, " .. name)
+end
+if secret then
+    greet("Player")
+end
+"#;
+
+    fn run(exclusions: Exclusions) -> (ParseResult, ObfuscatedScript) {
+        let parsed = LuauParser::new().parse(SOURCE).unwrap();
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parsed).unwrap();
+        let crypto = CryptoContext::new("review_password", None).unwrap();
+        let mut obfuscator = Obfuscator::with_seed(ObfuscationTier::Premium, crypto, 7);
+        obfuscator.set_exclusions(exclusions);
+        let obfuscated = obfuscator.obfuscate(&parsed, &analysis).unwrap();
+        (parsed, obfuscated)
+    }
+
+    #[test]
+    fn test_plan_lists_each_decision() {
+        let (parsed, obfuscated) = run(Exclusions::default());
+        let plan = ReviewPlan::new(&parsed, &obfuscated);
+
+        let of = |kind: ReviewKind| plan.items.iter().filter(move |item| item.kind == kind);
+        let strings: Vec<&str> = of(ReviewKind::String).map(|i| i.label.as_str()).collect();
+        assert!(strings.contains(&"sk_live_0123456789abcdef"));
+        assert!(of(ReviewKind::Name).any(|i| i.label == "secret" && !i.detail.is_empty()));
+        assert!(plan.items.iter().all(|item| item.enabled));
+        assert!(plan.exclusions().is_empty());
+    }
+
+    #[test]
+    fn test_exclusions_skip_switched_off_items() {
+        let (parsed, obfuscated) = run(Exclusions::default());
+        let mut plan = ReviewPlan::new(&parsed, &obfuscated);
+        let secret = plan
+            .items
+            .iter()
+            .position(|item| item.label == "sk_live_0123456789abcdef")
+            .unwrap();
+        plan.toggle(secret);
+        plan.set_all(ReviewKind::Name, false);
+        plan.set_all(ReviewKind::Function, false);
+
+        let exclusions = plan.exclusions();
+        assert_eq!(exclusions.strings, BTreeSet::from([(1, 16)]));
+        assert!(exclusions.names.contains("secret"));
+
+        let (_, rerun) = run(exclusions);
+        assert!(rerun
+            .encrypted_strings
+            .iter()
+            .all(|entry| entry.original != "sk_live_0123456789abcdef"));
+        assert!(rerun.pooled_strings.iter().all(|p| p.line != 1));
+        assert!(rerun.name_mappings.is_empty());
+        assert!(rerun.flattened_blocks.iter().all(|block| {
+            !parsed
+                .functions
+                .iter()
+                .any(|f| block.start_line <= f.end_line && f.line <= block.end_line)
+        }));
+    }
+}
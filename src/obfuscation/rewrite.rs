@@ -0,0 +1,632 @@
+//! Applying an obfuscation run to the source text
+//!
+//! Works token by token so everything the passes didn't touch (layout,
+//! comments, type annotations) comes through unchanged:
+//! - folded constant expressions (`"a" .. "b"`, `60 * 60`) become their one
+//!   literal, encrypted or obfuscated or not
+//! - encrypted string literals become `_decrypt(i)` calls, indexed like
+//!   `ObfuscatedScript::string_table`; in interpolated strings each fragment
+//!   becomes an interpolation of its own (`` `Hi {name}` `` →
+//!   `` `{_decrypt(i)}{name}` ``)
+//! - pooled literals and obfuscated numbers become their expressions
+//! - mapped identifiers are renamed, except field names (`a.b`, `a:b()`,
+//!   `{b = ...}`)
+//! - rewritten table keys are renamed, or become lookups through the string
+//!   table (`a.b` → `a[_decrypt(i)]`, `a:b()` → `a[_decrypt(i)](a)`)
+//! - dead code snippets are spread between top-level statements
+//! - outlined functions end in a call to their first helper, and the
+//!   helpers, with the edits inside them, are assigned after them
+//! - the settings block is cut out (it is written above the code), leaving
+//!   its lines blank
+//!
+//! Flattened blocks are not spliced in yet; `flattened_blocks` only reports
+//! what the flattener would dispatch.
+
+use super::{ObfuscatedScript, ObfuscationResult, OutlinedHelper};
+use crate::analysis::{FoldedConstant, KeySite, KeySiteKind};
+use crate::parser::ParseResult;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::node::Node;
+use full_moon::tokenizer::{tokens, InterpolatedStringKind, Symbol, Token, TokenType};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Rewrites source text with the output of `Obfuscator::obfuscate`
+pub struct SourceRewriter<'a> {
+    script: &'a ObfuscatedScript,
+}
+
+impl<'a> SourceRewriter<'a> {
+    pub fn new(script: &'a ObfuscatedScript) -> Self {
+        Self { script }
+    }
+
+    /// Rewrite `source` (already parsed into `parse_result`)
+    pub fn rewrite(&self, source: &str, parse_result: &ParseResult) -> Result<ObfuscationResult> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .collect();
+
+        let literals = self.literal_replacements();
+        let key_sites: HashMap<(usize, usize), &KeySite> = self
+            .script
+            .key_sites
+            .iter()
+            .map(|site| ((site.line, site.column), site))
+            .collect();
+        // Whether each fold is a number
+        let folds: HashMap<(usize, usize), (&FoldedConstant, bool)> = self
+            .script
+            .folded_strings
+            .iter()
+            .map(|fold| (fold, false))
+            .chain(self.script.folded_numbers.iter().map(|fold| (fold, true)))
+            .map(|(fold, number)| ((fold.line, fold.column), (fold, number)))
+            .collect();
+        // Last token of the folded expression being skipped
+        let mut folded_until = None;
+        // Byte offset → replacement covering `start..end`
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        // Open brackets and blocks, to tell `{name = ...}` keys from
+        // assignments (including those in a function inside a table)
+        let mut brackets: Vec<Symbol> = Vec::new();
+        let config_span = self.script.config_block.as_ref().map(|block| {
+            let blank = "\n".repeat(block.end_line - block.start_line);
+            edits.push((block.span.0, block.span.1, blank));
+            block.span.0..block.span.1
+        });
+
+        for (i, token) in significant.iter().enumerate() {
+            let start = token.start_position().bytes();
+            let end = token.end_position().bytes();
+            let position = (token.start_position().line(), token.start_position().character());
+
+            if config_span.as_ref().is_some_and(|span| span.contains(&start)) {
+                continue;
+            }
+            if folded_until.is_some_and(|last| i <= last) {
+                continue;
+            }
+            if let Some(&(fold, number)) = folds.get(&position) {
+                let end_position = (fold.end_line, fold.end_column);
+                let last = (i..significant.len())
+                    .find(|&j| {
+                        let at = significant[j].start_position();
+                        (at.line(), at.character()) == end_position
+                    })
+                    .unwrap_or(i);
+                let expr = match (literals.get(&position), number) {
+                    (Some(expr), true) => format!("({})", expr),
+                    (Some(expr), false) => expr.clone(),
+                    // `x - -(2 ^ 10)` mustn't become a `--` comment
+                    (None, true) if fold.value.starts_with('-') => format!("({})", fold.value),
+                    (None, true) => fold.value.clone(),
+                    (None, false) => format!("\"{}\"", fold.value),
+                };
+                // Line breaks inside the expression are kept so later lines keep their numbers
+                let breaks = "\n".repeat(fold.end_line - fold.line);
+                edits.push((start, significant[last].end_position().bytes(), expr + &breaks));
+                folded_until = Some(last);
+                continue;
+            }
+
+            match token.token_type() {
+                TokenType::Symbol { symbol } => match symbol {
+                    Symbol::LeftBrace
+                    | Symbol::LeftParen
+                    | Symbol::LeftBracket
+                    | Symbol::Function
+                    | Symbol::Do
+                    | Symbol::Then
+                    | Symbol::Repeat => brackets.push(*symbol),
+                    Symbol::RightBrace
+                    | Symbol::RightParen
+                    | Symbol::RightBracket
+                    | Symbol::End
+                    | Symbol::Until
+                    | Symbol::ElseIf => {
+                        brackets.pop();
+                    }
+                    _ => {}
+                },
+                TokenType::StringLiteral { .. } => {
+                    if let Some(expr) = literals.get(&position) {
+                        // `f "x"` call sugar needs real parentheses once the literal is gone
+                        let sugar = i > 0 && is_call_target(significant[i - 1]);
+                        let expr = if sugar { format!("({})", expr) } else { expr.clone() };
+                        edits.push((start, end, expr));
+                    } else if let Some(mangled) = key_sites
+                        .get(&position)
+                        .and_then(|site| self.script.key_mappings.get(&site.key))
+                    {
+                        edits.push((start, end, format!("\"{}\"", mangled)));
+                    }
+                }
+                TokenType::InterpolatedString { kind, .. } => {
+                    if let Some(expr) = literals.get(&position) {
+                        edits.push((start, end, interpolated_fragment(*kind, expr)));
+                    }
+                }
+                TokenType::Number { .. } => {
+                    if let Some(expr) = literals.get(&position) {
+                        edits.push((start, end, format!("({})", expr)));
+                    }
+                }
+                TokenType::Identifier { identifier } => {
+                    if let Some(site) = key_sites.get(&position) {
+                        edits.extend(self.key_edits(site, &significant, i, &literals));
+                        continue;
+                    }
+                    let Some(mangled) = self.script.name_mappings.get(identifier.as_str()) else {
+                        continue;
+                    };
+                    let member = i > 0
+                        && matches!(
+                            symbol_of(significant[i - 1]),
+                            Some(Symbol::Dot | Symbol::Colon)
+                        );
+                    let next = significant.get(i + 1).and_then(|t| symbol_of(t));
+                    let table_key = brackets.last() == Some(&Symbol::LeftBrace)
+                        && next == Some(Symbol::Equal);
+                    if !member && !table_key {
+                        edits.push((start, end, mangled.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        edits.extend(self.dead_code_insertions(parse_result));
+        edits.sort_by_key(|(start, end, _)| (*start, *end));
+        let edits = self.outline_edits(source, edits);
+        let code = splice(source, 0..source.len(), &edits);
+
+        Ok(ObfuscationResult {
+            code,
+            constant_pool: self.script.constant_pool.clone(),
+            environment_anchor: self.script.environment_anchor.clone(),
+        })
+    }
+
+    /// `(line, column)` of each replaced literal → its expression
+    fn literal_replacements(&self) -> HashMap<(usize, usize), String> {
+        // Split strings have one entry per fragment, all sharing the sites
+        let mut decrypts: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (index, entry) in self.script.encrypted_strings.iter().enumerate() {
+            for site in &entry.sites {
+                decrypts.entry(*site).or_default().push(index + 1);
+            }
+        }
+
+        let mut replacements: HashMap<(usize, usize), String> = decrypts
+            .into_iter()
+            .map(|(site, indices)| {
+                let calls: Vec<String> =
+                    indices.iter().map(|i| format!("_decrypt({})", i)).collect();
+                let expr = match calls.len() {
+                    1 => calls.into_iter().next().unwrap_or_default(),
+                    _ => format!("({})", calls.join(" .. ")),
+                };
+                (site, expr)
+            })
+            .collect();
+
+        for constant in self
+            .script
+            .obfuscated_constants
+            .iter()
+            .chain(&self.script.pooled_strings)
+        {
+            replacements.insert(
+                (constant.line, constant.column),
+                constant.obfuscated_expr.clone(),
+            );
+        }
+        replacements
+    }
+
+    /// Edits rewriting the table key identifier at `significant[i]`
+    fn key_edits(
+        &self,
+        site: &KeySite,
+        significant: &[&Token],
+        i: usize,
+        literals: &HashMap<(usize, usize), String>,
+    ) -> Vec<(usize, usize, String)> {
+        let start = significant[i].start_position().bytes();
+        let end = significant[i].end_position().bytes();
+        if let Some(mangled) = self.script.key_mappings.get(&site.key) {
+            return vec![(start, end, mangled.clone())];
+        }
+
+        let Some(expr) = literals.get(&(site.line, site.column)) else {
+            return Vec::new();
+        };
+        if site.kind == KeySiteKind::Constructor {
+            return vec![(start, end, format!("[{}]", expr))];
+        }
+        // Everything else replaces the `.`/`:` before the key as well
+        let Some(separator) = i.checked_sub(1).map(|p| significant[p].start_position().bytes())
+        else {
+            return Vec::new();
+        };
+        let renamed = |name: &str| {
+            self.script
+                .name_mappings
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| name.to_string())
+        };
+        // `(` → `(first, ` (or `(first` before `)`)
+        let prepend_argument = |paren: usize, first: String| {
+            let closes = significant
+                .get(paren + 1)
+                .is_some_and(|t| symbol_of(t) == Some(Symbol::RightParen));
+            let open = significant[paren];
+            let replacement = if closes {
+                format!("({}", first)
+            } else {
+                format!("({}, ", first)
+            };
+            (open.start_position().bytes(), open.end_position().bytes(), replacement)
+        };
+
+        match site.kind {
+            KeySiteKind::Field => vec![(separator, end, format!("[{}]", expr))],
+            // `t:key(...)` → `t[key](t, ...)`
+            KeySiteKind::Method { plain: true } => {
+                let receiver = match i.checked_sub(2).map(|p| significant[p].token_type()) {
+                    Some(TokenType::Identifier { identifier }) => renamed(identifier.as_str()),
+                    _ => return Vec::new(),
+                };
+                vec![
+                    (separator, end, format!("[{}]", expr)),
+                    prepend_argument(i + 1, receiver),
+                ]
+            }
+            // `function t:key(...)` → `t[key] = function(self, ...)`
+            KeySiteKind::Declaration { method } => {
+                let Some(keyword) = i
+                    .checked_sub(3)
+                    .map(|p| significant[p])
+                    .filter(|t| symbol_of(t) == Some(Symbol::Function))
+                else {
+                    return Vec::new();
+                };
+                let mut edits = vec![
+                    (keyword.start_position().bytes(), keyword.end_position().bytes(), String::new()),
+                    (separator, end, format!("[{}] = function", expr)),
+                ];
+                let paren = (i + 1..significant.len())
+                    .find(|&p| symbol_of(significant[p]) == Some(Symbol::LeftParen));
+                if let (true, Some(paren)) = (method, paren) {
+                    edits.push(prepend_argument(paren, renamed("self")));
+                }
+                edits
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// `edits` with the parts of outlined functions moved into helpers
+    ///
+    /// The edits inside a moved part go with it. Helpers are joined with
+    /// spaces, so the line count, and with it the lines after the function,
+    /// stays the same.
+    fn outline_edits(
+        &self,
+        source: &str,
+        edits: Vec<(usize, usize, String)>,
+    ) -> Vec<(usize, usize, String)> {
+        let functions = &self.script.outlined_functions;
+        if functions.is_empty() {
+            return edits;
+        }
+        let mut kept = Vec::with_capacity(edits.len());
+        let mut moved: Vec<Vec<(usize, usize, String)>> = vec![Vec::new(); functions.len()];
+        for edit in edits {
+            match functions.iter().position(|f| f.moved().contains(&edit.0)) {
+                Some(index) => moved[index].push(edit),
+                None => kept.push(edit),
+            }
+        }
+
+        let renamed = |name: &str| {
+            self.script
+                .name_mappings
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| name.to_string())
+        };
+        let arguments = |helper: &OutlinedHelper| {
+            let mut arguments: Vec<String> =
+                helper.parameters.iter().map(|name| renamed(name)).collect();
+            if helper.varargs {
+                arguments.push("...".to_string());
+            }
+            arguments.join(", ")
+        };
+        let call = |helper: &OutlinedHelper| {
+            format!("return {}({}) ", helper.name, arguments(helper))
+        };
+
+        for (function, inside) in functions.iter().zip(moved) {
+            let names: Vec<&str> = function
+                .order
+                .iter()
+                .map(|&index| function.helpers[index].name.as_str())
+                .collect();
+            let declaration = format!("local {}; ", names.join(", "));
+            kept.push((function.statement, function.statement, declaration));
+
+            let mut text = format!("{}end", call(&function.helpers[0]));
+            for &index in &function.order {
+                let helper = &function.helpers[index];
+                let range = function.helper_range(index);
+                let body = splice(
+                    source,
+                    range.clone(),
+                    inside.iter().filter(|edit| range.contains(&edit.0)),
+                );
+                let next = function.helpers.get(index + 1).map(call).unwrap_or_default();
+                text.push_str(&format!(
+                    " {} = function({}) {}{}end;",
+                    helper.name,
+                    arguments(helper),
+                    body,
+                    next
+                ));
+            }
+            let moved = function.moved();
+            kept.push((moved.start, moved.end, text));
+        }
+        kept.sort_by_key(|(start, end, _)| (*start, *end));
+        kept
+    }
+
+    /// Snippets inserted evenly before top-level statements
+    fn dead_code_insertions(&self, parse_result: &ParseResult) -> Vec<(usize, usize, String)> {
+        let snippets = &self.script.dead_code_snippets;
+        let starts: Vec<usize> = parse_result
+            .ast
+            .iter()
+            .flat_map(|ast| ast.nodes().stmts())
+            .filter_map(|stmt| stmt.start_position().map(|p| p.bytes()))
+            .collect();
+        if starts.is_empty() {
+            return Vec::new();
+        }
+
+        snippets
+            .iter()
+            .enumerate()
+            .map(|(k, snippet)| {
+                let at = starts[k * starts.len() / snippets.len()];
+                (at, at, format!("{}\n", snippet))
+            })
+            .collect()
+    }
+}
+
+/// `source[range]` with `edits` (sorted, all inside it) applied
+fn splice<'e>(
+    source: &str,
+    range: Range<usize>,
+    edits: impl IntoIterator<Item = &'e (usize, usize, String)>,
+) -> String {
+    let mut code = String::with_capacity(range.len());
+    let mut cursor = range.start;
+    for (start, end, replacement) in edits {
+        code.push_str(&source[cursor..*start]);
+        code.push_str(replacement);
+        cursor = *end;
+    }
+    code.push_str(&source[cursor..range.end]);
+    code
+}
+
+/// Whether a string after `token` is a call argument (`print "x"`)
+fn is_call_target(token: &Token) -> bool {
+    matches!(token.token_type(), TokenType::Identifier { .. })
+        || matches!(
+            symbol_of(token),
+            Some(Symbol::RightParen | Symbol::RightBracket)
+        )
+}
+
+/// An interpolated string token with its text interpolated from `expr`
+/// instead, keeping the delimiters that join it to its neighbours
+fn interpolated_fragment(kind: InterpolatedStringKind, expr: &str) -> String {
+    match kind {
+        InterpolatedStringKind::Begin => format!("`{{{}}}{{", expr),
+        InterpolatedStringKind::Middle => format!("}}{{{}}}{{", expr),
+        InterpolatedStringKind::End => format!("}}{{{}}}`", expr),
+        InterpolatedStringKind::Simple => format!("`{{{}}}`", expr),
+    }
+}
+
+fn symbol_of(token: &Token) -> Option<Symbol> {
+    match token.token_type() {
+        TokenType::Symbol { symbol } => Some(*symbol),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{AnalysisOptions, Analyzer};
+    use crate::crypto::CryptoContext;
+    use crate::obfuscation::{ObfuscationTier, Obfuscator, TableKeyMode};
+    use crate::parser::LuauParser;
+
+    #[test]
+    fn test_rewrite_replaces_literals_and_names() {
+        let source = r#"local greeting = "hello"
+local config = {greeting = 5}
+print(config.greeting, greeting)
+print "bye"
+"#;
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let script = Obfuscator::with_seed(ObfuscationTier::Standard, crypto_ctx, 1)
+            .obfuscate(&parse_result, &analysis)
+            .unwrap();
+        let mangled = &script.name_mappings["greeting"];
+
+        let result = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap();
+        let code = &result.code;
+
+        assert!(!code.contains("\"hello\""));
+        assert!(!code.contains("\"bye\""));
+        assert!(code.contains("print (_decrypt("));
+        assert!(code.contains(&format!("local {} = _decrypt(", mangled)));
+        // Field names keep their name; the local is renamed everywhere
+        assert!(code.contains("{greeting = ("));
+        let config = &script.name_mappings["config"];
+        assert!(code.contains(&format!("{}.greeting, {})", config, mangled)));
+        assert!(!code.contains("= 5}"));
+    }
+
+    #[test]
+    fn test_rewrite_folded_strings() {
+        let source = r#"local remote = "Remote" ..
+    "Event"
+print(string.format("%s-%d", "id", 7), remote)
+"#;
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let script = Obfuscator::with_seed(ObfuscationTier::Basic, crypto_ctx, 1)
+            .obfuscate(&parse_result, &analysis)
+            .unwrap();
+        let mut originals: Vec<&str> =
+            script.encrypted_strings.iter().map(|s| s.original.as_str()).collect();
+        originals.sort();
+        assert_eq!(originals, vec!["RemoteEvent", "id-7"]);
+
+        let code = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap().code;
+        let lines: Vec<&str> = code.lines().collect();
+        assert!(lines[0].ends_with(" = _decrypt(1)") || lines[0].ends_with(" = _decrypt(2)"));
+        assert_eq!(lines[1], "");
+        assert!(lines[2].starts_with("print(_decrypt("));
+        assert!(!code.contains(".."));
+        assert!(!code.contains("string.format"));
+    }
+
+    #[test]
+    fn test_rewrite_interpolated_strings() {
+        let source = "local greeting = `Hello {name}, you have {count} coins`\nprint(`done`)\n";
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let fragments: Vec<&str> = parse_result.strings.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(fragments, vec!["Hello ", ", you have ", " coins", "done"]);
+
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let script = Obfuscator::with_seed(ObfuscationTier::Basic, crypto_ctx, 1)
+            .obfuscate(&parse_result, &analysis)
+            .unwrap();
+        let code = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap().code;
+        let table = script.string_table();
+        let decrypt = |value: &str| {
+            let index = table.iter().position(|(original, _)| original == value).unwrap();
+            format!("{{_decrypt({})}}", index + 1)
+        };
+        let interpolated = format!(
+            "`{}{{name}}{}{{count}}{}`",
+            decrypt("Hello "),
+            decrypt(", you have "),
+            decrypt(" coins")
+        );
+        assert!(!code.contains("Hello"));
+        assert!(code.contains(&interpolated), "{}", code);
+        assert!(code.contains(&format!("print(`{}`)", decrypt("done"))));
+    }
+
+    #[test]
+    fn test_rewrite_folded_numbers() {
+        let source = "local day = 60 * 60 *\n    24\nprint(x - -(2 ^ 10))\n";
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        let mut script = ObfuscatedScript::new();
+        script.folded_numbers = analysis.folded_numbers;
+
+        let code = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap().code;
+        assert_eq!(code, "local day = 86400\n\nprint(x - (-1024))\n");
+    }
+
+    #[test]
+    fn test_rewrite_cuts_out_config_block() {
+        let source = r#"--!obfuscate: config
+local CONFIG = {
+    Greeting = "hello",
+    Speed = 24,
+}
+local message = "secret"
+print(CONFIG.Greeting, CONFIG.Speed, message)
+"#;
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let mut obfuscator = Obfuscator::with_seed(ObfuscationTier::Basic, crypto_ctx, 1);
+        obfuscator.set_table_keys(TableKeyMode::Mangle);
+        let script = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+
+        // Nothing in the block is encrypted; its name and keys are the interface
+        let originals: Vec<&str> =
+            script.encrypted_strings.iter().map(|s| s.original.as_str()).collect();
+        assert_eq!(originals, vec!["secret"]);
+        assert!(!script.name_mappings.contains_key("CONFIG"));
+        assert!(script.key_mappings.is_empty());
+
+        let code = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap().code;
+        let lines: Vec<&str> = code.lines().collect();
+        assert!(!code.contains("hello"));
+        assert!(lines[1..5].iter().all(|line| line.is_empty()));
+        assert!(lines[6].contains("CONFIG.Greeting, CONFIG.Speed"));
+    }
+
+    #[test]
+    fn test_rewrite_table_keys() {
+        let source = r#"local config = {timeout = 5}
+function config:bump()
+    self.timeout = self.timeout + 1
+end
+config:bump()
+print(config["timeout"], workspace.Name)
+"#;
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+
+        let mut obfuscator = Obfuscator::with_seed(ObfuscationTier::Basic, crypto_ctx.clone(), 1);
+        obfuscator.set_table_keys(TableKeyMode::Mangle);
+        let script = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+        let code = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap().code;
+        let timeout = &script.key_mappings["timeout"];
+        let bump = &script.key_mappings["bump"];
+        assert!(!code.contains("timeout"));
+        assert!(code.contains(&format!("{{{} = 5}}", timeout)));
+        assert!(code.contains(&format!(":{}()", bump)));
+        assert!(code.contains("workspace.Name"));
+        assert!(script.kept_keys.iter().any(|kept| kept.key == "Name"));
+
+        let mut obfuscator = Obfuscator::with_seed(ObfuscationTier::Basic, crypto_ctx, 1);
+        obfuscator.set_table_keys(TableKeyMode::Encrypt);
+        let script = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+        let code = SourceRewriter::new(&script).rewrite(source, &parse_result).unwrap().code;
+        let config = &script.name_mappings["config"];
+        assert!(script.key_mappings.is_empty());
+        assert!(!code.contains("timeout"));
+        assert!(!code.contains("bump"));
+        assert!(code.contains("{[_decrypt("));
+        assert!(code.contains(&format!("{}[_decrypt(", config)));
+        assert!(code.contains(")] = function("));
+        assert!(code.contains(&format!(")]({})", config)));
+    }
+}
@@ -0,0 +1,89 @@
+//! Randomness for the obfuscation passes
+//!
+//! Passes never create their own generator: each is handed an [`ObfRng`]
+//! when it is built. [`RngMode`] makes them for a whole run, from OS entropy
+//! or all derived from `--seed`, which makes it the one place a seed enters.
+//! Any `RngCore` is an `ObfRng`, so a test can inject a fixed sequence.
+//!
+//! Nonces, salts and keys never come from here; they stay on `SystemRandom`
+//! and `thread_rng`.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// Source of randomness for an obfuscation pass
+pub trait ObfRng: RngCore + Send {}
+
+impl<R: RngCore + Send> ObfRng for R {}
+
+/// A generator a pass owns
+pub type BoxedRng = Box<dyn ObfRng>;
+
+/// Where the generators of one run come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RngMode {
+    /// A CSPRNG seeded from OS entropy, different every run
+    #[default]
+    Entropy,
+    /// Derived from a seed: the same input and seed give the same names,
+    /// constants and layout
+    Seeded(u64),
+}
+
+impl RngMode {
+    pub fn from_seed(seed: Option<u64>) -> Self {
+        seed.map_or(RngMode::Entropy, RngMode::Seeded)
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        match *self {
+            RngMode::Entropy => None,
+            RngMode::Seeded(seed) => Some(seed),
+        }
+    }
+
+    /// Generator for one consumer; the streams of a seed are independent,
+    /// and stream 0 is the seed itself
+    pub fn stream(&self, stream: u64) -> BoxedRng {
+        match *self {
+            RngMode::Entropy => entropy(),
+            RngMode::Seeded(seed) => seeded(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        }
+    }
+}
+
+/// A fresh generator seeded from OS entropy
+pub fn entropy() -> BoxedRng {
+    Box::new(StdRng::from_entropy())
+}
+
+/// A generator reproducing the same sequence for the same `seed`
+pub fn seeded(seed: u64) -> BoxedRng {
+    Box::new(StdRng::seed_from_u64(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn draws(mut rng: BoxedRng) -> Vec<u32> {
+        (0..8).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_seeded_streams_repeat_and_differ() {
+        let mode = RngMode::from_seed(Some(42));
+        assert_eq!(mode.seed(), Some(42));
+        assert_eq!(draws(mode.stream(1)), draws(mode.stream(1)));
+        assert_ne!(draws(mode.stream(1)), draws(mode.stream(2)));
+        assert_eq!(draws(mode.stream(0)), draws(seeded(42)));
+    }
+
+    #[test]
+    fn test_entropy_streams_differ() {
+        let mode = RngMode::from_seed(None);
+        assert_eq!(mode, RngMode::Entropy);
+        assert_ne!(draws(mode.stream(1)), draws(mode.stream(1)));
+    }
+}
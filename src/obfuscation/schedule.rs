@@ -0,0 +1,310 @@
+//! Pass ordering
+//!
+//! Each pass declares the artifacts it reads, builds and invalidates.
+//! `PassScheduler` orders passes so every artifact is built before it is
+//! read (declaration order breaks ties), then walks that order and inserts
+//! a rebuild wherever a pass reads an artifact an earlier pass invalidated.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+/// Intermediate results passes share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Artifact {
+    /// Parsed syntax tree (with the literal and function tables)
+    Ast,
+    /// Control flow graph from analysis
+    Cfg,
+    /// Original → mangled identifier map
+    NameMap,
+    /// Encrypted string entries
+    StringTable,
+    /// Rewritten table keys (and the literals they add or rename)
+    KeyMap,
+}
+
+impl Artifact {
+    /// Whether the obfuscator can recompute this on its own
+    ///
+    /// The CFG can be re-derived from the AST; the AST needs the source and
+    /// the other artifacts are pass output, so nothing may invalidate them
+    /// before a pass that reads them.
+    pub fn rebuildable(self) -> bool {
+        matches!(self, Artifact::Cfg)
+    }
+}
+
+/// Obfuscation passes, in `Obfuscator::obfuscate`'s historical order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pass {
+    Strings,
+    Constants,
+    Names,
+    ControlFlow,
+    DeadCode,
+    TableKeys,
+    Outline,
+}
+
+impl Pass {
+    /// Dependencies of the pass
+    pub fn spec(self) -> PassSpec {
+        use Artifact::*;
+        let (requires, provides, invalidates): (&[Artifact], &[Artifact], &[Artifact]) = match self
+        {
+            // Encrypted keys are added to the literals the string pass takes
+            Pass::Strings => (&[Ast, KeyMap], &[StringTable], &[]),
+            // The pool takes over pooled strings from the string table
+            Pass::Constants => (&[Ast, StringTable, KeyMap], &[], &[]),
+            // Mangled names must not collide with mangled keys
+            Pass::Names => (&[Ast, KeyMap], &[NameMap], &[]),
+            // Dispatcher states reference renamed locals; flattening
+            // restructures the blocks it was given
+            Pass::ControlFlow => (&[Cfg, NameMap], &[], &[Cfg]),
+            // Snippets must not mention names the mangler is about to change
+            Pass::DeadCode => (&[Ast, NameMap], &[], &[]),
+            Pass::TableKeys => (&[Ast], &[KeyMap], &[]),
+            // Helper names must not collide with mangled names or keys
+            Pass::Outline => (&[Ast, NameMap, KeyMap], &[], &[]),
+        };
+        PassSpec {
+            pass: self,
+            requires,
+            provides,
+            invalidates,
+        }
+    }
+}
+
+/// What a pass reads, builds and invalidates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassSpec {
+    pub pass: Pass,
+    pub requires: &'static [Artifact],
+    pub provides: &'static [Artifact],
+    pub invalidates: &'static [Artifact],
+}
+
+/// One step of a schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Recompute an artifact a previous pass invalidated
+    Rebuild(Artifact),
+    Run(Pass),
+}
+
+/// Orders passes by their declared dependencies
+pub struct PassScheduler {
+    specs: Vec<PassSpec>,
+}
+
+impl PassScheduler {
+    /// Schedule `passes` (duplicates are ignored)
+    pub fn new(passes: &[Pass]) -> Self {
+        let mut seen = BTreeSet::new();
+        Self {
+            specs: passes
+                .iter()
+                .filter(|pass| seen.insert(**pass))
+                .map(|pass| pass.spec())
+                .collect(),
+        }
+    }
+
+    /// Schedule passes with explicit specs (for passes declared elsewhere)
+    pub fn with_specs(specs: Vec<PassSpec>) -> Self {
+        Self { specs }
+    }
+
+    /// Steps to run, in order
+    ///
+    /// Artifacts no scheduled pass provides are inputs (the AST and CFG from
+    /// analysis) or stay empty (no renaming means an empty name map), so
+    /// they are available from the start.
+    pub fn schedule(&self) -> Result<Vec<Step>> {
+        let order = self.topological_order()?;
+
+        let provided: BTreeSet<Artifact> = self
+            .specs
+            .iter()
+            .flat_map(|spec| spec.provides.iter().copied())
+            .collect();
+        let mut valid: BTreeSet<Artifact> = [
+            Artifact::Ast,
+            Artifact::Cfg,
+            Artifact::NameMap,
+            Artifact::StringTable,
+            Artifact::KeyMap,
+        ]
+        .into_iter()
+        .filter(|artifact| !provided.contains(artifact))
+        .collect();
+        let mut built = BTreeSet::new();
+
+        let mut steps = Vec::new();
+        for spec in order {
+            for &artifact in spec.requires {
+                if valid.contains(&artifact) {
+                    continue;
+                }
+                // Invalidated after being built (or an input that was)
+                let was_available = built.contains(&artifact) || !provided.contains(&artifact);
+                if !was_available || !artifact.rebuildable() {
+                    return Err(ObfuscatorError::ObfuscationError(format!(
+                        "Pass {:?} needs {:?}, which an earlier pass invalidated and cannot be rebuilt",
+                        spec.pass, artifact
+                    ))
+                    .into());
+                }
+                steps.push(Step::Rebuild(artifact));
+                valid.insert(artifact);
+            }
+
+            steps.push(Step::Run(spec.pass));
+            for &artifact in spec.invalidates {
+                valid.remove(&artifact);
+            }
+            for &artifact in spec.provides {
+                valid.insert(artifact);
+                built.insert(artifact);
+            }
+        }
+
+        Ok(steps)
+    }
+
+    /// Providers before consumers; ties keep declaration order
+    fn topological_order(&self) -> Result<Vec<PassSpec>> {
+        let n = self.specs.len();
+        // depends_on[i] = passes that must run before pass i
+        let depends_on: Vec<Vec<usize>> = self
+            .specs
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                (0..n)
+                    .filter(|&j| {
+                        j != i
+                            && self.specs[j]
+                                .provides
+                                .iter()
+                                .any(|artifact| spec.requires.contains(artifact))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut done = vec![false; n];
+        let mut order = Vec::with_capacity(n);
+        while order.len() < n {
+            let next = (0..n)
+                .find(|&i| !done[i] && depends_on[i].iter().all(|&j| done[j]))
+                .ok_or_else(|| {
+                    let stuck: Vec<Pass> =
+                        (0..n).filter(|&i| !done[i]).map(|i| self.specs[i].pass).collect();
+                    ObfuscatorError::ObfuscationError(format!(
+                        "Pass dependencies form a cycle among {:?}",
+                        stuck
+                    ))
+                })?;
+            done[next] = true;
+            order.push(self.specs[next]);
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(steps: &[Step]) -> Vec<Pass> {
+        steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Run(pass) => Some(*pass),
+                Step::Rebuild(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_consumers_run_after_providers() {
+        // Declared in the wrong order on purpose
+        let steps = PassScheduler::new(&[
+            Pass::DeadCode,
+            Pass::ControlFlow,
+            Pass::Constants,
+            Pass::Names,
+            Pass::Strings,
+            Pass::TableKeys,
+        ])
+        .schedule()
+        .unwrap();
+
+        let order = runs(&steps);
+        let position = |pass| order.iter().position(|p| *p == pass).unwrap();
+        assert!(position(Pass::Names) < position(Pass::DeadCode));
+        assert!(position(Pass::Names) < position(Pass::ControlFlow));
+        assert!(position(Pass::Strings) < position(Pass::Constants));
+        assert!(position(Pass::TableKeys) < position(Pass::Strings));
+        assert!(position(Pass::TableKeys) < position(Pass::Names));
+        assert_eq!(order.len(), 6);
+        assert!(!steps.iter().any(|s| matches!(s, Step::Rebuild(_))));
+    }
+
+    #[test]
+    fn test_invalidated_artifacts_are_rebuilt() {
+        // Two passes reading the CFG that each restructure it
+        let flatten = PassSpec {
+            pass: Pass::ControlFlow,
+            requires: &[Artifact::Cfg],
+            provides: &[],
+            invalidates: &[Artifact::Cfg],
+        };
+        let reader = PassSpec {
+            pass: Pass::DeadCode,
+            requires: &[Artifact::Cfg],
+            provides: &[],
+            invalidates: &[],
+        };
+        let steps = PassScheduler::with_specs(vec![flatten, reader]).schedule().unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                Step::Run(Pass::ControlFlow),
+                Step::Rebuild(Artifact::Cfg),
+                Step::Run(Pass::DeadCode),
+            ]
+        );
+
+        let breaks_ast = PassSpec {
+            invalidates: &[Artifact::Ast],
+            ..flatten
+        };
+        let needs_ast = PassSpec {
+            requires: &[Artifact::Ast],
+            ..reader
+        };
+        assert!(PassScheduler::with_specs(vec![breaks_ast, needs_ast]).schedule().is_err());
+    }
+
+    #[test]
+    fn test_cycles_are_rejected() {
+        let a = PassSpec {
+            pass: Pass::Strings,
+            requires: &[Artifact::NameMap],
+            provides: &[Artifact::StringTable],
+            invalidates: &[],
+        };
+        let b = PassSpec {
+            pass: Pass::Names,
+            requires: &[Artifact::StringTable],
+            provides: &[Artifact::NameMap],
+            invalidates: &[],
+        };
+        assert!(PassScheduler::with_specs(vec![a, b]).schedule().is_err());
+    }
+}
@@ -0,0 +1,776 @@
+//! Local inlining and dead local removal
+//!
+//! An optional pre-pass (`--simplify`) over the source, run before analysis.
+//! A local holding a literal that is read once has the read replaced by the
+//! literal, and a local nobody reads (a `local function` included) is
+//! removed. The passes then have fewer statements to encrypt, rename and
+//! pad, and a leaked build shows less of how the original was laid out.
+//! Rounds repeat until nothing changes, so `local a = 1 local b = a` ends up
+//! as one literal at the read of `b`.
+//!
+//! Only what is certain to behave the same is changed:
+//! - the value is a number, a one-line string, `true`, `false` or `nil` (or
+//!   there is no initializer), so nothing is evaluated or captured
+//! - the local is never assigned, redeclared or shadowed before its scope
+//!   ends, and no type annotation names it
+//! - the read is a plain value (`f(x)`, `x + 1`, `return x`), not the prefix
+//!   of a call or index
+//! - locals of a `repeat` body are left alone, since `until` can read them
+//!
+//! Removed code leaves its line breaks behind, so lines don't move and
+//! directives, diagnostics and symbol maps still point into the input.
+
+use crate::parser::{scan_types, BlockNesting};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Token, TokenType};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+/// Rounds before the result is taken as it is
+const MAX_ROUNDS: usize = 16;
+
+/// Tokens that may follow a plain read of a local
+const READ_FOLLOWERS: &[&str] = &[
+    "+", "-", "*", "/", "//", "%", "^", "..", "==", "~=", "<", "<=", ">", ">=", "and", "or", ")",
+    "]", "}", ";", "then", "do", "else", "elseif", "end", "until", "return", "local", "function",
+    "if", "for", "while", "repeat", "break",
+];
+
+/// Tokens that end a declaration and start the next statement (as does an
+/// identifier)
+const STATEMENT_BOUNDARIES: &[&str] = &[
+    ";", "local", "function", "if", "for", "while", "repeat", "do", "return", "break", "end",
+    "else", "elseif", "until",
+];
+
+/// A simplified script
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Simplified {
+    pub code: String,
+    /// Single-use locals replaced by their value
+    pub inlined: usize,
+    /// Unread locals and local functions removed
+    pub removed: usize,
+}
+
+/// Inlines single-use literal locals and removes unread ones
+pub struct Simplifier;
+
+impl Simplifier {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Simplify `source` until nothing more changes
+    pub fn simplify(&self, source: &str) -> Result<Simplified> {
+        let mut simplified = Simplified {
+            code: source.to_string(),
+            ..Default::default()
+        };
+        for _ in 0..MAX_ROUNDS {
+            let round = Round::scan(&simplified.code)?;
+            if round.edits.is_empty() {
+                break;
+            }
+            simplified.code = round.apply(&simplified.code);
+            simplified.inlined += round.inlined;
+            simplified.removed += round.removed;
+        }
+        Ok(simplified)
+    }
+}
+
+impl Default for Simplifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A replacement of source bytes
+struct Edit {
+    range: Range<usize>,
+    text: String,
+    /// Set on the removal of a declaration: whether its local was inlined
+    declaration: Option<bool>,
+}
+
+/// The edits of one round, nested ones dropped
+struct Round {
+    edits: Vec<Edit>,
+    inlined: usize,
+    removed: usize,
+}
+
+impl Round {
+    fn scan(source: &str) -> Result<Self> {
+        let all_tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        // Type annotations name types, not variables; a local they mention
+        // (`typeof(x)`) is left alone
+        let annotations = scan_types(source)?.annotations;
+        let mut annotation = annotations.iter().peekable();
+        let mut typed = BTreeSet::new();
+        let mut significant: Vec<&Token> = Vec::new();
+        for token in all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+        {
+            let at = token.start_position().bytes();
+            while annotation.next_if(|span| span.end <= at).is_some() {}
+            if !annotation.peek().is_some_and(|span| span.contains(&at)) {
+                significant.push(token);
+            } else if let TokenType::Identifier { identifier } = token.token_type() {
+                typed.insert(identifier.to_string());
+            }
+        }
+
+        let layout = Walker::new(&significant).run();
+        let mut uses: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, token) in significant.iter().enumerate() {
+            if let TokenType::Identifier { identifier } = token.token_type() {
+                uses.entry(identifier.as_str()).or_default().push(i);
+            }
+        }
+        let scanner = Scanner {
+            source,
+            tokens: &significant,
+            layout: &layout,
+            annotations: &annotations,
+        };
+
+        let mut edits = Vec::new();
+        for i in 0..significant.len() {
+            if !scanner.is(i, "local") {
+                continue;
+            }
+            let Some(local) = scanner.declaration(i) else {
+                continue;
+            };
+            if local.name == "self" || typed.contains(local.name) {
+                continue;
+            }
+            let scope = &layout.frames[layout.frames_at[i]];
+            if scope.close == Close::Until {
+                continue;
+            }
+
+            let mut reads = Vec::new();
+            let mut unclear = false;
+            for &j in uses.get(local.name).into_iter().flatten() {
+                if j <= local.name_at || j >= scope.end {
+                    continue;
+                }
+                match scanner.occurrence(j) {
+                    Occurrence::Ignored => {}
+                    Occurrence::Read => reads.push(j),
+                    Occurrence::Unclear => unclear = true,
+                }
+            }
+            if unclear {
+                continue;
+            }
+
+            let inlined = match (reads.as_slice(), &local.value) {
+                ([], _) => false,
+                ([read], Some(value)) => {
+                    edits.push(Edit {
+                        range: scanner.bytes(*read, *read),
+                        text: scanner.spaced(*read, value),
+                        declaration: None,
+                    });
+                    true
+                }
+                _ => continue,
+            };
+            let range = scanner.statement(i, local.last);
+            edits.push(Edit {
+                text: "\n".repeat(source[range.clone()].matches('\n').count()),
+                range,
+                declaration: Some(inlined),
+            });
+        }
+
+        // A removed function takes the edits inside it along
+        edits.sort_by_key(|edit| (edit.range.start, std::cmp::Reverse(edit.range.end)));
+        let mut kept: Vec<Edit> = Vec::with_capacity(edits.len());
+        let mut covered = 0;
+        for edit in edits {
+            if edit.range.start < covered {
+                continue;
+            }
+            if edit.declaration.is_some() {
+                covered = edit.range.end;
+            }
+            kept.push(edit);
+        }
+
+        let inlined = kept.iter().filter(|e| e.declaration == Some(true)).count();
+        let removed = kept.iter().filter(|e| e.declaration == Some(false)).count();
+        Ok(Self {
+            edits: kept,
+            inlined,
+            removed,
+        })
+    }
+
+    fn apply(&self, source: &str) -> String {
+        let mut code = String::with_capacity(source.len());
+        let mut at = 0;
+        for edit in &self.edits {
+            code.push_str(&source[at..edit.range.start]);
+            code.push_str(&edit.text);
+            at = edit.range.end;
+        }
+        code.push_str(&source[at..]);
+        code
+    }
+}
+
+/// A `local` statement that may be inlined or removed
+struct Declaration<'a> {
+    name: &'a str,
+    /// Token of the name
+    name_at: usize,
+    /// Last token of the statement (its `;` included)
+    last: usize,
+    /// Source text of the value; `None` for a `local function`
+    value: Option<String>,
+}
+
+/// How an identifier named like a local uses it
+enum Occurrence {
+    /// A field or table key, not the local
+    Ignored,
+    Read,
+    /// A write, a declaration or a use that isn't a plain value
+    Unclear,
+}
+
+struct Scanner<'a> {
+    source: &'a str,
+    tokens: &'a [&'a Token],
+    layout: &'a Layout,
+    annotations: &'a [Range<usize>],
+}
+
+impl<'a> Scanner<'a> {
+    /// The single-name declaration starting with `local` at `i`
+    fn declaration(&self, i: usize) -> Option<Declaration<'a>> {
+        if self.is(i + 1, "function") {
+            let name = self.identifier(i + 2)?;
+            let body = &self.layout.frames[*self.layout.function_frames.get(&(i + 1))?];
+            if body.close != Close::End {
+                return None;
+            }
+            return self.terminated(Declaration {
+                name,
+                name_at: i + 2,
+                last: body.end,
+                value: None,
+            });
+        }
+
+        let name = self.identifier(i + 1)?;
+        let (last, value) = if self.is(i + 2, "=") {
+            (i + 3, self.literal(i + 3)?)
+        } else {
+            (i + 1, "nil".to_string())
+        };
+        if !self.boundary(last + 1) {
+            return None;
+        }
+        self.terminated(Declaration {
+            name,
+            name_at: i + 1,
+            last,
+            value: Some(value),
+        })
+    }
+
+    /// `declaration` with a following `;` taken along; `None` when the next
+    /// statement starts with `(`, which removing it would join to the
+    /// statement before
+    fn terminated(&self, mut declaration: Declaration<'a>) -> Option<Declaration<'a>> {
+        if self.is(declaration.last + 1, ";") {
+            declaration.last += 1;
+        }
+        if self.is(declaration.last + 1, "(") {
+            return None;
+        }
+        Some(declaration)
+    }
+
+    /// Source text of the literal at `i`, if it is one that can be copied
+    fn literal(&self, i: usize) -> Option<String> {
+        let token = self.tokens.get(i)?;
+        let text = &self.source[self.bytes(i, i)];
+        let copyable = match token.token_type() {
+            TokenType::Number { .. } => true,
+            TokenType::StringLiteral { .. } => !text.contains('\n'),
+            TokenType::Symbol { .. } => matches!(text, "true" | "false" | "nil"),
+            _ => false,
+        };
+        copyable.then(|| text.to_string())
+    }
+
+    fn occurrence(&self, j: usize) -> Occurrence {
+        if self.layout.roles[j] != Role::Other {
+            return Occurrence::Unclear;
+        }
+        if j > 0 && (self.is(j - 1, ".") || self.is(j - 1, ":")) {
+            return Occurrence::Ignored;
+        }
+        let bracket = self.layout.brackets_at[j];
+        let after_separator =
+            j > 0 && (self.is(j - 1, "{") || self.is(j - 1, ",") || self.is(j - 1, ";"));
+        if bracket == Some(Bracket::Brace) && after_separator && self.is(j + 1, "=") {
+            return Occurrence::Ignored;
+        }
+
+        let Some(next) = self.tokens.get(j + 1).map(|t| t.to_string()) else {
+            return Occurrence::Read;
+        };
+        let listed = next == ","
+            && matches!(
+                bracket,
+                Some(Bracket::Paren | Bracket::Brace | Bracket::Square)
+            );
+        if READ_FOLLOWERS.contains(&next.as_str()) || listed || self.identifier(j + 1).is_some() {
+            Occurrence::Read
+        } else {
+            Occurrence::Unclear
+        }
+    }
+
+    /// `value` to put in place of token `i`, spaced so a number doesn't run
+    /// into a neighbouring `.`
+    fn spaced(&self, i: usize, value: &str) -> String {
+        if !value.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            return value.to_string();
+        }
+        let range = self.bytes(i, i);
+        let mut spaced = value.to_string();
+        if self.source[..range.start].ends_with('.') {
+            spaced.insert(0, ' ');
+        }
+        if self.source[range.end..].starts_with('.') {
+            spaced.push(' ');
+        }
+        spaced
+    }
+
+    /// Whether the statement before can end ahead of token `i`
+    fn boundary(&self, i: usize) -> bool {
+        match self.tokens.get(i) {
+            None => true,
+            Some(token) => {
+                STATEMENT_BOUNDARIES.contains(&token.to_string().as_str())
+                    || self.identifier(i).is_some()
+            }
+        }
+    }
+
+    /// Source bytes of the statement from token `first` to `last`, with an
+    /// annotation right after it (`local n: number`, `:: T`) taken along
+    fn statement(&self, first: usize, last: usize) -> Range<usize> {
+        let mut range = self.bytes(first, last);
+        let next = self
+            .tokens
+            .get(last + 1)
+            .map_or(self.source.len(), |t| t.start_position().bytes());
+        for span in self.annotations {
+            if span.start >= range.end && span.start < next {
+                range.end = range.end.max(span.end);
+            }
+        }
+        range
+    }
+
+    /// Source bytes of tokens `first` to `last`
+    fn bytes(&self, first: usize, last: usize) -> Range<usize> {
+        self.tokens[first].start_position().bytes()..self.tokens[last].end_position().bytes()
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.tokens.get(i).is_some_and(|t| t.to_string() == text)
+    }
+
+    fn identifier(&self, i: usize) -> Option<&'a str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// How a scope ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Close {
+    End,
+    /// At the `else` or `elseif` of its `if`
+    Else,
+    /// At `until`, whose condition still sees its locals
+    Until,
+    /// At the end of the script (the main chunk)
+    Eof,
+}
+
+/// A scope and the token it ends at
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    end: usize,
+    close: Close,
+}
+
+/// Innermost bracket around a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bracket {
+    Paren,
+    Brace,
+    Square,
+    /// A function or block body, where statements start again
+    Body,
+}
+
+/// What an identifier token does to the variable it names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Other,
+    /// `local`, `for` and parameter names
+    Declaration,
+    /// `function name()` assigns `name`
+    Write,
+}
+
+/// Construct waiting for its closing keyword
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opener {
+    Frame(usize),
+    /// Statement `if` before its first `then`
+    If,
+    /// `if` used as an expression; has no `end`
+    IfExpression,
+    /// `for` header, whose `do` opens the body
+    For,
+}
+
+/// Scopes, brackets and declarations of each token
+struct Layout {
+    roles: Vec<Role>,
+    /// Innermost scope around each token
+    frames_at: Vec<usize>,
+    brackets_at: Vec<Option<Bracket>>,
+    /// Frame 0 is the main chunk
+    frames: Vec<Frame>,
+    /// Body scope of each `function` keyword
+    function_frames: HashMap<usize, usize>,
+}
+
+/// Token-level walk recording the layout
+struct Walker<'a> {
+    tokens: &'a [&'a Token],
+    layout: Layout,
+    openers: Vec<Opener>,
+    brackets: Vec<Bracket>,
+    /// Tells an if-expression from an `if` statement
+    nesting: BlockNesting,
+}
+
+impl<'a> Walker<'a> {
+    fn new(tokens: &'a [&'a Token]) -> Self {
+        Self {
+            tokens,
+            layout: Layout {
+                roles: vec![Role::Other; tokens.len()],
+                frames_at: vec![0; tokens.len()],
+                brackets_at: vec![None; tokens.len()],
+                frames: vec![Frame {
+                    end: tokens.len(),
+                    close: Close::Eof,
+                }],
+                function_frames: HashMap::new(),
+            },
+            openers: Vec::new(),
+            brackets: Vec::new(),
+            nesting: BlockNesting::new(),
+        }
+    }
+
+    fn run(mut self) -> Layout {
+        let mut i = 0;
+        while i < self.tokens.len() {
+            self.record(i);
+            let in_expression = self.nesting.in_expression();
+            self.nesting.advance(self.tokens[i]);
+            i = match self.text(i).as_str() {
+                "function" => {
+                    let frame = self.open();
+                    self.layout.function_frames.insert(i, frame);
+                    self.function_header(i);
+                    i + 1
+                }
+                "local" => {
+                    if !self.is(i + 1, "function") {
+                        self.declare_names(i + 1);
+                    }
+                    i + 1
+                }
+                "for" => {
+                    self.openers.push(Opener::For);
+                    self.declare_names(i + 1);
+                    i + 1
+                }
+                "do" => {
+                    if self.openers.last() == Some(&Opener::For) {
+                        self.openers.pop();
+                    }
+                    self.open();
+                    i + 1
+                }
+                "repeat" => {
+                    self.open();
+                    i + 1
+                }
+                "if" => {
+                    let opener = if in_expression {
+                        Opener::IfExpression
+                    } else {
+                        Opener::If
+                    };
+                    self.openers.push(opener);
+                    i + 1
+                }
+                "then" => {
+                    if self.openers.last() == Some(&Opener::If) {
+                        self.openers.pop();
+                        self.open();
+                    }
+                    i + 1
+                }
+                "elseif" => {
+                    if matches!(self.openers.last(), Some(Opener::Frame(_))) {
+                        self.close(i, Close::Else);
+                        self.openers.push(Opener::If);
+                    }
+                    i + 1
+                }
+                "else" => match self.openers.last() {
+                    // `else if` continues the same if-expression
+                    Some(Opener::IfExpression) if self.is(i + 1, "if") => {
+                        self.record(i + 1);
+                        self.nesting.advance(self.tokens[i + 1]);
+                        i + 2
+                    }
+                    Some(Opener::IfExpression) => {
+                        self.openers.pop();
+                        i + 1
+                    }
+                    Some(Opener::Frame(_)) => {
+                        self.close(i, Close::Else);
+                        self.open();
+                        i + 1
+                    }
+                    _ => i + 1,
+                },
+                "end" => {
+                    self.close(i, Close::End);
+                    i + 1
+                }
+                "until" => {
+                    self.close(i, Close::Until);
+                    i + 1
+                }
+                "(" => {
+                    self.brackets.push(Bracket::Paren);
+                    i + 1
+                }
+                "{" => {
+                    self.brackets.push(Bracket::Brace);
+                    i + 1
+                }
+                "[" => {
+                    self.brackets.push(Bracket::Square);
+                    i + 1
+                }
+                ")" | "}" | "]" => {
+                    if matches!(self.brackets.last(), Some(b) if *b != Bracket::Body) {
+                        self.brackets.pop();
+                    }
+                    i + 1
+                }
+                _ => i + 1,
+            };
+        }
+        self.layout
+    }
+
+    fn record(&mut self, i: usize) {
+        self.layout.frames_at[i] = self
+            .openers
+            .iter()
+            .rev()
+            .find_map(|opener| match opener {
+                Opener::Frame(frame) => Some(*frame),
+                _ => None,
+            })
+            .unwrap_or(0);
+        self.layout.brackets_at[i] = self.brackets.last().copied();
+    }
+
+    /// Name and parameters of the function whose keyword is at `i`
+    fn function_header(&mut self, i: usize) {
+        let mut j = i + 1;
+        if self.identifier(j) {
+            self.layout.roles[j] = if i > 0 && self.is(i - 1, "local") {
+                Role::Declaration
+            } else {
+                Role::Write
+            };
+            j += 1;
+            while (self.is(j, ".") || self.is(j, ":")) && self.identifier(j + 1) {
+                j += 2;
+            }
+        }
+        if !self.is(j, "(") {
+            return;
+        }
+        j += 1;
+        while j < self.tokens.len() && !self.is(j, ")") {
+            if self.identifier(j) {
+                self.layout.roles[j] = Role::Declaration;
+            }
+            j += 1;
+        }
+    }
+
+    /// `a, b, c` after `local` or `for`
+    fn declare_names(&mut self, mut j: usize) {
+        while self.identifier(j) {
+            self.layout.roles[j] = Role::Declaration;
+            if !self.is(j + 1, ",") {
+                break;
+            }
+            j += 2;
+        }
+    }
+
+    fn open(&mut self) -> usize {
+        let frame = self.layout.frames.len();
+        self.layout.frames.push(Frame {
+            end: self.tokens.len(),
+            close: Close::Eof,
+        });
+        self.openers.push(Opener::Frame(frame));
+        self.brackets.push(Bracket::Body);
+        frame
+    }
+
+    fn close(&mut self, i: usize, close: Close) {
+        // A stray `end` in malformed input leaves the stack alone
+        if let Some(Opener::Frame(frame)) = self.openers.pop() {
+            self.layout.frames[frame] = Frame { end: i, close };
+            while let Some(bracket) = self.brackets.pop() {
+                if bracket == Bracket::Body {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn text(&self, i: usize) -> String {
+        self.tokens
+            .get(i)
+            .map(|t| t.to_string())
+            .unwrap_or_default()
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.tokens.get(i).is_some_and(|t| t.to_string() == text)
+    }
+
+    fn identifier(&self, i: usize) -> bool {
+        matches!(
+            self.tokens.get(i).map(|t| t.token_type()),
+            Some(TokenType::Identifier { .. })
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simplify(source: &str) -> Simplified {
+        let simplified = Simplifier::new().simplify(source).unwrap();
+        assert!(
+            full_moon::parse(&simplified.code).is_ok(),
+            "{}",
+            simplified.code
+        );
+        assert_eq!(simplified.code.lines().count(), source.lines().count());
+        simplified
+    }
+
+    #[test]
+    fn test_inlines_single_use_literals() {
+        let source = "local greeting = \"hi\"\nlocal unused = 3\nlocal a = 1\nlocal b = a\n\
+                      print(greeting, b + 2)\n";
+        let simplified = simplify(source);
+        assert_eq!(simplified.code, "\n\n\n\nprint(\"hi\", 1 + 2)\n");
+        assert_eq!((simplified.inlined, simplified.removed), (3, 1));
+
+        // A removed function's reads go with it
+        let source = "local limit = 5\nlocal function unused()\n    return limit\nend\nprint(1)\n";
+        assert_eq!(simplify(source).code, "\n\n\n\nprint(1)\n");
+
+        // `5..x` would read as a malformed number
+        assert_eq!(
+            simplify("local n = 5\nprint(n..\"s\")\n").code,
+            "\nprint(5 ..\"s\")\n"
+        );
+
+        // The `n` after `end` is a global, not the local
+        let source = "do\n    local n = 1\n    f(n)\nend\nprint(n)\n";
+        assert_eq!(simplify(source).code, "do\n    \n    f(1)\nend\nprint(n)\n");
+
+        // Annotations go with the declaration
+        let source = "local n: number = 1\nlocal unused: string?\nprint(n :: number)\n";
+        assert_eq!(simplify(source).code, "\n\nprint(1 :: number)\n");
+
+        // An if-expression after `while` opens no block of its own
+        let source = "while if ready then a else b do\n    local n = 1\n    f(n)\nend\nprint(n)\n";
+        assert_eq!(
+            simplify(source).code,
+            "while if ready then a else b do\n    \n    f(1)\nend\nprint(n)\n"
+        );
+    }
+
+    #[test]
+    fn test_leaves_unclear_locals_alone() {
+        let kept = [
+            // Read twice, written, or used as a prefix
+            "local n = 1\nprint(n, n)\n",
+            "local n = 1\nn = 2\nprint(n)\n",
+            "local s = \"a\"\nprint(s:upper())\n",
+            // Shadowed
+            "local n = 1\nlocal function f(n) return n end\nprint(n, f(2))\n",
+            // `until` sees the body's locals
+            "repeat\n    local done = true\nuntil done\n",
+            // Not a literal, or a call of its own
+            "local t = {}\nprint(t)\n",
+            "local function f() end\nf()\n",
+            // Removing it would join `(g)()` to the statement before
+            "print(1)\nlocal n = 1;\n(g)()\n",
+        ];
+        for source in kept {
+            let simplified = Simplifier::new().simplify(source).unwrap();
+            assert_eq!(simplified.code, source);
+        }
+
+        // Table keys and fields of the same name are not the local
+        let simplified = simplify("local n = 1\nprint({ n = n }, t.n)\n");
+        assert_eq!(simplified.code, "\nprint({ n = 1 }, t.n)\n");
+    }
+}
@@ -0,0 +1,171 @@
+//! Output size accounting
+//!
+//! Breaks the growth from input to protected script down by transform, so a
+//! tier that inflates a script can be traced to the pass responsible. The
+//! source is rewritten once per transform, each time with one more pass's
+//! output applied; whatever the final script adds on top (runtime, data
+//! tables, header, minus scrubbed comments) is attributed to the runtime.
+
+use super::{ObfuscatedScript, SourceRewriter};
+use crate::parser::ParseResult;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::io::Write;
+
+/// Source size Roblox Studio opens and replicates comfortably; scripts past
+/// this are slow to edit and to load on clients, and are better split
+pub const SCRIPT_SOURCE_BUDGET: usize = 200_000;
+
+/// Bytes one transform added (or removed)
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformDelta {
+    /// `simplify`, `strings`, `constants`, `names`, `table_keys`, `outline`,
+    /// `dead_code` or `runtime`
+    pub transform: &'static str,
+    pub bytes: i64,
+}
+
+/// Input and output sizes of one run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SizeStats {
+    pub input_bytes: usize,
+    /// Protected script (loader and payload together when split)
+    pub output_bytes: usize,
+    /// Output after gzip, roughly what it costs to download and replicate
+    pub compressed_bytes: usize,
+    /// In the order the transforms are applied; they sum to the growth
+    pub deltas: Vec<TransformDelta>,
+}
+
+impl SizeStats {
+    /// Measure `output`, generated from `source` with `obfuscated`
+    pub fn measure(
+        source: &str,
+        parse_result: &ParseResult,
+        obfuscated: &ObfuscatedScript,
+        output: &str,
+    ) -> Result<Self> {
+        // Flattened blocks are not spliced into the source, so they have no size
+        let stages: [(&'static str, fn(&mut ObfuscatedScript, &ObfuscatedScript)); 6] = [
+            ("strings", |partial, full| {
+                partial.encrypted_strings = full.encrypted_strings.clone();
+                partial.folded_strings = full.folded_strings.clone();
+            }),
+            ("constants", |partial, full| {
+                partial.obfuscated_constants = full.obfuscated_constants.clone();
+                partial.pooled_strings = full.pooled_strings.clone();
+                partial.constant_pool = full.constant_pool.clone();
+                partial.folded_numbers = full.folded_numbers.clone();
+            }),
+            ("names", |partial, full| {
+                partial.name_mappings = full.name_mappings.clone();
+            }),
+            // Encrypted keys' string table entries only take effect with the sites
+            ("table_keys", |partial, full| {
+                partial.table_keys = full.table_keys;
+                partial.key_mappings = full.key_mappings.clone();
+                partial.key_sites = full.key_sites.clone();
+            }),
+            ("outline", |partial, full| {
+                partial.outlined_functions = full.outlined_functions.clone();
+            }),
+            ("dead_code", |partial, full| {
+                partial.dead_code_snippets = full.dead_code_snippets.clone();
+            }),
+        ];
+
+        let mut partial = ObfuscatedScript::new();
+        let mut previous = source.len();
+        let mut deltas = Vec::with_capacity(stages.len() + 1);
+        for (transform, apply) in stages {
+            apply(&mut partial, obfuscated);
+            let size = SourceRewriter::new(&partial)
+                .rewrite(source, parse_result)?
+                .code
+                .len();
+            deltas.push(TransformDelta {
+                transform,
+                bytes: size as i64 - previous as i64,
+            });
+            previous = size;
+        }
+        deltas.push(TransformDelta {
+            transform: "runtime",
+            bytes: output.len() as i64 - previous as i64,
+        });
+
+        Ok(Self {
+            input_bytes: source.len(),
+            output_bytes: output.len(),
+            compressed_bytes: gzip_size(output.as_bytes())?,
+            deltas,
+        })
+    }
+
+    /// Output size as a multiple of the input size
+    pub fn growth(&self) -> f64 {
+        if self.input_bytes == 0 {
+            return 0.0;
+        }
+        self.output_bytes as f64 / self.input_bytes as f64
+    }
+
+    /// Output size as a share of `SCRIPT_SOURCE_BUDGET`, in percent
+    pub fn budget_percent(&self) -> f64 {
+        self.output_bytes as f64 / SCRIPT_SOURCE_BUDGET as f64 * 100.0
+    }
+
+    /// The transform that added the most bytes, if any added some
+    pub fn largest_delta(&self) -> Option<&TransformDelta> {
+        self.deltas
+            .iter()
+            .filter(|d| d.bytes > 0)
+            .max_by_key(|d| d.bytes)
+    }
+}
+
+/// Size of `bytes` after gzip at the default level
+pub fn gzip_size(bytes: &[u8]) -> Result<usize> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obfuscation::ObfuscatedConstant;
+    use crate::parser::LuauParser;
+
+    #[test]
+    fn test_deltas_sum_to_growth() {
+        let source = "local count = 10\nprint(count)\n";
+        let parse_result = LuauParser::new().parse(source).unwrap();
+        let mut obfuscated = ObfuscatedScript::new();
+        obfuscated
+            .name_mappings
+            .insert("count".to_string(), "_0x1f2a3".to_string());
+        let number = &parse_result.numbers[0];
+        obfuscated.obfuscated_constants.push(ObfuscatedConstant {
+            original: number.value.clone(),
+            obfuscated_expr: "7 + 3".to_string(),
+            line: number.line,
+            column: number.column,
+        });
+        let output = "-- header\nlocal _0x1f2a3 = (7 + 3)\nprint(_0x1f2a3)\n";
+
+        let size = SizeStats::measure(source, &parse_result, &obfuscated, output).unwrap();
+
+        let delta = |name| size.deltas.iter().find(|d| d.transform == name).unwrap().bytes;
+        assert_eq!(delta("strings"), 0);
+        assert_eq!(delta("constants"), 5);
+        assert_eq!(delta("names"), 6);
+        assert_eq!(delta("runtime"), 10);
+        let total: i64 = size.deltas.iter().map(|d| d.bytes).sum();
+        assert_eq!(total, size.output_bytes as i64 - size.input_bytes as i64);
+        assert!(size.compressed_bytes > 0);
+        assert_eq!(size.largest_delta().unwrap().transform, "runtime");
+    }
+}
@@ -1,29 +1,187 @@
 //! String encryption obfuscation
+//!
+//! Strings matching a `PlaintextRule` (asset URLs, chat filter input,
+//! animation ids, ...) are never encrypted: Roblox reads some of them in
+//! ways that break on an encrypted-then-decrypted copy, and they aren't
+//! secrets anyway. Instance paths (`WaitForChild("Remotes")`, ...) are
+//! encrypted whole: never fragmented, and never interned with a copy that was.
 
-use super::{EncryptedString, ObfuscatedConstant};
-use crate::crypto::{CryptoContext, EncryptedData};
-use crate::parser::{Sensitivity, StringLiteral};
+use super::rng::{self, BoxedRng};
+use super::{EncryptedString, ObfuscatedConstant, StringFragment};
+use crate::crypto::{Compression, CryptoContext, EncryptedData};
+use crate::parser::{compile_pattern, Sensitivity, StringLiteral};
 use crate::utils::errors::ObfuscatorError;
 use anyhow::Result;
 use rand::{distributions::Alphanumeric, Rng};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+/// When encrypted strings are decrypted at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DecryptionMode {
+    /// Decrypt every string once when the script loads
+    #[default]
+    Eager,
+    /// Decrypt on first use inside a memoizing closure
+    Lazy,
+}
+
+/// A pattern for strings that must stay plaintext
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaintextRule {
+    /// Label reported as the reason a string was kept
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Regex, or a glob when prefixed with `glob:`
+    pub pattern: String,
+}
+
+/// Compiled plaintext rules, checked in order
+#[derive(Debug, Clone, Default)]
+pub struct PlaintextRules {
+    rules: Vec<(String, Regex)>,
+}
+
+impl PlaintextRules {
+    pub fn new(rules: &[PlaintextRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let name = rule.name.clone().unwrap_or_else(|| rule.pattern.clone());
+                let regex = compile_pattern(&rule.pattern).map_err(|e| {
+                    ObfuscatorError::ConfigError(format!(
+                        "Invalid plaintext string rule '{}': {}",
+                        name, e
+                    ))
+                })?;
+                Ok((name, regex))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Name of the first rule `value` matches
+    pub fn matching(&self, value: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|(_, regex)| regex.is_match(value))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The literals of `strings` a rule keeps, with the rule
+    pub fn kept<'s>(
+        &self,
+        strings: impl IntoIterator<Item = &'s StringLiteral>,
+    ) -> Vec<PlaintextString> {
+        strings
+            .into_iter()
+            .filter_map(|string_lit| {
+                Some(PlaintextString {
+                    value: string_lit.value.clone(),
+                    line: string_lit.line,
+                    column: string_lit.column,
+                    rule: self.matching(&string_lit.value)?.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A string literal left plaintext by a rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlaintextString {
+    pub value: String,
+    pub line: usize,
+    pub column: usize,
+    /// Rule that matched it
+    pub rule: String,
+}
 
 /// String obfuscator using encryption
 pub struct StringObfuscator<'a> {
     crypto_ctx: &'a CryptoContext,
+    fragment_size: Option<usize>,
+    compression: Compression,
+    plaintext: PlaintextRules,
+    /// Positions of literals that are never split into fragments
+    whole: BTreeSet<(usize, usize)>,
+    /// For string ids; nonces come from the crypto context
+    rng: RefCell<BoxedRng>,
 }
 
 impl<'a> StringObfuscator<'a> {
     pub fn new(crypto_ctx: &'a CryptoContext) -> Self {
-        Self { crypto_ctx }
+        Self {
+            crypto_ctx,
+            fragment_size: None,
+            compression: Compression::None,
+            plaintext: PlaintextRules::default(),
+            whole: BTreeSet::new(),
+            rng: RefCell::new(rng::entropy()),
+        }
+    }
+
+    /// Create a string obfuscator that splits long strings into fragments
+    ///
+    /// Strings longer than `fragment_size` characters are encrypted as
+    /// separate ciphertexts, each at most `fragment_size` characters long.
+    pub fn with_fragment_size(crypto_ctx: &'a CryptoContext, fragment_size: usize) -> Self {
+        Self {
+            crypto_ctx,
+            fragment_size: Some(fragment_size.max(1)),
+            compression: Compression::None,
+            plaintext: PlaintextRules::default(),
+            whole: BTreeSet::new(),
+            rng: RefCell::new(rng::entropy()),
+        }
+    }
+
+    /// Draw string ids from `rng`
+    pub fn with_rng(mut self, rng: BoxedRng) -> Self {
+        self.rng = RefCell::new(rng);
+        self
+    }
+
+    /// Compress each string before encryption when that makes it smaller
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Leave strings matching `rules` unencrypted
+    pub fn with_plaintext_rules(mut self, rules: PlaintextRules) -> Self {
+        self.plaintext = rules;
+        self
+    }
+
+    /// Encrypt the literals at these `(line, column)` positions as one
+    /// ciphertext each, however long (Instance paths must decrypt in one call)
+    pub fn with_whole_strings(mut self, sites: BTreeSet<(usize, usize)>) -> Self {
+        self.whole = sites;
+        self
     }
 
     /// Obfuscate string literals
+    ///
+    /// Identical values are interned: the first occurrence is encrypted and
+    /// later ones are recorded as extra sites of the same entry, so every use
+    /// shares one ciphertext and one slot in the runtime decrypt cache.
     pub fn obfuscate(
         &self,
         strings: &[StringLiteral],
         encrypt_all: bool,
     ) -> Result<Vec<EncryptedString>> {
-        let mut encrypted_strings = Vec::new();
+        let mut encrypted_strings: Vec<EncryptedString> = Vec::new();
+        // (value, fragmented) → its entries (several when split into
+        // fragments); a whole literal never reuses a fragmented entry
+        let mut interned: HashMap<(&str, bool), std::ops::Range<usize>> = HashMap::new();
 
         for string_lit in strings {
             // Determine if this string should be encrypted
@@ -33,36 +191,104 @@ impl<'a> StringObfuscator<'a> {
                     Sensitivity::High | Sensitivity::Medium
                 );
 
-            if should_encrypt {
-                let encrypted = self.encrypt_string(string_lit)?;
-                encrypted_strings.push(encrypted);
+            if !should_encrypt || self.plaintext.matching(&string_lit.value).is_some() {
+                continue;
+            }
+
+            let fragment_size = self
+                .fragment_size
+                .filter(|size| string_lit.value.chars().count() > *size)
+                .filter(|_| !self.whole.contains(&(string_lit.line, string_lit.column)));
+            let key = (string_lit.value.as_str(), fragment_size.is_some());
+            if let Some(entries) = interned.get(&key) {
+                for entry in &mut encrypted_strings[entries.clone()] {
+                    entry.sites.push((string_lit.line, string_lit.column));
+                }
+                continue;
             }
+
+            let first = encrypted_strings.len();
+            match fragment_size {
+                Some(size) => encrypted_strings.extend(self.encrypt_fragments(string_lit, size)?),
+                None => encrypted_strings.push(self.encrypt_string(string_lit)?),
+            }
+            interned.insert(key, first..encrypted_strings.len());
         }
 
-        log::debug!("Encrypted {} strings", encrypted_strings.len());
+        tracing::debug!(
+            "Encrypted {} strings ({} unique)",
+            encrypted_strings.iter().map(|e| e.sites.len()).sum::<usize>(),
+            interned.len()
+        );
         Ok(encrypted_strings)
     }
 
+    /// Encrypt a single literal outside the sensitivity filter (used by the constant pool)
+    pub fn encrypt_literal(&self, string_lit: &StringLiteral) -> Result<EncryptedString> {
+        self.encrypt_string(string_lit)
+    }
+
+    /// Split a string on character boundaries and encrypt each fragment
+    fn encrypt_fragments(
+        &self,
+        string_lit: &StringLiteral,
+        fragment_size: usize,
+    ) -> Result<Vec<EncryptedString>> {
+        let chars: Vec<char> = string_lit.value.chars().collect();
+        let pieces: Vec<String> = chars
+            .chunks(fragment_size)
+            .map(|chunk| chunk.iter().collect())
+            .collect();
+
+        let parent_id = self.generate_string_id();
+        let count = pieces.len();
+
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(index, piece)| {
+                let mut encrypted = self.encrypt_string(&StringLiteral {
+                    value: piece,
+                    ..string_lit.clone()
+                })?;
+                encrypted.fragment = Some(StringFragment {
+                    parent_id: parent_id.clone(),
+                    index,
+                    count,
+                });
+                Ok(encrypted)
+            })
+            .collect()
+    }
+
     /// Encrypt a single string
     fn encrypt_string(&self, string_lit: &StringLiteral) -> Result<EncryptedString> {
         let plaintext = string_lit.value.as_bytes();
-        let encrypted_data = self.crypto_ctx.encrypt(plaintext)?;
+        let (encrypted_data, compression) = match self.compression.shrink(plaintext)? {
+            Some(compressed) => (self.crypto_ctx.encrypt(&compressed)?, self.compression),
+            None => (self.crypto_ctx.encrypt(plaintext)?, Compression::None),
+        };
 
         // Generate unique ID for this encrypted string
-        let id = Self::generate_string_id();
+        let id = self.generate_string_id();
 
         Ok(EncryptedString {
             original: string_lit.value.clone(),
             encrypted_data: encrypted_data.ciphertext,
             nonce: encrypted_data.nonce,
             line: string_lit.line,
+            column: string_lit.column,
+            sites: vec![(string_lit.line, string_lit.column)],
             id,
+            fragment: None,
+            compression,
+            sensitivity: string_lit.sensitivity,
         })
     }
 
     /// Generate unique identifier for encrypted string
-    fn generate_string_id() -> String {
-        let random_suffix: String = rand::thread_rng()
+    fn generate_string_id(&self) -> String {
+        let random_suffix: String = (&mut *self.rng.borrow_mut())
             .sample_iter(&Alphanumeric)
             .take(8)
             .map(char::from)
@@ -107,6 +333,34 @@ mod tests {
         assert!(encrypted.id.starts_with("_S"));
     }
 
+    #[test]
+    fn test_compression_only_when_smaller() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let obfuscator = StringObfuscator::new(&crypto_ctx).with_compression(Compression::Zlib);
+        let literal = |value: &str| StringLiteral {
+            value: value.to_string(),
+            line: 1,
+            column: 0,
+            sensitivity: Sensitivity::High,
+        };
+
+        let long = "{\"items\":[1,2,3],\"enabled\":true}".repeat(20);
+        let compressed = obfuscator.encrypt_string(&literal(&long)).unwrap();
+        let short = obfuscator.encrypt_string(&literal("Hi")).unwrap();
+
+        assert_eq!(compressed.compression, Compression::Zlib);
+        assert!(compressed.encrypted_data.len() < long.len());
+        assert_eq!(short.compression, Compression::None);
+        let data = EncryptedData {
+            ciphertext: compressed.encrypted_data,
+            nonce: compressed.nonce,
+            tag_len: 16,
+            key_version: 0,
+            compression: compressed.compression,
+        };
+        assert_eq!(*crypto_ctx.decrypt(&data).unwrap(), long.as_bytes());
+    }
+
     #[test]
     fn test_selective_encryption() {
         let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -161,6 +415,39 @@ mod tests {
         assert_eq!(encrypted.len(), 2);
     }
 
+    #[test]
+    fn test_identical_strings_are_interned() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let obfuscator = StringObfuscator::with_fragment_size(&crypto_ctx, 4);
+
+        let literal = |value: &str, line| StringLiteral {
+            value: value.to_string(),
+            line,
+            column: 5,
+            sensitivity: Sensitivity::Medium,
+        };
+        let strings = vec![
+            literal("Humanoid", 1),
+            literal("Part", 2),
+            literal("Humanoid", 3),
+            literal("Humanoid", 4),
+        ];
+
+        let encrypted = obfuscator.obfuscate(&strings, true).unwrap();
+
+        // "Humanoid" is split into two fragments once; both carry every site
+        assert_eq!(encrypted.len(), 3);
+        let humanoid: Vec<&EncryptedString> = encrypted
+            .iter()
+            .filter(|e| e.fragment.is_some())
+            .collect();
+        assert_eq!(humanoid.len(), 2);
+        for fragment in humanoid {
+            assert_eq!(fragment.sites, vec![(1, 5), (3, 5), (4, 5)]);
+        }
+        assert_eq!(encrypted[2].sites, vec![(2, 5)]);
+    }
+
     #[test]
     fn test_decrypt_call_generation() {
         let encrypted = EncryptedString {
@@ -168,7 +455,12 @@ mod tests {
             encrypted_data: vec![1, 2, 3, 4],
             nonce: vec![5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
             line: 1,
+            column: 1,
+            sites: vec![(1, 1)],
             id: "_S12345678".to_string(),
+            fragment: None,
+            compression: Compression::None,
+            sensitivity: Sensitivity::Medium,
         };
 
         let decrypt_call = StringObfuscator::generate_decrypt_call(&encrypted);
@@ -176,4 +468,123 @@ mod tests {
         assert!(decrypt_call.starts_with("_decrypt("));
         assert!(decrypt_call.contains("\"AQIDBA==")); // base64 of [1,2,3,4]
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_long_strings_are_fragmented() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let obfuscator = StringObfuscator::with_fragment_size(&crypto_ctx, 4);
+
+        let strings = vec![
+            StringLiteral {
+                value: "ReplicatedStorage".to_string(),
+                line: 1,
+                column: 0,
+                sensitivity: Sensitivity::High,
+            },
+            StringLiteral {
+                value: "abc".to_string(),
+                line: 2,
+                column: 0,
+                sensitivity: Sensitivity::High,
+            },
+        ];
+
+        let encrypted = obfuscator.obfuscate(&strings, true).unwrap();
+
+        // 17 chars in fragments of 4 -> 5 pieces, plus one short string
+        assert_eq!(encrypted.len(), 6);
+        let reassembled: String = encrypted[..5].iter().map(|e| e.original.as_str()).collect();
+        assert_eq!(reassembled, "ReplicatedStorage");
+        assert!(encrypted[..5].iter().all(|e| e.fragment.as_ref().unwrap().count == 5));
+        assert!(encrypted[5].fragment.is_none());
+    }
+
+    #[test]
+    fn test_fragments_split_on_char_boundaries() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let obfuscator = StringObfuscator::with_fragment_size(&crypto_ctx, 2);
+
+        let strings = vec![StringLiteral {
+            value: "héllo✓".to_string(),
+            line: 1,
+            column: 0,
+            sensitivity: Sensitivity::High,
+        }];
+
+        let encrypted = obfuscator.obfuscate(&strings, true).unwrap();
+        let pieces: Vec<&str> = encrypted.iter().map(|e| e.original.as_str()).collect();
+        assert_eq!(pieces, vec!["hé", "ll", "o✓"]);
+    }
+
+    #[test]
+    fn test_plaintext_rules_skip_matches() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let rules = PlaintextRules::new(&[
+            PlaintextRule {
+                name: Some("assets".to_string()),
+                pattern: "glob:rbxassetid://*".to_string(),
+            },
+            PlaintextRule {
+                name: None,
+                pattern: "^Anim_".to_string(),
+            },
+        ])
+        .unwrap();
+        let obfuscator = StringObfuscator::new(&crypto_ctx).with_plaintext_rules(rules.clone());
+
+        let literal = |value: &str, line| StringLiteral {
+            value: value.to_string(),
+            line,
+            column: 0,
+            sensitivity: Sensitivity::High,
+        };
+        let strings = vec![
+            literal("rbxassetid://507766666", 1),
+            literal("Anim_Reload", 2),
+            literal("sk_live_secret", 3),
+        ];
+
+        let encrypted = obfuscator.obfuscate(&strings, true).unwrap();
+        assert_eq!(encrypted.len(), 1);
+        assert_eq!(encrypted[0].original, "sk_live_secret");
+
+        let kept = rules.kept(&strings);
+        assert_eq!(kept.len(), 2);
+        assert_eq!((kept[0].line, kept[0].rule.as_str()), (1, "assets"));
+        assert_eq!(kept[1].rule, "^Anim_");
+
+        let invalid = PlaintextRule {
+            name: None,
+            pattern: "(".to_string(),
+        };
+        assert!(PlaintextRules::new(&[invalid]).is_err());
+    }
+
+    #[test]
+    fn test_whole_strings_are_not_fragmented_or_shared() {
+        let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+        let obfuscator = StringObfuscator::with_fragment_size(&crypto_ctx, 4)
+            .with_whole_strings([(2, 30)].into_iter().collect());
+        let literal = |line, column| StringLiteral {
+            value: "PlayerRemotes".to_string(),
+            line,
+            column,
+            sensitivity: Sensitivity::High,
+        };
+        let strings = vec![literal(1, 10), literal(2, 30), literal(3, 5)];
+
+        let encrypted = obfuscator.obfuscate(&strings, true).unwrap();
+        let whole: Vec<&EncryptedString> =
+            encrypted.iter().filter(|e| e.fragment.is_none()).collect();
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].original, "PlayerRemotes");
+        assert_eq!(whole[0].sites, vec![(2, 30)]);
+        // The other two share the fragments
+        assert_eq!(encrypted.len(), 5);
+        assert!(encrypted
+            .iter()
+            .filter(|e| e.fragment.is_some())
+            .all(|e| e.sites == vec![(1, 10), (3, 5)]));
+    }
+
+}
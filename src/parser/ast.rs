@@ -1,5 +1,8 @@
 //! AST definitions and data structures
 
+use super::config_block::ConfigBlock;
+use super::directives::FunctionDirective;
+use super::types::TypeAlias;
 use full_moon::ast::Ast;
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +17,15 @@ pub struct ParseResult {
     pub numbers: Vec<NumericLiteral>,
     /// Extracted function information
     pub functions: Vec<FunctionInfo>,
+    /// Declared Luau type aliases
+    pub type_aliases: Vec<TypeAlias>,
+    /// `--!obfuscate:` overrides, in source order
+    pub directives: Vec<FunctionDirective>,
+    /// Settings block marked `--!obfuscate: config`
+    pub config_block: Option<ConfigBlock>,
+    /// Lines of statements the visitor doesn't look into; literals in them
+    /// are neither extracted nor protected
+    pub unsupported_statements: Vec<usize>,
 }
 
 /// String literal found in the source
@@ -38,10 +50,78 @@ pub struct NumericLiteral {
     pub line: usize,
     /// Source location (column number)
     pub column: usize,
-    /// Whether this is a float
+    /// Whether this is a float (anything but an integer a double holds exactly)
     pub is_float: bool,
 }
 
+/// Largest magnitude up to which a Luau number holds every integer exactly
+pub const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+impl NumericLiteral {
+    /// Literal written as `text` at `line`, `column`
+    pub fn new(text: &str, line: usize, column: usize) -> Self {
+        Self {
+            value: text.to_string(),
+            line,
+            column,
+            is_float: parse_number(text).and_then(safe_integer).is_none(),
+        }
+    }
+
+    /// The literal's value, if it is valid Luau number syntax
+    pub fn number(&self) -> Option<f64> {
+        parse_number(&self.value)
+    }
+
+    /// The value in canonical decimal form (`0xFF` → `255`, `1e5` → `100000`)
+    ///
+    /// Falls back to the text without `_` separators if it doesn't parse.
+    pub fn normalized(&self) -> String {
+        match self.number() {
+            Some(value) => format_number(value),
+            None => self.value.replace('_', ""),
+        }
+    }
+}
+
+/// Value of Luau number syntax: decimal, scientific, `0x` hex or `0b`
+/// binary, with optional `_` separators and a leading `-`
+pub fn parse_number(text: &str) -> Option<f64> {
+    if let Some(magnitude) = text.strip_prefix('-') {
+        return parse_number(magnitude).map(|value| -value);
+    }
+    let digits = text.replace('_', "").to_ascii_lowercase();
+    if let Some(hex) = digits.strip_prefix("0x") {
+        return u64::from_str_radix(hex, 16).ok().map(|value| value as f64);
+    }
+    if let Some(binary) = digits.strip_prefix("0b") {
+        return u64::from_str_radix(binary, 2).ok().map(|value| value as f64);
+    }
+    // Rust also reads `inf` and `nan`, which Luau spells as expressions
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    digits.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+/// `value` as an `i64`, if it is an integer a double holds exactly
+pub fn safe_integer(value: f64) -> Option<i64> {
+    (value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER).then_some(value as i64)
+}
+
+/// Shortest Luau number literal that reads back as `value`
+pub fn format_number(value: f64) -> String {
+    if let Some(integer) = safe_integer(value) {
+        return integer.to_string();
+    }
+    let magnitude = value.abs();
+    if magnitude >= 1e16 || magnitude < 1e-5 {
+        format!("{:e}", value)
+    } else {
+        value.to_string()
+    }
+}
+
 /// Function information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionInfo {
@@ -51,6 +131,10 @@ pub struct FunctionInfo {
     pub parameters: Vec<String>,
     /// Source location (line number)
     pub line: usize,
+    /// Source location (column number)
+    pub column: usize,
+    /// Line of the closing `end`
+    pub end_line: usize,
     /// Whether this is a local function
     pub is_local: bool,
 }
@@ -59,38 +143,21 @@ pub struct FunctionInfo {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Sensitivity {
     /// High sensitivity (API keys, RemoteEvent names, etc.)
+    #[serde(alias = "high")]
     High,
     /// Medium sensitivity (UI text, config values)
+    #[serde(alias = "medium")]
     Medium,
     /// Low sensitivity (debug messages, comments)
+    #[serde(alias = "low")]
     Low,
 }
 
 impl Sensitivity {
-    /// Classify a string's sensitivity based on heuristics
+    /// Classify a string's sensitivity with the built-in rules
+    ///
+    /// See [`SensitivityRules`](super::SensitivityRules) for user-extendable rules.
     pub fn classify(value: &str) -> Self {
-        // High sensitivity patterns
-        if value.contains("Remote")
-            || value.contains("Event")
-            || value.contains("Function")
-            || value.contains("API")
-            || value.contains("Key")
-            || value.contains("Secret")
-            || value.contains("Token")
-        {
-            return Sensitivity::High;
-        }
-
-        // Low sensitivity patterns
-        if value.starts_with("[")
-            || value.starts_with("Debug:")
-            || value.starts_with("Warning:")
-            || value.starts_with("Error:")
-        {
-            return Sensitivity::Low;
-        }
-
-        // Default to medium
-        Sensitivity::Medium
+        super::sensitivity::SensitivityRules::builtin().classify(value)
     }
 }
@@ -0,0 +1,117 @@
+//! Block nesting over raw tokens
+//!
+//! The directive scanner and the chunked parser find where blocks end without
+//! building an AST. Luau's if-expressions (`local x = if a then b else c`)
+//! start with `if` but have no `end`, and `function` or `if` after `=` is an
+//! operand rather than a new statement, so the token before each keyword
+//! decides what it opens.
+
+use full_moon::tokenizer::{Symbol, Token, TokenType};
+
+/// Tokens after which an expression is expected
+const EXPRESSION_STARTS: &[&str] = &[
+    "=", "(", ",", "{", "[", "return", "if", "elseif", "while", "until", "..", "and", "or", "not",
+    "+", "-", "*", "/", "//", "%", "^", "#", "==", "~=", "<", "<=", ">", ">=", "+=", "-=", "*=",
+    "/=", "//=", "%=", "^=", "..=",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Open {
+    /// `function`, `do`, `if` statement or `repeat`; closed by `end`/`until`
+    Block,
+    /// `if` used as an expression; its `else` branch ends it
+    IfExpression,
+}
+
+/// Blocks open at the current token of a token walk
+#[derive(Debug, Default)]
+pub(crate) struct BlockNesting {
+    open: Vec<Open>,
+    /// Whether the next token is an operand
+    expression_next: bool,
+}
+
+impl BlockNesting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of open blocks that need an `end` or `until`
+    pub fn depth(&self) -> usize {
+        self.open.iter().filter(|open| **open == Open::Block).count()
+    }
+
+    /// Whether the next token continues an expression rather than starting
+    /// a statement
+    pub fn in_expression(&self) -> bool {
+        self.expression_next
+    }
+
+    /// Take the next significant (non-trivia) token into account
+    pub fn advance(&mut self, token: &Token) {
+        let TokenType::Symbol { symbol } = token.token_type() else {
+            self.expression_next = false;
+            return;
+        };
+        let in_if_expression = self.open.last() == Some(&Open::IfExpression);
+
+        match symbol {
+            Symbol::Function | Symbol::Do | Symbol::Repeat => self.open.push(Open::Block),
+            Symbol::If if self.expression_next => self.open.push(Open::IfExpression),
+            Symbol::If => self.open.push(Open::Block),
+            Symbol::Else if in_if_expression => {
+                self.open.pop();
+            }
+            Symbol::End | Symbol::Until => {
+                // An if-expression never outlives the block it is written in
+                while self.open.pop() == Some(Open::IfExpression) {}
+            }
+            _ => {}
+        }
+
+        self.expression_next = match symbol {
+            // Branches of an if-expression are expressions, statement bodies aren't
+            Symbol::Then | Symbol::Else => in_if_expression,
+            _ => EXPRESSION_STARTS.contains(&token.to_string().as_str()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use full_moon::tokenizer::tokens;
+
+    /// Depth after each significant token of `source`
+    fn depths(source: &str) -> Vec<usize> {
+        let mut nesting = BlockNesting::new();
+        tokens(source)
+            .unwrap()
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .map(|t| {
+                nesting.advance(t);
+                nesting.depth()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_if_expressions_open_no_block() {
+        let source = "local x = if a then 1 elseif b then 2 else if c then 3 else 4";
+        assert!(depths(source).iter().all(|depth| *depth == 0));
+
+        // Only the statement `if` needs an `end`
+        let source = "if if a then b else c then x += 1 end";
+        assert_eq!(depths(source).last(), Some(&0));
+        assert_eq!(depths(source).iter().max(), Some(&1));
+    }
+
+    #[test]
+    fn test_functions_inside_if_expressions_close() {
+        let source = "local f = if a then function() return 1 end else nil\nlocal y = 2";
+        let depths = depths(source);
+        assert_eq!(depths.iter().max(), Some(&1));
+        assert_eq!(depths.last(), Some(&0));
+    }
+}
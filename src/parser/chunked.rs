@@ -0,0 +1,286 @@
+//! Chunked parsing for very large scripts
+//!
+//! Splits the source into groups of top-level statements, parses each group
+//! on its own and drops its AST as soon as literals have been extracted. Peak
+//! memory is bounded by the largest chunk rather than the whole file.
+
+use super::ast::ParseResult;
+use super::blocks::BlockNesting;
+use super::config_block::scan_config_block;
+use super::directives::scan_directives;
+use super::types::scan_types;
+use super::visitor::AstVisitor;
+use crate::progress::{no_progress, SharedProgress, Stage};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, TokenType};
+use tracing::debug;
+
+/// Default number of top-level statements per chunk
+pub const DEFAULT_CHUNK_STATEMENTS: usize = 256;
+
+/// A slice of the source containing whole top-level statements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceChunk<'a> {
+    /// Source text of the chunk
+    pub source: &'a str,
+    /// Line number of the first line in the chunk (1-based)
+    pub start_line: usize,
+}
+
+/// Parser that processes top-level statements in bounded chunks
+pub struct ChunkedParser {
+    chunk_statements: usize,
+    progress: SharedProgress,
+}
+
+impl ChunkedParser {
+    pub fn new() -> Self {
+        Self {
+            chunk_statements: DEFAULT_CHUNK_STATEMENTS,
+            progress: no_progress(),
+        }
+    }
+
+    /// Create a chunked parser with a custom statement budget per chunk
+    pub fn with_chunk_size(chunk_statements: usize) -> Self {
+        Self {
+            chunk_statements: chunk_statements.max(1),
+            progress: no_progress(),
+        }
+    }
+
+    /// Report each parsed chunk to `progress`
+    pub fn with_progress(mut self, progress: SharedProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Parse source chunk by chunk, releasing each AST after extraction
+    pub fn parse(&self, source: &str) -> Result<ParseResult> {
+        let chunks = self.split_chunks(source)?;
+        debug!("Streaming parse over {} chunks", chunks.len());
+
+        let mut result = ParseResult {
+            ast: None,
+            strings: Vec::new(),
+            numbers: Vec::new(),
+            functions: Vec::new(),
+            type_aliases: Vec::new(),
+            // Scanned over the whole source: a directive comment can end up
+            // in the chunk before its function
+            directives: scan_directives(source)?,
+            config_block: scan_config_block(source)?,
+            unsupported_statements: Vec::new(),
+        };
+
+        let total = chunks.len();
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let ast = full_moon::parse(chunk.source).map_err(|e| {
+                ObfuscatorError::ParseError(format!(
+                    "Failed to parse chunk starting at line {}: {}",
+                    chunk.start_line, e
+                ))
+            })?;
+
+            let mut visitor = AstVisitor::new();
+            visitor.visit_ast(&ast);
+            drop(ast);
+
+            let offset = chunk.start_line - 1;
+            result
+                .type_aliases
+                .extend(scan_types(chunk.source)?.aliases.into_iter().map(|mut a| {
+                    a.line += offset;
+                    a
+                }));
+            result.strings.extend(visitor.strings.into_iter().map(|mut s| {
+                s.line += offset;
+                s
+            }));
+            result.numbers.extend(visitor.numbers.into_iter().map(|mut n| {
+                n.line += offset;
+                n
+            }));
+            result.functions.extend(visitor.functions.into_iter().map(|mut f| {
+                f.line += offset;
+                f.end_line += offset;
+                f
+            }));
+            result
+                .unsupported_statements
+                .extend(visitor.unsupported.into_iter().map(|line| line + offset));
+            self.progress.items_processed(Stage::Parse, index + 1, total);
+        }
+
+        Ok(result)
+    }
+
+    /// Split source into chunks on top-level statement boundaries
+    ///
+    /// A boundary is only taken at a line that starts with a statement keyword
+    /// while no block, table or parenthesised expression is open, so a chunk
+    /// never ends in the middle of an expression that continues on the next
+    /// line.
+    pub fn split_chunks<'a>(&self, source: &'a str) -> Result<Vec<SourceChunk<'a>>> {
+        let tokens = tokens(source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+        let mut chunks = Vec::new();
+        let mut blocks = BlockNesting::new();
+        let mut bracket_depth: i64 = 0;
+        let mut statements = 0;
+        let mut chunk_start_byte = 0;
+        let mut chunk_start_line = 1;
+        let mut last_line = 0;
+
+        for token in &tokens {
+            let token_type = token.token_type();
+            if token_type.is_trivia() {
+                continue;
+            }
+
+            let position = token.start_position();
+            let first_on_line = position.line() != last_line;
+            last_line = position.line();
+
+            if let TokenType::Symbol { symbol } = token_type {
+                let at_top_level = blocks.depth() == 0 && bracket_depth == 0;
+
+                // `local x =` followed by `if`/`function` on the next line is
+                // still the same statement
+                if at_top_level
+                    && first_on_line
+                    && !blocks.in_expression()
+                    && Self::starts_statement(symbol)
+                {
+                    if statements >= self.chunk_statements {
+                        chunks.push(SourceChunk {
+                            source: &source[chunk_start_byte..position.bytes()],
+                            start_line: chunk_start_line,
+                        });
+                        chunk_start_byte = position.bytes();
+                        chunk_start_line = position.line();
+                        statements = 0;
+                    }
+                    statements += 1;
+                }
+
+                match symbol {
+                    Symbol::LeftParen | Symbol::LeftBrace | Symbol::LeftBracket => {
+                        bracket_depth += 1
+                    }
+                    Symbol::RightParen | Symbol::RightBrace | Symbol::RightBracket => {
+                        bracket_depth -= 1
+                    }
+                    _ => {}
+                }
+            }
+            blocks.advance(token);
+        }
+
+        if chunk_start_byte < source.len() {
+            chunks.push(SourceChunk {
+                source: &source[chunk_start_byte..],
+                start_line: chunk_start_line,
+            });
+        }
+
+        Ok(chunks)
+    }
+
+    /// Keywords that can only begin a new statement
+    fn starts_statement(symbol: &Symbol) -> bool {
+        matches!(
+            symbol,
+            Symbol::Local
+                | Symbol::Function
+                | Symbol::If
+                | Symbol::For
+                | Symbol::While
+                | Symbol::Do
+                | Symbol::Repeat
+                | Symbol::Return
+        )
+    }
+}
+
+impl Default for ChunkedParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_respects_blocks() {
+        let source = "local a = 1\n\
+                      function f()\n\
+                          local b = 2\n\
+                          local c = 3\n\
+                      end\n\
+                      local d = 4\n";
+
+        let parser = ChunkedParser::with_chunk_size(1);
+        let chunks = parser.split_chunks(source).unwrap();
+
+        // Locals inside f() must not start a new chunk
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[1].source.contains("end"));
+        assert_eq!(chunks[2].start_line, 6);
+    }
+
+    #[test]
+    fn test_split_respects_tables() {
+        let source = "local t = {\n    1,\n    2,\n}\nlocal u = 5\n";
+
+        let parser = ChunkedParser::with_chunk_size(1);
+        let chunks = parser.split_chunks(source).unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].start_line, 5);
+    }
+
+    #[test]
+    fn test_split_respects_if_expressions() {
+        let source = "local a = if ready then \"on\" else \"off\"\n\
+                      local b =\n\
+                          if a == \"on\" then 1 else 2\n\
+                      local c = b\n";
+
+        let parser = ChunkedParser::with_chunk_size(1);
+        let chunks = parser.split_chunks(source).unwrap();
+
+        // The if-expressions close without `end`, and the `if` on line 3
+        // belongs to `local b`
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1].start_line, 2);
+        assert_eq!(chunks[2].start_line, 4);
+    }
+
+    #[test]
+    fn test_streaming_parse_matches_literals() {
+        let source = "local a = \"one\"\nlocal b = 2\nprint(\"three\")\nlocal c = 4\n";
+
+        let parser = ChunkedParser::with_chunk_size(1);
+        let result = parser.parse(source).unwrap();
+
+        assert!(result.ast.is_none());
+        assert_eq!(result.strings.len(), 2);
+        assert_eq!(result.numbers.len(), 2);
+        assert_eq!(result.numbers[1].line, 4);
+    }
+
+    #[test]
+    fn test_streaming_parse_reports_chunk_line() {
+        let source = "local a = 1\nlocal b = (\n";
+
+        let parser = ChunkedParser::with_chunk_size(1);
+        let err = parser.parse(source).unwrap_err().to_string();
+
+        assert!(err.contains("line 2"));
+    }
+}
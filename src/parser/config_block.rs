@@ -0,0 +1,312 @@
+//! Customer-editable settings block
+//!
+//! A `--!obfuscate: config` comment directly above a top-level table local
+//! marks the script's settings:
+//!
+//! ```lua
+//! --!obfuscate: config
+//! local CONFIG = {
+//!     Keybinds = { Sprint = Enum.KeyCode.LeftShift },
+//!     AccentColor = Color3.fromRGB(0, 170, 255),
+//! }
+//! ```
+//!
+//! The statement is lifted out of the protected code and written readable
+//! above it, so buyers can change values without seeing anything else. The
+//! local keeps its name and the table its keys, which is the interface the
+//! protected code reads the settings through. The block runs before the rest
+//! of the script, so it can only use globals (`Enum`, `Color3`, ...), not
+//! locals declared elsewhere.
+
+use super::blocks::BlockNesting;
+use super::directives::DIRECTIVE_PREFIX;
+use crate::utils::errors::{ObfuscatorError, SourceDiagnostic};
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::{Deserialize, Serialize};
+
+/// Directive level marking the settings block
+pub(crate) const CONFIG_DIRECTIVE: &str = "config";
+
+/// Tokens that would continue the expression after the table's `}`
+const CONTINUATIONS: &[&str] = &[
+    ".", ":", "[", "(", ",", "..", "and", "or", "+", "-", "*", "/", "//", "%", "^", "==", "~=",
+    "<", "<=", ">", ">=",
+];
+
+/// The `local NAME = { ... }` statement marked `--!obfuscate: config`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigBlock {
+    /// Name of the local holding the settings
+    pub name: String,
+    /// The statement as written, from `local` to the closing `}`
+    pub source: String,
+    /// Line of the `local` keyword
+    pub start_line: usize,
+    /// Line of the closing `}`
+    pub end_line: usize,
+    /// Byte range of the statement in the script
+    pub span: (usize, usize),
+}
+
+impl ConfigBlock {
+    /// Whether `line` falls inside the statement
+    pub fn covers(&self, line: usize) -> bool {
+        (self.start_line..=self.end_line).contains(&line)
+    }
+
+    /// The statement for the top of the protected script; `encode` writes
+    /// quoted strings as `\ddd` escapes so they don't read at a glance
+    pub fn render(&self, encode: bool) -> Result<String> {
+        if !encode {
+            return Ok(self.source.clone());
+        }
+        let all_tokens = tokens(&self.source)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        Ok(all_tokens
+            .iter()
+            .map(|token| match token.token_type() {
+                TokenType::StringLiteral {
+                    literal,
+                    multi_line: None,
+                    ..
+                } => {
+                    let text = token.to_string();
+                    let quote = &text[..1];
+                    format!("{}{}{}", quote, escape_literal(literal.as_str()), quote)
+                }
+                _ => token.to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Find the settings block marked `--!obfuscate: config`, if any
+///
+/// The marker must sit directly above a top-level `local NAME = { ... }` and
+/// appear only once.
+pub fn scan_config_block(source: &str) -> Result<Option<ConfigBlock>> {
+    let all_tokens = tokens(source)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+    let mut found: Option<ConfigBlock> = None;
+    let mut blocks = BlockNesting::new();
+    // Open `(`, `[` and `{`
+    let mut brackets = 0usize;
+    for (index, token) in all_tokens.iter().enumerate() {
+        let TokenType::SingleLineComment { comment } = token.token_type() else {
+            if !token.token_type().is_trivia() {
+                blocks.advance(token);
+                brackets = match symbol_of(token) {
+                    Some(Symbol::LeftParen | Symbol::LeftBracket | Symbol::LeftBrace) => {
+                        brackets + 1
+                    }
+                    Some(Symbol::RightParen | Symbol::RightBracket | Symbol::RightBrace) => {
+                        brackets.saturating_sub(1)
+                    }
+                    _ => brackets,
+                };
+            }
+            continue;
+        };
+        let is_marker = comment
+            .trim()
+            .strip_prefix(DIRECTIVE_PREFIX)
+            .and_then(|rest| rest.trim_start().strip_prefix(':'))
+            .is_some_and(|level| level.trim().eq_ignore_ascii_case(CONFIG_DIRECTIVE));
+        if !is_marker {
+            continue;
+        }
+
+        let line = token.start_position().line();
+        let error = |message: String| {
+            let column = token.start_position().character();
+            anyhow::Error::from(ObfuscatorError::SyntaxError(Box::new(
+                SourceDiagnostic::new(message, source, line, column),
+            )))
+        };
+        if let Some(first) = &found {
+            return Err(error(format!(
+                "only one `--!obfuscate: config` block is allowed (the first is above line {})",
+                first.start_line
+            )));
+        }
+        if blocks.depth() > 0 || brackets > 0 {
+            return Err(error(
+                "`--!obfuscate: config` must mark a top-level statement, not one inside a \
+                 function or block"
+                    .to_string(),
+            ));
+        }
+        let block = table_local(source, &all_tokens[index + 1..]).ok_or_else(|| {
+            error(
+                "`--!obfuscate: config` must be directly above `local NAME = { ... }`".to_string(),
+            )
+        })?;
+        found = Some(block);
+    }
+
+    Ok(found)
+}
+
+/// The `local NAME = { ... }` statement at the head of `tokens`
+fn table_local(source: &str, tokens: &[Token]) -> Option<ConfigBlock> {
+    let significant: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+        .collect();
+    let [local, name, equals, open, ..] = significant.as_slice() else {
+        return None;
+    };
+    let TokenType::Identifier { identifier } = name.token_type() else {
+        return None;
+    };
+    if symbol_of(local) != Some(Symbol::Local)
+        || symbol_of(equals) != Some(Symbol::Equal)
+        || symbol_of(open) != Some(Symbol::LeftBrace)
+    {
+        return None;
+    }
+
+    let mut depth = 0usize;
+    for (i, token) in significant.iter().enumerate().skip(3) {
+        match symbol_of(token) {
+            Some(Symbol::LeftBrace) => depth += 1,
+            Some(Symbol::RightBrace) => depth -= 1,
+            _ => {}
+        }
+        if depth > 0 {
+            continue;
+        }
+        let continues = significant
+            .get(i + 1)
+            .is_some_and(|next| CONTINUATIONS.contains(&next.to_string().as_str()));
+        if continues {
+            return None;
+        }
+        let span = (local.start_position().bytes(), token.end_position().bytes());
+        return Some(ConfigBlock {
+            name: identifier.to_string(),
+            source: source[span.0..span.1].to_string(),
+            start_line: local.start_position().line(),
+            end_line: token.start_position().line(),
+            span,
+        });
+    }
+    None
+}
+
+/// The body of a quoted string with each character outside escapes written
+/// as `\ddd` byte escapes
+fn escape_literal(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len() * 4);
+    let mut chars = literal.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                escaped.push_str(&format!("\\{:03}", byte));
+            }
+            continue;
+        }
+        // Existing escapes are copied whole, so their meaning doesn't change
+        escaped.push('\\');
+        let Some(kind) = chars.next() else {
+            break;
+        };
+        escaped.push(kind);
+        match kind {
+            '0'..='9' => {
+                for _ in 0..2 {
+                    match chars.next_if(char::is_ascii_digit) {
+                        Some(digit) => escaped.push(digit),
+                        None => break,
+                    }
+                }
+            }
+            'x' => escaped.extend(chars.by_ref().take(2)),
+            'u' => {
+                for c in chars.by_ref() {
+                    escaped.push(c);
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    escaped
+}
+
+fn symbol_of(token: &Token) -> Option<Symbol> {
+    match token.token_type() {
+        TokenType::Symbol { symbol } => Some(*symbol),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_block_found_below_marker() {
+        let source = r#"--!strict
+-- Settings
+--!obfuscate: config
+local CONFIG = {
+    Keybinds = { Sprint = "LeftShift" }, -- change me
+    Speed = 24,
+}
+
+local function run()
+    print(CONFIG.Speed)
+end
+"#;
+        let block = scan_config_block(source).unwrap().unwrap();
+        assert_eq!(block.name, "CONFIG");
+        assert_eq!((block.start_line, block.end_line), (4, 7));
+        assert!(block.source.starts_with("local CONFIG = {"));
+        assert!(block.source.ends_with("Speed = 24,\n}"));
+        assert_eq!(&source[block.span.0..block.span.1], block.source);
+        assert!(block.covers(5));
+        assert!(!block.covers(9));
+
+        assert!(scan_config_block("local x = 1\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_misplaced_config_markers_are_located() {
+        let cases = [
+            ("--!obfuscate: config\nlocal speed = 24\n", "directly above"),
+            (
+                "local function f()\n    --!obfuscate: config\n    local C = {}\nend\n",
+                "top-level",
+            ),
+            (
+                "--!obfuscate: config\nlocal C = {} .. x\n",
+                "directly above",
+            ),
+            (
+                "--!obfuscate: config\nlocal A = {}\n--!obfuscate: config\nlocal B = {}\n",
+                "only one",
+            ),
+        ];
+        for (source, expected) in cases {
+            let message = scan_config_block(source).unwrap_err().to_string();
+            assert!(message.contains(expected), "{}: {}", source, message);
+        }
+    }
+
+    #[test]
+    fn test_render_encodes_quoted_strings() {
+        let source = "--!obfuscate: config\nlocal C = { Title = \"Hi\\n\", Raw = [[x]] }\n";
+        let block = scan_config_block(source).unwrap().unwrap();
+        assert_eq!(block.render(false).unwrap(), block.source);
+        assert_eq!(
+            block.render(true).unwrap(),
+            "local C = { Title = \"\\072\\105\\n\", Raw = [[x]] }"
+        );
+    }
+}
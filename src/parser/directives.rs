@@ -0,0 +1,215 @@
+//! Per-function obfuscation directives
+//!
+//! A `--!obfuscate: <level>` comment directly above a function overrides the
+//! tier for that function's body:
+//!
+//! ```lua
+//! --!obfuscate: none
+//! RunService.RenderStepped:Connect(function(dt) ... end)
+//!
+//! --!obfuscate: max
+//! local function checkLicense(key) ... end
+//! ```
+//!
+//! `--!obfuscate: config` marks the settings block instead (see
+//! `config_block`).
+
+use super::blocks::BlockNesting;
+use super::config_block::CONFIG_DIRECTIVE;
+use crate::utils::errors::{ObfuscatorError, SourceDiagnostic};
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Comment prefix marking a directive (after the leading `--`)
+pub(super) const DIRECTIVE_PREFIX: &str = "!obfuscate";
+
+/// Protection requested for a single function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectionLevel {
+    /// Leave the function untouched except for renaming shared identifiers
+    None,
+    /// Basic tier settings
+    Basic,
+    /// Standard tier settings
+    Standard,
+    /// Premium tier settings
+    Max,
+}
+
+impl FromStr for ProtectionLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "off" => Ok(ProtectionLevel::None),
+            "basic" => Ok(ProtectionLevel::Basic),
+            "standard" => Ok(ProtectionLevel::Standard),
+            "max" | "premium" => Ok(ProtectionLevel::Max),
+            other => Err(format!(
+                "unknown obfuscation level `{}` (expected none, basic, standard or max)",
+                other
+            )),
+        }
+    }
+}
+
+/// A directive and the function it applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionDirective {
+    pub level: ProtectionLevel,
+    /// Line of the directive comment
+    pub directive_line: usize,
+    /// First line of the function (the `function` keyword)
+    pub start_line: usize,
+    /// Line of the function's closing `end`
+    pub end_line: usize,
+}
+
+impl FunctionDirective {
+    /// Whether `line` falls inside the annotated function
+    pub fn covers(&self, line: usize) -> bool {
+        (self.start_line..=self.end_line).contains(&line)
+    }
+}
+
+/// Find every `--!obfuscate:` directive and the function below it
+///
+/// Directives that aren't followed by a function on the next statement are
+/// ignored with a warning.
+pub fn scan_directives(source: &str) -> Result<Vec<FunctionDirective>> {
+    let all_tokens = tokens(source)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+
+    let mut directives = Vec::new();
+    for (index, token) in all_tokens.iter().enumerate() {
+        let TokenType::SingleLineComment { comment } = token.token_type() else {
+            continue;
+        };
+        let Some(rest) = comment.trim().strip_prefix(DIRECTIVE_PREFIX) else {
+            continue;
+        };
+
+        let value = rest.trim_start().strip_prefix(':');
+        if value.is_some_and(|level| level.trim().eq_ignore_ascii_case(CONFIG_DIRECTIVE)) {
+            continue;
+        }
+
+        let line = token.start_position().line();
+        let level = value
+            .ok_or_else(|| "expected `--!obfuscate: <level>`".to_string())
+            .and_then(|level| level.trim().parse::<ProtectionLevel>())
+            .map_err(|message| {
+                let column = token.start_position().character();
+                ObfuscatorError::SyntaxError(Box::new(SourceDiagnostic::new(
+                    message, source, line, column,
+                )))
+            })?;
+
+        match function_span(&all_tokens[index + 1..]) {
+            Some((start_line, end_line)) => directives.push(FunctionDirective {
+                level,
+                directive_line: line,
+                start_line,
+                end_line,
+            }),
+            None => log::warn!(
+                "Ignoring --!obfuscate directive on line {}: no function follows it",
+                line
+            ),
+        }
+    }
+
+    Ok(directives)
+}
+
+/// Lines spanned by the function starting in the statement at the head of
+/// `tokens` (`local function f`, `function M.f`, `x = function`, `f(function`)
+fn function_span(tokens: &[Token]) -> Option<(usize, usize)> {
+    let significant: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+        .collect();
+    let first_line = significant.first()?.start_position().line();
+
+    let start = significant
+        .iter()
+        .take_while(|t| t.start_position().line() == first_line)
+        .position(|t| is_symbol(t, Symbol::Function))?;
+
+    let mut blocks = BlockNesting::new();
+    for token in &significant[start..] {
+        blocks.advance(token);
+        if blocks.depth() == 0 {
+            return Some((first_line, token.start_position().line()));
+        }
+    }
+    None
+}
+
+fn is_symbol(token: &Token, expected: Symbol) -> bool {
+    matches!(token.token_type(), TokenType::Symbol { symbol } if *symbol == expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directives_attach_to_following_function() {
+        let source = r#"
+--!obfuscate: none
+RunService.RenderStepped:Connect(function(dt)
+    if dt > 1 then
+        print(dt)
+    end
+end)
+
+--!obfuscate: max
+local function checkLicense(key)
+    for i = 1, 3 do
+        print(i)
+    end
+    return key
+end
+
+--!obfuscate: max
+local x = 1
+"#;
+        let directives = scan_directives(source).unwrap();
+        assert_eq!(directives.len(), 2);
+
+        assert_eq!(directives[0].level, ProtectionLevel::None);
+        assert_eq!((directives[0].start_line, directives[0].end_line), (3, 7));
+
+        assert_eq!(directives[1].level, ProtectionLevel::Max);
+        assert_eq!((directives[1].start_line, directives[1].end_line), (10, 15));
+        assert!(directives[1].covers(12));
+        assert!(!directives[1].covers(17));
+    }
+
+    #[test]
+    fn test_directive_span_skips_if_expressions() {
+        let source = r#"
+--!obfuscate: none
+local function pick(flag)
+    local mode = if flag then "fast" else "safe"
+    return mode
+end
+local after = 1
+"#;
+        let directives = scan_directives(source).unwrap();
+        assert_eq!(directives.len(), 1);
+        assert_eq!((directives[0].start_line, directives[0].end_line), (3, 6));
+    }
+
+    #[test]
+    fn test_unknown_level_is_located() {
+        let err = scan_directives("\n--!obfuscate: extreme\nlocal function f() end\n").unwrap_err();
+        let diagnostic = err.to_string();
+        assert!(diagnostic.contains("unknown obfuscation level `extreme`"));
+        assert!(diagnostic.contains("<input>:2:"));
+    }
+}
@@ -0,0 +1,144 @@
+//! String literal values
+//!
+//! The tokenizer keeps a literal's text as written: escapes are still raw and
+//! a long string still holds the newline that may follow its opening bracket.
+//! Passes that encrypt or compare a literal need the string Luau builds at
+//! runtime, so they read it through [`string_value`].
+
+use full_moon::tokenizer::{Token, TokenType};
+
+/// Runtime value of a string literal token
+///
+/// `None` for other tokens, for malformed escapes, and for values that aren't
+/// valid UTF-8 (`"\255"`); callers leave those literals as written.
+pub(crate) fn string_value(token: &Token) -> Option<String> {
+    match token.token_type() {
+        TokenType::StringLiteral {
+            literal,
+            multi_line: Some(_),
+            ..
+        } => Some(long_string(literal).to_string()),
+        TokenType::StringLiteral { literal, .. } => unescape(literal),
+        _ => None,
+    }
+}
+
+/// Body of a `[[...]]` string, which skips a line break right after `[[`
+fn long_string(literal: &str) -> &str {
+    ["\r\n", "\n\r", "\n", "\r"]
+        .iter()
+        .find_map(|newline| literal.strip_prefix(newline))
+        .unwrap_or(literal)
+}
+
+/// Resolve the escapes of a quoted string's body
+fn unescape(literal: &str) -> Option<String> {
+    if !literal.contains('\\') {
+        return Some(literal.to_string());
+    }
+
+    let bytes = literal.as_bytes();
+    let mut value = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            value.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let escape = *bytes.get(i + 1)?;
+        i += 2;
+        match escape {
+            b'a' => value.push(0x07),
+            b'b' => value.push(0x08),
+            b'f' => value.push(0x0c),
+            b'n' => value.push(b'\n'),
+            b'r' => value.push(b'\r'),
+            b't' => value.push(b'\t'),
+            b'v' => value.push(0x0b),
+            b'\\' | b'"' | b'\'' => value.push(escape),
+            b'\n' | b'\r' => {
+                // A backslash before a line break keeps the break
+                value.push(b'\n');
+                let pair = if escape == b'\n' { b'\r' } else { b'\n' };
+                if bytes.get(i) == Some(&pair) {
+                    i += 1;
+                }
+            }
+            b'z' => {
+                while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+                    i += 1;
+                }
+            }
+            b'x' => {
+                let hex = literal.get(i..i + 2)?;
+                value.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 2;
+            }
+            b'u' => {
+                let rest = literal.get(i..)?.strip_prefix('{')?;
+                let close = rest.find('}')?;
+                let code = u32::from_str_radix(&rest[..close], 16).ok()?;
+                let character = char::from_u32(code)?;
+                value.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes());
+                i += close + 2;
+            }
+            b'0'..=b'9' => {
+                let digits = bytes[i - 1..]
+                    .iter()
+                    .take(3)
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                let code: u32 = literal[i - 1..i - 1 + digits].parse().ok()?;
+                value.push(u8::try_from(code).ok()?);
+                i += digits - 1;
+            }
+            _ => return None,
+        }
+    }
+    String::from_utf8(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use full_moon::tokenizer::tokens;
+
+    /// Value of the first string literal in `source`
+    fn value(source: &str) -> Option<String> {
+        let tokens = tokens(source).unwrap();
+        let literal = tokens
+            .iter()
+            .find(|t| matches!(t.token_type(), TokenType::StringLiteral { .. }))
+            .unwrap();
+        string_value(literal)
+    }
+
+    #[test]
+    fn test_quoted_escapes() {
+        assert_eq!(value(r#""plain""#).as_deref(), Some("plain"));
+        assert_eq!(value(r#""a\nb\tc""#).as_deref(), Some("a\nb\tc"));
+        assert_eq!(value(r#""say \"hi\"""#).as_deref(), Some("say \"hi\""));
+        assert_eq!(value(r#"'it\'s'"#).as_deref(), Some("it's"));
+        assert_eq!(value(r#""back\\slash""#).as_deref(), Some("back\\slash"));
+        assert_eq!(value(r#""\65\x42\u{43}\0679""#).as_deref(), Some("ABCC9"));
+        assert_eq!(value("\"a\\z   b\"").as_deref(), Some("ab"));
+        assert_eq!(value(r#""\u{20AC}""#).as_deref(), Some("€"));
+    }
+
+    #[test]
+    fn test_long_strings() {
+        assert_eq!(value("[[x]]").as_deref(), Some("x"));
+        assert_eq!(value("[==[a]]b]==]").as_deref(), Some("a]]b"));
+        assert_eq!(value("[[\nfirst\nsecond]]").as_deref(), Some("first\nsecond"));
+        assert_eq!(value(r"[[no \n escapes]]").as_deref(), Some(r"no \n escapes"));
+    }
+
+    #[test]
+    fn test_undecodable_values() {
+        assert_eq!(value(r#""\255""#), None);
+        assert_eq!(value(r#""\256""#), None);
+        assert_eq!(value(r#""\xZZ""#), None);
+        assert_eq!(value(r#""\q""#), None);
+    }
+}
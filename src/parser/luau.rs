@@ -1,21 +1,47 @@
 //! Luau-specific parser implementation using full_moon
 
 use super::ast::{FunctionInfo, NumericLiteral, ParseResult, StringLiteral};
+use super::chunked::ChunkedParser;
+use super::config_block::scan_config_block;
+use super::directives::scan_directives;
+use super::sensitivity::SensitivityRules;
+use super::types::scan_types;
 use super::visitor::AstVisitor;
-use anyhow::{Context, Result};
-use full_moon::ast::Ast;
+use crate::progress::{no_progress, SharedProgress};
+use crate::utils::errors::{ObfuscatorError, SourceDiagnostic};
+use anyhow::Result;
+use full_moon::ast::{Ast, AstError};
 use full_moon::parse;
-use log::debug;
+use tracing::debug;
 
 /// Luau parser using full_moon
 pub struct LuauParser {
-    // Parser configuration could go here
+    /// Replaces the built-in string sensitivity rules when set
+    sensitivity_rules: Option<SensitivityRules>,
+    progress: SharedProgress,
 }
 
 impl LuauParser {
     /// Create a new Luau parser
     pub fn new() -> Self {
-        Self {}
+        Self {
+            sensitivity_rules: None,
+            progress: no_progress(),
+        }
+    }
+
+    /// Create a parser classifying string literals with custom rules
+    pub fn with_sensitivity_rules(rules: SensitivityRules) -> Self {
+        Self {
+            sensitivity_rules: Some(rules),
+            progress: no_progress(),
+        }
+    }
+
+    /// Report streaming parses chunk by chunk to `progress`
+    pub fn with_progress(mut self, progress: SharedProgress) -> Self {
+        self.progress = progress;
+        self
     }
 
     /// Parse a Luau source string into an AST and extract information
@@ -23,7 +49,9 @@ impl LuauParser {
         debug!("Parsing Luau source ({} bytes)", source.len());
 
         // Parse the source using full_moon
-        let ast = parse(source).context("Failed to parse Luau source")?;
+        let ast = parse(source).map_err(|e| {
+            ObfuscatorError::SyntaxError(Box::new(syntax_diagnostic(source, &e)))
+        })?;
 
         debug!("Parse successful, visiting AST nodes");
 
@@ -32,12 +60,17 @@ impl LuauParser {
         visitor.visit_ast(&ast);
 
         // Extract results
-        let result = ParseResult {
+        let mut result = ParseResult {
             ast: Some(ast),
             strings: visitor.strings,
             numbers: visitor.numbers,
             functions: visitor.functions,
+            type_aliases: scan_types(source)?.aliases,
+            directives: scan_directives(source)?,
+            config_block: scan_config_block(source)?,
+            unsupported_statements: visitor.unsupported,
         };
+        self.classify_strings(&mut result);
 
         debug!(
             "Extraction complete: {} strings, {} numbers, {} functions",
@@ -48,6 +81,52 @@ impl LuauParser {
 
         Ok(result)
     }
+
+    /// Parse a very large script in bounded-memory chunks
+    ///
+    /// The returned result has no AST; each chunk's AST is released as soon
+    /// as its literals and functions have been extracted.
+    pub fn parse_streaming(&self, source: &str) -> Result<ParseResult> {
+        debug!("Streaming parse of Luau source ({} bytes)", source.len());
+        let mut result = ChunkedParser::new()
+            .with_progress(self.progress.clone())
+            .parse(source)?;
+        self.classify_strings(&mut result);
+        Ok(result)
+    }
+
+    /// Re-classify string literals with the configured rules
+    fn classify_strings(&self, result: &mut ParseResult) {
+        if let Some(rules) = &self.sensitivity_rules {
+            for string in &mut result.strings {
+                string.sensitivity = rules.classify(&string.value);
+            }
+        }
+    }
+}
+
+/// Locate a full_moon parse failure in the source
+fn syntax_diagnostic(source: &str, error: &full_moon::Error) -> SourceDiagnostic {
+    match error {
+        full_moon::Error::AstError(AstError::UnexpectedToken { token, additional }) => {
+            let position = token.token().start_position();
+            let message = match additional {
+                Some(detail) => format!("unexpected token `{}`, {}", token.token(), detail),
+                None => format!("unexpected token `{}`", token.token()),
+            };
+            SourceDiagnostic::new(message, source, position.line(), position.character())
+        }
+        full_moon::Error::TokenizerError(e) => {
+            let position = e.position();
+            SourceDiagnostic::new(e.error().to_string(), source, position.line(), position.character())
+        }
+        other => {
+            // Errors without a token (empty input, missing EOF) point at the end
+            let line = source.lines().count().max(1);
+            let column = source.lines().last().map_or(0, |l| l.chars().count()) + 1;
+            SourceDiagnostic::new(other.to_string(), source, line, column)
+        }
+    }
 }
 
 impl Default for LuauParser {
@@ -59,6 +138,7 @@ impl Default for LuauParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::{Sensitivity, SensitivityRule};
 
     #[test]
     fn test_parse_simple_script() {
@@ -87,6 +167,100 @@ mod tests {
         assert_eq!(result.functions[0].name, Some("greet".to_string()));
     }
 
+    #[test]
+    fn test_parse_literals_in_nested_blocks() {
+        let parser = LuauParser::new();
+        let source = r#"
+            local function run(list)
+                for _, item in ipairs({ "a", "b" }) do
+                    while item ~= "stop" do
+                        item = if item == "x" then "y" else "z"
+                    end
+                end
+                return list[1], "done"
+            end
+        "#;
+
+        let result = parser.parse(source).unwrap();
+        let strings: Vec<&str> = result.strings.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(strings, vec!["a", "b", "stop", "x", "y", "z", "done"]);
+        assert_eq!(result.numbers.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_modern_luau_syntax() {
+        let parser = LuauParser::new();
+        let source = r#"
+            local scores = { alice = 10, bob = 7 }
+            local total = 0
+            for name, score in scores do
+                if score < 8 then
+                    continue
+                end
+                total += score * 2
+                print(`{name} scored`, if score > 9 then "high" else "ok")
+            end
+            total -= 3
+        "#;
+
+        let result = parser.parse(source).unwrap();
+        let strings: Vec<&str> = result.strings.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(strings, vec![" scored", "high", "ok"]);
+        let numbers: Vec<&str> = result.numbers.iter().map(|n| n.value.as_str()).collect();
+        assert_eq!(numbers, vec!["10", "7", "0", "8", "2", "9", "3"]);
+    }
+
+    #[test]
+    fn test_literal_positions() {
+        let parser = LuauParser::new();
+        let source = "local a = 1\nif a then\n    print(\"x\", 2.5)\nend\nlocal f = function()\n    return 0\nend\n";
+
+        let result = parser.parse(source).unwrap();
+        assert_eq!((result.strings[0].line, result.strings[0].column), (3, 11));
+        assert_eq!(result.strings[0].value, "x");
+        let positions: Vec<(usize, usize)> =
+            result.numbers.iter().map(|n| (n.line, n.column)).collect();
+        assert_eq!(positions, vec![(1, 11), (3, 16), (6, 12)]);
+        assert_eq!((result.functions[0].line, result.functions[0].end_line), (5, 7));
+    }
+
+    #[test]
+    fn test_interpolated_string_fragments() {
+        let parser = LuauParser::new();
+        let source = "local msg = `Fire{remote:GetName()}Event {`n{n}`} \\{raw}`\n";
+
+        let result = parser.parse(source).unwrap();
+        let fragments: Vec<(&str, usize, Sensitivity)> = result
+            .strings
+            .iter()
+            .map(|s| (s.value.as_str(), s.column, s.sensitivity))
+            .collect();
+        // The nested string's empty tail and the escaped fragment are skipped
+        assert_eq!(
+            fragments,
+            vec![
+                ("Fire", 13, Sensitivity::Medium),
+                ("Event ", 35, Sensitivity::High),
+                ("n", 43, Sensitivity::Medium),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_sensitivity_rules() {
+        let rules = SensitivityRules::new(&[SensitivityRule {
+            name: None,
+            pattern: "glob:Coins*".to_string(),
+            level: Sensitivity::High,
+        }])
+        .unwrap();
+        let parser = LuauParser::with_sensitivity_rules(rules);
+
+        let result = parser.parse("local a, b = \"CoinsEarned\", \"hello\"\n").unwrap();
+        assert_eq!(result.strings[0].sensitivity, Sensitivity::High);
+        assert_eq!(result.strings[1].sensitivity, Sensitivity::Medium);
+    }
+
     #[test]
     fn test_parse_roblox_api() {
         let parser = LuauParser::new();
@@ -99,6 +273,22 @@ mod tests {
         assert!(result.is_ok(), "Should parse Roblox API calls");
     }
 
+    #[test]
+    fn test_parse_type_annotations() {
+        let parser = LuauParser::new();
+        let source = r#"
+            type Inventory = { [string]: number }
+            local function count(inv: Inventory, item: string): number
+                return inv[item] or 0
+            end
+        "#;
+
+        let result = parser.parse(source).unwrap();
+        assert_eq!(result.type_aliases.len(), 1);
+        assert_eq!(result.type_aliases[0].name, "Inventory");
+        assert_eq!(result.functions[0].parameters, vec!["inv", "item"]);
+    }
+
     #[test]
     fn test_parse_invalid_syntax() {
         let parser = LuauParser::new();
@@ -107,4 +297,19 @@ mod tests {
         let result = parser.parse(source);
         assert!(result.is_err(), "Should fail on invalid syntax");
     }
+
+    #[test]
+    fn test_parse_error_has_location() {
+        let parser = LuauParser::new();
+        let source = "local x = 1\nlocal y = = 2\n";
+
+        let err = parser.parse(source).unwrap_err();
+        match err.downcast_ref::<ObfuscatorError>() {
+            Some(ObfuscatorError::SyntaxError(diagnostic)) => {
+                assert_eq!(diagnostic.line, 2);
+                assert_eq!(diagnostic.source_line, "local y = = 2");
+            }
+            other => panic!("expected syntax error, got {:?}", other),
+        }
+    }
 }
@@ -1,9 +1,26 @@
 //! Luau parser module - AST parsing and analysis
 
 mod ast;
+mod blocks;
+mod chunked;
+mod config_block;
+mod directives;
+mod literals;
 mod luau;
+mod sensitivity;
+mod types;
 mod visitor;
 
-pub use ast::{ParseResult, StringLiteral, NumericLiteral, FunctionInfo};
+pub(crate) use blocks::BlockNesting;
+pub(crate) use literals::string_value;
+pub use ast::{
+    format_number, parse_number, safe_integer, FunctionInfo, NumericLiteral, ParseResult,
+    Sensitivity, StringLiteral, MAX_SAFE_INTEGER,
+};
+pub use chunked::{ChunkedParser, SourceChunk, DEFAULT_CHUNK_STATEMENTS};
+pub use config_block::{scan_config_block, ConfigBlock};
+pub use directives::{scan_directives, FunctionDirective, ProtectionLevel};
 pub use luau::LuauParser;
+pub use sensitivity::{compile_pattern, SensitivityRule, SensitivityRules};
+pub use types::{scan_types, strip_types, TypeAlias, TypeAnnotations, TypeScan};
 pub use visitor::AstVisitor;
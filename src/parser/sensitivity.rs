@@ -0,0 +1,191 @@
+//! Rule-based string sensitivity classification
+//!
+//! Each rule maps a regex or glob pattern to a [`Sensitivity`]; the first
+//! matching rule wins and unmatched strings are `Medium`. User rules from the
+//! config file are checked before the built-in ones:
+//!
+//! ```toml
+//! [[sensitivity_rules]]
+//! name = "shop ids"
+//! pattern = "glob:Shop_*"
+//! level = "high"
+//! ```
+
+use super::ast::Sensitivity;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Prefix marking a glob pattern (`*` and `?` wildcards, whole-string match)
+const GLOB_PREFIX: &str = "glob:";
+
+/// Built-in rules as `(name, pattern, level)`, in priority order
+const BUILTIN_RULES: &[(&str, &str, Sensitivity)] = &[
+    (
+        "webhook url",
+        r"(?i)^https?://([a-z0-9-]+\.)?discord(app)?\.com/api/webhooks/",
+        Sensitivity::High,
+    ),
+    (
+        "api key",
+        r"^(sk|pk|rk)_(live|test)_[A-Za-z0-9]{16,}$|^[A-Za-z0-9_-]{32,}$",
+        Sensitivity::High,
+    ),
+    ("asset id", r"(?i)^rbxassetid://\d+$|roblox\.com/asset/\?id=\d+", Sensitivity::High),
+    ("remote name", r"Remote|Event|Function", Sensitivity::High),
+    ("credential keyword", r"API|Key|Secret|Token", Sensitivity::High),
+    ("log message", r"^\[|^(Debug|Warning|Error):", Sensitivity::Low),
+];
+
+/// A user-defined classification rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensitivityRule {
+    /// Label used in diagnostics
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Regex, or a glob when prefixed with `glob:`
+    pub pattern: String,
+    pub level: Sensitivity,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    name: String,
+    regex: Regex,
+    level: Sensitivity,
+}
+
+/// Ordered set of compiled rules
+#[derive(Debug, Clone)]
+pub struct SensitivityRules {
+    rules: Vec<CompiledRule>,
+}
+
+impl SensitivityRules {
+    /// User rules followed by the built-in defaults
+    pub fn new(user_rules: &[SensitivityRule]) -> Result<Self> {
+        let mut rules = user_rules
+            .iter()
+            .map(|rule| {
+                let name = rule.name.clone().unwrap_or_else(|| rule.pattern.clone());
+                Ok(CompiledRule {
+                    regex: compile(&rule.pattern, &name)?,
+                    name,
+                    level: rule.level,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        rules.extend(Self::builtin().rules.iter().cloned());
+        Ok(Self { rules })
+    }
+
+    /// The built-in defaults, compiled once
+    pub fn builtin() -> &'static SensitivityRules {
+        static BUILTIN: OnceLock<SensitivityRules> = OnceLock::new();
+        BUILTIN.get_or_init(|| SensitivityRules {
+            rules: BUILTIN_RULES
+                .iter()
+                .map(|(name, pattern, level)| CompiledRule {
+                    name: name.to_string(),
+                    regex: Regex::new(pattern).expect("built-in sensitivity rule"),
+                    level: *level,
+                })
+                .collect(),
+        })
+    }
+
+    /// Sensitivity of `value` under the first matching rule
+    pub fn classify(&self, value: &str) -> Sensitivity {
+        match self.rules.iter().find(|rule| rule.regex.is_match(value)) {
+            Some(rule) => {
+                log::trace!("{:?} matched sensitivity rule '{}'", value, rule.name);
+                rule.level
+            }
+            None => Sensitivity::Medium,
+        }
+    }
+}
+
+impl Default for SensitivityRules {
+    fn default() -> Self {
+        Self::builtin().clone()
+    }
+}
+
+fn compile(pattern: &str, name: &str) -> Result<Regex> {
+    compile_pattern(pattern).map_err(|e| {
+        ObfuscatorError::ConfigError(format!("Invalid sensitivity rule '{}': {}", name, e)).into()
+    })
+}
+
+/// Compile a regex, or a glob when prefixed with `glob:`
+pub fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let source = match pattern.strip_prefix(GLOB_PREFIX) {
+        Some(glob) => glob_to_regex(glob),
+        None => pattern.to_string(),
+    };
+    Regex::new(&source)
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_rules() {
+        let rules = SensitivityRules::builtin();
+        assert_eq!(
+            rules.classify("https://discord.com/api/webhooks/123/abc"),
+            Sensitivity::High
+        );
+        assert_eq!(rules.classify("rbxassetid://1234567"), Sensitivity::High);
+        assert_eq!(rules.classify("PurchaseRemote"), Sensitivity::High);
+        assert_eq!(rules.classify("Debug: loaded"), Sensitivity::Low);
+        assert_eq!(rules.classify("Hello"), Sensitivity::Medium);
+    }
+
+    #[test]
+    fn test_user_rules_take_precedence() {
+        let rules = SensitivityRules::new(&[
+            SensitivityRule {
+                name: None,
+                pattern: "glob:Shop_*".to_string(),
+                level: Sensitivity::High,
+            },
+            SensitivityRule {
+                name: Some("quiet events".to_string()),
+                pattern: "^UIEvent".to_string(),
+                level: Sensitivity::Low,
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(rules.classify("Shop_Sword"), Sensitivity::High);
+        assert_eq!(rules.classify("MyShop_Sword"), Sensitivity::Medium);
+        assert_eq!(rules.classify("UIEventFired"), Sensitivity::Low);
+        // Built-ins still apply afterwards
+        assert_eq!(rules.classify("Debug: x"), Sensitivity::Low);
+
+        let invalid = SensitivityRule {
+            name: None,
+            pattern: "(".to_string(),
+            level: Sensitivity::High,
+        };
+        assert!(SensitivityRules::new(&[invalid]).is_err());
+    }
+}
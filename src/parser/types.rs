@@ -0,0 +1,361 @@
+//! Luau type annotation handling
+//!
+//! Locates type aliases and annotations (`type Foo = ...`, `x: T`,
+//! `): R`, `f<T>(...)`, `expr :: T`) at the token level. The spans can be
+//! removed with `strip_types`, or left in place so alias names are renamed
+//! through the same mapping as every other identifier.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// How type annotations are treated in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeAnnotations {
+    /// Keep annotations; alias names are mangled consistently with their uses
+    #[default]
+    Preserve,
+    /// Remove every annotation and type declaration
+    Strip,
+}
+
+/// A `type Name = ...` declaration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeAlias {
+    /// Alias name
+    pub name: String,
+    /// Source location (line number)
+    pub line: usize,
+    /// Declared with `export type`
+    pub exported: bool,
+}
+
+/// Type information found in a source file
+#[derive(Debug, Clone, Default)]
+pub struct TypeScan {
+    /// Byte ranges covering annotations and type declarations, in source order
+    pub annotations: Vec<Range<usize>>,
+    /// Declared type aliases
+    pub aliases: Vec<TypeAlias>,
+}
+
+/// Find all type annotations and aliases in Luau source
+pub fn scan_types(source: &str) -> Result<TypeScan> {
+    let all_tokens = tokens(source)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+    let significant: Vec<&Token> = all_tokens
+        .iter()
+        .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+        .collect();
+
+    let mut scanner = TypeScanner {
+        tokens: &significant,
+        scan: TypeScan::default(),
+    };
+    scanner.run();
+    Ok(scanner.scan)
+}
+
+/// Remove all type annotations and type declarations from Luau source
+pub fn strip_types(source: &str) -> Result<String> {
+    let scan = scan_types(source)?;
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+
+    for span in &scan.annotations {
+        output.push_str(&source[cursor..span.start]);
+        cursor = span.end;
+    }
+    output.push_str(&source[cursor..]);
+
+    Ok(output)
+}
+
+struct TypeScanner<'a> {
+    tokens: &'a [&'a Token],
+    scan: TypeScan,
+}
+
+impl<'a> TypeScanner<'a> {
+    fn run(&mut self) {
+        let mut i = 0;
+
+        while i < self.tokens.len() {
+            if let Some(next) = self.type_declaration(i) {
+                i = next;
+                continue;
+            }
+
+            i = match self.symbol(i) {
+                Some(Symbol::Function) => self.function_signature(i + 1),
+                Some(Symbol::Local) if self.symbol(i + 1) != Some(Symbol::Function) => {
+                    self.bindings(i + 1)
+                }
+                Some(Symbol::For) => self.bindings(i + 1),
+                Some(Symbol::TwoColons) => {
+                    let end = self.skip_type(i + 1);
+                    self.record(i, end);
+                    end
+                }
+                _ => i + 1,
+            };
+        }
+    }
+
+    /// `[export] type Name[<T>] = Type`
+    fn type_declaration(&mut self, i: usize) -> Option<usize> {
+        let (start, exported) = match self.identifier(i) {
+            Some("export") if self.identifier(i + 1) == Some("type") => (i, true),
+            Some("type") => (i, false),
+            _ => return None,
+        };
+
+        let name_index = if exported { i + 2 } else { i + 1 };
+        let name = self.identifier(name_index)?;
+
+        let mut j = name_index + 1;
+        if self.symbol(j) == Some(Symbol::LessThan) {
+            j = self.skip_balanced(j);
+        }
+        if self.symbol(j) != Some(Symbol::Equal) {
+            // `type(x)` and friends are ordinary calls
+            return None;
+        }
+
+        self.scan.aliases.push(TypeAlias {
+            name: name.to_string(),
+            line: self.tokens[name_index].start_position().line(),
+            exported,
+        });
+
+        let end = self.skip_type(j + 1);
+        self.record(start, end);
+        Some(end)
+    }
+
+    /// Generics, parameter annotations and return type after `function`
+    fn function_signature(&mut self, mut i: usize) -> usize {
+        // Skip the (possibly dotted/method) name
+        while self.identifier(i).is_some()
+            || matches!(self.symbol(i), Some(Symbol::Dot) | Some(Symbol::Colon))
+        {
+            i += 1;
+        }
+
+        if self.symbol(i) == Some(Symbol::LessThan) {
+            let end = self.skip_balanced(i);
+            self.record(i, end);
+            i = end;
+        }
+
+        if self.symbol(i) != Some(Symbol::LeftParen) {
+            return i;
+        }
+        i += 1;
+
+        while i < self.tokens.len() {
+            match self.symbol(i) {
+                Some(Symbol::RightParen) => {
+                    i += 1;
+                    break;
+                }
+                Some(Symbol::Colon) => {
+                    let end = self.skip_type(i + 1);
+                    self.record(i, end);
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+
+        if self.symbol(i) == Some(Symbol::Colon) {
+            let end = self.skip_type(i + 1);
+            self.record(i, end);
+            i = end;
+        }
+
+        i
+    }
+
+    /// `name[: Type], name[: Type]` after `local` or `for`
+    fn bindings(&mut self, mut i: usize) -> usize {
+        while self.identifier(i).is_some() {
+            i += 1;
+            if self.symbol(i) == Some(Symbol::Colon) {
+                let end = self.skip_type(i + 1);
+                self.record(i, end);
+                i = end;
+            }
+            if self.symbol(i) != Some(Symbol::Comma) {
+                break;
+            }
+            i += 1;
+        }
+        i
+    }
+
+    /// Skip a full type, including unions and intersections
+    fn skip_type(&self, i: usize) -> usize {
+        // Leading separator is allowed: `type T = | A | B`
+        let mut j = match self.symbol(i) {
+            Some(Symbol::Pipe) | Some(Symbol::Ampersand) => i + 1,
+            _ => i,
+        };
+
+        j = self.skip_simple_type(j);
+        while matches!(self.symbol(j), Some(Symbol::Pipe) | Some(Symbol::Ampersand)) {
+            j = self.skip_simple_type(j + 1);
+        }
+        j
+    }
+
+    fn skip_simple_type(&self, i: usize) -> usize {
+        let Some(token) = self.tokens.get(i) else {
+            return i;
+        };
+
+        let mut j = match token.token_type() {
+            TokenType::Symbol { symbol } => match symbol {
+                // Generic function type: <T>(T) -> T
+                Symbol::LessThan => return self.skip_simple_type(self.skip_balanced(i)),
+                Symbol::LeftParen => {
+                    let j = self.skip_balanced(i);
+                    if self.symbol(j) == Some(Symbol::ThinArrow) {
+                        return self.skip_type(j + 1);
+                    }
+                    j
+                }
+                Symbol::LeftBrace => self.skip_balanced(i),
+                Symbol::Ellipse => self.skip_simple_type(i + 1),
+                Symbol::Nil | Symbol::True | Symbol::False => i + 1,
+                _ => return i,
+            },
+            TokenType::Identifier { identifier } => {
+                let mut j = i + 1;
+                if identifier.as_str() == "typeof" && self.symbol(j) == Some(Symbol::LeftParen) {
+                    j = self.skip_balanced(j);
+                } else {
+                    while self.symbol(j) == Some(Symbol::Dot) && self.identifier(j + 1).is_some() {
+                        j += 2;
+                    }
+                    if self.symbol(j) == Some(Symbol::LessThan) {
+                        j = self.skip_balanced(j);
+                    }
+                }
+                j
+            }
+            TokenType::StringLiteral { .. } => i + 1,
+            _ => return i,
+        };
+
+        while self.symbol(j) == Some(Symbol::QuestionMark) {
+            j += 1;
+        }
+        j
+    }
+
+    /// Skip from an opening bracket to just past its matching close
+    fn skip_balanced(&self, i: usize) -> usize {
+        let mut depth = 0i64;
+        let mut j = i;
+
+        while j < self.tokens.len() {
+            match self.symbol(j) {
+                Some(Symbol::LeftParen)
+                | Some(Symbol::LeftBrace)
+                | Some(Symbol::LeftBracket)
+                | Some(Symbol::LessThan) => depth += 1,
+                Some(Symbol::RightParen)
+                | Some(Symbol::RightBrace)
+                | Some(Symbol::RightBracket)
+                | Some(Symbol::GreaterThan) => depth -= 1,
+                _ => {}
+            }
+            j += 1;
+            if depth <= 0 {
+                break;
+            }
+        }
+        j
+    }
+
+    /// Record tokens `start..end` as a removable span
+    fn record(&mut self, start: usize, end: usize) {
+        if end <= start || end > self.tokens.len() {
+            return;
+        }
+        let from = self.tokens[start].start_position().bytes();
+        let to = self.tokens[end - 1].end_position().bytes();
+        self.scan.annotations.push(from..to);
+    }
+
+    fn symbol(&self, i: usize) -> Option<Symbol> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Symbol { symbol } => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn identifier(&self, i: usize) -> Option<&'a str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPED: &str = "export type Point = { x: number, y: number }\n\
+                         type Callback<T> = (T, string?) -> ()\n\
+                         local function add(a: number, b: number): number\n\
+                             return a + b\n\
+                         end\n\
+                         local p: Point = { x = 1, y = 2 }\n\
+                         local n = (p :: any).x\n\
+                         for i: number = 1, 3 do print(type(i)) end\n\
+                         player:Kick(\"bye\")\n";
+
+    #[test]
+    fn test_scan_finds_aliases() {
+        let scan = scan_types(TYPED).unwrap();
+        let names: Vec<_> = scan.aliases.iter().map(|a| a.name.as_str()).collect();
+
+        assert_eq!(names, vec!["Point", "Callback"]);
+        assert!(scan.aliases[0].exported);
+        assert!(!scan.aliases[1].exported);
+        assert_eq!(scan.aliases[1].line, 2);
+    }
+
+    #[test]
+    fn test_strip_removes_annotations() {
+        let stripped = strip_types(TYPED).unwrap();
+
+        assert!(!stripped.contains("type Point"));
+        assert!(!stripped.contains("Callback"));
+        assert!(!stripped.contains(": number"));
+        assert!(!stripped.contains(":: any"));
+        assert!(stripped.contains("local function add(a, b)"));
+        assert!(stripped.contains("local p = { x = 1, y = 2 }"));
+        // Method calls and the type() builtin are untouched
+        assert!(stripped.contains("player:Kick(\"bye\")"));
+        assert!(stripped.contains("print(type(i))"));
+    }
+
+    #[test]
+    fn test_strip_without_types_is_identity() {
+        let source = "local x = 1\nfunction obj:method(a, b) return a end\n";
+        assert_eq!(strip_types(source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_stripped_output_parses() {
+        let stripped = strip_types(TYPED).unwrap();
+        assert!(full_moon::parse(&stripped).is_ok());
+    }
+}
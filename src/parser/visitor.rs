@@ -1,15 +1,24 @@
 //! AST visitor pattern for traversing full_moon AST
 
 use super::ast::{FunctionInfo, NumericLiteral, Sensitivity, StringLiteral};
-use full_moon::ast::{Ast, Expression, FunctionCall, Stmt, Value};
+use super::literals::string_value;
+use full_moon::ast::{
+    Ast, Block, Call, Expression, Field, FunctionArgs, FunctionCall, Index, LastStmt, Prefix, Stmt,
+    Suffix, TableConstructor, Value, Var,
+};
+use full_moon::node::Node;
+use full_moon::tokenizer::{TokenReference, TokenType};
 use full_moon::visitors::Visitor;
-use log::debug;
+use tracing::debug;
 
 /// AST visitor that extracts information during traversal
 pub struct AstVisitor {
     pub strings: Vec<StringLiteral>,
     pub numbers: Vec<NumericLiteral>,
     pub functions: Vec<FunctionInfo>,
+    /// Lines of statements skipped as unsupported
+    pub unsupported: Vec<usize>,
+    /// Line of the statement being visited
     current_line: usize,
 }
 
@@ -19,18 +28,31 @@ impl AstVisitor {
             strings: Vec::new(),
             numbers: Vec::new(),
             functions: Vec::new(),
+            unsupported: Vec::new(),
             current_line: 1,
         }
     }
 
     pub fn visit_ast(&mut self, ast: &Ast) {
         // Visit all top-level statements
-        for stmt in ast.nodes().stmts() {
+        self.visit_block(ast.nodes());
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for stmt in block.stmts() {
             self.visit_stmt(stmt);
         }
+
+        if let Some(last_stmt) = block.last_stmt() {
+            self.visit_last_stmt(last_stmt);
+        }
     }
 
     fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Some((line, _)) = start_of(stmt) {
+            self.current_line = line;
+        }
+
         match stmt {
             Stmt::LocalFunction(local_fn) => {
                 let name = local_fn.name().to_string();
@@ -41,14 +63,18 @@ impl AstVisitor {
                     .map(|p| p.to_string())
                     .collect();
 
+                let (line, column) = start_of(local_fn).unwrap_or((self.current_line, 0));
                 self.functions.push(FunctionInfo {
                     name: Some(name),
                     parameters,
-                    line: self.current_line,
+                    line,
+                    column,
+                    end_line: end_line_of(local_fn.func_body()).unwrap_or(line),
                     is_local: true,
                 });
 
-                debug!("Found local function at line {}", self.current_line);
+                debug!("Found local function at line {}", line);
+                self.visit_block(local_fn.func_body().block());
             }
 
             Stmt::FunctionDeclaration(fn_decl) => {
@@ -60,14 +86,18 @@ impl AstVisitor {
                     .map(|p| p.to_string())
                     .collect();
 
+                let (line, column) = start_of(fn_decl).unwrap_or((self.current_line, 0));
                 self.functions.push(FunctionInfo {
                     name: Some(name),
                     parameters,
-                    line: self.current_line,
+                    line,
+                    column,
+                    end_line: end_line_of(fn_decl.func_body()).unwrap_or(line),
                     is_local: false,
                 });
 
-                debug!("Found function declaration at line {}", self.current_line);
+                debug!("Found function declaration at line {}", line);
+                self.visit_block(fn_decl.func_body().block());
             }
 
             Stmt::LocalAssignment(local_assign) => {
@@ -78,22 +108,103 @@ impl AstVisitor {
             }
 
             Stmt::Assignment(assign) => {
+                // Index keys on the left (`t["key"] = ...`) hold literals too
+                for var in assign.var_list().iter() {
+                    self.visit_var(var);
+                }
                 // Visit expressions in the assignment
                 for expr in assign.expr_list().iter() {
                     self.visit_expression(expr);
                 }
             }
 
+            Stmt::CompoundAssignment(compound) => {
+                self.visit_var(compound.lhs());
+                self.visit_expression(compound.rhs());
+            }
+
             Stmt::FunctionCall(fn_call) => {
                 self.visit_function_call(fn_call);
             }
 
+            Stmt::Do(do_block) => {
+                self.visit_block(do_block.block());
+            }
+
+            Stmt::While(while_loop) => {
+                self.visit_expression(while_loop.condition());
+                self.visit_block(while_loop.block());
+            }
+
+            Stmt::Repeat(repeat) => {
+                self.visit_block(repeat.block());
+                self.visit_expression(repeat.until());
+            }
+
+            Stmt::If(if_stmt) => {
+                self.visit_expression(if_stmt.condition());
+                self.visit_block(if_stmt.block());
+
+                for else_if in if_stmt.else_if().into_iter().flatten() {
+                    self.visit_expression(else_if.condition());
+                    self.visit_block(else_if.block());
+                }
+
+                if let Some(else_block) = if_stmt.else_block() {
+                    self.visit_block(else_block);
+                }
+            }
+
+            Stmt::NumericFor(numeric_for) => {
+                self.visit_expression(numeric_for.start());
+                self.visit_expression(numeric_for.end());
+                if let Some(step) = numeric_for.step() {
+                    self.visit_expression(step);
+                }
+                self.visit_block(numeric_for.block());
+            }
+
+            Stmt::GenericFor(generic_for) => {
+                for expr in generic_for.expressions().iter() {
+                    self.visit_expression(expr);
+                }
+                self.visit_block(generic_for.block());
+            }
+
+            // Type declarations contain no runtime values
+            Stmt::TypeDeclaration(_) | Stmt::ExportedTypeDeclaration(_) => {}
+
             _ => {
-                // For other statement types, we'd need more detailed handling
+                debug!("Skipping unsupported statement at line {}", self.current_line);
+                self.unsupported.push(self.current_line);
             }
         }
     }
 
+    fn visit_last_stmt(&mut self, last_stmt: &LastStmt) {
+        // `break` and `continue` carry no values
+        if let LastStmt::Return(ret) = last_stmt {
+            for expr in ret.returns().iter() {
+                self.visit_expression(expr);
+            }
+        }
+    }
+
+    fn visit_var(&mut self, var: &Var) {
+        if let Var::Expression(var_expr) = var {
+            self.visit_prefix(var_expr.prefix());
+            for suffix in var_expr.suffixes() {
+                self.visit_suffix(suffix);
+            }
+        }
+    }
+
+    fn visit_prefix(&mut self, prefix: &Prefix) {
+        if let Prefix::Expression(expr) = prefix {
+            self.visit_expression(expr);
+        }
+    }
+
     fn visit_expression(&mut self, expr: &Expression) {
         match expr {
             Expression::Value { value, .. } => {
@@ -116,30 +227,19 @@ impl AstVisitor {
     fn visit_value(&mut self, value: &Box<Value>) {
         match value.as_ref() {
             Value::String(token) => {
-                let value_str = token.to_string();
-                // Remove surrounding quotes
-                let cleaned = value_str.trim_matches('"').trim_matches('\'').to_string();
-
-                self.strings.push(StringLiteral {
-                    value: cleaned.clone(),
-                    line: self.current_line,
-                    column: 0, // full_moon doesn't provide column info easily
-                    sensitivity: Sensitivity::classify(&cleaned),
-                });
-
-                debug!("Found string literal: {:?}", cleaned);
+                self.push_string(token);
             }
 
             Value::Number(token) => {
-                let value_str = token.to_string();
-                let is_float = value_str.contains('.');
-
-                self.numbers.push(NumericLiteral {
-                    value: value_str.clone(),
-                    line: self.current_line,
-                    column: 0,
-                    is_float,
-                });
+                let value_str = token.token().to_string();
+                let position = token.token().start_position();
+
+                // `0xFF` and `1e5` are integers too
+                self.numbers.push(NumericLiteral::new(
+                    &value_str,
+                    position.line(),
+                    position.character(),
+                ));
 
                 debug!("Found numeric literal: {}", value_str);
             }
@@ -148,57 +248,170 @@ impl AstVisitor {
                 self.visit_function_call(fn_call);
             }
 
+            Value::InterpolatedString(interpolated) => {
+                for segment in interpolated.segments() {
+                    self.push_fragment(&segment.literal);
+                    self.visit_expression(&segment.expression);
+                }
+                self.push_fragment(interpolated.last_string());
+            }
+
             Value::Function(func_body) => {
                 let parameters = func_body.parameters().iter().map(|p| p.to_string()).collect();
 
+                let (line, column) = start_of(func_body).unwrap_or((self.current_line, 0));
                 self.functions.push(FunctionInfo {
                     name: None,
                     parameters,
-                    line: self.current_line,
+                    line,
+                    column,
+                    end_line: end_line_of(func_body).unwrap_or(line),
                     is_local: false,
                 });
 
-                debug!("Found anonymous function at line {}", self.current_line);
+                debug!("Found anonymous function at line {}", line);
+                self.visit_block(func_body.block());
+            }
+
+            Value::TableConstructor(table) => {
+                self.visit_table(table);
+            }
+
+            Value::ParenthesesExpression(expr) => {
+                self.visit_expression(expr);
+            }
+
+            Value::Var(var) => {
+                self.visit_var(var);
+            }
+
+            Value::IfExpression(if_expr) => {
+                self.visit_expression(if_expr.condition());
+                self.visit_expression(if_expr.if_expression());
+                for else_if in if_expr.else_if_expressions().into_iter().flatten() {
+                    self.visit_expression(else_if.condition());
+                    self.visit_expression(else_if.expression());
+                }
+                self.visit_expression(if_expr.else_expression());
             }
 
             _ => {}
         }
     }
 
-    fn visit_function_call(&mut self, fn_call: &FunctionCall) {
-        // Visit arguments to extract string/number literals
-        if let Some(args) = fn_call.suffixes().iter().find_map(|suffix| {
-            if let full_moon::ast::Suffix::Call(call) = suffix {
-                match call {
-                    full_moon::ast::Call::AnonymousCall(args) => Some(args),
-                    full_moon::ast::Call::MethodCall(method_call) => Some(method_call.args()),
-                    _ => None,
+    fn visit_table(&mut self, table: &TableConstructor) {
+        for field in table.fields().iter() {
+            match field {
+                Field::ExpressionKey { key, value, .. } => {
+                    self.visit_expression(key);
+                    self.visit_expression(value);
                 }
-            } else {
-                None
-            }
-        }) {
-            match args {
-                full_moon::ast::FunctionArgs::Parentheses { arguments, .. } => {
-                    for expr in arguments.iter() {
-                        self.visit_expression(expr);
-                    }
+                Field::NameKey { value, .. } => {
+                    self.visit_expression(value);
                 }
-                full_moon::ast::FunctionArgs::String(token) => {
-                    let value_str = token.to_string();
-                    let cleaned = value_str.trim_matches('"').trim_matches('\'').to_string();
-
-                    self.strings.push(StringLiteral {
-                        value: cleaned.clone(),
-                        line: self.current_line,
-                        column: 0,
-                        sensitivity: Sensitivity::classify(&cleaned),
-                    });
+                Field::NoKey(value) => {
+                    self.visit_expression(value);
                 }
                 _ => {}
             }
         }
     }
+
+    fn visit_function_call(&mut self, fn_call: &FunctionCall) {
+        self.visit_prefix(fn_call.prefix());
+
+        // Visit every index and call in the chain (`a["b"](1):c("d")`)
+        for suffix in fn_call.suffixes() {
+            self.visit_suffix(suffix);
+        }
+    }
+
+    fn visit_suffix(&mut self, suffix: &Suffix) {
+        match suffix {
+            Suffix::Index(Index::Brackets { expression, .. }) => {
+                self.visit_expression(expression);
+            }
+            Suffix::Call(Call::AnonymousCall(args)) => {
+                self.visit_function_args(args);
+            }
+            Suffix::Call(Call::MethodCall(method_call)) => {
+                self.visit_function_args(method_call.args());
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_function_args(&mut self, args: &FunctionArgs) {
+        match args {
+            FunctionArgs::Parentheses { arguments, .. } => {
+                for expr in arguments.iter() {
+                    self.visit_expression(expr);
+                }
+            }
+            FunctionArgs::String(token) => {
+                self.push_string(token);
+            }
+            FunctionArgs::TableConstructor(table) => {
+                self.visit_table(table);
+            }
+            _ => {}
+        }
+    }
+
+    /// Record a string literal token at its source position
+    ///
+    /// The value is the string Luau builds at runtime, with escapes resolved
+    /// and long brackets removed; literals that don't decode to UTF-8 text
+    /// are left as written.
+    fn push_string(&mut self, token: &TokenReference) {
+        let Some(value) = string_value(token.token()) else {
+            return;
+        };
+        let position = token.token().start_position();
+
+        debug!("Found string literal: {:?}", value);
+
+        self.strings.push(StringLiteral {
+            sensitivity: Sensitivity::classify(&value),
+            value,
+            line: position.line(),
+            column: position.character(),
+        });
+    }
+
+    /// Record the constant text of an interpolated string token (`` `a{ ``,
+    /// `}b{` or `` }c` ``) at its source position
+    ///
+    /// Empty fragments hold nothing, and ones with escapes (`\{`, `` \` ``)
+    /// are left as written rather than encrypted as their raw text.
+    fn push_fragment(&mut self, token: &TokenReference) {
+        let TokenType::InterpolatedString { literal, .. } = token.token().token_type() else {
+            return;
+        };
+        if literal.is_empty() || literal.contains('\\') {
+            return;
+        }
+        let position = token.token().start_position();
+
+        self.strings.push(StringLiteral {
+            value: literal.to_string(),
+            line: position.line(),
+            column: position.character(),
+            sensitivity: Sensitivity::classify(literal.as_str()),
+        });
+
+        debug!("Found interpolated string fragment: {:?}", literal.as_str());
+    }
+}
+
+/// Line and column where a node starts
+fn start_of(node: &impl Node) -> Option<(usize, usize)> {
+    node.start_position().map(|p| (p.line(), p.character()))
+}
+
+/// Line of the closing `end` of a function body
+fn end_line_of(node: &impl Node) -> Option<usize> {
+    node.end_position().map(|p| p.line())
 }
 
 impl Default for AstVisitor {
@@ -0,0 +1,690 @@
+//! End-to-end protection pipeline
+//!
+//! Connects the stages into one call: parse, analyze, obfuscate, rewrite the
+//! source with the obfuscation run, then assemble the protected script. The
+//! CLI and library users go through `ObfuscationPipeline` rather than wiring
+//! the stages by hand.
+
+use crate::analysis::{AnalysisOptions, Analyzer, ApiDatabase, DynamicGlobal};
+use crate::codegen::{
+    CodeGenConfig, CodeGenerator, LintFinding, LintLevel, RobloxLint, RunContext,
+    RuntimeGenerator,
+};
+use crate::crypto::{Compression, CryptoContext, EncryptedData};
+use crate::diagnostics::{Diagnostic, Span};
+use crate::obfuscation::{
+    Exclusions, HotFunctions, NamingStyle, ObfuscatedScript, ObfuscationSettings, ObfuscationStats,
+    ObfuscationResult, ObfuscationTier, Obfuscator, OverheadBudget, PlaintextRules, RngMode,
+    Simplified, Simplifier, SizeStats, SkippedBlock, SourceRewriter, TableKeyMode, TransformDelta,
+    TransformFailure, SCRIPT_SOURCE_BUDGET,
+};
+use crate::memstats::MemoryStats;
+use crate::parser::{LuauParser, ParseResult};
+use crate::progress::{self, no_progress, SharedProgress, Stage};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+
+/// Options for one pipeline run
+#[derive(Debug, Clone)]
+pub struct PipelineSettings {
+    pub tier: ObfuscationTier,
+    /// Settings replacing the tier's (a preset's tuning of them)
+    pub obfuscation: Option<ObfuscationSettings>,
+    pub analysis: AnalysisOptions,
+    /// Roblox API names kept readable (`ApiDatabase::load` picks up
+    /// `update-api-db`'s refresh)
+    pub api_db: ApiDatabase,
+    /// Seed every randomized pass, so names and layout repeat across runs
+    /// (salts and nonces don't, so ciphertext still differs)
+    pub seed: Option<u64>,
+    /// Parse in bounded-memory chunks (no AST, so no dead code placement);
+    /// ignored by `protect_parsed`
+    pub streaming: bool,
+    /// Functions kept free of flattening and dead code
+    pub hot_functions: HotFunctions,
+    /// Cap on dead code as a share of the input size
+    pub max_overhead: Option<f32>,
+    /// Emit a loader and an encrypted payload module instead of one script
+    pub split: bool,
+    /// How mangled identifiers look
+    pub naming_style: NamingStyle,
+    /// Compress large strings and the payload chunk before encryption
+    pub compression: Compression,
+    /// Rewrite table keys proven local to the script
+    pub table_keys: Option<TableKeyMode>,
+    /// Check the output against Roblox's limits and APIs, failing on errors
+    pub lint: bool,
+    /// Strings, names and functions a review switched off
+    pub exclusions: Exclusions,
+    /// Strings never encrypted, whatever the tier
+    pub plaintext_rules: PlaintextRules,
+    /// Skip passes and blocks that fail, with a warning, instead of failing
+    pub best_effort: bool,
+    /// Inline single-use literal locals and drop unread ones before analysis
+    pub simplify: bool,
+}
+
+impl PipelineSettings {
+    /// The settings passes run with
+    pub fn obfuscation_settings(&self) -> ObfuscationSettings {
+        self.obfuscation
+            .clone()
+            .unwrap_or_else(|| self.tier.settings())
+    }
+}
+
+impl Default for PipelineSettings {
+    fn default() -> Self {
+        Self {
+            tier: ObfuscationTier::Standard,
+            obfuscation: None,
+            analysis: AnalysisOptions::default(),
+            api_db: ApiDatabase::bundled(),
+            seed: None,
+            streaming: false,
+            hot_functions: HotFunctions::default(),
+            max_overhead: None,
+            split: false,
+            naming_style: NamingStyle::default(),
+            compression: Compression::None,
+            table_keys: None,
+            lint: true,
+            exclusions: Exclusions::default(),
+            plaintext_rules: PlaintextRules::default(),
+            best_effort: false,
+            simplify: false,
+        }
+    }
+}
+
+/// Everything a pipeline run produced
+pub struct ProtectedScript {
+    /// The protected script, or the loader when split
+    pub script: String,
+    /// Encrypted payload module, when split
+    pub payload: Option<String>,
+    /// Strings the script decrypts, in `_decrypt` index order (what
+    /// `CodeGenerator::export_payload` serves in remote payload mode)
+    pub string_table: Vec<(String, EncryptedData)>,
+    pub obfuscated: ObfuscatedScript,
+    pub stats: ObfuscationStats,
+    pub parse_result: ParseResult,
+    /// Environment accesses that kept names readable (worth a warning)
+    pub dynamic_globals: Vec<DynamicGlobal>,
+    /// Lint warnings on the output (errors fail the run instead)
+    pub lint: Vec<LintFinding>,
+    /// Everything above worth reporting, plus skipped transforms, blocks left
+    /// unflattened, unsupported statements and the script budget
+    pub diagnostics: Vec<Diagnostic>,
+    /// Rewritten source the script was generated from
+    pub rewritten: ObfuscationResult,
+    /// Code generation settings the script was generated with
+    pub codegen_config: CodeGenConfig,
+}
+
+/// Parse → analyze → obfuscate → generate
+pub struct ObfuscationPipeline {
+    parser: LuauParser,
+    crypto: CryptoContext,
+    progress: SharedProgress,
+}
+
+impl ObfuscationPipeline {
+    pub fn new(crypto: CryptoContext) -> Self {
+        Self {
+            parser: LuauParser::new(),
+            crypto,
+            progress: no_progress(),
+        }
+    }
+
+    /// Use `parser` (e.g. with custom sensitivity rules)
+    pub fn with_parser(mut self, parser: LuauParser) -> Self {
+        self.parser = parser.with_progress(self.progress.clone());
+        self
+    }
+
+    /// Report stages, passes and warnings of every run to `progress`
+    pub fn with_progress(mut self, progress: SharedProgress) -> Self {
+        self.parser = self.parser.with_progress(progress.clone());
+        self.progress = progress;
+        self
+    }
+
+    /// Protect `source` and return the finished script
+    pub fn run(
+        &self,
+        source: &str,
+        settings: &PipelineSettings,
+        codegen_config: CodeGenConfig,
+    ) -> Result<String> {
+        Ok(self.protect(source, settings, codegen_config)?.script)
+    }
+
+    /// Protect `source`, keeping the intermediate results
+    pub fn protect(
+        &self,
+        source: &str,
+        settings: &PipelineSettings,
+        codegen_config: CodeGenConfig,
+    ) -> Result<ProtectedScript> {
+        let mut memory = MemoryStats::default();
+        let parse_result = self.stage(&mut memory, Stage::Parse, || {
+            if settings.streaming {
+                self.parser.parse_streaming(source)
+            } else {
+                self.parser.parse(source)
+            }
+        })?;
+        self.protect_measured(source, parse_result, settings, codegen_config, memory)
+    }
+
+    /// Protect `source` that the caller already parsed
+    ///
+    /// The settings' decryption mode, tier name and compression
+    /// override `codegen_config`'s, and environment-derived constants are
+    /// bound to the license key.
+    pub fn protect_parsed(
+        &self,
+        source: &str,
+        parse_result: ParseResult,
+        settings: &PipelineSettings,
+        codegen_config: CodeGenConfig,
+    ) -> Result<ProtectedScript> {
+        let memory = MemoryStats::default();
+        self.protect_measured(source, parse_result, settings, codegen_config, memory)
+    }
+
+    /// Run one stage, reporting it to the progress sink and recording its
+    /// allocations in `memory`
+    fn stage<T>(
+        &self,
+        memory: &mut MemoryStats,
+        stage: Stage,
+        work: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        progress::stage(&self.progress, stage, || memory.record(stage, work))
+    }
+
+    /// `protect_parsed`, adding to the allocations recorded so far
+    fn protect_measured(
+        &self,
+        source: &str,
+        parse_result: ParseResult,
+        settings: &PipelineSettings,
+        mut codegen_config: CodeGenConfig,
+        mut memory: MemoryStats,
+    ) -> Result<ProtectedScript> {
+        let original = source;
+        let simplified = if settings.simplify {
+            Simplifier::new().simplify(source)?
+        } else {
+            Simplified::default()
+        };
+        let (source, parse_result) = if settings.simplify && simplified.code != source {
+            // Lines are kept, so the re-parse maps back onto the input
+            let code = simplified.code.as_str();
+            let reparsed = self.stage(&mut memory, Stage::Parse, || {
+                if parse_result.ast.is_some() {
+                    self.parser.parse(code)
+                } else {
+                    self.parser.parse_streaming(code)
+                }
+            })?;
+            (code, reparsed)
+        } else {
+            (source, parse_result)
+        };
+
+        let analysis = self.stage(&mut memory, Stage::Analyze, || {
+            Analyzer::with_api_database(settings.analysis.clone(), settings.api_db.clone())
+                .with_progress(self.progress.clone())
+                .analyze(&parse_result)
+        })?;
+        let mut diagnostics: Vec<Diagnostic> = parse_result
+            .unsupported_statements
+            .iter()
+            .map(|&line| {
+                Diagnostic::warning(
+                    "unsupported-syntax",
+                    "statement not inspected, so its literals are left as written",
+                )
+                .at(Span::line(line))
+            })
+            .collect();
+        diagnostics.extend(analysis.dynamic_globals.iter().map(DynamicGlobal::diagnostic));
+
+        let crypto = self.crypto.clone();
+        let mut obfuscator = Obfuscator::new(settings.tier, crypto);
+        obfuscator.set_rng(RngMode::from_seed(settings.seed));
+        if !codegen_config.license_key.is_empty() {
+            obfuscator.bind_environment(codegen_config.license_key.clone());
+        }
+        if let Some(tuned) = &settings.obfuscation {
+            obfuscator.set_settings(tuned.clone());
+        }
+        obfuscator.set_hot_functions(settings.hot_functions.clone());
+        obfuscator.set_naming_style(settings.naming_style);
+        obfuscator.set_compression(settings.compression);
+        if let Some(mode) = settings.table_keys {
+            obfuscator.set_table_keys(mode);
+        }
+        obfuscator.set_exclusions(settings.exclusions.clone());
+        obfuscator.set_plaintext_rules(settings.plaintext_rules.clone());
+        obfuscator.set_best_effort(settings.best_effort);
+        obfuscator.set_progress(self.progress.clone());
+        if let Some(share) = settings.max_overhead {
+            obfuscator.set_overhead_budget(OverheadBudget::new(share, source.len()));
+        }
+        let obfuscated = self.stage(&mut memory, Stage::Obfuscate, || {
+            obfuscator.obfuscate(&parse_result, &analysis)
+        })?;
+        diagnostics.extend(obfuscated.failed_transforms.iter().map(TransformFailure::diagnostic));
+        diagnostics.extend(obfuscated.unflattened_blocks.iter().map(SkippedBlock::diagnostic));
+        let mut stats = obfuscator.get_stats(&obfuscated);
+        stats.inlined_locals = simplified.inlined;
+        stats.removed_locals = simplified.removed;
+
+        let result = self.stage(&mut memory, Stage::Rewrite, || {
+            SourceRewriter::new(&obfuscated).rewrite(source, &parse_result)
+        })?;
+        let string_table = obfuscated.string_table();
+
+        codegen_config.string_decryption = settings.obfuscation_settings().decryption_mode;
+        codegen_config.tier = settings.tier.name().to_string();
+        codegen_config.compression = settings.compression;
+        codegen_config.honeypot_traps = !obfuscated.traps.is_empty();
+        codegen_config.config_block = obfuscated.config_block.clone();
+        codegen_config.string_sensitivity = obfuscated.string_sensitivity();
+        let (script, payload) = self.stage(&mut memory, Stage::Generate, || {
+            let generator = CodeGenerator::new(codegen_config.clone(), self.crypto.clone())?;
+            if settings.split {
+                let split = generator.generate_split(&result, &string_table)?;
+                stats.compression_saved += split.payload_saved as i64;
+                Ok::<_, anyhow::Error>((split.loader, Some(split.payload)))
+            } else {
+                Ok((generator.generate(&result, &string_table)?, None))
+            }
+        })?;
+        let output = match &payload {
+            Some(payload) => format!("{}{}", script, payload),
+            None => script.clone(),
+        };
+        let lint = if settings.lint {
+            self.stage(&mut memory, Stage::Lint, || self.lint(&script, payload.as_deref()))?
+        } else {
+            Vec::new()
+        };
+        diagnostics.extend(lint.iter().map(LintFinding::diagnostic));
+        stats.size = SizeStats::measure(source, &parse_result, &obfuscated, &output)?;
+        if settings.simplify {
+            stats.size.input_bytes = original.len();
+            stats.size.deltas.insert(
+                0,
+                TransformDelta {
+                    transform: "simplify",
+                    bytes: source.len() as i64 - original.len() as i64,
+                },
+            );
+        }
+        if stats.size.output_bytes > SCRIPT_SOURCE_BUDGET {
+            diagnostics.push(
+                Diagnostic::warning(
+                    "script-budget",
+                    format!(
+                        "output is {} bytes, over the {} byte script budget",
+                        stats.size.output_bytes, SCRIPT_SOURCE_BUDGET
+                    ),
+                )
+                .with_suggestion("use a lower tier or split the output into a loader and payload"),
+            );
+        }
+        for diagnostic in &diagnostics {
+            self.progress.diagnostic(diagnostic);
+        }
+        if let Some(decompressor) =
+            RuntimeGenerator::new().generate_decompressor(settings.compression)?
+        {
+            stats.compression_saved -= decompressor.len() as i64;
+        }
+        stats.memory = memory;
+
+        Ok(ProtectedScript {
+            script,
+            payload,
+            string_table,
+            obfuscated,
+            stats,
+            parse_result,
+            dynamic_globals: analysis.dynamic_globals,
+            lint,
+            diagnostics,
+            rewritten: result,
+            codegen_config,
+        })
+    }
+
+    /// The protected script rebuilt for differential execution
+    ///
+    /// Same rewritten code, strings and decryption runtime, but without the
+    /// parts that only react to the live Roblox environment: the license and
+    /// binding checks, the anti-hook guard, executor and run context checks,
+    /// remote payloads, key delivery and the update channel. `_key` is bound
+    /// to the master key, which the live script would get from key delivery.
+    /// Always one script, even for a split build.
+    pub fn verification_script(&self, protected: &ProtectedScript) -> Result<String> {
+        let config = CodeGenConfig {
+            include_license: false,
+            include_hwid: false,
+            anti_hook: false,
+            remote_payload: None,
+            key_delivery: None,
+            update_channel: None,
+            violation_webhook: None,
+            executor_check: None,
+            run_context: RunContext::Shared,
+            ..protected.codegen_config.clone()
+        };
+        CodeGenerator::new(config, self.crypto.clone())?
+            .with_embedded_key()
+            .generate(&protected.rewritten, &protected.string_table)
+    }
+
+    /// Lint the script and payload, failing on the first error
+    fn lint(&self, script: &str, payload: Option<&str>) -> Result<Vec<LintFinding>> {
+        let linter = RobloxLint::new();
+        let mut findings = linter.check(script)?;
+        if let Some(payload) = payload {
+            findings.extend(linter.check(payload)?);
+        }
+        if let Some(error) = findings.iter().find(|f| f.level == LintLevel::Error) {
+            return Err(ObfuscatorError::CodeGenError(format!(
+                "Output would not load in Roblox: {}",
+                error
+            ))
+            .into());
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::MetadataSigner;
+    use crate::diagnostics::{DenyRules, Severity};
+
+    fn config() -> CodeGenConfig {
+        CodeGenConfig {
+            license_key: "TEST-1234".to_string(),
+            script_id: "test_script".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_produces_protected_script() {
+        let source = "local message = \"Hello, Roblox!\"\nprint(message, 42)\n";
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let settings = PipelineSettings {
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        let protected = ObfuscationPipeline::new(crypto)
+            .protect(source, &settings, config())
+            .unwrap();
+
+        let code = protected
+            .script
+            .split("-- [CODE] Protected Script")
+            .nth(1)
+            .unwrap();
+        assert!(code.contains("_decrypt(1)"));
+        assert!(!code.contains("message"));
+        assert!(!protected.script.contains("Hello, Roblox!"));
+        assert_eq!(protected.string_table.len(), 1);
+        assert_eq!(protected.stats.encrypted_strings, 1);
+        assert!(protected.payload.is_none());
+
+        let size = &protected.stats.size;
+        assert_eq!(size.input_bytes, source.len());
+        assert_eq!(size.output_bytes, protected.script.len());
+        let growth: i64 = size.deltas.iter().map(|d| d.bytes).sum();
+        assert_eq!(growth, size.output_bytes as i64 - size.input_bytes as i64);
+    }
+
+    #[test]
+    fn test_verification_script_binds_key() {
+        use base64::Engine;
+
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let pipeline = ObfuscationPipeline::new(crypto.clone());
+        let settings = PipelineSettings {
+            seed: Some(1),
+            ..Default::default()
+        };
+        let protected = pipeline.protect("print(\"hi\")\n", &settings, config()).unwrap();
+        let script = pipeline.verification_script(&protected).unwrap();
+
+        let key = base64::engine::general_purpose::STANDARD.encode(crypto.masked_key(&[]));
+        let binding = format!("local _key = \"{}\"", key);
+        let bound = script.find(&binding).unwrap();
+        assert!(bound < script.find("-- [CODE] Protected Script").unwrap());
+        assert!(!protected.script.contains(&binding));
+    }
+
+    #[test]
+    fn test_refreshed_api_database_names_kept() {
+        let cache = tempfile::tempdir().unwrap();
+        let mut refreshed = ApiDatabase::bundled();
+        refreshed.version = "version-refreshed".to_string();
+        refreshed.globals.push("FreshGlobal".to_string());
+        refreshed.save(&ApiDatabase::default_path(cache.path())).unwrap();
+
+        let source = "local FreshGlobal = 5\nprint(FreshGlobal + 1)\n";
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let code = |api_db: ApiDatabase| {
+            let settings = PipelineSettings {
+                seed: Some(1),
+                api_db,
+                ..Default::default()
+            };
+            let script = ObfuscationPipeline::new(crypto.clone())
+                .run(source, &settings, config())
+                .unwrap();
+            script.split("-- [CODE] Protected Script").nth(1).unwrap().to_string()
+        };
+
+        assert!(!code(ApiDatabase::bundled()).contains("FreshGlobal"));
+        assert!(code(ApiDatabase::load(Some(cache.path()), None)).contains("FreshGlobal"));
+        // A pin on another version falls back to the bundled snapshot
+        let pinned = ApiDatabase::load(Some(cache.path()), Some("version-other"));
+        assert!(!code(pinned).contains("FreshGlobal"));
+    }
+
+    #[test]
+    fn test_lint_warns_and_rejects_output() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let pipeline = ObfuscationPipeline::new(crypto);
+        let settings = PipelineSettings {
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        let protected = pipeline
+            .protect("local f = loadstring(\"return 1\")\n", &settings, config())
+            .unwrap();
+        assert_eq!(protected.lint.len(), 1);
+        assert_eq!(protected.lint[0].rule, "loadstring");
+        let diagnostic = &protected.diagnostics[0];
+        assert_eq!((diagnostic.id, diagnostic.severity), ("loadstring", Severity::Warning));
+        assert!(DenyRules::parse(&["loadstring"])
+            .unwrap()
+            .check(&protected.diagnostics)
+            .is_err());
+
+        let crowded: String = (0..210).map(|i| format!("local v{} = {}\n", i, i)).collect();
+        let error = pipeline.protect(&crowded, &settings, config()).err().unwrap();
+        assert!(error.to_string().contains("locals alive at once"));
+        let unchecked = PipelineSettings {
+            lint: false,
+            ..settings
+        };
+        assert!(pipeline.protect(&crowded, &unchecked, config()).is_ok());
+
+        // Unread locals are gone before they can crowd the limit
+        let simplified = PipelineSettings {
+            simplify: true,
+            ..unchecked
+        };
+        let protected = pipeline.protect(&crowded, &simplified, config()).unwrap();
+        assert_eq!(protected.stats.removed_locals, 210);
+        assert_eq!(protected.stats.size.input_bytes, crowded.len());
+        assert_eq!(protected.stats.size.deltas[0].transform, "simplify");
+    }
+
+    #[test]
+    fn test_run_signs_metadata_header() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let settings = PipelineSettings {
+            tier: ObfuscationTier::Premium,
+            ..Default::default()
+        };
+
+        let codegen_config = CodeGenConfig {
+            licensed_tier: Some("premium".to_string()),
+            ..config()
+        };
+        let script = ObfuscationPipeline::new(crypto.clone())
+            .run("print(\"hi\")\n", &settings, codegen_config)
+            .unwrap();
+
+        let metadata = MetadataSigner::new(&crypto).verify(&script).unwrap();
+        assert_eq!(metadata.script_id, "test_script");
+        assert_eq!(metadata.tier, "premium");
+        assert_eq!(metadata.licensed_tier.as_deref(), Some("premium"));
+        assert_eq!(metadata.key_version, 0);
+    }
+
+    #[test]
+    fn test_split_run_returns_payload() {
+        let source = "print(\"secret value\")\n";
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let settings = PipelineSettings {
+            split: true,
+            ..Default::default()
+        };
+
+        let protected = ObfuscationPipeline::new(crypto)
+            .protect(source, &settings, config())
+            .unwrap();
+
+        let payload = protected.payload.unwrap();
+        assert!(payload.contains("return {mask = "));
+        assert!(!payload.contains("secret value"));
+        assert!(!protected.script.contains("secret value"));
+    }
+
+    #[test]
+    fn test_compressed_run_includes_decompressor() {
+        let message = "player joined the lobby and picked a team; ".repeat(30);
+        let source = format!("print(\"{}\")\nprint(\"hi\")\n", message);
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let settings = PipelineSettings {
+            compression: Compression::Zlib,
+            ..Default::default()
+        };
+
+        let protected = ObfuscationPipeline::new(crypto)
+            .protect(&source, &settings, config())
+            .unwrap();
+
+        assert!(protected.script.contains("local _decompress"));
+        assert!(protected.script.contains("z = \"zlib\""));
+        // Only the long string shrinks
+        assert_eq!(protected.stats.compressed_strings, 1);
+        assert!(!protected.script.contains("picked a team"));
+    }
+
+    #[test]
+    fn test_run_scrubs_comments_and_adds_license_header() {
+        let source = "-- by someone, webhook https://discord.com/api/webhooks/1\r\nprint(1) -- TODO\r\n";
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let codegen_config = CodeGenConfig {
+            license_header: Some("Licensed copy of {{SCRIPT_ID}}".to_string()),
+            ..config()
+        };
+
+        let script = ObfuscationPipeline::new(crypto.clone())
+            .run(source, &PipelineSettings::default(), codegen_config)
+            .unwrap();
+
+        assert!(script.contains("-- Licensed copy of test_script\n"));
+        assert!(!script.contains("webhooks"));
+        assert!(!script.contains("TODO"));
+        // The signed header still verifies with the extra lines
+        assert!(MetadataSigner::new(&crypto).verify(&script).is_ok());
+    }
+
+    #[test]
+    fn test_run_keeps_directives_first() {
+        let source = "--!strict\n-- helpers\n--!optimize 1\nlocal x: number = 1\nprint(x)\n";
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let codegen_config = CodeGenConfig {
+            force_optimize: true,
+            ..config()
+        };
+
+        let script = ObfuscationPipeline::new(crypto)
+            .run(source, &PipelineSettings::default(), codegen_config)
+            .unwrap();
+
+        assert!(script.starts_with("--!strict\n--!optimize 2\n-- Protected by Luau Obfuscator\n"));
+        assert!(!script.contains("--!optimize 1"));
+        assert!(full_moon::parse(&script).is_ok());
+    }
+
+    #[test]
+    fn test_progress_reports_stages_and_passes() {
+        use crate::progress::ProgressSink;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl ProgressSink for Recorder {
+            fn stage_started(&self, stage: Stage) {
+                self.0.lock().unwrap().push(format!("start {}", stage));
+            }
+            fn stage_finished(&self, stage: Stage) {
+                self.0.lock().unwrap().push(format!("end {}", stage));
+            }
+            fn items_processed(&self, stage: Stage, done: usize, total: usize) {
+                self.0.lock().unwrap().push(format!("{} {}/{}", stage, done, total));
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let settings = PipelineSettings {
+            tier: ObfuscationTier::Basic,
+            seed: Some(1),
+            ..Default::default()
+        };
+        ObfuscationPipeline::new(crypto)
+            .with_progress(recorder.clone())
+            .protect("local a = \"x\"\nprint(a)\n", &settings, config())
+            .unwrap();
+
+        let events = recorder.0.lock().unwrap();
+        let expected: Vec<String> = Stage::ALL.iter().map(|s| format!("start {}", s)).collect();
+        let stages: Vec<String> =
+            events.iter().filter(|e| e.starts_with("start ")).cloned().collect();
+        assert_eq!(stages, expected);
+        assert!(events.contains(&"Analyzing 5/5".to_string()));
+        // Basic encrypts strings and renames locals
+        assert!(events.contains(&"Obfuscating 2/2".to_string()));
+        assert_eq!(events.last().unwrap(), "end Linting");
+    }
+}
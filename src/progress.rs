@@ -0,0 +1,100 @@
+//! Progress reporting for library consumers
+//!
+//! A [`ProgressSink`] hears when each pipeline stage starts and finishes,
+//! how far a stage with countable work has got (streaming parse chunks,
+//! analysis passes, obfuscation passes) and the diagnostics a run produces.
+//! GUIs and servers implement it to show real progress; the CLI drives its
+//! progress bar with one. Every method has an empty default, so a sink only
+//! implements what it shows.
+
+use crate::diagnostics::{Diagnostic, Severity};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Stage of a protection run, in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Parse,
+    Analyze,
+    Obfuscate,
+    /// Applying the obfuscation run to the source text
+    Rewrite,
+    Generate,
+    /// Checking the output against Roblox's limits and APIs
+    Lint,
+}
+
+impl Stage {
+    /// Every stage, in the order a run goes through them
+    pub const ALL: [Stage; 6] = [
+        Stage::Parse,
+        Stage::Analyze,
+        Stage::Obfuscate,
+        Stage::Rewrite,
+        Stage::Generate,
+        Stage::Lint,
+    ];
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Stage::Parse => "Parsing",
+            Stage::Analyze => "Analyzing",
+            Stage::Obfuscate => "Obfuscating",
+            Stage::Rewrite => "Rewriting",
+            Stage::Generate => "Generating",
+            Stage::Lint => "Linting",
+        })
+    }
+}
+
+/// Receives progress events; called from the thread running the stage
+pub trait ProgressSink: Send + Sync {
+    fn stage_started(&self, _stage: Stage) {}
+
+    fn stage_finished(&self, _stage: Stage) {}
+
+    /// `done` of `total` items of `stage` are finished
+    fn items_processed(&self, _stage: Stage, _done: usize, _total: usize) {}
+
+    fn warning(&self, _message: &str) {}
+
+    /// A finding of the run; warnings and errors are also sent to `warning`
+    fn diagnostic(&self, diagnostic: &Diagnostic) {
+        if diagnostic.severity >= Severity::Warning {
+            self.warning(&diagnostic.to_string());
+        }
+    }
+}
+
+/// Sink that ignores everything (the default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {}
+
+/// A sink shared by the stages of one run
+pub type SharedProgress = Arc<dyn ProgressSink>;
+
+/// The default sink
+pub fn no_progress() -> SharedProgress {
+    Arc::new(NoProgress)
+}
+
+/// Runs `work` between `stage_started` and `stage_finished`, inside a
+/// `stage` tracing span
+///
+/// `stage_finished` is only sent when `work` succeeds.
+pub(crate) fn stage<T, E>(
+    progress: &SharedProgress,
+    stage: Stage,
+    work: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    let _span = tracing::debug_span!("stage", %stage).entered();
+    progress.stage_started(stage);
+    let result = work()?;
+    progress.stage_finished(stage);
+    Ok(result)
+}
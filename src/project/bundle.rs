@@ -0,0 +1,335 @@
+//! Bundling a project into one script
+//!
+//! Starting from an entry script, every ModuleScript it requires (directly
+//! or through other modules) is wrapped in a function and registered under
+//! its instance path in a small module registry at the top of the bundle.
+//! Resolved `require(...)` calls become registry lookups, which run each
+//! module once and cache what it returns, like `require` does. The entry
+//! runs last, so the bundle can be protected as a single script.
+//!
+//! Type annotations are stripped (`export type` is only allowed at the top
+//! of a real module), and only the entry's hot comments are kept.
+
+use super::graph::{find_requires, RequireGraph, UnresolvedRequire};
+use super::rojo::{ProjectFile, RojoProject, ScriptKind};
+use super::significant_tokens;
+use crate::codegen::take_directives;
+use crate::parser::{strip_types, LuauParser};
+use crate::utils::errors::ObfuscatorError;
+use crate::utils::source::{InputOptions, LineEnding, SourceFile};
+use anyhow::{Context, Result};
+use full_moon::tokenizer::TokenType;
+use std::collections::{BTreeSet, HashMap};
+
+/// Registry table, cache and lookup every bundle starts with
+const REGISTRY: &str = "local __bundle_modules = {}
+local __bundle_cache = {}
+local function __bundle_require(name)
+\tlocal cached = __bundle_cache[name]
+\tif cached == nil then
+\t\tcached = { value = __bundle_modules[name]() }
+\t\t__bundle_cache[name] = cached
+\tend
+\treturn cached.value
+end
+";
+
+/// A module's use of `script` for something other than a bundled require
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptReference {
+    /// Index of the module in `RojoProject::files`
+    pub file: usize,
+    pub line: usize,
+}
+
+/// A project bundled into one script
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub code: String,
+    /// Line ending the entry script used
+    pub line_ending: LineEnding,
+    /// Index of the entry in `RojoProject::files`
+    pub entry: usize,
+    /// Modules inlined, in the order they are registered
+    pub modules: Vec<usize>,
+    /// Requires in the bundled scripts that couldn't be resolved; they are
+    /// left as written and run against the bundle's own `script`
+    pub unresolved: Vec<UnresolvedRequire>,
+    /// Inlined modules that use `script`, which now means the bundle's script
+    pub script_references: Vec<ScriptReference>,
+}
+
+/// Inlines required ModuleScripts into one script
+pub struct Bundler {
+    input: InputOptions,
+}
+
+impl Bundler {
+    pub fn new() -> Self {
+        Self {
+            input: InputOptions::default(),
+        }
+    }
+
+    /// Decode scripts as `options` say
+    pub fn with_input_options(mut self, options: InputOptions) -> Self {
+        self.input = options;
+        self
+    }
+
+    /// Bundle `project` starting from `entry`
+    ///
+    /// `entry` is an instance path (`ServerScriptService.Main`) or a path
+    /// relative to the project root; without one, the project's only Script
+    /// or LocalScript is used.
+    pub fn bundle(&self, project: &RojoProject, entry: Option<&str>) -> Result<Bundle> {
+        let files = project
+            .files
+            .iter()
+            .map(|file| {
+                let path = project.source_path(file);
+                let source = SourceFile::read(&path, &self.input)
+                    .with_context(|| format!("Failed to read project script: {:?}", path))?;
+                // Checked one by one, so errors name the file and its own lines
+                LuauParser::new()
+                    .parse(&source.text)
+                    .map_err(|e| with_origin(e, &path.display().to_string()))?;
+                Ok(SourceFile {
+                    text: strip_types(&source.text)?,
+                    ..source
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let sources: Vec<String> = files.iter().map(|file| file.text.clone()).collect();
+
+        let graph = RequireGraph::build(project, &sources)?;
+        let entry = find_entry(project, entry)?;
+
+        // Everything the entry reaches, dependencies first
+        let mut reachable = BTreeSet::from([entry]);
+        let mut pending = vec![entry];
+        while let Some(file) = pending.pop() {
+            for dependency in graph.dependencies(file) {
+                if reachable.insert(dependency) {
+                    pending.push(dependency);
+                }
+            }
+        }
+        let modules: Vec<usize> = graph
+            .topological_order()?
+            .into_iter()
+            .filter(|&i| i != entry && reachable.contains(&i))
+            .collect();
+        let keys: HashMap<&[String], String> = modules
+            .iter()
+            .map(|&i| {
+                let file = &project.files[i];
+                (file.instance_path.as_slice(), file.display_path())
+            })
+            .collect();
+
+        let (directives, _) = take_directives(&sources[entry]);
+        let mut code = directives.render();
+        code.push_str(REGISTRY);
+        let mut script_references = Vec::new();
+        for &i in &modules {
+            let file = &project.files[i];
+            let body = link(&sources[i], file, &keys)?;
+            script_references.extend(
+                script_lines(&body)?
+                    .into_iter()
+                    .map(|line| ScriptReference { file: i, line }),
+            );
+            code.push_str(&format!(
+                "\n__bundle_modules[{:?}] = function(...)\n{}\nend\n",
+                keys[file.instance_path.as_slice()],
+                take_directives(&body).1.trim_end()
+            ));
+        }
+        let body = link(&sources[entry], &project.files[entry], &keys)?;
+        code.push_str(&format!(
+            "\nreturn (function(...)\n{}\nend)(...)\n",
+            take_directives(&body).1.trim_end()
+        ));
+
+        Ok(Bundle {
+            code,
+            line_ending: files[entry].line_ending,
+            entry,
+            unresolved: graph
+                .unresolved
+                .iter()
+                .filter(|require| reachable.contains(&require.file))
+                .cloned()
+                .collect(),
+            modules,
+            script_references,
+        })
+    }
+}
+
+impl Default for Bundler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The entry named by `entry`, or the project's only runnable script
+fn find_entry(project: &RojoProject, entry: Option<&str>) -> Result<usize> {
+    let found = match entry {
+        Some(name) => {
+            let relative = std::path::Path::new(name);
+            project
+                .files
+                .iter()
+                .position(|f| f.display_path() == name || f.relative_path == relative)
+        }
+        None => {
+            let mut scripts = project
+                .files
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| f.kind != ScriptKind::Module)
+                .map(|(i, _)| i);
+            match (scripts.next(), scripts.next()) {
+                (Some(only), None) => Some(only),
+                _ => None,
+            }
+        }
+    };
+    found.ok_or_else(|| {
+        let scripts: Vec<String> = project
+            .files
+            .iter()
+            .filter(|f| entry.is_some() || f.kind != ScriptKind::Module)
+            .map(ProjectFile::display_path)
+            .collect();
+        let problem = match entry {
+            Some(name) => format!("No script {:?} in the project", name),
+            None => "Pick the script to bundle from with --entry".to_string(),
+        };
+        ObfuscatorError::ConfigError(format!("{}; scripts: {}", problem, scripts.join(", "))).into()
+    })
+}
+
+/// `source` with requires of bundled modules turned into registry lookups
+fn link(source: &str, file: &ProjectFile, keys: &HashMap<&[String], String>) -> Result<String> {
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for require in find_requires(source, &file.instance_path)? {
+        let (Some(target), Some((start, end))) = (&require.target, require.span) else {
+            continue;
+        };
+        let Some(key) = keys.get(target.as_slice()) else {
+            continue;
+        };
+        output.push_str(&source[cursor..start]);
+        output.push_str(&format!("__bundle_require({:?})", key));
+        cursor = end;
+    }
+    output.push_str(&source[cursor..]);
+    Ok(output)
+}
+
+/// Lines where `source` names `script`
+fn script_lines(source: &str) -> Result<Vec<usize>> {
+    let mut lines: Vec<usize> = significant_tokens(source)?
+        .iter()
+        .filter(|token| {
+            matches!(token.token_type(), TokenType::Identifier { identifier }
+                if identifier.as_str() == "script")
+        })
+        .map(|token| token.start_position().line())
+        .collect();
+    lines.dedup();
+    Ok(lines)
+}
+
+/// Attach `origin` to a parse diagnostic
+fn with_origin(error: anyhow::Error, origin: &str) -> anyhow::Error {
+    match error.downcast::<ObfuscatorError>() {
+        Ok(ObfuscatorError::SyntaxError(diagnostic)) => {
+            ObfuscatorError::SyntaxError(Box::new(diagnostic.with_origin(origin))).into()
+        }
+        Ok(other) => other.into(),
+        Err(error) => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::DEFAULT_PROJECT_FILE;
+    use std::fs;
+
+    #[test]
+    fn test_bundle_inlines_required_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/shared")).unwrap();
+        fs::create_dir_all(root.join("src/server")).unwrap();
+        fs::write(
+            root.join("src/shared/Util.lua"),
+            "local Format = require(script.Parent.Format)\n\
+             local Util = {}\n\
+             function Util.describe(x: number): string\n    return Format.wrap(x * 2)\nend\n\
+             return Util\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/shared/Format.lua"),
+            "export type Wrapped = string\nreturn { wrap = function(x) return \"<\" .. x .. \">\" end }\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/shared/Unused.lua"), "return 1\n").unwrap();
+        fs::write(
+            root.join("src/server/Main.server.lua"),
+            "--!strict\nlocal RS = game:GetService(\"ReplicatedStorage\")\n\
+             local Util = require(RS.Shared.Util)\n\
+             print(Util.describe(21))\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join(DEFAULT_PROJECT_FILE),
+            r#"{"name": "Game", "tree": {"$className": "DataModel",
+                "ReplicatedStorage": {"Shared": {"$path": "src/shared"}},
+                "ServerScriptService": {"Server": {"$path": "src/server"}}}}"#,
+        )
+        .unwrap();
+
+        let project = RojoProject::load(root).unwrap();
+        let bundle = Bundler::new().bundle(&project, None).unwrap();
+        let names: Vec<String> = bundle
+            .modules
+            .iter()
+            .map(|&i| project.files[i].display_path())
+            .collect();
+
+        // Dependencies first; modules nothing requires stay out
+        assert_eq!(
+            names,
+            [
+                "ReplicatedStorage.Shared.Format",
+                "ReplicatedStorage.Shared.Util"
+            ]
+        );
+        assert_eq!(
+            project.files[bundle.entry].display_path(),
+            "ServerScriptService.Server.Main"
+        );
+        assert!(bundle.code.starts_with("--!strict\n"));
+        assert!(!bundle.code.contains(" require("));
+        assert!(bundle
+            .code
+            .contains("__bundle_require(\"ReplicatedStorage.Shared.Util\")"));
+        assert!(!bundle.code.contains("export type"));
+        assert!(bundle.unresolved.is_empty());
+        assert!(bundle.script_references.is_empty());
+        assert!(full_moon::parse(&bundle.code).is_ok());
+
+        let error = Bundler::new().bundle(&project, Some("Nope")).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("ReplicatedStorage.Shared.Unused"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Module export discovery and cross-module field renaming
+
+use super::{significant_tokens, TokenCursor};
+use anyhow::Result;
+use full_moon::tokenizer::{Symbol, TokenType};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Fields a ModuleScript exposes on the table it returns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleExports {
+    /// Local holding the returned table (`return Util`)
+    pub table: Option<String>,
+    /// Field names defined on that table
+    pub fields: BTreeSet<String>,
+}
+
+/// Member names that are accessed somewhere other than through a module binding
+///
+/// Renaming such a name would break e.g. `obj:method()` on an instance
+/// created by the module, so these are excluded from the shared mapping.
+#[derive(Debug, Clone, Default)]
+pub struct MemberUsage {
+    pub unsafe_names: HashSet<String>,
+}
+
+/// Find the returned table and the fields defined on it
+pub fn module_exports(source: &str) -> Result<ModuleExports> {
+    let tokens = significant_tokens(source)?;
+    let cursor = TokenCursor::new(&tokens);
+
+    // Module pattern: the file ends with `return Name`
+    let n = tokens.len();
+    let table = match (n >= 2).then(|| (cursor.symbol(n - 2), cursor.identifier(n - 1))) {
+        Some((Some(Symbol::Return), Some(name))) => name.to_string(),
+        _ => return Ok(ModuleExports::default()),
+    };
+
+    let mut fields = BTreeSet::new();
+    for i in 0..n {
+        if cursor.identifier(i) != Some(table.as_str()) || is_member(&cursor, i) {
+            continue;
+        }
+
+        // function Table.field / function Table:method / Table.field =
+        if matches!(cursor.symbol(i + 1), Some(Symbol::Dot) | Some(Symbol::Colon)) {
+            if let Some(field) = cursor.identifier(i + 2) {
+                let defined = (i > 0 && cursor.symbol(i - 1) == Some(Symbol::Function))
+                    || cursor.symbol(i + 3) == Some(Symbol::Equal);
+                if defined {
+                    fields.insert(field.to_string());
+                }
+            }
+        }
+
+        // local Table = { field = ... }
+        if i > 0
+            && cursor.symbol(i - 1) == Some(Symbol::Local)
+            && cursor.symbol(i + 1) == Some(Symbol::Equal)
+            && cursor.symbol(i + 2) == Some(Symbol::LeftBrace)
+        {
+            for key in constructor_keys(&cursor, i + 2) {
+                fields.insert(cursor.identifier(key).unwrap_or_default().to_string());
+            }
+        }
+    }
+
+    Ok(ModuleExports {
+        table: Some(table),
+        fields,
+    })
+}
+
+/// Collect member names used through anything other than `bindings`
+pub fn member_usage(source: &str, bindings: &HashSet<String>, usage: &mut MemberUsage) -> Result<()> {
+    let tokens = significant_tokens(source)?;
+    let cursor = TokenCursor::new(&tokens);
+
+    for i in 0..tokens.len() {
+        match tokens[i].token_type() {
+            // Dynamic indexing by string may refer to any field
+            TokenType::StringLiteral { literal, .. } => {
+                usage.unsafe_names.insert(literal.to_string());
+            }
+            TokenType::Symbol {
+                symbol: Symbol::Dot | Symbol::Colon,
+            } => {
+                let Some(field) = cursor.identifier(i + 1) else {
+                    continue;
+                };
+                let through_binding = i > 0
+                    && cursor
+                        .identifier(i - 1)
+                        .map(|name| bindings.contains(name) && !is_member(&cursor, i - 1))
+                        .unwrap_or(false);
+                if !through_binding {
+                    usage.unsafe_names.insert(field.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Rename `binding.field` / `binding:field` (and the module's own table
+/// constructor keys) according to `mapping`
+pub fn rename_exports(
+    source: &str,
+    bindings: &HashSet<String>,
+    own_table: Option<&str>,
+    mapping: &HashMap<String, String>,
+) -> Result<String> {
+    let tokens = significant_tokens(source)?;
+    let cursor = TokenCursor::new(&tokens);
+    let mut renames: Vec<usize> = Vec::new();
+
+    for i in 0..tokens.len() {
+        let Some(name) = cursor.identifier(i) else {
+            continue;
+        };
+        if is_member(&cursor, i) {
+            continue;
+        }
+
+        if bindings.contains(name)
+            && matches!(cursor.symbol(i + 1), Some(Symbol::Dot) | Some(Symbol::Colon))
+            && cursor
+                .identifier(i + 2)
+                .map(|field| mapping.contains_key(field))
+                .unwrap_or(false)
+        {
+            renames.push(i + 2);
+        }
+
+        if Some(name) == own_table
+            && i > 0
+            && cursor.symbol(i - 1) == Some(Symbol::Local)
+            && cursor.symbol(i + 1) == Some(Symbol::Equal)
+            && cursor.symbol(i + 2) == Some(Symbol::LeftBrace)
+        {
+            renames.extend(
+                constructor_keys(&cursor, i + 2)
+                    .into_iter()
+                    .filter(|&k| mapping.contains_key(cursor.identifier(k).unwrap_or_default())),
+            );
+        }
+    }
+
+    renames.sort_unstable();
+    renames.dedup();
+
+    let mut output = String::with_capacity(source.len());
+    let mut last = 0;
+    for index in renames {
+        let token = &tokens[index];
+        let start = token.start_position().bytes();
+        output.push_str(&source[last..start]);
+        output.push_str(&mapping[&token.to_string()]);
+        last = token.end_position().bytes();
+    }
+    output.push_str(&source[last..]);
+
+    Ok(output)
+}
+
+/// Whether the identifier at `i` is itself a member access (`a.Name`)
+fn is_member(cursor: &TokenCursor, i: usize) -> bool {
+    i > 0 && matches!(cursor.symbol(i - 1), Some(Symbol::Dot) | Some(Symbol::Colon))
+}
+
+/// Indices of `key` identifiers in `key = value` entries at the top level of
+/// the table constructor opened at `open`
+fn constructor_keys(cursor: &TokenCursor, open: usize) -> Vec<usize> {
+    let mut keys = Vec::new();
+    let mut depth = 0i64;
+    let mut j = open;
+
+    while j < cursor.len() {
+        match cursor.symbol(j) {
+            Some(Symbol::LeftBrace) | Some(Symbol::LeftParen) | Some(Symbol::LeftBracket) => depth += 1,
+            Some(Symbol::RightBrace) | Some(Symbol::RightParen) | Some(Symbol::RightBracket) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {
+                let entry_start = matches!(
+                    cursor.symbol(j - 1),
+                    Some(Symbol::LeftBrace) | Some(Symbol::Comma) | Some(Symbol::Semicolon)
+                );
+                if depth == 1
+                    && entry_start
+                    && cursor.identifier(j).is_some()
+                    && cursor.symbol(j + 1) == Some(Symbol::Equal)
+                {
+                    keys.push(j);
+                }
+            }
+        }
+        j += 1;
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTIL: &str = "local Util = { VERSION = 1 }\n\
+                        function Util.clamp(x, lo, hi)\n\
+                            return math.clamp(x, lo, hi)\n\
+                        end\n\
+                        Util.helper = function() end\n\
+                        return Util\n";
+
+    #[test]
+    fn test_module_exports() {
+        let exports = module_exports(UTIL).unwrap();
+
+        assert_eq!(exports.table.as_deref(), Some("Util"));
+        let fields: Vec<_> = exports.fields.iter().map(String::as_str).collect();
+        assert_eq!(fields, vec!["VERSION", "clamp", "helper"]);
+    }
+
+    #[test]
+    fn test_script_without_return_has_no_exports() {
+        let exports = module_exports("print('hi')\n").unwrap();
+        assert!(exports.table.is_none());
+        assert!(exports.fields.is_empty());
+    }
+
+    #[test]
+    fn test_member_usage_flags_non_binding_access() {
+        let bindings: HashSet<String> = ["Util".to_string()].into();
+        let mut usage = MemberUsage::default();
+
+        member_usage(
+            "local v = Util.clamp(1, 2, 3)\nobj:helper()\nlocal k = t[\"VERSION\"]\n",
+            &bindings,
+            &mut usage,
+        )
+        .unwrap();
+
+        assert!(!usage.unsafe_names.contains("clamp"));
+        assert!(usage.unsafe_names.contains("helper"));
+        assert!(usage.unsafe_names.contains("VERSION"));
+    }
+
+    #[test]
+    fn test_rename_exports() {
+        let mapping: HashMap<String, String> = [
+            ("clamp".to_string(), "_0x0".to_string()),
+            ("VERSION".to_string(), "_0x1".to_string()),
+        ]
+        .into();
+        let own: HashSet<String> = ["Util".to_string()].into();
+
+        let renamed = rename_exports(UTIL, &own, Some("Util"), &mapping).unwrap();
+        assert!(renamed.contains("local Util = { _0x1 = 1 }"));
+        assert!(renamed.contains("function Util._0x0(x, lo, hi)"));
+        // Unrelated member accesses are untouched
+        assert!(renamed.contains("math.clamp(x, lo, hi)"));
+        assert!(renamed.contains("Util.helper"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Require graph between project scripts
+//!
+//! Resolves `require(...)` arguments built from `script`, `game`,
+//! `:GetService`, `:WaitForChild` and simple local aliases into instance
+//! paths, and matches them against the scripts in the project.
+
+use super::rojo::RojoProject;
+use super::{significant_tokens, TokenCursor};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::Symbol;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A `require` whose target could not be matched to a project script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedRequire {
+    /// Index of the requiring file in `RojoProject::files`
+    pub file: usize,
+    pub line: usize,
+}
+
+/// Dependency edges between project scripts
+#[derive(Debug, Clone, Default)]
+pub struct RequireGraph {
+    /// file → modules it requires
+    edges: Vec<BTreeSet<usize>>,
+    /// file → local name bound to a required module → module
+    bindings: Vec<BTreeMap<String, usize>>,
+    pub unresolved: Vec<UnresolvedRequire>,
+}
+
+impl RequireGraph {
+    /// Build the graph from project files and their sources (same order)
+    pub fn build(project: &RojoProject, sources: &[String]) -> Result<Self> {
+        let by_path: HashMap<&[String], usize> = project
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.instance_path.as_slice(), i))
+            .collect();
+
+        let mut graph = RequireGraph::default();
+
+        for (index, (file, source)) in project.files.iter().zip(sources).enumerate() {
+            let mut edges = BTreeSet::new();
+            let mut bindings = BTreeMap::new();
+
+            for require in find_requires(source, &file.instance_path)? {
+                match require.target.as_deref().and_then(|p| by_path.get(p)) {
+                    Some(&target) => {
+                        edges.insert(target);
+                        if let Some(name) = require.binding {
+                            bindings.insert(name, target);
+                        }
+                    }
+                    None => graph.unresolved.push(UnresolvedRequire {
+                        file: index,
+                        line: require.line,
+                    }),
+                }
+            }
+
+            graph.edges.push(edges);
+            graph.bindings.push(bindings);
+        }
+
+        Ok(graph)
+    }
+
+    /// Modules required by `file`
+    pub fn dependencies(&self, file: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges[file].iter().copied()
+    }
+
+    /// Local names in `file` bound to required modules
+    pub fn bindings(&self, file: usize) -> &BTreeMap<String, usize> {
+        &self.bindings[file]
+    }
+
+    /// Whether any script in the project requires `module`
+    pub fn is_required(&self, module: usize) -> bool {
+        self.edges.iter().any(|deps| deps.contains(&module))
+    }
+
+    /// Files ordered so every module comes before the scripts that require it
+    ///
+    /// Fails on a require cycle, which Luau also rejects at runtime.
+    pub fn topological_order(&self) -> Result<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            None,
+            Visiting,
+            Done,
+        }
+
+        fn visit(
+            graph: &RequireGraph,
+            node: usize,
+            marks: &mut [Mark],
+            order: &mut Vec<usize>,
+        ) -> Result<()> {
+            match marks[node] {
+                Mark::Done => return Ok(()),
+                Mark::Visiting => {
+                    return Err(ObfuscatorError::AnalysisError(format!(
+                        "Require cycle detected involving file #{}",
+                        node
+                    ))
+                    .into())
+                }
+                Mark::None => {}
+            }
+
+            marks[node] = Mark::Visiting;
+            for dep in graph.dependencies(node) {
+                visit(graph, dep, marks, order)?;
+            }
+            marks[node] = Mark::Done;
+            order.push(node);
+            Ok(())
+        }
+
+        let mut marks = vec![Mark::None; self.edges.len()];
+        let mut order = Vec::with_capacity(self.edges.len());
+        for node in 0..self.edges.len() {
+            visit(self, node, &mut marks, &mut order)?;
+        }
+        Ok(order)
+    }
+}
+
+/// A `require(...)` call found in a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RequireCall {
+    /// Local the result is assigned to (`local Util = require(...)`)
+    pub binding: Option<String>,
+    /// Resolved instance path, if the argument could be resolved statically
+    pub target: Option<Vec<String>>,
+    pub line: usize,
+    /// Byte range of the whole call, when the target was resolved
+    pub span: Option<(usize, usize)>,
+}
+
+/// Find every require in `source`, resolving paths relative to `script_path`
+pub(super) fn find_requires(source: &str, script_path: &[String]) -> Result<Vec<RequireCall>> {
+    let tokens = significant_tokens(source)?;
+    let cursor = TokenCursor::new(&tokens);
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    let mut requires = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        // local Name = <path> | require(<path>)
+        if cursor.symbol(i) == Some(Symbol::Local)
+            && cursor.symbol(i + 2) == Some(Symbol::Equal)
+        {
+            if let Some(name) = cursor.identifier(i + 1) {
+                if cursor.identifier(i + 3) == Some("require")
+                    && cursor.symbol(i + 4) == Some(Symbol::LeftParen)
+                {
+                    let (target, end) = resolve_argument(&cursor, i + 5, script_path, &aliases);
+                    requires.push(RequireCall {
+                        binding: Some(name.to_string()),
+                        span: target.as_ref().map(|_| cursor.span(i + 3, end - 1)),
+                        target,
+                        line: cursor.line(i + 3),
+                    });
+                    i = end;
+                    continue;
+                }
+                if let Some((path, end)) = resolve_path(&cursor, i + 3, script_path, &aliases) {
+                    aliases.insert(name.to_string(), path);
+                    i = end;
+                    continue;
+                }
+            }
+        }
+
+        if cursor.identifier(i) == Some("require") && cursor.symbol(i + 1) == Some(Symbol::LeftParen) {
+            let (target, end) = resolve_argument(&cursor, i + 2, script_path, &aliases);
+            requires.push(RequireCall {
+                binding: None,
+                span: target.as_ref().map(|_| cursor.span(i, end - 1)),
+                target,
+                line: cursor.line(i),
+            });
+            i = end;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    Ok(requires)
+}
+
+/// Resolve a require argument that must end at the closing paren
+fn resolve_argument(
+    cursor: &TokenCursor,
+    start: usize,
+    script_path: &[String],
+    aliases: &HashMap<String, Vec<String>>,
+) -> (Option<Vec<String>>, usize) {
+    match resolve_path(cursor, start, script_path, aliases) {
+        Some((path, end)) if cursor.symbol(end) == Some(Symbol::RightParen) => (Some(path), end + 1),
+        _ => (None, start),
+    }
+}
+
+/// Resolve an instance path expression such as `script.Parent.Util` or
+/// `game:GetService("ReplicatedStorage").Shared`
+fn resolve_path(
+    cursor: &TokenCursor,
+    start: usize,
+    script_path: &[String],
+    aliases: &HashMap<String, Vec<String>>,
+) -> Option<(Vec<String>, usize)> {
+    let mut path = match cursor.identifier(start)? {
+        "script" => script_path.to_vec(),
+        "game" => Vec::new(),
+        "workspace" => vec!["Workspace".to_string()],
+        name => aliases.get(name)?.clone(),
+    };
+
+    let mut j = start + 1;
+    loop {
+        match cursor.symbol(j) {
+            Some(Symbol::Dot) => {
+                let name = cursor.identifier(j + 1)?;
+                if name == "Parent" {
+                    path.pop()?;
+                } else {
+                    path.push(name.to_string());
+                }
+                j += 2;
+            }
+            Some(Symbol::Colon) => {
+                let method = cursor.identifier(j + 1)?;
+                if !matches!(method, "GetService" | "WaitForChild" | "FindFirstChild")
+                    || cursor.symbol(j + 2) != Some(Symbol::LeftParen)
+                {
+                    return None;
+                }
+                path.push(cursor.string(j + 3)?.to_string());
+                j += 4;
+                // WaitForChild("Name", timeout)
+                while j < cursor.len() && cursor.symbol(j) != Some(Symbol::RightParen) {
+                    j += 1;
+                }
+                j += 1;
+            }
+            Some(Symbol::LeftBracket) => {
+                let name = cursor.string(j + 1)?;
+                if cursor.symbol(j + 2) != Some(Symbol::RightBracket) {
+                    return None;
+                }
+                path.push(name.to_string());
+                j += 3;
+            }
+            _ => break,
+        }
+    }
+
+    Some((path, j))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_script_relative() {
+        let script = path(&["ReplicatedStorage", "Shared", "Combat"]);
+        let source = "local Util = require(script.Parent.Util)\n\
+                      local Net = require(script.Parent:WaitForChild(\"Net\", 5))\n";
+        let requires = find_requires(source, &script).unwrap();
+
+        assert_eq!(requires[0].binding.as_deref(), Some("Util"));
+        assert_eq!(requires[0].target, Some(path(&["ReplicatedStorage", "Shared", "Util"])));
+        assert_eq!(requires[1].target, Some(path(&["ReplicatedStorage", "Shared", "Net"])));
+        let (start, end) = requires[1].span.unwrap();
+        assert_eq!(&source[start..end], "require(script.Parent:WaitForChild(\"Net\", 5))");
+    }
+
+    #[test]
+    fn test_resolve_through_service_alias() {
+        let script = path(&["ServerScriptService", "Main"]);
+        let requires = find_requires(
+            "local RS = game:GetService(\"ReplicatedStorage\")\n\
+             local Shared = RS.Shared\n\
+             local Util = require(Shared.Util)\n\
+             require(12345)\n",
+            &script,
+        )
+        .unwrap();
+
+        assert_eq!(requires[0].target, Some(path(&["ReplicatedStorage", "Shared", "Util"])));
+        assert_eq!(requires[1].target, None);
+        assert_eq!(requires[1].line, 4);
+        assert_eq!(requires[1].span, None);
+    }
+}
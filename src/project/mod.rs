@@ -0,0 +1,454 @@
+//! Multi-module project obfuscation
+//!
+//! Reads a Rojo project, builds the require graph between its scripts and
+//! renames fields that ModuleScripts export so every requiring script uses
+//! the same mangled names. Optionally, remote names are renamed the same
+//! way across server and client scripts. Each script then goes through the
+//! `ObfuscationPipeline` with those names held fixed, and the protected tree
+//! is written out with the original layout and project file. `.rbxm`/`.rbxmx`
+//! models are handled the same way, with the scripts written back into the
+//! model.
+//!
+//! `Bundler` instead inlines the modules a script requires into one script,
+//! so the project can be protected and shipped as a single file.
+
+mod bundle;
+mod exports;
+mod graph;
+mod model;
+mod remotes;
+mod rojo;
+
+pub use bundle::{Bundle, Bundler, ScriptReference};
+pub use exports::{module_exports, ModuleExports};
+pub use graph::{RequireGraph, UnresolvedRequire};
+pub use model::{ModelFormat, RobloxModel};
+pub use remotes::RemoteMapping;
+pub use rojo::{ProjectFile, RojoProject, ScriptKind, DEFAULT_PROJECT_FILE};
+
+use exports::{member_usage, rename_exports, MemberUsage};
+use remotes::{remote_usage, rename_remotes, RemoteUsage};
+
+use crate::codegen::CodeGenConfig;
+use crate::obfuscation::{NameMangler, RngMode};
+use crate::pipeline::{ObfuscationPipeline, PipelineSettings};
+use crate::utils::errors::ObfuscatorError;
+use crate::utils::source::{InputOptions, SourceFile};
+use anyhow::{Context, Result};
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Summary of a project run
+#[derive(Debug, Clone, Default)]
+pub struct ProjectReport {
+    pub modules: usize,
+    pub scripts: usize,
+    /// Export names renamed consistently across modules
+    pub shared_mappings: HashMap<String, String>,
+    pub unresolved: Vec<UnresolvedRequire>,
+    /// Remotes renamed across scripts, when remote renaming is on
+    pub remote_mappings: Vec<RemoteMapping>,
+    /// Strings encrypted across all scripts
+    pub encrypted_strings: usize,
+    pub written: Vec<PathBuf>,
+}
+
+/// Obfuscates every script in a Rojo project with shared export mappings
+pub struct ProjectObfuscator {
+    pipeline: ObfuscationPipeline,
+    settings: PipelineSettings,
+    codegen_config: CodeGenConfig,
+    rename_remotes: bool,
+    input: InputOptions,
+}
+
+impl ProjectObfuscator {
+    /// Protect every script with `pipeline`, as `settings` and
+    /// `codegen_config` say
+    ///
+    /// The seed, naming style and API database of `settings` also drive the
+    /// shared renaming. All scripts get the script ID and license of
+    /// `codegen_config`.
+    pub fn new(
+        pipeline: ObfuscationPipeline,
+        settings: PipelineSettings,
+        codegen_config: CodeGenConfig,
+    ) -> Self {
+        Self {
+            pipeline,
+            settings,
+            codegen_config,
+            rename_remotes: false,
+            input: InputOptions::default(),
+        }
+    }
+
+    /// Also rename RemoteEvent/RemoteFunction names consistently across
+    /// server and client scripts (see `ProjectReport::remote_mappings`)
+    pub fn with_remote_renaming(mut self, enabled: bool) -> Self {
+        self.rename_remotes = enabled;
+        self
+    }
+
+    /// Decode scripts (and write them back) as `options` say
+    pub fn with_input_options(mut self, options: InputOptions) -> Self {
+        self.input = options;
+        self
+    }
+
+    /// Transform all project scripts into `output_dir`
+    pub fn run(&self, project: &RojoProject, output_dir: &Path) -> Result<ProjectReport> {
+        let files = project
+            .files
+            .iter()
+            .map(|file| {
+                let path = project.source_path(file);
+                SourceFile::read(&path, &self.input)
+                    .with_context(|| format!("Failed to read project script: {:?}", path))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let sources: Vec<String> = files.iter().map(|file| file.text.clone()).collect();
+
+        let (transformed, mut report) = self.transform(project, &sources)?;
+        for (i, code) in transformed {
+            let file = &project.files[i];
+            let destination = output_dir.join(&file.relative_path);
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+            }
+            fs::write(&destination, files[i].restore_line_endings(&code, &self.input))
+                .with_context(|| format!("Failed to write output file: {:?}", destination))?;
+            report.written.push(destination);
+        }
+
+        // Keep the project file so the output tree can be synced as-is
+        fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory: {:?}", output_dir))?;
+        if let Some(name) = project.project_file.file_name() {
+            fs::copy(&project.project_file, output_dir.join(name))
+                .with_context(|| format!("Failed to copy project file: {:?}", project.project_file))?;
+        }
+
+        Ok(report)
+    }
+
+    /// Transform the scripts of a model into a model file at `output`
+    ///
+    /// Only script sources change; every other instance is written as read.
+    pub fn run_model(&self, model: &RobloxModel, output: &Path) -> Result<ProjectReport> {
+        let project = model.project();
+        let mut sources = model.sources();
+
+        let (transformed, mut report) = self.transform(&project, &sources)?;
+        for (i, code) in transformed {
+            sources[i] = code;
+        }
+        model.write(output, &sources)?;
+
+        report.written.push(output.to_path_buf());
+        Ok(report)
+    }
+
+    /// Transformed source of every project script, in dependency order
+    fn transform(
+        &self,
+        project: &RojoProject,
+        sources: &[String],
+    ) -> Result<(Vec<(usize, String)>, ProjectReport)> {
+        let graph = RequireGraph::build(project, sources)?;
+        let order = graph.topological_order()?;
+
+        // Only modules required inside the project get their exports renamed;
+        // anything else may have consumers we can't see
+        let exports: Vec<Option<ModuleExports>> = project
+            .files
+            .iter()
+            .zip(sources)
+            .enumerate()
+            .map(|(i, (file, source))| {
+                if file.kind == ScriptKind::Module && graph.is_required(i) {
+                    module_exports(source).map(Some)
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        let renamed_bindings = |i: usize| -> HashSet<String> {
+            let mut names: HashSet<String> = graph
+                .bindings(i)
+                .iter()
+                .filter(|(_, module)| exports[**module].is_some())
+                .map(|(name, _)| name.clone())
+                .collect();
+            if let Some(table) = exports[i].as_ref().and_then(|e| e.table.clone()) {
+                names.insert(table);
+            }
+            names
+        };
+
+        let mut usage = MemberUsage::default();
+        for (i, source) in sources.iter().enumerate() {
+            member_usage(source, &renamed_bindings(i), &mut usage)?;
+        }
+
+        let shared: BTreeSet<String> = exports
+            .iter()
+            .flatten()
+            .flat_map(|e| e.fields.iter().cloned())
+            .filter(|name| !name.starts_with("__") && !usage.unsafe_names.contains(name))
+            .collect();
+
+        let preserved: Vec<String> = self.settings.api_db.preserved_names().into_iter().collect();
+        let rng = RngMode::from_seed(self.settings.seed);
+        let mut mangler = NameMangler::with_rng(&preserved, true, rng.stream(0))
+            .with_naming_style(self.settings.naming_style);
+        let shared_mappings = mangler.map_shared_names(&shared);
+        log::info!("Renaming {} shared export names", shared_mappings.len());
+
+        let mut remote_mappings = HashMap::new();
+        let mut remote_report = Vec::new();
+        if self.rename_remotes {
+            let mut remotes = RemoteUsage::default();
+            for (i, source) in sources.iter().enumerate() {
+                remote_usage(source, i, &mut remotes)?;
+            }
+            // Same mangler, so remote names never collide with export names
+            remote_mappings = mangler.map_shared_names(&remotes.remotes());
+            let mut names: Vec<&String> = remote_mappings.keys().collect();
+            names.sort();
+            remote_report = names
+                .into_iter()
+                .filter_map(|name| {
+                    remotes.mapping(name, &remote_mappings[name], &project.files)
+                })
+                .collect();
+            log::info!("Renaming {} remotes", remote_mappings.len());
+        }
+
+        let mut report = ProjectReport {
+            modules: exports.iter().filter(|e| e.is_some()).count(),
+            scripts: project.files.len(),
+            unresolved: graph.unresolved.clone(),
+            remote_mappings: remote_report,
+            shared_mappings: shared_mappings.clone(),
+            ..ProjectReport::default()
+        };
+
+        // The pipeline must not rename what the scripts now share
+        let mut settings = self.settings.clone();
+        settings
+            .exclusions
+            .names
+            .extend(shared_mappings.values().chain(remote_mappings.values()).cloned());
+
+        let mut transformed = Vec::with_capacity(order.len());
+        for i in order {
+            let file = &project.files[i];
+            let own_table = exports[i].as_ref().and_then(|e| e.table.as_deref());
+
+            let mut code =
+                rename_exports(&sources[i], &renamed_bindings(i), own_table, &shared_mappings)
+                    .with_context(|| format!("Failed to transform {}", file.display_path()))?;
+            if !remote_mappings.is_empty() {
+                code = rename_remotes(&code, &remote_mappings)?;
+            }
+            let protected = self
+                .pipeline
+                .protect(&code, &settings, self.codegen_config.clone())
+                .with_context(|| format!("Failed to protect {}", file.display_path()))?;
+            report.encrypted_strings += protected.stats.encrypted_strings;
+            transformed.push((i, protected.script));
+        }
+
+        Ok((transformed, report))
+    }
+}
+
+/// Tokenize `source`, dropping whitespace, comments and EOF
+fn significant_tokens(source: &str) -> Result<Vec<Token>> {
+    let all_tokens = tokens(source)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+    Ok(all_tokens
+        .into_iter()
+        .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+        .collect())
+}
+
+/// Indexed lookups over a significant-token list
+struct TokenCursor<'a> {
+    tokens: &'a [Token],
+}
+
+impl<'a> TokenCursor<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Self { tokens }
+    }
+
+    fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    fn symbol(&self, i: usize) -> Option<Symbol> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Symbol { symbol } => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    fn identifier(&self, i: usize) -> Option<&'a str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::Identifier { identifier } => Some(identifier.as_str()),
+            _ => None,
+        }
+    }
+
+    fn string(&self, i: usize) -> Option<&'a str> {
+        match self.tokens.get(i)?.token_type() {
+            TokenType::StringLiteral { literal, .. } => Some(literal.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Byte range from the start of token `first` to the end of token `last`
+    fn span(&self, first: usize, last: usize) -> (usize, usize) {
+        (
+            self.tokens[first].start_position().bytes(),
+            self.tokens[last].end_position().bytes(),
+        )
+    }
+
+    fn line(&self, i: usize) -> usize {
+        self.tokens
+            .get(i)
+            .map(|t| t.start_position().line())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::CryptoContext;
+
+    fn obfuscator() -> ProjectObfuscator {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let settings = PipelineSettings {
+            seed: Some(1),
+            ..Default::default()
+        };
+        let codegen_config = CodeGenConfig {
+            license_key: "TEST-KEY".to_string(),
+            script_id: "test_project".to_string(),
+            ..Default::default()
+        };
+        ProjectObfuscator::new(ObfuscationPipeline::new(crypto), settings, codegen_config)
+    }
+
+    /// Code of a protected script, after the generated runtime
+    fn code(script: &str) -> &str {
+        script.split("-- [CODE] Protected Script").nth(1).unwrap()
+    }
+
+    #[test]
+    fn test_project_shares_export_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("game");
+        fs::create_dir_all(root.join("src/shared")).unwrap();
+        fs::create_dir_all(root.join("src/server")).unwrap();
+        fs::write(
+            root.join("src/shared/Util.lua"),
+            "local Util = {}\nfunction Util.double(x)\n    return x * 2\nend\nreturn Util\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/server/Main.server.lua"),
+            "local RS = game:GetService(\"ReplicatedStorage\")\n\
+             local Util = require(RS.Shared.Util)\n\
+             print(Util.double(21), \"sale ends soon\")\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join(DEFAULT_PROJECT_FILE),
+            r#"{"name": "Game", "tree": {"$className": "DataModel",
+                "ReplicatedStorage": {"Shared": {"$path": "src/shared"}},
+                "ServerScriptService": {"Server": {"$path": "src/server"}}}}"#,
+        )
+        .unwrap();
+
+        let project = RojoProject::load(&root).unwrap();
+        let out = dir.path().join("out");
+        let report = obfuscator().run(&project, &out).unwrap();
+
+        let mangled = &report.shared_mappings["double"];
+        let util = fs::read_to_string(out.join("src/shared/Util.lua")).unwrap();
+        let main = fs::read_to_string(out.join("src/server/Main.server.lua")).unwrap();
+
+        assert_eq!(report.modules, 1);
+        assert!(report.unresolved.is_empty());
+        assert!(code(&util).contains(&format!(".{}(", mangled)));
+        assert!(code(&main).contains(&format!(".{}(", mangled)));
+        // Each script went through the pipeline, not just the shared renaming
+        assert!(!main.contains("sale ends soon"));
+        assert!(report.encrypted_strings > 0);
+        assert!(out.join(DEFAULT_PROJECT_FILE).exists());
+    }
+
+    #[test]
+    fn test_model_shares_export_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("Shop.rbxmx");
+        fs::write(
+            &input,
+            r#"<roblox version="4">
+  <Item class="Folder" referent="RBX0">
+    <Properties><string name="Name">Shop</string></Properties>
+    <Item class="ModuleScript" referent="RBX1">
+      <Properties>
+        <string name="Name">Util</string>
+        <ProtectedString name="Source"><![CDATA[local Util = {}
+function Util.double(x)
+    return x * 2
+end
+return Util
+]]></ProtectedString>
+      </Properties>
+    </Item>
+    <Item class="Script" referent="RBX2">
+      <Properties>
+        <string name="Name">Main</string>
+        <ProtectedString name="Source"><![CDATA[local Util = require(script.Parent.Util)
+print(Util.double(21), "sale ends soon")
+]]></ProtectedString>
+      </Properties>
+    </Item>
+    <Item class="Part" referent="RBX3">
+      <Properties><string name="Name">Sign</string></Properties>
+    </Item>
+  </Item>
+</roblox>"#,
+        )
+        .unwrap();
+
+        let model = RobloxModel::load(&input).unwrap();
+        let out = dir.path().join("out/Shop.rbxmx");
+        let report = obfuscator().run_model(&model, &out).unwrap();
+
+        let mangled = &report.shared_mappings["double"];
+        let protected = RobloxModel::load(&out).unwrap();
+        let sources = protected.sources();
+        assert_eq!(report.written, [out.clone()]);
+        assert!(code(&sources[0]).contains(&format!(".{}(", mangled)));
+        assert!(code(&sources[1]).contains(&format!(".{}(", mangled)));
+        assert!(!sources[0].contains("sale ends soon"));
+        let written = fs::read_to_string(&out).unwrap();
+        assert!(!written.contains("sale ends soon"));
+        assert!(written.contains(r#"<Item class="Part" referent="RBX3">"#));
+        assert!(obfuscator()
+            .run_model(&model, &dir.path().join("Shop.rbxm"))
+            .is_err());
+    }
+}
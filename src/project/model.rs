@@ -0,0 +1,841 @@
+//! Roblox model files (`.rbxm` binary, `.rbxmx` XML)
+//!
+//! A model is read as a project whose scripts are its Script, LocalScript
+//! and ModuleScript instances, named by their path from the model root.
+//! Writing replaces their `Source` and nothing else: every other instance,
+//! property and byte of the file is copied as read. In binary models the
+//! rewritten `Source` chunks are stored uncompressed, which Studio accepts.
+
+use super::{ProjectFile, RojoProject, ScriptKind};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// `<roblox!` and the signature every binary model starts with
+const BINARY_MAGIC: &[u8] = b"<roblox!\x89\xff\r\n\x1a\n";
+/// Magic, version, class and instance counts, reserved bytes
+const BINARY_HEADER_LEN: usize = 32;
+/// Property type `Source` is stored as
+const TYPE_STRING: u8 = 0x01;
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a model file is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// `.rbxm`
+    Binary,
+    /// `.rbxmx`
+    Xml,
+}
+
+impl ModelFormat {
+    /// Format of a model path by its extension, `None` for anything else
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "rbxm" => Some(ModelFormat::Binary),
+            "rbxmx" => Some(ModelFormat::Xml),
+            _ => None,
+        }
+    }
+}
+
+/// A Roblox model and the scripts in it
+#[derive(Debug, Clone)]
+pub struct RobloxModel {
+    pub format: ModelFormat,
+    pub path: PathBuf,
+    bytes: Vec<u8>,
+    /// Binary chunks in file order
+    chunks: Vec<Chunk>,
+    /// `Source` property chunks of binary models
+    source_chunks: Vec<SourceChunk>,
+    /// Sorted by instance path
+    scripts: Vec<ModelScript>,
+}
+
+#[derive(Debug, Clone)]
+struct ModelScript {
+    file: ProjectFile,
+    source: String,
+    location: SourceLocation,
+}
+
+#[derive(Debug, Clone)]
+enum SourceLocation {
+    /// Byte range of the `Source` element's content
+    Xml(Range<usize>),
+    /// Value `index` of `source_chunks[chunk]`
+    Binary { chunk: usize, index: usize },
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    name: [u8; 4],
+    /// Byte range of the whole chunk, header included
+    raw: Range<usize>,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct SourceChunk {
+    /// Index in `chunks`
+    chunk: usize,
+    class_id: u32,
+    values: Vec<String>,
+}
+
+impl RobloxModel {
+    /// Load a `.rbxm` or `.rbxmx` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = ModelFormat::from_path(path).ok_or_else(|| {
+            ObfuscatorError::ConfigError(format!("Not a .rbxm or .rbxmx model: {:?}", path))
+        })?;
+        let bytes =
+            fs::read(path).with_context(|| format!("Failed to read model file: {:?}", path))?;
+        Self::from_bytes(format, path, bytes)
+    }
+
+    /// Parse model `bytes`; `path` names the model and its scripts
+    pub fn from_bytes(format: ModelFormat, path: &Path, bytes: Vec<u8>) -> Result<Self> {
+        let mut model = Self {
+            format,
+            path: path.to_path_buf(),
+            bytes,
+            chunks: Vec::new(),
+            source_chunks: Vec::new(),
+            scripts: Vec::new(),
+        };
+        match format {
+            ModelFormat::Binary => model.read_binary()?,
+            ModelFormat::Xml => model.read_xml()?,
+        }
+        model
+            .scripts
+            .sort_by(|a, b| a.file.instance_path.cmp(&b.file.instance_path));
+        Ok(model)
+    }
+
+    /// The model as a project, for the require graph and export renaming
+    ///
+    /// Scripts are named like Rojo files (`Shared/Util.lua`,
+    /// `Main.server.lua`), though nothing is read from those paths.
+    pub fn project(&self) -> RojoProject {
+        RojoProject {
+            name: self.name(),
+            root: self
+                .path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            project_file: self.path.clone(),
+            files: self.scripts.iter().map(|s| s.file.clone()).collect(),
+        }
+    }
+
+    /// Script sources, in the order of `project().files`
+    pub fn sources(&self) -> Vec<String> {
+        self.scripts.iter().map(|s| s.source.clone()).collect()
+    }
+
+    /// The model with each script's source replaced by `sources`
+    pub fn to_bytes(&self, sources: &[String]) -> Result<Vec<u8>> {
+        if sources.len() != self.scripts.len() {
+            return Err(ObfuscatorError::ObfuscationError(format!(
+                "Model has {} scripts, got {} sources",
+                self.scripts.len(),
+                sources.len()
+            ))
+            .into());
+        }
+        Ok(match self.format {
+            ModelFormat::Binary => self.write_binary(sources),
+            ModelFormat::Xml => self.write_xml(sources),
+        })
+    }
+
+    /// Write the model with replaced sources to `path`
+    pub fn write(&self, path: &Path, sources: &[String]) -> Result<()> {
+        if ModelFormat::from_path(path) != Some(self.format) {
+            return Err(ObfuscatorError::ConfigError(format!(
+                "Output {:?} must have the input model's extension (.{})",
+                path,
+                self.extension()
+            ))
+            .into());
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        fs::write(path, self.to_bytes(sources)?)
+            .with_context(|| format!("Failed to write model file: {:?}", path))
+    }
+
+    fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Model".to_string())
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.format {
+            ModelFormat::Binary => "rbxm",
+            ModelFormat::Xml => "rbxmx",
+        }
+    }
+
+    fn read_binary(&mut self) -> Result<()> {
+        if !self.bytes.starts_with(BINARY_MAGIC) || self.bytes.len() < BINARY_HEADER_LEN {
+            return Err(invalid("not a binary Roblox model").into());
+        }
+
+        let mut reader = Reader::new(&self.bytes, BINARY_HEADER_LEN);
+        loop {
+            let start = reader.pos;
+            let name: [u8; 4] = reader.bytes(4)?.try_into().expect("four bytes");
+            let compressed = reader.u32()? as usize;
+            let uncompressed = reader.u32()? as usize;
+            reader.bytes(4)?;
+            let payload = reader.bytes(if compressed == 0 {
+                uncompressed
+            } else {
+                compressed
+            })?;
+
+            let data = if compressed == 0 {
+                payload.to_vec()
+            } else if payload.starts_with(ZSTD_MAGIC) {
+                return Err(invalid(
+                    "zstd-compressed chunks are not supported; save the model as .rbxmx instead",
+                )
+                .into());
+            } else {
+                lz4_flex::block::decompress(payload, uncompressed)
+                    .map_err(|e| invalid(&format!("corrupt chunk: {}", e)))?
+            };
+            self.chunks.push(Chunk {
+                name,
+                raw: start..reader.pos,
+                data,
+            });
+            if name == *b"END\0" {
+                break;
+            }
+        }
+
+        // Instances come before their properties, but don't rely on it
+        let mut classes: HashMap<u32, (String, Vec<i32>)> = HashMap::new();
+        let mut parents: HashMap<i32, i32> = HashMap::new();
+        for chunk in &self.chunks {
+            let mut data = Reader::new(&chunk.data, 0);
+            match &chunk.name {
+                b"INST" => {
+                    let class_id = data.u32()?;
+                    let class = data.string()?;
+                    data.u8()?;
+                    let count = data.u32()? as usize;
+                    let referents = data.referents(count)?;
+                    classes.insert(class_id, (class, referents));
+                }
+                b"PRNT" => {
+                    data.u8()?;
+                    let count = data.u32()? as usize;
+                    let children = data.referents(count)?;
+                    let parent_refs = data.referents(count)?;
+                    parents.extend(children.into_iter().zip(parent_refs));
+                }
+                _ => {}
+            }
+        }
+
+        let mut names: HashMap<i32, String> = HashMap::new();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if chunk.name != *b"PROP" {
+                continue;
+            }
+            let mut data = Reader::new(&chunk.data, 0);
+            let class_id = data.u32()?;
+            let property = data.string()?;
+            let Some((class, referents)) = classes.get(&class_id) else {
+                continue;
+            };
+            let is_source = property == "Source" && script_kind(class).is_some();
+            if property != "Name" && !is_source {
+                continue;
+            }
+            if data.u8()? != TYPE_STRING {
+                if is_source {
+                    return Err(invalid(&format!("{}.Source is not a string", class)).into());
+                }
+                continue;
+            }
+
+            let mut values = Vec::with_capacity(referents.len());
+            for _ in referents {
+                values.push(data.string()?);
+            }
+            if is_source {
+                self.source_chunks.push(SourceChunk {
+                    chunk: index,
+                    class_id,
+                    values,
+                });
+            } else {
+                names.extend(referents.iter().copied().zip(values));
+            }
+        }
+
+        let path_of = |referent: i32| -> Result<Vec<String>> {
+            let mut path = Vec::new();
+            let mut current = referent;
+            while current >= 0 {
+                if path.len() > parents.len() {
+                    return Err(invalid("instance is its own ancestor").into());
+                }
+                path.push(names.get(&current).cloned().unwrap_or_default());
+                current = parents.get(&current).copied().unwrap_or(-1);
+            }
+            path.reverse();
+            Ok(path)
+        };
+
+        for (chunk, source_chunk) in self.source_chunks.iter().enumerate() {
+            let (class, referents) = &classes[&source_chunk.class_id];
+            let kind = script_kind(class).expect("script class");
+            for (index, &referent) in referents.iter().enumerate() {
+                self.scripts.push(ModelScript {
+                    file: model_file(path_of(referent)?, kind),
+                    source: source_chunk.values[index].clone(),
+                    location: SourceLocation::Binary { chunk, index },
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn write_binary(&self, sources: &[String]) -> Vec<u8> {
+        let mut source_values: Vec<Vec<&str>> = self
+            .source_chunks
+            .iter()
+            .map(|c| c.values.iter().map(String::as_str).collect())
+            .collect();
+        for (script, source) in self.scripts.iter().zip(sources) {
+            if let SourceLocation::Binary { chunk, index } = script.location {
+                source_values[chunk][index] = source.as_str();
+            }
+        }
+
+        let mut out = self.bytes[..BINARY_HEADER_LEN].to_vec();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let Some(position) = self.source_chunks.iter().position(|c| c.chunk == index) else {
+                out.extend_from_slice(&self.bytes[chunk.raw.clone()]);
+                continue;
+            };
+
+            let mut data = self.source_chunks[position].class_id.to_le_bytes().to_vec();
+            push_string(&mut data, "Source");
+            data.push(TYPE_STRING);
+            for value in &source_values[position] {
+                push_string(&mut data, value);
+            }
+
+            out.extend_from_slice(b"PROP");
+            out.extend_from_slice(&0u32.to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&data);
+        }
+        let end = self.chunks.last().map_or(BINARY_HEADER_LEN, |c| c.raw.end);
+        out.extend_from_slice(&self.bytes[end..]);
+        out
+    }
+
+    fn read_xml(&mut self) -> Result<()> {
+        let text = std::str::from_utf8(&self.bytes)
+            .map_err(|_| invalid("XML model is not valid UTF-8"))?;
+
+        struct Item {
+            class: String,
+            name: Option<String>,
+            parent: Option<usize>,
+            source: Option<Range<usize>>,
+        }
+        let mut items: Vec<Item> = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+
+        let mut pos = 0;
+        while let Some(offset) = text[pos..].find('<') {
+            let start = pos + offset;
+            let rest = &text[start..];
+            if rest.starts_with("<!--") {
+                pos = skip_past(text, start, "-->")?;
+                continue;
+            }
+            if rest.starts_with("<![CDATA[") {
+                pos = skip_past(text, start, "]]>")?;
+                continue;
+            }
+            let tag_end = skip_past(text, start, ">")?;
+            let tag = &text[start + 1..tag_end - 1];
+            let self_closing = tag.ends_with('/');
+            pos = tag_end;
+
+            let tag_name = tag.split(char::is_whitespace).next().unwrap_or_default();
+            match tag_name.trim_end_matches('/') {
+                "Item" => {
+                    items.push(Item {
+                        class: attribute(tag, "class").unwrap_or_default().to_string(),
+                        name: None,
+                        parent: open.last().copied(),
+                        source: None,
+                    });
+                    if !self_closing {
+                        open.push(items.len() - 1);
+                    }
+                }
+                "/Item" => {
+                    open.pop();
+                }
+                "string" if !self_closing && attribute(tag, "name") == Some("Name") => {
+                    let content_end = element_end(text, tag_end, "</string>")?;
+                    if let Some(&i) = open.last() {
+                        if items[i].name.is_none() {
+                            items[i].name = Some(xml_text(&text[tag_end..content_end]));
+                        }
+                    }
+                    pos = content_end;
+                }
+                "ProtectedString" if !self_closing && attribute(tag, "name") == Some("Source") => {
+                    let content_end = element_end(text, tag_end, "</ProtectedString>")?;
+                    if let Some(&i) = open.last() {
+                        items[i].source = Some(tag_end..content_end);
+                    }
+                    pos = content_end;
+                }
+                _ => {}
+            }
+        }
+
+        for item in &items {
+            let (Some(kind), Some(range)) = (script_kind(&item.class), &item.source) else {
+                continue;
+            };
+            let mut path = Vec::new();
+            let mut current = Some(item);
+            while let Some(node) = current {
+                path.push(node.name.clone().unwrap_or_else(|| node.class.clone()));
+                current = node.parent.map(|i| &items[i]);
+            }
+            path.reverse();
+            self.scripts.push(ModelScript {
+                file: model_file(path, kind),
+                source: xml_text(&text[range.clone()]),
+                location: SourceLocation::Xml(range.clone()),
+            });
+        }
+        Ok(())
+    }
+
+    fn write_xml(&self, sources: &[String]) -> Vec<u8> {
+        let mut replacements: Vec<(&Range<usize>, &String)> = self
+            .scripts
+            .iter()
+            .zip(sources)
+            .filter_map(|(script, source)| match &script.location {
+                SourceLocation::Xml(range) => Some((range, source)),
+                SourceLocation::Binary { .. } => None,
+            })
+            .collect();
+        replacements.sort_by_key(|(range, _)| range.start);
+
+        let mut out = Vec::with_capacity(self.bytes.len());
+        let mut copied = 0;
+        for (range, source) in replacements {
+            out.extend_from_slice(&self.bytes[copied..range.start]);
+            out.extend_from_slice(b"<![CDATA[");
+            out.extend_from_slice(source.replace("]]>", "]]]]><![CDATA[>").as_bytes());
+            out.extend_from_slice(b"]]>");
+            copied = range.end;
+        }
+        out.extend_from_slice(&self.bytes[copied..]);
+        out
+    }
+}
+
+/// Script kind of a LuaSourceContainer class
+fn script_kind(class: &str) -> Option<ScriptKind> {
+    match class {
+        "ModuleScript" => Some(ScriptKind::Module),
+        "Script" => Some(ScriptKind::Server),
+        "LocalScript" => Some(ScriptKind::Client),
+        _ => None,
+    }
+}
+
+fn model_file(instance_path: Vec<String>, kind: ScriptKind) -> ProjectFile {
+    let suffix = match kind {
+        ScriptKind::Module => ".lua",
+        ScriptKind::Server => ".server.lua",
+        ScriptKind::Client => ".client.lua",
+    };
+    let mut relative_path: PathBuf = instance_path.iter().collect();
+    let file_name = format!(
+        "{}{}",
+        instance_path.last().cloned().unwrap_or_default(),
+        suffix
+    );
+    relative_path.set_file_name(file_name);
+    ProjectFile {
+        instance_path,
+        relative_path,
+        kind,
+    }
+}
+
+fn invalid(reason: &str) -> ObfuscatorError {
+    ObfuscatorError::ConfigError(format!("Invalid model file: {}", reason))
+}
+
+fn push_string(data: &mut Vec<u8>, value: &str) {
+    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    data.extend_from_slice(value.as_bytes());
+}
+
+/// Little-endian reads over a chunk
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(
+            self.bytes(4)?.try_into().expect("four bytes"),
+        ))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    /// `count` referents: byte-interleaved big-endian, zigzag-encoded deltas
+    fn referents(&mut self, count: usize) -> Result<Vec<i32>> {
+        let len = count
+            .checked_mul(4)
+            .ok_or_else(|| invalid("instance count out of range"))?;
+        let raw = self.bytes(len)?;
+        let mut previous = 0i32;
+        Ok((0..count)
+            .map(|i| {
+                let encoded = u32::from_be_bytes([
+                    raw[i],
+                    raw[count + i],
+                    raw[2 * count + i],
+                    raw[3 * count + i],
+                ]);
+                let delta = (encoded >> 1) as i32 ^ -((encoded & 1) as i32);
+                previous = previous.wrapping_add(delta);
+                previous
+            })
+            .collect())
+    }
+}
+
+/// Position just past the first `needle` at or after `from`
+fn skip_past(text: &str, from: usize, needle: &str) -> Result<usize> {
+    text[from..]
+        .find(needle)
+        .map(|i| from + i + needle.len())
+        .ok_or_else(|| invalid(&format!("missing {:?}", needle)).into())
+}
+
+/// Start of `closing` after element content starting at `from`, skipping
+/// CDATA sections
+fn element_end(text: &str, from: usize, closing: &str) -> Result<usize> {
+    let mut pos = from;
+    loop {
+        let offset = text[pos..]
+            .find('<')
+            .ok_or_else(|| invalid(&format!("missing {:?}", closing)))?;
+        let start = pos + offset;
+        if text[start..].starts_with(closing) {
+            return Ok(start);
+        }
+        pos = if text[start..].starts_with("<![CDATA[") {
+            skip_past(text, start, "]]>")?
+        } else {
+            start + 1
+        };
+    }
+}
+
+/// Value of `key="..."` in a tag
+fn attribute<'t>(tag: &'t str, key: &str) -> Option<&'t str> {
+    let pattern = format!(" {}=\"", key);
+    let start = tag.find(&pattern)? + pattern.len();
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+/// Text content of an element: CDATA sections as-is, entities decoded
+fn xml_text(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("<![CDATA[") {
+        text.push_str(&unescape(&rest[..start]));
+        let body = &rest[start + "<![CDATA[".len()..];
+        let end = body.find("]]>").unwrap_or(body.len());
+        text.push_str(&body[..end]);
+        rest = body.get(end + "]]>".len()..).unwrap_or_default();
+    }
+    text.push_str(&unescape(rest));
+    text
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let entity = &rest[1..semi];
+            let c = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(|code| code.ok())
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, semi + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML_MODEL: &str = r#"<roblox version="4">
+	<Item class="Folder" referent="RBX0">
+		<Properties>
+			<string name="Name">Shop</string>
+		</Properties>
+		<Item class="ModuleScript" referent="RBX1">
+			<Properties>
+				<string name="Name">Util</string>
+				<ProtectedString name="Source"><![CDATA[return { tag = "]]]]><![CDATA[>" }]]></ProtectedString>
+			</Properties>
+		</Item>
+		<Item class="Script" referent="RBX2">
+			<Properties>
+				<ProtectedString name="Source">print(1 &lt; 2)</ProtectedString>
+				<string name="Name">Main</string>
+			</Properties>
+		</Item>
+		<Item class="Part" referent="RBX3">
+			<Properties>
+				<string name="Name">Counter &amp; Sign</string>
+			</Properties>
+		</Item>
+	</Item>
+</roblox>"#;
+
+    /// Zigzag and interleave referent deltas like Roblox does
+    fn referents(values: &[i32]) -> Vec<u8> {
+        let mut previous = 0;
+        let encoded: Vec<[u8; 4]> = values
+            .iter()
+            .map(|&value| {
+                let delta = value - previous;
+                previous = value;
+                (((delta << 1) ^ (delta >> 31)) as u32).to_be_bytes()
+            })
+            .collect();
+        (0..4)
+            .flat_map(|byte| encoded.iter().map(move |e| e[byte]))
+            .collect()
+    }
+
+    fn chunk(name: &[u8; 4], data: &[u8], compress: bool) -> Vec<u8> {
+        let mut out = name.to_vec();
+        if compress {
+            let compressed = lz4_flex::block::compress(data);
+            out.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&compressed);
+        } else {
+            out.extend_from_slice(&0u32.to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    fn inst(class_id: u32, class: &str, refs: &[i32]) -> Vec<u8> {
+        let mut data = class_id.to_le_bytes().to_vec();
+        push_string(&mut data, class);
+        data.push(0);
+        data.extend_from_slice(&(refs.len() as u32).to_le_bytes());
+        data.extend_from_slice(&referents(refs));
+        chunk(b"INST", &data, true)
+    }
+
+    fn prop(class_id: u32, name: &str, values: &[&str]) -> Vec<u8> {
+        let mut data = class_id.to_le_bytes().to_vec();
+        push_string(&mut data, name);
+        data.push(TYPE_STRING);
+        for value in values {
+            push_string(&mut data, value);
+        }
+        chunk(b"PROP", &data, true)
+    }
+
+    /// Folder `Shop` holding ModuleScript `Util` and Script `Main`
+    fn binary_model() -> Vec<u8> {
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[0, 0, 3, 0, 0, 0, 3, 0, 0, 0]);
+        bytes.extend_from_slice(&[0; 8]);
+        bytes.extend(inst(0, "Folder", &[0]));
+        bytes.extend(inst(1, "ModuleScript", &[1]));
+        bytes.extend(inst(2, "Script", &[2]));
+        bytes.extend(prop(0, "Name", &["Shop"]));
+        bytes.extend(prop(1, "Name", &["Util"]));
+        bytes.extend(prop(1, "Source", &["return {}"]));
+        bytes.extend(prop(2, "Name", &["Main"]));
+        bytes.extend(prop(2, "Source", &["print(require(script.Parent.Util))"]));
+
+        let mut parents = vec![0];
+        parents.extend_from_slice(&3u32.to_le_bytes());
+        parents.extend(referents(&[0, 1, 2]));
+        parents.extend(referents(&[-1, 0, 0]));
+        bytes.extend(chunk(b"PRNT", &parents, false));
+        bytes.extend(chunk(b"END\0", b"</roblox>", false));
+        bytes
+    }
+
+    fn paths(model: &RobloxModel) -> Vec<String> {
+        model
+            .project()
+            .files
+            .iter()
+            .map(|f| f.display_path())
+            .collect()
+    }
+
+    #[test]
+    fn test_xml_model_scripts() {
+        let path = Path::new("Shop.rbxmx");
+        let model = RobloxModel::from_bytes(ModelFormat::Xml, path, XML_MODEL.into()).unwrap();
+
+        assert_eq!(paths(&model), ["Shop.Main", "Shop.Util"]);
+        assert_eq!(model.project().files[0].kind, ScriptKind::Server);
+        assert_eq!(
+            model.project().files[1].relative_path,
+            Path::new("Shop/Util.lua")
+        );
+        assert_eq!(
+            model.sources(),
+            ["print(1 < 2)", "return { tag = \"]]>\" }"]
+        );
+    }
+
+    #[test]
+    fn test_xml_model_rewrites_only_sources() {
+        let path = Path::new("Shop.rbxmx");
+        let model = RobloxModel::from_bytes(ModelFormat::Xml, path, XML_MODEL.into()).unwrap();
+        let bytes = model
+            .to_bytes(&["print(\"a]]>b\")".to_string(), "return 1".to_string()])
+            .unwrap();
+
+        let written = String::from_utf8(bytes.clone()).unwrap();
+        assert!(written.contains("<string name=\"Name\">Counter &amp; Sign</string>"));
+        let reread = RobloxModel::from_bytes(ModelFormat::Xml, path, bytes).unwrap();
+        assert_eq!(reread.sources(), ["print(\"a]]>b\")", "return 1"]);
+
+        let unchanged = model.to_bytes(&model.sources()).unwrap();
+        let reread = RobloxModel::from_bytes(ModelFormat::Xml, path, unchanged).unwrap();
+        assert_eq!(reread.sources(), model.sources());
+    }
+
+    #[test]
+    fn test_binary_model_round_trip() {
+        let path = Path::new("Shop.rbxm");
+        let original = binary_model();
+        let model = RobloxModel::from_bytes(ModelFormat::Binary, path, original.clone()).unwrap();
+
+        assert_eq!(paths(&model), ["Shop.Main", "Shop.Util"]);
+        assert_eq!(model.project().files[1].kind, ScriptKind::Module);
+        assert_eq!(model.sources()[1], "return {}");
+
+        let sources = vec!["print(1)".to_string(), "return { x = 1 }".to_string()];
+        let bytes = model.to_bytes(&sources).unwrap();
+        let reread = RobloxModel::from_bytes(ModelFormat::Binary, path, bytes.clone()).unwrap();
+        assert_eq!(reread.sources(), sources);
+        assert_eq!(paths(&reread), paths(&model));
+
+        // Everything before the first Source chunk is copied as read
+        let first_source = model.chunks[model.source_chunks[0].chunk].raw.start;
+        assert_eq!(bytes[..first_source], original[..first_source]);
+        assert!(bytes.ends_with(b"</roblox>"));
+    }
+
+    #[test]
+    fn test_invalid_models_are_rejected() {
+        let path = Path::new("Shop.rbxm");
+        let mut truncated = binary_model();
+        truncated.truncate(truncated.len() - 20);
+        assert!(RobloxModel::from_bytes(ModelFormat::Binary, path, truncated).is_err());
+        assert!(RobloxModel::from_bytes(ModelFormat::Binary, path, XML_MODEL.into()).is_err());
+
+        assert_eq!(
+            ModelFormat::from_path(Path::new("a.RBXM")),
+            Some(ModelFormat::Binary)
+        );
+        assert_eq!(ModelFormat::from_path(Path::new("a.lua")), None);
+    }
+}
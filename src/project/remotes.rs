@@ -0,0 +1,414 @@
+//! Coordinated RemoteEvent/RemoteFunction renaming
+//!
+//! Remote names are the first thing an exploit looks for. A name counts as
+//! a remote when some script uses what it names as one: looked up with
+//! `WaitForChild`/`FindFirstChild` or a dotted access and then fired,
+//! invoked or connected (directly or through a local), or assigned as the
+//! `Name` of an `Instance.new("RemoteEvent")`. Every lookup of such a name
+//! in the project gets the same new name, so servers and clients stay
+//! paired; the Instances in the place must be renamed to match, which is
+//! what the mapping records. Names only ever looked up (`"Humanoid"`,
+//! folders) are left alone.
+
+use super::{significant_tokens, ScriptKind, TokenCursor};
+use anyhow::Result;
+use full_moon::tokenizer::Symbol;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Methods whose first argument names a child
+const LOOKUPS: &[&str] = &["WaitForChild", "FindFirstChild"];
+
+/// Classes `Instance.new` creates remotes of
+const REMOTE_CLASSES: &[&str] = &["RemoteEvent", "RemoteFunction", "UnreliableRemoteEvent"];
+
+/// Members only remotes have, with the class they imply
+const REMOTE_MEMBERS: &[(&str, &str)] = &[
+    ("FireServer", "RemoteEvent"),
+    ("FireClient", "RemoteEvent"),
+    ("FireAllClients", "RemoteEvent"),
+    ("OnServerEvent", "RemoteEvent"),
+    ("OnClientEvent", "RemoteEvent"),
+    ("InvokeServer", "RemoteFunction"),
+    ("InvokeClient", "RemoteFunction"),
+    ("OnServerInvoke", "RemoteFunction"),
+    ("OnClientInvoke", "RemoteFunction"),
+];
+
+/// How a remote name appears in the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Site {
+    /// String argument of a lookup, or a `Name` assignment
+    String,
+    /// `parent.Name` access
+    Member,
+}
+
+/// A name in a position that refers to an Instance
+struct Occurrence {
+    token: usize,
+    name: String,
+    site: Site,
+    /// Remote class the code uses it as, if any
+    class: Option<&'static str>,
+}
+
+/// How the scripts of a project use one remote name
+#[derive(Debug, Clone, Default)]
+struct RemoteUse {
+    class: Option<&'static str>,
+    /// Used as a remote somewhere (not just looked up)
+    remote: bool,
+    files: BTreeSet<usize>,
+}
+
+/// Remote names found across a project's scripts
+#[derive(Debug, Clone, Default)]
+pub struct RemoteUsage {
+    names: BTreeMap<String, RemoteUse>,
+}
+
+impl RemoteUsage {
+    /// Names used as a remote by at least one script
+    pub fn remotes(&self) -> BTreeSet<String> {
+        self.names
+            .iter()
+            .filter(|(_, usage)| usage.remote)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Mapping entry for `name`, given the project's scripts
+    pub fn mapping(
+        &self,
+        name: &str,
+        renamed: &str,
+        files: &[super::ProjectFile],
+    ) -> Option<RemoteMapping> {
+        let usage = self.names.get(name)?;
+        let scripts = |kind: ScriptKind| -> Vec<String> {
+            usage
+                .files
+                .iter()
+                .map(|&i| &files[i])
+                .filter(|file| file.kind == kind)
+                .map(|file| file.display_path())
+                .collect()
+        };
+        Some(RemoteMapping {
+            original: name.to_string(),
+            renamed: renamed.to_string(),
+            class: usage.class.map(str::to_string),
+            server: scripts(ScriptKind::Server),
+            client: scripts(ScriptKind::Client),
+            modules: scripts(ScriptKind::Module),
+        })
+    }
+}
+
+/// One renamed remote, for renaming (or creating) the Instance to match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemoteMapping {
+    pub original: String,
+    pub renamed: String,
+    /// `RemoteEvent`, `RemoteFunction` or `UnreliableRemoteEvent`, when the
+    /// code shows which
+    pub class: Option<String>,
+    /// Scripts using it, by side
+    pub server: Vec<String>,
+    pub client: Vec<String>,
+    /// ModuleScripts using it (may run on either side)
+    pub modules: Vec<String>,
+}
+
+impl RemoteMapping {
+    /// Whether both a server and a client side use the remote (or a module
+    /// does, which may be either)
+    pub fn is_paired(&self) -> bool {
+        (!self.server.is_empty() && !self.client.is_empty()) || !self.modules.is_empty()
+    }
+}
+
+/// Record the remote names script `file` refers to
+pub fn remote_usage(source: &str, file: usize, usage: &mut RemoteUsage) -> Result<()> {
+    let tokens = significant_tokens(source)?;
+    for occurrence in occurrences(&TokenCursor::new(&tokens)) {
+        let entry = usage.names.entry(occurrence.name).or_default();
+        entry.files.insert(file);
+        if let Some(class) = occurrence.class {
+            entry.class.get_or_insert(class);
+            entry.remote = true;
+        }
+    }
+    Ok(())
+}
+
+/// Rename every lookup of a name in `mapping`
+pub fn rename_remotes(source: &str, mapping: &HashMap<String, String>) -> Result<String> {
+    let tokens = significant_tokens(source)?;
+    let mut renames: Vec<(usize, String)> = occurrences(&TokenCursor::new(&tokens))
+        .into_iter()
+        .filter_map(|occurrence| {
+            let renamed = mapping.get(&occurrence.name)?;
+            let text = match occurrence.site {
+                Site::String => format!("\"{}\"", renamed),
+                Site::Member => renamed.clone(),
+            };
+            Some((occurrence.token, text))
+        })
+        .collect();
+    renames.sort_by_key(|(token, _)| *token);
+    renames.dedup_by_key(|(token, _)| *token);
+
+    let mut output = String::with_capacity(source.len());
+    let mut last = 0;
+    for (index, text) in renames {
+        let token = &tokens[index];
+        output.push_str(&source[last..token.start_position().bytes()]);
+        output.push_str(&text);
+        last = token.end_position().bytes();
+    }
+    output.push_str(&source[last..]);
+    Ok(output)
+}
+
+/// Instance names referred to in a script, in token order
+fn occurrences(cursor: &TokenCursor) -> Vec<Occurrence> {
+    // Locals holding a new remote: `local r = Instance.new("RemoteEvent")`
+    let mut created: HashMap<&str, &'static str> = HashMap::new();
+    for i in 0..cursor.len() {
+        let new_remote = cursor.identifier(i) == Some("Instance")
+            && cursor.symbol(i + 1) == Some(Symbol::Dot)
+            && cursor.identifier(i + 2) == Some("new")
+            && cursor.symbol(i + 3) == Some(Symbol::LeftParen);
+        if !new_remote {
+            continue;
+        }
+        let class = cursor
+            .string(i + 4)
+            .and_then(|class| REMOTE_CLASSES.iter().find(|c| **c == class).copied());
+        if let (Some(class), Some(local)) = (class, bound_local(cursor, i)) {
+            created.insert(local, class);
+        }
+    }
+
+    let mut found = Vec::new();
+    for i in 0..cursor.len() {
+        let after_colon = i > 0 && cursor.symbol(i - 1) == Some(Symbol::Colon);
+        let after_dot = i > 0 && cursor.symbol(i - 1) == Some(Symbol::Dot);
+
+        // parent:WaitForChild("Name"[, timeout])
+        if after_colon
+            && cursor.identifier(i).map_or(false, |name| LOOKUPS.contains(&name))
+            && cursor.symbol(i + 1) == Some(Symbol::LeftParen)
+        {
+            if let (Some(name), Some(close)) = (cursor.string(i + 2), closing_paren(cursor, i + 1))
+            {
+                found.push(Occurrence {
+                    token: i + 2,
+                    name: name.to_string(),
+                    site: Site::String,
+                    class: usage_class(cursor, chain_start(cursor, i), close),
+                });
+            }
+            continue;
+        }
+
+        // remote.Name = "Name"
+        if after_dot
+            && cursor.identifier(i) == Some("Name")
+            && cursor.symbol(i + 1) == Some(Symbol::Equal)
+        {
+            let local = i.checked_sub(2).and_then(|j| cursor.identifier(j));
+            if let (Some(name), Some(local)) = (cursor.string(i + 2), local) {
+                found.push(Occurrence {
+                    token: i + 2,
+                    name: name.to_string(),
+                    site: Site::String,
+                    class: created.get(local).copied(),
+                });
+            }
+            continue;
+        }
+
+        // parent.Name, only where it's used as a remote
+        let Some(name) = cursor.identifier(i).filter(|_| after_dot) else {
+            continue;
+        };
+        let plain = !matches!(cursor.symbol(i + 1), Some(Symbol::Equal) | Some(Symbol::LeftParen));
+        if plain {
+            if let Some(class) = usage_class(cursor, chain_start(cursor, i), i) {
+                found.push(Occurrence {
+                    token: i,
+                    name: name.to_string(),
+                    site: Site::Member,
+                    class: Some(class),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// Remote class implied by what the code does with the value of the chain
+/// `start..=end`: a remote member right after it, or on the local it's
+/// assigned to
+fn usage_class(cursor: &TokenCursor, start: usize, end: usize) -> Option<&'static str> {
+    if let Some(class) = remote_member(cursor, end + 1) {
+        return Some(class);
+    }
+    let chain_continues = is_index(cursor, end + 1)
+        || matches!(
+            cursor.symbol(end + 1),
+            Some(Symbol::LeftParen) | Some(Symbol::LeftBracket)
+        );
+    if chain_continues {
+        return None;
+    }
+    let local = bound_local(cursor, start)?;
+    (end + 1..cursor.len()).find_map(|j| {
+        let is_local = cursor.identifier(j) == Some(local)
+            && !(j > 0 && is_index(cursor, j - 1));
+        if is_local {
+            remote_member(cursor, j + 1)
+        } else {
+            None
+        }
+    })
+}
+
+/// Class implied by `.Member`/`:Member` at `i`
+fn remote_member(cursor: &TokenCursor, i: usize) -> Option<&'static str> {
+    if !is_index(cursor, i) {
+        return None;
+    }
+    let member = cursor.identifier(i + 1)?;
+    REMOTE_MEMBERS
+        .iter()
+        .find(|(name, _)| *name == member)
+        .map(|(_, class)| *class)
+}
+
+/// First token of the index/call chain ending at `i` (`game.RS:Wait(...)`)
+fn chain_start(cursor: &TokenCursor, i: usize) -> usize {
+    let mut start = i;
+    while start >= 2 && is_index(cursor, start - 1) {
+        let previous = start - 2;
+        if cursor.identifier(previous).is_some() {
+            start = previous;
+        } else if cursor.symbol(previous) == Some(Symbol::RightParen) {
+            match opening_paren(cursor, previous) {
+                // A call: continue from its callee
+                Some(open) if open > 0 && cursor.identifier(open - 1).is_some() => start = open - 1,
+                Some(open) => return open,
+                None => break,
+            }
+        } else {
+            break;
+        }
+    }
+    start
+}
+
+/// `x` in `[local] x = <chain starting at start>`
+fn bound_local<'a>(cursor: &TokenCursor<'a>, start: usize) -> Option<&'a str> {
+    if start < 2 || cursor.symbol(start - 1) != Some(Symbol::Equal) {
+        return None;
+    }
+    let local = cursor.identifier(start - 2)?;
+    let field = start >= 3 && is_index(cursor, start - 3);
+    (!field).then_some(local)
+}
+
+/// Whether token `i` is `.` or `:`
+fn is_index(cursor: &TokenCursor, i: usize) -> bool {
+    matches!(cursor.symbol(i), Some(Symbol::Dot) | Some(Symbol::Colon))
+}
+
+/// Index of the `(` opened by the `)` at `close`
+fn opening_paren(cursor: &TokenCursor, close: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for j in (0..=close).rev() {
+        match cursor.symbol(j) {
+            Some(Symbol::RightParen) => depth += 1,
+            Some(Symbol::LeftParen) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(j);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the `)` closing the `(` at `open`
+fn closing_paren(cursor: &TokenCursor, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for j in open..cursor.len() {
+        match cursor.symbol(j) {
+            Some(Symbol::LeftParen) => depth += 1,
+            Some(Symbol::RightParen) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(j);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: &str = "local RS = game:GetService(\"ReplicatedStorage\")\n\
+                          local hit = Instance.new(\"RemoteEvent\")\n\
+                          hit.Name = \"DealDamage\"\n\
+                          hit.Parent = RS\n\
+                          hit.OnServerEvent:Connect(function(player, amount) end)\n\
+                          local shop = RS:WaitForChild(\"Remotes\"):WaitForChild(\"BuyItem\")\n\
+                          shop.OnServerInvoke = function(player, item) return true end\n";
+
+    const CLIENT: &str = "local RS = game:GetService(\"ReplicatedStorage\")\n\
+                          local hum = script.Parent:WaitForChild(\"Humanoid\")\n\
+                          RS:WaitForChild(\"DealDamage\"):FireServer(10)\n\
+                          local ok = RS.Remotes.BuyItem:InvokeServer(\"sword\")\n";
+
+    #[test]
+    fn test_remote_usage_needs_remote_use() {
+        let mut usage = RemoteUsage::default();
+        remote_usage(SERVER, 0, &mut usage).unwrap();
+        remote_usage(CLIENT, 1, &mut usage).unwrap();
+
+        let remotes: Vec<String> = usage.remotes().into_iter().collect();
+        assert_eq!(remotes, ["BuyItem", "DealDamage"]);
+        assert_eq!(usage.names["DealDamage"].class, Some("RemoteEvent"));
+        assert_eq!(usage.names["BuyItem"].class, Some("RemoteFunction"));
+        assert_eq!(usage.names["DealDamage"].files.len(), 2);
+        // Looked up, never used as a remote
+        assert!(!usage.names["Humanoid"].remote);
+        assert!(!usage.names["Remotes"].remote);
+    }
+
+    #[test]
+    fn test_rename_remotes_everywhere() {
+        let mapping: HashMap<String, String> = [
+            ("DealDamage".to_string(), "_0x0".to_string()),
+            ("BuyItem".to_string(), "_0x1".to_string()),
+        ]
+        .into();
+
+        let server = rename_remotes(SERVER, &mapping).unwrap();
+        assert!(server.contains("hit.Name = \"_0x0\""));
+        assert!(server.contains(":WaitForChild(\"Remotes\"):WaitForChild(\"_0x1\")"));
+
+        let client = rename_remotes(CLIENT, &mapping).unwrap();
+        assert!(client.contains("RS:WaitForChild(\"_0x0\"):FireServer(10)"));
+        assert!(client.contains("RS.Remotes._0x1:InvokeServer(\"sword\")"));
+        assert!(client.contains("WaitForChild(\"Humanoid\")"));
+    }
+}
@@ -0,0 +1,277 @@
+//! Rojo project layout (`default.project.json`)
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Project file Rojo looks for when given a directory
+pub const DEFAULT_PROJECT_FILE: &str = "default.project.json";
+
+/// Script class a source file is synced as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScriptKind {
+    /// `Name.lua` / `init.lua` → ModuleScript
+    Module,
+    /// `Name.server.lua` → Script
+    Server,
+    /// `Name.client.lua` → LocalScript
+    Client,
+}
+
+/// A script in the project tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFile {
+    /// Instance names from the DataModel root (e.g. `ReplicatedStorage.Shared.Util`)
+    pub instance_path: Vec<String>,
+    /// Path relative to the project root
+    pub relative_path: PathBuf,
+    pub kind: ScriptKind,
+}
+
+impl ProjectFile {
+    /// Dotted instance path for display
+    pub fn display_path(&self) -> String {
+        self.instance_path.join(".")
+    }
+}
+
+/// A Rojo project and the scripts it syncs
+#[derive(Debug, Clone)]
+pub struct RojoProject {
+    pub name: String,
+    /// Directory containing the project file
+    pub root: PathBuf,
+    /// The project file itself (the folder, for `from_folder`)
+    pub project_file: PathBuf,
+    /// Scripts sorted by relative path
+    pub files: Vec<ProjectFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectManifest {
+    name: String,
+    tree: serde_json::Value,
+}
+
+impl RojoProject {
+    /// Load a project from a `*.project.json` file or a directory containing one
+    pub fn load(path: &Path) -> Result<Self> {
+        let project_file = if path.is_dir() {
+            path.join(DEFAULT_PROJECT_FILE)
+        } else {
+            path.to_path_buf()
+        };
+        let root = project_file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let content = fs::read_to_string(&project_file)
+            .with_context(|| format!("Failed to read Rojo project: {:?}", project_file))?;
+        let manifest: ProjectManifest = serde_json::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid Rojo project {:?}: {}", project_file, e))
+        })?;
+
+        // A DataModel tree has no instance name of its own; library projects
+        // are mounted under the project name
+        let is_place = manifest.tree.get("$className").and_then(|v| v.as_str()) == Some("DataModel");
+        let base = if is_place {
+            Vec::new()
+        } else {
+            vec![manifest.name.clone()]
+        };
+
+        let mut files = Vec::new();
+        Self::walk_tree(&root, &manifest.tree, base, &mut files)?;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        Ok(Self {
+            name: manifest.name,
+            root,
+            project_file,
+            files,
+        })
+    }
+
+    /// Treat a plain folder as one instance named after it
+    ///
+    /// Subfolders become Folders and scripts are named as Rojo would name
+    /// them, so `script.Parent.Util` requires resolve the same way.
+    pub fn from_folder(path: &Path) -> Result<Self> {
+        let name = path
+            .canonicalize()
+            .with_context(|| format!("Failed to read directory: {:?}", path))?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Folder".to_string());
+
+        let mut files = Vec::new();
+        Self::collect_path(path, Path::new(""), vec![name.clone()], &mut files)?;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        Ok(Self {
+            name,
+            root: path.to_path_buf(),
+            project_file: path.to_path_buf(),
+            files,
+        })
+    }
+
+    /// Absolute path of a project file on disk
+    pub fn source_path(&self, file: &ProjectFile) -> PathBuf {
+        self.root.join(&file.relative_path)
+    }
+
+    fn walk_tree(
+        root: &Path,
+        node: &serde_json::Value,
+        instance_path: Vec<String>,
+        files: &mut Vec<ProjectFile>,
+    ) -> Result<()> {
+        let Some(object) = node.as_object() else {
+            return Ok(());
+        };
+
+        if let Some(path) = object.get("$path").and_then(|v| v.as_str()) {
+            Self::collect_path(root, Path::new(path), instance_path.clone(), files)?;
+        }
+
+        for (key, child) in object {
+            if key.starts_with('$') {
+                continue;
+            }
+            let mut child_path = instance_path.clone();
+            child_path.push(key.clone());
+            Self::walk_tree(root, child, child_path, files)?;
+        }
+
+        Ok(())
+    }
+
+    fn collect_path(
+        root: &Path,
+        relative: &Path,
+        instance_path: Vec<String>,
+        files: &mut Vec<ProjectFile>,
+    ) -> Result<()> {
+        let full = root.join(relative);
+
+        if full.is_dir() {
+            let mut entries = fs::read_dir(&full)
+                .with_context(|| format!("Failed to read directory: {:?}", full))?
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort_by_key(|e| e.file_name());
+
+            for entry in entries {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let child_relative = relative.join(&name);
+
+                if entry.path().is_dir() {
+                    let mut child_path = instance_path.clone();
+                    child_path.push(name);
+                    Self::collect_path(root, &child_relative, child_path, files)?;
+                } else if let Some((stem, kind)) = script_name(&name) {
+                    // init scripts become the folder instance itself
+                    let mut child_path = instance_path.clone();
+                    if stem != "init" {
+                        child_path.push(stem);
+                    }
+                    files.push(ProjectFile {
+                        instance_path: child_path,
+                        relative_path: child_relative,
+                        kind,
+                    });
+                }
+            }
+        } else if full.is_file() {
+            let file_name = relative
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            // A file mapped directly takes the name of its tree node
+            if let Some((_, kind)) = script_name(&file_name) {
+                files.push(ProjectFile {
+                    instance_path,
+                    relative_path: relative.to_path_buf(),
+                    kind,
+                });
+            }
+        } else {
+            return Err(ObfuscatorError::ConfigError(format!(
+                "Rojo $path does not exist: {:?}",
+                full
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Split `Name.server.luau` into `("Name", ScriptKind::Server)`
+fn script_name(file_name: &str) -> Option<(String, ScriptKind)> {
+    let stem = file_name
+        .strip_suffix(".luau")
+        .or_else(|| file_name.strip_suffix(".lua"))?;
+
+    let (stem, kind) = if let Some(s) = stem.strip_suffix(".server") {
+        (s, ScriptKind::Server)
+    } else if let Some(s) = stem.strip_suffix(".client") {
+        (s, ScriptKind::Client)
+    } else {
+        (stem, ScriptKind::Module)
+    };
+
+    Some((stem.to_string(), kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_name() {
+        assert_eq!(script_name("Util.lua"), Some(("Util".to_string(), ScriptKind::Module)));
+        assert_eq!(script_name("Main.server.luau"), Some(("Main".to_string(), ScriptKind::Server)));
+        assert_eq!(script_name("init.client.lua"), Some(("init".to_string(), ScriptKind::Client)));
+        assert_eq!(script_name("README.md"), None);
+    }
+
+    #[test]
+    fn test_load_project_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/shared/Net")).unwrap();
+        fs::create_dir_all(root.join("src/server")).unwrap();
+        fs::write(root.join("src/shared/Util.lua"), "return {}").unwrap();
+        fs::write(root.join("src/shared/Net/init.lua"), "return {}").unwrap();
+        fs::write(root.join("src/server/Main.server.lua"), "print(1)").unwrap();
+        fs::write(
+            root.join(DEFAULT_PROJECT_FILE),
+            r#"{
+                "name": "Game",
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": { "Shared": { "$path": "src/shared" } },
+                    "ServerScriptService": { "Server": { "$path": "src/server" } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let project = RojoProject::load(root).unwrap();
+        let paths: Vec<String> = project.files.iter().map(|f| f.display_path()).collect();
+
+        assert_eq!(project.name, "Game");
+        assert!(paths.contains(&"ReplicatedStorage.Shared.Util".to_string()));
+        assert!(paths.contains(&"ReplicatedStorage.Shared.Net".to_string()));
+        assert!(paths.contains(&"ServerScriptService.Server.Main".to_string()));
+
+        let folder = RojoProject::from_folder(&root.join("src/shared")).unwrap();
+        let paths: Vec<String> = folder.files.iter().map(|f| f.display_path()).collect();
+        assert_eq!(paths, ["shared.Net", "shared.Util"]);
+        assert_eq!(folder.files[1].relative_path, Path::new("Util.lua"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Detectability scoring of protected output
+//!
+//! Some anti-cheat tools and marketplace reviewers reject scripts that look
+//! obviously obfuscated. `DetectabilityAnalyzer` scores what such a reviewer
+//! sees: how much of the script is high-entropy encoded data, how many calls
+//! go through the string decryptor and how many identifiers follow a
+//! machine-generated pattern, then suggests settings that look less like it.
+
+use super::diff::shannon_entropy;
+use super::{identifier, CODE_MARKER};
+use crate::obfuscation::POOL_ACCESSOR;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Lines above this many bits per byte read as encoded data, not code
+pub const HIGH_ENTROPY_BITS: f64 = 5.0;
+
+/// Weights of the three signals in the score (sum to 100)
+const ENTROPY_WEIGHT: f64 = 35.0;
+const DECRYPT_WEIGHT: f64 = 30.0;
+const IDENTIFIER_WEIGHT: f64 = 35.0;
+
+/// Generated identifier patterns, by the naming style that produces them
+const IDENTIFIER_PATTERNS: &[(&str, &str)] = &[
+    ("hex", r"^_0x[0-9a-f]+$"),
+    ("random", r"^_[a-z0-9]{8}$"),
+    ("emoji", r"^_[A-Za-z]_[A-Za-z](_[A-Za-z]_[A-Za-z])*$"),
+    ("confusable", r"^[Il][Il1]{4,}$"),
+];
+
+/// Names the runtime itself declares, recognizable in any protected script
+const RUNTIME_NAMES: &[&str] = &[
+    "_decrypt",
+    "_decompress",
+    "_b85decode",
+    "_unshuffle",
+    "_encrypted_data",
+    "_K_pool",
+];
+
+/// Identifiers matching one generated pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternHit {
+    pub pattern: &'static str,
+    pub count: usize,
+}
+
+/// How obviously obfuscated a script looks
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DetectabilityReport {
+    /// 0 (looks hand-written) to 100 (unmistakably obfuscated)
+    pub score: u8,
+    /// Mean bits per byte over non-blank lines
+    pub mean_entropy: f64,
+    pub lines: usize,
+    /// Lines above `HIGH_ENTROPY_BITS`
+    pub high_entropy_lines: usize,
+    /// Call sites in the protected code
+    pub calls: usize,
+    /// Calls to `_decrypt` or the constant pool accessor
+    pub decrypt_calls: usize,
+    /// Distinct identifiers in the protected code
+    pub identifiers: usize,
+    /// Distinct identifiers matching a generated pattern
+    pub suspicious_identifiers: usize,
+    pub patterns: Vec<PatternHit>,
+    pub recommendations: Vec<String>,
+}
+
+impl DetectabilityReport {
+    pub fn decrypt_call_ratio(&self) -> f64 {
+        ratio(self.decrypt_calls, self.calls)
+    }
+
+    pub fn high_entropy_ratio(&self) -> f64 {
+        ratio(self.high_entropy_lines, self.lines)
+    }
+
+    pub fn suspicious_identifier_ratio(&self) -> f64 {
+        ratio(self.suspicious_identifiers, self.identifiers)
+    }
+
+    fn pattern_count(&self, pattern: &str) -> usize {
+        self.patterns
+            .iter()
+            .find(|hit| hit.pattern == pattern)
+            .map_or(0, |hit| hit.count)
+    }
+}
+
+/// Scores protected scripts
+pub struct DetectabilityAnalyzer {
+    patterns: Vec<(&'static str, Regex)>,
+}
+
+impl Default for DetectabilityAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DetectabilityAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            patterns: IDENTIFIER_PATTERNS
+                .iter()
+                .map(|(name, pattern)| (*name, Regex::new(pattern).expect("valid pattern")))
+                .collect(),
+        }
+    }
+
+    pub fn analyze(&self, protected: &str) -> Result<DetectabilityReport> {
+        let all_tokens = tokens(protected)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia())
+            .collect();
+
+        // Identifiers and calls only count in the code section; the runtime
+        // is the same in every script and scored through its data tables
+        let code_start = protected
+            .find(CODE_MARKER)
+            .map(|i| i + CODE_MARKER.len())
+            .unwrap_or(0);
+        let code: Vec<&Token> = significant
+            .iter()
+            .copied()
+            .filter(|t| t.start_position().bytes() >= code_start)
+            .collect();
+
+        let mut report = DetectabilityReport::default();
+
+        let entropies: Vec<f64> = protected
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("--"))
+            .map(|line| shannon_entropy(line.as_bytes()))
+            .collect();
+        report.lines = entropies.len();
+        report.high_entropy_lines = entropies.iter().filter(|&&e| e > HIGH_ENTROPY_BITS).count();
+        if !entropies.is_empty() {
+            report.mean_entropy = entropies.iter().sum::<f64>() / entropies.len() as f64;
+        }
+
+        for (i, token) in code.iter().enumerate() {
+            let Some(name) = identifier(token) else {
+                continue;
+            };
+            if is_call(code.get(i + 1)) {
+                report.calls += 1;
+                if name == "_decrypt" || name == POOL_ACCESSOR {
+                    report.decrypt_calls += 1;
+                }
+            }
+        }
+
+        let names: BTreeSet<&str> = code.iter().filter_map(|t| identifier(t)).collect();
+        report.identifiers = names.len();
+        for (pattern, regex) in &self.patterns {
+            let count = names.iter().filter(|name| regex.is_match(name)).count();
+            if count > 0 {
+                report.patterns.push(PatternHit {
+                    pattern: *pattern,
+                    count,
+                });
+            }
+        }
+        report.suspicious_identifiers = names
+            .iter()
+            .filter(|name| self.patterns.iter().any(|(_, regex)| regex.is_match(name)))
+            .count();
+        let runtime_names = significant
+            .iter()
+            .filter_map(|t| identifier(t))
+            .filter(|name| RUNTIME_NAMES.contains(name))
+            .collect::<BTreeSet<_>>()
+            .len();
+        if runtime_names > 0 {
+            report.patterns.push(PatternHit {
+                pattern: "runtime",
+                count: runtime_names,
+            });
+        }
+
+        let score = ENTROPY_WEIGHT * report.high_entropy_ratio()
+            + DECRYPT_WEIGHT * report.decrypt_call_ratio()
+            + IDENTIFIER_WEIGHT * report.suspicious_identifier_ratio();
+        report.score = score.round().clamp(0.0, 100.0) as u8;
+        report.recommendations = recommendations(&report);
+
+        Ok(report)
+    }
+}
+
+/// Settings that would lower the score, most effective first
+fn recommendations(report: &DetectabilityReport) -> Vec<String> {
+    let mut advice = Vec::new();
+
+    if report.suspicious_identifier_ratio() > 0.2 {
+        let style = ["hex", "random", "emoji", "confusable"]
+            .into_iter()
+            .max_by_key(|pattern| report.pattern_count(pattern))
+            .unwrap_or("hex");
+        advice.push(format!(
+            "{:.0}% of identifiers look generated ({} style); use \
+             `--naming-style dictionary` for ordinary-looking names",
+            report.suspicious_identifier_ratio() * 100.0,
+            style
+        ));
+    }
+    if report.decrypt_call_ratio() > 0.3 {
+        advice.push(format!(
+            "{:.0}% of calls decrypt a constant; a lower tier or narrower \
+             `sensitivity_rules` encrypt only the strings worth hiding",
+            report.decrypt_call_ratio() * 100.0
+        ));
+    }
+    if report.high_entropy_ratio() > 0.1 {
+        advice.push(format!(
+            "{} lines are encoded data; `--payload-url` keeps encrypted \
+             strings out of the script",
+            report.high_entropy_lines
+        ));
+    }
+
+    advice
+}
+
+/// Whether the token after an identifier starts a call
+fn is_call(next: Option<&&Token>) -> bool {
+    match next.map(|t| t.token_type()) {
+        Some(TokenType::Symbol { symbol }) => *symbol == Symbol::LeftParen,
+        Some(TokenType::StringLiteral { .. }) => true,
+        _ => false,
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_names_score_higher_than_plain_code() {
+        let analyzer = DetectabilityAnalyzer::new();
+        let obvious = format!(
+            "local _encrypted_data = {{\n    [1] = {{ct = \"q83vEjRWeJq8A+xH/w1k2Q==\", nonce = \"AAECAwQFBgcICQoL\"}},\n}}\n\
+             {}\n\
+             local _0x0 = _decrypt(1)\n\
+             local _0x1 = _K(2)\n\
+             print(_0x0, _0x1)\n",
+            CODE_MARKER
+        );
+        let plain = format!(
+            "{}\nlocal cachedHandler = _decrypt(1)\nlocal liveCount = 3\n\
+             print(cachedHandler, liveCount)\nwarn(cachedHandler)\n",
+            CODE_MARKER
+        );
+
+        let obvious = analyzer.analyze(&obvious).unwrap();
+        let plain = analyzer.analyze(&plain).unwrap();
+
+        assert_eq!(obvious.decrypt_calls, 2);
+        assert_eq!(obvious.calls, 3);
+        assert_eq!(obvious.pattern_count("hex"), 2);
+        assert!(obvious.score > plain.score);
+        assert!(obvious
+            .recommendations
+            .iter()
+            .any(|r| r.contains("--naming-style dictionary")));
+        assert_eq!(plain.suspicious_identifiers, 0);
+    }
+}
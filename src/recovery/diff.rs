@@ -0,0 +1,231 @@
+//! Pre-ship audit of a protected script against its original
+//!
+//! Compares what an attacker can read in the protected code with the
+//! original: string literals still in plaintext, identifiers that kept their
+//! names and how random each line looks. A sensitive literal in plaintext
+//! is a leak (e.g. a string a `--!obfuscate: none` directive or the tier's
+//! sensitivity threshold let through).
+
+use super::{identifier, CODE_MARKER};
+use crate::parser::{string_value, ParseResult, Sensitivity};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Token};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+
+/// Literals shorter than this are too common to say anything about the source
+const MIN_LITERAL_LEN: usize = 3;
+
+/// An original string literal that appears verbatim in the protected code
+#[derive(Debug, Clone, Serialize)]
+pub struct SurvivingLiteral {
+    pub value: String,
+    /// Line in the original script
+    pub line: usize,
+    pub sensitivity: Sensitivity,
+}
+
+/// Shannon entropy of one line of protected code
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LineEntropy {
+    pub line: usize,
+    /// Bits per byte (0 to 8)
+    pub entropy: f64,
+}
+
+/// Differences between an original and a protected script
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffReport {
+    /// String literals in the original, long enough to check
+    pub literals: usize,
+    pub surviving_literals: Vec<SurvivingLiteral>,
+    /// Distinct identifiers in the original
+    pub identifiers: usize,
+    /// Original identifiers still present in the protected code, sorted
+    /// (engine globals and property names are expected here)
+    pub retained_identifiers: Vec<String>,
+    /// Mean line entropy of the original
+    pub original_entropy: f64,
+    /// Mean line entropy of the protected code
+    pub protected_entropy: f64,
+    /// Per non-blank line of the protected code
+    pub line_entropy: Vec<LineEntropy>,
+}
+
+impl DiffReport {
+    /// Surviving literals classified as sensitive
+    pub fn leaks(&self) -> impl Iterator<Item = &SurvivingLiteral> {
+        self.surviving_literals
+            .iter()
+            .filter(|l| matches!(l.sensitivity, Sensitivity::High | Sensitivity::Medium))
+    }
+
+    /// Share of original identifiers that were renamed, 0 to 1
+    pub fn renamed_ratio(&self) -> f64 {
+        if self.identifiers == 0 {
+            return 1.0;
+        }
+        1.0 - self.retained_identifiers.len() as f64 / self.identifiers as f64
+    }
+}
+
+/// Compares an original script with its protected output
+pub struct ScriptDiff<'a> {
+    original: &'a str,
+    parse_result: &'a ParseResult,
+}
+
+impl<'a> ScriptDiff<'a> {
+    /// `parse_result` must come from parsing `original`
+    pub fn new(original: &'a str, parse_result: &'a ParseResult) -> Self {
+        Self {
+            original,
+            parse_result,
+        }
+    }
+
+    pub fn compare(&self, protected: &str) -> Result<DiffReport> {
+        // Only the protected code section; the runtime has strings of its own
+        let code = protected
+            .find(CODE_MARKER)
+            .map(|i| &protected[i + CODE_MARKER.len()..])
+            .unwrap_or(protected);
+
+        let protected_tokens = tokenize(code)?;
+        let protected_strings: HashSet<String> = protected_tokens
+            .iter()
+            .filter_map(|t| string_value(t))
+            .collect();
+        let protected_names: HashSet<&str> =
+            protected_tokens.iter().filter_map(|t| identifier(t)).collect();
+
+        let candidates: Vec<_> = self
+            .parse_result
+            .strings
+            .iter()
+            .filter(|s| s.value.chars().count() >= MIN_LITERAL_LEN)
+            .collect();
+        let surviving_literals = candidates
+            .iter()
+            .filter(|s| protected_strings.contains(&s.value))
+            .map(|s| SurvivingLiteral {
+                value: s.value.clone(),
+                line: s.line,
+                sensitivity: s.sensitivity,
+            })
+            .collect();
+
+        let original_tokens = tokenize(self.original)?;
+        let original_names: BTreeSet<&str> =
+            original_tokens.iter().filter_map(|t| identifier(t)).collect();
+        let retained_identifiers = original_names
+            .iter()
+            .filter(|name| protected_names.contains(*name))
+            .map(|name| name.to_string())
+            .collect();
+
+        let line_entropy = line_entropy(code);
+        Ok(DiffReport {
+            literals: candidates.len(),
+            surviving_literals,
+            identifiers: original_names.len(),
+            retained_identifiers,
+            original_entropy: mean(&line_entropy_of(self.original)),
+            protected_entropy: mean(&line_entropy.iter().map(|l| l.entropy).collect::<Vec<_>>()),
+            line_entropy,
+        })
+    }
+}
+
+/// Shannon entropy of `bytes` in bits per byte
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn line_entropy(code: &str) -> Vec<LineEntropy> {
+    code.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| LineEntropy {
+            line: i + 1,
+            entropy: shannon_entropy(line.trim().as_bytes()),
+        })
+        .collect()
+}
+
+fn line_entropy_of(code: &str) -> Vec<f64> {
+    line_entropy(code).into_iter().map(|l| l.entropy).collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn tokenize(code: &str) -> Result<Vec<Token>> {
+    Ok(tokens(code)
+        .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?
+        .into_iter()
+        .filter(|t| !t.token_type().is_trivia())
+        .collect())
+}
+
+/// Unquoted value of a string literal token
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LuauParser;
+
+    #[test]
+    fn test_diff_flags_plaintext_leaks_and_kept_names() {
+        let original = "local apiKey = \"sk_live_abcdef123456\"\n\
+                        local greeting = \"Hello there\"\n\
+                        print(greeting, apiKey)\n";
+        let parse_result = LuauParser::new().parse(original).unwrap();
+        let protected = format!(
+            "local _encrypted_data = {{}}\n{}\n\
+             local _0x1 = _decrypt(1)\n\
+             local greeting = \"Hello there\"\n\
+             print(greeting, _0x1)\n",
+            CODE_MARKER
+        );
+
+        let report = ScriptDiff::new(original, &parse_result)
+            .compare(&protected)
+            .unwrap();
+
+        assert_eq!(report.literals, 2);
+        assert_eq!(report.surviving_literals.len(), 1);
+        assert_eq!(report.surviving_literals[0].value, "Hello there");
+        assert_eq!(report.retained_identifiers, vec!["greeting", "print"]);
+        assert_eq!(report.identifiers, 3);
+        assert!(report.line_entropy.iter().all(|l| l.entropy > 0.0));
+    }
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert_eq!(shannon_entropy(b""), 0.0);
+        assert_eq!(shannon_entropy(b"aaaa"), 0.0);
+        assert!((shannon_entropy(b"abab") - 1.0).abs() < 1e-9);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((shannon_entropy(&all) - 8.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,323 @@
+//! Luau test harness for a protected build
+//!
+//! `gen-tests` writes a Script for Studio's TestService that runs the
+//! bundled ChaCha20 runtime (the one `validate` checks the build embeds)
+//! over every entry of the build's string table and constant pool, and
+//! compares each result against a hash of the plaintext, so the harness
+//! never contains the strings themselves. When the build has a license
+//! stub, it also runs it against a stubbed `HttpService`. Results are
+//! printed as `[PASS]`/`[FAIL]` lines and any failure raises an error.
+//!
+//! The harness embeds the decryption key: it is for checking a build before
+//! shipping and must never be shipped with it.
+
+use super::{identifier, script_encoding, script_key_version, table_entries, SymbolMap};
+use crate::codegen::{BinaryEncoding, RuntimeGenerator, DECOMPRESS_FUNCTION};
+use crate::crypto::{Compression, CryptoContext};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use base64::Engine;
+use full_moon::tokenizer::{tokens, Token};
+
+/// Marker line opening the license stub in assembled output
+const LICENSE_MARKER: &str = "-- [LICENSE] License Validation";
+
+/// One decryption check
+struct HarnessCase {
+    name: String,
+    /// `ct = ..., nonce = ...[, z = ...]` as written in the build
+    fields: String,
+    /// FNV-1a of the expected plaintext
+    hash: u32,
+}
+
+/// A generated harness and what it checks
+#[derive(Debug, Clone)]
+pub struct GeneratedHarness {
+    pub code: String,
+    /// Embedded strings checked
+    pub strings: usize,
+    /// Constant pool entries checked
+    pub constants: usize,
+    /// Whether the license stub is exercised
+    pub license_check: bool,
+}
+
+/// Generates TestService harnesses for protected builds
+pub struct TestHarness<'a> {
+    crypto_ctx: &'a CryptoContext,
+    symbol_map: &'a SymbolMap,
+}
+
+impl<'a> TestHarness<'a> {
+    /// `crypto_ctx` must be derived from the original password and the map's salt
+    pub fn new(crypto_ctx: &'a CryptoContext, symbol_map: &'a SymbolMap) -> Self {
+        Self {
+            crypto_ctx,
+            symbol_map,
+        }
+    }
+
+    /// Harness for `protected`
+    ///
+    /// Fails when an entry doesn't decrypt here (wrong password or symbol
+    /// map), since the harness would then only report false failures.
+    pub fn generate(&self, protected: &str) -> Result<GeneratedHarness> {
+        let all_tokens = tokens(protected)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia())
+            .collect();
+
+        let key_version = script_key_version(protected);
+        let encoding = script_encoding(protected);
+        let mut compression = Compression::None;
+        let mut cases = |table: &str, label: &str| {
+            self.cases(&significant, table, label, key_version, encoding, &mut compression)
+        };
+        let strings = cases("_encrypted_data", "string")?;
+        let constants = cases("_K_pool", "constant")?;
+        let license = license_section(protected);
+
+        let runtime = RuntimeGenerator::new();
+        let mut code = format!(
+            "-- Test harness for {} (generated by luau-obfuscator gen-tests)\n",
+            self.symbol_map.script_id
+        );
+        code.push_str("-- Put this Script in TestService and run the game in Studio.\n");
+        code.push_str("-- It contains the decryption key: never ship it with the build.\n\n");
+        code.push_str(HARNESS_PRELUDE);
+
+        let key = base64::engine::general_purpose::STANDARD.encode(self.master_key());
+        code.push_str(&format!("\nlocal _key = \"{}\"\n", key));
+        code.push_str("local ChaCha20 = (function()\n");
+        code.push_str(&runtime.generate()?);
+        code.push_str("\nend)()\n\n");
+        if let Some(decoder) = runtime.generate_decoder(encoding)? {
+            code.push_str(&decoder);
+            code.push_str("\n\n");
+        }
+        if let Some(decompressor) = runtime.generate_decompressor(compression)? {
+            code.push_str(&decompressor);
+            code.push_str("\n\n");
+        }
+
+        code.push_str("local _cases = {\n");
+        for case in strings.iter().chain(&constants) {
+            code.push_str(&format!(
+                "    {{name = \"{}\", {}, hash = 0x{:08x}}},\n",
+                case.name, case.fields, case.hash
+            ));
+        }
+        code.push_str("}\n\n");
+        code.push_str("for _, data in ipairs(_cases) do\n");
+        code.push_str("    local ok, value = pcall(function()\n");
+        code.push_str(&format!("        local value = {}\n", encoding.render_decrypt("data")));
+        if !compression.is_none() {
+            code.push_str(&format!(
+                "        if data.z then value = {}(value) end\n",
+                DECOMPRESS_FUNCTION
+            ));
+        }
+        code.push_str("        return value\n");
+        code.push_str("    end)\n");
+        code.push_str("    if not ok then\n");
+        code.push_str("        _check(data.name, false, tostring(value))\n");
+        code.push_str("    else\n");
+        code.push_str(
+            "        _check(data.name, _fnv1a(value) == data.hash, \"plaintext hash differs\")\n",
+        );
+        code.push_str("    end\n");
+        code.push_str("end\n\n");
+
+        if let Some(license) = &license {
+            code.push_str("-- License stub against a validator that accepts the key\n");
+            code.push_str("local _license = (function(game, spawn, wait)\n");
+            code.push_str(license);
+            code.push_str("\nend)(_stub_game, function() end, function() end)\n");
+            code.push_str(HARNESS_LICENSE_CHECK);
+            code.push('\n');
+        }
+
+        code.push_str(HARNESS_SUMMARY);
+
+        Ok(GeneratedHarness {
+            code,
+            strings: strings.len(),
+            constants: constants.len(),
+            license_check: license.is_some(),
+        })
+    }
+
+    /// Checks for every entry of the `table` table
+    fn cases(
+        &self,
+        tokens: &[&Token],
+        table: &str,
+        label: &str,
+        key_version: u32,
+        encoding: BinaryEncoding,
+        compression: &mut Compression,
+    ) -> Result<Vec<HarnessCase>> {
+        table_entries(tokens, table)
+            .iter()
+            .map(|entry| {
+                let plaintext = entry
+                    .encrypted(key_version, encoding)
+                    .and_then(|encrypted| {
+                        if !encrypted.compression.is_none() {
+                            *compression = encrypted.compression;
+                        }
+                        self.crypto_ctx.decrypt(&encrypted).ok()
+                    })
+                    .ok_or_else(|| {
+                        ObfuscatorError::CryptoError(format!(
+                            "{} {} does not decrypt (wrong password or symbol map?)",
+                            label, entry.index
+                        ))
+                    })?;
+
+                let mut fields = Vec::new();
+                for name in ["ct", "nonce", "z"] {
+                    if let Some(field) = entry.fields.get(name) {
+                        fields.push(format!("{} = {}", name, field.source()));
+                    }
+                }
+                Ok(HarnessCase {
+                    name: format!("{} {}", label, entry.index),
+                    fields: fields.join(", "),
+                    hash: fnv1a(&plaintext),
+                })
+            })
+            .collect()
+    }
+
+    /// The key `_key` holds in a build without delivered shares
+    fn master_key(&self) -> Vec<u8> {
+        // With no shares to mask it with, the masked key is the key itself
+        self.crypto_ctx.masked_key(&[])
+    }
+}
+
+/// 32-bit FNV-1a, as `_fnv1a` computes it in the harness
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Code of the license stub, up to the next section marker
+fn license_section(protected: &str) -> Option<String> {
+    let start = protected.find(LICENSE_MARKER)? + LICENSE_MARKER.len();
+    let section: Vec<&str> = protected[start..]
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.starts_with("-- ["))
+        .collect();
+    let section = section.join("\n");
+    // The stub is a module: it must end by returning its API
+    tokens(&section)
+        .ok()?
+        .iter()
+        .any(|t| identifier(t) == Some("validate"))
+        .then_some(section)
+}
+
+/// Result helpers, the FNV-1a hash and the stubbed `game`
+const HARNESS_PRELUDE: &str = r#"local _results = {passed = 0, failed = 0}
+
+local function _check(name, ok, detail)
+    if ok then
+        _results.passed += 1
+        print("[PASS] " .. name)
+    else
+        _results.failed += 1
+        warn("[FAIL] " .. name .. (detail and (": " .. detail) or ""))
+    end
+end
+
+-- 32-bit FNV-1a; 16777619 = 2^24 + 403 keeps every product exact
+local function _fnv1a(s)
+    local h = 2166136261
+    for i = 1, #s do
+        h = bit32.bxor(h, string.byte(s, i))
+        h = (h * 403 + bit32.lshift(h, 24)) % 4294967296
+    end
+    return h
+end
+
+-- Stand-in for the license API: records requests and accepts the key
+local _stub_http = {requests = {}}
+function _stub_http:UrlEncode(value)
+    return value
+end
+function _stub_http:GetAsync(url)
+    table.insert(self.requests, url)
+    return "valid"
+end
+function _stub_http:JSONDecode(body)
+    return {valid = body == "valid"}
+end
+
+local _stub_game = {PlaceId = game.PlaceId}
+function _stub_game:GetService(name)
+    if name == "HttpService" then
+        return _stub_http
+    elseif name == "Players" then
+        return {LocalPlayer = {UserId = 1}}
+    end
+    return game:GetService(name)
+end
+"#;
+
+/// Checks run on the license stub
+const HARNESS_LICENSE_CHECK: &str = r#"do
+    local ok, valid = pcall(_license.validate)
+    _check("license accepts a valid key", ok and valid == true, tostring(valid))
+    local requests = #_stub_http.requests
+    _check("license asks the validator", requests == 1, requests .. " requests")
+    local request = _stub_http.requests[1] or ""
+    _check("license sends the script id", string.find(request, "script=", 1, true) ~= nil, request)
+end
+"#;
+
+/// Summary line, failing the run when any check failed
+const HARNESS_SUMMARY: &str = r#"local passed, failed = _results.passed, _results.failed
+print(string.format("%d passed, %d failed", passed, failed))
+if failed > 0 then
+    error(string.format("%d check(s) failed", failed))
+end
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::tests::{protected_fixture, symbol_map};
+
+    #[test]
+    fn test_fnv1a_matches_reference() {
+        assert_eq!(fnv1a(b""), 0x811c_9dc5);
+        assert_eq!(fnv1a(b"a"), 0xe40c_292c);
+        assert_eq!(fnv1a(b"foobar"), 0xbf9c_f968);
+    }
+
+    #[test]
+    fn test_harness_checks_every_entry() {
+        let ctx = CryptoContext::new("owner_password", None).unwrap();
+        let map = symbol_map(&ctx);
+        let protected = protected_fixture(&ctx);
+
+        let harness = TestHarness::new(&ctx, &map).generate(&protected).unwrap();
+        assert_eq!((harness.strings, harness.constants), (1, 1));
+        assert!(!harness.license_check);
+        assert!(harness.code.contains("local ChaCha20 = (function()"));
+        assert!(harness.code.contains("{name = \"string 1\", ct = \""));
+        assert!(harness.code.contains(&format!("hash = 0x{:08x}", fnv1a(b"42"))));
+        // Only hashes of the plaintext are embedded
+        assert!(!harness.code.contains("Hello"));
+
+        let wrong = CryptoContext::new("guess", Some(&map.salt_bytes().unwrap())).unwrap();
+        assert!(TestHarness::new(&wrong, &map).generate(&protected).is_err());
+    }
+}
@@ -0,0 +1,652 @@
+//! Leak tracing
+//!
+//! Traces scripts collected from leak sites back to the release builds they
+//! were copied from, without the license API. A leaked script can carry
+//! three kinds of evidence:
+//!
+//! - the `-- Watermark:` line, which verifies against the license key the
+//!   build was protected with (from the release's customers CSV);
+//! - the digest of that line, which the release manifest records per build
+//!   and the `-- Metadata:` header repeats, so either line alone names the
+//!   build;
+//! - the build's own code. Every build has its own key and seed, so its
+//!   ciphertext literals, dead code and some names appear in no other
+//!   build; they survive when the header comments were stripped.
+//!
+//! Each piece of evidence has a confidence; a customer's score combines them
+//! as independent signals, per file and across files.
+
+use crate::codegen::{ScriptMetadata, WATERMARK_HEADER};
+use crate::crypto::{Watermark, WatermarkGenerator};
+use crate::release::{Customer, ReleaseManifest};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use full_moon::tokenizer::{tokens, TokenType};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Extensions of files `inspect_dir` reads
+const LEAK_EXTENSIONS: &[&str] = &["lua", "luau", "txt"];
+
+/// Suspects below this confidence are left out of reports
+pub const MIN_CONFIDENCE: f64 = 0.25;
+
+/// A build needs at least this many tokens of its own to be matched by them
+const MIN_DISTINCT_TOKENS: usize = 3;
+
+/// Shorter string literals are too common to tell builds apart
+const MIN_LITERAL_LEN: usize = 4;
+
+/// Why a leaked script points at a customer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Evidence {
+    /// The watermark verifies against the customer's license key
+    Watermark,
+    /// Only the watermark's secondary hash verifies (the rest was edited)
+    PartialWatermark,
+    /// The watermark line is the one the customer's build was written with
+    WatermarkDigest,
+    /// The metadata header names the watermark of the customer's build
+    MetadataDigest,
+    /// `matched` of the `total` identifiers and string literals only the
+    /// customer's build uses appear in the script
+    Fingerprint { matched: usize, total: usize },
+}
+
+impl Evidence {
+    pub fn confidence(&self) -> f64 {
+        match self {
+            Evidence::Watermark | Evidence::WatermarkDigest => 1.0,
+            Evidence::MetadataDigest => 0.9,
+            Evidence::PartialWatermark => 0.75,
+            Evidence::Fingerprint { matched, total } => 0.95 * *matched as f64 / *total as f64,
+        }
+    }
+}
+
+impl std::fmt::Display for Evidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Evidence::Watermark => write!(f, "watermark verifies"),
+            Evidence::PartialWatermark => write!(f, "watermark secondary hash verifies"),
+            Evidence::WatermarkDigest => write!(f, "watermark line matches the build"),
+            Evidence::MetadataDigest => write!(f, "metadata header matches the build"),
+            Evidence::Fingerprint { matched, total } => {
+                write!(f, "{} of {} build-specific tokens", matched, total)
+            }
+        }
+    }
+}
+
+/// A customer one leaked script points at
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suspect {
+    pub customer_id: String,
+    /// 0 to 1
+    pub confidence: f64,
+    pub evidence: Vec<Evidence>,
+}
+
+/// What one leaked script gave away
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeakedScript {
+    pub path: PathBuf,
+    /// Script id named by the watermark or metadata header
+    pub script_id: Option<String>,
+    /// Project root tag of the watermark; files with the same tag come from
+    /// one buyer even when no customer matches
+    pub root_tag: Option<String>,
+    /// Most likely first
+    pub suspects: Vec<Suspect>,
+}
+
+/// A customer and every leaked file that points at them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeakSuspect {
+    pub customer_id: String,
+    /// Confidence combined over `files`
+    pub confidence: f64,
+    pub files: Vec<PathBuf>,
+}
+
+/// Result of tracing a directory of leaked scripts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeakReport {
+    pub scripts: Vec<LeakedScript>,
+    /// Most likely leaker first
+    pub suspects: Vec<LeakSuspect>,
+}
+
+/// One recorded build of a release
+#[derive(Debug, Clone)]
+struct KnownBuild {
+    customer_id: String,
+    watermark_digest: Option<String>,
+    /// Identifiers and string literals of the released script
+    tokens: BTreeSet<String>,
+}
+
+/// Matches leaked scripts against recorded releases
+#[derive(Debug, Clone, Default)]
+pub struct LeakInspector {
+    /// Customer id → license key the build's watermark was made with
+    license_keys: BTreeMap<String, String>,
+    builds: Vec<KnownBuild>,
+}
+
+impl LeakInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify watermarks against these customers' license keys
+    pub fn add_customers(&mut self, customers: &[Customer]) {
+        for customer in customers {
+            self.license_keys
+                .insert(customer.id.clone(), customer.license_key.clone());
+        }
+    }
+
+    /// Record the builds of a release from its `manifest.json` (or the
+    /// release directory); returns the number added
+    ///
+    /// Build scripts are read from where the manifest says or next to it;
+    /// builds whose script is gone are matched by watermark only.
+    pub fn add_manifest(&mut self, path: &Path) -> Result<usize> {
+        let path = if path.is_dir() {
+            path.join("manifest.json")
+        } else {
+            path.to_path_buf()
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read release manifest: {:?}", path))?;
+        let manifest: ReleaseManifest = serde_json::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid release manifest {:?}: {}", path, e))
+        })?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let mut added = 0;
+        for entry in manifest.builds.into_iter().filter(|b| b.error.is_none()) {
+            let script = entry
+                .output
+                .iter()
+                .flat_map(|out| [out.clone(), dir.join(out.file_name().unwrap_or_default())])
+                .find(|candidate| candidate.is_file());
+            let tokens = match script {
+                Some(script) => {
+                    let bytes = std::fs::read(&script)
+                        .with_context(|| format!("Failed to read build: {:?}", script))?;
+                    code_tokens(&String::from_utf8_lossy(&bytes))
+                }
+                None => BTreeSet::new(),
+            };
+            self.builds.push(KnownBuild {
+                customer_id: entry.customer_id,
+                watermark_digest: entry.watermark_digest,
+                tokens,
+            });
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    /// Trace one leaked script
+    pub fn inspect(&self, path: &Path, script: &str) -> LeakedScript {
+        self.inspect_with(path, script, &self.distinct_tokens())
+    }
+
+    /// Trace every `.lua`, `.luau` and `.txt` file under `dir`
+    pub fn inspect_dir(&self, dir: &Path) -> Result<LeakReport> {
+        let mut files = Vec::new();
+        collect_files(dir, &mut files)
+            .with_context(|| format!("Failed to read leak directory: {:?}", dir))?;
+        files.sort();
+
+        let distinct = self.distinct_tokens();
+        let mut scripts = Vec::with_capacity(files.len());
+        for file in files {
+            let bytes = std::fs::read(&file)
+                .with_context(|| format!("Failed to read leaked script: {:?}", file))?;
+            let script = String::from_utf8_lossy(&bytes);
+            scripts.push(self.inspect_with(&file, &script, &distinct));
+        }
+
+        let mut by_customer: BTreeMap<&str, (f64, Vec<PathBuf>)> = BTreeMap::new();
+        for script in &scripts {
+            for suspect in &script.suspects {
+                let (unlikely, files) = by_customer
+                    .entry(&suspect.customer_id)
+                    .or_insert((1.0, Vec::new()));
+                *unlikely *= 1.0 - suspect.confidence;
+                files.push(script.path.clone());
+            }
+        }
+        let mut suspects: Vec<LeakSuspect> = by_customer
+            .into_iter()
+            .map(|(customer_id, (unlikely, files))| LeakSuspect {
+                customer_id: customer_id.to_string(),
+                confidence: 1.0 - unlikely,
+                files,
+            })
+            .collect();
+        suspects.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        Ok(LeakReport { scripts, suspects })
+    }
+
+    fn inspect_with(&self, path: &Path, script: &str, distinct: &[BTreeSet<&str>]) -> LeakedScript {
+        let mut evidence: HashMap<&str, Vec<Evidence>> = HashMap::new();
+
+        let line = header_value(script, WATERMARK_HEADER);
+        let watermark = line.and_then(decode_embedded);
+        if let Some(watermark) = &watermark {
+            let generator = WatermarkGenerator::new();
+            for (customer_id, license_key) in &self.license_keys {
+                if generator.verify(watermark, license_key) {
+                    evidence
+                        .entry(customer_id)
+                        .or_default()
+                        .push(Evidence::Watermark);
+                } else if generator.verify_secondary(watermark, license_key) {
+                    evidence
+                        .entry(customer_id)
+                        .or_default()
+                        .push(Evidence::PartialWatermark);
+                }
+            }
+        }
+
+        let line_digest = line.map(ScriptMetadata::watermark_digest);
+        let metadata = ScriptMetadata::read_unsigned(script);
+        for build in &self.builds {
+            let Some(digest) = &build.watermark_digest else {
+                continue;
+            };
+            if line_digest.as_ref() == Some(digest) {
+                evidence
+                    .entry(&build.customer_id)
+                    .or_default()
+                    .push(Evidence::WatermarkDigest);
+            } else if metadata.as_ref().map(|m| &m.watermark_digest) == Some(digest) {
+                evidence
+                    .entry(&build.customer_id)
+                    .or_default()
+                    .push(Evidence::MetadataDigest);
+            }
+        }
+
+        let tokens = code_tokens(script);
+        for (build, own) in self.builds.iter().zip(distinct) {
+            if own.len() < MIN_DISTINCT_TOKENS {
+                continue;
+            }
+            let matched = own.iter().filter(|token| tokens.contains(**token)).count();
+            if matched > 0 {
+                evidence
+                    .entry(&build.customer_id)
+                    .or_default()
+                    .push(Evidence::Fingerprint {
+                        matched,
+                        total: own.len(),
+                    });
+            }
+        }
+
+        let mut suspects: Vec<Suspect> = evidence
+            .into_iter()
+            .map(|(customer_id, evidence)| Suspect {
+                customer_id: customer_id.to_string(),
+                confidence: 1.0
+                    - evidence
+                        .iter()
+                        .map(|e| 1.0 - e.confidence())
+                        .product::<f64>(),
+                evidence,
+            })
+            .filter(|suspect| suspect.confidence >= MIN_CONFIDENCE)
+            .collect();
+        suspects.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.customer_id.cmp(&b.customer_id))
+        });
+
+        LeakedScript {
+            path: path.to_path_buf(),
+            script_id: watermark
+                .as_ref()
+                .map(|w| w.script_id.clone())
+                .or_else(|| metadata.map(|m| m.script_id)),
+            root_tag: watermark
+                .as_ref()
+                .and_then(|w| w.root_tag().map(str::to_string)),
+            suspects,
+        }
+    }
+
+    /// For each build, the tokens no other build has
+    fn distinct_tokens(&self) -> Vec<BTreeSet<&str>> {
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        for build in &self.builds {
+            for token in &build.tokens {
+                *uses.entry(token).or_insert(0) += 1;
+            }
+        }
+        self.builds
+            .iter()
+            .map(|build| {
+                build
+                    .tokens
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|token| uses[token] == 1)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl LeakReport {
+    /// Self-contained HTML page of the report
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Leak report</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; margin-bottom: 2em; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+             .none { color: #888; }\n\
+             </style>\n</head>\n<body>\n<h1>Leak report</h1>\n",
+        );
+        html.push_str(&format!(
+            "<p>{} file(s) scanned, {} suspect(s).</p>\n",
+            self.scripts.len(),
+            self.suspects.len()
+        ));
+
+        html.push_str("<h2>Suspects</h2>\n");
+        if self.suspects.is_empty() {
+            html.push_str("<p class=\"none\">No file matched a known customer.</p>\n");
+        } else {
+            html.push_str("<table>\n<tr><th>Customer</th><th>Confidence</th><th>Files</th></tr>\n");
+            for suspect in &self.suspects {
+                let files: Vec<String> = suspect
+                    .files
+                    .iter()
+                    .map(|f| escape_html(&f.display().to_string()))
+                    .collect();
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{:.0}%</td><td>{}</td></tr>\n",
+                    escape_html(&suspect.customer_id),
+                    suspect.confidence * 100.0,
+                    files.join("<br>")
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Files</h2>\n<table>\n");
+        html.push_str("<tr><th>File</th><th>Script</th><th>Root tag</th><th>Suspects</th></tr>\n");
+        for script in &self.scripts {
+            let suspects = if script.suspects.is_empty() {
+                "<span class=\"none\">none</span>".to_string()
+            } else {
+                script
+                    .suspects
+                    .iter()
+                    .map(|s| {
+                        let evidence: Vec<String> =
+                            s.evidence.iter().map(ToString::to_string).collect();
+                        format!(
+                            "{} ({:.0}%: {})",
+                            escape_html(&s.customer_id),
+                            s.confidence * 100.0,
+                            escape_html(&evidence.join(", "))
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("<br>")
+            };
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&script.path.display().to_string()),
+                escape_html(script.script_id.as_deref().unwrap_or("-")),
+                escape_html(script.root_tag.as_deref().unwrap_or("-")),
+                suspects
+            ));
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+/// Files under `dir` with a `LEAK_EXTENSIONS` extension
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| LEAK_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Value of the first `prefix` comment line
+fn header_value<'s>(script: &'s str, prefix: &str) -> Option<&'s str> {
+    script
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix(prefix))
+        .map(str::trim)
+}
+
+/// Identifiers and string literals of `script`, comments left out
+///
+/// Leaked copies are often cut off or edited; when they don't tokenize,
+/// every identifier-shaped word is taken instead.
+fn code_tokens(script: &str) -> BTreeSet<String> {
+    match tokens(script) {
+        Ok(tokens) => tokens
+            .iter()
+            .filter_map(|token| match token.token_type() {
+                TokenType::Identifier { identifier } => Some(identifier.to_string()),
+                TokenType::StringLiteral { literal, .. } => {
+                    Some(literal.to_string()).filter(|l| l.len() >= MIN_LITERAL_LEN)
+                }
+                _ => None,
+            })
+            .collect(),
+        Err(_) => {
+            let pattern = Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").expect("valid identifier pattern");
+            pattern
+                .find_iter(script)
+                .map(|m| m.as_str().to_string())
+                .collect()
+        }
+    }
+}
+
+/// Watermark from its `-- Watermark:` line (base64 of its debug form)
+fn decode_embedded(encoded: &str) -> Option<Watermark> {
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let capture = |pattern: &str| -> Option<String> {
+        Regex::new(pattern)
+            .ok()?
+            .captures(&decoded)
+            .map(|c| c[1].to_string())
+    };
+    let bytes = |field: &str| -> Option<Vec<u8>> {
+        let list = capture(&format!(r"{}: \[([0-9, ]*)\]", field))?;
+        list.split(',')
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .map(|b| b.parse().ok())
+            .collect()
+    };
+    let string =
+        |pattern: &str| capture(pattern).map(|s| s.replace("\\\"", "\"").replace("\\\\", "\\"));
+
+    let mut metadata = HashMap::new();
+    for key in ["project", "root_tag"] {
+        if let Some(value) = string(&format!(r#""{}": "((?:[^"\\]|\\.)*)""#, key)) {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+    Some(Watermark {
+        primary_hash: bytes("primary_hash")?,
+        secondary_hash: bytes("secondary_hash")?,
+        timestamp: capture(r"timestamp: (\d+)")?.parse().ok()?,
+        script_id: string(r#"script_id: "((?:[^"\\]|\\.)*)""#)?,
+        version: capture(r"version: (\d+)")?.parse().ok()?,
+        metadata,
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::CodeGenConfig;
+    use crate::crypto::CryptoContext;
+    use crate::pipeline::{ObfuscationPipeline, PipelineSettings};
+    use crate::release::{ManifestEntry, ReleaseMatrix};
+
+    const SOURCE: &str = r#"
+local Shop = {}
+local prices = { sword = 100, shield = 250 }
+local function priceOf(item)
+    return prices[item] or 0
+end
+function Shop.buy(player, item)
+    local cost = priceOf(item)
+    local balance = player.leaderstats.Coins.Value
+    if balance >= cost then
+        player.leaderstats.Coins.Value = balance - cost
+        return true
+    end
+    return false
+end
+return Shop
+"#;
+
+    #[test]
+    fn test_traces_leaks_to_their_builds() {
+        let dir = std::env::temp_dir().join(format!("leak-trace-{}", std::process::id()));
+        let leaks = dir.join("leaks");
+        std::fs::create_dir_all(&leaks).unwrap();
+
+        let customers = vec![
+            Customer {
+                id: "alice".to_string(),
+                license_key: "KEY-ALICE".to_string(),
+                hwid: None,
+                line: 1,
+            },
+            Customer {
+                id: "bob".to_string(),
+                license_key: "KEY-BOB".to_string(),
+                hwid: None,
+                line: 2,
+            },
+        ];
+        let settings = PipelineSettings {
+            seed: Some(11),
+            ..Default::default()
+        };
+        let config = CodeGenConfig {
+            script_id: "shop".to_string(),
+            ..Default::default()
+        };
+        let builds = ReleaseMatrix::new(settings, config)
+            .with_password("pw".to_string())
+            .with_jobs(1)
+            .build(SOURCE, &customers);
+
+        let mut entries = Vec::new();
+        let mut scripts = Vec::new();
+        for (customer, build) in customers.iter().zip(builds) {
+            let build = build.unwrap();
+            // Recorded relative to another working directory
+            let output = dir.join(format!("shop.{}.lua", customer.id));
+            std::fs::write(&output, &build.script).unwrap();
+            entries.push(ManifestEntry {
+                customer_id: customer.id.clone(),
+                hwid: None,
+                seed: build.seed,
+                output: Some(Path::new("dist").join(output.file_name().unwrap())),
+                payload: None,
+                symbol_map: None,
+                watermark_digest: Some(build.watermark_digest),
+                error: None,
+            });
+            scripts.push(build.script);
+        }
+        let manifest = ReleaseManifest {
+            script_id: "shop".to_string(),
+            tier: "standard".to_string(),
+            builds: entries,
+        };
+        std::fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        // Alice's copy as released; Bob's with every header comment removed
+        std::fs::write(leaks.join("alice.lua"), &scripts[0]).unwrap();
+        let stripped: String = scripts[1]
+            .lines()
+            .filter(|line| !line.starts_with("--"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        std::fs::write(leaks.join("bob.txt"), stripped).unwrap();
+        std::fs::write(leaks.join("unrelated.lua"), "print('hello')\n").unwrap();
+
+        let mut inspector = LeakInspector::new();
+        inspector.add_customers(&customers);
+        assert_eq!(inspector.add_manifest(&dir).unwrap(), 2);
+        let report = inspector.inspect_dir(&leaks).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.scripts.len(), 3);
+        let alice = &report.scripts[0];
+        assert_eq!(alice.script_id.as_deref(), Some("shop"));
+        assert_eq!(alice.suspects[0].customer_id, "alice");
+        assert_eq!(alice.suspects[0].confidence, 1.0);
+        assert!(alice.suspects[0].evidence.contains(&Evidence::Watermark));
+        assert!(alice.suspects[0]
+            .evidence
+            .contains(&Evidence::WatermarkDigest));
+        assert!(alice.suspects.iter().all(|s| s.customer_id != "bob"));
+
+        let bob = &report.scripts[1];
+        assert_eq!(bob.script_id, None);
+        assert_eq!(bob.suspects.len(), 1);
+        assert_eq!(bob.suspects[0].customer_id, "bob");
+        assert!(matches!(
+            bob.suspects[0].evidence[..],
+            [Evidence::Fingerprint { .. }]
+        ));
+        assert!(bob.suspects[0].confidence > 0.8);
+
+        assert!(report.scripts[2].suspects.is_empty());
+        assert_eq!(report.suspects[0].customer_id, "alice");
+        assert!(report.to_html().contains("<td>bob</td>"));
+    }
+}
@@ -0,0 +1,661 @@
+//! Owner-side recovery of protected scripts
+//!
+//! Given the original password and the symbol map written at protection
+//! time, decrypts the embedded string table and constant pool, inlines the
+//! plaintext back into the code and reverses identifier renaming. The result
+//! is best-effort readable Luau, not the original source. Rotating the key
+//! (`Rekeyer`), structural validation (`ScriptValidator`), the pre-ship
+//! audit against the original (`ScriptDiff`), detectability scoring
+//! (`DetectabilityAnalyzer`), Luau test harnesses (`TestHarness`) and leak
+//! tracing (`LeakInspector`) read protected scripts the same way.
+
+mod detect;
+mod diff;
+mod harness;
+mod leak;
+mod rekey;
+mod validate;
+
+pub use detect::{DetectabilityAnalyzer, DetectabilityReport, PatternHit, HIGH_ENTROPY_BITS};
+pub use diff::{shannon_entropy, DiffReport, LineEntropy, ScriptDiff, SurvivingLiteral};
+pub use harness::{GeneratedHarness, TestHarness};
+pub use leak::{Evidence, LeakInspector, LeakReport, LeakSuspect, LeakedScript, Suspect};
+pub use rekey::{RekeyedScript, Rekeyer};
+pub use validate::{CheckStatus, ScriptValidator, ValidationCheck, ValidationReport};
+
+use crate::codegen::{
+    BinaryEncoding, TableShuffle, ENCODING_HEADER, KEY_VERSION_HEADER, POOL_ORDER,
+    SHUFFLE_FUNCTION, STRING_ORDER,
+};
+use crate::crypto::{Compression, CryptoContext, EncryptedData, KdfParams};
+use crate::obfuscation::{ObfuscatedScript, POOL_ACCESSOR};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::{Context, Result};
+use full_moon::tokenizer::{tokens, Symbol, Token, TokenType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Current symbol map format version
+pub const SYMBOL_MAP_VERSION: u32 = 1;
+
+/// Marker preceding the protected code in assembled output
+const CODE_MARKER: &str = "-- [CODE] Protected Script";
+
+/// Everything needed to reverse a protection run, besides the password
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolMap {
+    pub version: u32,
+    pub script_id: String,
+    /// Hex-encoded KDF salt
+    pub salt: String,
+    /// Argon2id parameters used with the salt (maps without them predate
+    /// `--kdf-profile` and used the defaults)
+    #[serde(default)]
+    pub kdf: KdfParams,
+    /// Key generation, bumped by every `rekey`
+    #[serde(default)]
+    pub key_version: u32,
+    /// Original identifier → mangled identifier
+    pub names: BTreeMap<String, String>,
+    /// Original table key → mangled key (`--table-keys mangle`)
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+    /// Encrypted string id → original `[line, column]`, for error remapping
+    #[serde(default)]
+    pub locations: BTreeMap<String, (usize, usize)>,
+}
+
+impl SymbolMap {
+    /// Build the symbol map for an obfuscation run
+    pub fn from_script(
+        script_id: &str,
+        crypto_ctx: &CryptoContext,
+        script: &ObfuscatedScript,
+    ) -> Self {
+        Self {
+            version: SYMBOL_MAP_VERSION,
+            script_id: script_id.to_string(),
+            salt: hex::encode(crypto_ctx.salt()),
+            kdf: crypto_ctx.kdf_params(),
+            key_version: crypto_ctx.key_version(),
+            names: script
+                .name_mappings
+                .iter()
+                .map(|(original, mangled)| (original.clone(), mangled.clone()))
+                .collect(),
+            keys: script
+                .key_mappings
+                .iter()
+                .map(|(original, mangled)| (original.clone(), mangled.clone()))
+                .collect(),
+            locations: script
+                .encrypted_strings
+                .iter()
+                .map(|s| (s.id.clone(), (s.line, s.column)))
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read symbol map: {:?}", path))?;
+        let map: Self = serde_json::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid symbol map {:?}: {}", path, e))
+        })?;
+
+        if map.version != SYMBOL_MAP_VERSION {
+            return Err(ObfuscatorError::ConfigError(format!(
+                "Unsupported symbol map version {} (expected {})",
+                map.version, SYMBOL_MAP_VERSION
+            ))
+            .into());
+        }
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize symbol map")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write symbol map: {:?}", path))
+    }
+
+    /// Decoded KDF salt
+    pub fn salt_bytes(&self) -> Result<Vec<u8>> {
+        hex::decode(&self.salt).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid salt in symbol map: {}", e)).into()
+        })
+    }
+
+    /// Re-derive the protection key from the original password
+    pub fn crypto_context(&self, password: &str) -> Result<CryptoContext> {
+        Ok(CryptoContext::with_params(password, Some(&self.salt_bytes()?), self.kdf)?
+            .with_key_version(self.key_version))
+    }
+
+    /// This map after rotating to `crypto_ctx` (same script and mappings)
+    pub fn rekeyed(&self, crypto_ctx: &CryptoContext) -> Self {
+        Self {
+            salt: hex::encode(crypto_ctx.salt()),
+            kdf: crypto_ctx.kdf_params(),
+            key_version: crypto_ctx.key_version(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Output of a recovery run
+#[derive(Debug, Clone)]
+pub struct RecoveredScript {
+    pub code: String,
+    /// Encrypted strings and pool constants that were inlined
+    pub constants_recovered: usize,
+    /// Identifier occurrences restored to their original names
+    pub names_restored: usize,
+    /// Table entries that failed to decrypt (wrong password or tampered data)
+    pub failures: usize,
+}
+
+/// A decrypted table value
+#[derive(Debug, Clone, PartialEq)]
+enum RecoveredValue {
+    String(String),
+    Number(String),
+}
+
+/// Reverses string encryption, constant pooling and name mangling
+pub struct ScriptRecovery<'a> {
+    crypto_ctx: &'a CryptoContext,
+    reverse_names: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> ScriptRecovery<'a> {
+    /// `crypto_ctx` must be derived from the original password and the map's salt
+    pub fn new(crypto_ctx: &'a CryptoContext, symbol_map: &'a SymbolMap) -> Self {
+        Self {
+            crypto_ctx,
+            reverse_names: symbol_map
+                .names
+                .iter()
+                .chain(&symbol_map.keys)
+                .map(|(original, mangled)| (mangled.as_str(), original.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Recover readable code from a protected script
+    pub fn recover(&self, protected: &str) -> Result<RecoveredScript> {
+        let all_tokens = tokens(protected)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia())
+            .collect();
+
+        let mut failures = 0;
+        let key_version = script_key_version(protected);
+        let encoding = script_encoding(protected);
+        let strings = self.decrypt_table(
+            &significant,
+            "_encrypted_data",
+            key_version,
+            encoding,
+            &mut failures,
+        );
+        let strings = unshuffle(strings, table_order(&significant, STRING_ORDER));
+        let pool =
+            self.decrypt_table(&significant, "_K_pool", key_version, encoding, &mut failures);
+        let pool = unshuffle(pool, table_order(&significant, POOL_ORDER));
+
+        // Only the protected code section is worth returning
+        let code_start = protected
+            .find(CODE_MARKER)
+            .map(|i| i + CODE_MARKER.len())
+            .unwrap_or(0);
+
+        let mut output = String::from("-- Recovered by luau-obfuscator (best effort)\n");
+        let mut cursor = code_start;
+        let mut constants_recovered = 0;
+        let mut names_restored = 0;
+        let mut i = 0;
+
+        while i < significant.len() {
+            let token = significant[i];
+            let start = token.start_position().bytes();
+            if start < code_start {
+                i += 1;
+                continue;
+            }
+
+            // _decrypt(n) / _K(n)
+            if let Some(table) = match identifier(token) {
+                Some("_decrypt") => Some(&strings),
+                Some(name) if name == POOL_ACCESSOR => Some(&pool),
+                _ => None,
+            } {
+                if let Some((index, end)) = call_index(&significant, i) {
+                    if let Some(value) = table.get(&index) {
+                        output.push_str(&protected[cursor..start]);
+                        output.push_str(&render_value(value));
+                        cursor = significant[end].end_position().bytes();
+                        constants_recovered += 1;
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(original) = identifier(token).and_then(|name| self.reverse_names.get(name)) {
+                output.push_str(&protected[cursor..start]);
+                output.push_str(original);
+                cursor = token.end_position().bytes();
+                names_restored += 1;
+            }
+
+            i += 1;
+        }
+        output.push_str(&protected[cursor..]);
+
+        Ok(RecoveredScript {
+            code: output,
+            constants_recovered,
+            names_restored,
+            failures,
+        })
+    }
+
+    /// Decrypt every entry of the `name` table
+    fn decrypt_table(
+        &self,
+        tokens: &[&Token],
+        name: &str,
+        key_version: u32,
+        encoding: BinaryEncoding,
+        failures: &mut usize,
+    ) -> HashMap<u64, RecoveredValue> {
+        let mut values = HashMap::new();
+
+        for entry in table_entries(tokens, name) {
+            let plaintext = entry
+                .encrypted(key_version, encoding)
+                .and_then(|encrypted| self.crypto_ctx.decrypt(&encrypted).ok())
+                .and_then(|plaintext| String::from_utf8(plaintext.to_vec()).ok());
+
+            match plaintext {
+                Some(plaintext) if entry.is_number() => {
+                    values.insert(entry.index, RecoveredValue::Number(plaintext));
+                }
+                Some(plaintext) => {
+                    values.insert(entry.index, RecoveredValue::String(plaintext));
+                }
+                None => *failures += 1,
+            }
+        }
+
+        values
+    }
+}
+
+/// One `[index] = {ct = "...", nonce = "..."[, n = true][, z = "lz4"]}`
+/// entry of an encrypted table
+struct TableEntry<'t> {
+    index: u64,
+    fields: HashMap<&'t str, Field<'t>>,
+}
+
+impl TableEntry<'_> {
+    fn encrypted(&self, key_version: u32, encoding: BinaryEncoding) -> Option<EncryptedData> {
+        Some(EncryptedData {
+            ciphertext: encoding.decode(&self.fields.get("ct")?.source())?,
+            nonce: encoding.decode(&self.fields.get("nonce")?.source())?,
+            tag_len: 16,
+            key_version,
+            compression: match self.fields.get("z") {
+                Some(field) => Compression::from_name(&field.text())?,
+                None => Compression::None,
+            },
+        })
+    }
+
+    fn is_number(&self) -> bool {
+        self.fields.get("n").map(Field::text).as_deref() == Some("true")
+    }
+}
+
+/// A field's value: one token, or all of a `{...}` byte array's
+struct Field<'t> {
+    tokens: Vec<&'t Token>,
+}
+
+impl Field<'_> {
+    /// Source of the value, as `BinaryEncoding::decode` reads it
+    fn source(&self) -> String {
+        self.tokens.iter().map(|t| t.to_string()).collect()
+    }
+
+    /// Value of a single-token field, without string quotes
+    fn text(&self) -> String {
+        value_text(self.tokens[0])
+    }
+
+    /// Byte range of the value in the script
+    fn span(&self) -> (usize, usize) {
+        (
+            self.tokens[0].start_position().bytes(),
+            self.tokens[self.tokens.len() - 1].end_position().bytes(),
+        )
+    }
+}
+
+/// Entries of `name = { [i] = {...}, ... }`, in source order
+fn table_entries<'t>(tokens: &[&'t Token], name: &str) -> Vec<TableEntry<'t>> {
+    let mut entries = Vec::new();
+
+    let Some(start) = (0..tokens.len()).find(|&i| {
+        identifier(tokens[i]) == Some(name)
+            && symbol(tokens.get(i + 1)) == Some(Symbol::Equal)
+            && symbol(tokens.get(i + 2)) == Some(Symbol::LeftBrace)
+    }) else {
+        return entries;
+    };
+
+    let mut j = start + 3;
+    while j < tokens.len() && symbol(tokens.get(j)) != Some(Symbol::RightBrace) {
+        // [index] = {
+        let index = match (
+            symbol(tokens.get(j)),
+            tokens.get(j + 1).and_then(|t| number(t)),
+        ) {
+            (Some(Symbol::LeftBracket), Some(index)) => index,
+            _ => {
+                j += 1;
+                continue;
+            }
+        };
+        j += 5;
+
+        let mut fields = HashMap::new();
+        while j < tokens.len() && symbol(tokens.get(j)) != Some(Symbol::RightBrace) {
+            if let (Some(key), Some(Symbol::Equal)) =
+                (identifier(tokens[j]), symbol(tokens.get(j + 1)))
+            {
+                // Byte arrays run to their closing brace
+                let mut end = j + 2;
+                if symbol(tokens.get(end)) == Some(Symbol::LeftBrace) {
+                    while end < tokens.len()
+                        && symbol(tokens.get(end)) != Some(Symbol::RightBrace)
+                    {
+                        end += 1;
+                    }
+                }
+                if end < tokens.len() {
+                    fields.insert(
+                        key,
+                        Field {
+                            tokens: tokens[j + 2..=end].to_vec(),
+                        },
+                    );
+                }
+                j = end + 1;
+            } else {
+                j += 1;
+            }
+        }
+        j += 1;
+
+        entries.push(TableEntry { index, fields });
+    }
+
+    entries
+}
+
+/// `local <name> = _unshuffle(<seed>, <len>)`: the order a shuffled table
+/// was written in
+fn table_order(tokens: &[&Token], name: &str) -> Option<TableShuffle> {
+    tokens.windows(9).find_map(|w| {
+        let declaration = identifier(w[0]) == Some(name)
+            && symbol(Some(&w[1])) == Some(Symbol::Equal)
+            && identifier(w[2]) == Some(SHUFFLE_FUNCTION)
+            && symbol(Some(&w[3])) == Some(Symbol::LeftParen)
+            && symbol(Some(&w[5])) == Some(Symbol::Comma)
+            && symbol(Some(&w[7])) == Some(Symbol::RightParen);
+        if !declaration {
+            return None;
+        }
+        let seed = u32::try_from(number(w[4])?).ok()?;
+        let len = usize::try_from(number(w[6])?).ok()?;
+        Some(TableShuffle::from_seed(seed, len))
+    })
+}
+
+/// Table values by the index the code uses, given the order they were
+/// written in (unshuffled tables are indexed directly)
+fn unshuffle<V>(mut values: HashMap<u64, V>, order: Option<TableShuffle>) -> HashMap<u64, V> {
+    let Some(order) = order else {
+        return values;
+    };
+    (1..=order.len())
+        .filter_map(|index| {
+            let value = values.remove(&(order.position(index) as u64))?;
+            Some((index as u64, value))
+        })
+        .collect()
+}
+
+/// Encoding of the embedded tables from the script header; base64 when absent
+fn script_encoding(protected: &str) -> BinaryEncoding {
+    protected
+        .lines()
+        .take_while(|line| line.starts_with("--") || line.is_empty())
+        .find_map(|line| line.strip_prefix(ENCODING_HEADER))
+        .and_then(|name| BinaryEncoding::from_name(name.trim()))
+        .unwrap_or(BinaryEncoding::Base64)
+}
+
+/// Key generation from the script header; scripts from before key
+/// versioning have none and use the original key
+fn script_key_version(protected: &str) -> u32 {
+    protected
+        .lines()
+        .take_while(|line| line.starts_with("--") || line.is_empty())
+        .find_map(|line| line.strip_prefix(KEY_VERSION_HEADER))
+        .and_then(|version| version.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// `name ( <number> )` starting at `i`; returns the index and the `)` position
+fn call_index(tokens: &[&Token], i: usize) -> Option<(u64, usize)> {
+    if symbol(tokens.get(i + 1)) != Some(Symbol::LeftParen)
+        || symbol(tokens.get(i + 3)) != Some(Symbol::RightParen)
+    {
+        return None;
+    }
+    Some((number(tokens.get(i + 2)?)?, i + 3))
+}
+
+fn identifier(token: &Token) -> Option<&str> {
+    match token.token_type() {
+        TokenType::Identifier { identifier } => Some(identifier.as_str()),
+        _ => None,
+    }
+}
+
+fn symbol(token: Option<&&Token>) -> Option<Symbol> {
+    match token?.token_type() {
+        TokenType::Symbol { symbol } => Some(*symbol),
+        _ => None,
+    }
+}
+
+fn number(token: &Token) -> Option<u64> {
+    match token.token_type() {
+        TokenType::Number { text } => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Token text without string quotes
+fn value_text(token: &Token) -> String {
+    match token.token_type() {
+        TokenType::StringLiteral { literal, .. } => literal.to_string(),
+        _ => token.to_string(),
+    }
+}
+
+/// Render a recovered value as a Luau expression
+fn render_value(value: &RecoveredValue) -> String {
+    match value {
+        RecoveredValue::Number(n) => n.clone(),
+        RecoveredValue::String(s) => {
+            let mut out = String::with_capacity(s.len() + 2);
+            out.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    c if c.is_control() => out.push_str(&format!("\\{:03}", c as u32)),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    pub(super) fn protected_fixture(ctx: &CryptoContext) -> String {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let greeting = ctx.encrypt(b"Hello \"world\"").unwrap();
+        let answer = ctx.encrypt(b"42").unwrap();
+
+        format!(
+            "local _encrypted_data = {{\n    [1] = {{ct = \"{}\", nonce = \"{}\"}},\n}}\n\
+             local _K_pool = {{\n    [1] = {{ct = \"{}\", nonce = \"{}\", n = true}},\n}}\n\
+             {}\n\
+             local _0x0 = _decrypt(1)\n\
+             print(_0x0, _K(1))\n",
+            engine.encode(&greeting.ciphertext),
+            engine.encode(&greeting.nonce),
+            engine.encode(&answer.ciphertext),
+            engine.encode(&answer.nonce),
+            CODE_MARKER,
+        )
+    }
+
+    pub(super) fn symbol_map(ctx: &CryptoContext) -> SymbolMap {
+        SymbolMap {
+            version: SYMBOL_MAP_VERSION,
+            script_id: "script".to_string(),
+            salt: hex::encode(ctx.salt()),
+            kdf: ctx.kdf_params(),
+            key_version: ctx.key_version(),
+            names: [("greeting".to_string(), "_0x0".to_string())].into(),
+            keys: BTreeMap::new(),
+            locations: [("_S1".to_string(), (3, 17))].into(),
+        }
+    }
+
+    #[test]
+    fn test_recover_inlines_strings_and_names() {
+        let ctx = CryptoContext::new("owner_password", None).unwrap();
+        let map = symbol_map(&ctx);
+        let protected = protected_fixture(&ctx);
+
+        let recovered = ScriptRecovery::new(&ctx, &map).recover(&protected).unwrap();
+
+        assert!(recovered.code.contains("local greeting = \"Hello \\\"world\\\"\""));
+        assert!(recovered.code.contains("print(greeting, 42)"));
+        assert!(!recovered.code.contains("_encrypted_data"));
+        assert_eq!(recovered.constants_recovered, 2);
+        assert_eq!(recovered.names_restored, 2);
+        assert_eq!(recovered.failures, 0);
+    }
+
+    #[test]
+    fn test_recover_reads_shuffled_tables() {
+        let ctx = CryptoContext::new("owner_password", None).unwrap();
+        let map = symbol_map(&ctx);
+        let engine = base64::engine::general_purpose::STANDARD;
+        let order = TableShuffle::from_seed(42, 3);
+        assert_eq!(order.position(1), 2);
+
+        let mut entries = Vec::new();
+        for (index, text) in ["first", "second", "third"].iter().enumerate() {
+            let encrypted = ctx.encrypt(text.as_bytes()).unwrap();
+            entries.push(format!(
+                "    [{}] = {{ct = \"{}\", nonce = \"{}\"}},\n",
+                order.position(index + 1),
+                engine.encode(&encrypted.ciphertext),
+                engine.encode(&encrypted.nonce)
+            ));
+        }
+        let protected = format!(
+            "local _encrypted_data = {{\n{}}}\n{}{}\nprint(_decrypt(1), _decrypt(2), _decrypt(3))\n",
+            entries.concat(),
+            order.render_order(STRING_ORDER),
+            CODE_MARKER,
+        );
+
+        let recovered = ScriptRecovery::new(&ctx, &map).recover(&protected).unwrap();
+
+        assert!(recovered.code.contains("print(\"first\", \"second\", \"third\")"));
+        assert_eq!(recovered.failures, 0);
+    }
+
+    #[test]
+    fn test_wrong_password_reports_failures() {
+        let ctx = CryptoContext::new("owner_password", None).unwrap();
+        let map = symbol_map(&ctx);
+        let protected = protected_fixture(&ctx);
+
+        let wrong = CryptoContext::new("guess", Some(&map.salt_bytes().unwrap())).unwrap();
+        let recovered = ScriptRecovery::new(&wrong, &map).recover(&protected).unwrap();
+
+        assert_eq!(recovered.failures, 2);
+        assert!(recovered.code.contains("_decrypt(1)"));
+    }
+
+    #[test]
+    fn test_symbol_map_roundtrip() {
+        let ctx = CryptoContext::new("owner_password", None).unwrap();
+        let map = symbol_map(&ctx);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.map.json");
+
+        map.save(&path).unwrap();
+        let loaded = SymbolMap::load(&path).unwrap();
+
+        assert_eq!(loaded, map);
+        assert_eq!(loaded.salt_bytes().unwrap(), ctx.salt());
+    }
+
+    #[test]
+    fn test_map_rederives_key_with_its_params() {
+        let ctx = CryptoContext::with_params("owner_password", None, KdfParams::interactive())
+            .unwrap();
+        let map = symbol_map(&ctx);
+        let protected = protected_fixture(&ctx);
+
+        let json = serde_json::to_string(&map).unwrap();
+        let loaded: SymbolMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.kdf, KdfParams::interactive());
+
+        let rederived = loaded.crypto_context("owner_password").unwrap();
+        let recovered = ScriptRecovery::new(&rederived, &loaded).recover(&protected).unwrap();
+        assert_eq!(recovered.failures, 0);
+
+        // Maps written before the parameters were recorded used the defaults
+        let mut legacy = serde_json::to_value(&map).unwrap();
+        legacy.as_object_mut().unwrap().remove("kdf");
+        let legacy: SymbolMap = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.kdf, KdfParams::default());
+    }
+}
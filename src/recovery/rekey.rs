@@ -0,0 +1,241 @@
+//! Key rotation for protected scripts
+//!
+//! Re-encrypts the embedded string table and constant pool under a new
+//! master key without re-running obfuscation: code, name mappings and the
+//! watermark stay byte-for-byte the same, only the `ct`/`nonce` fields (in
+//! the script's data encoding), the key version header and the signed
+//! metadata header change.
+
+use super::{script_encoding, script_key_version, table_entries};
+use crate::codegen::{MetadataSigner, ScriptMetadata, KEY_VERSION_HEADER, METADATA_HEADER};
+use crate::crypto::{Compression, CryptoContext};
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use full_moon::tokenizer::{tokens, Token};
+use zeroize::Zeroizing;
+
+/// Tables holding data encrypted under the master key
+const ENCRYPTED_TABLES: &[&str] = &["_encrypted_data", "_K_pool"];
+
+/// Sections whose key material can't be rotated in place
+const UNSUPPORTED_SECTIONS: &[(&str, &str)] = &[
+    ("-- [KEY] Delivered Key Shares", "delivered key shares are derived from the old key"),
+    ("-- [STAGE] Payload Chunk", "the payload chunk is sealed separately"),
+    ("-- [DATA] Remote Encrypted Strings", "its strings live in the exported payload"),
+];
+
+/// Output of a rekey run
+#[derive(Debug, Clone)]
+pub struct RekeyedScript {
+    pub code: String,
+    /// Table entries re-encrypted
+    pub entries: usize,
+}
+
+/// Moves a protected script from one master key to another
+pub struct Rekeyer<'a> {
+    old: &'a CryptoContext,
+    new: &'a CryptoContext,
+}
+
+impl<'a> Rekeyer<'a> {
+    /// `old` must be re-derived from the script's symbol map; `new` carries
+    /// the next key version
+    pub fn new(old: &'a CryptoContext, new: &'a CryptoContext) -> Self {
+        Self { old, new }
+    }
+
+    pub fn rekey(&self, protected: &str) -> Result<RekeyedScript> {
+        if let Some((_, reason)) = UNSUPPORTED_SECTIONS
+            .iter()
+            .find(|(marker, _)| protected.contains(marker))
+        {
+            return Err(ObfuscatorError::CryptoError(format!(
+                "Cannot rekey this script in place: {}; protect the source again instead",
+                reason
+            ))
+            .into());
+        }
+
+        let version = script_key_version(protected);
+        if version != self.old.key_version() {
+            return Err(ObfuscatorError::CryptoError(format!(
+                "Script is encrypted with key version {}, the symbol map is for version {}",
+                version,
+                self.old.key_version()
+            ))
+            .into());
+        }
+
+        // Refuse a tampered header rather than signing it with the new key
+        let metadata = if protected.lines().any(|line| line.starts_with(METADATA_HEADER)) {
+            Some(MetadataSigner::new(self.old).verify(protected)?)
+        } else {
+            None
+        };
+
+        let all_tokens = tokens(protected)
+            .map_err(|e| ObfuscatorError::ParseError(format!("Tokenization failed: {}", e)))?;
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia())
+            .collect();
+
+        let encoding = script_encoding(protected);
+        // Byte range of a `ct`/`nonce` literal → replacement
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        for table in ENCRYPTED_TABLES {
+            for entry in table_entries(&significant, table) {
+                let old = entry.encrypted(version, encoding);
+                let plaintext = old
+                    .as_ref()
+                    .and_then(|encrypted| self.old.decrypt(encrypted).ok())
+                    .ok_or_else(|| {
+                        ObfuscatorError::CryptoError(format!(
+                            "{}[{}] failed to decrypt (wrong password or symbol map?)",
+                            table, entry.index
+                        ))
+                    })?;
+                // Compressed again so the entry's `z` flag still holds
+                let compression = old.map_or(Compression::None, |encrypted| encrypted.compression);
+                let encrypted = self
+                    .new
+                    .encrypt(&Zeroizing::new(compression.compress(&plaintext)?))?;
+
+                let fields = [("ct", &encrypted.ciphertext), ("nonce", &encrypted.nonce)];
+                for (field, bytes) in fields {
+                    let (start, end) = entry.fields[field].span();
+                    edits.push((start, end, encoding.encode(bytes)));
+                }
+            }
+        }
+        let entries = edits.len() / 2;
+        edits.sort_by_key(|(start, _, _)| *start);
+
+        let mut code = String::with_capacity(protected.len());
+        let mut cursor = 0;
+        for (start, end, replacement) in edits {
+            code.push_str(&protected[cursor..start]);
+            code.push_str(&replacement);
+            cursor = end;
+        }
+        code.push_str(&protected[cursor..]);
+
+        let mut code = set_key_version(&code, self.new.key_version());
+        if let Some(metadata) = metadata {
+            let metadata = ScriptMetadata {
+                key_version: self.new.key_version(),
+                ..metadata
+            };
+            let header = MetadataSigner::new(self.new).sign(&metadata)?;
+            code = code
+                .split_inclusive('\n')
+                .map(|line| match line.starts_with(METADATA_HEADER) {
+                    true => format!("{}\n", header),
+                    false => line.to_string(),
+                })
+                .collect();
+        }
+
+        Ok(RekeyedScript { code, entries })
+    }
+}
+
+/// Replace the key version header, adding it after the watermark if missing
+fn set_key_version(code: &str, version: u32) -> String {
+    let header = format!("{}{}\n", KEY_VERSION_HEADER, version);
+
+    let mut output = String::with_capacity(code.len() + header.len());
+    let mut written = false;
+    for line in code.split_inclusive('\n') {
+        if !written && line.starts_with(KEY_VERSION_HEADER) {
+            output.push_str(&header);
+            written = true;
+            continue;
+        }
+        output.push_str(line);
+        if !written && line.starts_with("-- Watermark:") {
+            output.push_str(&header);
+            written = true;
+        }
+    }
+
+    if written {
+        output
+    } else {
+        header + &output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::tests::{protected_fixture, symbol_map};
+    use crate::recovery::ScriptRecovery;
+
+    #[test]
+    fn test_rekey_reencrypts_tables_only() {
+        let old = CryptoContext::new("old_password", None).unwrap();
+        let map = symbol_map(&old);
+        let protected = protected_fixture(&old);
+
+        let new = CryptoContext::new("new_password", None)
+            .unwrap()
+            .with_key_version(1);
+        let rekeyed = Rekeyer::new(&old, &new).rekey(&protected).unwrap();
+        assert_eq!(rekeyed.entries, 2);
+        assert!(rekeyed.code.starts_with("-- Key version: 1\n"));
+        assert!(rekeyed.code.ends_with("local _0x0 = _decrypt(1)\nprint(_0x0, _K(1))\n"));
+
+        let new_map = map.rekeyed(&new);
+        let recovered = ScriptRecovery::new(&new, &new_map).recover(&rekeyed.code).unwrap();
+        assert_eq!(recovered.failures, 0);
+        assert!(recovered.code.contains("print(greeting, 42)"));
+
+        // The old key no longer opens it, and can't rekey it twice
+        let stale = ScriptRecovery::new(&old, &map).recover(&rekeyed.code).unwrap();
+        assert_eq!(stale.failures, 2);
+        assert!(Rekeyer::new(&old, &new).rekey(&rekeyed.code).is_err());
+        assert_eq!(new_map.key_version, 1);
+        assert_eq!(new_map.names, map.names);
+    }
+
+    #[test]
+    fn test_rekey_resigns_metadata_header() {
+        let old = CryptoContext::new("old_password", None).unwrap();
+        let metadata = ScriptMetadata {
+            script_id: "script".to_string(),
+            tier: "standard".to_string(),
+            licensed_tier: None,
+            timestamp: 1_700_000_000,
+            key_version: 0,
+            watermark_digest: ScriptMetadata::watermark_digest("V0FURVJNQVJL"),
+        };
+        let header = MetadataSigner::new(&old).sign(&metadata).unwrap();
+        let protected = format!(
+            "-- Watermark: V0FURVJNQVJL\n-- Key version: 0\n{}\n\n{}",
+            header,
+            protected_fixture(&old)
+        );
+
+        let new = CryptoContext::new("new_password", None)
+            .unwrap()
+            .with_key_version(1);
+        let rekeyed = Rekeyer::new(&old, &new).rekey(&protected).unwrap();
+        let resigned = MetadataSigner::new(&new).verify(&rekeyed.code).unwrap();
+        assert_eq!(resigned.key_version, 1);
+        assert_eq!(resigned.timestamp, metadata.timestamp);
+
+        let forged = protected.replace("V0FURVJNQVJL", "Rk9SR0VE");
+        assert!(Rekeyer::new(&old, &new).rekey(&forged).is_err());
+    }
+
+    #[test]
+    fn test_key_delivery_scripts_are_refused() {
+        let old = CryptoContext::new("old_password", None).unwrap();
+        let new = CryptoContext::new("new_password", None).unwrap();
+        let protected = format!("-- [KEY] Delivered Key Shares\n{}", protected_fixture(&old));
+
+        assert!(Rekeyer::new(&old, &new).rekey(&protected).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Structural validation of protected scripts
+//!
+//! Runs a fixed list of checks over a protected script and reports each as
+//! pass, fail or skip. Checks that need the key (signed metadata, encrypted
+//! tables) are skipped unless the validator is given the script's crypto
+//! context. Everything is compared token by token, so output formatting
+//! (minify, packed) doesn't matter.
+
+use super::{script_encoding, script_key_version, table_entries};
+use crate::codegen::{MetadataSigner, RuntimeGenerator, KEY_VERSION_HEADER};
+use crate::crypto::CryptoContext;
+use base64::Engine;
+use full_moon::tokenizer::{tokens, Token, TokenType};
+use regex::Regex;
+use serde::Serialize;
+
+/// First line of every protected script
+const BANNER: &str = "-- Protected by Luau Obfuscator";
+
+/// Tables holding data encrypted under the master key
+const ENCRYPTED_TABLES: &[&str] = &["_encrypted_data", "_K_pool"];
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// Not applicable to this script, or needs the key
+    Skip,
+}
+
+/// One row of a validation report
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Every check run against a script, in order
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub checks: Vec<ValidationCheck>,
+    /// Script id from the watermark (or signed metadata, when verified)
+    pub script_id: Option<String>,
+}
+
+impl ValidationReport {
+    /// Whether no check failed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ValidationCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail)
+    }
+
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(ValidationCheck {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Checks a protected script's structure and, with the key, its integrity
+#[derive(Default)]
+pub struct ScriptValidator {
+    /// Script id the license stub and watermark must name
+    script_id: Option<String>,
+    crypto_ctx: Option<CryptoContext>,
+}
+
+impl ScriptValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the watermark and license stub to name `script_id`
+    pub fn with_script_id(mut self, script_id: impl Into<String>) -> Self {
+        self.script_id = Some(script_id.into());
+        self
+    }
+
+    /// Verify the signed metadata and encrypted tables under `crypto_ctx`
+    /// (re-derived from the symbol map)
+    pub fn with_crypto(mut self, crypto_ctx: CryptoContext) -> Self {
+        self.crypto_ctx = Some(crypto_ctx);
+        self
+    }
+
+    pub fn validate(&self, protected: &str) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        match full_moon::parse(protected) {
+            Ok(_) => report.push("parse", CheckStatus::Pass, "valid Luau"),
+            Err(e) => report.push("parse", CheckStatus::Fail, e.to_string()),
+        }
+
+        // The shebang and hot comments sit above the banner
+        let header = protected
+            .lines()
+            .find(|line| !line.starts_with("#!") && !line.starts_with("--!"))
+            .unwrap_or_default();
+        if header.trim_end() == BANNER {
+            report.push("header", CheckStatus::Pass, "banner present");
+        } else {
+            report.push("header", CheckStatus::Fail, "banner missing or moved");
+        }
+
+        let Ok(all_tokens) = tokens(protected) else {
+            report.push("tokens", CheckStatus::Fail, "script could not be tokenized");
+            return report;
+        };
+        let significant: Vec<&Token> = all_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .collect();
+
+        let watermark_id = self.check_watermark(protected, &mut report);
+        report.script_id = self.script_id.clone().or(watermark_id);
+        self.check_runtime(&significant, &mut report);
+        self.check_license(&significant, &mut report);
+        self.check_signature(protected, &mut report);
+        self.check_encrypted_tables(protected, &significant, &mut report);
+
+        report
+    }
+
+    /// The watermark line decodes and names the expected script
+    fn check_watermark(&self, protected: &str, report: &mut ValidationReport) -> Option<String> {
+        let Some(encoded) = header_value(protected, "-- Watermark: ") else {
+            report.push("watermark", CheckStatus::Fail, "watermark line missing");
+            return None;
+        };
+
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        let script_id = decoded.as_deref().and_then(watermark_script_id);
+        match (&script_id, &self.script_id) {
+            (None, _) => report.push("watermark", CheckStatus::Fail, "watermark does not decode"),
+            (Some(found), Some(expected)) if found != expected => report.push(
+                "watermark",
+                CheckStatus::Fail,
+                format!("watermark names script {:?}, expected {:?}", found, expected),
+            ),
+            (Some(found), _) => {
+                report.push("watermark", CheckStatus::Pass, format!("script {:?}", found))
+            }
+        }
+        script_id
+    }
+
+    /// The ChaCha20 runtime is present token for token
+    fn check_runtime(&self, significant: &[&Token], report: &mut ValidationReport) {
+        let runtime = match RuntimeGenerator::new().generate() {
+            Ok(runtime) => runtime,
+            Err(e) => return report.push("runtime", CheckStatus::Fail, e.to_string()),
+        };
+        let Ok(runtime_tokens) = tokens(&runtime) else {
+            return report.push("runtime", CheckStatus::Fail, "bundled runtime does not tokenize");
+        };
+        let expected: Vec<String> = runtime_tokens
+            .iter()
+            .filter(|t| !t.token_type().is_trivia() && !matches!(t.token_type(), TokenType::Eof))
+            .map(|t| t.to_string())
+            .collect();
+        let script: Vec<String> = significant.iter().map(|t| t.to_string()).collect();
+
+        if script.windows(expected.len()).any(|window| window == expected.as_slice()) {
+            report.push("runtime", CheckStatus::Pass, "ChaCha20 runtime intact");
+        } else if script.iter().any(|t| t == "ChaCha20") {
+            report.push("runtime", CheckStatus::Fail, "ChaCha20 runtime was modified");
+        } else {
+            report.push("runtime", CheckStatus::Skip, "no runtime embedded");
+        }
+    }
+
+    /// `local SCRIPT_ID = "..."` in the license stub names the expected script
+    fn check_license(&self, significant: &[&Token], report: &mut ValidationReport) {
+        let declared = significant.windows(4).find_map(|w| {
+            let declaration = w[0].to_string() == "local"
+                && w[1].to_string() == "SCRIPT_ID"
+                && w[2].to_string() == "=";
+            if declaration {
+                string_value(w[3])
+            } else {
+                None
+            }
+        });
+
+        let expected = report.script_id.clone();
+        match (declared, expected.as_deref()) {
+            (None, _) => report.push("license", CheckStatus::Skip, "no license stub"),
+            (Some(found), Some(expected)) if found != expected => report.push(
+                "license",
+                CheckStatus::Fail,
+                format!("license stub names script {:?}, expected {:?}", found, expected),
+            ),
+            (Some(found), _) => {
+                report.push("license", CheckStatus::Pass, format!("script {:?}", found))
+            }
+        }
+    }
+
+    /// The signed metadata header verifies under the key
+    fn check_signature(&self, protected: &str, report: &mut ValidationReport) {
+        let Some(crypto_ctx) = &self.crypto_ctx else {
+            return report.push("signature", CheckStatus::Skip, "needs the symbol map and password");
+        };
+
+        match MetadataSigner::new(crypto_ctx).verify(protected) {
+            Ok(metadata) => {
+                let licensed = match &metadata.licensed_tier {
+                    Some(licensed) => format!(" (licensed {})", licensed),
+                    None => String::new(),
+                };
+                report.push(
+                    "signature",
+                    CheckStatus::Pass,
+                    format!(
+                        "{} tier{}, key version {}",
+                        metadata.tier, licensed, metadata.key_version
+                    ),
+                );
+                report.script_id = Some(metadata.script_id);
+            }
+            Err(e) => report.push("signature", CheckStatus::Fail, e.to_string()),
+        }
+    }
+
+    /// Every encrypted table entry authenticates under the key
+    fn check_encrypted_tables(
+        &self,
+        protected: &str,
+        significant: &[&Token],
+        report: &mut ValidationReport,
+    ) {
+        let Some(crypto_ctx) = &self.crypto_ctx else {
+            return report.push("encryption", CheckStatus::Skip, "needs the symbol map and password");
+        };
+
+        let key_version = script_key_version(protected);
+        if key_version != crypto_ctx.key_version() {
+            return report.push(
+                "encryption",
+                CheckStatus::Fail,
+                format!(
+                    "{}{} but the symbol map is for version {}",
+                    KEY_VERSION_HEADER,
+                    key_version,
+                    crypto_ctx.key_version()
+                ),
+            );
+        }
+
+        let encoding = script_encoding(protected);
+        let mut total = 0;
+        let mut failed = 0;
+        for table in ENCRYPTED_TABLES {
+            for entry in table_entries(significant, table) {
+                total += 1;
+                let authentic = entry
+                    .encrypted(key_version, encoding)
+                    .is_some_and(|encrypted| crypto_ctx.decrypt(&encrypted).is_ok());
+                if !authentic {
+                    failed += 1;
+                }
+            }
+        }
+
+        match (total, failed) {
+            (0, _) => report.push("encryption", CheckStatus::Skip, "no embedded encrypted data"),
+            (_, 0) => report.push(
+                "encryption",
+                CheckStatus::Pass,
+                format!("{} entries authenticated", total),
+            ),
+            _ => report.push(
+                "encryption",
+                CheckStatus::Fail,
+                format!("{} of {} entries failed authentication", failed, total),
+            ),
+        }
+    }
+}
+
+/// Value of the first `prefix` comment line
+fn header_value<'s>(protected: &'s str, prefix: &str) -> Option<&'s str> {
+    protected
+        .lines()
+        .find_map(|line| line.strip_prefix(prefix))
+        .map(str::trim)
+}
+
+/// `script_id` field of a decoded watermark
+fn watermark_script_id(decoded: &str) -> Option<String> {
+    let pattern = Regex::new(r#"script_id: "((?:[^"\\]|\\.)*)""#).ok()?;
+    pattern.captures(decoded).map(|c| c[1].to_string())
+}
+
+fn string_value(token: &Token) -> Option<String> {
+    match token.token_type() {
+        TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{CodeGenConfig, OutputFormatting};
+    use crate::pipeline::{ObfuscationPipeline, PipelineSettings};
+
+    fn protect(crypto: &CryptoContext, formatting: OutputFormatting) -> String {
+        let config = CodeGenConfig {
+            license_key: "TEST-1234".to_string(),
+            script_id: "test_script".to_string(),
+            output_formatting: formatting,
+            ..Default::default()
+        };
+        ObfuscationPipeline::new(crypto.clone())
+            .run("print(\"hello\")\n", &PipelineSettings::default(), config)
+            .unwrap()
+    }
+
+    fn status(report: &ValidationReport, name: &str) -> CheckStatus {
+        report.checks.iter().find(|c| c.name == name).unwrap().status
+    }
+
+    #[test]
+    fn test_protected_script_passes_every_check() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        for formatting in [OutputFormatting::Readable, OutputFormatting::Minify] {
+            let script = protect(&crypto, formatting);
+            let report = ScriptValidator::new()
+                .with_script_id("test_script")
+                .with_crypto(crypto.clone())
+                .validate(&script);
+
+            assert!(report.passed(), "{:?}", report.checks);
+            assert_eq!(status(&report, "runtime"), CheckStatus::Pass);
+            assert_eq!(status(&report, "license"), CheckStatus::Pass);
+            assert_eq!(status(&report, "encryption"), CheckStatus::Pass);
+            assert_eq!(report.script_id.as_deref(), Some("test_script"));
+        }
+    }
+
+    #[test]
+    fn test_edits_are_reported() {
+        let crypto = CryptoContext::new("test_password", None).unwrap();
+        let script = protect(&crypto, OutputFormatting::Readable);
+
+        let keyless = ScriptValidator::new().validate(&script);
+        assert!(keyless.passed());
+        assert_eq!(status(&keyless, "signature"), CheckStatus::Skip);
+
+        let other_id = ScriptValidator::new().with_script_id("other").validate(&script);
+        assert_eq!(status(&other_id, "watermark"), CheckStatus::Fail);
+        assert_eq!(status(&other_id, "license"), CheckStatus::Fail);
+
+        let patched = script.replacen("state, 1, 5, 9, 13", "state, 1, 5, 9, 12", 1);
+        let report = ScriptValidator::new().validate(&patched);
+        assert_eq!(status(&report, "runtime"), CheckStatus::Fail);
+    }
+}
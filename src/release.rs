@@ -0,0 +1,322 @@
+//! Per-customer release builds
+//!
+//! Protects one source once per customer. Every build gets the customer's
+//! license key and HWID, its own seed and salt, and so its own watermark and
+//! table shuffle; two customers' copies differ structurally and a leaked one
+//! traces back to its buyer through the manifest. Builds run on worker
+//! threads, each with its own pipeline.
+
+use crate::codegen::{CodeGenConfig, MetadataSigner};
+use crate::crypto::{CryptoContext, KdfParams};
+use crate::pipeline::{ObfuscationPipeline, PipelineSettings};
+use crate::recovery::SymbolMap;
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// One buyer of a release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Customer {
+    /// Used in output file names, so limited to letters, digits, `-` and `_`
+    pub id: String,
+    pub license_key: String,
+    /// Roblox UserId to bind the build to
+    pub hwid: Option<u64>,
+    /// Line number in the source CSV (for error reporting)
+    pub line: usize,
+}
+
+/// Parse a customers CSV file (`customer_id,license_key[,hwid]`)
+///
+/// The first row may be a header; blank lines and `#` comments are skipped.
+pub fn parse_customers_csv(content: &str) -> Result<Vec<Customer>> {
+    let mut customers: Vec<Customer> = Vec::new();
+
+    for (idx, raw_line) in content.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim().trim_matches('"')).collect();
+
+        // Skip header row
+        if idx == 0 && fields[0].eq_ignore_ascii_case("customer_id") {
+            continue;
+        }
+
+        let invalid = |what: &str, value: &str| {
+            ObfuscatorError::ConfigError(format!(
+                "Invalid {} on line {}: {:?}",
+                what, line_no, value
+            ))
+        };
+
+        let id = fields[0];
+        let valid_id = !id.is_empty()
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_id {
+            return Err(invalid("customer id", id).into());
+        }
+        if customers.iter().any(|c| c.id == id) {
+            return Err(invalid("customer id (duplicate)", id).into());
+        }
+
+        let license_key = fields.get(1).copied().unwrap_or_default();
+        if license_key.is_empty() {
+            return Err(invalid("license key", license_key).into());
+        }
+
+        let hwid = match fields.get(2).filter(|f| !f.is_empty()) {
+            Some(hwid) => Some(hwid.parse::<u64>().map_err(|_| invalid("HWID", hwid))?),
+            None => None,
+        };
+
+        customers.push(Customer {
+            id: id.to_string(),
+            license_key: license_key.to_string(),
+            hwid,
+            line: line_no,
+        });
+    }
+
+    Ok(customers)
+}
+
+/// One customer's protected build
+#[derive(Debug, Clone)]
+pub struct ReleaseBuild {
+    pub customer_id: String,
+    pub seed: u64,
+    pub script: String,
+    /// Encrypted payload module, when split
+    pub payload: Option<String>,
+    pub symbol_map: SymbolMap,
+    /// Digest of the build's watermark, as signed into its metadata header
+    pub watermark_digest: String,
+}
+
+/// Manifest written next to the builds of a release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    pub script_id: String,
+    pub tier: String,
+    pub builds: Vec<ManifestEntry>,
+}
+
+/// Customer → files → watermark digest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub customer_id: String,
+    pub hwid: Option<u64>,
+    pub seed: u64,
+    pub output: Option<PathBuf>,
+    pub payload: Option<PathBuf>,
+    pub symbol_map: Option<PathBuf>,
+    pub watermark_digest: Option<String>,
+    /// Why the build failed; the other fields are then empty
+    pub error: Option<String>,
+}
+
+/// Builds one script for a list of customers
+pub struct ReleaseMatrix {
+    settings: PipelineSettings,
+    config: CodeGenConfig,
+    /// Random per build when unset (the builds then can't be recovered)
+    password: Option<String>,
+    kdf: KdfParams,
+    jobs: usize,
+}
+
+impl ReleaseMatrix {
+    /// `settings.seed` makes the per-customer seeds reproducible;
+    /// `config`'s license key and HWID are replaced per customer
+    pub fn new(settings: PipelineSettings, config: CodeGenConfig) -> Self {
+        Self {
+            settings,
+            config,
+            password: None,
+            kdf: KdfParams::default(),
+            jobs: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Derive every build's key from `password` (each with its own salt)
+    pub fn with_password(mut self, password: String) -> Self {
+        self.password = Some(password);
+        self
+    }
+
+    pub fn with_kdf_params(mut self, kdf: KdfParams) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    /// Build at most `jobs` customers at once
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Seed of the build at `index` (distinct for every index)
+    pub fn seed_for(base: u64, index: usize) -> u64 {
+        // Multiplying by an odd constant is a bijection, so seeds never collide
+        base.wrapping_add(index as u64)
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    /// Build `source` for every customer, in customer order
+    pub fn build(&self, source: &str, customers: &[Customer]) -> Vec<Result<ReleaseBuild>> {
+        self.build_with_progress(source, customers, |_| {})
+    }
+
+    /// `build`, calling `on_done` as each customer's build finishes
+    pub fn build_with_progress(
+        &self,
+        source: &str,
+        customers: &[Customer],
+        on_done: impl Fn(&Customer) + Sync,
+    ) -> Vec<Result<ReleaseBuild>> {
+        let base_seed = self.settings.seed.unwrap_or_else(rand::random);
+
+        // No worker threads on targets without them (wasm32)
+        if self.jobs == 1 {
+            return customers
+                .iter()
+                .enumerate()
+                .map(|(index, customer)| {
+                    let build = self.build_one(source, customer, Self::seed_for(base_seed, index));
+                    on_done(customer);
+                    build
+                })
+                .collect();
+        }
+
+        let results: Vec<Mutex<Option<Result<ReleaseBuild>>>> =
+            customers.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.min(customers.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(customer) = customers.get(index) else {
+                        break;
+                    };
+                    let build = self.build_one(source, customer, Self::seed_for(base_seed, index));
+                    *results[index].lock().unwrap() = Some(build);
+                    on_done(customer);
+                });
+            }
+        });
+
+        results
+            .into_iter()
+            .map(|result| result.into_inner().unwrap().expect("every customer is built"))
+            .collect()
+    }
+
+    fn build_one(&self, source: &str, customer: &Customer, seed: u64) -> Result<ReleaseBuild> {
+        let password = match &self.password {
+            Some(password) => password.clone(),
+            None => {
+                use rand::distributions::{Alphanumeric, DistString};
+                Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+            }
+        };
+        let crypto = CryptoContext::with_params(&password, None, self.kdf)?;
+
+        let settings = PipelineSettings {
+            seed: Some(seed),
+            ..self.settings.clone()
+        };
+        let config = CodeGenConfig {
+            license_key: customer.license_key.clone(),
+            hwid: customer.hwid,
+            include_hwid: customer.hwid.is_some(),
+            ..self.config.clone()
+        };
+        let script_id = config.script_id.clone();
+
+        let protected =
+            ObfuscationPipeline::new(crypto.clone()).protect(source, &settings, config)?;
+        let metadata = MetadataSigner::new(&crypto).verify(&protected.script)?;
+
+        Ok(ReleaseBuild {
+            customer_id: customer.id.clone(),
+            seed,
+            symbol_map: SymbolMap::from_script(&script_id, &crypto, &protected.obfuscated),
+            script: protected.script,
+            payload: protected.payload,
+            watermark_digest: metadata.watermark_digest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_customers_csv() {
+        let csv = "customer_id,license_key,hwid\n\
+                   # comment\n\
+                   acme,KEY-1,12345\n\
+                   \n\
+                   bob_2,KEY-2\n";
+        let customers = parse_customers_csv(csv).unwrap();
+        assert_eq!(customers.len(), 2);
+        assert_eq!(customers[0].hwid, Some(12345));
+        assert_eq!(customers[1].id, "bob_2");
+        assert_eq!(customers[1].hwid, None);
+        assert_eq!(customers[1].line, 5);
+
+        assert!(parse_customers_csv("../evil,KEY-1\n").is_err());
+        assert!(parse_customers_csv("acme,KEY-1\nacme,KEY-2\n").is_err());
+        assert!(parse_customers_csv("acme,\n").is_err());
+        assert!(parse_customers_csv("acme,KEY-1,not-a-number\n").is_err());
+    }
+
+    #[test]
+    fn test_builds_differ_per_customer() {
+        let source = "local greeting = \"hello\"\nlocal name = \"world\"\nprint(greeting, name, 42)\n";
+        let customers = parse_customers_csv("acme,KEY-1\nbob,KEY-2,777\n").unwrap();
+        let settings = PipelineSettings {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let config = CodeGenConfig {
+            script_id: "release_script".to_string(),
+            include_license: false,
+            ..Default::default()
+        };
+        let fast = KdfParams {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        };
+
+        let builds: Vec<ReleaseBuild> = ReleaseMatrix::new(settings, config)
+            .with_password("owner_password".to_string())
+            .with_kdf_params(fast)
+            .with_jobs(2)
+            .build(source, &customers)
+            .into_iter()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(builds[0].customer_id, "acme");
+        assert_eq!(builds[1].customer_id, "bob");
+        assert_ne!(builds[0].seed, builds[1].seed);
+        assert_ne!(builds[0].watermark_digest, builds[1].watermark_digest);
+        assert_ne!(builds[0].symbol_map.salt, builds[1].symbol_map.salt);
+        assert!(!builds[0].script.contains("-- [HWID]"));
+        assert!(builds[1].script.contains("-- [HWID]"));
+        assert_eq!(ReleaseMatrix::seed_for(7, 1), builds[1].seed);
+    }
+}
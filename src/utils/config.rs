@@ -1,10 +1,27 @@
 //! Configuration management
 
+use super::errors::ObfuscatorError;
+use super::source::InputOptions;
+use crate::codegen::{AntiDumpPolicy, CodeGenConfig, ExecutorCheck, KeyDelivery, TamperPolicy};
+use crate::obfuscation::{
+    event_handlers, Exclusions, ObfuscationSettings, PlaintextRule, PlaintextRules, TierPolicy,
+};
+use crate::parser::{SensitivityRule, SensitivityRules};
+use crate::project::module_exports;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+/// Config file name inside the config directory
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Built-in protection presets, by name
+const BUILTIN_PRESETS: &str = include_str!("presets.toml");
+
 /// Configuration for the obfuscator
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     /// Default API endpoint
     pub api_endpoint: Option<String>,
@@ -17,6 +34,67 @@ pub struct Config {
 
     /// Cache directory
     pub cache_dir: Option<PathBuf>,
+
+    /// Pinned Roblox API database version (e.g. "version-1a2b3c4d5e6f7a8b")
+    #[serde(default)]
+    pub api_db_version: Option<String>,
+
+    /// Seconds a successful license validation is reused without going online
+    #[serde(default)]
+    pub validation_cache_ttl: Option<u64>,
+
+    /// What `protect`, `protect-project` and `release` do when `--tier` is
+    /// above the validated license's tier
+    pub tier_policy: TierPolicy,
+
+    /// Extra string sensitivity rules, checked before the built-in ones
+    #[serde(default)]
+    pub sensitivity_rules: Vec<SensitivityRule>,
+
+    /// Strings never encrypted (asset URLs, chat filter input, ...)
+    #[serde(default)]
+    pub plaintext_strings: Vec<PlaintextRule>,
+
+    /// Instance attributes/StringValues that hold part of the decryption key
+    #[serde(default)]
+    pub key_delivery: Option<KeyDelivery>,
+
+    /// License/EULA text emitted as header comments in protected scripts
+    #[serde(default)]
+    pub license_header: Option<String>,
+
+    /// Start protected scripts with `--!optimize 2`
+    #[serde(default)]
+    pub force_optimize: bool,
+
+    /// Write the strings of a script's settings block as escapes
+    #[serde(default)]
+    pub encode_config: bool,
+
+    /// Directory of templates replacing the built-in Luau templates
+    #[serde(default)]
+    pub templates_dir: Option<PathBuf>,
+
+    /// Queue and upload usage events for the license API (on unless set
+    /// to `false`)
+    pub telemetry: bool,
+
+    /// Roblox Open Cloud API key for `--verify-place` (the
+    /// `ROBLOX_OPEN_CLOUD_API_KEY` environment variable takes precedence)
+    pub roblox_api_key: Option<String>,
+
+    /// UserId that must own the experience of a verified place
+    pub place_owner_user: Option<u64>,
+
+    /// GroupId that must own the experience of a verified place
+    pub place_owner_group: Option<u64>,
+
+    /// Protection presets added to the built-in ones (or replacing one of
+    /// the same name)
+    pub presets: BTreeMap<String, Preset>,
+
+    /// How script files are decoded and protected output line endings
+    pub input: InputOptions,
 }
 
 impl Default for Config {
@@ -26,15 +104,101 @@ impl Default for Config {
             default_tier: "standard".to_string(),
             api_key: None,
             cache_dir: None,
+            api_db_version: None,
+            validation_cache_ttl: None,
+            tier_policy: TierPolicy::default(),
+            sensitivity_rules: Vec::new(),
+            plaintext_strings: Vec::new(),
+            key_delivery: None,
+            license_header: None,
+            force_optimize: false,
+            encode_config: false,
+            templates_dir: None,
+            telemetry: true,
+            roblox_api_key: None,
+            place_owner_user: None,
+            place_owner_group: None,
+            presets: BTreeMap::new(),
+            input: InputOptions::default(),
         }
     }
 }
 
 impl Config {
-    /// Load configuration from file
+    /// Load configuration from ~/.luau-obfuscator/config.toml, if present
     pub fn load() -> anyhow::Result<Self> {
-        // TODO: Implement config loading from ~/.luau-obfuscator/config.toml
-        Ok(Self::default())
+        let path = Self::config_dir().join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        toml::from_str(&content).map_err(|e| {
+            ObfuscatorError::ConfigError(format!("Invalid config file {:?}: {}", path, e)).into()
+        })
+    }
+
+    /// Sensitivity rules: configured rules followed by the built-in defaults
+    pub fn sensitivity_rules(&self) -> anyhow::Result<SensitivityRules> {
+        SensitivityRules::new(&self.sensitivity_rules)
+    }
+
+    /// Configured plaintext rules followed by `patterns` (from the command line)
+    pub fn plaintext_rules(&self, patterns: &[String]) -> anyhow::Result<PlaintextRules> {
+        let extra = patterns.iter().map(|pattern| PlaintextRule {
+            name: None,
+            pattern: pattern.clone(),
+        });
+        let rules: Vec<PlaintextRule> =
+            self.plaintext_strings.iter().cloned().chain(extra).collect();
+        PlaintextRules::new(&rules)
+    }
+
+    /// Built-in and configured presets, by name
+    pub fn presets(&self) -> BTreeMap<String, Preset> {
+        let mut presets = Preset::builtin();
+        presets.extend(self.presets.clone());
+        presets
+    }
+
+    /// The preset called `name`
+    pub fn preset(&self, name: &str) -> anyhow::Result<Preset> {
+        let mut presets = self.presets();
+        presets.remove(name).ok_or_else(|| {
+            let available: Vec<String> = presets.into_keys().collect();
+            ObfuscatorError::ConfigError(format!(
+                "Unknown preset {:?} (available: {})",
+                name,
+                available.join(", ")
+            ))
+            .into()
+        })
+    }
+
+    /// Per-user configuration directory (~/.luau-obfuscator)
+    pub fn config_dir() -> PathBuf {
+        std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".luau-obfuscator")
+    }
+
+    /// Cache directory, defaulting to ~/.luau-obfuscator/cache
+    pub fn resolved_cache_dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(|| Self::config_dir().join("cache"))
+    }
+
+    /// Directory holding cached license validation tokens
+    pub fn validation_token_dir() -> PathBuf {
+        Self::config_dir().join("tokens")
+    }
+
+    /// Queue of usage events waiting to be uploaded
+    pub fn telemetry_queue_path() -> PathBuf {
+        Self::config_dir().join("telemetry.jsonl")
     }
 
     /// Save configuration to file
@@ -43,3 +207,144 @@ impl Config {
         Ok(())
     }
 }
+
+/// Protection profile for one kind of script
+///
+/// Tunes the tier's settings: fields left unset keep the tier's value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    /// What the preset is for
+    pub description: String,
+
+    pub encrypt_all_strings: Option<bool>,
+    pub obfuscate_constants: Option<bool>,
+    pub mangle_functions: Option<bool>,
+    pub flatten_control_flow: Option<bool>,
+    pub outline_functions: Option<bool>,
+    pub inject_dead_code: Option<bool>,
+    pub dead_code_density: Option<f32>,
+    pub honeypot_traps: Option<usize>,
+    pub use_constant_pool: Option<bool>,
+    pub string_fragment_size: Option<usize>,
+
+    /// Emit the environment guard that detects hooked globals
+    pub anti_hook: Option<bool>,
+
+    /// Globals the guard checks in addition to the default ones
+    pub guarded_globals: Vec<String>,
+
+    /// What the script does when a check fails
+    pub tamper_policy: Option<TamperPolicy>,
+
+    /// How decrypted strings of each sensitivity are kept at runtime
+    pub anti_dump: Option<AntiDumpPolicy>,
+
+    /// Look for exploit executor globals at startup
+    pub executor_check: Option<ExecutorCheck>,
+
+    /// Never flatten functions connected to these signals (`RenderStepped`,
+    /// `Heartbeat`, ...), which run every frame
+    pub unflattened_events: Vec<String>,
+
+    /// Keep the names of the fields a module returns, and of the functions
+    /// behind them
+    pub preserve_exports: bool,
+}
+
+impl Preset {
+    /// The presets shipped with the tool
+    pub fn builtin() -> BTreeMap<String, Preset> {
+        toml::from_str(BUILTIN_PRESETS).expect("built-in presets are valid")
+    }
+
+    /// `base` (the tier's settings) as tuned by this preset
+    pub fn settings(&self, base: ObfuscationSettings) -> ObfuscationSettings {
+        ObfuscationSettings {
+            encrypt_all_strings: self.encrypt_all_strings.unwrap_or(base.encrypt_all_strings),
+            obfuscate_constants: self.obfuscate_constants.unwrap_or(base.obfuscate_constants),
+            mangle_functions: self.mangle_functions.unwrap_or(base.mangle_functions),
+            flatten_control_flow: self.flatten_control_flow.unwrap_or(base.flatten_control_flow),
+            outline_functions: self.outline_functions.unwrap_or(base.outline_functions),
+            inject_dead_code: self.inject_dead_code.unwrap_or(base.inject_dead_code),
+            dead_code_density: self.dead_code_density.unwrap_or(base.dead_code_density),
+            honeypot_traps: self.honeypot_traps.unwrap_or(base.honeypot_traps),
+            use_constant_pool: self.use_constant_pool.unwrap_or(base.use_constant_pool),
+            string_fragment_size: self.string_fragment_size.or(base.string_fragment_size),
+            ..base
+        }
+    }
+
+    /// Apply the runtime guard settings to `config`
+    pub fn apply_codegen(&self, config: &mut CodeGenConfig) {
+        if let Some(anti_hook) = self.anti_hook {
+            config.anti_hook = anti_hook;
+        }
+        for global in &self.guarded_globals {
+            if !config.guarded_globals.contains(global) {
+                config.guarded_globals.push(global.clone());
+            }
+        }
+        if let Some(policy) = &self.tamper_policy {
+            config.tamper_policy = policy.clone();
+        }
+        if let Some(policy) = self.anti_dump {
+            config.anti_dump = policy;
+        }
+        if let Some(check) = &self.executor_check {
+            config.executor_check = Some(check.clone());
+        }
+    }
+
+    /// Functions and names of `source` the preset leaves alone
+    pub fn exclusions(&self, source: &str) -> anyhow::Result<Exclusions> {
+        let mut exclusions = Exclusions::default();
+        if !self.unflattened_events.is_empty() {
+            exclusions.functions = event_handlers(source, &self.unflattened_events)?;
+        }
+        if self.preserve_exports {
+            let exports = module_exports(source)?;
+            exclusions.names.extend(exports.table);
+            exclusions.names.extend(exports.fields);
+        }
+        Ok(exclusions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obfuscation::ObfuscationTier;
+
+    #[test]
+    fn test_builtin_presets_tune_the_tier() {
+        let presets = Config::default().presets();
+        let names: Vec<&str> = presets.keys().map(String::as_str).collect();
+        assert_eq!(names, ["admin-panel", "anti-cheat", "gun-system", "loader", "ui-library"]);
+
+        let anti_cheat = &presets["anti-cheat"];
+        let settings = anti_cheat.settings(ObfuscationTier::Standard.settings());
+        assert!(settings.use_constant_pool);
+        assert_eq!(settings.string_fragment_size, Some(8));
+        // Unset fields keep the tier's value
+        assert!(settings.environment_constants);
+
+        let mut config = CodeGenConfig::default();
+        anti_cheat.apply_codegen(&mut config);
+        assert_eq!(config.tamper_policy, TamperPolicy::SilentExit);
+        assert!(config.guarded_globals.iter().any(|g| g == "task.spawn"));
+
+        let source = "local Library = {}\n\
+                      function Library.createButton(text) end\n\
+                      return Library\n";
+        let exclusions = presets["ui-library"].exclusions(source).unwrap();
+        assert!(exclusions.names.contains("createButton"));
+        assert!(exclusions.names.contains("Library"));
+    }
+
+    #[test]
+    fn test_unknown_preset_lists_available() {
+        let err = Config::default().preset("sniper").unwrap_err().to_string();
+        assert!(err.contains("anti-cheat"));
+    }
+}
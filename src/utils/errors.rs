@@ -1,12 +1,40 @@
 //! Error types for the obfuscator
+//!
+//! Every error category maps to a distinct process exit code so build
+//! scripts can tell a syntax error in the input apart from a rejected
+//! license or an unreachable API.
 
+use std::fmt;
 use thiserror::Error;
 
+/// Exit code for errors outside the taxonomy
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit code table shown in `--help`
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+   0  Success
+   1  Unexpected failure
+   3  Configuration error (invalid config, arguments or input files)
+   4  I/O error (file could not be read or written)
+   5  Denied diagnostic (a warning matched `--deny`)
+  10  Parse error (input is not valid Luau)
+  11  Analysis error
+  12  Obfuscation error
+  13  Code generation error
+  20  Cryptography error (wrong password, corrupted data, changed test vectors)
+  21  License error (license invalid, expired or not bound to this user)
+  30  API error (license server unreachable or rejected the request)";
+
 #[derive(Error, Debug)]
 pub enum ObfuscatorError {
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    /// Parse failure with a source location
+    #[error("{0}")]
+    SyntaxError(Box<SourceDiagnostic>),
+
     #[error("Analysis error: {0}")]
     AnalysisError(String),
 
@@ -19,6 +47,9 @@ pub enum ObfuscatorError {
     #[error("Code generation error: {0}")]
     CodeGenError(String),
 
+    #[error("License error: {0}")]
+    LicenseError(String),
+
     #[error("API error: {0}")]
     ApiError(String),
 
@@ -27,4 +58,152 @@ pub enum ObfuscatorError {
 
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
+
+    /// A diagnostic matched `--deny`
+    #[error("Denied diagnostic: {0}")]
+    DeniedDiagnostic(String),
+}
+
+impl ObfuscatorError {
+    /// Process exit code for this error category
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ObfuscatorError::ConfigError(_) => 3,
+            ObfuscatorError::IoError(_) => 4,
+            ObfuscatorError::DeniedDiagnostic(_) => 5,
+            ObfuscatorError::ParseError(_) | ObfuscatorError::SyntaxError(_) => 10,
+            ObfuscatorError::AnalysisError(_) => 11,
+            ObfuscatorError::ObfuscationError(_) => 12,
+            ObfuscatorError::CodeGenError(_) => 13,
+            ObfuscatorError::CryptoError(_) => 20,
+            ObfuscatorError::LicenseError(_) => 21,
+            ObfuscatorError::ApiError(_) => 30,
+        }
+    }
+}
+
+/// Exit code for an error chain, using the first categorized cause
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|cause| {
+            cause
+                .downcast_ref::<ObfuscatorError>()
+                .map(ObfuscatorError::exit_code)
+                .or_else(|| cause.downcast_ref::<std::io::Error>().map(|_| 4))
+        })
+        .unwrap_or(EXIT_FAILURE)
+}
+
+/// An error pointing at a location in Luau source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDiagnostic {
+    pub message: String,
+    /// File name shown in the location line, if known
+    pub origin: Option<String>,
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+    /// Text of the offending line
+    pub source_line: String,
+}
+
+impl SourceDiagnostic {
+    /// Build a diagnostic, capturing the offending line from `source`
+    pub fn new(message: impl Into<String>, source: &str, line: usize, column: usize) -> Self {
+        Self {
+            message: message.into(),
+            origin: None,
+            line,
+            column,
+            source_line: source
+                .lines()
+                .nth(line.saturating_sub(1))
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// Attach the file name the source was read from
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+}
+
+impl fmt::Display for SourceDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gutter = self.line.to_string().len();
+        let origin = self.origin.as_deref().unwrap_or("<input>");
+
+        writeln!(f, "Parse error: {}", self.message)?;
+        writeln!(f, "{:gutter$}--> {}:{}:{}", "", origin, self.line, self.column)?;
+        writeln!(f, "{:gutter$} |", "")?;
+        writeln!(f, "{} | {}", self.line, self.source_line)?;
+        write!(
+            f,
+            "{:gutter$} | {}^",
+            "",
+            " ".repeat(self.column.saturating_sub(1))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            ObfuscatorError::ConfigError(String::new()),
+            ObfuscatorError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)),
+            ObfuscatorError::DeniedDiagnostic(String::new()),
+            ObfuscatorError::ParseError(String::new()),
+            ObfuscatorError::AnalysisError(String::new()),
+            ObfuscatorError::ObfuscationError(String::new()),
+            ObfuscatorError::CodeGenError(String::new()),
+            ObfuscatorError::CryptoError(String::new()),
+            ObfuscatorError::LicenseError(String::new()),
+            ObfuscatorError::ApiError(String::new()),
+        ];
+
+        let mut codes: Vec<i32> = errors.iter().map(|e| e.exit_code()).collect();
+        for code in &codes {
+            // Every code is documented in --help
+            assert!(EXIT_CODES_HELP.contains(&format!("{:>4}  ", code)));
+        }
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+    }
+
+    #[test]
+    fn test_exit_code_looks_through_context() {
+        let err: anyhow::Error = Err::<(), _>(ObfuscatorError::LicenseError("expired".to_string()))
+            .context("Protecting script.lua")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 21);
+
+        let io: anyhow::Error = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::NotFound))
+            .context("Failed to read input file")
+            .unwrap_err();
+        assert_eq!(exit_code(&io), 4);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("boom")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_source_diagnostic_rendering() {
+        let source = "local x = 1\nfunction foo( { return\n";
+        let diagnostic = SourceDiagnostic::new("unexpected token `{`", source, 2, 15)
+            .with_origin("script.lua");
+
+        let rendered = diagnostic.to_string();
+        assert!(rendered.contains(" --> script.lua:2:15"));
+        assert!(rendered.contains("2 | function foo( { return"));
+        assert!(rendered.ends_with(&format!("  | {}^", " ".repeat(14))));
+    }
 }
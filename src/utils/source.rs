@@ -0,0 +1,290 @@
+//! Reading script files
+//!
+//! Scripts exported from Windows tools often start with a byte order mark,
+//! use CRLF line endings, or hold Latin-1 bytes inside string literals. The
+//! loader strips the mark, reads UTF-16 files, turns every line ending into
+//! `\n` (Luau reads `\r\n` inside long strings as `\n` too) and deals with
+//! bytes that aren't UTF-8 as configured. The line ending the file used is
+//! remembered so the protected script can be written the same way.
+
+use super::errors::{ObfuscatorError, SourceDiagnostic};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// What to do with bytes that aren't UTF-8
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidBytes {
+    /// Stop with the line and column of the first one
+    #[default]
+    Error,
+    /// Write each as a `\ddd` escape, so string literals keep their exact
+    /// bytes at runtime (not inside `[[long strings]]` or comments)
+    Escape,
+    /// Read each as a Latin-1 character; string literals hold its UTF-8
+    /// encoding at runtime, which is what Roblox displays
+    Latin1,
+}
+
+/// Line endings of protected output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndings {
+    /// Whatever the input used
+    #[default]
+    Preserve,
+    Lf,
+    Crlf,
+}
+
+/// The `[input]` config section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputOptions {
+    /// Drop a leading UTF-8 byte order mark (the parser doesn't accept one)
+    pub strip_bom: bool,
+    pub invalid_bytes: InvalidBytes,
+    pub line_endings: LineEndings,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        Self {
+            strip_bom: true,
+            invalid_bytes: InvalidBytes::Error,
+            line_endings: LineEndings::Preserve,
+        }
+    }
+}
+
+/// A line ending style found in a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// A script as the pipeline sees it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Decoded text with `\n` line endings
+    pub text: String,
+    /// Encoding named by a byte order mark that was removed
+    pub bom: Option<&'static str>,
+    /// Line ending most lines used
+    pub line_ending: LineEnding,
+    /// Bytes that weren't UTF-8 (escaped or read as Latin-1)
+    pub invalid_bytes: usize,
+}
+
+impl SourceFile {
+    /// Read and decode a script file
+    pub fn read(path: &Path, options: &InputOptions) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read input file: {:?}", path))?;
+        Self::decode(&bytes, &path.display().to_string(), options)
+    }
+
+    /// Read and decode a script from stdin
+    pub fn read_stdin(options: &InputOptions) -> Result<Self> {
+        use std::io::Read;
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("Failed to read the script from stdin")?;
+        Self::decode(&bytes, "<stdin>", options)
+    }
+
+    /// Decode script bytes; `origin` names them in diagnostics
+    pub fn decode(bytes: &[u8], origin: &str, options: &InputOptions) -> Result<Self> {
+        let (text, bom, invalid_bytes) = if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+            (
+                decode_utf16(rest, u16::from_le_bytes, origin)?,
+                Some("UTF-16 LE"),
+                0,
+            )
+        } else if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+            (
+                decode_utf16(rest, u16::from_be_bytes, origin)?,
+                Some("UTF-16 BE"),
+                0,
+            )
+        } else {
+            let (bytes, bom) = match bytes.strip_prefix(UTF8_BOM) {
+                Some(rest) if options.strip_bom => (rest, Some("UTF-8")),
+                _ => (bytes, None),
+            };
+            let (text, invalid) = decode_utf8(bytes, origin, options.invalid_bytes)?;
+            (text, bom, invalid)
+        };
+
+        let crlf = text.matches("\r\n").count();
+        let line_ending = if crlf * 2 > text.matches('\n').count() {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        };
+        Ok(Self {
+            text: text.replace("\r\n", "\n").replace('\r', "\n"),
+            bom,
+            line_ending,
+            invalid_bytes,
+        })
+    }
+
+    /// The line ending output should be written with
+    pub fn output_line_ending(&self, options: &InputOptions) -> LineEnding {
+        match options.line_endings {
+            LineEndings::Preserve => self.line_ending,
+            LineEndings::Lf => LineEnding::Lf,
+            LineEndings::Crlf => LineEnding::Crlf,
+        }
+    }
+
+    /// `output` (written with `\n`) in the line ending this file's output uses
+    pub fn restore_line_endings(&self, output: &str, options: &InputOptions) -> String {
+        match self.output_line_ending(options) {
+            LineEnding::Lf => output.to_string(),
+            LineEnding::Crlf => output.replace("\r\n", "\n").replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Decode UTF-8, handling invalid bytes as `mode` says
+fn decode_utf8(bytes: &[u8], origin: &str, mode: InvalidBytes) -> Result<(String, usize)> {
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid = 0;
+    let mut rest = bytes;
+    loop {
+        let e = match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return Ok((text, invalid));
+            }
+            Err(e) => e,
+        };
+        let (valid, after) = rest.split_at(e.valid_up_to());
+        text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+        let len = e.error_len().unwrap_or(after.len());
+        for &byte in &after[..len] {
+            match mode {
+                InvalidBytes::Error => return Err(undecodable(&text, bytes, byte, origin)),
+                // Always three digits, so a digit after it isn't read as part of it
+                InvalidBytes::Escape => text.push_str(&format!("\\{:03}", byte)),
+                InvalidBytes::Latin1 => text.push(char::from(byte)),
+            }
+            invalid += 1;
+        }
+        rest = &after[len..];
+    }
+}
+
+/// Decode UTF-16 code units read by `unit`
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16, origin: &str) -> Result<String> {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    match String::from_utf16(&units) {
+        Ok(text) if bytes.len() % 2 == 0 => Ok(text),
+        _ => Err(ObfuscatorError::ParseError(format!(
+            "{} starts with a UTF-16 byte order mark but isn't valid UTF-16",
+            origin
+        ))
+        .into()),
+    }
+}
+
+/// Diagnostic for the first byte that isn't UTF-8 (`before` is the text read so far)
+fn undecodable(before: &str, bytes: &[u8], byte: u8, origin: &str) -> anyhow::Error {
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    let message = format!(
+        "byte 0x{:02X} is not valid UTF-8 (files from Windows tools are often Latin-1); \
+         re-save the file as UTF-8, or set `invalid_bytes = \"escape\"` or \"latin1\" \
+         under [input] in the config (or pass --invalid-bytes)",
+        byte
+    );
+    let source = String::from_utf8_lossy(bytes).replace("\r\n", "\n");
+    ObfuscatorError::SyntaxError(Box::new(
+        SourceDiagnostic::new(message, &source, line, column).with_origin(origin),
+    ))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], invalid_bytes: InvalidBytes) -> Result<SourceFile> {
+        let options = InputOptions {
+            invalid_bytes,
+            ..Default::default()
+        };
+        SourceFile::decode(bytes, "test.lua", &options)
+    }
+
+    #[test]
+    fn test_bom_and_crlf_are_normalized_and_restored() {
+        let file = decode(
+            b"\xEF\xBB\xBFlocal a = 1\r\nprint(a)\r\n",
+            InvalidBytes::Error,
+        )
+        .unwrap();
+        assert_eq!(file.text, "local a = 1\nprint(a)\n");
+        assert_eq!(file.bom, Some("UTF-8"));
+        assert_eq!(file.line_ending, LineEnding::Crlf);
+
+        let options = InputOptions::default();
+        assert_eq!(file.restore_line_endings("x\ny\n", &options), "x\r\ny\r\n");
+        let lf = InputOptions {
+            line_endings: LineEndings::Lf,
+            ..Default::default()
+        };
+        assert_eq!(file.restore_line_endings("x\ny\n", &lf), "x\ny\n");
+
+        let utf16: Vec<u8> = b"\xFF\xFE"
+            .iter()
+            .copied()
+            .chain("print('é')\n".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let file = decode(&utf16, InvalidBytes::Error).unwrap();
+        assert_eq!(file.text, "print('é')\n");
+        assert_eq!(file.line_ending, LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        let source = b"local a = 1\nlocal name = \"caf\xE9\"\n";
+
+        let error = decode(source, InvalidBytes::Error).unwrap_err();
+        match error.downcast_ref::<ObfuscatorError>() {
+            Some(ObfuscatorError::SyntaxError(diagnostic)) => {
+                assert_eq!((diagnostic.line, diagnostic.column), (2, 18));
+                assert!(diagnostic.message.contains("0xE9"));
+                assert_eq!(diagnostic.origin.as_deref(), Some("test.lua"));
+            }
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+
+        let escaped = decode(source, InvalidBytes::Escape).unwrap();
+        assert_eq!(escaped.text, "local a = 1\nlocal name = \"caf\\233\"\n");
+        assert_eq!(escaped.invalid_bytes, 1);
+
+        let latin1 = decode(source, InvalidBytes::Latin1).unwrap();
+        assert_eq!(latin1.text, "local a = 1\nlocal name = \"café\"\n");
+    }
+}
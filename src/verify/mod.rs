@@ -0,0 +1,357 @@
+//! Differential execution
+//!
+//! Runs an original script and its protected build in an embedded Luau VM
+//! and compares what each one does that a player could see: `print` and
+//! `warn` output, tasks that errored, and the values the chunk returns (or
+//! that it failed). Tables are compared by a snapshot of their contents, not
+//! their address.
+//!
+//! Both runs start from the same deterministic stand-ins for the Roblox
+//! environment (`stubs.lua`): services and Instances are stubs, clocks stand
+//! still, `math.random` is seeded the same way and `task.defer` work runs
+//! after the chunk. Error messages are not compared, since they name lines
+//! and locals the protected build renamed; only whether each run failed.
+//!
+//! Run the protected build as `ObfuscationPipeline::verification_script`
+//! assembles it: the license and binding checks depend on the live
+//! environment, not on the script.
+
+use crate::utils::errors::ObfuscatorError;
+use anyhow::Result;
+use mlua::{Function, Lua, MultiValue, Table, VmState};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Roblox stand-ins, run before each script
+const STUBS: &str = include_str!("stubs.lua");
+
+/// Error raised from the interrupt when a run is out of steps
+const STEP_LIMIT_ERROR: &str = "step limit reached";
+
+/// Default cap on VM steps (Luau checks in at calls and loop iterations)
+pub const DEFAULT_MAX_STEPS: u64 = 20_000_000;
+
+/// Default cap on VM memory
+pub const DEFAULT_MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Something a run did that a player could see
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Print(String),
+    Warn(String),
+    /// A `task.spawn`/`task.defer` callback raised an error
+    TaskError,
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Print(text) => write!(f, "print({:?})", text),
+            Event::Warn(text) => write!(f, "warn({:?})", text),
+            Event::TaskError => write!(f, "a task errored"),
+        }
+    }
+}
+
+/// How a run ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Snapshots of the values the chunk returned
+    Returned(Vec<String>),
+    /// The chunk raised an error (the message, for reports only)
+    Failed(String),
+    /// The run used up its steps, e.g. in a `while true do wait() end` loop
+    StepLimit,
+}
+
+impl Outcome {
+    /// Whether two runs ended the same way
+    fn matches(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (Outcome::Failed(_), Outcome::Failed(_)) => true,
+            _ => self == other,
+        }
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Returned(values) if values.is_empty() => write!(f, "returned nothing"),
+            Outcome::Returned(values) => write!(f, "returned {}", values.join(", ")),
+            Outcome::Failed(message) => write!(f, "failed: {}", message),
+            Outcome::StepLimit => write!(f, "ran out of steps"),
+        }
+    }
+}
+
+/// Everything one run did, in order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Observation {
+    pub events: Vec<Event>,
+    pub outcome: Outcome,
+}
+
+/// The first point where two runs part ways
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Mismatch {
+    /// The runs differ at event `index`; `None` when one run had no more
+    Event {
+        index: usize,
+        original: Option<Event>,
+        protected: Option<Event>,
+    },
+    Outcome { original: Outcome, protected: Outcome },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let event = |event: &Option<Event>| match event {
+            Some(event) => event.to_string(),
+            None => "nothing more".to_string(),
+        };
+        match self {
+            Mismatch::Event {
+                index,
+                original,
+                protected,
+            } => write!(
+                f,
+                "output #{} differs: the original did {}, the protected build {}",
+                index + 1,
+                event(original),
+                event(protected)
+            ),
+            Mismatch::Outcome {
+                original,
+                protected,
+            } => write!(
+                f,
+                "the original {}, the protected build {}",
+                original, protected
+            ),
+        }
+    }
+}
+
+/// Both runs and where they differ, if anywhere
+#[derive(Debug, Clone, Serialize)]
+pub struct DifferentialReport {
+    pub original: Observation,
+    pub protected: Observation,
+    pub mismatch: Option<Mismatch>,
+}
+
+impl DifferentialReport {
+    /// Compare two runs
+    ///
+    /// When both ran out of steps the protected build (which also decrypts)
+    /// got less far, so only the events both reached are compared.
+    pub fn new(original: Observation, protected: Observation) -> Self {
+        let both_cut = original.outcome == Outcome::StepLimit
+            && protected.outcome == Outcome::StepLimit;
+        let compared = if both_cut {
+            original.events.len().min(protected.events.len())
+        } else {
+            original.events.len().max(protected.events.len())
+        };
+        let mismatch = (0..compared)
+            .find(|&i| original.events.get(i) != protected.events.get(i))
+            .map(|index| Mismatch::Event {
+                index,
+                original: original.events.get(index).cloned(),
+                protected: protected.events.get(index).cloned(),
+            })
+            .or_else(|| {
+                (!original.outcome.matches(&protected.outcome)).then(|| Mismatch::Outcome {
+                    original: original.outcome.clone(),
+                    protected: protected.outcome.clone(),
+                })
+            });
+        Self {
+            original,
+            protected,
+            mismatch,
+        }
+    }
+
+    /// Whether the protected build behaved like the original
+    pub fn is_equivalent(&self) -> bool {
+        self.mismatch.is_none()
+    }
+}
+
+/// Runs scripts in a fresh Luau VM with the Roblox stand-ins
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    max_steps: u64,
+    memory_limit: usize,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self {
+            max_steps: DEFAULT_MAX_STEPS,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+
+    /// Stop each run after `max_steps` VM steps
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Run `original` and `protected` and compare what they did
+    pub fn compare(&self, original: &str, protected: &str) -> Result<DifferentialReport> {
+        let original = self.run(original).map_err(|e| {
+            ObfuscatorError::ParseError(format!("The original doesn't run in Luau: {}", e))
+        })?;
+        let protected = self.run(protected).map_err(|e| {
+            ObfuscatorError::ObfuscationError(format!(
+                "The protected build doesn't run in Luau: {}",
+                e
+            ))
+        })?;
+        Ok(DifferentialReport::new(original, protected))
+    }
+
+    /// Run one script
+    ///
+    /// Fails only when the VM can't be set up or the script doesn't compile;
+    /// a script that raises an error ends with `Outcome::Failed`.
+    pub fn run(&self, source: &str) -> Result<Observation> {
+        let lua = Lua::new();
+        lua.set_memory_limit(self.memory_limit).map_err(vm_error)?;
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let record = lua
+            .create_function(move |_, (kind, text): (String, String)| {
+                sink.borrow_mut().push(match kind.as_str() {
+                    "warn" => Event::Warn(text),
+                    "task_error" => Event::TaskError,
+                    _ => Event::Print(text),
+                });
+                Ok(())
+            })
+            .map_err(vm_error)?;
+        lua.globals().set("__record", record).map_err(vm_error)?;
+        let host: Table = lua.load(STUBS).set_name("stubs").eval().map_err(vm_error)?;
+        let snapshot: Function = host.get("snapshot").map_err(vm_error)?;
+        let drain: Function = host.get("drain").map_err(vm_error)?;
+
+        let chunk = lua
+            .load(source)
+            .set_name("script")
+            .into_function()
+            .map_err(vm_error)?;
+
+        let steps = Rc::new(Cell::new(0u64));
+        let counter = steps.clone();
+        let max_steps = self.max_steps;
+        lua.set_interrupt(move |_| {
+            counter.set(counter.get() + 1);
+            if counter.get() > max_steps {
+                return Err(mlua::Error::runtime(STEP_LIMIT_ERROR));
+            }
+            Ok(VmState::Continue)
+        });
+
+        let result = chunk.call::<_, MultiValue>(()).and_then(|values| {
+            drain.call::<_, ()>(())?;
+            values
+                .into_iter()
+                .map(|value| snapshot.call::<_, String>(value))
+                .collect::<mlua::Result<Vec<String>>>()
+        });
+        let outcome = match result {
+            Ok(values) => Outcome::Returned(values),
+            Err(_) if steps.get() > max_steps => Outcome::StepLimit,
+            Err(e) => Outcome::Failed(first_line(&e.to_string())),
+        };
+        lua.remove_interrupt();
+
+        let events = events.take();
+        Ok(Observation { events, outcome })
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn vm_error(e: mlua::Error) -> ObfuscatorError {
+    ObfuscatorError::ObfuscationError(first_line(&e.to_string()))
+}
+
+/// Error message without the traceback
+fn first_line(message: &str) -> String {
+    message.lines().next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_are_deterministic() {
+        let source = r#"
+local Players = game:GetService("Players")
+print("hello", 1 + 2, { b = 2, a = { 1, 2 } }, Players.Name)
+warn(typeof(Vector3.new(1, 2, 3)), tick(), tostring({}))
+task.defer(print, "later")
+task.spawn(error, "boom")
+return { math.random(1, 100), math.random() }, os.clock()
+"#;
+        let sandbox = Sandbox::new();
+        let first = sandbox.run(source).unwrap();
+        assert_eq!(first, sandbox.run(source).unwrap());
+        assert_eq!(
+            first.events,
+            vec![
+                Event::Print(
+                    "hello\t3\t{[\"a\"] = {[1] = 1, [2] = 2}, [\"b\"] = 2}\tPlayers".into()
+                ),
+                Event::Warn("Vector3\t1700000000\ttable".into()),
+                Event::TaskError,
+                Event::Print("later".into()),
+            ]
+        );
+        assert!(matches!(&first.outcome, Outcome::Returned(values) if values[1] == "0"));
+    }
+
+    #[test]
+    fn test_compare_finds_first_difference() {
+        let sandbox = Sandbox::new().with_max_steps(10_000);
+        let same = sandbox
+            .compare("print('a') return 1", "local x = 'a' print(x) return 1")
+            .unwrap();
+        assert!(same.is_equivalent());
+
+        let differs = sandbox.compare("print('a') print('b')", "print('a') print('c')").unwrap();
+        assert_eq!(
+            differs.mismatch,
+            Some(Mismatch::Event {
+                index: 1,
+                original: Some(Event::Print("b".into())),
+                protected: Some(Event::Print("c".into())),
+            })
+        );
+
+        // Different messages, but both failed
+        let failed = sandbox.compare("error('x')", "local y = nil; y()").unwrap();
+        assert!(failed.is_equivalent());
+
+        let looping = sandbox.compare("while true do end", "return 1").unwrap();
+        assert!(looping.mismatch.unwrap().to_string().contains("ran out of steps"));
+
+        assert!(sandbox.compare("local = 1", "return").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+-- Deterministic stand-ins for the Roblox environment
+--
+-- Run before each script in the differential sandbox. Every service,
+-- Instance, datatype and clock answers the same way on every run, so the
+-- original and protected scripts see the same world. `__record(kind, text)`
+-- is provided by the host; the returned table holds the host's helpers.
+
+local record = __record
+__record = nil
+
+local native_type = type
+local native_typeof = typeof
+local native_tostring = tostring
+local native_date = os.date
+
+-- 2023-11-14 22:13:20 UTC
+local FIXED_TIME = 1700000000
+
+-- Stub value -> { kind = typeof() answer, text = description }
+local stubs = setmetatable({}, { __mode = "k" })
+
+local describe
+
+local function key_order(a, b)
+    local ta, tb = native_type(a), native_type(b)
+    if ta ~= tb then
+        return ta < tb
+    end
+    if ta == "number" or ta == "string" then
+        return a < b
+    end
+    return describe(a, {}) < describe(b, {})
+end
+
+-- Same text for the same value on every run (no addresses)
+describe = function(value, seen)
+    local stub = stubs[value]
+    if stub then
+        return stub.text
+    end
+    local kind = native_type(value)
+    if kind == "string" then
+        return string.format("%q", value)
+    elseif kind == "table" then
+        if seen[value] then
+            return "<cycle>"
+        end
+        seen[value] = true
+        local keys = {}
+        for key in pairs(value) do
+            table.insert(keys, key)
+        end
+        table.sort(keys, key_order)
+        local parts = {}
+        for _, key in ipairs(keys) do
+            table.insert(parts, "[" .. describe(key, seen) .. "] = " .. describe(value[key], seen))
+        end
+        seen[value] = nil
+        return "{" .. table.concat(parts, ", ") .. "}"
+    elseif kind == "function" or kind == "thread" or kind == "userdata" or kind == "buffer" then
+        return kind
+    end
+    return native_tostring(value)
+end
+
+local function snapshot(value)
+    return describe(value, {})
+end
+
+function tostring(value)
+    local stub = stubs[value]
+    if stub then
+        return stub.text
+    end
+    local kind = native_type(value)
+    if kind == "table" then
+        local meta = getmetatable(value)
+        if native_type(meta) == "table" and rawget(meta, "__tostring") then
+            return native_tostring(value)
+        end
+        return "table"
+    elseif kind == "function" or kind == "thread" or kind == "userdata" or kind == "buffer" then
+        return kind
+    end
+    return native_tostring(value)
+end
+
+function typeof(value)
+    local stub = stubs[value]
+    if stub then
+        return stub.kind
+    end
+    return native_typeof(value)
+end
+
+local function show(...)
+    local parts = {}
+    for i = 1, select("#", ...) do
+        local value = select(i, ...)
+        if native_type(value) == "string" then
+            parts[i] = value
+        elseif native_type(value) == "table" and not stubs[value] then
+            parts[i] = snapshot(value)
+        else
+            parts[i] = tostring(value)
+        end
+    end
+    return table.concat(parts, "\t")
+end
+
+function print(...)
+    record("print", show(...))
+end
+
+function warn(...)
+    record("warn", show(...))
+end
+
+-- Instances: any property is a child Instance, any method call returns one
+local PROPERTIES = {
+    Name = function(self) return stubs[self].name end,
+    ClassName = function(self) return stubs[self].class end,
+    PlaceId = 0,
+    GameId = 0,
+    JobId = "",
+    CreatorId = 0,
+    PrivateServerId = "",
+    UserId = 1,
+    DisplayName = "Player1",
+}
+
+local instance
+
+instance = function(class, name, parent)
+    local children = {}
+    local object = setmetatable({}, {
+        __index = function(self, key)
+            local property = PROPERTIES[key]
+            if native_type(property) == "function" then
+                return property(self)
+            elseif property ~= nil then
+                return property
+            elseif key == "Parent" then
+                return parent
+            elseif native_type(key) ~= "string" then
+                -- So `ipairs(folder:GetChildren())` finds no children
+                return nil
+            end
+            local child = children[key]
+            if child == nil then
+                child = instance(key, key, self)
+                children[key] = child
+            end
+            return child
+        end,
+        -- `obj:Method(...)` calls the child named after the method
+        __call = function(self, receiver, first)
+            if native_type(first) == "string" then
+                return receiver[first]
+            end
+            return instance(name, name, receiver)
+        end,
+        __tostring = function(self)
+            return name
+        end,
+    })
+    stubs[object] = {
+        kind = "Instance",
+        text = "Instance(" .. name .. ")",
+        name = name,
+        class = class,
+    }
+    return object
+end
+
+game = instance("DataModel", "game")
+workspace = game.Workspace
+script = instance("Script", "Script", game.ServerScriptService)
+
+Instance = {
+    new = function(class, parent)
+        return instance(class, class, parent)
+    end,
+}
+
+-- Datatypes: `Name.new(...)` and friends describe themselves by their arguments
+local function datatype(kind)
+    local function construct(...)
+        local args = { ... }
+        local parts = {}
+        for i = 1, select("#", ...) do
+            parts[i] = describe(args[i], {})
+        end
+        local value = setmetatable({}, {
+            __index = function()
+                return 0
+            end,
+            __tostring = function()
+                return kind .. "(" .. table.concat(parts, ", ") .. ")"
+            end,
+        })
+        stubs[value] = { kind = kind, text = kind .. "(" .. table.concat(parts, ", ") .. ")" }
+        return value
+    end
+    return setmetatable({}, {
+        __index = function()
+            return construct
+        end,
+    })
+end
+
+for _, kind in ipairs({
+    "Vector3", "Vector2", "CFrame", "Color3", "UDim", "UDim2", "BrickColor", "TweenInfo",
+    "NumberRange", "NumberSequence", "ColorSequence", "Ray", "Rect", "Region3",
+}) do
+    _G[kind] = datatype(kind)
+end
+
+local function enum_path(path)
+    local item = setmetatable({}, {
+        __index = function(_, key)
+            return enum_path(path .. "." .. native_tostring(key))
+        end,
+        __tostring = function()
+            return path
+        end,
+    })
+    stubs[item] = { kind = "EnumItem", text = path }
+    return item
+end
+Enum = enum_path("Enum")
+
+-- Clocks stand still
+function tick()
+    return FIXED_TIME
+end
+
+function time()
+    return 0
+end
+
+function elapsedTime()
+    return 0
+end
+
+os.time = function()
+    return FIXED_TIME
+end
+
+os.clock = function()
+    return 0
+end
+
+os.date = function(format, at)
+    return native_date(format, at or FIXED_TIME)
+end
+
+-- Seeded the same way on every run
+math.randomseed(1)
+
+-- Yielding never resumes; deferred work runs in order after the script
+local deferred = {}
+
+local function run_task(callback, ...)
+    local ok = pcall(callback, ...)
+    if not ok then
+        record("task_error", "")
+    end
+end
+
+function wait()
+    return 0, FIXED_TIME
+end
+
+function spawn(callback)
+    run_task(callback)
+end
+
+function delay(_, callback)
+    table.insert(deferred, { callback })
+end
+
+task = {
+    wait = function()
+        return 0
+    end,
+    spawn = function(callback, ...)
+        run_task(callback, ...)
+    end,
+    defer = function(callback, ...)
+        table.insert(deferred, { callback, ... })
+    end,
+    delay = function(_, callback, ...)
+        table.insert(deferred, { callback, ... })
+    end,
+    cancel = function() end,
+}
+
+-- Modules aren't available: every require gets an empty table
+function require()
+    return {}
+end
+
+local function drain()
+    local i = 1
+    while i <= #deferred do
+        local entry = deferred[i]
+        run_task(table.unpack(entry))
+        i += 1
+    end
+end
+
+return {
+    snapshot = snapshot,
+    drain = drain,
+}
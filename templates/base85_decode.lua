@@ -0,0 +1,30 @@
+-- Base85 Decoder Template
+-- Decodes Z85 text embedded by the base85 encoding into a byte array (a trailing group of n + 1 digits holds n bytes)
+-- Template variables: none
+
+local _b85decode = (function()
+    local alphabet = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#"
+    local digits = {}
+    for i = 1, #alphabet do
+        digits[string.byte(alphabet, i)] = i - 1
+    end
+
+    return function(text)
+        local bytes, n = {}, 0
+        local len = #text
+        for i = 1, len, 5 do
+            local count = math.min(5, len - i + 1)
+            -- Missing digits of a trailing group are padded with the highest
+            local value = 0
+            for j = 0, 4 do
+                value = value * 85 + (j < count and digits[string.byte(text, i + j)] or 84)
+            end
+            -- Big-endian; doubles hold 85^5 exactly
+            for j = 1, count - 1 do
+                bytes[n + j] = math.floor(value / 256 ^ (4 - j)) % 256
+            end
+            n = n + count - 1
+        end
+        return bytes
+    end
+end)()
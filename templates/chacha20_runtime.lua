@@ -195,7 +195,11 @@ end
 
 -- Parse nonce from base64 string (12 bytes = 3 uint32)
 function ChaCha20.parse_nonce(nonce_b64)
-    local nonce_bytes = ChaCha20.base64_decode(nonce_b64)
+    return ChaCha20.nonce_from_bytes(ChaCha20.base64_decode(nonce_b64))
+end
+
+-- Nonce from a byte array (12 bytes = 3 uint32)
+function ChaCha20.nonce_from_bytes(nonce_bytes)
     local nonce = {}
     for i = 0, 2 do
         nonce[i+1] = bytes_to_uint32(
@@ -217,4 +221,12 @@ function ChaCha20.decrypt_string(encrypted_b64, key_b64, nonce_b64)
     return ChaCha20.bytes_to_string(plaintext)
 end
 
+-- Decrypt function for data embedded as byte arrays rather than base64
+function ChaCha20.decrypt_bytes(ciphertext, key_b64, nonce_bytes)
+    local key = ChaCha20.parse_key(key_b64)
+    local nonce = ChaCha20.nonce_from_bytes(nonce_bytes)
+    local plaintext = ChaCha20.crypt(key, nonce, ciphertext)
+    return ChaCha20.bytes_to_string(plaintext)
+end
+
 return ChaCha20
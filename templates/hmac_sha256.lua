@@ -0,0 +1,93 @@
+-- HMAC-SHA256 Template
+-- Checks the signature of update envelopes; returns the tag as lowercase hex
+-- Template variables: none
+
+local _hmac_sha256 = (function()
+    local band, bxor, bnot = bit32.band, bit32.bxor, bit32.bnot
+    local lshift, rshift, rrotate = bit32.lshift, bit32.rshift, bit32.rrotate
+    local byte, char, rep, format = string.byte, string.char, string.rep, string.format
+
+    local K = {
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    }
+
+    -- SHA-256 of `message` as a 32-byte string
+    local function sha256(message)
+        -- Pad to a multiple of 64 bytes, ending with the big-endian bit length
+        local bits = #message * 8
+        local length = table.create(8)
+        for i = 8, 1, -1 do
+            length[i] = bits % 256
+            bits = math.floor(bits / 256)
+        end
+        message = message .. "\128" .. rep("\0", (55 - #message) % 64) .. char(table.unpack(length, 1, 8))
+
+        local H = { 0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19 }
+        local w = table.create(64)
+        for block = 1, #message, 64 do
+            for i = 0, 15 do
+                local b1, b2, b3, b4 = byte(message, block + i * 4, block + i * 4 + 3)
+                w[i + 1] = lshift(b1, 24) + lshift(b2, 16) + lshift(b3, 8) + b4
+            end
+            for i = 17, 64 do
+                local x, y = w[i - 15], w[i - 2]
+                local s0 = bxor(rrotate(x, 7), rrotate(x, 18), rshift(x, 3))
+                local s1 = bxor(rrotate(y, 17), rrotate(y, 19), rshift(y, 10))
+                w[i] = (w[i - 16] + s0 + w[i - 7] + s1) % 4294967296
+            end
+
+            local a, b, c, d, e, f, g, h = H[1], H[2], H[3], H[4], H[5], H[6], H[7], H[8]
+            for i = 1, 64 do
+                local S1 = bxor(rrotate(e, 6), rrotate(e, 11), rrotate(e, 25))
+                local ch = bxor(band(e, f), band(bnot(e), g))
+                local t1 = (h + S1 + ch + K[i] + w[i]) % 4294967296
+                local S0 = bxor(rrotate(a, 2), rrotate(a, 13), rrotate(a, 22))
+                local maj = bxor(band(a, b), band(a, c), band(b, c))
+                h, g, f, e = g, f, e, (d + t1) % 4294967296
+                d, c, b, a = c, b, a, (t1 + S0 + maj) % 4294967296
+            end
+            H[1] = (H[1] + a) % 4294967296
+            H[2] = (H[2] + b) % 4294967296
+            H[3] = (H[3] + c) % 4294967296
+            H[4] = (H[4] + d) % 4294967296
+            H[5] = (H[5] + e) % 4294967296
+            H[6] = (H[6] + f) % 4294967296
+            H[7] = (H[7] + g) % 4294967296
+            H[8] = (H[8] + h) % 4294967296
+        end
+
+        local out = table.create(32)
+        for i = 1, 8 do
+            local word = H[i]
+            out[i * 4 - 3] = char(rshift(word, 24))
+            out[i * 4 - 2] = char(band(rshift(word, 16), 0xFF))
+            out[i * 4 - 1] = char(band(rshift(word, 8), 0xFF))
+            out[i * 4] = char(band(word, 0xFF))
+        end
+        return table.concat(out)
+    end
+
+    return function(key, message)
+        if #key > 64 then
+            key = sha256(key)
+        end
+        key = key .. rep("\0", 64 - #key)
+        local ipad, opad = table.create(64), table.create(64)
+        for i = 1, 64 do
+            local k = byte(key, i)
+            ipad[i] = char(bxor(k, 0x36))
+            opad[i] = char(bxor(k, 0x5C))
+        end
+        local tag = sha256(table.concat(opad) .. sha256(table.concat(ipad) .. message))
+        return (tag:gsub(".", function(c)
+            return format("%02x", byte(c))
+        end))
+    end
+end)()
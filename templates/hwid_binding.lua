@@ -1,21 +1,11 @@
 -- HWID Binding Template
--- Enforces hardware/user ID binding for license protection
--- Template variables: {{AUTHORIZED_USERID}}, {{AUTHORIZED_PLACEID}}, {{BINDING_MODE}}
-
-local AUTHORIZED_USERID = {{AUTHORIZED_USERID}} -- nil or number
-local AUTHORIZED_PLACEID = {{AUTHORIZED_PLACEID}} -- nil or number
-local BINDING_MODE = "{{BINDING_MODE}}" -- "userid", "placeid", "both", "whitelist"
+-- Enforces user, place, universe and group binding for license protection
+-- Template variables: {{BINDING_POLICY}}
 
 -- HWID validation state
 local _hwid_valid = false
 local _hwid_error = nil
 
--- Whitelist of authorized UserIds (for multi-user licenses)
-local AUTHORIZED_USERS = {
-    -- Populated by template processor if BINDING_MODE == "whitelist"
-    {{AUTHORIZED_USERS_LIST}}
-}
-
 -- Get current UserId
 local function get_user_id()
     local success, result = pcall(function()
@@ -33,9 +23,22 @@ local function get_user_id()
     return nil
 end
 
--- Get current PlaceId
-local function get_place_id()
-    return game.PlaceId
+-- Whether value is one of the authorized ids
+local function one_of(value, ids)
+    if value == nil then
+        return false
+    end
+    for _, id in ipairs(ids) do
+        if value == id then
+            return true
+        end
+    end
+    return false
+end
+
+-- Whether the experience is owned by a group
+local function owned_by_group(group_id)
+    return game.CreatorType == Enum.CreatorType.Group and game.CreatorId == group_id
 end
 
 -- Validate HWID binding
@@ -44,107 +47,16 @@ local function validate_hwid()
         return true
     end
     
-    if BINDING_MODE == "userid" then
-        -- Check UserId binding
-        if AUTHORIZED_USERID == nil then
-            _hwid_error = "No authorized UserId configured"
-            return false
-        end
-        
-        local current_userid = get_user_id()
-        if current_userid == nil then
-            _hwid_error = "Failed to get current UserId (server-side script?)"
-            return false
-        end
-        
-        if current_userid ~= AUTHORIZED_USERID then
-            _hwid_error = string.format(
-                "UserId mismatch: expected %d, got %d",
-                AUTHORIZED_USERID,
-                current_userid
-            )
-            error("[HWID] " .. _hwid_error)
-            return false
-        end
-        
-    elseif BINDING_MODE == "placeid" then
-        -- Check PlaceId binding
-        if AUTHORIZED_PLACEID == nil then
-            _hwid_error = "No authorized PlaceId configured"
-            return false
-        end
-        
-        local current_placeid = get_place_id()
-        if current_placeid ~= AUTHORIZED_PLACEID then
-            _hwid_error = string.format(
-                "PlaceId mismatch: expected %d, got %d",
-                AUTHORIZED_PLACEID,
-                current_placeid
-            )
-            error("[HWID] " .. _hwid_error)
-            return false
-        end
-        
-    elseif BINDING_MODE == "both" then
-        -- Check both UserId and PlaceId
-        if AUTHORIZED_USERID == nil or AUTHORIZED_PLACEID == nil then
-            _hwid_error = "Incomplete HWID configuration"
-            return false
-        end
-        
-        local current_userid = get_user_id()
-        local current_placeid = get_place_id()
-        
-        if current_userid == nil then
-            _hwid_error = "Failed to get current UserId"
-            return false
-        end
-        
-        if current_userid ~= AUTHORIZED_USERID then
-            _hwid_error = string.format("UserId mismatch: expected %d, got %d", 
-                AUTHORIZED_USERID, current_userid)
-            error("[HWID] " .. _hwid_error)
-            return false
-        end
-        
-        if current_placeid ~= AUTHORIZED_PLACEID then
-            _hwid_error = string.format("PlaceId mismatch: expected %d, got %d", 
-                AUTHORIZED_PLACEID, current_placeid)
-            error("[HWID] " .. _hwid_error)
-            return false
-        end
-        
-    elseif BINDING_MODE == "whitelist" then
-        -- Check if current UserId is in whitelist
-        local current_userid = get_user_id()
-        if current_userid == nil then
-            _hwid_error = "Failed to get current UserId"
-            return false
-        end
-        
-        local found = false
-        for _, authorized_id in ipairs(AUTHORIZED_USERS) do
-            if current_userid == authorized_id then
-                found = true
-                break
-            end
-        end
-        
-        if not found then
-            _hwid_error = string.format("UserId %d not in authorized whitelist", current_userid)
-            error("[HWID] " .. _hwid_error)
-            return false
-        end
-        
-    else
-        -- Invalid binding mode
-        _hwid_error = "Invalid HWID binding mode: " .. tostring(BINDING_MODE)
+    local ok, satisfied = pcall(function()
+        return {{BINDING_POLICY}}
+    end)
+    if not ok or not satisfied then
+        _hwid_error = "Binding policy not satisfied"
         return false
     end
     
     -- Validation successful
     _hwid_valid = true
-    print("[HWID] ✓ Hardware ID validated successfully")
     return true
 end
 
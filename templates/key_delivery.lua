@@ -0,0 +1,41 @@
+-- Key Delivery Template
+-- Rebuilds the decryption key from the embedded share and the shares a licensing plugin set on instances
+-- Template variables: {{EMBEDDED_SHARE}}, {{TAMPER}}; SOURCES lists each READER function
+
+local _key = (function()
+    local key = ChaCha20.base64_decode("{{EMBEDDED_SHARE}}")
+    local sources = {
+        {{#each SOURCES}}
+        {{READER}},
+        {{/each}}
+    }
+
+    for _, read in ipairs(sources) do
+        local ok, value = pcall(read)
+        local share = ok and type(value) == "string" and ChaCha20.base64_decode(value)
+        if not share or #share ~= #key then
+            {{TAMPER}}("key")
+            return nil
+        end
+        for i = 1, #key do
+            key[i] = bit32.bxor(key[i], share[i])
+        end
+    end
+
+    -- Re-encode for ChaCha20.decrypt_string
+    local alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+    local out = {}
+    for i = 1, #key, 3 do
+        local b, c = key[i + 1], key[i + 2]
+        local n = bit32.bor(bit32.lshift(key[i], 16), bit32.lshift(b or 0, 8), c or 0)
+        for j = 0, 3 do
+            if j < 2 or (j == 2 and b) or (j == 3 and c) then
+                local k = bit32.band(bit32.rshift(n, 18 - j * 6), 63) + 1
+                out[#out + 1] = string.sub(alphabet, k, k)
+            else
+                out[#out + 1] = "="
+            end
+        end
+    end
+    return table.concat(out)
+end)()
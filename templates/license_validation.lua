@@ -1,6 +1,7 @@
 -- License Validation Template
 -- Validates license key with API server and checks HWID binding
--- Template variables: {{LICENSE_KEY}}, {{SCRIPT_ID}}, {{API_ENDPOINT}}, {{WATERMARK}}
+-- Reports a session heartbeat so the server can enforce seat limits
+-- Template variables: {{LICENSE_KEY}}, {{SCRIPT_ID}}, {{API_ENDPOINT}}, {{WATERMARK}}; REVOKED lists each FINGERPRINT
 
 local LICENSE_KEY = "{{LICENSE_KEY}}"
 local SCRIPT_ID = "{{SCRIPT_ID}}"
@@ -11,12 +12,48 @@ local WATERMARK = "{{WATERMARK}}"
 local _license_validated = false
 local _validation_error = nil
 
+-- Error the API answers with once a license's concurrent servers or players are used up
+local SEAT_LIMIT_EXCEEDED = "seat_limit_exceeded"
+
+-- Seat session: the server's JobId, or a random id where there is none (Studio)
+local _session_id = game.JobId
+if _session_id == "" then
+    _session_id = game:GetService("HttpService"):GenerateGUID(false)
+end
+local _hwid = nil
+
+-- Fingerprints of revoked keys, rejected without asking the API
+local REVOKED_KEYS = {
+    {{#each REVOKED}}
+    ["{{FINGERPRINT}}"] = true,
+    {{/each}}
+}
+
+-- Two 32-bit hashes of "script:key", as in `revocation_fingerprint`
+local function key_fingerprint(key)
+    local text = SCRIPT_ID .. ":" .. key
+    local a, b = 2166136261, 5381
+    for i = 1, #text do
+        local byte = string.byte(text, i)
+        a = (a * 33 + byte) % 4294967296
+        b = (b * 131 + byte) % 4294967296
+    end
+    return string.format("%08x%08x", a, b)
+end
+
 -- Validate license (called at script startup)
 local function validate_license()
     if _license_validated then
         return true
     end
     
+    -- Revoked keys fail even when the API can't be reached
+    if REVOKED_KEYS[key_fingerprint(LICENSE_KEY)] then
+        _validation_error = "License revoked"
+        warn("[License] " .. _validation_error)
+        return false
+    end
+    
     -- Get HWID (Roblox UserId)
     local success, hwid = pcall(function()
         local Players = game:GetService("Players")
@@ -32,22 +69,25 @@ local function validate_license()
         _validation_error = "Failed to get HWID: " .. tostring(hwid)
         return false
     end
+    _hwid = hwid
     
     -- Attempt online validation (phone home)
     local online_valid = false
     local online_error = nil
+    local seats_exceeded = false
     
     pcall(function()
         local HttpService = game:GetService("HttpService")
         
         -- Build validation URL
         local url = string.format(
-            "%s/validate?key=%s&script=%s&hwid=%s&watermark=%s",
+            "%s/validate?key=%s&script=%s&hwid=%s&watermark=%s&session=%s",
             API_ENDPOINT,
             HttpService:UrlEncode(LICENSE_KEY),
             HttpService:UrlEncode(SCRIPT_ID),
             HttpService:UrlEncode(hwid),
-            HttpService:UrlEncode(WATERMARK)
+            HttpService:UrlEncode(WATERMARK),
+            HttpService:UrlEncode(_session_id)
         )
         
         -- Make HTTP request (GET)
@@ -60,9 +100,17 @@ local function validate_license()
             online_valid = true
         else
             online_error = data.error or "License validation failed"
+            seats_exceeded = data.error == SEAT_LIMIT_EXCEEDED
         end
     end)
     
+    -- The server answered, so a full license is enforced even in offline mode
+    if seats_exceeded then
+        _validation_error = "License seat limit reached"
+        warn("[License] " .. _validation_error)
+        return false
+    end
+    
     -- For now, allow offline mode (graceful degradation)
     -- In production, you might want to enforce online validation
     if not online_valid then
@@ -107,6 +155,47 @@ spawn(function()
     end
 end)
 
+-- Session heartbeat: keeps this server's seat and reports the player using it
+local HEARTBEAT_INTERVAL = 60 -- 1 minute
+
+local function send_heartbeat(closing)
+    local HttpService = game:GetService("HttpService")
+    local url = string.format(
+        "%s/heartbeat?key=%s&script=%s&hwid=%s&session=%s%s",
+        API_ENDPOINT,
+        HttpService:UrlEncode(LICENSE_KEY),
+        HttpService:UrlEncode(SCRIPT_ID),
+        HttpService:UrlEncode(_hwid),
+        HttpService:UrlEncode(_session_id),
+        closing and "&closing=1" or ""
+    )
+    return HttpService:JSONDecode(HttpService:GetAsync(url, true))
+end
+
+spawn(function()
+    while true do
+        wait(HEARTBEAT_INTERVAL)
+        if _license_validated and _hwid then
+            local ok, data = pcall(send_heartbeat, false)
+            -- Network failures keep the seat; only an explicit rejection drops it
+            if ok and data.valid == false and data.error == SEAT_LIMIT_EXCEEDED then
+                _license_validated = false
+                _validation_error = "License seat limit reached"
+                warn("[License] Seat limit reached - script may stop working")
+            end
+        end
+    end
+end)
+
+-- Free the seat when the server shuts down (not available on clients)
+pcall(function()
+    game:BindToClose(function()
+        if _hwid then
+            pcall(send_heartbeat, true)
+        end
+    end)
+end)
+
 -- Export validation API
 return {
     validate = validate_license,
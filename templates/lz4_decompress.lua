@@ -0,0 +1,54 @@
+-- LZ4 Decompressor Template
+-- Decompresses a decrypted LZ4 block (4-byte little-endian size prefix, then the block)
+-- Template variables: none (includes partials/join_bytes.lua)
+
+local function _decompress(input)
+    local byte = string.byte
+    local out, n = {}, 0
+    local pos, len = 5, #input
+
+    while pos <= len do
+        local token = byte(input, pos)
+        pos = pos + 1
+
+        -- Literal run (15 means more length bytes follow)
+        local literals = bit32.rshift(token, 4)
+        if literals == 15 then
+            repeat
+                local extra = byte(input, pos)
+                pos = pos + 1
+                literals = literals + extra
+            until extra ~= 255
+        end
+        for i = 1, literals do
+            out[n + i] = byte(input, pos + i - 1)
+        end
+        n = n + literals
+        pos = pos + literals
+
+        -- The last sequence has literals only
+        if pos > len then
+            break
+        end
+
+        -- Match: copy from `offset` bytes back, byte by byte so overlapping runs repeat
+        local offset = byte(input, pos) + byte(input, pos + 1) * 256
+        pos = pos + 2
+        local length = bit32.band(token, 15)
+        if length == 15 then
+            repeat
+                local extra = byte(input, pos)
+                pos = pos + 1
+                length = length + extra
+            until extra ~= 255
+        end
+        length = length + 4
+        local from = n - offset
+        for i = 1, length do
+            out[n + i] = out[from + i]
+        end
+        n = n + length
+    end
+
+    {{> partials/join_bytes.lua}}
+end
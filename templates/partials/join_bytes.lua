@@ -0,0 +1,6 @@
+-- string.char takes a limited number of arguments
+local parts = {}
+for i = 1, n, 4096 do
+    parts[#parts + 1] = string.char(table.unpack(out, i, math.min(i + 4095, n)))
+end
+return table.concat(parts)
@@ -0,0 +1,52 @@
+-- Remote Payload Loader Template
+-- Fetches the encrypted string table once startup validation has passed
+-- Template variables: {{SCRIPT_ID}}, {{LICENSE_KEY}}, {{VERSION}}; TOPIC and TIMEOUT if MESSAGING, else URL
+
+local function _load_payload()
+    local HttpService = game:GetService("HttpService")
+    local request = HttpService:JSONEncode({
+        script = "{{SCRIPT_ID}}",
+        key = "{{LICENSE_KEY}}",
+        place = game.PlaceId,
+        job = game.JobId,
+    })
+
+    local fetched, body = pcall(function()
+        {{#if MESSAGING}}
+        local MessagingService = game:GetService("MessagingService")
+        local parts, total = {}, nil
+        local connection = MessagingService:SubscribeAsync({{TOPIC}} .. "/" .. game.JobId, function(message)
+            parts[message.Data.i] = message.Data.data
+            total = message.Data.n
+        end)
+        MessagingService:PublishAsync({{TOPIC}}, request)
+        local deadline = os.clock() + {{TIMEOUT}}
+        while (total == nil or #parts < total) and os.clock() < deadline do
+            task.wait(0.1)
+        end
+        connection:Disconnect()
+        if total == nil or #parts < total then return nil end
+        return table.concat(parts)
+        {{else}}
+        return HttpService:PostAsync({{URL}}, request, Enum.HttpContentType.ApplicationJson)
+        {{/if}}
+    end)
+    if not fetched or type(body) ~= "string" then
+        return false
+    end
+
+    local decoded, payload = pcall(HttpService.JSONDecode, HttpService, body)
+    if not decoded
+        or type(payload) ~= "table"
+        or payload.version ~= {{VERSION}}
+        or payload.script_id ~= "{{SCRIPT_ID}}"
+        or type(payload.strings) ~= "table"
+    then
+        return false
+    end
+
+    for index, entry in ipairs(payload.strings) do
+        _encrypted_data[index] = {ct = entry.ct, nonce = entry.nonce, z = entry.z, m = entry.m}
+    end
+    return true
+end
@@ -0,0 +1,62 @@
+-- Split Loader Template
+-- Recovers the payload key for the current UserId, decrypts the payload chunk and runs it
+-- Template variables: {{MODULE}}, {{SEED}}, {{IMPORTS}}, {{TAMPER}}; DECOMPRESS if the chunk may be compressed,
+-- UPDATES if `_fetch_update` may replace it with a newer version
+
+local function _load_chunk()
+    {{#if UPDATES}}
+    -- A signed newer version is a complete script and takes no imports
+    local update = _fetch_update()
+    if update then
+        return update, true
+    end
+    {{/if}}
+    local ok, chunk = pcall(require, script.{{MODULE}})
+    if not ok or type(chunk) ~= "table" then
+        return nil
+    end
+
+    local Players = game:GetService("Players")
+    local player = Players.LocalPlayer
+    local hwid = player and player.UserId or 0
+
+    -- xorshift32 keystream seeded from the UserId unmasks the key
+    local state = bit32.bxor(hwid % 4294967296, math.floor(hwid / 4294967296), {{SEED}})
+    if state == 0 then
+        state = {{SEED}}
+    end
+    local mask = ChaCha20.base64_decode(chunk.mask)
+    local key = {}
+    for i = 0, 7 do
+        local word = 0
+        for j = 0, 3 do
+            state = bit32.bxor(state, bit32.lshift(state, 13))
+            state = bit32.bxor(state, bit32.rshift(state, 17))
+            state = bit32.bxor(state, bit32.lshift(state, 5))
+            local byte = bit32.bxor(mask[i * 4 + j + 1], bit32.band(state, 0xFF))
+            word = bit32.bor(word, bit32.lshift(byte, j * 8))
+        end
+        key[i + 1] = word
+    end
+
+    local source = ChaCha20.bytes_to_string(ChaCha20.crypt(
+        key,
+        ChaCha20.parse_nonce(chunk.nonce),
+        ChaCha20.base64_decode(chunk.ct)
+    ))
+    {{#if DECOMPRESS}}
+    if chunk.z then
+        source = _decompress(source)
+    end
+    {{/if}}
+    return loadstring(source)
+end
+
+local _chunk, _updated = _load_chunk()
+if not _chunk then
+    {{TAMPER}}("payload")
+elseif _updated then
+    _chunk()
+else
+    _chunk({{IMPORTS}})
+end
@@ -0,0 +1,43 @@
+-- String Vault Template
+-- Holds decrypted strings XOR-masked so a dump of the script's tables doesn't show them
+-- Template variables: none
+
+local _vault = (function()
+    local byte, char, bxor, concat = string.byte, string.char, bit32.bxor, table.concat
+    local random = math.random
+    -- Masks and keys live in separate tables, neither readable alone
+    local cells, keys, rolling = {}, {}, {}
+    local function xor(value, key)
+        local out = table.create(#value)
+        for i = 1, #value do
+            out[i] = char(bxor(byte(value, i), key[(i - 1) % #key + 1]))
+        end
+        return concat(out)
+    end
+    local function store(index, value)
+        local key = table.create(8)
+        for i = 1, 8 do
+            key[i] = random(0, 255)
+        end
+        keys[index] = key
+        cells[index] = xor(value, key)
+    end
+    local vault = {}
+    -- mode 1 keeps one key; mode 2 re-masks under a fresh key after every read
+    function vault.put(index, value, mode)
+        rolling[index] = mode == 2
+        store(index, value)
+    end
+    function vault.get(index)
+        local cell = cells[index]
+        if cell == nil then
+            return nil
+        end
+        local value = xor(cell, keys[index])
+        if rolling[index] then
+            store(index, value)
+        end
+        return value
+    end
+    return vault
+end)()
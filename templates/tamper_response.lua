@@ -0,0 +1,20 @@
+-- Tamper Response Template
+-- Called by license, HWID and environment checks when they fail
+-- Template variables: {{POLICY}}, {{SETUP}}, {{RESPONSE}}
+
+-- Policy: {{POLICY}}
+local _tamper_state = {triggered = false, at = nil, report = nil}
+
+{{SETUP}}
+
+local function _tamper(reason)
+    local first = not _tamper_state.triggered
+    if first then
+        _tamper_state.triggered = true
+        _tamper_state.at = os.clock()
+        if _tamper_state.report then
+            task.spawn(pcall, _tamper_state.report, reason)
+        end
+    end
+{{RESPONSE}}
+end
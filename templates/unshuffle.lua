@@ -0,0 +1,18 @@
+-- Unshuffle Template
+-- Rebuilds the license-bound order of a shuffled table from its seed (xorshift32-driven Fisher-Yates)
+-- Template variables: none
+
+local function _unshuffle(seed, n)
+    local order, state = {}, seed
+    for i = 1, n do
+        order[i] = i
+    end
+    for i = n, 2, -1 do
+        state = bit32.bxor(state, bit32.lshift(state, 13))
+        state = bit32.bxor(state, bit32.rshift(state, 17))
+        state = bit32.bxor(state, bit32.lshift(state, 5))
+        local j = state % i + 1
+        order[i], order[j] = order[j], order[i]
+    end
+    return order
+end
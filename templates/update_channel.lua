@@ -0,0 +1,41 @@
+-- Update Channel Template
+-- Asks the update endpoint for a newer signed payload version; nil keeps the embedded one
+-- Template variables: {{URL}}, {{SCRIPT_ID}}, {{VERSION}}, {{ENCRYPT_KEY}}, {{SIGN_KEY}} (includes hmac_sha256.lua)
+
+{{> hmac_sha256.lua}}
+
+local function _fetch_update()
+    local HttpService = game:GetService("HttpService")
+    local fetched, body = pcall(function()
+        return HttpService:GetAsync(
+            {{URL}} .. "script=" .. HttpService:UrlEncode("{{SCRIPT_ID}}") .. "&version={{VERSION}}",
+            true
+        )
+    end)
+    if not fetched or type(body) ~= "string" then
+        return nil
+    end
+
+    local decoded, update = pcall(HttpService.JSONDecode, HttpService, body)
+    if not decoded
+        or type(update) ~= "table"
+        or update.script_id ~= "{{SCRIPT_ID}}"
+        or type(update.version) ~= "number"
+        or update.version <= {{VERSION}}
+        or type(update.ct) ~= "string"
+        or type(update.nonce) ~= "string"
+        or type(update.sig) ~= "string"
+    then
+        return nil
+    end
+
+    -- Only what the developer signed runs; anything else keeps the embedded payload
+    local sign_key = ChaCha20.bytes_to_string(ChaCha20.base64_decode("{{SIGN_KEY}}"))
+    local signed = table.concat({ update.script_id, tostring(update.version), update.nonce, update.ct }, "\n")
+    if _hmac_sha256(sign_key, signed) ~= update.sig then
+        return nil
+    end
+
+    local source = ChaCha20.decrypt_string(update.ct, "{{ENCRYPT_KEY}}", update.nonce)
+    return loadstring(source)
+end
@@ -0,0 +1,164 @@
+-- Zlib Inflate Template
+-- Inflates a decrypted zlib stream (stored, fixed and dynamic Huffman blocks; the Adler-32 trailer is not checked)
+-- Template variables: none (includes partials/join_bytes.lua)
+
+local _decompress = (function()
+    local byte = string.byte
+    local band, lshift, rshift = bit32.band, bit32.lshift, bit32.rshift
+
+    local LENGTH_BASE = {3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258}
+    local LENGTH_EXTRA = {0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0}
+    local DIST_BASE = {1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577}
+    local DIST_EXTRA = {0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13}
+    local CODE_LENGTH_ORDER = {16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15}
+
+    -- Canonical Huffman table from `count` code lengths starting at `lengths[first]`
+    local function build(lengths, first, count)
+        local counts, offsets, symbols = {}, {}, {}
+        for bits = 0, 15 do
+            counts[bits] = 0
+        end
+        for symbol = 0, count - 1 do
+            local bits = lengths[first + symbol] or 0
+            counts[bits] = counts[bits] + 1
+        end
+        offsets[1] = 0
+        for bits = 1, 14 do
+            offsets[bits + 1] = offsets[bits] + counts[bits]
+        end
+        for symbol = 0, count - 1 do
+            local bits = lengths[first + symbol] or 0
+            if bits ~= 0 then
+                symbols[offsets[bits]] = symbol
+                offsets[bits] = offsets[bits] + 1
+            end
+        end
+        return {counts = counts, symbols = symbols}
+    end
+
+    local fixed_lengths = {}
+    for symbol = 0, 287 do
+        fixed_lengths[symbol] = symbol < 144 and 8 or symbol < 256 and 9 or symbol < 280 and 7 or 8
+    end
+    local FIXED_LITERALS = build(fixed_lengths, 0, 288)
+    local fixed_distances = {}
+    for symbol = 0, 29 do
+        fixed_distances[symbol] = 5
+    end
+    local FIXED_DISTANCES = build(fixed_distances, 0, 30)
+
+    return function(input)
+        -- Skip the 2-byte zlib header
+        local pos = 3
+        local bitbuf, bitcnt = 0, 0
+        local out, n = {}, 0
+
+        local function bits(count)
+            while bitcnt < count do
+                bitbuf = bitbuf + lshift(byte(input, pos) or 0, bitcnt)
+                pos = pos + 1
+                bitcnt = bitcnt + 8
+            end
+            local value = band(bitbuf, lshift(1, count) - 1)
+            bitbuf = rshift(bitbuf, count)
+            bitcnt = bitcnt - count
+            return value
+        end
+
+        local function decode(huffman)
+            local code, first, index = 0, 0, 0
+            for length = 1, 15 do
+                code = code + bits(1)
+                local count = huffman.counts[length]
+                if code - count < first then
+                    return huffman.symbols[index + code - first]
+                end
+                index = index + count
+                first = (first + count) * 2
+                code = code * 2
+            end
+            error("inflate: invalid Huffman code")
+        end
+
+        local function dynamic()
+            local literal_count = bits(5) + 257
+            local distance_count = bits(5) + 1
+            local code_count = bits(4) + 4
+
+            local code_lengths = {}
+            for i = 0, 18 do
+                code_lengths[i] = 0
+            end
+            for i = 1, code_count do
+                code_lengths[CODE_LENGTH_ORDER[i]] = bits(3)
+            end
+            local code_table = build(code_lengths, 0, 19)
+
+            local lengths, i = {}, 0
+            while i < literal_count + distance_count do
+                local symbol = decode(code_table)
+                if symbol < 16 then
+                    lengths[i] = symbol
+                    i = i + 1
+                else
+                    local value, repeats = 0, 0
+                    if symbol == 16 then
+                        value, repeats = lengths[i - 1], 3 + bits(2)
+                    elseif symbol == 17 then
+                        repeats = 3 + bits(3)
+                    else
+                        repeats = 11 + bits(7)
+                    end
+                    for _ = 1, repeats do
+                        lengths[i] = value
+                        i = i + 1
+                    end
+                end
+            end
+            return build(lengths, 0, literal_count), build(lengths, literal_count, distance_count)
+        end
+
+        repeat
+            local final = bits(1)
+            local kind = bits(2)
+            if kind == 0 then
+                -- Stored block: skip to the byte boundary, LEN and NLEN, then raw bytes
+                bitbuf, bitcnt = 0, 0
+                local length = byte(input, pos) + byte(input, pos + 1) * 256
+                pos = pos + 4
+                for i = 1, length do
+                    out[n + i] = byte(input, pos + i - 1)
+                end
+                n = n + length
+                pos = pos + length
+            elseif kind == 3 then
+                error("inflate: invalid block type")
+            else
+                local literals, distances = FIXED_LITERALS, FIXED_DISTANCES
+                if kind == 2 then
+                    literals, distances = dynamic()
+                end
+                while true do
+                    local symbol = decode(literals)
+                    if symbol < 256 then
+                        n = n + 1
+                        out[n] = symbol
+                    elseif symbol == 256 then
+                        break
+                    else
+                        symbol = symbol - 256
+                        local length = LENGTH_BASE[symbol] + bits(LENGTH_EXTRA[symbol])
+                        local d = decode(distances) + 1
+                        local from = n - DIST_BASE[d] - bits(DIST_EXTRA[d])
+                        for i = 1, length do
+                            out[n + i] = out[from + i]
+                        end
+                        n = n + length
+                    end
+                end
+            end
+        until final == 1
+
+        {{> partials/join_bytes.lua}}
+    end
+end)()
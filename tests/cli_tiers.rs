@@ -0,0 +1,58 @@
+//! Tier enforcement through the CLI
+//!
+//! Runs the built binary in a scratch home directory, so no user config
+//! (and its `tier_policy`) or license cache takes part.
+
+#![cfg(feature = "cli")]
+
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Run `luau-obfuscator --format json <args>` in `home`
+fn run(home: &TempDir, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_luau-obfuscator"))
+        .env("HOME", home.path())
+        .current_dir(home.path())
+        .args(["--no-telemetry", "--format", "json"])
+        .args(args)
+        .output()
+        .expect("Failed to run luau-obfuscator")
+}
+
+#[test]
+fn test_protect_without_api_keeps_requested_tier() {
+    // No --api-endpoint: no license reports a tier, so nothing is enforced
+    let home = TempDir::new().unwrap();
+    std::fs::write(home.path().join("script.lua"), "print(\"hello\")\n").unwrap();
+
+    let output = run(
+        &home,
+        &["protect", "script.lua", "-o", "out.lua", "-l", "TEST-1234", "-t", "premium"],
+    );
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["result"]["tier"], "premium");
+    assert!(report["result"]["licensed_tier"].is_null());
+    let warnings = report["warnings"].as_array().unwrap();
+    assert!(
+        !warnings.iter().any(|w| w.as_str().unwrap().contains("protecting at")),
+        "{:?}",
+        warnings
+    );
+    assert!(home.path().join("out.lua").exists());
+}
+
+#[test]
+fn test_tier_policy_needs_api_endpoint() {
+    // Without a license to check against, a policy would have nothing to apply to
+    let home = TempDir::new().unwrap();
+    std::fs::write(home.path().join("script.lua"), "print(\"hello\")\n").unwrap();
+
+    let output = run(
+        &home,
+        &["protect", "script.lua", "-o", "out.lua", "-l", "TEST-1234", "--tier-policy", "fail"],
+    );
+    assert!(!output.status.success());
+    assert!(!home.path().join("out.lua").exists());
+}
@@ -106,7 +106,7 @@ fn test_aes_256_gcm_decryption() {
     let encrypted = aes.encrypt(plaintext).unwrap();
     let decrypted = aes.decrypt(&encrypted).unwrap();
     
-    assert_eq!(decrypted, plaintext);
+    assert_eq!(*decrypted, plaintext);
 }
 
 #[test]
@@ -167,7 +167,7 @@ fn test_aes_empty_plaintext() {
     let encrypted = aes.encrypt(plaintext).unwrap();
     let decrypted = aes.decrypt(&encrypted).unwrap();
     
-    assert_eq!(decrypted, plaintext);
+    assert_eq!(*decrypted, plaintext);
 }
 
 #[test]
@@ -179,7 +179,7 @@ fn test_aes_large_plaintext() {
     let encrypted = aes.encrypt(&plaintext).unwrap();
     let decrypted = aes.decrypt(&encrypted).unwrap();
     
-    assert_eq!(decrypted, plaintext);
+    assert_eq!(*decrypted, plaintext);
 }
 
 #[test]
@@ -0,0 +1,148 @@
+//! Differential execution per tier
+//!
+//! Protects scripts at every tier and runs each build next to its original
+//! in the embedded Luau VM (`verify::Sandbox`): the prints, warnings and
+//! return values must match.
+
+#![cfg(feature = "verify")]
+
+use luau_obfuscator::{
+    verify::{DifferentialReport, Sandbox},
+    CodeGenConfig, CryptoContext, ObfuscationPipeline, ObfuscationTier, PipelineSettings,
+};
+
+const TIERS: [ObfuscationTier; 3] = [
+    ObfuscationTier::Basic,
+    ObfuscationTier::Standard,
+    ObfuscationTier::Premium,
+];
+
+/// Game scripts that run against the Roblox stand-ins
+const FIXTURES: &[&str] = &[
+    "admin_commands.lua",
+    "combat_system.lua",
+    "gui_controller.lua",
+    "inventory_system.lua",
+    "tycoon_manager.lua",
+];
+
+fn verify(source: &str, tier: ObfuscationTier, crypto: &CryptoContext) -> DifferentialReport {
+    let settings = PipelineSettings {
+        tier,
+        seed: Some(42),
+        ..Default::default()
+    };
+    let config = CodeGenConfig {
+        license_key: "TEST-1234".to_string(),
+        script_id: "differential".to_string(),
+        ..Default::default()
+    };
+    let pipeline = ObfuscationPipeline::new(crypto.clone());
+    let protected = pipeline.protect(source, &settings, config).unwrap();
+    let script = pipeline.verification_script(&protected).unwrap();
+    Sandbox::new().compare(source, &script).unwrap()
+}
+
+#[test]
+fn test_tiers_preserve_behavior() {
+    let source = r#"
+local Players = game:GetService("Players")
+local API_KEY = "sk_live_1234567890"
+local config = { endpoint = "https://api.example.com/v1", retries = 3, ratio = 0.25 }
+
+local Counter = {}
+Counter.__index = Counter
+function Counter.new(start)
+    return setmetatable({ value = start }, Counter)
+end
+function Counter:add(amount)
+    self.value += amount
+    return self
+end
+
+local function describe(...)
+    local parts = {}
+    for i = 1, select("#", ...) do
+        table.insert(parts, string.format("%s=%s", tostring(i), tostring((select(i, ...)))))
+    end
+    return table.concat(parts, ";")
+end
+
+local total = 0
+for i = 1, 10 do
+    if i % 3 == 0 then
+        total += i * 2
+    elseif i % 2 == 0 then
+        total -= 1
+    else
+        total += #API_KEY
+    end
+end
+
+local ok, message = pcall(function()
+    error("retry limit " .. config.retries)
+end)
+print("total", total, describe("a", 2, true), ok)
+print(message:match("retry limit %d+"), `{Players.Name} on {config.endpoint}`)
+warn(Counter.new(5):add(10):add(-3).value, 60 * 60 * 24, 7 // 2, 2 ^ 10)
+task.defer(print, "deferred", config.ratio)
+return config, Counter.new(1).value
+"#;
+    let crypto = CryptoContext::new("test_password", None).unwrap();
+    for tier in TIERS {
+        let report = verify(source, tier, &crypto);
+        assert!(
+            report.is_equivalent(),
+            "{:?}: {}",
+            tier,
+            report.mismatch.unwrap()
+        );
+        assert_eq!(report.original.events.len(), 4);
+    }
+}
+
+#[test]
+fn test_fixtures_preserve_behavior() {
+    let crypto = CryptoContext::new("test_password", None).unwrap();
+    for fixture in FIXTURES {
+        let source = std::fs::read_to_string(format!("tests/fixtures/{}", fixture))
+            .expect("Failed to load fixture");
+        for tier in TIERS {
+            let report = verify(&source, tier, &crypto);
+            assert!(
+                report.is_equivalent(),
+                "{} at {:?}: {}",
+                fixture,
+                tier,
+                report.mismatch.unwrap()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_escaped_and_long_strings_preserve_behavior() {
+    let source = r#"
+local SECRET_TOKEN = "sk_live_\"quoted\"\tkey"
+local lines = "first\nsecond\\third"
+local codes = "\65\x42\u{43}\z   D"
+local quote = 'it\'s "fine"'
+local long = [[
+raw \n text "kept"]]
+local nested = [==[a]]b]==]
+print(SECRET_TOKEN, #SECRET_TOKEN, lines, codes)
+print(quote, long, nested, #long)
+return "say \"hi\"", string.byte("\0\255", 1, -1)
+"#;
+    let crypto = CryptoContext::new("test_password", None).unwrap();
+    for tier in TIERS {
+        let report = verify(source, tier, &crypto);
+        assert!(
+            report.is_equivalent(),
+            "{:?}: {}",
+            tier,
+            report.mismatch.unwrap()
+        );
+        assert_eq!(report.original.events.len(), 2);
+    }
+}
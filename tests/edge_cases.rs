@@ -4,7 +4,7 @@
 //! to ensure robustness of the obfuscation system.
 
 use luau_obfuscator::{
-    analysis::Analyzer,
+    analysis::{AnalysisOptions, Analyzer},
     crypto::CryptoContext,
     obfuscation::{Obfuscator, ObfuscationTier},
     parser::LuauParser,
@@ -26,7 +26,7 @@ fn test_empty_script() {
     assert!(result.is_ok(), "Should parse empty script");
     
     let parse_result = result.unwrap();
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result);
     assert!(analysis.is_ok(), "Should analyze empty script");
 }
@@ -37,7 +37,7 @@ fn test_single_line_script() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -71,7 +71,7 @@ fn test_deeply_nested_functions() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Should handle deep nesting
@@ -87,7 +87,7 @@ fn test_maximum_string_length() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -110,7 +110,7 @@ fn test_unicode_and_special_characters() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -137,7 +137,7 @@ fn test_extreme_number_values() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -166,7 +166,7 @@ fn test_reserved_roblox_identifiers() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Verify Roblox API detection
@@ -245,7 +245,7 @@ fn test_table_with_mixed_keys() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -276,7 +276,7 @@ fn test_coroutine_and_async_patterns() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -313,7 +313,7 @@ fn test_metamethods_and_metatables() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -346,7 +346,7 @@ fn test_error_handling_patterns() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -375,7 +375,7 @@ fn test_goto_and_labels() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -426,7 +426,7 @@ fn test_multiple_returns_and_varargs() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -456,7 +456,7 @@ fn test_global_vs_local_shadowing() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Should track scope correctly
@@ -481,7 +481,7 @@ fn test_string_patterns_and_escapes() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
@@ -504,7 +504,7 @@ fn test_operator_precedence_edge_cases() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test", None).unwrap();
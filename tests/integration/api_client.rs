@@ -4,7 +4,8 @@
 //! you would need to set up a test server or use a mocking library like mockito.
 
 use luau_obfuscator::api::{
-    ApiClient, GenerateLicenseRequest, TrackObfuscationRequest, ValidateLicenseRequest,
+    ApiClient, GenerateLicenseRequest, SeatLimits, TrackObfuscationRequest,
+    ValidateLicenseRequest,
 };
 use std::time::Duration;
 
@@ -48,6 +49,7 @@ fn test_generate_license_request_structure() {
         expiration: Some(\"2026-01-01T00:00:00Z\".to_string()),
         tier: Some(\"premium\".to_string()),
         hwid_restrictions: Some(vec![\"userid:123\".to_string()]),
+        seats: SeatLimits::default(),
     };
 
     // Verify structure is serializable
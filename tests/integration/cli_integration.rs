@@ -389,6 +389,33 @@ fn test_protect_with_verbose_logging() {
         .stdout(predicate::str::contains("Parsing").or(predicate::str::contains("DEBUG")));
 }
 
+#[test]
+fn test_protect_stdin_to_stdout() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let symbol_map = temp_dir.path().join("piped.map.json");
+
+    let assert = luau_obfuscator()
+        .arg("protect")
+        .arg("--stdin")
+        .arg("--stdout")
+        .arg("--symbol-map")
+        .arg(&symbol_map)
+        .arg("--license-key")
+        .arg("TEST-KEY")
+        .arg("--tier")
+        .arg("basic")
+        .write_stdin("local message = \"piped\"\nprint(message)\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("-- Protected by Luau Obfuscator"))
+        .stdout(predicate::str::contains("Protection complete").not());
+
+    // stdout is the script and nothing else
+    let script = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(full_moon::parse(&script).is_ok(), "stdout should be a valid script");
+    assert!(symbol_map.exists(), "Symbol map should be written where asked");
+}
+
 #[test]
 fn test_protect_with_custom_api_endpoint() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -4,7 +4,7 @@
 //! input script parsing through final protected output generation.
 
 use luau_obfuscator::{
-    analysis::Analyzer,
+    analysis::{AnalysisOptions, Analyzer},
     cli::args::ObfuscationTier,
     codegen::{CodeGenConfig, CodeGenerator},
     crypto::CryptoContext,
@@ -46,7 +46,7 @@ fn test_end_to_end_basic_tier() {
     assert!(parse_result.functions.len() >= 1, "Should extract functions");
 
     // Phase 2: Analyze
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
 
     assert!(
@@ -86,6 +86,8 @@ fn test_end_to_end_basic_tier() {
                     ciphertext: es.encrypted_data.clone(),
                     nonce: es.nonce.clone(),
                     tag_len: 16,
+                    key_version: 0,
+                    compression: es.compression,
                 },
             )
         })
@@ -94,6 +96,8 @@ fn test_end_to_end_basic_tier() {
     // Generate fake obfuscation result for testing
     let obfuscation_result = luau_obfuscator::obfuscation::ObfuscationResult {
         code: "-- Obfuscated code placeholder\nprint('test')".to_string(),
+        constant_pool: None,
+        environment_anchor: None,
     };
 
     let protected_script = code_generator
@@ -158,7 +162,7 @@ fn test_end_to_end_standard_tier() {
     let parse_result = parser.parse(test_script).unwrap();
 
     // Analyze
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
 
     // Verify Roblox API preservation
@@ -257,7 +261,7 @@ fn test_end_to_end_premium_tier() {
     );
 
     // Analyze
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
 
     // Obfuscate (Premium tier)
@@ -313,7 +317,7 @@ fn test_roblox_api_preservation() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(test_script).unwrap();
 
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
 
     // Verify all Roblox APIs are preserved
@@ -1,9 +1,9 @@
 //! Obfuscation tier comparison and validation tests
 
-use luau_obfuscator::analysis::Analyzer;
+use luau_obfuscator::analysis::{AnalysisOptions, Analyzer};
 use luau_obfuscator::crypto::CryptoContext;
 use luau_obfuscator::obfuscation::*;
-use luau_obfuscator::parser::LuauParser;
+use luau_obfuscator::parser::{LuauParser, NumericLiteral};
 
 mod helpers;
 use helpers::*;
@@ -18,6 +18,7 @@ fn test_basic_tier_settings() {
     assert!(settings.mangle_names);
     assert!(!settings.mangle_functions);
     assert!(!settings.flatten_control_flow);
+    assert!(!settings.outline_functions);
     assert!(!settings.inject_dead_code);
     assert_eq!(settings.dead_code_density, 0.0);
 }
@@ -32,6 +33,7 @@ fn test_standard_tier_settings() {
     assert!(settings.mangle_names);
     assert!(settings.mangle_functions);
     assert!(settings.flatten_control_flow);
+    assert!(!settings.outline_functions);
     assert!(settings.inject_dead_code);
     assert_eq!(settings.dead_code_density, 0.1);
 }
@@ -46,6 +48,7 @@ fn test_premium_tier_settings() {
     assert!(settings.mangle_names);
     assert!(settings.mangle_functions);
     assert!(settings.flatten_control_flow);
+    assert!(settings.outline_functions);
     assert!(settings.inject_dead_code);
     assert_eq!(settings.dead_code_density, 0.3); // Higher density
 }
@@ -57,7 +60,7 @@ fn test_basic_tier_obfuscation() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -86,7 +89,7 @@ fn test_standard_tier_obfuscation() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -110,7 +113,7 @@ fn test_premium_tier_obfuscation() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -134,7 +137,7 @@ fn test_tier_progression() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Obfuscate with each tier
@@ -160,6 +163,22 @@ fn test_tier_progression() {
     assert!(premium_result.obfuscated_constants.len() > 0);
 }
 
+#[test]
+fn test_tier_entitlement() {
+    assert_eq!(ObfuscationTier::from_name("Premium"), Some(ObfuscationTier::Premium));
+    assert_eq!(ObfuscationTier::from_name("gold"), None);
+
+    let basic = ObfuscationTier::Basic;
+    let premium = ObfuscationTier::Premium;
+    assert_eq!(basic.entitled(premium, TierPolicy::Fail).unwrap(), basic);
+    assert_eq!(premium.entitled(premium, TierPolicy::Fail).unwrap(), premium);
+    assert_eq!(premium.entitled(basic, TierPolicy::Downgrade).unwrap(), basic);
+
+    let err = premium.entitled(basic, TierPolicy::Fail).unwrap_err();
+    let err = err.downcast_ref::<luau_obfuscator::ObfuscatorError>().unwrap();
+    assert_eq!(err.exit_code(), 21);
+}
+
 #[test]
 fn test_string_obfuscator_selective() {
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -279,6 +298,10 @@ fn test_dead_code_generation() {
         ],
         numbers: vec![],
         functions: vec![],
+        type_aliases: vec![],
+        directives: vec![],
+        config_block: None,
+        unsupported_statements: vec![],
     };
     
     let snippets = injector.generate(&parse_result).unwrap();
@@ -294,7 +317,7 @@ fn test_obfuscation_stats() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -317,7 +340,7 @@ fn test_performance_comparison() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&source).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Measure each tier
@@ -346,3 +369,202 @@ fn test_performance_comparison() {
     // All should complete reasonably quickly (< 5 seconds for complex script)
     assert!(premium_time.as_secs() < 5, "Even premium tier should be reasonably fast");
 }
+
+#[test]
+fn test_function_directives_override_tier() {
+    use luau_obfuscator::parser::{
+        scan_directives, NumericLiteral, ParseResult, Sensitivity, StringLiteral,
+    };
+
+    let source = r#"
+--!obfuscate: none
+RunService.RenderStepped:Connect(function(dt)
+    print("frame", 60)
+end)
+
+print("outside", 7)
+"#;
+    let string = |value: &str, line| StringLiteral {
+        value: value.to_string(),
+        line,
+        column: 0,
+        sensitivity: Sensitivity::Medium,
+    };
+    let number = |value: &str, line| NumericLiteral {
+        value: value.to_string(),
+        line,
+        column: 0,
+        is_float: false,
+    };
+    let parse_result = ParseResult {
+        ast: None,
+        strings: vec![string("frame", 4), string("outside", 7)],
+        numbers: vec![number("60", 4), number("7", 7)],
+        functions: vec![],
+        type_aliases: vec![],
+        directives: scan_directives(source).unwrap(),
+        config_block: None,
+        unsupported_statements: vec![],
+    };
+    let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+
+    let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+    let obfuscator = Obfuscator::with_seed(ObfuscationTier::Standard, crypto_ctx, 1);
+    let result = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+
+    let encrypted: Vec<&str> = result
+        .encrypted_strings
+        .iter()
+        .map(|s| s.original.as_str())
+        .collect();
+    assert_eq!(encrypted, vec!["outside"]);
+    let constants: Vec<&str> = result
+        .obfuscated_constants
+        .iter()
+        .map(|c| c.original.as_str())
+        .collect();
+    assert_eq!(constants, vec!["7"]);
+    // The only block spans the whole script, including the hot path
+    assert!(result.flattened_blocks.is_empty());
+}
+
+#[test]
+fn test_literals_inside_control_structures_are_obfuscated() {
+    let source = r#"
+local function check(items)
+    for i = 1, 3 do
+        if items[i] == "alpha" then
+            return "found"
+        elseif items[i] == "beta" then
+            warn("second")
+        else
+            repeat
+                task.wait(0.5)
+            until items["ready"]
+        end
+    end
+    while true do
+        do
+            print({ label = "nested" })
+        end
+    end
+end
+"#;
+    let parse_result = LuauParser::new().parse(source).unwrap();
+    let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+
+    let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+    let obfuscator = Obfuscator::with_seed(ObfuscationTier::Standard, crypto_ctx, 1);
+    let result = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+
+    let encrypted: Vec<&str> = result
+        .encrypted_strings
+        .iter()
+        .map(|s| s.original.as_str())
+        .collect();
+    for expected in ["alpha", "found", "beta", "second", "ready", "nested"] {
+        assert!(encrypted.contains(&expected), "{} was not encrypted", expected);
+    }
+
+    let constants: Vec<&str> = result
+        .obfuscated_constants
+        .iter()
+        .map(|c| c.original.as_str())
+        .collect();
+    for expected in ["1", "3", "0.5"] {
+        assert!(constants.contains(&expected), "{} was not obfuscated", expected);
+    }
+}
+
+#[test]
+fn test_repeated_strings_share_one_entry() {
+    let source = r#"
+local a = workspace:FindFirstChild("Humanoid")
+local b = workspace:FindFirstChild("Humanoid")
+local c = workspace:FindFirstChild("Humanoid")
+print("done")
+"#;
+    let parse_result = LuauParser::new().parse(source).unwrap();
+    let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+
+    let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+    let obfuscator = Obfuscator::with_seed(ObfuscationTier::Standard, crypto_ctx, 1);
+    let result = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+
+    assert_eq!(result.encrypted_strings.len(), 2);
+    let humanoid = &result.encrypted_strings[0];
+    assert_eq!(humanoid.original, "Humanoid");
+    assert_eq!(humanoid.sites.len(), 3);
+
+    let stats = obfuscator.get_stats(&result);
+    assert_eq!(stats.deduplicated_strings, 2);
+    assert_eq!(
+        stats.dedup_bytes_saved,
+        2 * (humanoid.encrypted_data.len() + humanoid.nonce.len())
+    );
+}
+
+#[test]
+fn test_hot_functions_keep_their_budget() {
+    let source = r#"
+local function update(dt)
+    if dt > 1 then
+        print("slow frame")
+    end
+end
+
+local ready = workspace:GetAttribute("Ready")
+if ready then
+    print("ready", "to", "go")
+else
+    print("waiting")
+end
+"#;
+    let parse_result = LuauParser::new().parse(source).unwrap();
+    let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+
+    let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+    let mut obfuscator = Obfuscator::with_seed(ObfuscationTier::Basic, crypto_ctx, 1);
+    obfuscator.set_hot_functions(HotFunctions::new(["update"]));
+    let result = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+
+    // Basic tier alone flattens nothing; outside the hot function it now does
+    assert!(!result.flattened_blocks.is_empty());
+    assert!(!result.dead_code_snippets.is_empty());
+    for flattened in &result.flattened_blocks {
+        let block = &analysis.control_flow.blocks[&flattened.block_id];
+        assert!(
+            block.start_line > 6 || block.end_line < 2,
+            "block {}..{} reaches into the hot function",
+            block.start_line,
+            block.end_line
+        );
+    }
+}
+
+#[test]
+fn test_best_effort_skips_a_failing_pass() {
+    let source = "local secret = \"api_key_123\"\nlocal count = 42\nprint(secret, count)\n";
+    let mut parse_result = LuauParser::new().parse(source).unwrap();
+    let analysis = Analyzer::new(AnalysisOptions::default()).analyze(&parse_result).unwrap();
+    // A literal the constant pass can't read
+    parse_result.numbers.push(NumericLiteral::new("0xZZ", 2, 15));
+
+    let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
+    let strict = Obfuscator::with_seed(ObfuscationTier::Standard, crypto_ctx.clone(), 1);
+    assert!(strict.obfuscate(&parse_result, &analysis).is_err());
+
+    let mut obfuscator = Obfuscator::with_seed(ObfuscationTier::Standard, crypto_ctx, 1);
+    obfuscator.set_best_effort(true);
+    let result = obfuscator.obfuscate(&parse_result, &analysis).unwrap();
+
+    assert_eq!(result.failed_transforms.len(), 1);
+    let failure = &result.failed_transforms[0];
+    assert_eq!(failure.pass, Pass::Constants);
+    assert_eq!(failure.lines, None);
+    assert!(failure.error.contains("0xZZ"));
+    // The other passes still ran
+    assert!(result.obfuscated_constants.is_empty());
+    assert!(!result.encrypted_strings.is_empty());
+    assert!(!result.name_mappings.is_empty());
+}
@@ -4,7 +4,7 @@
 //! high volumes of operations, and resource-intensive scenarios.
 
 use luau_obfuscator::{
-    analysis::Analyzer,
+    analysis::{AnalysisOptions, Analyzer},
     crypto::CryptoContext,
     obfuscation::{Obfuscator, ObfuscationTier},
     parser::LuauParser,
@@ -36,7 +36,7 @@ end
     let parse_time = start.elapsed();
     println!("Parse 1000 lines: {:?}", parse_time);
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let start = Instant::now();
     let analysis = analyzer.analyze(&parse_result).unwrap();
     let analysis_time = start.elapsed();
@@ -80,7 +80,7 @@ fn test_large_script_10000_lines() {
     let parse_time = start.elapsed();
     println!("Parse 10,000 lines: {:?}", parse_time);
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -114,7 +114,7 @@ fn test_many_strings_1000() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -156,7 +156,7 @@ fn test_deeply_nested_tables() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -192,7 +192,7 @@ end
     let parser = LuauParser::new();
     let parse_result = parser.parse(&script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let start = Instant::now();
     let analysis = analyzer.analyze(&parse_result).unwrap();
     let analysis_time = start.elapsed();
@@ -251,7 +251,7 @@ local function complex()
     let parser = LuauParser::new();
     let parse_result = parser.parse(&script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -281,7 +281,7 @@ fn test_massive_table_literal() {
     let parse_time = start.elapsed();
     println!("Parse 5000-element table: {:?}", parse_time);
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -304,7 +304,7 @@ fn test_string_concatenation_chain() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(&script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -328,7 +328,7 @@ fn test_memory_efficiency_multiple_obfuscations() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Run 100 obfuscations in sequence
@@ -368,7 +368,7 @@ fn test_all_tiers_performance_comparison() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Test all three tiers
@@ -418,7 +418,7 @@ end
     let parser = LuauParser::new();
     let parse_result = parser.parse(&script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     let crypto_ctx = CryptoContext::new("test_password", None).unwrap();
@@ -513,7 +513,7 @@ fn test_roblox_script_realistic_scenario() {
     let parser = LuauParser::new();
     let parse_result = parser.parse(script).unwrap();
     
-    let analyzer = Analyzer::new();
+    let analyzer = Analyzer::new(AnalysisOptions::default());
     let analysis = analyzer.analyze(&parse_result).unwrap();
     
     // Verify Roblox API preservation